@@ -42,8 +42,14 @@ import {
   hasCompletedOnboarding,
   setOnboardingCompleted
 } from './services/habitService';
-import { useDesktopNotificationScheduler } from './services/desktopNotificationScheduler';
 import { sendHabitNotification } from './services/notificationService';
+import {
+  flushBackendWrites,
+  importLegacyState,
+  loadHabitsFromBackend,
+  onBackendHabitsChanged,
+  syncHabitsToBackend,
+} from './services/backendService';
 import { HabitGrid } from './components/HabitGrid';
 import { StatsView } from './components/StatsView';
 import { SettingsView } from './components/SettingsView';
@@ -147,21 +153,24 @@ const App: React.FC = () => {
 
   // Load data on mount
   useEffect(() => {
-    const data = loadHabits();
-    if (data.length === 0) {
-      // First time user experience
-      setHabits(getMockHabits());
-    } else {
-      setHabits(data);
-    }
+    void (async () => {
+      await importLegacyState();
+      const data = (await loadHabitsFromBackend()) ?? loadHabits();
+      if (data.length === 0) {
+        // First time user experience
+        setHabits(getMockHabits());
+      } else {
+        setHabits(data);
+      }
 
-    // Check onboarding status
-    if (!hasCompletedOnboarding()) {
-      // Small delay to ensure UI is ready
-      setTimeout(() => setShowTour(true), 500);
-    }
+      // Check onboarding status
+      if (!hasCompletedOnboarding()) {
+        // Small delay to ensure UI is ready
+        setTimeout(() => setShowTour(true), 500);
+      }
 
-    setIsLoading(false);
+      setIsLoading(false);
+    })();
   }, []);
 
   // Tray: "Add Habit" -> open modal
@@ -183,13 +192,41 @@ const App: React.FC = () => {
     };
   }, []);
 
-  // Desktop-only: scheduler while app is running (reminders + missed alerts)
-  useDesktopNotificationScheduler(habits);
+  // Desktop-only: backend-originated changes (local API, tray, ...)
+  useEffect(() => {
+    let unlisten: (() => void) | null = null;
+    void (async () => {
+      unlisten = await onBackendHabitsChanged(setHabits);
+    })();
+    return () => {
+      try {
+        unlisten?.();
+      } catch {
+        // ignore
+      }
+    };
+  }, []);
+
+  // Desktop-only: batched syncs are written out before the page goes away.
+  useEffect(() => {
+    const flush = () => {
+      if (document.visibilityState === 'hidden') flushBackendWrites();
+    };
+    window.addEventListener('pagehide', flushBackendWrites);
+    document.addEventListener('visibilitychange', flush);
+    return () => {
+      window.removeEventListener('pagehide', flushBackendWrites);
+      document.removeEventListener('visibilitychange', flush);
+    };
+  }, []);
+
+  // Desktop reminders + missed alerts are sent by the backend scheduler (schedule-aware).
 
   // Persistence
   useEffect(() => {
     if (!isLoading) {
       saveHabits(habits);
+      void syncHabitsToBackend(habits);
     }
   }, [habits, isLoading]);
 
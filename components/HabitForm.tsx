@@ -37,7 +37,7 @@ export const HabitForm: React.FC<HabitFormProps> = ({ onSave, onCancel, existing
   const [categoryName, setCategoryName] = useState(initialData?.category || '');
   const [selectedColor, setSelectedColor] = useState(initialData?.color || PREDEFINED_CATEGORIES[0].color);
   const [selectedIcon, setSelectedIcon] = useState(initialData?.icon || 'sparkles');
-  const [frequencyType, setFrequencyType] = useState<'daily' | 'weekly' | 'monthly'>(initialData?.frequency?.type || 'daily');
+  const [frequencyType, setFrequencyType] = useState<HabitFrequency['type']>(initialData?.frequency?.type || 'daily');
   const [frequencyGoal, setFrequencyGoal] = useState(initialData?.frequency?.goal || 1);
   const [reminderTime, setReminderTime] = useState(initialData?.reminderTime || '');
 
@@ -272,12 +272,14 @@ export const HabitForm: React.FC<HabitFormProps> = ({ onSave, onCancel, existing
                     if (newType === 'daily') setFrequencyGoal(1);
                     if (newType === 'weekly') setFrequencyGoal(3);
                     if (newType === 'monthly') setFrequencyGoal(10);
+                    if (newType === 'interval') setFrequencyGoal(2);
                   }}
                   className="w-full px-3 py-2 border border-slate-300 dark:border-slate-700 rounded-lg focus:outline-none focus:ring-2 focus:ring-indigo-500/20 focus:border-indigo-500 transition-all text-slate-900 dark:text-white bg-white dark:bg-slate-950"
                 >
                   <option value="daily">Daily</option>
                   <option value="weekly">Weekly</option>
                   <option value="monthly">Monthly</option>
+                  <option value="interval">Every N days</option>
                 </select>
               </div>
               
@@ -285,14 +287,14 @@ export const HabitForm: React.FC<HabitFormProps> = ({ onSave, onCancel, existing
                 <input
                   type="number"
                   min="1"
-                  max={frequencyType === 'weekly' ? 7 : frequencyType === 'monthly' ? 31 : 1}
+                  max={frequencyType === 'weekly' ? 7 : frequencyType === 'monthly' ? 31 : frequencyType === 'interval' ? 30 : 1}
                   value={frequencyGoal}
                   onChange={(e) => setFrequencyGoal(parseInt(e.target.value) || 1)}
                   disabled={frequencyType === 'daily'}
                   className="w-20 px-3 py-2 border border-slate-300 dark:border-slate-700 rounded-lg focus:outline-none focus:ring-2 focus:ring-indigo-500/20 focus:border-indigo-500 transition-all text-slate-900 dark:text-white bg-white dark:bg-slate-950 disabled:opacity-50 disabled:bg-slate-100 dark:disabled:bg-slate-900"
                 />
                 <span className="text-sm text-slate-500 dark:text-slate-400">
-                  {frequencyType === 'daily' ? 'time / day' : frequencyType === 'weekly' ? 'days / week' : 'days / month'}
+                  {frequencyType === 'daily' ? 'time / day' : frequencyType === 'weekly' ? 'days / week' : frequencyType === 'interval' ? 'days apart' : 'days / month'}
                 </span>
               </div>
             </div>
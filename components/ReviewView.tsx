@@ -197,7 +197,9 @@ export const ReviewView: React.FC<ReviewViewProps> = ({ habits, onClose, onUpdat
             Goal: {currentHabit.frequency
               ? (currentHabit.frequency.type === 'daily'
                   ? 'Daily'
-                  : `${currentHabit.frequency.goal} times/${currentHabit.frequency.type}`)
+                  : currentHabit.frequency.type === 'interval'
+                    ? `Every ${currentHabit.frequency.goal} days`
+                    : `${currentHabit.frequency.goal} times/${currentHabit.frequency.type}`)
               : 'N/A'}
           </p>
         </div>
@@ -3,6 +3,7 @@ import { AlertTriangle, Database, Trash2, Download, Upload, FileJson, Archive, R
 import { Button } from './ui/Button';
 import { Habit } from '../types';
 import { HabitIcon } from './HabitIcon';
+import { errorMessage } from '../services/backendService';
 import { exportBackup, importBackup, isAutoBackupEnabled, setAutoBackupEnabled } from '../services/nativeFileService';
 import { parseBackupJson } from '../services/backupService';
 import { isAutostartEnabled, setAutostartEnabled } from '../services/startupService';
@@ -34,7 +35,7 @@ export const SettingsView: React.FC<SettingsViewProps> = ({ onClearData, onImpor
         alert(`Backup successfully saved to:\n${savedPath}`);
       }
     } catch (err) {
-      alert(err instanceof Error ? err.message : 'Failed to export backup.');
+      alert(errorMessage(err, 'Failed to export backup.'));
     }
   };
 
@@ -45,7 +46,7 @@ export const SettingsView: React.FC<SettingsViewProps> = ({ onClearData, onImpor
         const imported = await importBackup();
         if (imported) onImportData(imported);
       } catch (err) {
-        alert(err instanceof Error ? err.message : 'Failed to import backup.');
+        alert(errorMessage(err, 'Failed to import backup.'));
       }
       return;
     }
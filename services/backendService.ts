@@ -0,0 +1,64 @@
+import { invoke, isTauri } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import type { AppError, Habit, LegacyImport } from '../types';
+
+// Desktop only: keep the backend's copy of the data in sync so the local API,
+// tray and other Rust-side features see the same habits as the UI.
+export async function syncHabitsToBackend(habits: Habit[]): Promise<void> {
+  if (!isTauri()) return;
+  try {
+    await invoke('sync_habits', { habits });
+  } catch (e) {
+    console.warn('Failed to sync habits to backend', e);
+  }
+}
+
+// Desktop only: syncs are batched for a moment in the backend; write them out now, e.g.
+// when the page is about to go away.
+export function flushBackendWrites(): void {
+  if (!isTauri()) return;
+  invoke('flush_writes').catch(e => console.warn('Failed to flush backend writes', e));
+}
+
+// Desktop only: hands the habits older builds kept only in localStorage to the backend.
+// The backend does it once and ignores later calls; localStorage is left as it is.
+export async function importLegacyState(): Promise<LegacyImport | null> {
+  if (!isTauri()) return null;
+  const json = localStorage.getItem('habitflow_data_v1');
+  if (!json) return null;
+  try {
+    return await invoke<LegacyImport>('import_legacy_state', { json });
+  } catch (e) {
+    console.warn('Failed to import habits saved by an older version', e);
+    return null;
+  }
+}
+
+// Desktop only: the backend copy can be newer than localStorage (e.g. edits made with the
+// CLI while the window was closed). Returns null when there's nothing to prefer.
+export async function loadHabitsFromBackend(): Promise<Habit[] | null> {
+  if (!isTauri()) return null;
+  try {
+    const habits = await invoke<Habit[]>('list_habits');
+    return habits.length > 0 ? habits : null;
+  } catch (e) {
+    console.warn('Failed to load habits from backend', e);
+    return null;
+  }
+}
+
+// Fired when the backend changed data itself (e.g. a completion via the local API).
+export async function onBackendHabitsChanged(handler: (habits: Habit[]) => void): Promise<() => void> {
+  if (!isTauri()) return () => {};
+  return listen<Habit[]>('habits:changed', event => handler(event.payload));
+}
+
+export function isAppError(e: unknown): e is AppError {
+  return typeof e === 'object' && e !== null && 'code' in e && 'message' in e;
+}
+
+// A message for the user from whatever a command or other code threw.
+export function errorMessage(e: unknown, fallback: string): string {
+  if (isAppError(e)) return e.message;
+  return e instanceof Error ? e.message : fallback;
+}
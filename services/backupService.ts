@@ -22,7 +22,7 @@ const habitSchema = {
     frequency: {
       type: 'object',
       properties: {
-        type: { type: 'string', enum: ['daily', 'weekly', 'monthly'] },
+        type: { type: 'string', enum: ['daily', 'weekly', 'monthly', 'interval'] },
         goal: { type: 'integer' },
       },
       required: ['type', 'goal'],
@@ -1,6 +1,6 @@
 import type { Habit } from '../types';
 import { createBackupPayload, parseBackupJson } from './backupService';
-import { isTauri } from '@tauri-apps/api/core';
+import { invoke, isTauri } from '@tauri-apps/api/core';
 
 // Browser fallback export
 function exportInBrowser(filename: string, jsonText: string) {
@@ -11,8 +11,6 @@ function exportInBrowser(filename: string, jsonText: string) {
   linkElement.click();
 }
 
-const LAST_EXPORT_DIR_KEY = 'habitflow_last_export_dir_v1';
-
 export async function exportBackup(habits: Habit[]): Promise<string | void> {
   const filename = `habitflow-backup-${new Date().toISOString().split('T')[0]}.json`;
   const payload = createBackupPayload(habits);
@@ -23,75 +21,12 @@ export async function exportBackup(habits: Habit[]): Promise<string | void> {
     return;
   }
 
-  const { save } = await import('@tauri-apps/plugin-dialog');
-  const { writeTextFile, mkdir, exists } = await import('@tauri-apps/plugin-fs');
-  const { documentDir, join, dirname } = await import('@tauri-apps/api/path');
-
-  // Check if user has previously chosen a directory
-  let cachedDir: string | null = null;
-  try {
-    cachedDir = localStorage.getItem(LAST_EXPORT_DIR_KEY);
-  } catch {
-    // localStorage unavailable
-  }
-
-  // If we have a cached directory, use it directly without prompting
-  if (cachedDir) {
-    try {
-      const dirExists = await exists(cachedDir);
-      if (!dirExists) {
-        await mkdir(cachedDir, { recursive: true });
-      }
-      const fullPath = await join(cachedDir, filename);
-      await writeTextFile(fullPath, jsonText);
-      return fullPath;
-    } catch (e) {
-      console.warn('Failed to write to cached directory:', cachedDir, e);
-      // Clear the cached path and fall through to prompt
-      try {
-        localStorage.removeItem(LAST_EXPORT_DIR_KEY);
-      } catch {}
-    }
-  }
-
-  // No cached directory or it failed - prompt user
-  const baseDir = await documentDir();
-  const backupDir = await join(baseDir, 'HabitFlow');
-
-  const dirExists = await exists(backupDir);
-  if (!dirExists) {
-    await mkdir(backupDir, { recursive: true });
-  }
-
-  const defaultPath = await join(backupDir, filename);
-  const selectedPath = await save({
-    defaultPath,
-    filters: [{ name: 'JSON', extensions: ['json'] }],
+  // The backend writes to the folder used last time, or asks where to save.
+  const savedPath = await invoke<string | null>('save_backup', {
+    fileName: filename,
+    contents: jsonText,
   });
-
-  if (!selectedPath) return;
-
-  try {
-    await writeTextFile(selectedPath, jsonText);
-  } catch (err) {
-    const message = err instanceof Error ? err.message : String(err);
-    if (/not allowed|denied|forbidden/i.test(message)) {
-      throw new Error(
-        'Cannot write to the selected location due to Tauri file access permissions. Please choose a folder within your User directory (e.g., Documents, Desktop, Downloads).'
-      );
-    }
-    throw err;
-  }
-
-  // Save the directory (not the full path) for next time
-  try {
-    const selectedDir = await dirname(selectedPath);
-    localStorage.setItem(LAST_EXPORT_DIR_KEY, selectedDir);
-  } catch {
-    // ignore
-  }
-
-  return selectedPath;
+  return savedPath ?? undefined;
 }
 
 export async function importBackup(): Promise<Habit[] | null> {
@@ -99,23 +34,13 @@ export async function importBackup(): Promise<Habit[] | null> {
     throw new Error('Browser import is handled via file picker.');
   }
 
-  const { open } = await import('@tauri-apps/plugin-dialog');
-  const { readTextFile } = await import('@tauri-apps/plugin-fs');
-  const { documentDir, join } = await import('@tauri-apps/api/path');
-
-  const baseDir = await documentDir();
-  const backupDir = await join(baseDir, 'HabitFlow');
-
-  const selected = await open({
-    defaultPath: backupDir,
-    multiple: false,
+  // Only paths picked in the backend's dialog can be read.
+  const selected = await invoke<string | null>('pick_open_path', {
     filters: [{ name: 'JSON', extensions: ['json'] }],
   });
-
   if (!selected) return null;
-  if (Array.isArray(selected)) return null;
 
-  const text = await readTextFile(selected);
+  const text = await invoke<string>('import_data', { path: selected });
   return parseBackupJson(text);
 }
 
@@ -157,20 +82,7 @@ export async function maybeAutoBackup(habits: Habit[]): Promise<void> {
   const payload = createBackupPayload(habits);
   const jsonText = JSON.stringify(payload, null, 2);
 
-  const { writeTextFile, mkdir, exists } = await import('@tauri-apps/plugin-fs');
-  const { appDataDir, join } = await import('@tauri-apps/api/path');
-
-  const baseDir = await appDataDir();
-  const backupDir = await join(baseDir, 'HabitFlow', 'Backups');
-
-  const dirExists = await exists(backupDir);
-  if (!dirExists) {
-    await mkdir(backupDir, { recursive: true });
-  }
-
-  const filename = `autobackup-${today}.json`;
-  const path = await join(backupDir, filename);
-  await writeTextFile(path, jsonText);
+  await invoke('write_auto_backup', { contents: jsonText });
 
   try {
     localStorage.setItem(LAST_AUTO_BACKUP_KEY, now.toISOString());
@@ -0,0 +1,76 @@
+package com.sushil930.habitflow.nfc
+
+import android.app.Activity
+import android.content.Intent
+import android.nfc.NfcAdapter
+import android.webkit.WebView
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Channel
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+
+@InvokeArg
+class TagHandlerArgs {
+    lateinit var handler: Channel
+}
+
+// Registered from nfc.rs. A tag tapped against the phone opens the app with a
+// TAG/TECH/NDEF_DISCOVERED intent; its hardware id goes to the backend, which says what
+// happened in a notification. A tap from before the handler was set, such as the one
+// that launched the app, is held until it is.
+@TauriPlugin
+class NfcPlugin(private val activity: Activity) : Plugin(activity) {
+    override fun load(webView: WebView) {
+        receive(activity.intent)
+    }
+
+    override fun onNewIntent(intent: Intent) {
+        receive(intent)
+    }
+
+    @Command
+    fun setTagHandler(invoke: Invoke) {
+        val channel = invoke.parseArgs(TagHandlerArgs::class.java).handler
+        val held = synchronized(this) {
+            handler = channel
+            pending.toList().also { pending.clear() }
+        }
+        held.forEach { send(channel, it) }
+        invoke.resolve()
+    }
+
+    private fun receive(intent: Intent?) {
+        if (intent?.action !in DISCOVERED) return
+        val id = intent?.getByteArrayExtra(NfcAdapter.EXTRA_ID) ?: return
+        // Handled once, even if the activity is recreated with the same intent.
+        activity.intent = Intent(activity.intent).setAction(Intent.ACTION_MAIN)
+        val tagId = id.joinToString("") { "%02X".format(it) }
+        val channel = synchronized(this) {
+            handler ?: run {
+                pending.add(tagId)
+                null
+            }
+        }
+        channel?.let { send(it, tagId) }
+    }
+
+    private fun send(channel: Channel, tagId: String) {
+        val tap = JSObject()
+        tap.put("tagId", tagId)
+        channel.send(tap)
+    }
+
+    private var handler: Channel? = null
+    private val pending = mutableListOf<String>()
+
+    companion object {
+        private val DISCOVERED = setOf(
+            NfcAdapter.ACTION_NDEF_DISCOVERED,
+            NfcAdapter.ACTION_TECH_DISCOVERED,
+            NfcAdapter.ACTION_TAG_DISCOVERED,
+        )
+    }
+}
@@ -0,0 +1,70 @@
+package com.sushil930.habitflow.reminders
+
+import android.app.NotificationChannel
+import android.app.NotificationManager
+import android.content.Context
+import androidx.core.app.NotificationCompat
+import androidx.work.ExistingPeriodicWorkPolicy
+import androidx.work.PeriodicWorkRequestBuilder
+import androidx.work.WorkManager
+import androidx.work.Worker
+import androidx.work.WorkerParameters
+import org.json.JSONObject
+import java.io.File
+import java.time.OffsetDateTime
+import java.util.concurrent.TimeUnit
+
+// Reminders are registered two weeks ahead by the backend (mobile_reminders.rs), which only
+// runs while the app does. Once a day this checks reminders/horizon.json, and when they're
+// about to run out, asks for the app to be opened so the next two weeks get registered.
+class ReminderRefreshWorker(context: Context, params: WorkerParameters) : Worker(context, params) {
+    override fun doWork(): Result {
+        val file = File(applicationContext.dataDir, "reminders/horizon.json")
+        if (!file.exists()) return Result.success()
+        val until = runCatching {
+            JSONObject(file.readText()).optString("until").takeIf { it.isNotEmpty() }
+                ?.let { OffsetDateTime.parse(it) }
+        }.getOrNull() ?: return Result.success()
+        if (until.isAfter(OffsetDateTime.now().plusDays(LOW_DAYS))) return Result.success()
+
+        val manager = applicationContext.getSystemService(NotificationManager::class.java)
+        manager.createNotificationChannel(
+            NotificationChannel(CHANNEL, "Reminder upkeep", NotificationManager.IMPORTANCE_LOW),
+        )
+        val launch = applicationContext.packageManager
+            .getLaunchIntentForPackage(applicationContext.packageName)
+        val notification = NotificationCompat.Builder(applicationContext, CHANNEL)
+            .setSmallIcon(applicationContext.applicationInfo.icon)
+            .setContentTitle("Habit reminders are running out")
+            .setContentText("Open HabitFlow to keep them coming.")
+            .setAutoCancel(true)
+            .apply {
+                launch?.let {
+                    setContentIntent(
+                        android.app.PendingIntent.getActivity(
+                            applicationContext,
+                            0,
+                            it,
+                            android.app.PendingIntent.FLAG_IMMUTABLE,
+                        ),
+                    )
+                }
+            }
+            .build()
+        manager.notify(NOTIFICATION_ID, notification)
+        return Result.success()
+    }
+
+    companion object {
+        private const val WORK_NAME = "habitflow-reminder-refresh"
+        private const val CHANNEL = "reminder-upkeep"
+        private const val NOTIFICATION_ID = 0x68660001
+        private const val LOW_DAYS = 3L
+
+        fun schedule(context: Context) {
+            val request = PeriodicWorkRequestBuilder<ReminderRefreshWorker>(1, TimeUnit.DAYS).build()
+            WorkManager.getInstance(context)
+                .enqueueUniquePeriodicWork(WORK_NAME, ExistingPeriodicWorkPolicy.KEEP, request)
+        }
+    }
+}
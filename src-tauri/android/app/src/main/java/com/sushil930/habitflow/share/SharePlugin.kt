@@ -0,0 +1,113 @@
+package com.sushil930.habitflow.share
+
+import android.app.Activity
+import android.content.Intent
+import android.net.Uri
+import android.os.Build
+import android.webkit.WebView
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Channel
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.JSObject
+import app.tauri.plugin.Plugin
+import org.json.JSONArray
+import org.json.JSONObject
+import java.io.File
+import java.time.Instant
+import java.util.UUID
+
+@InvokeArg
+class ShareHandlerArgs {
+    lateinit var handler: Channel
+}
+
+// Registered from share.rs. Text and photos shared to the app arrive as ACTION_SEND or
+// ACTION_SEND_MULTIPLE intents on the main activity; they're copied into share/inbox/<id>/
+// and the backend is told to pick them up. The backend only ever reads the inbox, so a
+// share that arrives before the handler is set is picked up on start.
+@TauriPlugin
+class SharePlugin(private val activity: Activity) : Plugin(activity) {
+    override fun load(webView: WebView) {
+        receive(activity.intent)
+    }
+
+    override fun onNewIntent(intent: Intent) {
+        receive(intent)
+    }
+
+    @Command
+    fun setShareHandler(invoke: Invoke) {
+        handler = invoke.parseArgs(ShareHandlerArgs::class.java).handler
+        invoke.resolve()
+    }
+
+    private fun receive(intent: Intent?) {
+        if (intent == null) return
+        if (intent.action != Intent.ACTION_SEND && intent.action != Intent.ACTION_SEND_MULTIPLE) return
+        val text = intent.getStringExtra(Intent.EXTRA_TEXT)
+        val photos = streams(intent).filter {
+            activity.contentResolver.getType(it)?.startsWith("image/") == true
+        }
+        // Handled once, even if the activity is recreated with the same intent.
+        activity.intent = Intent(activity.intent).setAction(Intent.ACTION_MAIN)
+        if (text.isNullOrBlank() && photos.isEmpty()) return
+
+        val id = UUID.randomUUID().toString()
+        val inbox = File(activity.dataDir, INBOX_DIR)
+        // Written under a dot name and renamed, so the backend never sees half a share.
+        val partial = File(inbox, ".$id").apply { mkdirs() }
+        val files = JSONArray()
+        photos.forEachIndexed { i, uri ->
+            val name = "photo-${i + 1}.${extension(uri)}"
+            val copied = runCatching {
+                activity.contentResolver.openInputStream(uri)?.use { input ->
+                    File(partial, name).outputStream().use { input.copyTo(it) }
+                }
+            }.isSuccess
+            if (copied) files.put(name)
+        }
+        val manifest = JSONObject()
+            .put("receivedAt", Instant.now().toString())
+            .put("files", files)
+        text?.takeIf { it.isNotBlank() }?.let { manifest.put("text", it) }
+        File(partial, "share.json").writeText(manifest.toString())
+        partial.renameTo(File(inbox, id))
+
+        handler?.send(JSObject().put("id", id))
+    }
+
+    @Suppress("DEPRECATION")
+    private fun streams(intent: Intent): List<Uri> = when (intent.action) {
+        Intent.ACTION_SEND_MULTIPLE ->
+            if (Build.VERSION.SDK_INT >= 33) {
+                intent.getParcelableArrayListExtra(Intent.EXTRA_STREAM, Uri::class.java)
+            } else {
+                intent.getParcelableArrayListExtra(Intent.EXTRA_STREAM)
+            }.orEmpty()
+        else -> listOfNotNull(
+            if (Build.VERSION.SDK_INT >= 33) {
+                intent.getParcelableExtra(Intent.EXTRA_STREAM, Uri::class.java)
+            } else {
+                intent.getParcelableExtra(Intent.EXTRA_STREAM)
+            },
+        )
+    }
+
+    // The backend takes PNG, JPEG, WebP and GIF; anything else is refused on attach.
+    private fun extension(uri: Uri): String = when (activity.contentResolver.getType(uri)) {
+        "image/png" -> "png"
+        "image/webp" -> "webp"
+        "image/gif" -> "gif"
+        "image/jpeg" -> "jpg"
+        else -> "bin"
+    }
+
+    companion object {
+        private const val INBOX_DIR = "share/inbox"
+
+        @Volatile
+        var handler: Channel? = null
+    }
+}
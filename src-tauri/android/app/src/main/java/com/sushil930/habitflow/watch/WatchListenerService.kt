@@ -0,0 +1,54 @@
+package com.sushil930.habitflow.watch
+
+import android.content.Context
+import app.tauri.plugin.JSObject
+import com.google.android.gms.wearable.MessageEvent
+import com.google.android.gms.wearable.PutDataMapRequest
+import com.google.android.gms.wearable.Wearable
+import com.google.android.gms.wearable.WearableListenerService
+import com.sushil930.habitflow.widget.WidgetTapReceiver
+import org.json.JSONObject
+import java.io.File
+
+// The phone side of the Wear OS app. The glance the backend writes (watch.rs) is kept on
+// the Data Layer at /habitflow/glance; a "complete" message from the watch goes to the
+// backend like a widget tap, and the glance written after it reaches the watch the same
+// way.
+class WatchListenerService : WearableListenerService() {
+    override fun onMessageReceived(event: MessageEvent) {
+        when (event.path) {
+            COMPLETE_PATH -> {
+                val request = runCatching { JSONObject(String(event.data)) }.getOrNull()
+                val glance = glance(this)
+                // Without a habit, the glance's next one.
+                val habitId = request?.optString("habitId")?.takeIf { it.isNotEmpty() }
+                    ?: glance?.optJSONObject("next")?.optString("id")
+                    ?: return
+                val tap = JSObject()
+                tap.put("action", "complete")
+                tap.put("habitId", habitId)
+                glance?.optString("date")?.let { tap.put("date", it) }
+                WidgetTapReceiver.send(this, tap)
+            }
+            GLANCE_PATH -> push(this)
+        }
+    }
+
+    companion object {
+        const val GLANCE_PATH = "/habitflow/glance"
+        const val COMPLETE_PATH = "/habitflow/complete"
+
+        private fun glance(context: Context): JSONObject? =
+            File(context.dataDir, "widget/glance.json").takeIf { it.exists() }?.let {
+                runCatching { JSONObject(it.readText()) }.getOrNull()
+            }
+
+        // Called when the backend has written a new glance.
+        fun push(context: Context) {
+            val glance = glance(context) ?: return
+            val request = PutDataMapRequest.create(GLANCE_PATH)
+            request.dataMap.putString("glance", glance.toString())
+            Wearable.getDataClient(context).putDataItem(request.asPutDataRequest().setUrgent())
+        }
+    }
+}
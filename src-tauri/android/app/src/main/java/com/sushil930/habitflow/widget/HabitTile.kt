@@ -0,0 +1,45 @@
+package com.sushil930.habitflow.widget
+
+import android.content.ComponentName
+import android.content.Context
+import android.service.quicksettings.Tile
+import android.service.quicksettings.TileService
+import app.tauri.plugin.JSObject
+
+// The Quick Settings tile: toggles one habit for today. Its label and state come from
+// the "tile" entry of widget/today.json; taps go the same way as the widget's.
+class HabitTile : TileService() {
+    override fun onStartListening() {
+        val tile = qsTile ?: return
+        val state = HabitWidget.readSnapshot(this)?.optJSONObject("tile")
+        if (state == null) {
+            tile.label = "HabitFlow"
+            tile.state = Tile.STATE_UNAVAILABLE
+        } else {
+            tile.label = state.optString("label")
+            tile.state = when {
+                !state.optBoolean("available", true) -> Tile.STATE_UNAVAILABLE
+                state.optBoolean("done") -> Tile.STATE_ACTIVE
+                else -> Tile.STATE_INACTIVE
+            }
+        }
+        tile.updateTile()
+    }
+
+    override fun onClick() {
+        val tile = qsTile ?: return
+        if (tile.state == Tile.STATE_UNAVAILABLE) return
+        val tap = JSObject()
+        tap.put("action", "toggleTile")
+        WidgetTapReceiver.send(this, tap)
+        // Shown flipped right away; the backend's next snapshot has the real state.
+        tile.state = if (tile.state == Tile.STATE_ACTIVE) Tile.STATE_INACTIVE else Tile.STATE_ACTIVE
+        tile.updateTile()
+    }
+
+    companion object {
+        fun update(context: Context) {
+            TileService.requestListeningState(context, ComponentName(context, HabitTile::class.java))
+        }
+    }
+}
@@ -0,0 +1,99 @@
+package com.sushil930.habitflow.widget
+
+import android.app.PendingIntent
+import android.appwidget.AppWidgetManager
+import android.appwidget.AppWidgetProvider
+import android.content.ComponentName
+import android.content.Context
+import android.content.Intent
+import android.graphics.Color
+import android.view.View
+import android.widget.RemoteViews
+import com.sushil930.habitflow.R
+import org.json.JSONObject
+import java.io.File
+
+// Today's habits from widget/today.json, which the backend (widgets.rs) rewrites
+// whenever they change. Each row checks its habit off when tapped.
+class HabitWidget : AppWidgetProvider() {
+    override fun onUpdate(context: Context, manager: AppWidgetManager, ids: IntArray) {
+        val views = render(context)
+        ids.forEach { manager.updateAppWidget(it, views) }
+    }
+
+    companion object {
+        private const val MAX_ROWS = 6
+
+        fun snapshotFile(context: Context) = File(context.dataDir, "widget/today.json")
+
+        fun intentsFile(context: Context) = File(context.dataDir, "widget/intents.jsonl")
+
+        fun readSnapshot(context: Context): JSONObject? =
+            snapshotFile(context).takeIf { it.exists() }?.let {
+                runCatching { JSONObject(it.readText()) }.getOrNull()
+            }
+
+        fun updateAll(context: Context) {
+            val manager = AppWidgetManager.getInstance(context)
+            val ids = manager.getAppWidgetIds(ComponentName(context, HabitWidget::class.java))
+            if (ids.isNotEmpty()) {
+                val views = render(context)
+                ids.forEach { manager.updateAppWidget(it, views) }
+            }
+        }
+
+        private fun render(context: Context): RemoteViews {
+            val views = RemoteViews(context.packageName, R.layout.habit_widget)
+            views.removeAllViews(R.id.habit_rows)
+            val snapshot = readSnapshot(context)
+            if (snapshot == null) {
+                views.setTextViewText(R.id.habit_summary, "Open HabitFlow to get started")
+                return views
+            }
+            val done = snapshot.optInt("done")
+            val total = snapshot.optInt("total")
+            views.setTextViewText(
+                R.id.habit_summary,
+                if (total == 0) "Nothing due today" else "$done of $total done today",
+            )
+
+            // Hidden while locked or in privacy mode: the counts are all there is.
+            val habits = snapshot.optJSONArray("habits")
+            val date = snapshot.optString("date")
+            val shown = minOf(habits?.length() ?: 0, MAX_ROWS)
+            for (i in 0 until shown) {
+                val habit = habits!!.getJSONObject(i)
+                val id = habit.getString("id")
+                val row = RemoteViews(context.packageName, R.layout.habit_widget_row)
+                val emoji = habit.optString("emoji")
+                val name = habit.optString("name")
+                row.setTextViewText(R.id.habit_name, if (emoji.isEmpty()) name else "$emoji $name")
+                val goal = habit.optInt("goal", 1)
+                row.setTextViewText(
+                    R.id.habit_progress,
+                    if (goal > 1) "${habit.optInt("count")}/$goal" else "",
+                )
+                runCatching { Color.parseColor(habit.optString("color")) }.getOrNull()?.let {
+                    row.setInt(R.id.habit_color, "setBackgroundColor", it)
+                }
+                val isDone = habit.optBoolean("done")
+                row.setViewVisibility(R.id.habit_check, if (isDone) View.VISIBLE else View.INVISIBLE)
+                if (!isDone) {
+                    val tap = Intent(context, WidgetTapReceiver::class.java)
+                        .setAction(WidgetTapReceiver.ACTION_COMPLETE)
+                        .putExtra(WidgetTapReceiver.EXTRA_HABIT_ID, id)
+                        .putExtra(WidgetTapReceiver.EXTRA_DATE, date)
+                    val pending = PendingIntent.getBroadcast(
+                        context,
+                        id.hashCode(),
+                        tap,
+                        PendingIntent.FLAG_UPDATE_CURRENT or PendingIntent.FLAG_IMMUTABLE,
+                    )
+                    row.setOnClickPendingIntent(R.id.habit_row, pending)
+                }
+                views.addView(R.id.habit_rows, row)
+            }
+            return views
+        }
+    }
+}
@@ -0,0 +1,47 @@
+package com.sushil930.habitflow.widget
+
+import android.app.Activity
+import android.webkit.WebView
+import app.tauri.annotation.Command
+import app.tauri.annotation.InvokeArg
+import app.tauri.annotation.TauriPlugin
+import app.tauri.plugin.Channel
+import app.tauri.plugin.Invoke
+import app.tauri.plugin.Plugin
+import com.sushil930.habitflow.reminders.ReminderRefreshWorker
+import com.sushil930.habitflow.watch.WatchListenerService
+
+@InvokeArg
+class TapHandlerArgs {
+    lateinit var handler: Channel
+}
+
+// Registered from widgets.rs. Taps go to the backend through the handler while
+// the app is running.
+@TauriPlugin
+class WidgetPlugin(private val activity: Activity) : Plugin(activity) {
+    // The app's one native hook at startup, so background work is set up here too.
+    override fun load(webView: WebView) {
+        ReminderRefreshWorker.schedule(activity.applicationContext)
+    }
+
+    @Command
+    fun setTapHandler(invoke: Invoke) {
+        tapHandler = invoke.parseArgs(TapHandlerArgs::class.java).handler
+        invoke.resolve()
+    }
+
+    // The snapshot and the watch's glance were rewritten.
+    @Command
+    fun refresh(invoke: Invoke) {
+        HabitWidget.updateAll(activity.applicationContext)
+        HabitTile.update(activity.applicationContext)
+        WatchListenerService.push(activity.applicationContext)
+        invoke.resolve()
+    }
+
+    companion object {
+        @Volatile
+        var tapHandler: Channel? = null
+    }
+}
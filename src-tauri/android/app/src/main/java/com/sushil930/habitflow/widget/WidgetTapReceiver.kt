@@ -0,0 +1,65 @@
+package com.sushil930.habitflow.widget
+
+import android.content.BroadcastReceiver
+import android.content.Context
+import android.content.Intent
+import app.tauri.plugin.JSObject
+
+// A tap on a widget row. Goes straight to the backend when the app is running; otherwise
+// it's queued in widget/intents.jsonl for the next start. Either way the row is shown as
+// done right away, and the backend's next snapshot corrects it if the tap was refused.
+class WidgetTapReceiver : BroadcastReceiver() {
+    override fun onReceive(context: Context, intent: Intent) {
+        if (intent.action != ACTION_COMPLETE) return
+        val habitId = intent.getStringExtra(EXTRA_HABIT_ID) ?: return
+        val date = intent.getStringExtra(EXTRA_DATE)
+
+        val tap = JSObject()
+        tap.put("action", "complete")
+        tap.put("habitId", habitId)
+        date?.let { tap.put("date", it) }
+        send(context, tap)
+
+        markDone(context, habitId)
+        HabitWidget.updateAll(context)
+    }
+
+    private fun markDone(context: Context, habitId: String) {
+        val snapshot = HabitWidget.readSnapshot(context) ?: return
+        val habits = snapshot.optJSONArray("habits") ?: return
+        for (i in 0 until habits.length()) {
+            val habit = habits.getJSONObject(i)
+            if (habit.getString("id") != habitId || habit.optBoolean("done")) continue
+            habit.put("count", habit.optInt("count") + 1)
+            // A repeated habit is done once the backend says its last check-in landed.
+            if (!habit.optBoolean("repeated")) {
+                habit.put("done", true)
+                snapshot.put("done", snapshot.optInt("done") + 1)
+            }
+        }
+        val file = HabitWidget.snapshotFile(context)
+        val temp = java.io.File(file.parentFile, "today.widget.tmp")
+        temp.writeText(snapshot.toString())
+        temp.renameTo(file)
+    }
+
+    companion object {
+        const val ACTION_COMPLETE = "com.sushil930.habitflow.widget.COMPLETE"
+        const val EXTRA_HABIT_ID = "habitId"
+        const val EXTRA_DATE = "date"
+
+        // To the backend if it's running, else onto the queue.
+        fun send(context: Context, tap: JSObject) {
+            val handler = WidgetPlugin.tapHandler
+            if (handler != null) {
+                handler.send(tap)
+                return
+            }
+            synchronized(WidgetTapReceiver::class.java) {
+                val file = HabitWidget.intentsFile(context)
+                file.parentFile?.mkdirs()
+                file.appendText(tap.toString() + "\n")
+            }
+        }
+    }
+}
@@ -0,0 +1,170 @@
+import AppIntents
+import Foundation
+
+// Siri and Shortcuts actions. Each one is a request to the backend (shortcuts.rs), which
+// does the work and says what Siri should answer.
+
+@_silgen_name("habitflow_run_shortcut")
+func habitflow_run_shortcut(_ request: UnsafePointer<CChar>) -> UnsafeMutablePointer<CChar>?
+
+@_silgen_name("habitflow_free_string")
+func habitflow_free_string(_ s: UnsafeMutablePointer<CChar>?)
+
+struct BackendError: Error, CustomLocalizedStringResourceConvertible {
+  let message: String
+  var localizedStringResource: LocalizedStringResource { "\(message)" }
+}
+
+struct Reply: Decodable {
+  struct Habit: Decodable {
+    let id: String
+    let name: String
+    let unit: String?
+  }
+  let dialog: String
+  let done: Int?
+  let total: Int?
+  let habits: [Habit]?
+}
+
+enum Backend {
+  private struct Response: Decodable {
+    struct Failure: Decodable {
+      let code: String
+      let message: String
+    }
+    let reply: Reply?
+    let error: Failure?
+  }
+
+  // A launch for a shortcut runs the backend's setup first, so "failed" is retried for
+  // a few seconds before giving up.
+  static func run(_ request: [String: Any]) async throws -> Reply {
+    let json = String(data: try JSONSerialization.data(withJSONObject: request), encoding: .utf8)!
+    for attempt in 0..<10 {
+      guard let raw = json.withCString({ habitflow_run_shortcut($0) }) else {
+        throw BackendError(message: "HabitFlow didn't answer")
+      }
+      let data = Data(String(cString: raw).utf8)
+      habitflow_free_string(raw)
+      let response = try JSONDecoder().decode(Response.self, from: data)
+      if let reply = response.reply {
+        return reply
+      }
+      let failure = response.error
+      if failure?.code != "failed" || attempt == 9 {
+        throw BackendError(message: failure?.message ?? "Something went wrong")
+      }
+      try await Task.sleep(nanoseconds: 500_000_000)
+    }
+    throw BackendError(message: "HabitFlow didn't answer")
+  }
+}
+
+struct HabitEntity: AppEntity {
+  static var typeDisplayRepresentation: TypeDisplayRepresentation = "Habit"
+  static var defaultQuery = HabitQuery()
+
+  let id: String
+  let name: String
+
+  var displayRepresentation: DisplayRepresentation { "\(name)" }
+}
+
+struct HabitQuery: EntityStringQuery {
+  func all() async throws -> [HabitEntity] {
+    let reply = try await Backend.run(["intent": "listHabits"])
+    return (reply.habits ?? []).map { HabitEntity(id: $0.id, name: $0.name) }
+  }
+
+  func entities(for identifiers: [String]) async throws -> [HabitEntity] {
+    try await all().filter { identifiers.contains($0.id) }
+  }
+
+  func entities(matching string: String) async throws -> [HabitEntity] {
+    try await all().filter { $0.name.localizedCaseInsensitiveContains(string) }
+  }
+
+  func suggestedEntities() async throws -> [HabitEntity] {
+    try await all()
+  }
+}
+
+struct CompleteHabitIntent: AppIntent {
+  static var title: LocalizedStringResource = "Complete Habit"
+  static var description = IntentDescription("Checks a habit off for today.")
+
+  @Parameter(title: "Habit")
+  var habit: HabitEntity
+
+  func perform() async throws -> some IntentResult & ProvidesDialog {
+    let reply = try await Backend.run(["intent": "completeHabit", "habit": habit.id])
+    return .result(dialog: "\(reply.dialog)")
+  }
+}
+
+struct LogValueIntent: AppIntent {
+  static var title: LocalizedStringResource = "Log Value"
+  static var description = IntentDescription("Adds an amount to today's total for a habit.")
+
+  @Parameter(title: "Habit")
+  var habit: HabitEntity
+
+  @Parameter(title: "Amount")
+  var value: Double
+
+  func perform() async throws -> some IntentResult & ProvidesDialog {
+    let reply = try await Backend.run([
+      "intent": "logValue", "habit": habit.id, "value": value,
+    ])
+    return .result(dialog: "\(reply.dialog)")
+  }
+}
+
+struct TodayProgressIntent: AppIntent {
+  static var title: LocalizedStringResource = "Get Today's Progress"
+  static var description = IntentDescription("Says which habits are done today and which are left.")
+
+  func perform() async throws -> some IntentResult & ReturnsValue<Int> & ProvidesDialog {
+    let reply = try await Backend.run(["intent": "todayProgress"])
+    return .result(value: reply.done ?? 0, dialog: "\(reply.dialog)")
+  }
+}
+
+// For a Shortcuts automation on an NFC tag: iOS doesn't hand the tag's id over, so the
+// tag is named by the label it was bound with.
+struct CheckInWithTagIntent: AppIntent {
+  static var title: LocalizedStringResource = "Check In with Tag"
+  static var description = IntentDescription("Checks off the habit an NFC tag is linked to.")
+
+  @Parameter(title: "Tag label")
+  var tag: String
+
+  func perform() async throws -> some IntentResult & ProvidesDialog {
+    let reply = try await Backend.run(["intent": "scanTag", "tag": tag])
+    return .result(dialog: "\(reply.dialog)")
+  }
+}
+
+struct HabitShortcuts: AppShortcutsProvider {
+  static var appShortcuts: [AppShortcut] {
+    AppShortcut(
+      intent: CompleteHabitIntent(),
+      phrases: ["Complete \(\.$habit) in \(.applicationName)", "Mark \(\.$habit) done in \(.applicationName)"],
+      shortTitle: "Complete Habit",
+      systemImageName: "checkmark.circle"
+    )
+    AppShortcut(
+      intent: LogValueIntent(),
+      phrases: ["Log \(\.$habit) in \(.applicationName)"],
+      shortTitle: "Log Value",
+      systemImageName: "plus.circle"
+    )
+    AppShortcut(
+      intent: TodayProgressIntent(),
+      phrases: ["Which habits are done in \(.applicationName)", "How am I doing in \(.applicationName)"],
+      shortTitle: "Today's Progress",
+      systemImageName: "chart.bar"
+    )
+  }
+}
@@ -0,0 +1,44 @@
+import BackgroundTasks
+import Foundation
+
+// Reminders are registered two weeks ahead by the backend (mobile_reminders.rs). iOS wakes
+// the app now and then for this task, which has the backend register the next two weeks.
+
+@_silgen_name("habitflow_refresh_reminders")
+func habitflow_refresh_reminders() -> Bool
+
+enum ReminderRefresh {
+  static let identifier = "com.sushil930.habitflow.reminders"
+
+  static func scheduleNext() {
+    let request = BGAppRefreshTaskRequest(identifier: identifier)
+    request.earliestBeginDate = Date(timeIntervalSinceNow: 12 * 60 * 60)
+    try? BGTaskScheduler.shared.submit(request)
+  }
+
+  static func handle(_ task: BGAppRefreshTask) {
+    scheduleNext()
+    let work = Task.detached {
+      // A launch for the task runs the backend's setup first.
+      for _ in 0..<20 {
+        if habitflow_refresh_reminders() {
+          task.setTaskCompleted(success: true)
+          return
+        }
+        try? await Task.sleep(nanoseconds: 500_000_000)
+      }
+      task.setTaskCompleted(success: false)
+    }
+    task.expirationHandler = { work.cancel() }
+  }
+}
+
+// Called by the backend before the app finishes launching, as iOS requires.
+@_cdecl("habitflow_register_reminder_refresh")
+func registerReminderRefresh() {
+  BGTaskScheduler.shared.register(forTaskWithIdentifier: ReminderRefresh.identifier, using: nil) {
+    task in
+    ReminderRefresh.handle(task as! BGAppRefreshTask)
+  }
+  ReminderRefresh.scheduleNext()
+}
@@ -0,0 +1,65 @@
+import Foundation
+import WatchConnectivity
+
+// The phone side of the watch app. The glance the backend writes next to the widget's
+// snapshot (watch.rs) goes to the watch as the application context, sent again whenever
+// the widget reloads; messages from the watch ({"action": "glance"} or
+// {"action": "complete", "habitId": ...}) go to the backend and are answered with the
+// glance as it is afterwards.
+
+@_silgen_name("habitflow_watch_request")
+func habitflow_watch_request(_ request: UnsafePointer<CChar>) -> UnsafeMutablePointer<CChar>?
+
+final class WatchBridge: NSObject, WCSessionDelegate {
+  static let shared = WatchBridge()
+
+  func activate() {
+    guard WCSession.isSupported() else { return }
+    WCSession.default.delegate = self
+    WCSession.default.activate()
+  }
+
+  // Called when the backend has written a new glance.
+  func push() {
+    guard WCSession.isSupported(), WCSession.default.activationState == .activated,
+      WCSession.default.isPaired, WCSession.default.isWatchAppInstalled,
+      let container = FileManager.default.containerURL(
+        forSecurityApplicationGroupIdentifier: WidgetPlugin.appGroup),
+      let data = try? Data(contentsOf: container.appendingPathComponent("widget/glance.json")),
+      let glance = try? JSONSerialization.jsonObject(with: data) as? [String: Any]
+    else { return }
+    try? WCSession.default.updateApplicationContext(["glance": glance])
+    if WCSession.default.isComplicationEnabled {
+      WCSession.default.transferCurrentComplicationUserInfo(["glance": glance])
+    }
+  }
+
+  func session(
+    _ session: WCSession, didReceiveMessage message: [String: Any],
+    replyHandler: @escaping ([String: Any]) -> Void
+  ) {
+    guard let request = try? JSONSerialization.data(withJSONObject: message),
+      let json = String(data: request, encoding: .utf8),
+      let raw = json.withCString({ habitflow_watch_request($0) })
+    else {
+      replyHandler(["error": ["code": "failed", "message": "HabitFlow didn't answer"]])
+      return
+    }
+    let data = Data(String(cString: raw).utf8)
+    habitflow_free_string(raw)
+    replyHandler((try? JSONSerialization.jsonObject(with: data) as? [String: Any]) ?? [:])
+  }
+
+  func session(
+    _ session: WCSession, activationDidCompleteWith state: WCSessionActivationState,
+    error: Error?
+  ) {
+    if state == .activated { push() }
+  }
+
+  func sessionDidBecomeInactive(_ session: WCSession) {}
+
+  func sessionDidDeactivate(_ session: WCSession) {
+    WCSession.default.activate()
+  }
+}
@@ -0,0 +1,36 @@
+import Tauri
+import UIKit
+import WebKit
+import WidgetKit
+
+// Registered from widgets.rs. The backend writes the widget's snapshot into the App Group
+// container and calls reload when it changed. The watch's glance is written with it.
+class WidgetPlugin: Plugin {
+  static let appGroup = "group.com.sushil930.habitflow"
+
+  override func load(webview: WKWebView) {
+    WatchBridge.shared.activate()
+  }
+
+  @objc public func containerPath(_ invoke: Invoke) {
+    guard
+      let url = FileManager.default.containerURL(
+        forSecurityApplicationGroupIdentifier: WidgetPlugin.appGroup)
+    else {
+      invoke.reject("the \(WidgetPlugin.appGroup) App Group isn't set up")
+      return
+    }
+    invoke.resolve(["path": url.path])
+  }
+
+  @objc public func reload(_ invoke: Invoke) {
+    WidgetCenter.shared.reloadAllTimelines()
+    WatchBridge.shared.push()
+    invoke.resolve()
+  }
+}
+
+@_cdecl("init_plugin_habit_widget")
+func initPlugin() -> Plugin {
+  return WidgetPlugin()
+}
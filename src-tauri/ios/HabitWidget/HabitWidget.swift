@@ -0,0 +1,127 @@
+import SwiftUI
+import WidgetKit
+
+// Today's progress and streak, from widget/today.json in the App Group container. The app
+// rewrites the file and reloads the timeline whenever it changes (widgets.rs).
+
+struct TodayHabit: Decodable {
+  let id: String
+  let name: String
+  let emoji: String?
+  var done: Bool
+  let streak: Int
+}
+
+struct TodaySnapshot: Decodable {
+  let date: String
+  var habits: [TodayHabit]
+  var done: Int
+  let total: Int
+  let streak: Int
+  let hidden: Bool
+
+  // The same habits with nothing done yet, until the app writes the new day's snapshot.
+  func nextDay() -> TodaySnapshot {
+    var next = self
+    next.done = 0
+    next.habits = habits.map { habit in
+      var habit = habit
+      habit.done = false
+      return habit
+    }
+    return next
+  }
+}
+
+struct TodayEntry: TimelineEntry {
+  let date: Date
+  let snapshot: TodaySnapshot?
+}
+
+struct TodayProvider: TimelineProvider {
+  static let appGroup = "group.com.sushil930.habitflow"
+
+  func load() -> TodaySnapshot? {
+    guard
+      let url = FileManager.default.containerURL(
+        forSecurityApplicationGroupIdentifier: TodayProvider.appGroup),
+      let data = try? Data(contentsOf: url.appendingPathComponent("widget/today.json"))
+    else { return nil }
+    return try? JSONDecoder().decode(TodaySnapshot.self, from: data)
+  }
+
+  func placeholder(in context: Context) -> TodayEntry {
+    TodayEntry(date: Date(), snapshot: nil)
+  }
+
+  func getSnapshot(in context: Context, completion: @escaping (TodayEntry) -> Void) {
+    completion(TodayEntry(date: Date(), snapshot: load()))
+  }
+
+  // Nothing changes between the app's reloads except the day, so the next entry is at
+  // midnight, when yesterday's checks no longer count.
+  func getTimeline(in context: Context, completion: @escaping (Timeline<TodayEntry>) -> Void) {
+    let now = Date()
+    let midnight = Calendar.current.startOfDay(for: now).addingTimeInterval(24 * 60 * 60)
+    let snapshot = load()
+    let entries = [
+      TodayEntry(date: now, snapshot: snapshot),
+      TodayEntry(date: midnight, snapshot: snapshot?.nextDay()),
+    ]
+    completion(Timeline(entries: entries, policy: .after(midnight)))
+  }
+}
+
+struct TodayView: View {
+  @Environment(\.widgetFamily) var family
+  let entry: TodayEntry
+
+  var body: some View {
+    let snapshot = entry.snapshot
+    let done = snapshot?.done ?? 0
+    let total = snapshot?.total ?? 0
+    let streak = snapshot?.streak ?? 0
+    switch family {
+    case .accessoryCircular:
+      Gauge(value: Double(done), in: 0...Double(max(total, 1))) {
+        Text("🔥")
+      } currentValueLabel: {
+        Text("\(done)/\(total)")
+      }
+      .gaugeStyle(.accessoryCircularCapacity)
+    case .accessoryRectangular:
+      VStack(alignment: .leading) {
+        Text("\(done) of \(total) done").font(.headline)
+        Text("🔥 \(streak) day streak")
+      }
+    default:
+      VStack(alignment: .leading, spacing: 4) {
+        Text("\(done) of \(total) done").font(.headline)
+        ProgressView(value: Double(done), total: Double(max(total, 1)))
+        Text("🔥 \(streak)").font(.subheadline)
+        // Left out while the app is locked or in privacy mode.
+        if let habits = snapshot?.habits, !(snapshot?.hidden ?? true) {
+          ForEach(habits.prefix(3), id: \.id) { habit in
+            Text("\(habit.done ? "✓" : "○") \(habit.emoji ?? "") \(habit.name)")
+              .font(.caption)
+              .lineLimit(1)
+          }
+        }
+        Spacer(minLength: 0)
+      }
+      .containerBackground(.fill.tertiary, for: .widget)
+    }
+  }
+}
+
+@main
+struct HabitWidget: Widget {
+  var body: some WidgetConfiguration {
+    StaticConfiguration(kind: "HabitWidget", provider: TodayProvider()) { entry in
+      TodayView(entry: entry)
+    }
+    .configurationDisplayName("Today")
+    .description("Today's progress and your longest streak.")
+    .supportedFamilies([.systemSmall, .accessoryCircular, .accessoryRectangular])
+  }
+}
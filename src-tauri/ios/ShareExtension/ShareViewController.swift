@@ -0,0 +1,95 @@
+import UIKit
+import UniformTypeIdentifiers
+
+// The share extension. It can't reach the app's database, so it only copies what was
+// shared into share/inbox/<id>/ in the App Group container; the app picks the share up
+// from there when it next starts or comes to the foreground (share.rs).
+class ShareViewController: UIViewController {
+  static let appGroup = "group.com.sushil930.habitflow"
+
+  override func viewDidAppear(_ animated: Bool) {
+    super.viewDidAppear(animated)
+    Task {
+      let saved = await save()
+      let alert = UIAlertController(
+        title: saved ? "Saved for HabitFlow" : "Nothing to save",
+        message: saved ? "Open HabitFlow to choose the habit it's for." : nil,
+        preferredStyle: .alert)
+      alert.addAction(
+        UIAlertAction(title: "OK", style: .default) { _ in
+          self.extensionContext?.completeRequest(returningItems: nil)
+        })
+      present(alert, animated: true)
+    }
+  }
+
+  private func save() async -> Bool {
+    guard
+      let container = FileManager.default.containerURL(
+        forSecurityApplicationGroupIdentifier: ShareViewController.appGroup)
+    else { return false }
+    let providers = (extensionContext?.inputItems as? [NSExtensionItem] ?? [])
+      .flatMap { $0.attachments ?? [] }
+
+    var texts: [String] = []
+    var photos: [Data] = []
+    for provider in providers {
+      if provider.hasItemConformingToTypeIdentifier(UTType.image.identifier),
+        let photo = await jpeg(from: provider)
+      {
+        photos.append(photo)
+      } else if provider.hasItemConformingToTypeIdentifier(UTType.url.identifier),
+        let url = try? await provider.loadItem(forTypeIdentifier: UTType.url.identifier) as? URL
+      {
+        texts.append(url.absoluteString)
+      } else if provider.hasItemConformingToTypeIdentifier(UTType.plainText.identifier),
+        let text = try? await provider.loadItem(forTypeIdentifier: UTType.plainText.identifier)
+          as? String
+      {
+        texts.append(text)
+      }
+    }
+    let text = texts.joined(separator: "\n").trimmingCharacters(in: .whitespacesAndNewlines)
+    if text.isEmpty && photos.isEmpty { return false }
+
+    let id = UUID().uuidString
+    let inbox = container.appendingPathComponent("share/inbox")
+    // Written under a dot name and renamed, so the app never sees half a share.
+    let partial = inbox.appendingPathComponent(".\(id)")
+    do {
+      try FileManager.default.createDirectory(at: partial, withIntermediateDirectories: true)
+      var files: [String] = []
+      for (i, photo) in photos.enumerated() {
+        let name = "photo-\(i + 1).jpg"
+        try photo.write(to: partial.appendingPathComponent(name))
+        files.append(name)
+      }
+      var manifest: [String: Any] = [
+        "receivedAt": ISO8601DateFormatter().string(from: Date()),
+        "files": files,
+      ]
+      if !text.isEmpty { manifest["text"] = text }
+      try JSONSerialization.data(withJSONObject: manifest)
+        .write(to: partial.appendingPathComponent("share.json"))
+      try FileManager.default.moveItem(at: partial, to: inbox.appendingPathComponent(id))
+      return true
+    } catch {
+      try? FileManager.default.removeItem(at: partial)
+      return false
+    }
+  }
+
+  // Photos come as HEIC more often than not; the app takes JPEG.
+  private func jpeg(from provider: NSItemProvider) async -> Data? {
+    guard let item = try? await provider.loadItem(forTypeIdentifier: UTType.image.identifier)
+    else { return nil }
+    let image: UIImage?
+    switch item {
+    case let url as URL: image = UIImage(contentsOfFile: url.path)
+    case let data as Data: image = UIImage(data: data)
+    case let picture as UIImage: image = picture
+    default: image = nil
+    }
+    return image?.jpegData(compressionQuality: 0.85)
+  }
+}
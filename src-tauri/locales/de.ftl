@@ -0,0 +1,127 @@
+# German. Keep the ids and placeables as in en.ftl.
+
+## Counts
+
+habit-count = { $count ->
+    [one] 1 Gewohnheit
+   *[other] { $count } Gewohnheiten
+}
+habits-due = { $count ->
+    [one] 1 Gewohnheit fällig
+   *[other] { $count } Gewohnheiten fällig
+}
+
+## Tray
+
+tray-open = Öffnen
+tray-add-habit = Gewohnheit hinzufügen
+tray-quit = Beenden
+tray-all-done = Heute alles erledigt
+tray-tooltip-level = HabitFlow · Level { $level } ({ $xp }/{ $next } XP)
+tray-focus = Fokus
+tray-stop-focus = Fokus beenden
+tray-next-minutes = Als Nächstes: { $habit } in { $minutes } Min.
+tray-next-hours = Als Nächstes: { $habit } in { $hours } Std. { $minutes } Min.
+tray-next-tomorrow = Als Nächstes: { $habit } morgen um { $time }
+tray-next-later = Als Nächstes: { $habit } am { $date } um { $time }
+tray-next-hidden = eine Gewohnheit
+
+## Reminders
+
+reminder-title = Erinnerung
+missed-title = Heute noch offen
+missed-hidden = { $count ->
+    [one] 1 Gewohnheit heute noch offen
+   *[other] { $count } Gewohnheiten heute noch offen
+}
+missed-more = { $names } (+{ $count } weitere)
+up-next-title = Als Nächstes
+up-next-hidden = { $count ->
+    [one] 1 Gewohnheit bereit
+   *[other] { $count } Gewohnheiten bereit
+}
+welcome-back-title = Willkommen zurück
+welcome-back-habit = { $name } ist ab heute wieder aktiv
+welcome-back-hidden = { $count ->
+    [one] 1 Gewohnheit ist ab heute wieder aktiv
+   *[other] { $count } Gewohnheiten sind ab heute wieder aktiv
+}
+
+## Progress
+
+achievement-title = Erfolg freigeschaltet
+challenge-title = Herausforderung geschafft
+challenge-hidden = Heutige Herausforderung (+{ $xp } XP)
+level-up-title = ⬆️ Level aufgestiegen!
+level-up-body = Du hast Level { $level } erreicht
+milestone-title = 🎉 Meilenstein erreicht
+milestone-hidden = Eine Gewohnheit hat einen Meilenstein erreicht
+milestone-completions = { $name }: { $threshold ->
+    [one] 1 Mal erledigt
+   *[other] { $threshold } Mal erledigt
+}
+milestone-streak = { $name }: eine Serie von { $threshold } Tagen
+milestone-total = { $name }: { $threshold } insgesamt
+reward-title = 🎁 Belohnung eingelöst
+reward-body = Viel Spaß mit { $name } (−{ $cost ->
+    [one] 1 Punkt
+   *[other] { $cost } Punkte
+})
+
+## Pomodoro
+
+pomodoro-done-title = Pomodoro fertig
+pomodoro-done-body = Zeit für eine Pause
+break-over-title = Pause vorbei
+break-over-body = Zurück an die Arbeit
+focus-done-title = Fokus vorbei
+focus-done-body = { $minutes } Min. für { $habit }
+focus-habit-hidden = deine Gewohnheit
+focus-held = { $count ->
+    [one] 1 Benachrichtigung wurde zurückgehalten
+   *[other] { $count } Benachrichtigungen wurden zurückgehalten
+}
+
+## NFC tags
+
+nfc-locked = Entsperre HabitFlow, um mit einem Tag einzuchecken
+nfc-linked = Tag mit { $name } verknüpft
+nfc-linked-hidden = Tag mit der Gewohnheit verknüpft
+nfc-unknown = Dieser Tag ist noch mit keiner Gewohnheit verknüpft
+nfc-hidden-name = Gewohnheit
+nfc-already-done = { $name } ist heute schon erledigt
+nfc-done = ✓ { $name } erledigt
+nfc-progress = { $name }: { $done } von { $goal } heute
+nfc-failed = Einchecken mit diesem Tag fehlgeschlagen
+
+## Exports, snapshots, updates and crashes
+
+snapshot-manual = Manueller Snapshot { $date } { $time }
+export-failed-title = Export fehlgeschlagen
+backup-drive-title = Backup kopiert
+backup-drive-copied = Backup auf { $drive } kopiert
+backup-drive-failed-title = Backup konnte nicht kopiert werden
+screen-time-lapse = { $minutes } Min. { $category }
+block-title = Gerade gesperrt
+block-warning = { $app } gehört zu { $category } und ist bis { $until } tabu.
+block-closed = { $app } wurde geschlossen: { $category } ist bis { $until } gesperrt.
+block-until-tomorrow = morgen
+block-override = Sperre aufgehoben
+block-override-note = Sperre aufgehoben: { $reason }
+update-available = HabitFlow { $version } ist verfügbar
+crash-title = Absturzbericht senden?
+crash-body = { $count ->
+    [one] Bei HabitFlow ist ein Problem aufgetreten.
+   *[other] Bei HabitFlow ist { $count } Mal ein Problem aufgetreten.
+} Einen Bericht senden, damit es behoben werden kann? Berichte sagen, was schiefging und wo im Code, und enthalten keine deiner Gewohnheiten.
+crash-send = Senden
+crash-dont-send = Nicht senden
+
+card-current-streak = Aktuelle Serie
+card-longest-streak = Längste Serie
+card-rate = Letzte 30 Tage
+card-days = { $count ->
+    [one] 1 Tag
+   *[other] { $count } Tage
+}
+card-habit-hidden = Eine Gewohnheit
@@ -0,0 +1,128 @@
+# Strings the backend shows itself (src/i18n.rs). Every other catalog follows this one;
+# a message missing there falls back to the English here.
+
+## Counts
+
+habit-count = { $count ->
+    [one] 1 habit
+   *[other] { $count } habits
+}
+habits-due = { $count ->
+    [one] 1 habit due
+   *[other] { $count } habits due
+}
+
+## Tray
+
+tray-open = Open
+tray-add-habit = Add Habit
+tray-quit = Quit
+tray-all-done = All done today
+tray-tooltip-level = HabitFlow · Level { $level } ({ $xp }/{ $next } XP)
+tray-focus = Focus
+tray-stop-focus = Stop focus
+tray-next-minutes = Next: { $habit } in { $minutes } min
+tray-next-hours = Next: { $habit } in { $hours } h { $minutes } min
+tray-next-tomorrow = Next: { $habit } tomorrow at { $time }
+tray-next-later = Next: { $habit } on { $date } at { $time }
+tray-next-hidden = a habit
+
+## Reminders
+
+reminder-title = Habit reminder
+missed-title = Missed habits today
+missed-hidden = { $count ->
+    [one] 1 habit still to do today
+   *[other] { $count } habits still to do today
+}
+missed-more = { $names } (+{ $count } more)
+up-next-title = Up next
+up-next-hidden = { $count ->
+    [one] 1 habit ready to do
+   *[other] { $count } habits ready to do
+}
+welcome-back-title = Welcome back
+welcome-back-habit = { $name } is back on from today
+welcome-back-hidden = { $count ->
+    [one] 1 habit back on from today
+   *[other] { $count } habits back on from today
+}
+
+## Progress
+
+achievement-title = Achievement unlocked
+challenge-title = Challenge complete
+challenge-hidden = Today's challenge (+{ $xp } XP)
+level-up-title = ⬆️ Level up!
+level-up-body = You reached level { $level }
+milestone-title = 🎉 Milestone reached
+milestone-hidden = A habit reached a milestone
+milestone-completions = { $name }: { $threshold ->
+    [one] 1 completion
+   *[other] { $threshold } completions
+}
+milestone-streak = { $name }: a { $threshold }-day streak
+milestone-total = { $name }: { $threshold } in total
+reward-title = 🎁 Reward redeemed
+reward-body = Enjoy { $name } (−{ $cost ->
+    [one] 1 point
+   *[other] { $cost } points
+})
+
+## Pomodoro
+
+pomodoro-done-title = Pomodoro done
+pomodoro-done-body = Time for a break
+break-over-title = Break over
+break-over-body = Back to work
+focus-done-title = Focus done
+focus-done-body = { $minutes } min on { $habit }
+focus-habit-hidden = your habit
+focus-held = { $count ->
+    [one] 1 notification was held back
+   *[other] { $count } notifications were held back
+}
+
+## NFC tags
+
+nfc-locked = Unlock HabitFlow to check in with a tag
+nfc-linked = Tag linked to { $name }
+nfc-linked-hidden = Tag linked to the habit
+nfc-unknown = This tag isn't linked to a habit yet
+nfc-hidden-name = Habit
+nfc-already-done = { $name } is already done today
+nfc-done = ✓ { $name } done
+nfc-progress = { $name }: { $done } of { $goal } today
+nfc-failed = Couldn't check in with that tag
+
+## Exports, snapshots, updates and crashes
+
+snapshot-manual = Manual snapshot { $date } { $time }
+export-failed-title = Export failed
+backup-drive-title = Backup copied
+backup-drive-copied = Backup copied to { $drive }
+backup-drive-failed-title = Couldn't copy the backup
+screen-time-lapse = { $minutes } min of { $category }
+block-title = Blocked for now
+block-warning = { $app } is { $category }, which is off limits until { $until }.
+block-closed = Closed { $app }: { $category } is blocked until { $until }.
+block-until-tomorrow = tomorrow
+block-override = Overrode a block
+block-override-note = Overrode a block: { $reason }
+update-available = HabitFlow { $version } is available
+crash-title = Send a crash report?
+crash-body = { $count ->
+    [one] HabitFlow ran into a problem.
+   *[other] HabitFlow ran into a problem { $count } times.
+} Send a report so it can be fixed? Reports say what went wrong and where in the code, and have none of your habits in them.
+crash-send = Send
+crash-dont-send = Don't Send
+
+card-current-streak = Current streak
+card-longest-streak = Longest streak
+card-rate = Last 30 days
+card-days = { $count ->
+    [one] 1 day
+   *[other] { $count } days
+}
+card-habit-hidden = A habit
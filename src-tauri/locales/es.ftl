@@ -0,0 +1,127 @@
+# Spanish. Keep the ids and placeables as in en.ftl.
+
+## Counts
+
+habit-count = { $count ->
+    [one] 1 hábito
+   *[other] { $count } hábitos
+}
+habits-due = { $count ->
+    [one] 1 hábito pendiente
+   *[other] { $count } hábitos pendientes
+}
+
+## Tray
+
+tray-open = Abrir
+tray-add-habit = Añadir hábito
+tray-quit = Salir
+tray-all-done = Todo hecho hoy
+tray-tooltip-level = HabitFlow · Nivel { $level } ({ $xp }/{ $next } XP)
+tray-focus = Concentración
+tray-stop-focus = Terminar concentración
+tray-next-minutes = Siguiente: { $habit } en { $minutes } min
+tray-next-hours = Siguiente: { $habit } en { $hours } h { $minutes } min
+tray-next-tomorrow = Siguiente: { $habit } mañana a las { $time }
+tray-next-later = Siguiente: { $habit } el { $date } a las { $time }
+tray-next-hidden = un hábito
+
+## Reminders
+
+reminder-title = Recordatorio de hábito
+missed-title = Hábitos pendientes hoy
+missed-hidden = { $count ->
+    [one] Queda 1 hábito por hacer hoy
+   *[other] Quedan { $count } hábitos por hacer hoy
+}
+missed-more = { $names } (y { $count } más)
+up-next-title = A continuación
+up-next-hidden = { $count ->
+    [one] 1 hábito listo para hacer
+   *[other] { $count } hábitos listos para hacer
+}
+welcome-back-title = Bienvenido de nuevo
+welcome-back-habit = { $name } vuelve a estar activo desde hoy
+welcome-back-hidden = { $count ->
+    [one] 1 hábito vuelve a estar activo desde hoy
+   *[other] { $count } hábitos vuelven a estar activos desde hoy
+}
+
+## Progress
+
+achievement-title = Logro desbloqueado
+challenge-title = Reto completado
+challenge-hidden = Reto de hoy (+{ $xp } XP)
+level-up-title = ⬆️ ¡Subiste de nivel!
+level-up-body = Has llegado al nivel { $level }
+milestone-title = 🎉 Hito alcanzado
+milestone-hidden = Un hábito ha alcanzado un hito
+milestone-completions = { $name }: { $threshold ->
+    [one] 1 vez completado
+   *[other] { $threshold } veces completado
+}
+milestone-streak = { $name }: una racha de { $threshold } días
+milestone-total = { $name }: { $threshold } en total
+reward-title = 🎁 Recompensa canjeada
+reward-body = Disfruta de { $name } (−{ $cost ->
+    [one] 1 punto
+   *[other] { $cost } puntos
+})
+
+## Pomodoro
+
+pomodoro-done-title = Pomodoro terminado
+pomodoro-done-body = Hora de un descanso
+break-over-title = Fin del descanso
+break-over-body = De vuelta al trabajo
+focus-done-title = Concentración terminada
+focus-done-body = { $minutes } min en { $habit }
+focus-habit-hidden = tu hábito
+focus-held = { $count ->
+    [one] Se retuvo 1 notificación
+   *[other] Se retuvieron { $count } notificaciones
+}
+
+## NFC tags
+
+nfc-locked = Desbloquea HabitFlow para registrar con una etiqueta
+nfc-linked = Etiqueta vinculada a { $name }
+nfc-linked-hidden = Etiqueta vinculada al hábito
+nfc-unknown = Esta etiqueta aún no está vinculada a ningún hábito
+nfc-hidden-name = Hábito
+nfc-already-done = { $name } ya está hecho hoy
+nfc-done = ✓ { $name } hecho
+nfc-progress = { $name }: { $done } de { $goal } hoy
+nfc-failed = No se pudo registrar con esa etiqueta
+
+## Exports, snapshots, updates and crashes
+
+snapshot-manual = Copia manual { $date } { $time }
+export-failed-title = Error al exportar
+backup-drive-title = Copia de seguridad copiada
+backup-drive-copied = Copia de seguridad copiada en { $drive }
+backup-drive-failed-title = No se pudo copiar la copia de seguridad
+screen-time-lapse = { $minutes } min de { $category }
+block-title = Bloqueado por ahora
+block-warning = { $app } es { $category }, que está vetado hasta { $until }.
+block-closed = Se cerró { $app }: { $category } está bloqueado hasta { $until }.
+block-until-tomorrow = mañana
+block-override = Se saltó un bloqueo
+block-override-note = Se saltó un bloqueo: { $reason }
+update-available = HabitFlow { $version } está disponible
+crash-title = ¿Enviar un informe de error?
+crash-body = { $count ->
+    [one] HabitFlow ha tenido un problema.
+   *[other] HabitFlow ha tenido un problema { $count } veces.
+} ¿Enviar un informe para que se pueda corregir? Los informes dicen qué falló y en qué parte del código, y no incluyen ninguno de tus hábitos.
+crash-send = Enviar
+crash-dont-send = No enviar
+
+card-current-streak = Racha actual
+card-longest-streak = Racha más larga
+card-rate = Últimos 30 días
+card-days = { $count ->
+    [one] 1 día
+   *[other] { $count } días
+}
+card-habit-hidden = Un hábito
@@ -0,0 +1,124 @@
+# French. Keep the ids and placeables as in en.ftl. 0 and 1 are both [one] here.
+
+## Counts
+
+habit-count = { $count ->
+    [one] { $count } habitude
+   *[other] { $count } habitudes
+}
+habits-due = { $count ->
+    [one] { $count } habitude à faire
+   *[other] { $count } habitudes à faire
+}
+
+## Tray
+
+tray-open = Ouvrir
+tray-add-habit = Ajouter une habitude
+tray-quit = Quitter
+tray-all-done = Tout est fait aujourd'hui
+tray-tooltip-level = HabitFlow · Niveau { $level } ({ $xp }/{ $next } XP)
+tray-focus = Concentration
+tray-stop-focus = Arrêter la concentration
+tray-next-minutes = Ensuite : { $habit } dans { $minutes } min
+tray-next-hours = Ensuite : { $habit } dans { $hours } h { $minutes } min
+tray-next-tomorrow = Ensuite : { $habit } demain à { $time }
+tray-next-later = Ensuite : { $habit } le { $date } à { $time }
+tray-next-hidden = une habitude
+
+## Reminders
+
+reminder-title = Rappel d'habitude
+missed-title = Habitudes manquées aujourd'hui
+missed-hidden = { $count ->
+    [one] { $count } habitude encore à faire aujourd'hui
+   *[other] { $count } habitudes encore à faire aujourd'hui
+}
+missed-more = { $names } (+{ $count } autres)
+up-next-title = À suivre
+up-next-hidden = { $count ->
+    [one] { $count } habitude prête
+   *[other] { $count } habitudes prêtes
+}
+welcome-back-title = Bon retour
+welcome-back-habit = { $name } reprend à partir d'aujourd'hui
+welcome-back-hidden = { $count ->
+    [one] { $count } habitude reprend à partir d'aujourd'hui
+   *[other] { $count } habitudes reprennent à partir d'aujourd'hui
+}
+
+## Progress
+
+achievement-title = Succès débloqué
+challenge-title = Défi réussi
+challenge-hidden = Défi du jour (+{ $xp } XP)
+level-up-title = ⬆️ Niveau supérieur !
+level-up-body = Tu as atteint le niveau { $level }
+milestone-title = 🎉 Étape franchie
+milestone-hidden = Une habitude a franchi une étape
+milestone-completions = { $name } : { $threshold } fois
+milestone-streak = { $name } : une série de { $threshold } jours
+milestone-total = { $name } : { $threshold } au total
+reward-title = 🎁 Récompense utilisée
+reward-body = Profite de { $name } (−{ $cost ->
+    [one] { $cost } point
+   *[other] { $cost } points
+})
+
+## Pomodoro
+
+pomodoro-done-title = Pomodoro terminé
+pomodoro-done-body = C'est l'heure de la pause
+break-over-title = Fin de la pause
+break-over-body = Au travail
+focus-done-title = Concentration terminée
+focus-done-body = { $minutes } min sur { $habit }
+focus-habit-hidden = ton habitude
+focus-held = { $count ->
+    [one] 1 notification a été retenue
+   *[other] { $count } notifications ont été retenues
+}
+
+## NFC tags
+
+nfc-locked = Déverrouille HabitFlow pour pointer avec un tag
+nfc-linked = Tag associé à { $name }
+nfc-linked-hidden = Tag associé à l'habitude
+nfc-unknown = Ce tag n'est encore associé à aucune habitude
+nfc-hidden-name = Habitude
+nfc-already-done = { $name } est déjà fait aujourd'hui
+nfc-done = ✓ { $name } fait
+nfc-progress = { $name } : { $done } sur { $goal } aujourd'hui
+nfc-failed = Impossible de pointer avec ce tag
+
+## Exports, snapshots, updates and crashes
+
+snapshot-manual = Instantané manuel du { $date } à { $time }
+export-failed-title = Échec de l'export
+backup-drive-title = Sauvegarde copiée
+backup-drive-copied = Sauvegarde copiée sur { $drive }
+backup-drive-failed-title = Impossible de copier la sauvegarde
+screen-time-lapse = { $minutes } min de { $category }
+block-title = Bloqué pour l'instant
+block-warning = { $app } fait partie de { $category }, interdit jusqu'à { $until }.
+block-closed = { $app } a été fermé : { $category } est bloqué jusqu'à { $until }.
+block-until-tomorrow = demain
+block-override = Blocage levé
+block-override-note = Blocage levé : { $reason }
+update-available = HabitFlow { $version } est disponible
+crash-title = Envoyer un rapport de plantage ?
+crash-body = { $count ->
+    [one] HabitFlow a rencontré un problème.
+   *[other] HabitFlow a rencontré un problème { $count } fois.
+} Envoyer un rapport pour qu'il soit corrigé ? Les rapports indiquent ce qui s'est mal passé et où dans le code, et ne contiennent aucune de tes habitudes.
+crash-send = Envoyer
+crash-dont-send = Ne pas envoyer
+
+card-current-streak = Série en cours
+card-longest-streak = Meilleure série
+card-rate = 30 derniers jours
+card-days = { $count ->
+    [one] 1 jour
+   *[other] { $count } jours
+}
+card-habit-hidden = Une habitude
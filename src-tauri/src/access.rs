@@ -0,0 +1,63 @@
+//! Which commands each webview window may call, enforced in the invoke handler (see
+//! `guarded`) so a small window can't reach more than it needs even if its page is
+//! compromised. The main window gets everything; the quick-add popover and the widget
+//! get short allowlists; a window with any other label gets nothing. Plugin commands are
+//! governed by the per-window files in `capabilities/` instead.
+
+use tauri::ipc::Invoke;
+use tauri::Runtime;
+
+use crate::error::AppError;
+
+pub const MAIN_WINDOW: &str = "main";
+pub const QUICK_ADD_WINDOW: &str = "quick-add";
+pub const WIDGET_WINDOW: &str = "widget";
+
+/// Parses what was typed; the main window creates the habit.
+const QUICK_ADD_COMMANDS: &[&str] = &["parse_quick_add", "list_categories", "get_lock_status"];
+
+/// Reads today's habits and logs completions.
+const WIDGET_COMMANDS: &[&str] = &[
+    "list_due_habits",
+    "log_habit_value",
+    "log_repetition",
+    "undo_repetition",
+    "get_lock_status",
+    "get_today_snapshot",
+    "toggle_tile_habit",
+    "get_window_effects",
+    "set_window_effect",
+    "set_window_opacity",
+];
+
+/// The commands `window` may call; `None` means all of them.
+fn allowlist(window: &str) -> Option<&'static [&'static str]> {
+    match window {
+        MAIN_WINDOW => None,
+        QUICK_ADD_WINDOW => Some(QUICK_ADD_COMMANDS),
+        WIDGET_WINDOW => Some(WIDGET_COMMANDS),
+        _ => Some(&[]),
+    }
+}
+
+pub fn allows(window: &str, command: &str) -> bool {
+    allowlist(window).map_or(true, |commands| commands.contains(&command))
+}
+
+/// Wraps the invoke handler so windows can only call the commands they're allowed.
+pub fn guarded<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let command = invoke.message.command();
+        let window = invoke.message.webview_ref().label().to_string();
+        if !allows(&window, command) {
+            log::warn!("access: the {window} window tried to call {command}");
+            invoke.resolver.reject(AppError::Forbidden(format!(
+                "the {window} window can't call {command}"
+            )));
+            return true;
+        }
+        handler(invoke)
+    }
+}
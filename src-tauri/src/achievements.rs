@@ -0,0 +1,288 @@
+//! Achievements: a fixed catalog of badges ("first 7-day streak", "1000 completions",
+//! "perfect month"), checked after every change and at the day rollover. Unlocks are
+//! stored with their time and never taken back, even if the history that earned them is
+//! edited later. Each unlock publishes `achievementUnlocked`, shows a notification and
+//! emits `achievement:unlocked` to the webview.
+
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::events::{EventBus, HabitEvent};
+use crate::model::Habit;
+use crate::stats::{parse_date, today};
+use crate::storage::Storage;
+
+pub const ACHIEVEMENT_UNLOCKED_EVENT: &str = "achievement:unlocked";
+/// Set once the catalog has been checked; the first check unlocks quietly so existing
+/// history doesn't bring a burst of notifications.
+const EVALUATED_KEY: &str = "achievements.evaluated";
+/// How far back perfect days are looked for.
+const MAX_HISTORY_DAYS: u64 = 3 * 366;
+
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub emoji: &'static str,
+}
+
+const fn achievement(
+    id: &'static str,
+    name: &'static str,
+    description: &'static str,
+    emoji: &'static str,
+) -> Achievement {
+    Achievement {
+        id,
+        name,
+        description,
+        emoji,
+    }
+}
+
+pub const CATALOG: [Achievement; 11] = [
+    achievement(
+        "first-completion",
+        "First step",
+        "Complete a habit for the first time",
+        "👣",
+    ),
+    achievement("streak-7", "One week strong", "Reach a 7-day streak", "🔥"),
+    achievement(
+        "streak-30",
+        "Monthly momentum",
+        "Reach a 30-day streak",
+        "🚀",
+    ),
+    achievement("streak-100", "Centurion", "Reach a 100-day streak", "💯"),
+    achievement(
+        "completions-100",
+        "Hundred club",
+        "100 completions in total",
+        "🥉",
+    ),
+    achievement(
+        "completions-500",
+        "Dedicated",
+        "500 completions in total",
+        "🥈",
+    ),
+    achievement(
+        "completions-1000",
+        "Thousand strong",
+        "1000 completions in total",
+        "🥇",
+    ),
+    achievement(
+        "perfect-day",
+        "Perfect day",
+        "Finish every daily habit in a day",
+        "⭐",
+    ),
+    achievement(
+        "perfect-week",
+        "Perfect week",
+        "Seven perfect days in a row",
+        "🌟",
+    ),
+    achievement(
+        "perfect-month",
+        "Perfect month",
+        "Every day of a calendar month perfect",
+        "🏆",
+    ),
+    achievement("five-habits", "Collector", "Keep five active habits", "🧺"),
+];
+
+/// Perfect days (see `freezes::is_perfect`) up to yesterday, oldest first. Today is left
+/// out until it's over.
+fn perfect_days(habits: &[Habit], today: NaiveDate) -> Vec<NaiveDate> {
+    let Some(first) = habits
+        .iter()
+        .filter_map(|h| parse_date(h.start_date()))
+        .min()
+    else {
+        return Vec::new();
+    };
+    let first = first.max(today - chrono::Days::new(MAX_HISTORY_DAYS));
+    first
+        .iter_days()
+        .take_while(|d| *d < today)
+        .filter(|d| crate::freezes::is_perfect(habits, *d))
+        .collect()
+}
+
+/// A run of `len` consecutive days in `days` (sorted).
+fn has_run(days: &[NaiveDate], len: usize) -> bool {
+    let mut run = 0;
+    let mut prev: Option<NaiveDate> = None;
+    for day in days {
+        run = match prev {
+            Some(p) if p.succ_opt() == Some(*day) => run + 1,
+            _ => 1,
+        };
+        if run >= len {
+            return true;
+        }
+        prev = Some(*day);
+    }
+    false
+}
+
+/// A whole calendar month of perfect days.
+fn has_perfect_month(days: &[NaiveDate]) -> bool {
+    let mut run_start: Option<NaiveDate> = None;
+    let mut prev: Option<NaiveDate> = None;
+    for day in days {
+        if prev.and_then(|p| p.succ_opt()) != Some(*day) {
+            run_start = Some(*day);
+        }
+        prev = Some(*day);
+        let month_done = day
+            .succ_opt()
+            .is_some_and(|next| next.month() != day.month());
+        if month_done && run_start.is_some_and(|start| start <= day.with_day(1).unwrap_or(*day)) {
+            return true;
+        }
+    }
+    false
+}
+
+/// Ids of every achievement `habits` have earned.
+pub fn earned(storage: &Storage, habits: &[Habit], today: NaiveDate) -> Vec<&'static str> {
+    let completions: usize = habits.iter().map(|h| h.done_dates().len()).sum();
+    let best_streak = habits
+        .iter()
+        .map(|h| crate::streaks::longest(storage, h, today))
+        .max()
+        .unwrap_or(0);
+    let perfect = perfect_days(habits, today);
+    let active = habits.iter().filter(|h| !h.archived).count();
+    CATALOG
+        .iter()
+        .filter(|a| match a.id {
+            "first-completion" => completions > 0,
+            "streak-7" => best_streak >= 7,
+            "streak-30" => best_streak >= 30,
+            "streak-100" => best_streak >= 100,
+            "completions-100" => completions >= 100,
+            "completions-500" => completions >= 500,
+            "completions-1000" => completions >= 1000,
+            "perfect-day" => !perfect.is_empty(),
+            "perfect-week" => has_run(&perfect, 7),
+            "perfect-month" => has_perfect_month(&perfect),
+            "five-habits" => active >= 5,
+            _ => false,
+        })
+        .map(|a| a.id)
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AchievementStatus {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    pub emoji: &'static str,
+    /// RFC 3339, once unlocked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unlocked_at: Option<String>,
+}
+
+pub fn list(storage: &Storage) -> Result<Vec<AchievementStatus>, String> {
+    let unlocked = storage
+        .list_unlocked_achievements()
+        .map_err(|e| e.to_string())?;
+    Ok(CATALOG
+        .iter()
+        .map(|a| AchievementStatus {
+            id: a.id,
+            name: a.name,
+            description: a.description,
+            emoji: a.emoji,
+            unlocked_at: unlocked.get(a.id).cloned(),
+        })
+        .collect())
+}
+
+/// Stores newly earned achievements and returns them, with whether to announce them.
+pub fn evaluate(
+    storage: &Storage,
+    today: NaiveDate,
+) -> Result<(Vec<AchievementStatus>, bool), String> {
+    let habits = storage.list_habits().map_err(|e| e.to_string())?;
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut unlocked = Vec::new();
+    for id in earned(storage, &habits, today) {
+        if storage
+            .unlock_achievement(id, &now)
+            .map_err(|e| e.to_string())?
+        {
+            unlocked.push(id);
+        }
+    }
+    let announce = storage
+        .get_meta(EVALUATED_KEY)
+        .map_err(|e| e.to_string())?
+        .is_some();
+    if !announce {
+        storage
+            .set_meta(EVALUATED_KEY, &now)
+            .map_err(|e| e.to_string())?;
+    }
+    let statuses = list(storage)?
+        .into_iter()
+        .filter(|a| unlocked.contains(&a.id))
+        .collect();
+    Ok((statuses, announce))
+}
+
+fn check(app: &AppHandle) {
+    let (unlocked, announce) = match evaluate(&app.state::<Storage>(), today()) {
+        Ok(r) => r,
+        Err(e) => {
+            log::warn!("achievements: check failed: {e}");
+            return;
+        }
+    };
+    if !announce {
+        return;
+    }
+    let bus = app.state::<EventBus>();
+    for achievement in unlocked {
+        bus.publish(HabitEvent::AchievementUnlocked {
+            achievement_id: achievement.id.to_string(),
+            name: achievement.name.to_string(),
+        });
+        crate::reminders::notify(
+            app,
+            &crate::i18n::t("achievement-title"),
+            &format!(
+                "{} {}: {}",
+                achievement.emoji, achievement.name, achievement.description
+            ),
+        );
+        let _ = app.emit(ACHIEVEMENT_UNLOCKED_EVENT, &achievement);
+    }
+}
+
+/// Checks on startup and after every data change, which includes the day rollover.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    let mut data = app.state::<EventBus>().watch_data();
+    tauri::async_runtime::spawn(async move {
+        check(&app);
+        while data.changed().await.is_ok() {
+            check(&app);
+        }
+    });
+}
+
+/// The whole catalog, with `unlockedAt` on the ones earned.
+#[tauri::command]
+pub fn list_achievements(storage: State<'_, Storage>) -> Result<Vec<AchievementStatus>, AppError> {
+    Ok(list(&storage)?)
+}
@@ -0,0 +1,312 @@
+//! Optional localhost REST API for scripts and launcher extensions.
+//!
+//! Off by default. Binds to 127.0.0.1 only, and every route requires
+//! `Authorization: Bearer <token>` using the token from `settings.api`.
+//! Browser-based clients that can't set headers (WebSocket, OBS browser sources)
+//! may pass `?token=<token>` instead.
+//!
+//! While the app lock is on, every route answers `423 Locked`.
+//!
+//! `GET /api/events` upgrades to a WebSocket that streams `HabitEvent`s as JSON text frames.
+//! The socket is closed when the app locks.
+
+use std::sync::Mutex;
+
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use tauri::{AppHandle, Manager};
+use tokio::sync::{broadcast, oneshot, watch};
+
+use crate::error::AppError;
+use crate::events::{EventBus, HabitEvent};
+use crate::features::{self, Feature};
+use crate::model::Habit;
+use crate::settings::SettingsStore;
+use crate::stats::{self, date_key, parse_date, today};
+use crate::storage::Storage;
+
+/// Handle to the running server, if any.
+#[derive(Default)]
+pub struct ApiServer {
+    shutdown: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+/// Starts, stops or restarts the server to match the current settings.
+pub fn apply(app: &AppHandle) {
+    let server = app.state::<ApiServer>();
+    let mut shutdown = server.shutdown.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(tx) = shutdown.take() {
+        let _ = tx.send(());
+    }
+
+    let settings = app.state::<SettingsStore>().get().api;
+    if !settings.enabled || !features::enabled(Feature::LocalApi) {
+        return;
+    }
+
+    let (tx, rx) = oneshot::channel();
+    *shutdown = Some(tx);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let listener = match tokio::net::TcpListener::bind(("127.0.0.1", settings.port)).await {
+            Ok(l) => l,
+            Err(e) => {
+                log::error!("local API: failed to bind port {}: {e}", settings.port);
+                return;
+            }
+        };
+        log::info!("local API listening on 127.0.0.1:{}", settings.port);
+
+        let result = axum::serve(listener, router(app))
+            .with_graceful_shutdown(async {
+                let _ = rx.await;
+            })
+            .await;
+        if let Err(e) = result {
+            log::error!("local API stopped: {e}");
+        }
+    });
+}
+
+fn router(app: AppHandle) -> Router {
+    Router::new()
+        .route("/api/habits", get(list_habits))
+        .route("/api/habits/{id}", get(get_habit))
+        .route("/api/habits/{id}/completions", post(add_completion))
+        .route(
+            "/api/habits/{id}/completions/{date}",
+            delete(remove_completion),
+        )
+        .route("/api/habits/{id}/lapses", post(add_lapse))
+        .route("/api/stats", get(get_stats))
+        .route("/api/events", get(events))
+        .layer(middleware::from_fn_with_state(app.clone(), require_token))
+        .with_state(app)
+}
+
+async fn require_token(State(app): State<AppHandle>, req: Request, next: Next) -> Response {
+    if app.state::<crate::lock::AppLock>().is_locked() {
+        return (StatusCode::LOCKED, "HabitFlow is locked").into_response();
+    }
+    let expected = app.state::<SettingsStore>().get().api.token;
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .or_else(|| {
+            req.uri()
+                .query()?
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("token="))
+        });
+
+    match provided {
+        Some(token)
+            if !expected.is_empty() && constant_time_eq(token.as_bytes(), expected.as_bytes()) =>
+        {
+            next.run(req).await
+        }
+        _ => (StatusCode::UNAUTHORIZED, "missing or invalid token").into_response(),
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+type ApiResult<T> = Result<Json<T>, (StatusCode, String)>;
+
+fn internal(e: impl ToString) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+/// The status that goes with an error from the layer below.
+fn failed(e: AppError) -> (StatusCode, String) {
+    let status = match e {
+        AppError::Invalid(_) => StatusCode::BAD_REQUEST,
+        AppError::NotFound { .. } => StatusCode::NOT_FOUND,
+        AppError::Conflict { .. } => StatusCode::CONFLICT,
+        AppError::Locked => StatusCode::LOCKED,
+        AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, e.to_string())
+}
+
+fn not_found() -> (StatusCode, String) {
+    (StatusCode::NOT_FOUND, "habit not found".into())
+}
+
+async fn list_habits(State(app): State<AppHandle>) -> ApiResult<Vec<Habit>> {
+    app.state::<Storage>()
+        .list_habits()
+        .map(Json)
+        .map_err(internal)
+}
+
+async fn get_habit(State(app): State<AppHandle>, Path(id): Path<String>) -> ApiResult<Habit> {
+    app.state::<Storage>()
+        .get_habit(&id)
+        .map_err(internal)?
+        .map(Json)
+        .ok_or_else(not_found)
+}
+
+#[derive(Deserialize, Default)]
+struct CompletionBody {
+    /// `yyyy-MM-dd`; defaults to today.
+    date: Option<String>,
+    /// Quantified habits: amount to add instead of checking the day off outright.
+    value: Option<f64>,
+    /// Labels for the check-in created by `value`.
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Journal note for the day.
+    note: Option<String>,
+}
+
+async fn add_completion(
+    State(app): State<AppHandle>,
+    Path(id): Path<String>,
+    body: Option<Json<CompletionBody>>,
+) -> ApiResult<Habit> {
+    let Json(body) = body.unwrap_or_default();
+    let date = match body.date {
+        Some(d) => parse_date(&d).ok_or((StatusCode::BAD_REQUEST, "invalid date".into()))?,
+        None => today(),
+    };
+    let habit = match body.value {
+        Some(value) => {
+            crate::habits::log_value(&app, &id, date, value, body.tags).map_err(failed)?
+        }
+        None => crate::habits::set_completion(&app, &id, date, true).map_err(failed)?,
+    };
+    // A value that didn't reach the target yet leaves nothing to attach the note to.
+    let key = date_key(date);
+    let habit = match (habit, body.note) {
+        (Some(h), Some(note)) if h.is_done_on(&key) => {
+            let updated = app
+                .state::<Storage>()
+                .transaction(|tx| crate::notes::update(tx, &id, &key, Some(&note)))
+                .map_err(failed)?;
+            app.state::<EventBus>().data_changed();
+            crate::habits::notify_changed(&app);
+            updated
+        }
+        (habit, _) => habit,
+    };
+    habit.map(Json).ok_or_else(not_found)
+}
+
+async fn remove_completion(
+    State(app): State<AppHandle>,
+    Path((id, date)): Path<(String, String)>,
+) -> ApiResult<Habit> {
+    let date = parse_date(&date).ok_or((StatusCode::BAD_REQUEST, "invalid date".into()))?;
+    crate::habits::set_completion(&app, &id, date, false)
+        .map_err(failed)?
+        .map(Json)
+        .ok_or_else(not_found)
+}
+
+#[derive(Deserialize, Default)]
+struct LapseBody {
+    /// `yyyy-MM-dd`; defaults to today.
+    date: Option<String>,
+    note: Option<String>,
+}
+
+async fn add_lapse(
+    State(app): State<AppHandle>,
+    Path(id): Path<String>,
+    body: Option<Json<LapseBody>>,
+) -> ApiResult<Habit> {
+    let Json(body) = body.unwrap_or_default();
+    let date = match body.date {
+        Some(d) => parse_date(&d).ok_or((StatusCode::BAD_REQUEST, "invalid date".into()))?,
+        None => today(),
+    };
+    crate::habits::log_lapse(&app, &id, date, body.note)
+        .map_err(failed)?
+        .map(Json)
+        .ok_or_else(not_found)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct StatsQuery {
+    /// Only habits with this tag.
+    tag: Option<String>,
+    /// Leave out days checked off after the fact.
+    #[serde(default)]
+    exclude_backfilled: bool,
+}
+
+async fn get_stats(
+    State(app): State<AppHandle>,
+    Query(query): Query<StatsQuery>,
+) -> ApiResult<stats::StatsSummary> {
+    let storage = app.state::<Storage>();
+    let mut habits = storage.list_habits().map_err(internal)?;
+    if let Some(tag) = query.tag {
+        habits = crate::tags::with_tag(habits, &tag);
+    }
+    if query.exclude_backfilled {
+        habits = crate::backfill::without_backfilled(habits);
+    }
+    let categories = storage.list_categories().map_err(internal)?;
+    Ok(Json(stats::summarize(&habits, &categories, today())))
+}
+
+async fn events(State(app): State<AppHandle>, ws: WebSocketUpgrade) -> Response {
+    let rx = app.state::<EventBus>().subscribe();
+    let locked = app.state::<crate::lock::AppLock>().subscribe();
+    ws.on_upgrade(move |socket| stream_events(socket, rx, locked))
+}
+
+async fn stream_events(
+    mut socket: WebSocket,
+    mut rx: broadcast::Receiver<HabitEvent>,
+    mut locked: watch::Receiver<bool>,
+) {
+    loop {
+        // Checked every time round, which also catches it locking before the upgrade.
+        if *locked.borrow_and_update() {
+            let close = CloseFrame {
+                code: close_code::POLICY,
+                reason: "HabitFlow is locked".into(),
+            };
+            let _ = socket.send(Message::Close(Some(close))).await;
+            break;
+        }
+        tokio::select! {
+            // Loops back round to the check above.
+            Ok(()) = locked.changed() => {}
+            event = rx.recv() => match event {
+                Ok(event) => {
+                    let Ok(text) = serde_json::to_string(&event) else { continue };
+                    if socket.send(Message::Text(text.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("local API: websocket client lagged, skipped {skipped} events");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            // Clients don't send anything meaningful; this just notices disconnects.
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! How a habit looks: its color, icon, emoji and cover image, kept in the backend so the
+//! tray menu, notifications and exports show the same thing as the app.
+//!
+//! The app draws icons by name (`sparkles`, `dumbbell`, ...); places that can only show
+//! text fall back to the emoji that the icon stands for. Cover images are copied into the
+//! `covers` folder of the app data dir, named after the habit.
+
+use std::path::{Path, PathBuf};
+
+use base64::Engine;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::Habit;
+use crate::storage::Storage;
+
+/// Largest cover image accepted, in bytes.
+pub const MAX_COVER_BYTES: u64 = 5 * 1024 * 1024;
+
+pub const COVERS_DIR: &str = "covers";
+
+/// The emoji for each of the app's icon names.
+const ICON_EMOJI: &[(&str, &str)] = &[
+    ("activity", "🏃"),
+    ("book", "📖"),
+    ("briefcase", "💼"),
+    ("coffee", "☕"),
+    ("code", "💻"),
+    ("dumbbell", "🏋️"),
+    ("water", "💧"),
+    ("game", "🎮"),
+    ("heart", "❤️"),
+    ("leaf", "🌿"),
+    ("moon", "🌙"),
+    ("music", "🎵"),
+    ("sun", "☀️"),
+    ("food", "🥗"),
+    ("zap", "⚡"),
+    ("smile", "😊"),
+    ("brain", "🧠"),
+    ("money", "💰"),
+    ("sleep", "😴"),
+    ("home", "🏠"),
+    ("star", "⭐"),
+    ("phone", "📱"),
+    ("tv", "📺"),
+    ("sparkles", "✨"),
+    ("pen", "✏️"),
+    ("monitor", "🖥️"),
+    ("cart", "🛒"),
+];
+
+/// The habit's own emoji, or the one its icon stands for.
+pub fn emoji(habit: &Habit) -> Option<&str> {
+    habit
+        .emoji
+        .as_deref()
+        .filter(|e| !e.is_empty())
+        .or_else(|| {
+            let icon = habit.icon.as_deref()?;
+            ICON_EMOJI
+                .iter()
+                .find(|(name, _)| *name == icon)
+                .map(|(_, emoji)| *emoji)
+        })
+}
+
+/// The name with its emoji in front, for text-only places.
+pub fn label(habit: &Habit) -> String {
+    match emoji(habit) {
+        Some(emoji) => format!("{emoji} {}", habit.name),
+        None => habit.name.clone(),
+    }
+}
+
+/// `#rgb` or `#rrggbb`.
+pub fn validate_color(color: &str) -> Result<(), AppError> {
+    let hex = color.strip_prefix('#').unwrap_or("");
+    if (hex.len() == 3 || hex.len() == 6) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(AppError::Invalid(format!(
+            "invalid color {color:?}, expected #rrggbb"
+        )))
+    }
+}
+
+/// Changes the given parts of a habit's look; an empty `icon` or `emoji` clears it.
+pub fn update(
+    storage: &Storage,
+    habit_id: &str,
+    color: Option<String>,
+    icon: Option<String>,
+    emoji: Option<String>,
+) -> Result<Habit, AppError> {
+    let Some(habit) = storage.get_habit(habit_id)? else {
+        return Err(AppError::not_found("habit", habit_id));
+    };
+    let color = color.unwrap_or(habit.color);
+    validate_color(&color)?;
+    let clear = |v: String| Some(v.trim().to_string()).filter(|v| !v.is_empty());
+    let icon = icon.map_or(habit.icon, clear);
+    let emoji = emoji.map_or(habit.emoji, clear);
+    if emoji.as_ref().is_some_and(|e| e.chars().count() > 8) {
+        return Err(AppError::Invalid("emoji must be a single emoji".into()));
+    }
+    storage.set_habit_appearance(habit_id, &color, icon.as_deref(), emoji.as_deref())?;
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+pub(crate) fn covers_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join(COVERS_DIR);
+    Ok(dir)
+}
+
+pub fn mime_type(extension: &str) -> Option<&'static str> {
+    match extension {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "webp" => Some("image/webp"),
+        "gif" => Some("image/gif"),
+        _ => None,
+    }
+}
+
+pub fn extension(path: &Path) -> String {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase()
+}
+
+/// Copies `source` into `data_dir`'s covers as the habit's cover, or with `None` removes it.
+pub fn set_cover(
+    storage: &Storage,
+    data_dir: &Path,
+    habit_id: &str,
+    source: Option<&Path>,
+) -> Result<Habit, AppError> {
+    let habit = storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+    let dir = data_dir.join(COVERS_DIR);
+    let cover = match source {
+        Some(source) => {
+            let ext = extension(source);
+            if mime_type(&ext).is_none() {
+                return Err(AppError::Invalid(
+                    "covers must be PNG, JPEG, WebP or GIF images".into(),
+                ));
+            }
+            let size = std::fs::metadata(source)?.len();
+            if size > MAX_COVER_BYTES {
+                return Err(AppError::Invalid(format!(
+                    "covers are limited to {} MB",
+                    MAX_COVER_BYTES / 1024 / 1024
+                )));
+            }
+            std::fs::create_dir_all(&dir)?;
+            let name = format!("{habit_id}.{ext}");
+            std::fs::copy(source, dir.join(&name))?;
+            Some(name)
+        }
+        None => None,
+    };
+    if let Some(old) = habit.cover.filter(|old| Some(old) != cover.as_ref()) {
+        let _ = std::fs::remove_file(dir.join(old));
+    }
+    storage.set_habit_cover(habit_id, cover.as_deref())?;
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+/// `habit_id`'s cover as a `data:` URL the webview can show directly.
+pub fn cover_data_url(
+    storage: &Storage,
+    data_dir: &Path,
+    habit_id: &str,
+) -> Result<Option<String>, AppError> {
+    let habit = storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+    let Some(name) = &habit.cover else {
+        return Ok(None);
+    };
+    Ok(data_url(&data_dir.join(COVERS_DIR).join(name))?)
+}
+
+/// An image file as a `data:` URL, or `None` if it's gone.
+pub fn data_url(path: &Path) -> Result<Option<String>, String> {
+    let mime = mime_type(&extension(path)).unwrap_or("application/octet-stream");
+    match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(format!(
+            "data:{mime};base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn changed(app: &AppHandle) {
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(app);
+}
+
+/// Changes the given parts; leave one out to keep it, pass `""` to clear an icon or emoji.
+#[tauri::command]
+pub fn set_habit_appearance(
+    app: AppHandle,
+    habit_id: String,
+    color: Option<String>,
+    icon: Option<String>,
+    emoji: Option<String>,
+) -> Result<Habit, AppError> {
+    let habit = update(&app.state::<Storage>(), &habit_id, color, icon, emoji)?;
+    changed(&app);
+    Ok(habit)
+}
+
+/// `path` is an image file to copy in; leave it out to remove the cover.
+#[tauri::command]
+pub fn set_habit_cover(
+    app: AppHandle,
+    habit_id: String,
+    path: Option<String>,
+) -> Result<Habit, AppError> {
+    let habit = set_cover(
+        &app.state::<Storage>(),
+        &app.path().app_data_dir()?,
+        &habit_id,
+        path.as_deref().map(Path::new),
+    )?;
+    changed(&app);
+    Ok(habit)
+}
+
+#[tauri::command]
+pub fn get_habit_cover(app: AppHandle, habit_id: String) -> Result<Option<String>, AppError> {
+    cover_data_url(
+        &app.state::<Storage>(),
+        &app.path().app_data_dir()?,
+        &habit_id,
+    )
+}
@@ -0,0 +1,117 @@
+//! Passphrase-encrypted export files, so backups left in cloud folders can't be read by
+//! the provider.
+//!
+//! The file is `HFLOWENC`, a format version byte, the argon2id cost parameters (memory
+//! KiB, iterations, lanes; u32 little-endian each), a 16-byte salt and a 24-byte nonce,
+//! followed by the XChaCha20-Poly1305 ciphertext. The key is argon2id of the passphrase
+//! and salt, and the whole header is authenticated along with the data, so tampering with
+//! the parameters fails decryption like a wrong passphrase does.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+const MAGIC: &[u8; 8] = b"HFLOWENC";
+const FORMAT_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LEN + NONCE_LEN;
+/// argon2id cost for new files: 64 MiB, 3 passes, 1 lane.
+const MEMORY_KIB: u32 = 64 * 1024;
+const ITERATIONS: u32 = 3;
+const LANES: u32 = 1;
+/// Refuse costs beyond these when reading, so a crafted file can't exhaust memory or hang.
+const MAX_MEMORY_KIB: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 16;
+pub const MIN_PASSPHRASE_LEN: usize = 8;
+/// Appended to the export's own extension: `habitflow-2026-10-14.json.enc`.
+pub const EXTENSION: &str = "enc";
+
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+fn key(
+    passphrase: &str,
+    salt: &[u8],
+    (memory, iterations, lanes): (u32, u32, u32),
+) -> Result<[u8; 32], String> {
+    let params = Params::new(memory, iterations, lanes, Some(32))
+        .map_err(|e| format!("invalid key parameters: {e}"))?;
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("failed to derive the key: {e}"))?;
+    Ok(key)
+}
+
+pub fn encrypt(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    use rand::RngCore;
+
+    if passphrase.chars().count() < MIN_PASSPHRASE_LEN {
+        return Err(format!(
+            "the passphrase needs at least {MIN_PASSPHRASE_LEN} characters"
+        ));
+    }
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::rng().fill_bytes(&mut salt);
+    rand::rng().fill_bytes(&mut nonce);
+
+    let mut out = Vec::with_capacity(HEADER_LEN + plaintext.len() + 16);
+    out.extend_from_slice(MAGIC);
+    out.push(FORMAT_VERSION);
+    for n in [MEMORY_KIB, ITERATIONS, LANES] {
+        out.extend_from_slice(&n.to_le_bytes());
+    }
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce);
+
+    let key = key(passphrase, &salt, (MEMORY_KIB, ITERATIONS, LANES))?;
+    let ciphertext = XChaCha20Poly1305::new(&key.into())
+        .encrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: &out,
+            },
+        )
+        .map_err(|_| "encryption failed".to_string())?;
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+pub fn decrypt(data: &[u8], passphrase: &str) -> Result<Vec<u8>, String> {
+    if !is_encrypted(data) || data.len() < HEADER_LEN {
+        return Err("not an encrypted HabitFlow export".into());
+    }
+    let (header, ciphertext) = data.split_at(HEADER_LEN);
+    if header[MAGIC.len()] != FORMAT_VERSION {
+        return Err("this encrypted export needs a newer version of HabitFlow".into());
+    }
+    let mut fields = header[MAGIC.len() + 1..]
+        .chunks_exact(4)
+        .take(3)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+    let (memory, iterations, lanes) = match (fields.next(), fields.next(), fields.next()) {
+        (Some(m), Some(t), Some(p)) => (m, t, p),
+        _ => return Err("not an encrypted HabitFlow export".into()),
+    };
+    if memory > MAX_MEMORY_KIB || iterations > MAX_ITERATIONS {
+        return Err("the export's key parameters are out of range".into());
+    }
+    let salt_at = MAGIC.len() + 1 + 3 * 4;
+    let salt = &header[salt_at..salt_at + SALT_LEN];
+    let nonce = &header[salt_at + SALT_LEN..];
+
+    let key = key(passphrase, salt, (memory, iterations, lanes))?;
+    XChaCha20Poly1305::new(&key.into())
+        .decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: header,
+            },
+        )
+        .map_err(|_| "wrong passphrase, or the file is damaged".into())
+}
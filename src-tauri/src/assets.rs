@@ -0,0 +1,348 @@
+//! The `habit-asset://` protocol, which hands the webview images without giving it file
+//! system access or pushing them through `data:` URLs.
+//!
+//! - `attachment/<id>`: a check-in photo (`attachments.rs`).
+//! - `cover/<habit id>`: a habit's cover (`appearance.rs`).
+//! - `heatmap/<habit id>?weeks=N`: an SVG of the last `N` weeks (53 by default, at most
+//!   260), one square per day.
+//! - `card/<habit id>`: an SVG card with the habit's streaks and completion rate.
+//!
+//! On macOS, iOS and Linux the URLs are `habit-asset://localhost/<path>`, on Windows and
+//! Android `http://habit-asset.localhost/<path>`; `convertFileSrc(path, "habit-asset")`
+//! builds the right one. Files are read from the app data dir only, `Range` requests get
+//! just the bytes asked for, and nothing is served while the app is locked.
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use chrono::{Datelike, Days};
+use percent_encoding::percent_decode_str;
+use tauri::http::{header, Method, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+
+use crate::i18n::{t, t_args};
+use crate::model::Habit;
+use crate::stats::{self, date_key, today};
+use crate::storage::Storage;
+
+pub const SCHEME: &str = "habit-asset";
+
+const DEFAULT_WEEKS: u32 = 53;
+const MAX_WEEKS: u32 = 260;
+const CELL: u32 = 12;
+const GAP: u32 = 2;
+const EMPTY_FILL: &str = "#8882";
+const SVG: &str = "image/svg+xml";
+
+/// Answers one request; called off the main thread.
+pub fn respond(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    if request.method() != Method::GET && request.method() != Method::HEAD {
+        return error(StatusCode::METHOD_NOT_ALLOWED);
+    }
+    if app.state::<crate::lock::AppLock>().is_locked() {
+        return error(StatusCode::FORBIDDEN);
+    }
+    let path = percent_decode_str(request.uri().path()).decode_utf8_lossy();
+    let range = request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok());
+    let result = match path.trim_matches('/').split_once('/') {
+        Some(("attachment", id)) => attachment(app, id, range),
+        Some(("cover", id)) => cover(app, id, range),
+        Some(("heatmap", id)) => {
+            let weeks = query(request, "weeks")
+                .and_then(|w| w.parse().ok())
+                .unwrap_or(DEFAULT_WEEKS);
+            habit(app, id).map(|h| generated(heatmap(&h, weeks).into_bytes(), range))
+        }
+        Some(("card", id)) => habit(app, id).map(|h| generated(card(app, &h).into_bytes(), range)),
+        _ => Err(StatusCode::NOT_FOUND),
+    };
+    let mut response = result.unwrap_or_else(error);
+    if request.method() == Method::HEAD {
+        response.body_mut().clear();
+    }
+    response
+}
+
+fn query<'a>(request: &'a Request<Vec<u8>>, name: &str) -> Option<&'a str> {
+    request
+        .uri()
+        .query()?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+}
+
+fn error(status: StatusCode) -> Response<Vec<u8>> {
+    let mut response = Response::new(Vec::new());
+    *response.status_mut() = status;
+    response
+}
+
+fn habit(app: &AppHandle, id: &str) -> Result<Habit, StatusCode> {
+    match app.state::<Storage>().get_habit(id) {
+        Ok(Some(habit)) => Ok(habit),
+        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            log::error!("assets: failed to load habit {id}: {e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+fn attachment(
+    app: &AppHandle,
+    id: &str,
+    range: Option<&str>,
+) -> Result<Response<Vec<u8>>, StatusCode> {
+    let attachment = match app.state::<Storage>().get_attachment(id) {
+        Ok(Some(attachment)) => attachment,
+        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            log::error!("assets: failed to load attachment {id}: {e}");
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+    let dir = crate::attachments::attachments_dir(app).map_err(|_| StatusCode::NOT_FOUND)?;
+    // Attachment files never change under the same id.
+    file(
+        &dir.join(&attachment.file),
+        &attachment.mime,
+        range,
+        "max-age=31536000, immutable",
+    )
+}
+
+fn cover(app: &AppHandle, id: &str, range: Option<&str>) -> Result<Response<Vec<u8>>, StatusCode> {
+    let name = habit(app, id)?.cover.ok_or(StatusCode::NOT_FOUND)?;
+    let path = crate::appearance::covers_dir(app)
+        .map_err(|_| StatusCode::NOT_FOUND)?
+        .join(name);
+    let mime = crate::appearance::mime_type(&crate::appearance::extension(&path))
+        .unwrap_or("application/octet-stream");
+    file(&path, mime, range, "no-cache")
+}
+
+/// The byte range a `Range` header asks of `len` bytes, end exclusive. `Ok(None)` for the
+/// whole thing, including headers this doesn't understand, which may be answered in full.
+fn byte_range(range: Option<&str>, len: u64) -> Result<Option<(u64, u64)>, StatusCode> {
+    let Some(spec) = range.and_then(|r| r.trim().strip_prefix("bytes=")) else {
+        return Ok(None);
+    };
+    // Several ranges would need a multipart answer; the whole file does as well.
+    let Some((start, end)) = spec.split_once('-').filter(|_| !spec.contains(',')) else {
+        return Ok(None);
+    };
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(n) if n > 0 => (len.saturating_sub(n), len),
+            _ => return Err(StatusCode::RANGE_NOT_SATISFIABLE),
+        },
+        (start, "") => (
+            start
+                .parse()
+                .map_err(|_| StatusCode::RANGE_NOT_SATISFIABLE)?,
+            len,
+        ),
+        (start, end) => {
+            let start: u64 = start
+                .parse()
+                .map_err(|_| StatusCode::RANGE_NOT_SATISFIABLE)?;
+            let end: u64 = end.parse().map_err(|_| StatusCode::RANGE_NOT_SATISFIABLE)?;
+            (start, end.saturating_add(1).min(len))
+        }
+    };
+    if start >= end {
+        return Err(StatusCode::RANGE_NOT_SATISFIABLE);
+    }
+    Ok(Some((start, end)))
+}
+
+fn response(
+    body: Vec<u8>,
+    mime: &str,
+    cache: &str,
+    range: Option<(u64, u64)>,
+    len: u64,
+) -> Response<Vec<u8>> {
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime)
+        .header(header::CONTENT_LENGTH, body.len())
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, cache)
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        // So `fetch` and canvases work from every app origin (see the module docs).
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*");
+    if mime == SVG {
+        builder = builder.header(
+            header::CONTENT_SECURITY_POLICY,
+            "default-src 'none'; style-src 'unsafe-inline'",
+        );
+    }
+    if let Some((start, end)) = range {
+        builder = builder.status(StatusCode::PARTIAL_CONTENT).header(
+            header::CONTENT_RANGE,
+            format!("bytes {start}-{}/{len}", end - 1),
+        );
+    }
+    builder
+        .body(body)
+        .unwrap_or_else(|_| error(StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+/// The file, or the part of it `range` asks for.
+fn file(
+    path: &Path,
+    mime: &str,
+    range: Option<&str>,
+    cache: &str,
+) -> Result<Response<Vec<u8>>, StatusCode> {
+    let io_error = |e: std::io::Error| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            StatusCode::NOT_FOUND
+        } else {
+            log::warn!("assets: can't read {}: {e}", path.display());
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    let mut file = std::fs::File::open(path).map_err(io_error)?;
+    let len = file.metadata().map_err(io_error)?.len();
+    let range = byte_range(range, len)?;
+    let (start, end) = range.unwrap_or((0, len));
+    let mut body = Vec::with_capacity((end - start) as usize);
+    file.seek(SeekFrom::Start(start)).map_err(io_error)?;
+    file.take(end - start)
+        .read_to_end(&mut body)
+        .map_err(io_error)?;
+    Ok(response(body, mime, cache, range, len))
+}
+
+/// A generated image, or the part of it `range` asks for. Never cached: it changes with
+/// every check-in.
+fn generated(body: Vec<u8>, range: Option<&str>) -> Response<Vec<u8>> {
+    let len = body.len() as u64;
+    match byte_range(range, len) {
+        Ok(Some((start, end))) => response(
+            body[start as usize..end as usize].to_vec(),
+            SVG,
+            "no-store",
+            Some((start, end)),
+            len,
+        ),
+        Ok(None) => response(body, SVG, "no-store", None, len),
+        Err(status) => error(status),
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// The habit's color if it's a valid one, so nothing else ends up in the SVG.
+fn color(habit: &Habit) -> &str {
+    if crate::appearance::validate_color(&habit.color).is_ok() {
+        &habit.color
+    } else {
+        "#4f46e5"
+    }
+}
+
+/// Weeks as columns from Sunday to Saturday, ending with the current one. Done days are
+/// filled in the habit's color, excused ones faintly, partial progress in between.
+pub fn heatmap(habit: &Habit, weeks: u32) -> String {
+    let weeks = weeks.clamp(1, MAX_WEEKS);
+    let today = today();
+    let days_back = (weeks - 1) * 7 + today.weekday().num_days_from_sunday();
+    let start = today
+        .checked_sub_days(Days::new(days_back.into()))
+        .unwrap_or(today);
+    let step = CELL + GAP;
+    let (width, height) = (weeks * step - GAP, 7 * step - GAP);
+    let fill = color(habit);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">"
+    );
+    for (i, date) in start.iter_days().take_while(|d| *d <= today).enumerate() {
+        let i = i as u32;
+        let key = date_key(date);
+        let opacity = if key.as_str() < habit.start_date() {
+            0.0
+        } else if habit.is_done_on(&key) {
+            1.0
+        } else if habit.is_excused(&key) {
+            0.3
+        } else {
+            match &habit.target {
+                Some(target) if target.amount > 0.0 => {
+                    (habit.value_on(&key) / target.amount).clamp(0.0, 1.0) * 0.7
+                }
+                _ => 0.0,
+            }
+        };
+        let (x, y) = ((i / 7) * step, (i % 7) * step);
+        if opacity > 0.0 {
+            svg.push_str(&format!(
+                "<rect x=\"{x}\" y=\"{y}\" width=\"{CELL}\" height=\"{CELL}\" rx=\"2\" fill=\"{fill}\" fill-opacity=\"{opacity:.2}\"><title>{key}</title></rect>"
+            ));
+        } else {
+            svg.push_str(&format!(
+                "<rect x=\"{x}\" y=\"{y}\" width=\"{CELL}\" height=\"{CELL}\" rx=\"2\" fill=\"{EMPTY_FILL}\"><title>{key}</title></rect>"
+            ));
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+/// A 400×200 card with the habit's name, unless names are hidden, its streaks and its
+/// completion rate over the last 30 days.
+pub fn card(app: &AppHandle, habit: &Habit) -> String {
+    let stats = stats::habit_stats(habit, today());
+    let name = if crate::privacy::hide_names(app) {
+        t("card-habit-hidden")
+    } else {
+        crate::appearance::label(habit)
+    };
+    let fill = color(habit);
+    let figures = [
+        (
+            t("card-current-streak"),
+            t_args("card-days", &[("count", stats.current_streak.into())]),
+        ),
+        (
+            t("card-longest-streak"),
+            t_args("card-days", &[("count", stats.longest_streak.into())]),
+        ),
+        (t("card-rate"), format!("{}%", stats.completion_rate_30d)),
+    ];
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"400\" height=\"200\" viewBox=\"0 0 400 200\" font-family=\"system-ui, sans-serif\">\
+         <rect width=\"400\" height=\"200\" rx=\"16\" fill=\"#fff\"/>\
+         <rect width=\"8\" height=\"200\" rx=\"4\" fill=\"{fill}\"/>\
+         <text x=\"32\" y=\"56\" font-size=\"24\" font-weight=\"600\" fill=\"#111\">{}</text>",
+        escape(&crate::text::truncate(&name, 28))
+    );
+    for (i, (label, value)) in figures.iter().enumerate() {
+        let x = 32 + i * 120;
+        svg.push_str(&format!(
+            "<text x=\"{x}\" y=\"130\" font-size=\"28\" font-weight=\"700\" fill=\"{fill}\">{}</text>\
+             <text x=\"{x}\" y=\"158\" font-size=\"13\" fill=\"#666\">{}</text>",
+            escape(value),
+            escape(label)
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
@@ -0,0 +1,168 @@
+//! Photos on check-ins: the view from the run, the page that was read. They're attached to
+//! a completed day, like notes, and copied into the `attachments` folder of the app data
+//! dir, one folder per habit. On desktop they come from a file dialog; on mobile from the
+//! share sheet as well (`share.rs`).
+
+use std::path::{Path, PathBuf};
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::appearance::{extension, mime_type};
+use crate::error::AppError;
+use crate::settings::generate_token;
+use crate::stats::{date_arg, date_key};
+use crate::storage::{Attachment, Storage};
+
+pub const ATTACHMENTS_DIR: &str = "attachments";
+/// Largest photo accepted, in bytes.
+pub const MAX_ATTACHMENT_BYTES: u64 = 20 * 1024 * 1024;
+/// Photos per check-in.
+pub const MAX_PER_DAY: usize = 10;
+
+pub(crate) fn attachments_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join(ATTACHMENTS_DIR);
+    Ok(dir)
+}
+
+/// Whether `source` can be attached, and if so its type and size.
+pub fn check(source: &Path) -> Result<(&'static str, u64), AppError> {
+    let Some(mime) = mime_type(&extension(source)) else {
+        return Err(AppError::Invalid(
+            "attachments must be PNG, JPEG, WebP or GIF images".into(),
+        ));
+    };
+    let size = std::fs::metadata(source)?.len();
+    if size > MAX_ATTACHMENT_BYTES {
+        return Err(AppError::Invalid(format!(
+            "attachments are limited to {} MB",
+            MAX_ATTACHMENT_BYTES / 1024 / 1024
+        )));
+    }
+    Ok((mime, size))
+}
+
+/// Copies `source` in as a photo on a completed day. Returns `Ok(None)` if the habit
+/// doesn't exist.
+pub fn add(
+    app: &AppHandle,
+    habit_id: &str,
+    date: &str,
+    source: &Path,
+) -> Result<Option<Attachment>, AppError> {
+    add_in(
+        &app.state::<Storage>(),
+        &app.path().app_data_dir()?,
+        habit_id,
+        date,
+        source,
+    )
+}
+
+/// [`add`] for `data_dir`'s attachments folder.
+pub fn add_in(
+    storage: &Storage,
+    data_dir: &Path,
+    habit_id: &str,
+    date: &str,
+    source: &Path,
+) -> Result<Option<Attachment>, AppError> {
+    let date = date_arg(date)?;
+    let key = date_key(date);
+    let Some(habit) = storage.get_habit(habit_id)? else {
+        return Ok(None);
+    };
+    if !habit.is_done_on(&key) {
+        return Err(AppError::Invalid(format!(
+            "{} isn't checked off on {key}",
+            habit.name
+        )));
+    }
+    let existing = storage.list_attachments(habit_id, Some(&key))?;
+    if existing.len() >= MAX_PER_DAY {
+        return Err(AppError::Invalid(format!(
+            "a check-in can have at most {MAX_PER_DAY} photos"
+        )));
+    }
+    let (mime, size) = check(source)?;
+
+    let id = generate_token()[..16].to_string();
+    let dir = data_dir.join(ATTACHMENTS_DIR).join(habit_id);
+    std::fs::create_dir_all(&dir)?;
+    let name = format!("{id}.{}", extension(source));
+    std::fs::copy(source, dir.join(&name))?;
+    let attachment = Attachment {
+        id,
+        habit_id: habit_id.to_string(),
+        date: key,
+        file: format!("{habit_id}/{name}"),
+        mime: mime.to_string(),
+        size,
+        added_at: chrono::Utc::now().to_rfc3339(),
+    };
+    if let Err(e) = storage.add_attachment(&attachment) {
+        let _ = std::fs::remove_file(dir.join(&name));
+        return Err(e.into());
+    }
+    Ok(Some(attachment))
+}
+
+/// Deletes the photo and its file from `data_dir`.
+pub fn remove_in(storage: &Storage, data_dir: &Path, id: &str) -> Result<(), AppError> {
+    let attachment = storage
+        .get_attachment(id)?
+        .ok_or_else(|| AppError::not_found("attachment", id))?;
+    storage.delete_attachment(id)?;
+    let _ = std::fs::remove_file(data_dir.join(ATTACHMENTS_DIR).join(&attachment.file));
+    Ok(())
+}
+
+/// The photo in `data_dir` as a `data:` URL the webview can show directly.
+pub fn data_url_in(
+    storage: &Storage,
+    data_dir: &Path,
+    id: &str,
+) -> Result<Option<String>, AppError> {
+    let attachment = storage
+        .get_attachment(id)?
+        .ok_or_else(|| AppError::not_found("attachment", id))?;
+    Ok(crate::appearance::data_url(
+        &data_dir.join(ATTACHMENTS_DIR).join(&attachment.file),
+    )?)
+}
+
+/// `path` is an image file to copy in, chosen in a file dialog.
+#[tauri::command]
+pub fn add_checkin_attachment(
+    app: AppHandle,
+    habit_id: String,
+    date: String,
+    path: String,
+) -> Result<Attachment, AppError> {
+    let path = crate::files::allowed(&app, &path)?;
+    add(&app, &habit_id, &date, &path)?.ok_or_else(|| AppError::not_found("habit", &habit_id))
+}
+
+/// A habit's photos, on one day or all of them.
+#[tauri::command]
+pub fn list_checkin_attachments(
+    storage: State<'_, Storage>,
+    habit_id: String,
+    date: Option<String>,
+) -> Result<Vec<Attachment>, AppError> {
+    Ok(storage.list_attachments(&habit_id, date.as_deref())?)
+}
+
+/// The photo as a `data:` URL the webview can show directly.
+#[tauri::command]
+pub fn get_checkin_attachment(app: AppHandle, id: String) -> Result<Option<String>, AppError> {
+    data_url_in(&app.state::<Storage>(), &app.path().app_data_dir()?, &id)
+}
+
+#[tauri::command]
+pub fn remove_checkin_attachment(app: AppHandle, id: String) -> Result<(), AppError> {
+    remove_in(&app.state::<Storage>(), &app.path().app_data_dir()?, &id)
+}
@@ -0,0 +1,299 @@
+//! User automation rules written in Rhai.
+//!
+//! Every `*.rhai` file in `<app data>/automations/` is compiled at startup (and on
+//! `reload_automations`). A script opts into hooks by defining any of:
+//!
+//! ```text
+//! fn on_habit_created(event) {}
+//! fn on_habit_completed(event) {}      // event: #{ habitId, name, date, streak }
+//! fn on_streak_milestone(event) {}
+//! fn on_streak_broken(event) {}
+//! fn on_streak_frozen(event) {}
+//! fn on_milestone_reached(event) {}    // event: #{ habitId, name, milestoneId, kind, threshold }
+//! fn on_habit_paused(event) {}         // event: #{ habitId, name, until }
+//! fn on_habit_resumed(event) {}        // event: #{ habitId, name }
+//! fn on_level_up(event) {}             // event: #{ level, xp, date }
+//! fn on_achievement_unlocked(event) {} // event: #{ achievementId, name }
+//! fn on_day_rollover(date) {}          // "yyyy-MM-dd" of the new day
+//! fn on_minute(time) {}                // "HH:MM", local time
+//! ```
+//!
+//! Scripts can't touch the filesystem or network. They get `habits()`, `habit(ref)`,
+//! `all_done()`, `complete(ref)`, `notify(title, body)`, `emit(name, data)`, `today()`
+//! and `now()`; `print` goes to the app log. Each hook call is capped by an
+//! operation budget so a runaway loop can't stall the backend.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rhai::{Dynamic, Engine, Scope, AST};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::events::{EventBus, HabitEvent};
+use crate::stats::{date_key, today};
+use crate::storage::Storage;
+
+pub const AUTOMATIONS_DIR: &str = "automations";
+/// Webview event carrying `emit(name, data)` calls from scripts.
+pub const AUTOMATION_EVENT: &str = "automation:event";
+
+pub struct Automations {
+    engine: Engine,
+    dir: PathBuf,
+    scripts: Mutex<Vec<Script>>,
+}
+
+struct Script {
+    name: String,
+    path: PathBuf,
+    ast: Option<AST>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AutomationInfo {
+    pub name: String,
+    pub path: String,
+    pub loaded: bool,
+    /// Compile error, or the most recent runtime error.
+    pub error: Option<String>,
+    pub hooks: Vec<String>,
+}
+
+/// Script-facing view of a habit.
+#[derive(Serialize)]
+struct ScriptHabit {
+    id: String,
+    name: String,
+    category: String,
+    done_today: bool,
+    streak: u32,
+}
+
+impl Automations {
+    /// Empty until the first [`reload`](Self::reload).
+    pub fn new(app: &AppHandle, data_dir: &Path) -> Self {
+        Self {
+            engine: build_engine(app),
+            dir: data_dir.join(AUTOMATIONS_DIR),
+            scripts: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// (Re)compiles every script in the automations folder.
+    pub fn reload(&self) {
+        if let Err(e) = std::fs::create_dir_all(&self.dir) {
+            log::warn!("automations: can't create {}: {e}", self.dir.display());
+        }
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.extension().is_some_and(|ext| ext == "rhai"))
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+
+        let scripts = paths
+            .into_iter()
+            .map(|path| {
+                let name = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                match self.engine.compile_file(path.clone()) {
+                    Ok(ast) => Script {
+                        name,
+                        path,
+                        ast: Some(ast),
+                        error: None,
+                    },
+                    Err(e) => {
+                        log::warn!("automations: {name} failed to compile: {e}");
+                        Script {
+                            name,
+                            path,
+                            ast: None,
+                            error: Some(e.to_string()),
+                        }
+                    }
+                }
+            })
+            .collect();
+        *self.lock() = scripts;
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Script>> {
+        self.scripts.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn list(&self) -> Vec<AutomationInfo> {
+        self.lock()
+            .iter()
+            .map(|s| AutomationInfo {
+                name: s.name.clone(),
+                path: s.path.display().to_string(),
+                loaded: s.ast.is_some(),
+                error: s.error.clone(),
+                hooks: s
+                    .ast
+                    .iter()
+                    .flat_map(|ast| ast.iter_functions())
+                    .filter(|f| f.name.starts_with("on_"))
+                    .map(|f| f.name.to_string())
+                    .collect(),
+            })
+            .collect()
+    }
+
+    /// Calls `hook(arg)` in every script that defines it.
+    pub fn call(&self, hook: &str, arg: Dynamic) {
+        let mut scripts = self.lock();
+        for script in scripts.iter_mut() {
+            let Some(ast) = &script.ast else { continue };
+            if !ast
+                .iter_functions()
+                .any(|f| f.name == hook && f.params.len() == 1)
+            {
+                continue;
+            }
+            let result =
+                self.engine
+                    .call_fn::<Dynamic>(&mut Scope::new(), ast, hook, (arg.clone(),));
+            if let Err(e) = result {
+                log::warn!("automations: {}::{hook} failed: {e}", script.name);
+                script.error = Some(e.to_string());
+            }
+        }
+    }
+}
+
+fn build_engine(app: &AppHandle) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(100_000);
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+    engine.set_max_string_size(16 * 1024);
+    engine.set_max_array_size(10_000);
+    engine.set_max_map_size(1_000);
+    engine.on_print(|s| log::info!("automations: {s}"));
+    engine.on_debug(|s, _, pos| log::debug!("automations: {s} ({pos})"));
+
+    let a = app.clone();
+    engine.register_fn("habits", move || -> rhai::Array {
+        script_habits(&a)
+            .into_iter()
+            .filter_map(|h| rhai::serde::to_dynamic(h).ok())
+            .collect()
+    });
+
+    let a = app.clone();
+    engine.register_fn("habit", move |reference: &str| -> Dynamic {
+        let habits = a.state::<Storage>().list_habits().unwrap_or_default();
+        crate::habits::find(&habits, reference)
+            .and_then(|h| script_habits(&a).into_iter().find(|s| s.id == h.id))
+            .and_then(|h| rhai::serde::to_dynamic(h).ok())
+            .unwrap_or(Dynamic::UNIT)
+    });
+
+    let a = app.clone();
+    engine.register_fn("all_done", move || -> bool {
+        let habits = script_habits(&a);
+        !habits.is_empty() && habits.iter().all(|h| h.done_today)
+    });
+
+    let a = app.clone();
+    engine.register_fn("complete", move |reference: &str| -> bool {
+        let habits = a.state::<Storage>().list_habits().unwrap_or_default();
+        let Some(habit) = crate::habits::find(&habits, reference) else {
+            return false;
+        };
+        crate::habits::set_completion(&a, &habit.id, today(), true)
+            .ok()
+            .flatten()
+            .is_some()
+    });
+
+    let a = app.clone();
+    engine.register_fn("notify", move |title: &str, body: &str| {
+        crate::reminders::notify(&a, title, body);
+    });
+
+    let a = app.clone();
+    engine.register_fn("emit", move |name: &str, data: Dynamic| {
+        let data = rhai::serde::from_dynamic::<serde_json::Value>(&data).unwrap_or_default();
+        let _ = a.emit(
+            AUTOMATION_EVENT,
+            serde_json::json!({ "name": name, "data": data }),
+        );
+    });
+
+    engine.register_fn("today", || date_key(today()));
+    engine.register_fn("now", || crate::clock::now().format("%H:%M").to_string());
+
+    engine
+}
+
+fn script_habits(app: &AppHandle) -> Vec<ScriptHabit> {
+    let today = today();
+    let key = date_key(today);
+    let storage = app.state::<Storage>();
+    storage
+        .list_habits()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|h| !h.archived)
+        .map(|h| ScriptHabit {
+            done_today: h.is_done_on(&key),
+            streak: crate::streaks::current(&storage, &h, today),
+            id: h.id,
+            name: h.name,
+            category: h.category,
+        })
+        .collect()
+}
+
+/// Feeds habit events into the scripts for the lifetime of the app.
+pub fn start(app: &AppHandle) {
+    let mut rx = app.state::<EventBus>().subscribe();
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+            let hook = match &event {
+                HabitEvent::HabitCreated { .. } => "on_habit_created",
+                HabitEvent::HabitCompleted { .. } => "on_habit_completed",
+                HabitEvent::StreakMilestone { .. } => "on_streak_milestone",
+                HabitEvent::StreakBroken { .. } => "on_streak_broken",
+                HabitEvent::StreakFrozen { .. } => "on_streak_frozen",
+                HabitEvent::MilestoneReached { .. } => "on_milestone_reached",
+                HabitEvent::HabitPaused { .. } => "on_habit_paused",
+                HabitEvent::HabitResumed { .. } => "on_habit_resumed",
+                HabitEvent::LevelUp { .. } => "on_level_up",
+                HabitEvent::AchievementUnlocked { .. } => "on_achievement_unlocked",
+            };
+            let Ok(arg) = rhai::serde::to_dynamic(&event) else {
+                continue;
+            };
+            app.state::<Automations>().call(hook, arg);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn list_automations(automations: State<'_, Automations>) -> Vec<AutomationInfo> {
+    automations.list()
+}
+
+#[tauri::command]
+pub fn reload_automations(automations: State<'_, Automations>) -> Vec<AutomationInfo> {
+    automations.reload();
+    automations.list()
+}
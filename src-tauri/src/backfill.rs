@@ -0,0 +1,123 @@
+//! Retroactive check-ins: "I did go running on Tuesday, I just forgot to tick it".
+//!
+//! `backfill_completion` only reaches back `settings.backfill.maxDays`, never into the
+//! future or before the habit existed, and refuses days that are already checked off.
+//! Backfilled days are marked (`Habit::backfilled`), so stats can leave them out.
+
+use chrono::{Days, NaiveDate};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::{Habit, HabitKind};
+use crate::revisions;
+use crate::settings::{BackfillSettings, SettingsStore};
+use crate::stats::{date_arg, date_key, today};
+use crate::storage::{Storage, Tx};
+
+/// Why `date` can't be backfilled for `habit`, if it can't.
+pub fn check(
+    habit: &Habit,
+    date: NaiveDate,
+    today: NaiveDate,
+    rules: &BackfillSettings,
+) -> Result<(), AppError> {
+    let key = date_key(date);
+    if habit.kind == HabitKind::Avoid {
+        return Err(AppError::Invalid(format!(
+            "{} counts days without a lapse",
+            habit.name
+        )));
+    }
+    if date > today {
+        return Err(AppError::Invalid("future days can't be checked off".into()));
+    }
+    if date == today {
+        return Err(AppError::Invalid(
+            "today isn't a past day; check it off as usual".into(),
+        ));
+    }
+    if rules.max_days > 0 {
+        let limit = today.checked_sub_days(Days::new(u64::from(rules.max_days)));
+        if limit.is_some_and(|limit| date < limit) {
+            return Err(AppError::Invalid(format!(
+                "only the last {} days can be backfilled",
+                rules.max_days
+            )));
+        }
+    }
+    if key.as_str() < habit.start_date() {
+        return Err(AppError::Invalid(format!(
+            "{} didn't exist yet on {key}",
+            habit.name
+        )));
+    }
+    if habit.is_done_on(&key) {
+        return Err(AppError::Invalid(format!(
+            "{} is already checked off on {key}",
+            habit.name
+        )));
+    }
+    Ok(())
+}
+
+/// Checks off a past day after validating it. Returns `Ok(None)` if the habit doesn't
+/// exist.
+pub fn backfill(
+    tx: &Tx<'_>,
+    rules: &BackfillSettings,
+    habit_id: &str,
+    date: NaiveDate,
+) -> Result<Option<Habit>, AppError> {
+    let Some(habit) = tx.get_habit(habit_id)? else {
+        return Ok(None);
+    };
+    check(&habit, date, today(), rules)?;
+    tx.backfill_completion(habit_id, &date_key(date))?;
+    Ok(tx.get_habit(habit_id)?)
+}
+
+/// [`backfill`] if the day is still on `expected` (see `revisions.rs`).
+pub fn backfill_checked(
+    storage: &Storage,
+    rules: &BackfillSettings,
+    habit_id: &str,
+    date: NaiveDate,
+    expected: Option<u64>,
+) -> Result<Habit, AppError> {
+    revisions::check_day(storage, habit_id, date, expected, |tx| {
+        backfill(tx, rules, habit_id, date)?.ok_or_else(|| AppError::not_found("habit", habit_id))
+    })
+}
+
+/// Copies of `habits` as if nothing had been backfilled.
+pub fn without_backfilled(habits: Vec<Habit>) -> Vec<Habit> {
+    habits
+        .into_iter()
+        .map(|mut h| {
+            for date in std::mem::take(&mut h.backfilled) {
+                h.logs.remove(&date);
+                h.values.remove(&date);
+            }
+            h
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn backfill_completion(
+    app: AppHandle,
+    habit_id: String,
+    date: String,
+    expected_revision: Option<u64>,
+) -> Result<Habit, AppError> {
+    let date = date_arg(&date)?;
+    let rules = app.state::<SettingsStore>().get().backfill;
+    let storage = app.state::<Storage>();
+    let habit = backfill_checked(&storage, &rules, &habit_id, date, expected_revision)?;
+    let bus = app.state::<EventBus>();
+    crate::habits::publish_completion(&storage, &bus, &habit, &date_key(date), today());
+    bus.data_changed();
+    crate::habits::notify_changed(&app);
+    Ok(habit)
+}
@@ -0,0 +1,390 @@
+//! Copying backups to an external drive when it's plugged in (`settings.backupDrive`).
+//!
+//! The drive is picked by its volume label or ID from `list_volumes`. The mounted volumes
+//! are checked every few seconds. While the drive is there, the newest encrypted export
+//! (the latest successful run of an encrypted export job) is copied into its `folder`,
+//! unless the drive already has that file. A notification says when a copy is done, or
+//! why it failed.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::settings::{BackupDriveSettings, SettingsStore};
+use crate::storage::Storage;
+
+const POLL: Duration = Duration::from_secs(5);
+/// Export runs to look through for the newest encrypted one.
+const RUNS: u32 = 100;
+
+/// A mounted volume.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Volume {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// The filesystem UUID on Linux and macOS, the volume serial number on Windows.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub mount_point: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupDriveStatus {
+    pub enabled: bool,
+    /// Where the drive is mounted, while it is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mount_point: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_copied_at: Option<String>,
+    /// The file last copied, on the drive.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_copied: Option<String>,
+    /// Why the last copy failed; cleared by the next one that works.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct BackupDrive {
+    status: Mutex<BackupDriveStatus>,
+}
+
+impl BackupDrive {
+    fn status(&self) -> std::sync::MutexGuard<'_, BackupDriveStatus> {
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    use super::Volume;
+
+    /// Undoes `\040`-style octal escapes, as in `/proc/self/mountinfo`.
+    fn unescape_octal(s: &str) -> String {
+        let mut out = Vec::with_capacity(s.len());
+        let bytes = s.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let code = s
+                .get(i + 1..i + 4)
+                .filter(|_| bytes[i] == b'\\')
+                .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+            match code {
+                Some(b) => {
+                    out.push(b);
+                    i += 4;
+                }
+                None => {
+                    out.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    /// Undoes `\x20`-style hex escapes, as in `/dev/disk/by-label`.
+    fn unescape_hex(s: &str) -> String {
+        let mut out = Vec::with_capacity(s.len());
+        let bytes = s.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let code = s
+                .get(i + 2..i + 4)
+                .filter(|_| bytes[i] == b'\\' && bytes.get(i + 1) == Some(&b'x'))
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+            match code {
+                Some(b) => {
+                    out.push(b);
+                    i += 4;
+                }
+                None => {
+                    out.push(bytes[i]);
+                    i += 1;
+                }
+            }
+        }
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    /// Device path => name, from the symlinks in `/dev/disk/<dir>`.
+    fn names(dir: &str) -> HashMap<PathBuf, String> {
+        let Ok(entries) = std::fs::read_dir(Path::new("/dev/disk").join(dir)) else {
+            return HashMap::new();
+        };
+        entries
+            .flatten()
+            .filter_map(|e| {
+                let device = std::fs::canonicalize(e.path()).ok()?;
+                Some((device, unescape_hex(&e.file_name().to_string_lossy())))
+            })
+            .collect()
+    }
+
+    /// Block devices mounted outside the system's own folders.
+    pub fn volumes() -> Vec<Volume> {
+        let Ok(mountinfo) = std::fs::read_to_string("/proc/self/mountinfo") else {
+            return Vec::new();
+        };
+        let labels = names("by-label");
+        let uuids = names("by-uuid");
+        mountinfo
+            .lines()
+            .filter_map(|line| {
+                let (fields, rest) = line.split_once(" - ")?;
+                let mount_point = unescape_octal(fields.split(' ').nth(4)?);
+                let source = unescape_octal(rest.split(' ').nth(1)?);
+                let system = ["/", "/home", "/usr", "/var"].contains(&mount_point.as_str())
+                    || ["/boot", "/efi", "/snap"]
+                        .iter()
+                        .any(|dir| Path::new(&mount_point).starts_with(dir));
+                if system || !source.starts_with("/dev/") {
+                    return None;
+                }
+                let device = std::fs::canonicalize(&source).unwrap_or_else(|_| source.into());
+                Some(Volume {
+                    label: labels.get(&device).cloned(),
+                    id: uuids.get(&device).cloned(),
+                    mount_point,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use std::path::Path;
+
+    use super::Volume;
+
+    /// `Volume UUID` from `diskutil info`.
+    fn uuid(mount_point: &str) -> Option<String> {
+        let output = std::process::Command::new("diskutil")
+            .args(["info", mount_point])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("Volume UUID:"))
+            .map(|uuid| uuid.trim().to_string())
+    }
+
+    /// The volumes under `/Volumes`, except the startup disk's link to `/`.
+    pub fn volumes() -> Vec<Volume> {
+        let Ok(entries) = std::fs::read_dir("/Volumes") else {
+            return Vec::new();
+        };
+        entries
+            .flatten()
+            .filter(|e| std::fs::canonicalize(e.path()).map_or(true, |p| p != Path::new("/")))
+            .map(|e| {
+                let mount_point = e.path().display().to_string();
+                Volume {
+                    label: Some(e.file_name().to_string_lossy().into_owned()),
+                    id: uuid(&mount_point),
+                    mount_point,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use windows_sys::Win32::Storage::FileSystem::{GetLogicalDrives, GetVolumeInformationW};
+
+    use super::Volume;
+
+    /// Every drive letter but `C:`, with its label and serial number.
+    pub fn volumes() -> Vec<Volume> {
+        let drives = unsafe { GetLogicalDrives() };
+        (b'D'..=b'Z')
+            .filter(|letter| drives & (1 << (letter - b'A')) != 0)
+            .filter_map(|letter| {
+                let root = format!("{}:\\", letter as char);
+                let wide: Vec<u16> = root.encode_utf16().chain([0]).collect();
+                let mut name = [0u16; 261];
+                let mut serial = 0u32;
+                let ok = unsafe {
+                    GetVolumeInformationW(
+                        wide.as_ptr(),
+                        name.as_mut_ptr(),
+                        name.len() as u32,
+                        &mut serial,
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                        0,
+                    )
+                };
+                // No disc in the drive, or a card reader without a card.
+                if ok == 0 {
+                    return None;
+                }
+                let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+                let label = String::from_utf16_lossy(&name[..len]);
+                Some(Volume {
+                    label: (!label.is_empty()).then_some(label),
+                    id: Some(format!("{:04X}-{:04X}", serial >> 16, serial & 0xFFFF)),
+                    mount_point: root,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod imp {
+    use super::Volume;
+
+    pub fn volumes() -> Vec<Volume> {
+        Vec::new()
+    }
+}
+
+/// The mounted volumes a backup drive can be picked from.
+pub fn volumes() -> Vec<Volume> {
+    imp::volumes()
+}
+
+/// The chosen drive, if it's mounted: by its ID when one is set, else by its label.
+fn find(settings: &BackupDriveSettings, volumes: Vec<Volume>) -> Option<Volume> {
+    let id = settings.volume_id.trim();
+    let label = settings.label.trim();
+    volumes.into_iter().find(|v| {
+        if !id.is_empty() {
+            v.id.as_deref().is_some_and(|v| v.eq_ignore_ascii_case(id))
+        } else {
+            !label.is_empty() && v.label.as_deref() == Some(label)
+        }
+    })
+}
+
+/// The newest encrypted export that's still where it was written.
+fn latest_backup(storage: &Storage) -> Result<Option<PathBuf>, String> {
+    let runs = storage
+        .list_export_runs(None, RUNS)
+        .map_err(|e| e.to_string())?;
+    Ok(runs
+        .into_iter()
+        .filter(|r| r.success)
+        .filter_map(|r| r.path.map(PathBuf::from))
+        .find(|p| {
+            p.extension()
+                .is_some_and(|e| e == crate::archive::EXTENSION)
+                && p.is_file()
+        }))
+}
+
+/// Copies `backup` into `dir` unless a file of the same name and size is there already.
+/// Returns the copy, if one was made.
+fn copy(backup: &Path, dir: &Path) -> Result<Option<PathBuf>, String> {
+    let name = backup.file_name().ok_or("the backup has no file name")?;
+    let target = dir.join(name);
+    let size = |p: &Path| std::fs::metadata(p).map(|m| m.len()).ok();
+    if size(&target).is_some() && size(&target) == size(backup) {
+        return Ok(None);
+    }
+    std::fs::create_dir_all(dir).map_err(|e| format!("can't create {}: {e}", dir.display()))?;
+    // Copied under another name first, so pulling the drive out halfway doesn't leave a
+    // broken file that looks like a backup.
+    let mut partial = target.clone().into_os_string();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    std::fs::copy(backup, &partial)
+        .and_then(|_| std::fs::File::open(&partial)?.sync_all())
+        .and_then(|_| std::fs::rename(&partial, &target))
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&partial);
+            format!("can't copy to {}: {e}", dir.display())
+        })?;
+    Ok(Some(target))
+}
+
+/// Copies the latest backup if the drive is mounted and doesn't have it yet.
+fn check(app: &AppHandle, settings: &BackupDriveSettings) {
+    let drive = app.state::<BackupDrive>();
+    let volume = find(settings, volumes());
+    drive.status().mount_point = volume.as_ref().map(|v| v.mount_point.clone());
+    let Some(volume) = volume else {
+        return;
+    };
+    let name = volume.label.clone().unwrap_or(volume.mount_point.clone());
+    let result = latest_backup(&app.state::<Storage>()).and_then(|backup| match backup {
+        Some(backup) => copy(
+            &backup,
+            &Path::new(&volume.mount_point).join(&settings.folder),
+        ),
+        None => Err("there's no encrypted backup yet; add an encrypted export job".into()),
+    });
+
+    let mut status = drive.status();
+    match result {
+        Ok(None) => {}
+        Ok(Some(copied)) => {
+            log::info!("backup drive: copied {}", copied.display());
+            status.last_copied_at = Some(crate::clock::now().to_rfc3339());
+            status.last_copied = Some(copied.display().to_string());
+            status.last_error = None;
+            drop(status);
+            crate::reminders::notify(
+                app,
+                &crate::i18n::t("backup-drive-title"),
+                &crate::i18n::t_args("backup-drive-copied", &[("drive", name.as_str().into())]),
+            );
+        }
+        // Told once, not every few seconds while the drive stays plugged in.
+        Err(e) if status.last_error.as_ref() == Some(&e) => {}
+        Err(e) => {
+            log::warn!("backup drive: {e}");
+            status.last_error = Some(e.clone());
+            drop(status);
+            crate::reminders::notify_critical(
+                app,
+                &crate::i18n::t("backup-drive-failed-title"),
+                &format!("{name}: {e}"),
+            );
+        }
+    }
+}
+
+/// Looks for the drive every few seconds while it's configured.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut ticks = tokio::time::interval(POLL);
+        loop {
+            ticks.tick().await;
+            let settings = app.state::<SettingsStore>().get().backup_drive;
+            if !settings.enabled {
+                app.state::<BackupDrive>().status().mount_point = None;
+                continue;
+            }
+            let a = app.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || check(&a, &settings)).await;
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn list_volumes() -> Vec<Volume> {
+    tauri::async_runtime::spawn_blocking(volumes)
+        .await
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_backup_drive_status(app: AppHandle) -> BackupDriveStatus {
+    let mut status = app.state::<BackupDrive>().status().clone();
+    status.enabled = app.state::<SettingsStore>().get().backup_drive.enabled;
+    status
+}
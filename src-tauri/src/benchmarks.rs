@@ -0,0 +1,228 @@
+//! `run_benchmarks`, for performance work: times the hot paths over a synthetic dataset in
+//! a scratch database, so the real data is never touched and runs are comparable. Each
+//! path runs `iterations` times and reports the mean, the fastest and the slowest run:
+//!
+//! - `import`: parsing a JSON export and writing it, the way a restore or a sync does
+//! - `streaks.walk`: current and longest streaks from the full history (`stats.rs`)
+//! - `streaks.rebuild` and `streaks.read`: building the streak runs, then reading from them
+//! - `stats.summary`: the dashboard summary behind `get_stats`
+//! - `stats.range`: a year of range stats per habit, with the cache emptied first
+//! - `search`: a full-text note search
+//!
+//! Only available in debug builds, or with `HABITFLOW_BENCHMARKS=1` set, since release
+//! builds are the ones worth timing.
+
+use std::path::PathBuf;
+use std::time::Instant;
+
+use chrono::Days;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+use crate::model::Habit;
+use crate::stats::{self, date_key, today};
+use crate::storage::{Storage, DB_FILE_NAME};
+
+/// Set to `1` to allow benchmarks in release builds.
+pub const BENCHMARKS_ENV: &str = "HABITFLOW_BENCHMARKS";
+
+const MAX_HABITS: u32 = 500;
+const MAX_DAYS: u32 = 3650;
+const MAX_ITERATIONS: u32 = 100;
+/// Share of days checked off, and of those, the share with a note.
+const DONE_RATE: f64 = 0.7;
+const NOTE_RATE: f64 = 0.1;
+const CATEGORIES: &[&str] = &["Health", "Work", "Mind"];
+const NOTE_WORDS: &[&str] = &[
+    "morning", "walk", "tired", "easy", "rain", "late", "focused", "gym", "quick", "long",
+];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BenchmarkOptions {
+    /// Default 20, at most 500.
+    pub habits: Option<u32>,
+    /// Days of history per habit. Default 365, at most 3650.
+    pub days: Option<u32>,
+    /// Default 5, at most 100.
+    pub iterations: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkResult {
+    pub name: &'static str,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BenchmarkReport {
+    pub habits: u32,
+    pub days: u32,
+    pub iterations: u32,
+    /// In the dataset, across every habit.
+    pub completions: usize,
+    pub notes: usize,
+    pub debug_build: bool,
+    pub results: Vec<BenchmarkResult>,
+    pub total_ms: f64,
+}
+
+/// The scratch database's folder, removed when dropped.
+struct Scratch(PathBuf);
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+fn enabled() -> bool {
+    cfg!(debug_assertions) || std::env::var(BENCHMARKS_ENV).is_ok_and(|v| v == "1")
+}
+
+fn dataset(habits: u32, days: u32) -> Result<Vec<Habit>, AppError> {
+    let mut rng = rand::rng();
+    let today = today();
+    let first = today
+        .checked_sub_days(Days::new(days.saturating_sub(1).into()))
+        .unwrap_or(today);
+    (0..habits)
+        .map(|i| {
+            let mut habit: Habit = serde_json::from_value(serde_json::json!({
+                "id": format!("bench-{i}"),
+                "name": format!("Habit {i}"),
+                "category": CATEGORIES[i as usize % CATEGORIES.len()],
+                "createdAt": format!("{}T08:00:00Z", date_key(first)),
+            }))
+            .map_err(|e| AppError::Failed(e.to_string()))?;
+            for day in first.iter_days().take(days as usize) {
+                if !rng.random_bool(DONE_RATE) {
+                    continue;
+                }
+                let key = date_key(day);
+                if rng.random_bool(NOTE_RATE) {
+                    let words: Vec<&str> = (0..4)
+                        .map(|_| NOTE_WORDS[rng.random_range(0..NOTE_WORDS.len())])
+                        .collect();
+                    habit.notes.insert(key.clone(), words.join(" "));
+                }
+                habit.logs.insert(key, true);
+            }
+            Ok(habit)
+        })
+        .collect()
+}
+
+/// Runs `f` `iterations` times.
+fn time(
+    name: &'static str,
+    iterations: u32,
+    mut f: impl FnMut() -> Result<(), AppError>,
+) -> Result<BenchmarkResult, AppError> {
+    let mut runs = Vec::with_capacity(iterations as usize);
+    for _ in 0..iterations {
+        let started = Instant::now();
+        f()?;
+        runs.push(started.elapsed().as_secs_f64() * 1000.0);
+    }
+    Ok(BenchmarkResult {
+        name,
+        mean_ms: runs.iter().sum::<f64>() / runs.len().max(1) as f64,
+        min_ms: runs.iter().copied().fold(f64::INFINITY, f64::min),
+        max_ms: runs.iter().copied().fold(0.0, f64::max),
+    })
+}
+
+pub fn run(options: &BenchmarkOptions) -> Result<BenchmarkReport, AppError> {
+    if !enabled() {
+        return Err(AppError::Forbidden(format!(
+            "benchmarks only run in debug builds or with {BENCHMARKS_ENV}=1"
+        )));
+    }
+    let habits = options.habits.unwrap_or(20).clamp(1, MAX_HABITS);
+    let days = options.days.unwrap_or(365).clamp(1, MAX_DAYS);
+    let iterations = options.iterations.unwrap_or(5).clamp(1, MAX_ITERATIONS);
+    let started = Instant::now();
+
+    let dir = std::env::temp_dir().join(format!(
+        "habitflow-bench-{}",
+        &crate::settings::generate_token()[..12]
+    ));
+    std::fs::create_dir_all(&dir)?;
+    let scratch = Scratch(dir);
+    let storage = Storage::open(&scratch.0.join(DB_FILE_NAME))?;
+
+    let data = dataset(habits, days)?;
+    let export = crate::exports::render(crate::settings::ExportFormat::Json, &data, &[]);
+    let today = today();
+    let mut results = Vec::new();
+
+    results.push(time("import", iterations, || {
+        let mut payload: serde_json::Value =
+            serde_json::from_str(&export).map_err(|e| AppError::Failed(e.to_string()))?;
+        let parsed: Vec<Habit> = serde_json::from_value(payload["habits"].take())
+            .map_err(|e| AppError::Failed(e.to_string()))?;
+        Ok(storage.replace_habits(&parsed)?)
+    })?);
+    let loaded = storage.list_habits()?;
+    let categories = storage.list_categories()?;
+
+    results.push(time("streaks.walk", iterations, || {
+        for habit in &loaded {
+            std::hint::black_box(stats::current_streak(habit, today));
+            std::hint::black_box(stats::longest_streak(habit, today));
+        }
+        Ok(())
+    })?);
+    results.push(time("streaks.rebuild", iterations, || {
+        crate::streaks::rebuild_all(&storage, None).map(|_| ())
+    })?);
+    results.push(time("streaks.read", iterations, || {
+        for habit in &loaded {
+            std::hint::black_box(crate::streaks::current(&storage, habit, today));
+            std::hint::black_box(crate::streaks::longest(&storage, habit, today));
+        }
+        Ok(())
+    })?);
+    results.push(time("stats.summary", iterations, || {
+        std::hint::black_box(stats::summarize(&loaded, &categories, today));
+        Ok(())
+    })?);
+    results.push(time("stats.range", iterations, || {
+        storage.stats_cache().forget_habit(None);
+        std::hint::black_box(crate::stats_cache::range_stats(&storage, None, None, None)?);
+        Ok(())
+    })?);
+    results.push(time("search", iterations, || {
+        std::hint::black_box(crate::notes::search(&storage, "morning wal", None)?);
+        Ok(())
+    })?);
+
+    Ok(BenchmarkReport {
+        habits,
+        days,
+        iterations,
+        completions: data.iter().map(|h| h.logs.len()).sum(),
+        notes: data.iter().map(|h| h.notes.len()).sum(),
+        debug_build: cfg!(debug_assertions),
+        results,
+        total_ms: started.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+/// Developer tool: times the hot paths over a synthetic dataset. Takes a while with large
+/// datasets, so it runs off the main thread.
+#[tauri::command]
+pub async fn run_benchmarks(
+    options: Option<BenchmarkOptions>,
+) -> Result<BenchmarkReport, AppError> {
+    let options = options.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || run(&options))
+        .await
+        .map_err(|e| AppError::Failed(e.to_string()))?
+}
@@ -0,0 +1,184 @@
+//! Biometric unlock for the app lock (`lock.rs`): Touch ID on macOS, Windows Hello on
+//! Windows, and the system biometric prompt on Android and iOS through
+//! `tauri-plugin-biometric`. Elsewhere only the PIN is offered.
+//!
+//! It's opt-in (`settings.lock.biometric`) and needs a PIN, which always keeps working as
+//! the fallback; a failed or cancelled prompt doesn't count toward the PIN backoff.
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::lock::AppLock;
+use crate::settings::SettingsStore;
+
+const PROMPT_REASON: &str = "Unlock HabitFlow";
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use block2::RcBlock;
+    use objc2::runtime::Bool;
+    use objc2_foundation::{NSError, NSString};
+    use objc2_local_authentication::{LAContext, LAPolicy};
+    use tauri::AppHandle;
+
+    pub const KIND: &str = "touchId";
+    const POLICY: LAPolicy = LAPolicy::DeviceOwnerAuthenticationWithBiometrics;
+
+    pub fn available(_app: &AppHandle) -> bool {
+        let context = unsafe { LAContext::new() };
+        unsafe { context.canEvaluatePolicy_error(POLICY) }.is_ok()
+    }
+
+    pub fn authenticate(_app: &AppHandle, reason: &str) -> Result<(), String> {
+        let context = unsafe { LAContext::new() };
+        let (tx, rx) = std::sync::mpsc::channel();
+        let reply = RcBlock::new(move |success: Bool, _error: *mut NSError| {
+            let _ = tx.send(success.as_bool());
+        });
+        unsafe {
+            context.evaluatePolicy_localizedReason_reply(
+                POLICY,
+                &NSString::from_str(reason),
+                &reply,
+            )
+        };
+        match rx.recv() {
+            Ok(true) => Ok(()),
+            _ => Err("Touch ID didn't confirm it's you".into()),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use tauri::AppHandle;
+    use windows::core::HSTRING;
+    use windows::Security::Credentials::UI::{
+        UserConsentVerificationResult, UserConsentVerifier, UserConsentVerifierAvailability,
+    };
+
+    pub const KIND: &str = "windowsHello";
+
+    pub fn available(_app: &AppHandle) -> bool {
+        UserConsentVerifier::CheckAvailabilityAsync()
+            .and_then(|op| op.join())
+            .is_ok_and(|a| a == UserConsentVerifierAvailability::Available)
+    }
+
+    pub fn authenticate(_app: &AppHandle, reason: &str) -> Result<(), String> {
+        let result = UserConsentVerifier::RequestVerificationAsync(&HSTRING::from(reason))
+            .and_then(|op| op.join())
+            .map_err(|e| format!("Windows Hello failed: {e}"))?;
+        if result == UserConsentVerificationResult::Verified {
+            Ok(())
+        } else {
+            Err("Windows Hello didn't confirm it's you".into())
+        }
+    }
+}
+
+#[cfg(mobile)]
+mod imp {
+    use tauri::AppHandle;
+    use tauri_plugin_biometric::{AuthOptions, BiometricExt};
+
+    pub const KIND: &str = "biometrics";
+
+    pub fn available(app: &AppHandle) -> bool {
+        app.biometric().status().is_ok_and(|s| s.is_available)
+    }
+
+    pub fn authenticate(app: &AppHandle, reason: &str) -> Result<(), String> {
+        app.biometric()
+            .authenticate(
+                reason.to_string(),
+                AuthOptions {
+                    // The PIN is the fallback, not the device passcode.
+                    allow_device_credential: false,
+                    ..Default::default()
+                },
+            )
+            .map_err(|e| format!("biometric check failed: {e}"))
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows, mobile)))]
+mod imp {
+    use tauri::AppHandle;
+
+    pub const KIND: &str = "none";
+
+    pub fn available(_app: &AppHandle) -> bool {
+        false
+    }
+
+    pub fn authenticate(_app: &AppHandle, _reason: &str) -> Result<(), String> {
+        Err("biometric unlock isn't supported on this platform".into())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthMethods {
+    /// A PIN is set; it always works.
+    pub pin: bool,
+    /// The device can check biometrics.
+    pub biometric_available: bool,
+    /// `touchId`, `windowsHello` or `biometrics`; `none` where unsupported.
+    pub biometric_kind: &'static str,
+    /// Biometric unlock is switched on and can be used right now.
+    pub biometric: bool,
+}
+
+pub fn methods(app: &AppHandle) -> AuthMethods {
+    let pin = crate::lock::status(&app.state::<AppLock>()).configured;
+    let available = imp::available(app);
+    AuthMethods {
+        pin,
+        biometric_available: available,
+        biometric_kind: imp::KIND,
+        biometric: pin && available && app.state::<SettingsStore>().get().lock.biometric,
+    }
+}
+
+/// Allowed while locked.
+#[tauri::command]
+pub fn get_auth_methods(app: AppHandle) -> AuthMethods {
+    methods(&app)
+}
+
+/// Switches biometric unlock on or off. Needs a PIN and, to switch on, a device that
+/// supports it.
+#[tauri::command]
+pub fn set_biometric_unlock(app: AppHandle, enabled: bool) -> Result<AuthMethods, AppError> {
+    let current = methods(&app);
+    if enabled && !current.pin {
+        return Err(AppError::Invalid(
+            "set a PIN first; it stays the fallback".into(),
+        ));
+    }
+    if enabled && !current.biometric_available {
+        return Err(AppError::Invalid(
+            "this device can't check biometrics".into(),
+        ));
+    }
+    app.state::<SettingsStore>()
+        .update(|s| s.lock.biometric = enabled)?;
+    Ok(methods(&app))
+}
+
+/// Shows the system prompt and unlocks on success. Allowed while locked; on failure the
+/// PIN is the way in.
+#[tauri::command]
+pub async fn unlock_app_biometric(app: AppHandle) -> Result<crate::lock::LockStatus, AppError> {
+    if !methods(&app).biometric {
+        return Err(AppError::Invalid(
+            "biometric unlock is off; use the PIN".into(),
+        ));
+    }
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || imp::authenticate(&handle, PROMPT_REASON))
+        .await??;
+    Ok(crate::lock::unlock_verified(&app))
+}
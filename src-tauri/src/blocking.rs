@@ -0,0 +1,405 @@
+//! Warnings and blocks for avoidance habits ("no gaming before 6pm"), on top of the
+//! screen-time samples (`screen_time.rs`).
+//!
+//! A rule in `settings.screenTime.rules` ties an avoidance habit to a category and, if
+//! given, a time of day. While its habit is in force (not archived, paused or excused,
+//! and inside the window), the rule is on:
+//!
+//! - `warn` shows a notification when the category comes to the foreground, every few
+//!   minutes while it stays there.
+//! - `block` also closes apps the category names when they come to the foreground, and
+//!   points its `domains` at 0.0.0.0 in the hosts file. Windows that only matched by
+//!   title (a browser tab) are warned about, not closed.
+//!
+//! `override_block` lets the user through for a while. The override is logged as a lapse
+//! on the habit, so it shows up in its history.
+//!
+//! The hosts file needs admin rights to change. Without them the block falls back to
+//! closing apps, and `get_block_status` says why. It's replaced in one rename, so a crash
+//! mid-write can't leave it half written, and the HabitFlow section is taken out again when
+//! the app quits and when it starts (in case it last ended without quitting).
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, FixedOffset};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::model::{Habit, HabitKind};
+use crate::screen_time::Foreground;
+use crate::settings::{BlockMode, BlockRule, ScreenCategory, ScreenTimeSettings, SettingsStore};
+use crate::stats::{date_key, today};
+use crate::storage::Storage;
+
+const WARN_EVERY: Duration = Duration::from_secs(5 * 60);
+const DEFAULT_OVERRIDE_MINUTES: u32 = 15;
+const MAX_OVERRIDE_MINUTES: u32 = 240;
+const HOSTS_START: &str = "# >>> HabitFlow blocks (removed when the block ends)";
+const HOSTS_END: &str = "# <<< HabitFlow blocks";
+
+#[derive(Default)]
+struct State {
+    /// Per habit, when its override runs out.
+    overrides: HashMap<String, DateTime<FixedOffset>>,
+    /// Per category, when it was last warned about.
+    warned: HashMap<String, Instant>,
+    /// The domains in the hosts file; `None` until it's been looked at.
+    hosts: Option<BTreeSet<String>>,
+    hosts_error: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Blocking {
+    state: Mutex<State>,
+}
+
+impl Blocking {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The habit a rule is for, if the rule is on now.
+fn in_force(
+    storage: &Storage,
+    state: &State,
+    rule: &BlockRule,
+    now: DateTime<FixedOffset>,
+) -> Option<Habit> {
+    let habit = storage.get_habit(&rule.habit_id).ok()??;
+    let minute = now.format("%H:%M").to_string();
+    let on = habit.kind == HabitKind::Avoid
+        && !habit.archived
+        && !habit.is_excused(&date_key(now.date_naive()))
+        && rule.window.as_ref().map_or(true, |w| w.contains(&minute))
+        && state
+            .overrides
+            .get(&habit.id)
+            .map_or(true, |until| *until <= now);
+    on.then_some(habit)
+}
+
+fn category<'a>(settings: &'a ScreenTimeSettings, name: &str) -> Option<&'a ScreenCategory> {
+    settings.categories.iter().find(|c| c.name == name)
+}
+
+fn hosts_path() -> PathBuf {
+    if cfg!(windows) {
+        let root = std::env::var_os("SystemRoot").unwrap_or_else(|| "C:\\Windows".into());
+        PathBuf::from(root).join("System32\\drivers\\etc\\hosts")
+    } else {
+        PathBuf::from("/etc/hosts")
+    }
+}
+
+/// `contents` without the HabitFlow section, and with one for `domains` if any.
+fn with_section(contents: &str, domains: &BTreeSet<String>) -> String {
+    let mut out = String::with_capacity(contents.len());
+    let mut inside = false;
+    for line in contents.lines() {
+        match line.trim() {
+            HOSTS_START => inside = true,
+            HOSTS_END => inside = false,
+            _ if !inside => {
+                out.push_str(line);
+                out.push('\n');
+            }
+            _ => {}
+        }
+    }
+    if !domains.is_empty() {
+        out.push_str(HOSTS_START);
+        out.push('\n');
+        for domain in domains {
+            out.push_str(&format!("0.0.0.0 {domain}\n"));
+            if !domain.starts_with("www.") {
+                out.push_str(&format!("0.0.0.0 www.{domain}\n"));
+            }
+        }
+        out.push_str(HOSTS_END);
+        out.push('\n');
+    }
+    out
+}
+
+fn write_hosts(domains: &BTreeSet<String>) -> Result<(), String> {
+    let path = hosts_path();
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| format!("can't read {}: {e}", path.display()))?;
+    let updated = with_section(&contents, domains);
+    if updated == contents {
+        return Ok(());
+    }
+    replace(&path, &updated).map_err(|e| match e.kind() {
+        std::io::ErrorKind::PermissionDenied => format!(
+            "blocking sites needs permission to change {}; apps are still closed",
+            path.display()
+        ),
+        _ => format!("can't change {}: {e}", path.display()),
+    })
+}
+
+/// Writes `contents` next to `path` and renames it over, keeping `path`'s permissions.
+fn replace(path: &Path, contents: &str) -> std::io::Result<()> {
+    let tmp = path.with_file_name(".hosts.habitflow");
+    let written = std::fs::write(&tmp, contents)
+        .and_then(|()| std::fs::set_permissions(&tmp, std::fs::metadata(path)?.permissions()))
+        .and_then(|()| std::fs::rename(&tmp, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    written
+}
+
+/// Puts `domains` in the hosts file, if that's not what's there already.
+fn sync_hosts(state: &mut State, domains: BTreeSet<String>) {
+    if state.hosts.as_ref() == Some(&domains) {
+        return;
+    }
+    match write_hosts(&domains) {
+        Ok(()) => {
+            log::info!("blocking: {} domains blocked", domains.len());
+            state.hosts_error = None;
+        }
+        Err(e) => {
+            log::warn!("blocking: {e}");
+            state.hosts_error = Some(e);
+        }
+    }
+    state.hosts = Some(domains);
+}
+
+/// Takes the HabitFlow section out of the hosts file. Called at startup and on quit.
+pub fn clear_hosts(app: &AppHandle) {
+    let blocking = app.state::<Blocking>();
+    let mut state = blocking.state();
+    state.hosts = None;
+    sync_hosts(&mut state, BTreeSet::new());
+}
+
+#[cfg(windows)]
+fn terminate(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{OpenProcess, TerminateProcess, PROCESS_TERMINATE};
+
+    unsafe {
+        let process = OpenProcess(PROCESS_TERMINATE, 0, pid);
+        if process.is_null() {
+            return false;
+        }
+        let ok = TerminateProcess(process, 1) != 0;
+        CloseHandle(process);
+        ok
+    }
+}
+
+#[cfg(unix)]
+fn terminate(pid: u32) -> bool {
+    match i32::try_from(pid) {
+        Ok(pid) if pid > 1 => unsafe { libc::kill(pid, libc::SIGTERM) == 0 },
+        _ => false,
+    }
+}
+
+#[cfg(not(any(windows, unix)))]
+fn terminate(_pid: u32) -> bool {
+    false
+}
+
+fn until(rule: &BlockRule) -> String {
+    rule.window
+        .as_ref()
+        .map_or_else(|| crate::i18n::t("block-until-tomorrow"), |w| w.end.clone())
+}
+
+/// Warns about or closes what's in the foreground if a rule that's on covers it, and keeps
+/// the hosts file in step with the rules. Called with every screen-time sample.
+pub fn enforce(app: &AppHandle, settings: &ScreenTimeSettings, foreground: Option<&Foreground>) {
+    let blocking = app.state::<Blocking>();
+    let storage = app.state::<Storage>();
+    let now = crate::clock::now();
+    let mut state = blocking.state();
+    state.overrides.retain(|_, until| *until > now);
+    let active: Vec<&BlockRule> = if settings.enabled {
+        settings
+            .rules
+            .iter()
+            .filter(|r| in_force(&storage, &state, r, now).is_some())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let domains = active
+        .iter()
+        .filter(|r| r.mode == BlockMode::Block)
+        .filter_map(|r| category(settings, &r.category))
+        .flat_map(|c| &c.domains)
+        .map(|d| d.trim().trim_end_matches('.').to_lowercase())
+        .filter(|d| !d.is_empty() && !d.contains(char::is_whitespace))
+        .collect();
+    sync_hosts(&mut state, domains);
+
+    let Some(foreground) = foreground else {
+        return;
+    };
+    let Some(name) = crate::screen_time::category(&settings.categories, foreground) else {
+        return;
+    };
+    let Some(rule) = active.iter().find(|r| r.category == name) else {
+        return;
+    };
+    let by_app = category(settings, name).is_some_and(|c| {
+        let app = foreground.app.to_lowercase();
+        c.apps
+            .iter()
+            .map(|a| a.trim().to_lowercase())
+            .any(|a| !a.is_empty() && app.contains(&a))
+    });
+    let own = foreground.pid == Some(std::process::id());
+    let closed =
+        rule.mode == BlockMode::Block && by_app && !own && foreground.pid.is_some_and(terminate);
+
+    let args = [
+        ("app", foreground.app.as_str().into()),
+        ("category", name.into()),
+        ("until", until(rule).as_str().into()),
+    ];
+    if closed {
+        log::info!("blocking: closed {}", foreground.app);
+        state.warned.insert(name.to_string(), Instant::now());
+        drop(state);
+        crate::reminders::notify_critical(
+            app,
+            &crate::i18n::t("block-title"),
+            &crate::i18n::t_args("block-closed", &args),
+        );
+        return;
+    }
+    let due = state
+        .warned
+        .get(name)
+        .map_or(true, |at| at.elapsed() >= WARN_EVERY);
+    if due {
+        state.warned.insert(name.to_string(), Instant::now());
+        drop(state);
+        crate::reminders::notify(
+            app,
+            &crate::i18n::t("block-title"),
+            &crate::i18n::t_args("block-warning", &args),
+        );
+    }
+}
+
+/// Refuses rules with a window that isn't `HH:MM`-`HH:MM`.
+pub fn validate(settings: &ScreenTimeSettings) -> Result<(), AppError> {
+    for rule in &settings.rules {
+        if let Some(window) = &rule.window {
+            crate::time_windows::validate(window)
+                .map_err(|e| AppError::Invalid(format!("block rule for {}: {e}", rule.habit_id)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Lifts a blocked category for `minutes`, logging a lapse on the habit.
+pub fn override_block(
+    app: &AppHandle,
+    habit_id: &str,
+    minutes: Option<u32>,
+    reason: Option<String>,
+) -> Result<Habit, AppError> {
+    let settings = app.state::<SettingsStore>().get().screen_time;
+    if !settings.rules.iter().any(|r| r.habit_id == habit_id) {
+        return Err(AppError::Invalid(format!(
+            "no block rule is set for {habit_id}"
+        )));
+    }
+    let minutes = minutes.unwrap_or(DEFAULT_OVERRIDE_MINUTES);
+    if !(1..=MAX_OVERRIDE_MINUTES).contains(&minutes) {
+        return Err(AppError::Invalid(format!(
+            "an override lasts between 1 and {MAX_OVERRIDE_MINUTES} minutes"
+        )));
+    }
+    let note = match reason.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        Some(reason) => crate::i18n::t_args("block-override-note", &[("reason", reason.into())]),
+        None => crate::i18n::t("block-override"),
+    };
+    let habit = crate::habits::log_lapse(app, habit_id, today(), Some(note))?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+    let until = crate::clock::now() + chrono::Duration::minutes(i64::from(minutes));
+    app.state::<Blocking>()
+        .state()
+        .overrides
+        .insert(habit_id.to_string(), until);
+    log::info!("blocking: {habit_id} overridden for {minutes} minutes");
+    enforce(app, &settings, None);
+    Ok(habit)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleStatus {
+    pub habit_id: String,
+    pub category: String,
+    pub mode: BlockMode,
+    /// On right now.
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overridden_until: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockStatus {
+    pub rules: Vec<RuleStatus>,
+    /// In the hosts file now.
+    pub blocked_domains: Vec<String>,
+    /// Why the hosts file couldn't be changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hosts_error: Option<String>,
+}
+
+#[tauri::command]
+pub fn get_block_status(app: AppHandle) -> BlockStatus {
+    let settings = app.state::<SettingsStore>().get().screen_time;
+    let storage = app.state::<Storage>();
+    let now = crate::clock::now();
+    let state = app.state::<Blocking>();
+    let state = state.state();
+    BlockStatus {
+        rules: settings
+            .rules
+            .iter()
+            .map(|r| RuleStatus {
+                habit_id: r.habit_id.clone(),
+                category: r.category.clone(),
+                mode: r.mode,
+                active: settings.enabled && in_force(&storage, &state, r, now).is_some(),
+                overridden_until: state
+                    .overrides
+                    .get(&r.habit_id)
+                    .filter(|until| **until > now)
+                    .map(|until| until.to_rfc3339()),
+            })
+            .collect(),
+        blocked_domains: state.hosts.iter().flatten().cloned().collect(),
+        hosts_error: state.hosts_error.clone(),
+    }
+}
+
+/// Lets the user past a rule for `minutes` (15 when left out, at most 240). Logged as a
+/// lapse on the habit, with `reason` as its note.
+#[tauri::command]
+pub fn override_block_rule(
+    app: AppHandle,
+    habit_id: String,
+    minutes: Option<u32>,
+    reason: Option<String>,
+) -> Result<Habit, AppError> {
+    override_block(&app, &habit_id, minutes, reason)
+}
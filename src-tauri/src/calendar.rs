@@ -0,0 +1,173 @@
+//! Which day a week starts on, and how dates are written out for people.
+//!
+//! Both come from `calendar` in the settings, and with those on `auto`, from the region of
+//! the locale (`language.locale`, or the system's): CLDR's first day of the week for it,
+//! and its usual day, month and year order. A locale without a region (`de`, `ja`) gets
+//! its language's most likely one.
+//!
+//! Weekly quotas and their streaks (`schedule.rs`), the week totals next to the heatmap
+//! (`stats_cache.rs`) and the year in review (`review.rs`) all use the week start. The
+//! social leaderboard doesn't: friends have to agree on what a week is, so it keeps ISO
+//! weeks. Dates in exports and the API stay `yyyy-MM-dd`.
+
+use std::sync::RwLock;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use tauri::{AppHandle, Manager};
+
+use crate::events::EventBus;
+use crate::settings::{DateFormat, SettingsStore, WeekStart};
+
+/// Regions whose weeks start on Sunday.
+const SUNDAY_FIRST: &[&str] = &[
+    "AG", "AS", "BD", "BR", "BS", "BT", "BW", "BZ", "CA", "CN", "CO", "DM", "DO", "ET", "GT", "GU",
+    "HK", "HN", "ID", "IL", "IN", "JM", "JP", "KE", "KH", "KR", "LA", "MH", "MM", "MO", "MT", "MX",
+    "MZ", "NI", "NP", "PA", "PE", "PH", "PK", "PR", "PT", "PY", "SA", "SG", "SV", "TH", "TT", "TW",
+    "UM", "US", "VE", "VI", "WS", "YE", "ZA", "ZW",
+];
+/// Regions whose weeks start on Saturday.
+const SATURDAY_FIRST: &[&str] = &[
+    "AE", "AF", "BH", "DJ", "DZ", "EG", "IQ", "IR", "JO", "KW", "LY", "OM", "QA", "SD", "SY",
+];
+/// Regions that write the month first.
+const MONTH_FIRST: &[&str] = &["US", "PH", "FM", "MH", "PW", "AS", "GU", "PR", "UM", "VI"];
+/// Regions that write the year first.
+const YEAR_FIRST: &[&str] = &["CN", "JP", "KR", "TW", "HU", "LT", "MN", "SE", "CA", "IR"];
+/// Regions that write day.month.year with dots.
+const DOTTED: &[&str] = &[
+    "AT", "AZ", "BY", "BG", "CH", "CZ", "DE", "DK", "EE", "FI", "HR", "IS", "KZ", "LV", "NO", "PL",
+    "RO", "RS", "RU", "SI", "SK", "TR", "UA",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Order {
+    DayMonthYear,
+    MonthDayYear,
+    YearMonthDay,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Calendar {
+    week_start: Weekday,
+    order: Order,
+    separator: char,
+}
+
+static CURRENT: RwLock<Calendar> = RwLock::new(Calendar {
+    week_start: Weekday::Mon,
+    order: Order::YearMonthDay,
+    separator: '-',
+});
+
+/// The region subtag of a BCP 47 tag, or the likeliest one for its language.
+fn region(tag: &str) -> String {
+    let mut parts = tag.split(['-', '_']);
+    let lang = parts.next().unwrap_or_default().to_lowercase();
+    if let Some(region) = parts.find(|p| p.len() == 2 && p.chars().all(char::is_alphabetic)) {
+        return region.to_uppercase();
+    }
+    match lang.as_str() {
+        "en" => "US",
+        "ja" => "JP",
+        "zh" => "CN",
+        "ko" => "KR",
+        "he" => "IL",
+        "hi" => "IN",
+        "ar" => "EG",
+        "fa" => "IR",
+        "pt" => "BR",
+        "sv" => "SE",
+        "da" => "DK",
+        "nb" | "nn" => "NO",
+        "cs" => "CZ",
+        "uk" => "UA",
+        "el" => "GR",
+        _ => return lang.to_uppercase(),
+    }
+    .to_string()
+}
+
+fn resolve(tag: &str, week_start: WeekStart, format: DateFormat) -> Calendar {
+    let region = region(tag);
+    let listed = |list: &[&str]| list.contains(&region.as_str());
+    let week_start = match week_start {
+        WeekStart::Monday => Weekday::Mon,
+        WeekStart::Saturday => Weekday::Sat,
+        WeekStart::Sunday => Weekday::Sun,
+        WeekStart::Auto if listed(SUNDAY_FIRST) => Weekday::Sun,
+        WeekStart::Auto if listed(SATURDAY_FIRST) => Weekday::Sat,
+        WeekStart::Auto => Weekday::Mon,
+    };
+    let order = match format {
+        DateFormat::Iso => Order::YearMonthDay,
+        DateFormat::DayMonthYear => Order::DayMonthYear,
+        DateFormat::MonthDayYear => Order::MonthDayYear,
+        DateFormat::Auto if listed(MONTH_FIRST) => Order::MonthDayYear,
+        DateFormat::Auto if listed(YEAR_FIRST) => Order::YearMonthDay,
+        DateFormat::Auto => Order::DayMonthYear,
+    };
+    let separator = match (format, order) {
+        (DateFormat::Iso, _) => '-',
+        (_, Order::YearMonthDay) if matches!(region.as_str(), "CN" | "JP" | "TW") => '/',
+        (_, Order::YearMonthDay) => '-',
+        _ if listed(DOTTED) => '.',
+        (_, Order::DayMonthYear) if region == "NL" => '-',
+        _ => '/',
+    };
+    Calendar {
+        week_start,
+        order,
+        separator,
+    }
+}
+
+fn current() -> Calendar {
+    *CURRENT.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Follows `calendar` and the locale. A new week start changes weekly progress and
+/// streaks, so it counts as a data change.
+pub fn apply(app: &AppHandle) {
+    let settings = app.state::<SettingsStore>();
+    let calendar = settings.get().calendar;
+    let next = resolve(
+        &crate::i18n::tag(&settings),
+        calendar.week_start,
+        calendar.date_format,
+    );
+    let previous = std::mem::replace(
+        &mut *CURRENT.write().unwrap_or_else(|e| e.into_inner()),
+        next,
+    );
+    if previous.week_start != next.week_start {
+        log::info!("calendar: weeks start on {:?}", next.week_start);
+        app.state::<EventBus>().data_changed();
+    }
+}
+
+pub fn week_start() -> Weekday {
+    current().week_start
+}
+
+/// The week (inclusive) that `date` falls in.
+pub fn week_containing(date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let into =
+        (7 + date.weekday().num_days_from_monday() - week_start().num_days_from_monday()) % 7;
+    let start = date
+        .checked_sub_days(Days::new(into.into()))
+        .unwrap_or(date);
+    (start, start.checked_add_days(Days::new(6)).unwrap_or(start))
+}
+
+/// `date` as the locale writes it, `14/10/2026` or `10/14/2026` or `2026-10-14`.
+pub fn format_date(date: NaiveDate) -> String {
+    let Calendar {
+        order, separator, ..
+    } = current();
+    let (d, m, y) = (date.day(), date.month(), date.year());
+    match order {
+        Order::DayMonthYear => format!("{d:02}{separator}{m:02}{separator}{y}"),
+        Order::MonthDayYear => format!("{m:02}{separator}{d:02}{separator}{y}"),
+        Order::YearMonthDay => format!("{y}{separator}{m:02}{separator}{d:02}"),
+    }
+}
@@ -0,0 +1,194 @@
+//! Habit categories as backend records: a name, a color and a position in the list.
+//!
+//! Habits keep referring to their category by name, so the webview (which only knows the
+//! name) keeps working; renaming or deleting a category here rewrites the habits in it and
+//! pushes the result to the webview. Stats, the tray menu and exports group by category
+//! in this order.
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::{Category, Habit, DEFAULT_CATEGORY};
+use crate::settings::generate_token;
+use crate::storage::Storage;
+
+/// Used when a category is created without a color.
+pub const DEFAULT_COLOR: &str = "#64748b";
+
+fn clean_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Invalid("category name is required".into()));
+    }
+    Ok(name.to_string())
+}
+
+fn find(storage: &Storage, id: &str) -> Result<Category, AppError> {
+    storage
+        .list_categories()?
+        .into_iter()
+        .find(|c| c.id == id)
+        .ok_or_else(|| AppError::not_found("category", id))
+}
+
+fn ensure_unique(
+    categories: &[Category],
+    name: &str,
+    except: Option<&str>,
+) -> Result<(), AppError> {
+    let lowered = name.to_lowercase();
+    if categories
+        .iter()
+        .any(|c| c.name.to_lowercase() == lowered && Some(c.id.as_str()) != except)
+    {
+        return Err(AppError::Invalid(format!(
+            "a category named {name:?} already exists"
+        )));
+    }
+    Ok(())
+}
+
+/// Adds a category at the end of the list.
+pub fn create(storage: &Storage, name: &str, color: Option<String>) -> Result<Category, AppError> {
+    let name = clean_name(name)?;
+    let categories = storage.list_categories()?;
+    ensure_unique(&categories, &name, None)?;
+    let category = Category {
+        id: generate_token(),
+        name,
+        color: color
+            .filter(|c| !c.trim().is_empty())
+            .unwrap_or_else(|| DEFAULT_COLOR.into()),
+        position: categories.iter().map(|c| c.position + 1).max().unwrap_or(0),
+    };
+    storage.insert_category(&category)?;
+    Ok(category)
+}
+
+/// Returns the updated category and how many habits a rename moved.
+pub fn update(
+    storage: &Storage,
+    id: &str,
+    name: Option<String>,
+    color: Option<String>,
+) -> Result<(Category, usize), AppError> {
+    let mut category = find(storage, id)?;
+    if let Some(name) = name {
+        let name = clean_name(&name)?;
+        let categories = storage.list_categories()?;
+        ensure_unique(&categories, &name, Some(id))?;
+        category.name = name;
+    }
+    if let Some(color) = color.filter(|c| !c.trim().is_empty()) {
+        category.color = color;
+    }
+    let moved = storage
+        .update_category(id, &category.name, &category.color)?
+        .ok_or_else(|| AppError::not_found("category", id))?;
+    Ok((category, moved))
+}
+
+/// Deletes a category; its habits move to "General". Returns how many moved.
+pub fn remove(storage: &Storage, id: &str) -> Result<usize, AppError> {
+    let categories = storage.list_categories()?;
+    let category = categories
+        .iter()
+        .find(|c| c.id == id)
+        .ok_or_else(|| AppError::not_found("category", id))?;
+    if category.name.eq_ignore_ascii_case(DEFAULT_CATEGORY) {
+        return Err(AppError::Invalid(format!(
+            "the {DEFAULT_CATEGORY} category can't be deleted"
+        )));
+    }
+    let fallback = Category {
+        id: generate_token(),
+        name: DEFAULT_CATEGORY.into(),
+        color: DEFAULT_COLOR.into(),
+        position: categories.iter().map(|c| c.position + 1).max().unwrap_or(0),
+    };
+    storage
+        .delete_category(id, &fallback)?
+        .ok_or_else(|| AppError::not_found("category", id))
+}
+
+/// Moves a habit into a category.
+pub fn assign(storage: &Storage, habit_id: &str, category_id: &str) -> Result<Habit, AppError> {
+    let category = find(storage, category_id)?;
+    if !storage.set_habit_category(habit_id, &category.name)? {
+        return Err(AppError::not_found("habit", habit_id));
+    }
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+/// Category order also drives the tray menu and stats, so reordering counts as a change
+/// even though no habit moved.
+fn changed(app: &AppHandle, habits_moved: bool) {
+    app.state::<EventBus>().data_changed();
+    if habits_moved {
+        crate::habits::notify_changed(app);
+    }
+}
+
+#[tauri::command]
+pub fn list_categories(storage: State<'_, Storage>) -> Result<Vec<Category>, AppError> {
+    Ok(storage.list_categories()?)
+}
+
+#[tauri::command]
+pub fn create_category(
+    app: AppHandle,
+    name: String,
+    color: Option<String>,
+) -> Result<Category, AppError> {
+    let category = create(&app.state::<Storage>(), &name, color)?;
+    changed(&app, false);
+    Ok(category)
+}
+
+/// Renaming rewrites the habits in the category.
+#[tauri::command]
+pub fn update_category(
+    app: AppHandle,
+    id: String,
+    name: Option<String>,
+    color: Option<String>,
+) -> Result<Category, AppError> {
+    let (category, moved) = update(&app.state::<Storage>(), &id, name, color)?;
+    changed(&app, moved > 0);
+    Ok(category)
+}
+
+/// Returns the categories in their new order.
+pub fn reorder(storage: &Storage, ids: &[String]) -> Result<Vec<Category>, AppError> {
+    storage.reorder_categories(ids)?;
+    Ok(storage.list_categories()?)
+}
+
+/// `ids` in the new order; categories left out keep their order after the listed ones.
+#[tauri::command]
+pub fn reorder_categories(app: AppHandle, ids: Vec<String>) -> Result<Vec<Category>, AppError> {
+    let categories = reorder(&app.state::<Storage>(), &ids)?;
+    changed(&app, false);
+    Ok(categories)
+}
+
+#[tauri::command]
+pub fn delete_category(app: AppHandle, id: String) -> Result<(), AppError> {
+    let moved = remove(&app.state::<Storage>(), &id)?;
+    changed(&app, moved > 0);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn assign_category(
+    app: AppHandle,
+    habit_id: String,
+    category_id: String,
+) -> Result<Habit, AppError> {
+    let habit = assign(&app.state::<Storage>(), &habit_id, &category_id)?;
+    changed(&app, true);
+    Ok(habit)
+}
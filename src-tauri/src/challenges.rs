@@ -0,0 +1,330 @@
+//! Daily challenges: one small, optional goal a day picked from the user's habits ("do
+//! Meditate before 08:30", "complete all Health habits today"), on while
+//! `settings.gamification.dailyChallenges` is set.
+//!
+//! The pick is seeded by the date, so every surface agrees on it and it doesn't change on
+//! a restart. The challenge is checked after every change and every minute; completing it
+//! is worth its `xp` on top of the regular XP (see `gamification.rs`). Whatever is still
+//! open at the day rollover has failed. Changes are emitted to the webview as
+//! `challenge:changed`.
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::{ChallengeKind, ChallengeStatus, DailyChallenge, Habit, HabitKind};
+use crate::schedule;
+use crate::settings::SettingsStore;
+use crate::stats::date_key;
+use crate::storage::Storage;
+use crate::time_windows;
+
+pub const CHALLENGE_CHANGED_EVENT: &str = "challenge:changed";
+/// How much more than the target an `Overachieve` challenge asks for.
+const OVERACHIEVE_FACTOR: f64 = 1.5;
+/// A `BeforeTime` deadline is this long after the habit's reminder, or noon without one.
+const DEADLINE_SLACK_MINUTES: i64 = 60;
+
+fn xp(kind: ChallengeKind) -> u32 {
+    match kind {
+        ChallengeKind::BeforeTime => 15,
+        ChallengeKind::Overachieve => 20,
+        ChallengeKind::AllInCategory => 25,
+        ChallengeKind::AllDone => 40,
+    }
+}
+
+fn eligible(habit: &Habit, date: NaiveDate) -> bool {
+    !habit.archived && habit.kind == HabitKind::Build && !habit.is_excused(&date_key(date))
+}
+
+fn deadline(habit: &Habit, date: NaiveDate) -> NaiveTime {
+    let noon = NaiveTime::from_hms_opt(12, 0, 0).unwrap_or_default();
+    let Some(reminder) = time_windows::reminder_time(habit, date)
+        .and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok())
+    else {
+        return noon;
+    };
+    let minutes = i64::from(reminder.num_seconds_from_midnight() / 60) + DEADLINE_SLACK_MINUTES;
+    let minutes = minutes.min(23 * 60 + 59) as u32;
+    NaiveTime::from_hms_opt(minutes / 60, minutes % 60, 0).unwrap_or(noon)
+}
+
+fn challenge(date: NaiveDate, kind: ChallengeKind, title: String) -> DailyChallenge {
+    DailyChallenge {
+        date: date_key(date),
+        kind,
+        title,
+        habit_id: None,
+        category: None,
+        deadline: None,
+        xp: xp(kind),
+        status: ChallengeStatus::Open,
+        resolved_at: None,
+    }
+}
+
+/// Every challenge `habits` allow on `now`'s day, in a stable order.
+fn candidates(habits: &[Habit], now: NaiveDateTime) -> Vec<DailyChallenge> {
+    let date = now.date();
+    let eligible: Vec<&Habit> = habits.iter().filter(|h| eligible(h, date)).collect();
+    let mut out = Vec::new();
+    for habit in &eligible {
+        let deadline = deadline(habit, date);
+        // Generated late in the day, a deadline that already passed would be a sure fail.
+        if deadline > now.time() {
+            out.push(DailyChallenge {
+                habit_id: Some(habit.id.clone()),
+                deadline: Some(deadline.format("%H:%M").to_string()),
+                ..challenge(
+                    date,
+                    ChallengeKind::BeforeTime,
+                    format!("Do {} before {}", habit.name, deadline.format("%H:%M")),
+                )
+            });
+        }
+        if let Some(target) = &habit.target {
+            out.push(DailyChallenge {
+                habit_id: Some(habit.id.clone()),
+                ..challenge(
+                    date,
+                    ChallengeKind::Overachieve,
+                    format!(
+                        "Log {} {} of {}",
+                        target.amount * OVERACHIEVE_FACTOR,
+                        target.unit,
+                        habit.name
+                    ),
+                )
+            });
+        }
+    }
+    let mut categories: Vec<&str> = eligible.iter().map(|h| h.category.as_str()).collect();
+    categories.sort_unstable();
+    for category in categories.iter().copied() {
+        let size = categories.iter().filter(|c| **c == category).count();
+        if size >= 2 && out.iter().all(|c| c.category.as_deref() != Some(category)) {
+            out.push(DailyChallenge {
+                category: Some(category.to_string()),
+                ..challenge(
+                    date,
+                    ChallengeKind::AllInCategory,
+                    format!("Complete all {category} habits today"),
+                )
+            });
+        }
+    }
+    if eligible.len() >= 2 {
+        out.push(challenge(
+            date,
+            ChallengeKind::AllDone,
+            "Finish everything due today".into(),
+        ));
+    }
+    out
+}
+
+/// The challenge for `now`'s day, or `None` without eligible habits.
+pub fn generate(habits: &[Habit], now: NaiveDateTime) -> Option<DailyChallenge> {
+    let candidates = candidates(habits, now);
+    if candidates.is_empty() {
+        return None;
+    }
+    // Knuth's multiplicative hash spreads consecutive days over the candidates.
+    let seed = u64::from(now.date().num_days_from_ce().unsigned_abs()).wrapping_mul(2_654_435_761);
+    let pick = (seed >> 16) as usize % candidates.len();
+    candidates.into_iter().nth(pick)
+}
+
+/// Everything due among `habits` is done, and at least one was done today.
+fn all_done<'a>(habits: impl Iterator<Item = &'a Habit> + Clone, date: NaiveDate) -> bool {
+    let key = date_key(date);
+    habits.clone().any(|h| h.is_done_on(&key))
+        && habits.into_iter().all(|h| !schedule::is_due(h, date))
+}
+
+/// What `challenge` comes to at `now`, if it's settled yet.
+fn verdict(
+    challenge: &DailyChallenge,
+    habits: &[Habit],
+    now: NaiveDateTime,
+) -> Option<ChallengeStatus> {
+    let date = now.date();
+    let key = date_key(date);
+    let habit = || {
+        let id = challenge.habit_id.as_deref()?;
+        habits.iter().find(|h| h.id == id)
+    };
+    let met = match challenge.kind {
+        ChallengeKind::BeforeTime => {
+            let deadline = challenge
+                .deadline
+                .as_deref()
+                .and_then(|t| NaiveTime::parse_from_str(t, "%H:%M").ok())?;
+            let done = habit().is_some_and(|h| h.is_done_on(&key));
+            if !done && now.time() >= deadline {
+                return Some(ChallengeStatus::Failed);
+            }
+            done
+        }
+        ChallengeKind::Overachieve => habit().is_some_and(|h| {
+            h.target
+                .as_ref()
+                .is_some_and(|t| h.value_on(&key) >= t.amount * OVERACHIEVE_FACTOR)
+        }),
+        ChallengeKind::AllInCategory => {
+            let category = challenge.category.as_deref()?;
+            all_done(
+                habits
+                    .iter()
+                    .filter(|h| eligible(h, date) && h.category == category),
+                date,
+            )
+        }
+        ChallengeKind::AllDone => all_done(habits.iter().filter(|h| eligible(h, date)), date),
+    };
+    met.then_some(ChallengeStatus::Completed)
+}
+
+fn enabled(settings: &SettingsStore) -> bool {
+    settings.get().gamification.daily_challenges
+}
+
+/// Today's challenge, generating it first if needed. `None` while challenges are off or
+/// there's nothing to pick from.
+pub fn today(
+    storage: &Storage,
+    settings: &SettingsStore,
+) -> Result<Option<DailyChallenge>, String> {
+    if !enabled(settings) {
+        return Ok(None);
+    }
+    let now = time_windows::now();
+    let key = date_key(now.date());
+    if let Some(challenge) = storage.get_challenge(&key).map_err(|e| e.to_string())? {
+        return Ok(Some(challenge));
+    }
+    let habits = storage.list_habits().map_err(|e| e.to_string())?;
+    let Some(challenge) = generate(&habits, now) else {
+        return Ok(None);
+    };
+    storage
+        .insert_challenge(&challenge)
+        .map_err(|e| e.to_string())?;
+    storage.get_challenge(&key).map_err(|e| e.to_string())
+}
+
+/// Fails challenges left open on earlier days, then checks today's. Returns today's if its
+/// status changed.
+pub fn evaluate(
+    storage: &Storage,
+    settings: &SettingsStore,
+) -> Result<Option<DailyChallenge>, String> {
+    let at = chrono::Utc::now().to_rfc3339();
+    for date in storage
+        .open_challenges_before(&date_key(time_windows::now().date()))
+        .map_err(|e| e.to_string())?
+    {
+        storage
+            .resolve_challenge(&date, ChallengeStatus::Failed, &at)
+            .map_err(|e| e.to_string())?;
+    }
+    let Some(challenge) = today(storage, settings)? else {
+        return Ok(None);
+    };
+    if challenge.status != ChallengeStatus::Open {
+        return Ok(None);
+    }
+    let habits = storage.list_habits().map_err(|e| e.to_string())?;
+    let Some(status) = verdict(&challenge, &habits, time_windows::now()) else {
+        return Ok(None);
+    };
+    if !storage
+        .resolve_challenge(&challenge.date, status, &at)
+        .map_err(|e| e.to_string())?
+    {
+        return Ok(None);
+    }
+    storage
+        .get_challenge(&challenge.date)
+        .map_err(|e| e.to_string())
+}
+
+/// Skips today's open challenge. `Ok(None)` while there is none.
+pub fn skip(storage: &Storage, settings: &SettingsStore) -> Result<Option<DailyChallenge>, String> {
+    let Some(challenge) = today(storage, settings)? else {
+        return Ok(None);
+    };
+    let at = chrono::Utc::now().to_rfc3339();
+    if !storage
+        .resolve_challenge(&challenge.date, ChallengeStatus::Skipped, &at)
+        .map_err(|e| e.to_string())?
+    {
+        return Err("today's challenge is already settled".into());
+    }
+    storage
+        .get_challenge(&challenge.date)
+        .map_err(|e| e.to_string())
+}
+
+/// Runs on data changes, every minute and at the rollover.
+pub fn check(app: &AppHandle) {
+    let changed = match evaluate(&app.state::<Storage>(), &app.state::<SettingsStore>()) {
+        Ok(c) => c,
+        Err(e) => {
+            log::warn!("challenges: check failed: {e}");
+            return;
+        }
+    };
+    let Some(challenge) = changed else {
+        return;
+    };
+    if challenge.status == ChallengeStatus::Completed {
+        // Titles name the habit.
+        let body = if crate::privacy::hide_names(app) {
+            crate::i18n::t_args("challenge-hidden", &[("xp", challenge.xp.into())])
+        } else {
+            format!("{} (+{} XP)", challenge.title, challenge.xp)
+        };
+        crate::reminders::notify(app, &crate::i18n::t("challenge-title"), &body);
+        // Bonus XP counts toward the level.
+        app.state::<EventBus>().data_changed();
+    }
+    let _ = app.emit(CHALLENGE_CHANGED_EVENT, &challenge);
+}
+
+/// The new day's challenge is generated and the old one settled as soon as the day turns.
+pub fn on_day_rollover(app: &AppHandle) {
+    check(app);
+    if let Ok(Some(challenge)) = today(&app.state::<Storage>(), &app.state::<SettingsStore>()) {
+        let _ = app.emit(CHALLENGE_CHANGED_EVENT, &challenge);
+    }
+}
+
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    let mut data = app.state::<EventBus>().watch_data();
+    tauri::async_runtime::spawn(async move {
+        check(&app);
+        while data.changed().await.is_ok() {
+            check(&app);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_daily_challenge(app: AppHandle) -> Result<Option<DailyChallenge>, AppError> {
+    Ok(today(
+        &app.state::<Storage>(),
+        &app.state::<SettingsStore>(),
+    )?)
+}
+
+/// Passes on today's challenge; it then neither awards XP nor counts as failed.
+#[tauri::command]
+pub fn skip_daily_challenge(app: AppHandle) -> Result<Option<DailyChallenge>, AppError> {
+    let challenge = skip(&app.state::<Storage>(), &app.state::<SettingsStore>())?;
+    let _ = app.emit(CHALLENGE_CHANGED_EVENT, &challenge);
+    Ok(challenge)
+}
@@ -0,0 +1,166 @@
+//! Checklist habits: a habit made of steps ("Morning routine": make bed, stretch, journal).
+//!
+//! Items live under their habit in the database and are checked off per day. The habit
+//! completes itself once every item is done that day and un-completes when one is
+//! unchecked; checking the habit itself off (from the webview, tray, API, ...) checks off
+//! all of its items.
+
+use chrono::NaiveDate;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::{ChecklistItem, Habit};
+use crate::revisions;
+use crate::settings::generate_token;
+use crate::stats::{date_key, day_arg, today};
+use crate::storage::{Storage, Tx};
+
+fn clean_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Invalid("item name is required".into()));
+    }
+    Ok(name.to_string())
+}
+
+fn load(storage: &Storage, habit_id: &str) -> Result<Habit, AppError> {
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+/// Appends an item and returns the updated habit.
+pub fn add_item(storage: &Storage, habit_id: &str, name: &str) -> Result<Habit, AppError> {
+    let item = ChecklistItem {
+        id: generate_token(),
+        name: clean_name(name)?,
+        done: Default::default(),
+    };
+    if !storage.insert_checklist_item(habit_id, &item)? {
+        return Err(AppError::not_found("habit", habit_id));
+    }
+    load(storage, habit_id)
+}
+
+pub fn rename_item(storage: &Storage, item_id: &str, name: &str) -> Result<Habit, AppError> {
+    let habit_id = storage
+        .rename_checklist_item(item_id, &clean_name(name)?)?
+        .ok_or_else(|| AppError::not_found("checklist item", item_id))?;
+    load(storage, &habit_id)
+}
+
+pub fn remove_item(storage: &Storage, item_id: &str) -> Result<Habit, AppError> {
+    let habit_id = storage
+        .delete_checklist_item(item_id)?
+        .ok_or_else(|| AppError::not_found("checklist item", item_id))?;
+    load(storage, &habit_id)
+}
+
+pub fn reorder(storage: &Storage, habit_id: &str, item_ids: &[String]) -> Result<Habit, AppError> {
+    storage.reorder_checklist(habit_id, item_ids)?;
+    load(storage, habit_id)
+}
+
+/// Checks or unchecks an item for `date`. Returns the updated habit and whether this
+/// completed the habit for the day.
+pub fn set_item_done(
+    tx: &Tx<'_>,
+    item_id: &str,
+    date: NaiveDate,
+    done: bool,
+) -> Result<(Habit, bool), AppError> {
+    let key = date_key(date);
+    let (habit_id, changed) = tx
+        .set_checklist_item_done(item_id, &key, done)?
+        .ok_or_else(|| AppError::not_found("checklist item", item_id))?;
+    let habit = tx
+        .get_habit(&habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", &habit_id))?;
+    let completed = changed && habit.is_done_on(&key);
+    Ok((habit, completed))
+}
+
+fn changed(app: &AppHandle) {
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(app);
+}
+
+#[tauri::command]
+pub fn add_checklist_item(
+    app: AppHandle,
+    habit_id: String,
+    name: String,
+) -> Result<Habit, AppError> {
+    let habit = add_item(&app.state::<Storage>(), &habit_id, &name)?;
+    changed(&app);
+    Ok(habit)
+}
+
+#[tauri::command]
+pub fn rename_checklist_item(
+    app: AppHandle,
+    item_id: String,
+    name: String,
+) -> Result<Habit, AppError> {
+    let habit = rename_item(&app.state::<Storage>(), &item_id, &name)?;
+    changed(&app);
+    Ok(habit)
+}
+
+#[tauri::command]
+pub fn remove_checklist_item(app: AppHandle, item_id: String) -> Result<Habit, AppError> {
+    let habit = remove_item(&app.state::<Storage>(), &item_id)?;
+    changed(&app);
+    Ok(habit)
+}
+
+/// `item_ids` in the new order.
+#[tauri::command]
+pub fn reorder_checklist(
+    app: AppHandle,
+    habit_id: String,
+    item_ids: Vec<String>,
+) -> Result<Habit, AppError> {
+    let habit = reorder(&app.state::<Storage>(), &habit_id, &item_ids)?;
+    changed(&app);
+    Ok(habit)
+}
+
+/// [`set_item_done`] if the item's day is still on `expected` (see `revisions.rs`).
+pub fn set_item_done_checked(
+    storage: &Storage,
+    item_id: &str,
+    date: NaiveDate,
+    done: bool,
+    expected: Option<u64>,
+) -> Result<(Habit, bool), AppError> {
+    let habit_id = storage
+        .checklist_item_habit(item_id)?
+        .ok_or_else(|| AppError::not_found("checklist item", item_id))?;
+    revisions::check_day(storage, &habit_id, date, expected, |tx| {
+        set_item_done(tx, item_id, date, done)
+    })
+}
+
+/// Checks an item off (or back on) for `date`, default today; the habit completes once
+/// every item is done.
+#[tauri::command]
+pub fn set_checklist_item_done(
+    app: AppHandle,
+    item_id: String,
+    done: bool,
+    date: Option<String>,
+    expected_revision: Option<u64>,
+) -> Result<Habit, AppError> {
+    let date = day_arg(date.as_deref())?;
+    let storage = app.state::<Storage>();
+    let (habit, completed) =
+        set_item_done_checked(&storage, &item_id, date, done, expected_revision)?;
+    let bus = app.state::<EventBus>();
+    if completed {
+        crate::habits::publish_completion(&storage, &bus, &habit, &date_key(date), today());
+    }
+    changed(&app);
+    Ok(habit)
+}
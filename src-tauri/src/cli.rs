@@ -0,0 +1,287 @@
+//! Headless command-line mode, used when the binary is started with a known subcommand:
+//!
+//! ```text
+//! habit-tracker list [--today] [--all] [--json]
+//! habit-tracker complete <habit> [--date yyyy-MM-dd]
+//! habit-tracker undo <habit> [--date yyyy-MM-dd]
+//! habit-tracker stats [--all] [--json]
+//! habit-tracker --rpc
+//! ```
+//!
+//! `<habit>` is an id or a (case-insensitive) name. Works directly on the app's database,
+//! so a running window picks changes up on its next scheduler tick. With a PIN set, the PIN
+//! has to be in `HABITFLOW_PIN`.
+
+use std::path::PathBuf;
+
+use crate::error::AppError;
+use crate::model::Habit;
+use crate::settings::{SettingsStore, SETTINGS_FILE_NAME};
+use crate::stats::{self, date_key, parse_date, today};
+use crate::storage::{Storage, DB_FILE_NAME};
+
+/// Must match `identifier` in `tauri.conf.json`; Tauri derives the app data dir from it.
+const APP_IDENTIFIER: &str = "com.sushil930.habitflow";
+
+const USAGE: &str = "\
+Usage:
+  habit-tracker list [--today] [--all] [--json]
+  habit-tracker complete <habit> [--date yyyy-MM-dd]
+  habit-tracker undo <habit> [--date yyyy-MM-dd]
+  habit-tracker stats [--all] [--json]
+  habit-tracker --rpc          JSON-RPC 2.0 over stdin/stdout, one message per line";
+
+/// Runs a CLI command if `args` (without the program name) starts with one.
+/// Returns the process exit code, or `None` to continue starting the GUI.
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = args.first()?.as_str();
+    if !matches!(
+        command,
+        "list" | "complete" | "undo" | "stats" | "help" | "--help" | "-h" | "--rpc"
+    ) {
+        return None;
+    }
+    attach_console();
+
+    if command == "--rpc" {
+        return Some(crate::rpc::serve());
+    }
+
+    if matches!(command, "help" | "--help" | "-h") {
+        println!("{USAGE}");
+        return Some(0);
+    }
+
+    let storage = match open_storage() {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return Some(1);
+        }
+    };
+    if let Err(e) = unlock(&storage) {
+        eprintln!("error: {e}");
+        return Some(1);
+    }
+
+    let rest = &args[1..];
+    let result = match command {
+        "list" => list(&storage, rest),
+        "complete" => set_done(&storage, rest, true),
+        "undo" => set_done(&storage, rest, false),
+        "stats" => print_stats(&storage, rest),
+        _ => unreachable!(),
+    };
+
+    Some(match result {
+        Ok(()) => 0,
+        Err(CliError::Usage(msg)) => {
+            eprintln!("error: {msg}\n\n{USAGE}");
+            2
+        }
+        Err(CliError::Failed(msg)) => {
+            eprintln!("error: {msg}");
+            1
+        }
+    })
+}
+
+enum CliError {
+    Usage(String),
+    Failed(String),
+}
+
+impl From<rusqlite::Error> for CliError {
+    fn from(e: rusqlite::Error) -> Self {
+        CliError::Failed(e.to_string())
+    }
+}
+
+/// Where Tauri's `app_data_dir()` points for this app.
+pub(crate) fn app_data_dir() -> Result<PathBuf, String> {
+    dirs::data_dir()
+        .map(|d| d.join(APP_IDENTIFIER))
+        .ok_or_else(|| "could not determine the app data directory".into())
+}
+
+pub(crate) fn open_storage() -> Result<Storage, String> {
+    let path = app_data_dir()?.join(DB_FILE_NAME);
+    if !path.exists() {
+        return Err(format!(
+            "no data found at {} (launch HabitFlow once first)",
+            path.display()
+        ));
+    }
+    Storage::open(&path).map_err(|e| e.to_string())
+}
+
+/// With a PIN set, checks the one in `HABITFLOW_PIN`. There's no prompt, so it doesn't
+/// end up echoed in the terminal.
+fn unlock(storage: &Storage) -> Result<(), AppError> {
+    if !crate::lock::pin_required(storage)? {
+        return Ok(());
+    }
+    let pin = std::env::var(crate::lock::PIN_ENV).map_err(|_| {
+        AppError::Forbidden(format!(
+            "HabitFlow is locked; set {} to the PIN",
+            crate::lock::PIN_ENV
+        ))
+    })?;
+    crate::lock::unlock_headless(storage, &pin)
+}
+
+struct Flags<'a> {
+    positional: Vec<&'a str>,
+    json: bool,
+    today: bool,
+    all: bool,
+    date: Option<&'a str>,
+}
+
+fn parse_flags(args: &[String]) -> Result<Flags<'_>, CliError> {
+    let mut flags = Flags {
+        positional: Vec::new(),
+        json: false,
+        today: false,
+        all: false,
+        date: None,
+    };
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--json" => flags.json = true,
+            "--today" => flags.today = true,
+            "--all" => flags.all = true,
+            "--date" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| CliError::Usage("--date needs a value".into()))?;
+                flags.date = Some(value);
+            }
+            other if other.starts_with("--") => {
+                return Err(CliError::Usage(format!("unknown option {other}")))
+            }
+            other => flags.positional.push(other),
+        }
+    }
+    Ok(flags)
+}
+
+fn list(storage: &Storage, args: &[String]) -> Result<(), CliError> {
+    let flags = parse_flags(args)?;
+    let habits: Vec<Habit> = storage
+        .list_habits()?
+        .into_iter()
+        .filter(|h| flags.all || !h.archived)
+        .collect();
+    let today = date_key(today());
+
+    if flags.json {
+        print_json(&habits)?;
+        return Ok(());
+    }
+    if habits.is_empty() {
+        println!("No habits yet.");
+        return Ok(());
+    }
+    for h in &habits {
+        let archived = if h.archived { " (archived)" } else { "" };
+        if flags.today {
+            let mark = if h.is_done_on(&today) { "x" } else { " " };
+            println!("[{mark}] {}{archived}", h.name);
+        } else {
+            println!("{}  {} · {}{archived}", h.id, h.name, h.category);
+        }
+    }
+    Ok(())
+}
+
+fn set_done(storage: &Storage, args: &[String], done: bool) -> Result<(), CliError> {
+    let flags = parse_flags(args)?;
+    let query = match flags.positional.as_slice() {
+        [] => return Err(CliError::Usage("missing habit name or id".into())),
+        parts => parts.join(" "),
+    };
+    let date = match flags.date {
+        Some(d) => parse_date(d)
+            .ok_or_else(|| CliError::Usage(format!("invalid date {d:?}, expected yyyy-MM-dd")))?,
+        None => today(),
+    };
+
+    let habits = storage.list_habits()?;
+    let habit = crate::habits::find(&habits, &query)
+        .ok_or_else(|| CliError::Failed(format!("no habit matches {query:?}")))?;
+
+    let rules = SettingsStore::load(
+        app_data_dir()
+            .map_err(CliError::Failed)?
+            .join(SETTINGS_FILE_NAME),
+    )
+    .get()
+    .backfill;
+    let changed = storage
+        .transaction(|tx| crate::habits::record_completion(tx, &rules, &habit.id, date, done))
+        .map_err(|e| CliError::Failed(e.to_string()))?
+        .is_some_and(|(_, changed)| changed);
+    let key = date_key(date);
+    let verb = if done { "completed" } else { "not completed" };
+    if changed {
+        println!("{} marked {verb} for {key}", habit.name);
+    } else {
+        println!("{} was already {verb} for {key}", habit.name);
+    }
+    Ok(())
+}
+
+fn print_stats(storage: &Storage, args: &[String]) -> Result<(), CliError> {
+    let flags = parse_flags(args)?;
+    let summary = stats::summarize(
+        &storage.list_habits()?,
+        &storage.list_categories()?,
+        today(),
+    );
+
+    if flags.json {
+        return print_json(&summary);
+    }
+    println!(
+        "{}: {}/{} done today",
+        summary.date, summary.completed_today, summary.active_habits
+    );
+    for s in summary.habits.iter().filter(|s| flags.all || !s.archived) {
+        println!(
+            "  {:<24} streak {:>3}  best {:>3}  30d {:>3}%",
+            s.name, s.current_streak, s.longest_streak, s.completion_rate_30d
+        );
+    }
+    if summary.categories.len() > 1 {
+        println!();
+        for c in &summary.categories {
+            println!(
+                "  {:<24} {}/{} done today, 30d {}%",
+                c.category, c.completed_today, c.active_habits, c.completion_rate_30d
+            );
+        }
+    }
+    Ok(())
+}
+
+fn print_json<T: serde::Serialize>(value: &T) -> Result<(), CliError> {
+    let text = serde_json::to_string_pretty(value).map_err(|e| CliError::Failed(e.to_string()))?;
+    println!("{text}");
+    Ok(())
+}
+
+/// Release builds use the Windows GUI subsystem, so stdout goes nowhere unless we attach
+/// to the console of the shell that launched us.
+#[cfg(windows)]
+fn attach_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // SAFETY: plain Win32 call with no pointers; failure just means there's no parent console.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
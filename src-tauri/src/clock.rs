@@ -0,0 +1,93 @@
+//! What time it is, for everything that decides "today": streaks, time windows, the
+//! scheduler's minute, export schedules and snapshot labels. The database asks it too: the
+//! triggers that record when a row changed for libSQL sync call it as `habitflow_now()`.
+//!
+//! The app always runs on [`SystemClock`]. In tests, `harness` swaps in a `ManualClock`
+//! for the thread it drives commands on, so a test can stand on either side of midnight,
+//! a DST change or a leap day, or move timezones, without waiting for one.
+
+use std::cell::RefCell;
+use std::sync::Arc;
+#[cfg(test)]
+use std::sync::Mutex;
+
+#[cfg(test)]
+use chrono::Duration;
+use chrono::{DateTime, FixedOffset, Local};
+
+pub trait Clock: Send + Sync {
+    /// The current instant, in the local offset at that instant.
+    fn now(&self) -> DateTime<FixedOffset>;
+}
+
+/// The system's clock and timezone.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        Local::now().fixed_offset()
+    }
+}
+
+/// A clock that only moves when told to.
+#[cfg(test)]
+pub struct ManualClock {
+    now: Mutex<DateTime<FixedOffset>>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new(now: DateTime<FixedOffset>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, DateTime<FixedOffset>> {
+        self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn set(&self, now: DateTime<FixedOffset>) {
+        *self.lock() = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.lock();
+        *now += by;
+    }
+
+    /// Keeps the instant but reads it in `offset`: a DST change, or a flight.
+    pub fn set_offset(&self, offset: FixedOffset) {
+        let mut now = self.lock();
+        *now = now.with_timezone(&offset);
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<FixedOffset> {
+        *self.lock()
+    }
+}
+
+thread_local! {
+    static OVERRIDE: RefCell<Option<Arc<dyn Clock>>> = const { RefCell::new(None) };
+}
+
+/// Uses `clock` on this thread until [`reset`].
+#[cfg(test)]
+pub fn install(clock: Arc<dyn Clock>) {
+    OVERRIDE.with(|o| *o.borrow_mut() = Some(clock));
+}
+
+/// Back to the system clock on this thread.
+#[cfg(test)]
+pub fn reset() {
+    OVERRIDE.with(|o| *o.borrow_mut() = None);
+}
+
+pub fn now() -> DateTime<FixedOffset> {
+    OVERRIDE
+        .with(|o| o.borrow().as_ref().map(|c| c.now()))
+        .unwrap_or_else(|| SystemClock.now())
+}
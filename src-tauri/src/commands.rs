@@ -0,0 +1,187 @@
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::Habit;
+use crate::schedule::DueHabit;
+use crate::settings::{Settings, SettingsStore};
+use crate::stats::day_arg;
+use crate::storage::{CheckIn, Storage};
+
+/// Called by the webview after every local change so the backend copy stays current.
+/// Queued, and written with the changes that follow it (see `write_queue.rs`).
+#[tauri::command]
+pub fn sync_habits(app: AppHandle, habits: Vec<Habit>) {
+    crate::write_queue::queue_habits(&app, habits);
+}
+
+#[tauri::command]
+pub fn list_habits(storage: State<'_, Storage>) -> Result<Vec<Habit>, AppError> {
+    Ok(storage.list_habits()?)
+}
+
+/// Logs progress on a quantified habit; `date` defaults to today. `tags` label just this
+/// check-in. `expected_revision` is the day's, as in `revisions.rs`.
+#[tauri::command]
+pub fn log_habit_value(
+    app: AppHandle,
+    habit_id: String,
+    value: f64,
+    date: Option<String>,
+    tags: Option<Vec<String>>,
+    expected_revision: Option<u64>,
+) -> Result<Habit, AppError> {
+    let date = day_arg(date.as_deref())?;
+    let logged = crate::habits::record_value_checked(
+        &app.state::<Storage>(),
+        &habit_id,
+        date,
+        value,
+        tags.unwrap_or_default(),
+        expected_revision,
+    )?;
+    Ok(crate::habits::value_logged(&app, logged, date))
+}
+
+/// Records a slip-up on an avoidance habit; `date` defaults to today.
+#[tauri::command]
+pub fn log_lapse(
+    app: AppHandle,
+    habit_id: String,
+    date: Option<String>,
+    note: Option<String>,
+    expected_revision: Option<u64>,
+) -> Result<Habit, AppError> {
+    let date = day_arg(date.as_deref())?;
+    let lapsed = crate::habits::record_lapse_checked(
+        &app.state::<Storage>(),
+        &habit_id,
+        date,
+        note,
+        expected_revision,
+    )?;
+    Ok(crate::habits::lapse_logged(&app, lapsed))
+}
+
+#[tauri::command]
+pub fn list_check_ins(
+    storage: State<'_, Storage>,
+    habit_id: String,
+    date: Option<String>,
+) -> Result<Vec<CheckIn>, AppError> {
+    crate::habits::check_ins(&storage, &habit_id, date.as_deref())
+}
+
+/// Takes a habit out of due lists, reminders and the tray while keeping its history.
+#[tauri::command]
+pub fn archive_habit(
+    app: AppHandle,
+    habit_id: String,
+    expected_revision: Option<u64>,
+) -> Result<Habit, AppError> {
+    archive(&app, &habit_id, true, expected_revision)
+}
+
+#[tauri::command]
+pub fn unarchive_habit(
+    app: AppHandle,
+    habit_id: String,
+    expected_revision: Option<u64>,
+) -> Result<Habit, AppError> {
+    archive(&app, &habit_id, false, expected_revision)
+}
+
+fn archive(
+    app: &AppHandle,
+    habit_id: &str,
+    archived: bool,
+    expected_revision: Option<u64>,
+) -> Result<Habit, AppError> {
+    let habit = crate::habits::set_archived_checked(
+        &app.state::<Storage>(),
+        habit_id,
+        archived,
+        expected_revision,
+    )?;
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(app);
+    Ok(habit)
+}
+
+/// Habits still due on `date` (default today) under their schedules.
+#[tauri::command]
+pub fn list_due_habits(
+    storage: State<'_, Storage>,
+    date: Option<String>,
+) -> Result<Vec<DueHabit>, AppError> {
+    crate::schedule::due(&storage, day_arg(date.as_deref())?)
+}
+
+#[tauri::command]
+pub fn get_settings(settings: State<'_, SettingsStore>) -> Settings {
+    settings.get()
+}
+
+/// Checks and saves `new_settings` (see [`SettingsStore::replace_with`]), then applies the
+/// feature flags and language. Everything else that reads them needs the app.
+pub(crate) fn save_settings(
+    settings: &SettingsStore,
+    new_settings: Settings,
+    then: impl FnOnce(&mut Settings),
+) -> Result<Settings, AppError> {
+    crate::blocking::validate(&new_settings.screen_time)?;
+    let updated = settings.replace_with(new_settings, then)?;
+    crate::features::apply(settings);
+    crate::i18n::apply(settings);
+    Ok(updated)
+}
+
+#[tauri::command]
+pub fn update_settings(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    new_settings: Settings,
+) -> Result<Settings, AppError> {
+    // Export folders only change to ones picked with `pick_folder`.
+    let current = settings.get();
+    let quit_dir = crate::files::kept_folder(
+        &app,
+        &current.quit_export.directory,
+        &new_settings.quit_export.directory,
+    );
+    let job_dirs: Vec<(String, String)> = new_settings
+        .export_jobs
+        .iter()
+        .filter_map(|job| {
+            let old = current.export_jobs.iter().find(|j| j.id == job.id)?;
+            let dir = crate::files::kept_folder(&app, &old.directory, &job.directory);
+            Some((job.id.clone(), dir))
+        })
+        .collect();
+    let updated = save_settings(&settings, new_settings, |s| {
+        s.quit_export.directory = quit_dir;
+        for job in &mut s.export_jobs {
+            if let Some((_, dir)) = job_dirs.iter().find(|(id, _)| *id == job.id) {
+                job.directory = dir.clone();
+            }
+        }
+    })?;
+    crate::calendar::apply(&app);
+    crate::api::apply(&app);
+    crate::mqtt::apply(&app);
+    crate::tray::refresh(&app);
+    crate::widgets::refresh(&app);
+    crate::mobile_reminders::sync_logged(&app);
+    crate::redact::refresh_secrets(&app);
+    crate::redact::refresh_habits(&app);
+    crate::sync_queue::wake(&app);
+    crate::replica::wake(&app);
+    crate::network::check_now(&app);
+    crate::telemetry::apply(&app);
+    Ok(updated)
+}
+
+#[tauri::command]
+pub fn regenerate_api_token(settings: State<'_, SettingsStore>) -> Result<String, AppError> {
+    Ok(settings.regenerate_api_token()?)
+}
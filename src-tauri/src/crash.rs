@@ -0,0 +1,457 @@
+//! Crash reports, kept on this device until you choose to send them.
+//!
+//! - A panic hook writes a report for every panic: the message, where it happened, the
+//!   thread and a backtrace. The default hook still runs after it.
+//! - A native crash (a segfault or an abort on Unix, an unhandled exception on Windows)
+//!   can't safely do much, so its handler only writes the signal or exception code to
+//!   `crashes/native.crash`. That's made into a report on the next launch.
+//! - Everything in a report goes through [`crate::redact`] first, so tokens, home paths
+//!   and, in privacy mode, habit names stay out. Reports never include habit data.
+//!
+//! On the launch after a crash, a dialog asks whether to send the new reports. Nothing is
+//! ever sent without a yes there or a `submit_crash_report` call, and builds without
+//! `HABITFLOW_CRASH_REPORT_URL` set at compile time have nowhere to send them and don't
+//! ask. Only the newest [`MAX_REPORTS`] are kept.
+
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons};
+
+use crate::error::AppError;
+use crate::i18n::{t, t_args};
+use crate::redact::redact;
+
+pub const CRASH_DIR: &str = "crashes";
+const NATIVE_MARKER: &str = "native.crash";
+pub const MAX_REPORTS: usize = 20;
+/// Where reports are sent, set when building a release.
+const REPORT_URL: Option<&str> = option_env!("HABITFLOW_CRASH_REPORT_URL");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CrashKind {
+    Panic,
+    Native,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReportStatus {
+    /// Not asked about yet.
+    New,
+    /// Kept, but "Don't Send" was chosen.
+    Dismissed,
+    Submitted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CrashReport {
+    pub id: String,
+    pub kind: CrashKind,
+    pub occurred_at: String,
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thread: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backtrace: Option<String>,
+    pub status: ReportStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub submitted_at: Option<String>,
+}
+
+struct Context {
+    dir: PathBuf,
+    version: String,
+}
+
+static CONTEXT: OnceLock<Context> = OnceLock::new();
+
+fn dir() -> Result<&'static Path, AppError> {
+    CONTEXT
+        .get()
+        .map(|c| c.dir.as_path())
+        .ok_or_else(|| AppError::Failed("crash reporting isn't set up".into()))
+}
+
+fn new_report(kind: CrashKind, occurred_at: String, message: String) -> CrashReport {
+    CrashReport {
+        id: crate::settings::generate_token()[..16].to_string(),
+        kind,
+        occurred_at,
+        app_version: CONTEXT.get().map(|c| c.version.clone()).unwrap_or_default(),
+        os: std::env::consts::OS.into(),
+        arch: std::env::consts::ARCH.into(),
+        message: redact(&message),
+        location: None,
+        thread: None,
+        backtrace: None,
+        status: ReportStatus::New,
+        submitted_at: None,
+    }
+}
+
+fn save(dir: &Path, report: &CrashReport) -> Result<(), AppError> {
+    let json = serde_json::to_vec_pretty(report).map_err(|e| AppError::Failed(e.to_string()))?;
+    std::fs::write(dir.join(format!("{}.json", report.id)), json)?;
+    Ok(())
+}
+
+/// Starts recording panics and native crashes into `<data_dir>/crashes`, and turns what a
+/// native crash left last time into a report. Called once, as early in setup as the data
+/// dir is known.
+pub fn install(data_dir: &Path, version: &str) {
+    let dir = data_dir.join(CRASH_DIR);
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        log::warn!("crash: can't create the reports folder: {e}");
+        return;
+    }
+    if CONTEXT
+        .set(Context {
+            dir: dir.clone(),
+            version: version.to_string(),
+        })
+        .is_err()
+    {
+        return;
+    }
+    collect_native(&dir);
+    prune(&dir);
+
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        let payload = info.payload();
+        let message = payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "panic".into());
+        let location = info
+            .location()
+            .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+        record_panic(message, location);
+        previous(info);
+    }));
+    native::install(&dir.join(NATIVE_MARKER));
+}
+
+fn record_panic(message: String, location: Option<String>) {
+    let Some(context) = CONTEXT.get() else {
+        return;
+    };
+    let mut report = new_report(CrashKind::Panic, chrono::Utc::now().to_rfc3339(), message);
+    report.location = location;
+    report.thread = std::thread::current().name().map(str::to_string);
+    report.backtrace = Some(redact(
+        &std::backtrace::Backtrace::force_capture().to_string(),
+    ));
+    // Nowhere to log to from here; the default hook prints the panic anyway.
+    let _ = save(&context.dir, &report);
+}
+
+/// Makes `native.crash`, if the last run left one, into a report.
+fn collect_native(dir: &Path) {
+    let marker = dir.join(NATIVE_MARKER);
+    let Ok(text) = std::fs::read_to_string(&marker) else {
+        return;
+    };
+    let occurred_at = std::fs::metadata(&marker)
+        .and_then(|m| m.modified())
+        .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+        .unwrap_or_else(|_| chrono::Utc::now().to_rfc3339());
+    let report = new_report(
+        CrashKind::Native,
+        occurred_at,
+        native::describe(text.trim()),
+    );
+    if let Err(e) = save(dir, &report) {
+        log::warn!("crash: failed to save the native crash report: {e}");
+        return;
+    }
+    let _ = std::fs::remove_file(&marker);
+}
+
+/// Newest first.
+fn load_all(dir: &Path) -> Vec<CrashReport> {
+    let mut reports: Vec<CrashReport> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|e| e.path().extension().is_some_and(|x| x == "json"))
+        .filter_map(|e| std::fs::read(e.path()).ok())
+        .filter_map(|bytes| serde_json::from_slice(&bytes).ok())
+        .collect();
+    reports.sort_by(|a, b| b.occurred_at.cmp(&a.occurred_at));
+    reports
+}
+
+fn prune(dir: &Path) {
+    for report in load_all(dir).iter().skip(MAX_REPORTS) {
+        let _ = std::fs::remove_file(dir.join(format!("{}.json", report.id)));
+    }
+}
+
+/// Only ids this module made, so `id` can't point outside the folder.
+fn path(id: &str) -> Result<PathBuf, AppError> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::not_found("crash report", id));
+    }
+    let path = dir()?.join(format!("{id}.json"));
+    if !path.exists() {
+        return Err(AppError::not_found("crash report", id));
+    }
+    Ok(path)
+}
+
+fn load(id: &str) -> Result<CrashReport, AppError> {
+    let bytes = std::fs::read(path(id)?)?;
+    serde_json::from_slice(&bytes).map_err(|e| AppError::Failed(e.to_string()))
+}
+
+pub fn list() -> Result<Vec<CrashReport>, AppError> {
+    Ok(load_all(dir()?))
+}
+
+/// Sends one report and marks it as sent.
+pub async fn submit(id: &str) -> Result<CrashReport, AppError> {
+    let url = REPORT_URL
+        .ok_or_else(|| AppError::Invalid("this build has nowhere to send crash reports".into()))?;
+    let mut report = load(id)?;
+    let response = reqwest::Client::new()
+        .post(url)
+        .timeout(std::time::Duration::from_secs(30))
+        .json(&report)
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(AppError::Failed(format!(
+            "the crash report server answered {}",
+            response.status()
+        )));
+    }
+    report.status = ReportStatus::Submitted;
+    report.submitted_at = Some(chrono::Utc::now().to_rfc3339());
+    save(dir()?, &report)?;
+    Ok(report)
+}
+
+fn dismiss(reports: &[CrashReport]) {
+    let Ok(dir) = dir() else {
+        return;
+    };
+    for report in reports {
+        let mut report = report.clone();
+        report.status = ReportStatus::Dismissed;
+        if let Err(e) = save(dir, &report) {
+            log::warn!("crash: failed to update report {}: {e}", report.id);
+        }
+    }
+}
+
+/// Asks whether to send the reports from crashes since the last launch.
+pub fn start(app: &AppHandle) {
+    if REPORT_URL.is_none() {
+        return;
+    }
+    let Ok(reports) = list() else {
+        return;
+    };
+    let new: Vec<CrashReport> = reports
+        .into_iter()
+        .filter(|r| r.status == ReportStatus::New)
+        .collect();
+    if new.is_empty() {
+        return;
+    }
+    app.dialog()
+        .message(t_args("crash-body", &[("count", new.len().into())]))
+        .title(t("crash-title"))
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            t("crash-send"),
+            t("crash-dont-send"),
+        ))
+        .show(move |send| {
+            if !send {
+                dismiss(&new);
+                return;
+            }
+            tauri::async_runtime::spawn(async move {
+                for report in &new {
+                    if let Err(e) = submit(&report.id).await {
+                        log::warn!("crash: failed to send report {}: {e}", report.id);
+                    }
+                }
+            });
+        });
+}
+
+#[cfg(unix)]
+mod native {
+    use std::ffi::CString;
+    use std::path::Path;
+    use std::sync::OnceLock;
+
+    const SIGNALS: [libc::c_int; 5] = [
+        libc::SIGSEGV,
+        libc::SIGBUS,
+        libc::SIGILL,
+        libc::SIGFPE,
+        libc::SIGABRT,
+    ];
+
+    static MARKER: OnceLock<CString> = OnceLock::new();
+    // Written once in `install`, before any handler can run.
+    static mut PREVIOUS: [libc::sigaction; 5] = unsafe { std::mem::zeroed() };
+
+    /// Only async-signal-safe calls in here: write the marker, put the previous handler
+    /// back and return, so the fault happens again and reaches it (Rust's stack overflow
+    /// message, or the default crash).
+    extern "C" fn handle(signal: libc::c_int) {
+        if let Some(marker) = MARKER.get() {
+            let mut text = *b"signal 000\n";
+            let n = signal.clamp(0, 999) as u8;
+            text[7] = b'0' + n / 100;
+            text[8] = b'0' + n / 10 % 10;
+            text[9] = b'0' + n % 10;
+            unsafe {
+                let fd = libc::open(
+                    marker.as_ptr(),
+                    libc::O_WRONLY | libc::O_CREAT | libc::O_TRUNC,
+                    0o600,
+                );
+                if fd >= 0 {
+                    libc::write(fd, text.as_ptr().cast(), text.len());
+                    libc::close(fd);
+                }
+            }
+        }
+        if let Some(i) = SIGNALS.iter().position(|s| *s == signal) {
+            unsafe {
+                let previous = std::ptr::addr_of!(PREVIOUS)
+                    .cast::<libc::sigaction>()
+                    .add(i);
+                libc::sigaction(signal, previous, std::ptr::null_mut());
+            }
+        }
+    }
+
+    pub fn install(marker: &Path) {
+        use std::os::unix::ffi::OsStrExt;
+
+        let Ok(marker) = CString::new(marker.as_os_str().as_bytes()) else {
+            return;
+        };
+        if MARKER.set(marker).is_err() {
+            return;
+        }
+        for (i, signal) in SIGNALS.iter().enumerate() {
+            unsafe {
+                let mut action: libc::sigaction = std::mem::zeroed();
+                action.sa_sigaction = handle as extern "C" fn(libc::c_int) as usize;
+                // The alternate stack Rust sets up, so a stack overflow can be recorded.
+                action.sa_flags = libc::SA_ONSTACK;
+                libc::sigemptyset(&mut action.sa_mask);
+                let previous = std::ptr::addr_of_mut!(PREVIOUS)
+                    .cast::<libc::sigaction>()
+                    .add(i);
+                libc::sigaction(*signal, &action, previous);
+            }
+        }
+    }
+
+    pub fn describe(marker: &str) -> String {
+        let number: libc::c_int = marker
+            .strip_prefix("signal ")
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0);
+        let name = match number {
+            libc::SIGSEGV => "SIGSEGV (invalid memory access)",
+            libc::SIGBUS => "SIGBUS (bus error)",
+            libc::SIGILL => "SIGILL (illegal instruction)",
+            libc::SIGFPE => "SIGFPE (arithmetic error)",
+            libc::SIGABRT => "SIGABRT (aborted)",
+            _ => return format!("native crash: {marker}"),
+        };
+        format!("native crash: {name}")
+    }
+}
+
+#[cfg(windows)]
+mod native {
+    use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
+
+    use windows_sys::Win32::System::Diagnostics::Debug::{
+        SetUnhandledExceptionFilter, EXCEPTION_CONTINUE_SEARCH, EXCEPTION_POINTERS,
+    };
+
+    static MARKER: OnceLock<PathBuf> = OnceLock::new();
+
+    /// Runs on the crashing thread, so it keeps to writing the exception code and lets
+    /// Windows carry on with the crash.
+    unsafe extern "system" fn handle(info: *const EXCEPTION_POINTERS) -> i32 {
+        if let (Some(marker), Some(record)) = (MARKER.get(), info.as_ref()) {
+            if let Some(record) = record.ExceptionRecord.as_ref() {
+                let _ = std::fs::write(
+                    marker,
+                    format!("exception 0x{:08X}\n", record.ExceptionCode as u32),
+                );
+            }
+        }
+        EXCEPTION_CONTINUE_SEARCH
+    }
+
+    pub fn install(marker: &Path) {
+        if MARKER.set(marker.to_path_buf()).is_ok() {
+            unsafe {
+                SetUnhandledExceptionFilter(Some(handle));
+            }
+        }
+    }
+
+    pub fn describe(marker: &str) -> String {
+        let name = match marker.strip_prefix("exception ").unwrap_or(marker) {
+            "0xC0000005" => "access violation",
+            "0xC00000FD" => "stack overflow",
+            "0xC000001D" => "illegal instruction",
+            "0xC0000409" => "stack buffer overrun",
+            code => return format!("native crash: exception {code}"),
+        };
+        format!("native crash: {name}")
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod native {
+    pub fn install(_marker: &std::path::Path) {}
+
+    pub fn describe(marker: &str) -> String {
+        format!("native crash: {marker}")
+    }
+}
+
+/// Newest first.
+#[tauri::command]
+pub fn list_crash_reports() -> Result<Vec<CrashReport>, AppError> {
+    list()
+}
+
+/// Sends the report to the crash report server. Only ever called on request.
+#[tauri::command]
+pub async fn submit_crash_report(id: String) -> Result<CrashReport, AppError> {
+    submit(&id).await
+}
+
+#[tauri::command]
+pub fn delete_crash_report(id: String) -> Result<(), AppError> {
+    std::fs::remove_file(path(&id)?)?;
+    Ok(())
+}
@@ -0,0 +1,206 @@
+//! Sample data for screenshots, demos and trying the app out on first run.
+//!
+//! `load_demo_data` adds a handful of everyday habits with half a year of history, kept
+//! the way `seed` keeps synthetic ones (runs of good and bad days, weekend slips, the odd
+//! skipped week), and a mood for most days. The history is the same every time, shifted to
+//! end yesterday, so today is still open to check off. Demo habits have ids starting with
+//! `demo-`; loading again replaces them and leaves every other habit alone.
+//!
+//! `reset_to_fresh` goes the other way: every habit and everything recorded about them is
+//! deleted, as on a new install. Settings are kept.
+
+use std::path::Path;
+
+use chrono::{Days, NaiveDate};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::Habit;
+use crate::stats::{date_key, today};
+use crate::storage::{MoodEntry, Storage};
+
+pub const DEMO_PREFIX: &str = "demo-";
+
+const DAYS: u64 = 180;
+/// The same numbers every time, so screenshots can be redone.
+const SEED: u64 = 0x6861_6269_7466_6c6f;
+/// Built-in templates to make demo habits of, and how many days before today each was
+/// started.
+const HABITS: &[(&str, u64)] = &[
+    ("builtin:meditate", DAYS),
+    ("builtin:read", DAYS),
+    ("builtin:workout", DAYS),
+    ("builtin:water", 150),
+    ("builtin:journal", 120),
+    ("builtin:no-sugar", 90),
+    ("builtin:call-family", 75),
+    ("builtin:practice", 40),
+];
+const PINNED: &str = "builtin:meditate";
+const MOOD_RATE: f64 = 0.85;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DemoSummary {
+    pub habits: usize,
+    pub completions: usize,
+    pub moods: usize,
+    /// Demo habits from an earlier load that were replaced.
+    pub replaced: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResetSummary {
+    pub habits: usize,
+    /// Rows deleted across all tables, habits included.
+    pub rows: u64,
+}
+
+/// The demo habits, with history through `last`.
+fn generate(last: NaiveDate, rng: &mut impl Rng) -> Vec<Habit> {
+    let templates = crate::templates::builtin();
+    HABITS
+        .iter()
+        .filter_map(|(id, days)| {
+            let template = templates.iter().find(|t| t.id == *id)?;
+            let mut habit = crate::templates::instantiate(template, None, None);
+            habit.id = format!("{DEMO_PREFIX}{}", id.trim_start_matches("builtin:"));
+            habit.pinned = *id == PINNED;
+            let first = last.checked_sub_days(Days::new(*days)).unwrap_or(last);
+            habit.created_at = format!("{}T08:00:00Z", date_key(first));
+            crate::seed::fill_history(&mut habit, first, last, rng);
+            Some(habit)
+        })
+        .collect()
+}
+
+/// A mood for most days, better on days more of `habits` got done.
+fn moods(
+    habits: &[Habit],
+    first: NaiveDate,
+    last: NaiveDate,
+    rng: &mut impl Rng,
+) -> Vec<MoodEntry> {
+    let mut moods = Vec::new();
+    for day in first.iter_days().take_while(|d| *d <= last) {
+        if !rng.random_bool(MOOD_RATE) {
+            continue;
+        }
+        let key = date_key(day);
+        let done = habits.iter().filter(|h| h.is_done_on(&key)).count();
+        let share = done as f64 / habits.len().max(1) as f64;
+        let score = (1.5 + share * 3.0 + rng.random_range(-1.0..1.0)).round();
+        moods.push(MoodEntry {
+            logged_at: format!("{key}T21:00:00Z"),
+            date: key,
+            score: score.clamp(1.0, 5.0) as u8,
+            tags: Vec::new(),
+        });
+    }
+    moods
+}
+
+fn changed(app: &AppHandle) {
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(app);
+    crate::mobile_reminders::sync_logged(app);
+}
+
+/// Replaces earlier demo habits with fresh ones. Moods are only added to days without one.
+pub fn load(app: &AppHandle) -> Result<DemoSummary, AppError> {
+    let summary = load_into(&app.state::<Storage>())?;
+    changed(app);
+    log::info!("demo: loaded {} habits", summary.habits);
+    Ok(summary)
+}
+
+/// [`load`] without telling the webview.
+pub fn load_into(storage: &Storage) -> Result<DemoSummary, AppError> {
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let last = today().pred_opt().unwrap_or_else(today);
+    let generated = generate(last, &mut rng);
+    let first = last.checked_sub_days(Days::new(DAYS)).unwrap_or(last);
+
+    let logged: std::collections::HashSet<String> = storage
+        .list_moods(None, None)?
+        .into_iter()
+        .map(|m| m.date)
+        .collect();
+    let moods: Vec<MoodEntry> = moods(&generated, first, last, &mut rng)
+        .into_iter()
+        .filter(|m| !logged.contains(&m.date))
+        .collect();
+    let summary = DemoSummary {
+        habits: generated.len(),
+        completions: generated.iter().map(|h| h.logs.len()).sum(),
+        moods: moods.len(),
+        replaced: 0,
+    };
+
+    let (earlier, mut kept): (Vec<Habit>, Vec<Habit>) = storage
+        .list_habits()?
+        .into_iter()
+        .partition(|h| h.id.starts_with(DEMO_PREFIX));
+    kept.extend(generated);
+    storage.replace_habits(&kept)?;
+    for mood in &moods {
+        storage.set_mood(mood)?;
+    }
+    Ok(DemoSummary {
+        replaced: earlier.len(),
+        ..summary
+    })
+}
+
+/// Deletes every habit, check-in, mood, note and everything else in the database, and
+/// the attachment and cover files.
+pub fn reset(app: &AppHandle) -> Result<ResetSummary, AppError> {
+    let summary = reset_in(&app.state::<Storage>(), &app.path().app_data_dir()?)?;
+    changed(app);
+    log::info!("demo: reset to fresh, {} rows removed", summary.rows);
+    Ok(summary)
+}
+
+/// [`reset`] for `storage`, removing the files in `data_dir`.
+pub fn reset_in(storage: &Storage, data_dir: &Path) -> Result<ResetSummary, AppError> {
+    let habits = storage.list_habits()?.len();
+    // The registered mobile reminders stay listed, so the sync below can cancel them. The
+    // legacy import stays done, or the old localStorage copy would come back on next start.
+    // The libSQL sync state stays, so the reset is sent up rather than pulled back over.
+    let mut keep = vec![
+        crate::mobile_reminders::REGISTERED_KEY,
+        crate::legacy::IMPORTED_KEY,
+    ];
+    keep.extend(crate::replica::LOCAL_KEYS);
+    let rows = storage.clear_all(&keep)?;
+    for dir in [
+        crate::attachments::ATTACHMENTS_DIR,
+        crate::appearance::COVERS_DIR,
+    ] {
+        match std::fs::remove_dir_all(data_dir.join(dir)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    Ok(ResetSummary { habits, rows })
+}
+
+/// Adds the sample habits and their history. Runs off the main thread.
+#[tauri::command]
+pub async fn load_demo_data(app: AppHandle) -> Result<DemoSummary, AppError> {
+    tauri::async_runtime::spawn_blocking(move || load(&app))
+        .await
+        .map_err(|e| AppError::Failed(e.to_string()))?
+}
+
+/// Deletes all habit data, for starting over after a demo. It can't be undone, short of
+/// restoring a backup or a data version.
+#[tauri::command]
+pub fn reset_to_fresh(app: AppHandle) -> Result<ResetSummary, AppError> {
+    reset(&app)
+}
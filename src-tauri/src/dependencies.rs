@@ -0,0 +1,218 @@
+//! Habit chains: a habit can name prerequisites ("Cold shower" after "Workout").
+//!
+//! Until every prerequisite is done for the day, a habit is blocked: it's left out of the
+//! due list, its reminder stays quiet and the evening alert skips it. Finishing the last
+//! prerequisite sends an "Up next" notification instead. Archived prerequisites don't
+//! block anything. Cycles are rejected when dependencies are set.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::i18n::{t, t_args};
+use crate::model::Habit;
+use crate::stats::{date_key, day_arg, today};
+use crate::storage::Storage;
+
+/// Prerequisites of `habit` that aren't done on `date` yet.
+pub fn blocked_by<'a>(habit: &Habit, habits: &'a [Habit], date: NaiveDate) -> Vec<&'a Habit> {
+    if habit.depends_on.is_empty() {
+        return Vec::new();
+    }
+    let key = date_key(date);
+    habits
+        .iter()
+        .filter(|h| habit.depends_on.contains(&h.id))
+        .filter(|h| !h.archived && !h.is_done_on(&key))
+        .collect()
+}
+
+pub fn is_blocked(habit: &Habit, habits: &[Habit], date: NaiveDate) -> bool {
+    !blocked_by(habit, habits, date).is_empty()
+}
+
+/// Whether `habit_id` depending on `depends_on` would close a loop.
+fn creates_cycle(habits: &[Habit], habit_id: &str, depends_on: &[String]) -> bool {
+    let edges: HashMap<&str, Vec<&str>> = habits
+        .iter()
+        .map(|h| {
+            let deps = if h.id == habit_id {
+                depends_on.iter().map(String::as_str).collect()
+            } else {
+                h.depends_on.iter().map(String::as_str).collect()
+            };
+            (h.id.as_str(), deps)
+        })
+        .collect();
+
+    let mut seen = HashSet::new();
+    let mut stack: Vec<&str> = depends_on.iter().map(String::as_str).collect();
+    while let Some(id) = stack.pop() {
+        if id == habit_id {
+            return true;
+        }
+        if seen.insert(id) {
+            stack.extend(edges.get(id).into_iter().flatten());
+        }
+    }
+    false
+}
+
+/// Replaces a habit's prerequisites.
+pub fn set_dependencies(
+    storage: &Storage,
+    habit_id: &str,
+    depends_on: Vec<String>,
+) -> Result<Habit, AppError> {
+    let habits = storage.list_habits()?;
+    if let Some(unknown) = depends_on
+        .iter()
+        .find(|id| !habits.iter().any(|h| h.id == **id))
+    {
+        return Err(AppError::not_found("habit", unknown));
+    }
+    if creates_cycle(&habits, habit_id, &depends_on) {
+        return Err(AppError::Invalid(
+            "that would make the habits depend on each other".into(),
+        ));
+    }
+    if !storage.set_habit_dependencies(habit_id, &depends_on)? {
+        return Err(AppError::not_found("habit", habit_id));
+    }
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GraphNode {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    pub done: bool,
+    pub blocked: bool,
+}
+
+/// `from` must be done before `to`.
+#[derive(Debug, Clone, Serialize)]
+pub struct GraphEdge {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyGraph {
+    pub date: String,
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+/// [`graph`] of the stored habits.
+pub fn graph_on(storage: &Storage, date: NaiveDate) -> Result<DependencyGraph, AppError> {
+    Ok(graph(&storage.list_habits()?, date))
+}
+
+/// Non-archived habits that take part in a chain, with their state on `date`.
+pub fn graph(habits: &[Habit], date: NaiveDate) -> DependencyGraph {
+    let key = date_key(date);
+    let active: Vec<&Habit> = habits.iter().filter(|h| !h.archived).collect();
+    let edges: Vec<GraphEdge> = active
+        .iter()
+        .flat_map(|h| {
+            h.depends_on
+                .iter()
+                .filter(|d| active.iter().any(|p| p.id == **d))
+                .map(|d| GraphEdge {
+                    from: d.clone(),
+                    to: h.id.clone(),
+                })
+        })
+        .collect();
+    let nodes = active
+        .iter()
+        .filter(|h| edges.iter().any(|e| e.from == h.id || e.to == h.id))
+        .map(|h| GraphNode {
+            id: h.id.clone(),
+            name: h.name.clone(),
+            color: h.color.clone(),
+            done: h.is_done_on(&key),
+            blocked: is_blocked(h, habits, date),
+        })
+        .collect();
+    DependencyGraph {
+        date: key,
+        nodes,
+        edges,
+    }
+}
+
+/// Sends "Up next" when a completion unblocks other habits.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    let mut rx = app.state::<EventBus>().subscribe();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+            if let crate::events::HabitEvent::HabitCompleted { habit_id, date, .. } = event {
+                if date == date_key(today()) {
+                    announce_unblocked(&app, &habit_id);
+                }
+            }
+        }
+    });
+}
+
+fn announce_unblocked(app: &AppHandle, completed_id: &str) {
+    let habits = match app.state::<Storage>().list_habits() {
+        Ok(h) => h,
+        Err(e) => {
+            log::warn!("dependencies: failed to load habits: {e}");
+            return;
+        }
+    };
+    let today = today();
+    let next: Vec<&Habit> = habits
+        .iter()
+        .filter(|h| h.depends_on.iter().any(|d| d == completed_id))
+        .filter(|h| crate::schedule::is_due(h, today) && !is_blocked(h, &habits, today))
+        .collect();
+    if crate::privacy::hide_names(app) && !next.is_empty() {
+        let body = t_args("up-next-hidden", &[("count", next.len().into())]);
+        crate::reminders::notify(app, &t("up-next-title"), &body);
+        return;
+    }
+    for habit in next {
+        crate::reminders::notify(app, &t("up-next-title"), &crate::appearance::label(habit));
+    }
+}
+
+/// Replaces the prerequisites of a habit.
+#[tauri::command]
+pub fn set_habit_dependencies(
+    app: AppHandle,
+    habit_id: String,
+    depends_on: Vec<String>,
+) -> Result<Habit, AppError> {
+    let habit = set_dependencies(&app.state::<Storage>(), &habit_id, depends_on)?;
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(&app);
+    Ok(habit)
+}
+
+/// Habits in a chain and their links, with each habit's state on `date` (default today).
+#[tauri::command]
+pub fn get_dependency_graph(
+    storage: State<'_, Storage>,
+    date: Option<String>,
+) -> Result<DependencyGraph, AppError> {
+    graph_on(&storage, day_arg(date.as_deref())?)
+}
@@ -0,0 +1,168 @@
+//! `create_diagnostic_bundle`: one zip for a bug report. It holds:
+//!
+//! - `about.json`: app and Tauri versions, the build, and the OS (from the os plugin)
+//! - `settings.json`: the settings with tokens, passwords, secrets and the telemetry id
+//!   blanked out, and URLs cut down to their host
+//! - `database.json`: the schema version, row counts, page stats, file sizes and a quick
+//!   integrity check
+//! - `logs/`: the newest log files, up to [`MAX_LOG_BYTES`]
+//!
+//! Everything but the logs also goes through `redact`, like the logs did when written, so
+//! home paths and (in privacy mode) habit names stay out.
+
+use std::path::Path;
+use std::time::SystemTime;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::redact::redact;
+use crate::settings::SettingsStore;
+use crate::storage::Storage;
+
+/// How much of the newest logs goes in.
+pub const MAX_LOG_BYTES: u64 = 8 * 1024 * 1024;
+const REDACTED: &str = "[redacted]";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticBundle {
+    pub path: String,
+    /// Names of the files in the zip.
+    pub files: Vec<String>,
+    pub bytes: u64,
+}
+
+/// Keys whose values are never included.
+fn is_secret(key: &str) -> bool {
+    let key = key.to_lowercase();
+    ["token", "password", "secret", "passphrase", "installid"]
+        .iter()
+        .any(|s| key.contains(s))
+}
+
+/// Blanks secrets, shortens URLs to their host, and redacts whatever else is a string.
+fn scrub(value: &mut serde_json::Value) {
+    use serde_json::Value;
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                match value {
+                    Value::String(s) if is_secret(key) && !s.is_empty() => {
+                        *s = REDACTED.into();
+                    }
+                    _ => scrub(value),
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(scrub),
+        Value::String(s) => {
+            *s = match reqwest::Url::parse(s) {
+                // Webhook URLs are secrets of their own.
+                Ok(url) if url.has_host() => {
+                    format!(
+                        "{}://{}/…",
+                        url.scheme(),
+                        url.host_str().unwrap_or_default()
+                    )
+                }
+                _ => redact(s),
+            };
+        }
+        _ => {}
+    }
+}
+
+fn json(value: &serde_json::Value) -> Result<Vec<u8>, AppError> {
+    serde_json::to_vec_pretty(value).map_err(|e| AppError::Failed(e.to_string()))
+}
+
+fn about(app: &AppHandle) -> serde_json::Value {
+    serde_json::json!({
+        "appVersion": app.package_info().version.to_string(),
+        "tauriVersion": tauri::VERSION,
+        "build": if cfg!(debug_assertions) { "debug" } else { "release" },
+        "os": {
+            "type": tauri_plugin_os::type_().to_string(),
+            "platform": tauri_plugin_os::platform(),
+            "version": tauri_plugin_os::version().to_string(),
+            "family": tauri_plugin_os::family(),
+            "arch": tauri_plugin_os::arch(),
+            "locale": tauri_plugin_os::locale(),
+        },
+        "locale": crate::i18n::locale(),
+        "createdAt": chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+fn database(app: &AppHandle) -> Result<serde_json::Value, AppError> {
+    let storage = app.state::<Storage>();
+    let (applied, known) = storage.schema_version()?;
+    let (page_size, page_count, freelist_count) = storage.page_stats()?;
+    let tables: serde_json::Map<String, serde_json::Value> = storage
+        .table_counts()?
+        .into_iter()
+        .map(|(name, rows)| (name, rows.into()))
+        .collect();
+    let usage = crate::resources::usage(app)?;
+    Ok(serde_json::json!({
+        "schemaVersion": applied,
+        "latestSchemaVersion": known,
+        "quickCheck": storage.quick_check()?,
+        "pageSize": page_size,
+        "pageCount": page_count,
+        "freelistCount": freelist_count,
+        "tables": tables,
+        "resources": usage,
+    }))
+}
+
+pub fn create(app: &AppHandle, path: &Path) -> Result<DiagnosticBundle, AppError> {
+    let now = SystemTime::now();
+    let mut settings = serde_json::to_value(app.state::<SettingsStore>().get())
+        .map_err(|e| AppError::Failed(e.to_string()))?;
+    scrub(&mut settings);
+    let mut database = database(app)?;
+    scrub(&mut database);
+    let mut entries = vec![
+        ("about.json".to_string(), json(&about(app))?, now),
+        ("settings.json".to_string(), json(&settings)?, now),
+        ("database.json".to_string(), json(&database)?, now),
+    ];
+
+    log::logger().flush();
+    let mut log_bytes = 0;
+    for (file, modified) in crate::logging::log_files(&crate::logging::log_dir(app)?) {
+        let size = std::fs::metadata(&file).map(|m| m.len()).unwrap_or(0);
+        if log_bytes + size > MAX_LOG_BYTES && log_bytes > 0 {
+            break;
+        }
+        log_bytes += size;
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        entries.push((format!("logs/{name}"), std::fs::read(&file)?, modified));
+    }
+
+    let mut zip = Vec::new();
+    crate::logging::write_zip(&mut zip, &entries)?;
+    std::fs::write(path, &zip)?;
+    log::info!("diagnostics: wrote a bundle of {} files", entries.len());
+    Ok(DiagnosticBundle {
+        path: path.display().to_string(),
+        files: entries.into_iter().map(|(name, ..)| name).collect(),
+        bytes: zip.len() as u64,
+    })
+}
+
+/// Writes the bundle to `path`, which has to come from `pick_save_path`.
+#[tauri::command]
+pub fn create_diagnostic_bundle(
+    app: AppHandle,
+    path: String,
+) -> Result<DiagnosticBundle, AppError> {
+    let path = crate::files::allowed(&app, &path)?;
+    create(&app, &path)
+}
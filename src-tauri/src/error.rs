@@ -0,0 +1,147 @@
+//! The error every command returns. It reaches the webview as
+//! `{ code, message, detail, retryable }`:
+//!
+//! - `code` is stable, so the UI can branch on it
+//! - `message` can be shown to the user as is
+//! - `detail` is for logs and bug reports
+//! - `retryable` says whether trying again later can help (a busy database, a flaky
+//!   network), as opposed to errors that will keep failing until something changes
+//!
+//! A `conflict` also carries `revision` and `current`: the record as it is now, which the
+//! write was refused in favour of (see `revisions.rs`).
+//!
+//! Validators return `Invalid` themselves (`stats::date_arg`, `text::habit_name`,
+//! `backfill::check` and the like), and so do the helpers that call them, down to the
+//! transaction cores in `habits.rs`. Other helpers below the command layer mostly still
+//! return `String`; those convert into `Failed`, with the text as both message and detail.
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+
+#[derive(Debug, thiserror::Error)]
+pub enum AppError {
+    #[error("{what} not found: {id}")]
+    NotFound { what: &'static str, id: String },
+    #[error("{0}")]
+    Invalid(String),
+    #[error("HabitFlow is locked")]
+    Locked,
+    #[error("{0}")]
+    Forbidden(String),
+    #[error("cancelled")]
+    Cancelled,
+    #[error("stale revision {expected}, the record is at {revision}")]
+    Conflict {
+        expected: u64,
+        revision: u64,
+        /// The current habit.
+        current: Box<serde_json::Value>,
+    },
+    #[error("database error: {0}")]
+    Storage(#[from] rusqlite::Error),
+    #[error("file error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("network error: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("{0}")]
+    Failed(String),
+}
+
+impl AppError {
+    pub fn not_found(what: &'static str, id: &str) -> Self {
+        Self::NotFound {
+            what,
+            id: id.to_string(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotFound { .. } => "not_found",
+            Self::Invalid(_) => "invalid",
+            Self::Locked => "locked",
+            Self::Forbidden(_) => "forbidden",
+            Self::Cancelled => "cancelled",
+            Self::Conflict { .. } => "conflict",
+            Self::Storage(_) => "storage",
+            Self::Io(_) => "io",
+            Self::Network(_) => "network",
+            Self::Failed(_) => "failed",
+        }
+    }
+
+    /// What the user sees; the low-level errors get a plain sentence instead.
+    pub fn message(&self) -> String {
+        match self {
+            Self::NotFound { what, .. } => {
+                let mut what = what.to_string();
+                if let Some(first) = what.get_mut(..1) {
+                    first.make_ascii_uppercase();
+                }
+                format!("{what} not found; it may have been deleted")
+            }
+            Self::Conflict { .. } => "This was changed somewhere else in the meantime".into(),
+            Self::Storage(_) => "Couldn't read or save your data".into(),
+            Self::Io(_) => "Couldn't read or write the file".into(),
+            Self::Network(_) => "Couldn't reach the server".into(),
+            _ => self.to_string(),
+        }
+    }
+
+    pub fn retryable(&self) -> bool {
+        match self {
+            Self::Storage(rusqlite::Error::SqliteFailure(e, _)) => matches!(
+                e.code,
+                rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked
+            ),
+            Self::Io(e) => matches!(
+                e.kind(),
+                std::io::ErrorKind::Interrupted
+                    | std::io::ErrorKind::TimedOut
+                    | std::io::ErrorKind::WouldBlock
+            ),
+            Self::Network(e) => !e.status().is_some_and(|s| s.is_client_error()),
+            _ => false,
+        }
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(e: tauri::Error) -> Self {
+        Self::Failed(e.to_string())
+    }
+}
+
+impl From<String> for AppError {
+    fn from(message: String) -> Self {
+        Self::Failed(message)
+    }
+}
+
+impl From<&str> for AppError {
+    fn from(message: &str) -> Self {
+        Self::Failed(message.to_string())
+    }
+}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let conflict = match self {
+            Self::Conflict {
+                revision, current, ..
+            } => Some((revision, current)),
+            _ => None,
+        };
+        let fields = if conflict.is_some() { 6 } else { 4 };
+        let mut s = serializer.serialize_struct("AppError", fields)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.message())?;
+        s.serialize_field("detail", &self.to_string())?;
+        s.serialize_field("retryable", &self.retryable())?;
+        if let Some((revision, current)) = conflict {
+            s.serialize_field("revision", revision)?;
+            s.serialize_field("current", current)?;
+        }
+        s.end()
+    }
+}
@@ -0,0 +1,146 @@
+use serde::Serialize;
+use tokio::sync::{broadcast, watch};
+
+use crate::model::MilestoneKind;
+
+/// Streak lengths that trigger a `streakMilestone` event (same as the UI's notifications).
+pub const STREAK_MILESTONES: &[u32] = &[7, 14, 30, 50, 100];
+
+/// Every `HabitEvent::kind()`, for validating subscriptions.
+pub const EVENT_KINDS: &[&str] = &[
+    "habitCreated",
+    "habitCompleted",
+    "streakMilestone",
+    "streakBroken",
+    "streakFrozen",
+    "milestoneReached",
+    "habitPaused",
+    "habitResumed",
+    "levelUp",
+    "achievementUnlocked",
+];
+
+/// Habit activity broadcast to external consumers (WebSocket clients, ...).
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum HabitEvent {
+    HabitCreated {
+        habit_id: String,
+        name: String,
+    },
+    HabitCompleted {
+        habit_id: String,
+        name: String,
+        date: String,
+        streak: u32,
+    },
+    StreakMilestone {
+        habit_id: String,
+        name: String,
+        streak: u32,
+    },
+    /// Reported at day rollover for daily habits whose streak just ended.
+    StreakBroken {
+        habit_id: String,
+        name: String,
+        streak: u32,
+    },
+    /// Reported at day rollover when a streak freeze covered a missed day.
+    StreakFrozen {
+        habit_id: String,
+        name: String,
+        date: String,
+        streak: u32,
+    },
+    /// A habit reached one of its milestones for the first time.
+    MilestoneReached {
+        habit_id: String,
+        name: String,
+        milestone_id: String,
+        kind: MilestoneKind,
+        threshold: f64,
+    },
+    /// A habit was paused; `until` is the day it comes back.
+    HabitPaused {
+        habit_id: String,
+        name: String,
+        until: String,
+    },
+    /// A pause ended, on schedule or early.
+    HabitResumed {
+        habit_id: String,
+        name: String,
+    },
+    /// XP crossed into a new level (only while gamification is on).
+    LevelUp {
+        level: u32,
+        xp: u64,
+        date: String,
+    },
+    /// An achievement from the catalog was earned for the first time.
+    AchievementUnlocked {
+        achievement_id: String,
+        name: String,
+    },
+}
+
+impl HabitEvent {
+    /// Matches the serialized `type` tag.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            HabitEvent::HabitCreated { .. } => "habitCreated",
+            HabitEvent::HabitCompleted { .. } => "habitCompleted",
+            HabitEvent::StreakMilestone { .. } => "streakMilestone",
+            HabitEvent::StreakBroken { .. } => "streakBroken",
+            HabitEvent::StreakFrozen { .. } => "streakFrozen",
+            HabitEvent::MilestoneReached { .. } => "milestoneReached",
+            HabitEvent::HabitPaused { .. } => "habitPaused",
+            HabitEvent::HabitResumed { .. } => "habitResumed",
+            HabitEvent::LevelUp { .. } => "levelUp",
+            HabitEvent::AchievementUnlocked { .. } => "achievementUnlocked",
+        }
+    }
+}
+
+/// In-process fan-out of `HabitEvent`s. Publishing never blocks; slow subscribers
+/// just miss events (see `broadcast::error::RecvError::Lagged`).
+///
+/// Separately tracks a data revision that bumps on *any* change (including ones with no
+/// event, like un-checking a day) for consumers that mirror state rather than react to events.
+pub struct EventBus {
+    tx: broadcast::Sender<HabitEvent>,
+    revision: watch::Sender<u64>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        let (tx, _) = broadcast::channel(256);
+        Self {
+            tx,
+            revision: watch::Sender::new(0),
+        }
+    }
+}
+
+impl EventBus {
+    pub fn publish(&self, event: HabitEvent) {
+        // Err only means there are no subscribers right now.
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<HabitEvent> {
+        self.tx.subscribe()
+    }
+
+    pub fn data_changed(&self) {
+        self.revision.send_modify(|r| *r += 1);
+    }
+
+    pub fn watch_data(&self) -> watch::Receiver<u64> {
+        self.revision.subscribe()
+    }
+}
@@ -0,0 +1,507 @@
+//! Export formats and recurring export jobs ("every Sunday, write a CSV to ~/Dropbox/habits").
+//!
+//! Jobs live in settings; the scheduler calls [`run_due`] once a minute. A job is due when
+//! its most recent scheduled occurrence is newer than its last run, so occurrences missed
+//! while the app was closed run once on the next launch. Every run lands in the export
+//! history, and failures raise a desktop notification. With `settings.quitExport` on, an
+//! export is also written while the app quits ([`export_on_quit`]).
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, Local, Months, NaiveDate, NaiveDateTime, NaiveTime,
+};
+use tauri::{AppHandle, Manager, State};
+
+use crate::archive;
+use crate::error::AppError;
+use crate::model::{Category, Habit};
+use crate::secrets;
+use crate::settings::{generate_token, ExportFormat, ExportJob, ExportSchedule, SettingsStore};
+use crate::stats::{date_key, group_by_category};
+use crate::storage::{ExportRun, Storage};
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// Renders `habits` in `format`, grouped by category in `categories` order.
+pub fn render(format: ExportFormat, habits: &[Habit], categories: &[Category]) -> String {
+    let groups = group_by_category(habits, categories);
+    let habits = groups
+        .iter()
+        .flat_map(|(_, members)| members.iter().copied());
+    match format {
+        ExportFormat::Json => {
+            // Importers that predate categories ignore the extra key.
+            let categories: Vec<&Category> = groups
+                .iter()
+                .map(|(c, _)| c)
+                .filter(|c| !c.id.is_empty())
+                .collect();
+            let payload = serde_json::json!({
+                "schemaVersion": 2,
+                "exportedAt": chrono::Utc::now().to_rfc3339(),
+                "app": "HabitFlow",
+                "categories": categories,
+                "habits": habits.collect::<Vec<_>>(),
+            });
+            serde_json::to_string_pretty(&payload).unwrap_or_default()
+        }
+        ExportFormat::Csv => {
+            let mut out =
+                String::from("habit_id,name,category,archived,date,value,unit,note,color,emoji\n");
+            for h in habits {
+                let unit = h.target.as_ref().map(|t| t.unit.as_str()).unwrap_or("");
+                let emoji = crate::appearance::emoji(h).unwrap_or("");
+                for date in h.done_dates() {
+                    let value = h.values.get(date).map(f64::to_string).unwrap_or_default();
+                    let note = h.notes.get(date).map(String::as_str).unwrap_or("");
+                    out.push_str(&format!(
+                        "{},{},{},{},{},{},{},{},{},{}\n",
+                        csv_field(&h.id),
+                        csv_field(&h.name),
+                        csv_field(&h.category),
+                        h.archived,
+                        date,
+                        value,
+                        csv_field(unit),
+                        csv_field(note),
+                        csv_field(&h.color),
+                        csv_field(emoji)
+                    ));
+                }
+            }
+            out
+        }
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub(crate) fn expand_home(dir: &str) -> PathBuf {
+    match dir.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => dirs::home_dir()
+            .map(|home| home.join(rest.trim_start_matches(['/', '\\'])))
+            .unwrap_or_else(|| PathBuf::from(dir)),
+        _ => PathBuf::from(dir),
+    }
+}
+
+fn parse_time(time: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M").ok()
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    let first = date.with_day(1).unwrap_or(date);
+    (first + Months::new(1) - Days::new(1)).day()
+}
+
+impl ExportSchedule {
+    fn time(&self) -> &str {
+        match self {
+            ExportSchedule::Daily { time }
+            | ExportSchedule::Weekly { time, .. }
+            | ExportSchedule::Monthly { time, .. } => time,
+        }
+    }
+
+    fn matches(&self, date: NaiveDate) -> bool {
+        match self {
+            ExportSchedule::Daily { .. } => true,
+            ExportSchedule::Weekly { weekday, .. } => {
+                date.weekday().num_days_from_sunday() == *weekday
+            }
+            ExportSchedule::Monthly { day, .. } => {
+                date.day() == (*day).min(last_day_of_month(date))
+            }
+        }
+    }
+
+    /// The latest scheduled time at or before `now`.
+    fn last_occurrence(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let time = parse_time(self.time())?;
+        // A month back always contains an occurrence of any valid schedule.
+        (0..=31)
+            .filter_map(|back| now.date().checked_sub_days(Days::new(back)))
+            .filter(|d| self.matches(*d))
+            .map(|d| d.and_time(time))
+            .find(|at| *at <= now)
+    }
+
+    fn validate(&self) -> Result<(), AppError> {
+        if parse_time(self.time()).is_none() {
+            return Err(AppError::Invalid(format!(
+                "invalid time {:?}, expected HH:MM",
+                self.time()
+            )));
+        }
+        match self {
+            ExportSchedule::Weekly { weekday, .. } if *weekday > 6 => Err(AppError::Invalid(
+                "weekday must be 0 (Sunday) to 6 (Saturday)".into(),
+            )),
+            ExportSchedule::Monthly { day, .. } if !(1..=31).contains(day) => {
+                Err(AppError::Invalid("day must be between 1 and 31".into()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn local_time(rfc3339: &str) -> Option<NaiveDateTime> {
+    DateTime::parse_from_rfc3339(rfc3339)
+        .ok()
+        .map(|t| t.with_timezone(&Local).naive_local())
+}
+
+fn is_due(storage: &Storage, job: &ExportJob, now: NaiveDateTime) -> bool {
+    let Some(occurrence) = job.schedule.last_occurrence(now) else {
+        return false;
+    };
+    let last_run = match storage.list_export_runs(Some(&job.id), 1) {
+        Ok(runs) => runs.into_iter().next().map(|r| r.started_at),
+        Err(e) => {
+            log::error!("exports: failed to read history: {e}");
+            return false;
+        }
+    };
+    let since = last_run.unwrap_or_else(|| job.created_at.clone());
+    local_time(&since).map_or(true, |since| since < occurrence)
+}
+
+/// Keychain key for an encrypted job's passphrase.
+pub(crate) fn passphrase_key(job_id: &str) -> String {
+    format!("exports.{job_id}")
+}
+
+/// Writes `contents` to `path`, encrypted with `passphrase` if given. Returns the path
+/// written.
+fn write(path: &Path, contents: String, passphrase: Option<&str>) -> Result<PathBuf, String> {
+    let bytes = match passphrase {
+        Some(passphrase) => archive::encrypt(contents.as_bytes(), passphrase)?,
+        None => contents.into_bytes(),
+    };
+    std::fs::write(path, bytes).map_err(|e| format!("can't write {}: {e}", path.display()))?;
+    Ok(path.to_path_buf())
+}
+
+/// Writes `habitflow-<date>.<ext>` to `directory`, or `habitflow-<date>.<ext>.enc` when
+/// encrypted, replacing one from earlier that day.
+fn write_dated(
+    storage: &Storage,
+    directory: &str,
+    started: DateTime<FixedOffset>,
+    format: ExportFormat,
+    passphrase: Option<&str>,
+) -> Result<PathBuf, String> {
+    let habits = storage.list_habits().map_err(|e| e.to_string())?;
+    let categories = storage.list_categories().map_err(|e| e.to_string())?;
+    let dir = expand_home(directory);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("can't create {}: {e}", dir.display()))?;
+    let mut name = format!(
+        "habitflow-{}.{}",
+        date_key(started.date_naive()),
+        format.extension()
+    );
+    if passphrase.is_some() {
+        name = format!("{name}.{}", archive::EXTENSION);
+    }
+    write(
+        &dir.join(name),
+        render(format, &habits, &categories),
+        passphrase,
+    )
+}
+
+/// Held while an encrypted job runs and while passphrases are rotated (`key_rotation.rs`),
+/// so no file is written with a passphrase that's on its way out.
+pub(crate) static ENCRYPTED_WRITES: Mutex<()> = Mutex::new(());
+
+/// Writes one export for `job` and records the run.
+pub fn run_job(storage: &Storage, job: &ExportJob) -> ExportRun {
+    let _writing = job
+        .encrypted
+        .then(|| ENCRYPTED_WRITES.lock().unwrap_or_else(|e| e.into_inner()));
+    record(storage, &job.id, |started| {
+        let passphrase = if job.encrypted {
+            Some(
+                secrets::get(&passphrase_key(&job.id))?
+                    .ok_or("the job's passphrase is missing from the keychain")?,
+            )
+        } else {
+            None
+        };
+        write_dated(
+            storage,
+            &job.directory,
+            started,
+            job.format,
+            passphrase.as_deref(),
+        )
+    })
+}
+
+/// Runs `export` and records it in the history under `job_id`.
+fn record(
+    storage: &Storage,
+    job_id: &str,
+    export: impl FnOnce(DateTime<FixedOffset>) -> Result<PathBuf, String>,
+) -> ExportRun {
+    let started = crate::clock::now();
+    let result = export(started);
+    let mut run = ExportRun {
+        id: 0,
+        job_id: job_id.to_string(),
+        started_at: started.to_rfc3339(),
+        success: result.is_ok(),
+        path: result.as_ref().ok().map(|p| p.display().to_string()),
+        error: result.err(),
+    };
+    match storage.record_export_run(&run) {
+        Ok(id) => run.id = id,
+        Err(e) => log::error!("exports: failed to record run: {e}"),
+    }
+    run
+}
+
+/// Runs `job` and tells the user if it failed.
+fn run_and_notify(app: &AppHandle, job: &ExportJob) -> ExportRun {
+    let run = run_job(&app.state::<Storage>(), job);
+    if let Some(error) = &run.error {
+        log::warn!("exports: {} failed: {error}", job.name);
+        crate::reminders::notify_critical(
+            app,
+            &crate::i18n::t("export-failed-title"),
+            &format!("{}: {error}", job.name),
+        );
+    }
+    run
+}
+
+/// `jobId` of the exports written on quit in the export history.
+pub const QUIT_JOB_ID: &str = "quit";
+
+/// Set once the quit export is written, since several of the quit path's events call it.
+static QUIT_EXPORTED: AtomicBool = AtomicBool::new(false);
+
+/// Writes the export in `settings.quitExport`, if it's on. Called from every way out of the
+/// app; only the first call writes.
+pub fn export_on_quit(app: &AppHandle) {
+    let settings = app.state::<SettingsStore>().get().quit_export;
+    if !settings.enabled || QUIT_EXPORTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    if settings.directory.trim().is_empty() {
+        log::warn!("exports: the quit export has no directory");
+        return;
+    }
+    let storage = app.state::<Storage>();
+    let run = record(&storage, QUIT_JOB_ID, |started| {
+        write_dated(
+            &storage,
+            &settings.directory,
+            started,
+            settings.format,
+            None,
+        )
+    });
+    match (&run.path, &run.error) {
+        (Some(path), _) => log::info!("exports: wrote {path} on quit"),
+        (_, Some(error)) => log::warn!("exports: quit export failed: {error}"),
+        _ => {}
+    }
+}
+
+/// Runs every enabled job whose schedule has come up since its last run.
+pub fn run_due(app: &AppHandle) {
+    let now = crate::clock::now().naive_local();
+    let storage = app.state::<Storage>();
+    let jobs = app.state::<SettingsStore>().get().export_jobs;
+    for job in jobs
+        .iter()
+        .filter(|j| j.enabled && is_due(&storage, j, now))
+    {
+        run_and_notify(app, job);
+    }
+}
+
+pub fn add(
+    settings: &SettingsStore,
+    name: String,
+    format: ExportFormat,
+    directory: String,
+    schedule: ExportSchedule,
+    passphrase: Option<String>,
+) -> Result<ExportJob, AppError> {
+    if name.trim().is_empty() {
+        return Err(AppError::Invalid("name is required".into()));
+    }
+    if directory.trim().is_empty() {
+        return Err(AppError::Invalid("directory is required".into()));
+    }
+    schedule.validate()?;
+    if passphrase
+        .as_ref()
+        .is_some_and(|p| p.chars().count() < archive::MIN_PASSPHRASE_LEN)
+    {
+        return Err(AppError::Invalid(format!(
+            "the passphrase needs at least {} characters",
+            archive::MIN_PASSPHRASE_LEN
+        )));
+    }
+
+    let job = ExportJob {
+        id: generate_token()[..16].to_string(),
+        name,
+        format,
+        directory,
+        schedule,
+        enabled: true,
+        created_at: crate::clock::now().to_rfc3339(),
+        encrypted: passphrase.is_some(),
+    };
+    if let Some(passphrase) = &passphrase {
+        secrets::set(&passphrase_key(&job.id), passphrase)?;
+    }
+    settings
+        .update(|s| s.export_jobs.push(job.clone()))
+        .map_err(|e| e.to_string())?;
+    Ok(job)
+}
+
+pub fn remove(settings: &SettingsStore, id: &str) -> Result<(), String> {
+    if let Err(e) = secrets::delete(&passphrase_key(id)) {
+        log::warn!("exports: {e}");
+    }
+    settings
+        .update(|s| s.export_jobs.retain(|j| j.id != id))
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+pub fn find(settings: &SettingsStore, id: &str) -> Result<ExportJob, AppError> {
+    settings
+        .get()
+        .export_jobs
+        .into_iter()
+        .find(|j| j.id == id)
+        .ok_or_else(|| AppError::not_found("export job", id))
+}
+
+#[tauri::command]
+pub fn list_export_jobs(settings: State<'_, SettingsStore>) -> Vec<ExportJob> {
+    settings.get().export_jobs
+}
+
+/// `directory` has to have been picked with `pick_folder`. With a `passphrase`, every file
+/// the job writes is encrypted with it.
+#[tauri::command]
+pub fn add_export_job(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    name: String,
+    format: ExportFormat,
+    directory: String,
+    schedule: ExportSchedule,
+    passphrase: Option<String>,
+) -> Result<ExportJob, AppError> {
+    let directory = if directory.trim().is_empty() {
+        directory
+    } else {
+        crate::files::allowed_folder(&app, &directory).map_err(AppError::Forbidden)?
+    };
+    let job = add(&settings, name, format, directory, schedule, passphrase)?;
+    crate::redact::refresh_secrets(&app);
+    Ok(job)
+}
+
+#[tauri::command]
+pub fn remove_export_job(settings: State<'_, SettingsStore>, id: String) -> Result<(), AppError> {
+    Ok(remove(&settings, &id)?)
+}
+
+/// Runs a job immediately, regardless of its schedule or enabled state.
+#[tauri::command]
+pub fn run_export_job_now(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    id: String,
+) -> Result<ExportRun, AppError> {
+    let job = find(&settings, &id)?;
+    Ok(run_and_notify(&app, &job))
+}
+
+#[tauri::command]
+pub fn get_export_history(
+    storage: State<'_, Storage>,
+    job_id: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<ExportRun>, AppError> {
+    Ok(storage.list_export_runs(job_id.as_deref(), limit.unwrap_or(100))?)
+}
+
+/// Writes a one-off export to `path`, exactly, encrypted if there's a `passphrase`.
+/// Returns the path written.
+pub fn export_to(
+    storage: &Storage,
+    path: &Path,
+    format: ExportFormat,
+    passphrase: Option<&str>,
+) -> Result<PathBuf, String> {
+    let habits = storage.list_habits().map_err(|e| e.to_string())?;
+    let categories = storage.list_categories().map_err(|e| e.to_string())?;
+    write(path, render(format, &habits, &categories), passphrase)
+}
+
+/// Reads an export back, decrypting it if it's encrypted.
+pub fn read_export(path: &Path, passphrase: Option<&str>) -> Result<String, String> {
+    let data = std::fs::read(path).map_err(|e| format!("can't read {}: {e}", path.display()))?;
+    let data = if archive::is_encrypted(&data) {
+        let passphrase = passphrase.ok_or("this export is encrypted; enter its passphrase")?;
+        archive::decrypt(&data, passphrase)?
+    } else {
+        data
+    };
+    String::from_utf8(data).map_err(|_| "the export isn't valid text".into())
+}
+
+/// `path` has to come from `pick_save_path` (see `files.rs`).
+#[tauri::command]
+pub fn export_data(
+    app: AppHandle,
+    storage: State<'_, Storage>,
+    path: String,
+    format: ExportFormat,
+    passphrase: Option<String>,
+) -> Result<String, AppError> {
+    let path = crate::files::allowed(&app, &path)?;
+    Ok(export_to(&storage, &path, format, passphrase.as_deref())
+        .map(|p| p.display().to_string())?)
+}
+
+/// Returns the contents of an export or backup file, decrypted, for the webview to import.
+/// `path` has to come from `pick_open_path`.
+#[tauri::command]
+pub fn import_data(
+    app: AppHandle,
+    path: String,
+    passphrase: Option<String>,
+) -> Result<String, AppError> {
+    Ok(read_export(
+        &crate::files::allowed(&app, &path)?,
+        passphrase.as_deref(),
+    )?)
+}
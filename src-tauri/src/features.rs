@@ -0,0 +1,176 @@
+//! Feature flags for the subsystems that are still finding their shape.
+//!
+//! Each flag's default is set at build time: on, unless `HABITFLOW_FEATURES` lists it with
+//! a `-` (`HABITFLOW_FEATURES=-sync,-plugins`; `+name` turns it back on). `features` in the
+//! settings overrides a default at runtime, by name. A flag that's off keeps its subsystem
+//! from running whatever its own settings say, and the commands that would act on it are
+//! rejected. The commands that report status keep working, so the webview can see that
+//! the feature is off. `get_feature_flags` lists them all.
+
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+
+use serde::Serialize;
+use tauri::ipc::Invoke;
+use tauri::Runtime;
+
+use crate::error::AppError;
+use crate::settings::SettingsStore;
+
+/// Flag defaults as set when building.
+const BUILD_FEATURES: Option<&str> = option_env!("HABITFLOW_FEATURES");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Sync,
+    Gamification,
+    LocalApi,
+    Mqtt,
+    Plugins,
+    Libsql,
+}
+
+struct Definition {
+    feature: Feature,
+    name: &'static str,
+    description: &'static str,
+    /// Rejected while the flag is off.
+    commands: &'static [&'static str],
+}
+
+const FEATURES: &[Definition] = &[
+    Definition {
+        feature: Feature::Sync,
+        name: "sync",
+        description: "Sending changes to a sync server",
+        commands: &["sync_now", "reset_sync"],
+    },
+    Definition {
+        feature: Feature::Gamification,
+        name: "gamification",
+        description: "XP, levels and level-up notifications",
+        commands: &[],
+    },
+    Definition {
+        feature: Feature::LocalApi,
+        name: "localApi",
+        description: "The HTTP API on localhost",
+        commands: &[],
+    },
+    Definition {
+        feature: Feature::Mqtt,
+        name: "mqtt",
+        description: "Publishing to an MQTT broker and Home Assistant",
+        commands: &[],
+    },
+    Definition {
+        feature: Feature::Plugins,
+        name: "plugins",
+        description: "WebAssembly plugins",
+        commands: &["reload_plugins", "enable_plugin", "run_plugin_export"],
+    },
+    Definition {
+        feature: Feature::Libsql,
+        name: "libsql",
+        description: "Keeping the data in a remote libSQL/Turso database too",
+        commands: &["libsql_sync_now"],
+    },
+];
+
+/// `features` from the settings, as last applied.
+static OVERRIDES: RwLock<BTreeMap<String, bool>> = RwLock::new(BTreeMap::new());
+
+fn definition(feature: Feature) -> &'static Definition {
+    FEATURES
+        .iter()
+        .find(|d| d.feature == feature)
+        .expect("every feature is defined")
+}
+
+/// The build's default for `name`: on unless `HABITFLOW_FEATURES` says otherwise.
+fn default(name: &str) -> bool {
+    BUILD_FEATURES
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter_map(|entry| match entry.strip_prefix('-') {
+            Some(rest) => Some((rest, false)),
+            None => Some((entry.strip_prefix('+')?, true)),
+        })
+        .rev()
+        .find(|(flag, _)| *flag == name)
+        .map_or(true, |(_, on)| on)
+}
+
+fn overridden(name: &str) -> Option<bool> {
+    OVERRIDES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .copied()
+}
+
+pub fn enabled(feature: Feature) -> bool {
+    let name = definition(feature).name;
+    overridden(name).unwrap_or_else(|| default(name))
+}
+
+/// Follows `features` in the settings. Unknown names are kept but do nothing.
+pub fn apply(settings: &SettingsStore) {
+    let overrides = settings.get().features;
+    for name in overrides.keys() {
+        if !FEATURES.iter().any(|d| d.name == name) {
+            log::warn!("features: no feature called {name:?}");
+        }
+    }
+    *OVERRIDES.write().unwrap_or_else(|e| e.into_inner()) = overrides;
+}
+
+/// Wraps the invoke handler so the commands of a feature that's off are rejected.
+pub fn guarded<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let command = invoke.message.command();
+        let off = FEATURES
+            .iter()
+            .find(|d| d.commands.contains(&command) && !enabled(d.feature));
+        if let Some(definition) = off {
+            invoke.resolver.reject(AppError::Forbidden(format!(
+                "the {} feature is turned off",
+                definition.name
+            )));
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureFlag {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub enabled: bool,
+    /// What this build does without an override.
+    pub default: bool,
+    /// Set in `features` in the settings.
+    pub overridden: bool,
+}
+
+#[tauri::command]
+pub fn get_feature_flags() -> Vec<FeatureFlag> {
+    FEATURES
+        .iter()
+        .map(|d| {
+            let overridden = overridden(d.name);
+            FeatureFlag {
+                name: d.name,
+                description: d.description,
+                enabled: overridden.unwrap_or_else(|| default(d.name)),
+                default: default(d.name),
+                overridden: overridden.is_some(),
+            }
+        })
+        .collect()
+}
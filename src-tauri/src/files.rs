@@ -0,0 +1,280 @@
+//! File access for the webview, which has no filesystem scope of its own (see
+//! `capabilities/default.json`). It can only reach files the user picked in a dialog the
+//! backend showed this session, and the automatic backups in the app's data directory.
+//! Paths are canonicalized before they're checked, so `..` and symlinks can't lead
+//! outside either.
+//!
+//! Folders the app writes to on its own are kept in the settings: the last one a backup
+//! was saved to (`files.exportDir`), each export job's and the quit export's. They only
+//! ever come from a dialog: `SettingsStore::replace_with` keeps them, and `update_settings`
+//! only takes a new one the user picked with `pick_folder` this session.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use serde::Deserialize;
+use tauri::{AppHandle, Manager, Wry};
+use tauri_plugin_dialog::{DialogExt, FileDialogBuilder, FilePath};
+
+use crate::error::AppError;
+use crate::settings::SettingsStore;
+use crate::stats::{date_key, today};
+
+/// Where automatic backups go, inside the app's data directory.
+const AUTO_BACKUP_DIR: [&str; 2] = ["HabitFlow", "Backups"];
+
+/// Files and folders picked in a dialog this session, canonical.
+#[derive(Default)]
+pub struct Grants {
+    files: Mutex<HashSet<PathBuf>>,
+    folders: Mutex<HashSet<PathBuf>>,
+}
+
+impl Grants {
+    fn files(&self) -> MutexGuard<'_, HashSet<PathBuf>> {
+        self.files.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn folders(&self) -> MutexGuard<'_, HashSet<PathBuf>> {
+        self.folders.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileFilter {
+    pub name: String,
+    pub extensions: Vec<String>,
+}
+
+/// `path` with symlinks and `..` resolved. A file that doesn't exist yet is resolved
+/// through its folder, which has to.
+fn canonical(path: &Path) -> Result<PathBuf, String> {
+    if path.exists() {
+        return path
+            .canonicalize()
+            .map_err(|e| format!("can't open {}: {e}", path.display()));
+    }
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(format!("{} isn't a file", path.display()));
+    };
+    dir.canonicalize()
+        .map(|dir| dir.join(name))
+        .map_err(|e| format!("can't open {}: {e}", dir.display()))
+}
+
+/// The canonical form of `path` if the webview may use it.
+pub fn allowed(app: &AppHandle, path: &str) -> Result<PathBuf, String> {
+    let path = canonical(Path::new(path))?;
+    if app.state::<Grants>().files().contains(&path) {
+        return Ok(path);
+    }
+    let backups = app.path().app_data_dir().ok().and_then(|mut dir| {
+        dir.extend(AUTO_BACKUP_DIR);
+        dir.canonicalize().ok()
+    });
+    if backups.is_some_and(|dir| path.starts_with(dir)) {
+        return Ok(path);
+    }
+    Err(format!(
+        "{} wasn't chosen in a file dialog; pick it again",
+        path.display()
+    ))
+}
+
+/// The canonical form of `dir` if the user picked it with [`pick_folder`] this session.
+pub fn allowed_folder(app: &AppHandle, dir: &str) -> Result<String, String> {
+    let dir = crate::exports::expand_home(dir);
+    let canonical = dir
+        .canonicalize()
+        .map_err(|e| format!("can't open {}: {e}", dir.display()))?;
+    if app.state::<Grants>().folders().contains(&canonical) {
+        Ok(canonical.display().to_string())
+    } else {
+        Err(format!(
+            "{} wasn't chosen in a folder dialog; pick it again",
+            canonical.display()
+        ))
+    }
+}
+
+/// `current`, or `new` if it's another folder the user picked this session (or none).
+pub fn kept_folder(app: &AppHandle, current: &str, new: &str) -> String {
+    if new == current || new.trim().is_empty() {
+        return new.to_string();
+    }
+    match allowed_folder(app, new) {
+        Ok(dir) => dir,
+        Err(e) => {
+            log::warn!("files: keeping {current:?}: {e}");
+            current.to_string()
+        }
+    }
+}
+
+/// A bare file name, without folders.
+fn bare_name(name: &str) -> Result<&str, String> {
+    if !name.is_empty() && Path::new(name).file_name().is_some_and(|n| n == name) {
+        Ok(name)
+    } else {
+        Err(format!("{name:?} isn't a file name"))
+    }
+}
+
+/// Documents/HabitFlow, created if needed, where the dialogs open by default.
+fn default_dir(app: &AppHandle) -> Option<PathBuf> {
+    let dir = app.path().document_dir().ok()?.join("HabitFlow");
+    std::fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+fn dialog(app: &AppHandle, filters: &[FileFilter]) -> FileDialogBuilder<Wry> {
+    let mut dialog = app.dialog().file();
+    for filter in filters {
+        let extensions: Vec<&str> = filter.extensions.iter().map(String::as_str).collect();
+        dialog = dialog.add_filter(&filter.name, &extensions);
+    }
+    if let Some(window) = app.get_webview_window(crate::access::MAIN_WINDOW) {
+        dialog = dialog.set_parent(&window);
+    }
+    dialog
+}
+
+/// Records the path the user picked, if they picked one.
+fn grant(app: &AppHandle, picked: Option<FilePath>) -> Result<Option<PathBuf>, String> {
+    let Some(picked) = picked else {
+        return Ok(None);
+    };
+    let path = canonical(&picked.into_path().map_err(|e| e.to_string())?)?;
+    app.state::<Grants>().files().insert(path.clone());
+    Ok(Some(path))
+}
+
+async fn save_dialog(
+    app: &AppHandle,
+    file_name: &str,
+    filters: &[FileFilter],
+) -> Result<Option<PathBuf>, String> {
+    let mut dialog = dialog(app, filters).set_file_name(file_name);
+    if let Some(dir) = default_dir(app) {
+        dialog = dialog.set_directory(dir);
+    }
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    dialog.save_file(move |picked| {
+        let _ = tx.send(picked);
+    });
+    grant(app, rx.await.map_err(|e| e.to_string())?)
+}
+
+/// Shows a save dialog and returns the chosen path, which the export and import commands
+/// then accept; `None` if cancelled. With `encrypted`, for an export with a passphrase, the
+/// name gets `.enc` and the dialog only offers encrypted exports, since that's the file
+/// `export_data` will write.
+#[tauri::command]
+pub async fn pick_save_path(
+    app: AppHandle,
+    file_name: String,
+    filters: Vec<FileFilter>,
+    encrypted: Option<bool>,
+) -> Result<Option<String>, AppError> {
+    let mut file_name = bare_name(&file_name)?.to_string();
+    let mut filters = filters;
+    if encrypted == Some(true) {
+        let extension = crate::archive::EXTENSION;
+        if !file_name.ends_with(&format!(".{extension}")) {
+            file_name = format!("{file_name}.{extension}");
+        }
+        filters = vec![FileFilter {
+            name: "Encrypted export".into(),
+            extensions: vec![extension.into()],
+        }];
+    }
+    let path = save_dialog(&app, &file_name, &filters).await?;
+    Ok(path.map(|p| p.display().to_string()))
+}
+
+/// Shows an open dialog and returns the chosen file; `None` if cancelled.
+#[tauri::command]
+pub async fn pick_open_path(
+    app: AppHandle,
+    filters: Vec<FileFilter>,
+) -> Result<Option<String>, AppError> {
+    let mut dialog = dialog(&app, &filters);
+    if let Some(dir) = default_dir(&app) {
+        dialog = dialog.set_directory(dir);
+    }
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    dialog.pick_file(move |picked| {
+        let _ = tx.send(picked);
+    });
+    let path = grant(&app, rx.await.map_err(|e| e.to_string())?)?;
+    Ok(path.map(|p| p.display().to_string()))
+}
+
+/// Shows a folder dialog and returns the chosen folder, which export jobs and the quit
+/// export then accept; `None` if cancelled.
+#[tauri::command]
+pub async fn pick_folder(app: AppHandle) -> Result<Option<String>, AppError> {
+    let mut dialog = dialog(&app, &[]);
+    if let Some(dir) = default_dir(&app) {
+        dialog = dialog.set_directory(dir);
+    }
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    dialog.pick_folder(move |picked| {
+        let _ = tx.send(picked);
+    });
+    let Some(picked) = rx.await.map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    let dir = picked.into_path().map_err(|e| e.to_string())?;
+    let dir = dir
+        .canonicalize()
+        .map_err(|e| format!("can't open {}: {e}", dir.display()))?;
+    app.state::<Grants>().folders().insert(dir.clone());
+    Ok(Some(dir.display().to_string()))
+}
+
+/// Writes a backup the webview rendered into the folder used last time, or where the
+/// user picks if there's none yet or it's gone. Returns the file's path, or `None` if the
+/// dialog was cancelled.
+#[tauri::command]
+pub async fn save_backup(
+    app: AppHandle,
+    file_name: String,
+    contents: String,
+) -> Result<Option<String>, AppError> {
+    let name = bare_name(&file_name)?;
+    let settings = app.state::<SettingsStore>();
+    if let Some(dir) = settings.get().files.export_dir {
+        let path = Path::new(&dir).join(name);
+        match std::fs::create_dir_all(&dir).and_then(|()| std::fs::write(&path, &contents)) {
+            Ok(()) => return Ok(Some(path.display().to_string())),
+            Err(e) => log::warn!("files: can't write to {dir}, asking again: {e}"),
+        }
+    }
+    let filters = [FileFilter {
+        name: "JSON".into(),
+        extensions: vec!["json".into()],
+    }];
+    let Some(path) = save_dialog(&app, name, &filters).await? else {
+        return Ok(None);
+    };
+    std::fs::write(&path, contents).map_err(|e| format!("can't write {}: {e}", path.display()))?;
+    let dir = path.parent().map(|d| d.display().to_string());
+    if let Err(e) = settings.update(|s| s.files.export_dir = dir) {
+        log::warn!("files: failed to remember the backup folder: {e}");
+    }
+    Ok(Some(path.display().to_string()))
+}
+
+/// Writes today's automatic backup into the app's data directory.
+#[tauri::command]
+pub fn write_auto_backup(app: AppHandle, contents: String) -> Result<String, AppError> {
+    let mut dir = app.path().app_data_dir()?;
+    dir.extend(AUTO_BACKUP_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| format!("can't create {}: {e}", dir.display()))?;
+    let path = dir.join(format!("autobackup-{}.json", date_key(today())));
+    std::fs::write(&path, contents).map_err(|e| format!("can't write {}: {e}", path.display()))?;
+    Ok(path.display().to_string())
+}
@@ -0,0 +1,228 @@
+//! Focus mode: a countdown spent on one habit, started from the tray's Focus menu or
+//! `start_focus`.
+//!
+//! While it runs, notifications other than critical ones (failed backups, closed apps)
+//! are held back, and the tray shows the time left. When the countdown finishes the
+//! session is logged like a Pomodoro work interval: its length for habits with a time
+//! target, one unit for other quantified habits, and a check-off for plain ones. Stopping
+//! early logs nothing. Sessions don't survive quitting the app.
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::i18n::{t, t_args};
+use crate::settings::SettingsStore;
+use crate::storage::Storage;
+
+/// Emitted with `get_focus`'s payload whenever a session starts or ends.
+pub const FOCUS_CHANGED_EVENT: &str = "focus:changed";
+
+const TICK: Duration = Duration::from_secs(1);
+const MAX_MINUTES: u32 = 8 * 60;
+
+struct Session {
+    habit_id: String,
+    minutes: u32,
+    started: Instant,
+}
+
+impl Session {
+    fn remaining(&self) -> Duration {
+        Duration::from_secs(u64::from(self.minutes) * 60).saturating_sub(self.started.elapsed())
+    }
+}
+
+#[derive(Default)]
+pub struct Focus {
+    session: Mutex<Option<Session>>,
+    /// Notifications held back during the current session.
+    held: AtomicU32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FocusStatus {
+    pub habit_id: String,
+    pub minutes: u32,
+    pub remaining_secs: u64,
+    /// Notifications held back so far.
+    pub held: u32,
+}
+
+impl Focus {
+    fn lock(&self) -> MutexGuard<'_, Option<Session>> {
+        self.session.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn status(&self) -> Option<FocusStatus> {
+        self.lock().as_ref().map(|s| FocusStatus {
+            habit_id: s.habit_id.clone(),
+            minutes: s.minutes,
+            remaining_secs: s.remaining().as_secs(),
+            held: self.held.load(Ordering::Relaxed),
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Counts a notification that wasn't shown. Returns false when there's no session, in
+    /// which case it should be shown.
+    pub(crate) fn hold(&self) -> bool {
+        let active = self.is_active();
+        if active {
+            self.held.fetch_add(1, Ordering::Relaxed);
+        }
+        active
+    }
+
+    fn begin(&self, habit_id: String, minutes: u32) {
+        self.held.store(0, Ordering::Relaxed);
+        *self.lock() = Some(Session {
+            habit_id,
+            minutes,
+            started: Instant::now(),
+        });
+    }
+
+    fn end(&self) -> Option<Session> {
+        self.lock().take()
+    }
+
+    /// The session, if its countdown has run out.
+    fn finished(&self) -> Option<Session> {
+        let mut guard = self.lock();
+        if guard.as_ref()?.remaining().is_zero() {
+            guard.take()
+        } else {
+            None
+        }
+    }
+}
+
+/// `🎯 MM:SS`, with the habit unless names are hidden, for the tray.
+fn countdown(app: &AppHandle, status: &FocusStatus) -> String {
+    let secs = status.remaining_secs;
+    let time = format!("🎯 {:02}:{:02}", secs / 60, secs % 60);
+    if crate::privacy::hide_names(app) {
+        return time;
+    }
+    match app.state::<Storage>().get_habit(&status.habit_id) {
+        Ok(Some(habit)) => format!("{time} · {}", crate::appearance::label(&habit)),
+        _ => time,
+    }
+}
+
+fn changed(app: &AppHandle) {
+    let status = app.state::<Focus>().status();
+    crate::tray::set_status(app, status.as_ref().map(|s| countdown(app, s)).as_deref());
+    crate::tray::refresh(app);
+    let _ = app.emit(FOCUS_CHANGED_EVENT, status);
+}
+
+/// Starts a session on `habit_id` for `minutes` (`settings.focus.minutes` when left out),
+/// replacing any current one.
+pub fn begin(
+    app: &AppHandle,
+    habit_id: &str,
+    minutes: Option<u32>,
+) -> Result<FocusStatus, AppError> {
+    if app.state::<Storage>().get_habit(habit_id)?.is_none() {
+        return Err(AppError::not_found("habit", habit_id));
+    }
+    if app.state::<crate::pomodoro::Pomodoro>().status().is_some() {
+        return Err(AppError::Invalid(
+            "a Pomodoro session is running; stop it first".into(),
+        ));
+    }
+    let minutes = minutes.unwrap_or(app.state::<SettingsStore>().get().focus.minutes);
+    if !(1..=MAX_MINUTES).contains(&minutes) {
+        return Err(AppError::Invalid(format!(
+            "a focus session lasts between 1 and {MAX_MINUTES} minutes"
+        )));
+    }
+    let focus = app.state::<Focus>();
+    focus.begin(habit_id.to_string(), minutes);
+    log::info!("focus: {minutes} minutes on {habit_id}");
+    changed(app);
+    focus
+        .status()
+        .ok_or_else(|| AppError::Failed("focus session ended".into()))
+}
+
+/// Ends the session early without logging it. Returns false without a session.
+pub fn end(app: &AppHandle) -> bool {
+    let ended = app.state::<Focus>().end().is_some();
+    if ended {
+        changed(app);
+    }
+    ended
+}
+
+fn finish(app: &AppHandle, session: Session) {
+    let held = app.state::<Focus>().held.swap(0, Ordering::Relaxed);
+    changed(app);
+    if let Err(e) = crate::pomodoro::log_progress(app, &session.habit_id, session.minutes) {
+        log::warn!("focus: failed to log {}: {e}", session.habit_id);
+    }
+    let habit = match app.state::<Storage>().get_habit(&session.habit_id) {
+        Ok(Some(habit)) if !crate::privacy::hide_names(app) => crate::appearance::label(&habit),
+        _ => t("focus-habit-hidden"),
+    };
+    let mut body = t_args(
+        "focus-done-body",
+        &[("minutes", session.minutes.into()), ("habit", habit.into())],
+    );
+    if held > 0 {
+        body.push('\n');
+        body.push_str(&t_args("focus-held", &[("count", held.into())]));
+    }
+    crate::reminders::notify(app, &t("focus-done-title"), &body);
+}
+
+/// Ends sessions whose countdown ran out and keeps the tray's countdown current.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            interval.tick().await;
+            let focus = app.state::<Focus>();
+            if let Some(session) = focus.finished() {
+                finish(&app, session);
+            } else if let Some(status) = focus.status() {
+                crate::tray::set_status(&app, Some(&countdown(&app, &status)));
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_focus(focus: State<'_, Focus>) -> Option<FocusStatus> {
+    focus.status()
+}
+
+/// Starts focusing on a habit; notifications wait until the session ends.
+#[tauri::command]
+pub fn start_focus(
+    app: AppHandle,
+    habit_id: String,
+    minutes: Option<u32>,
+) -> Result<FocusStatus, AppError> {
+    begin(&app, &habit_id, minutes)
+}
+
+/// Ends the session early; nothing is logged.
+#[tauri::command]
+pub fn stop_focus(app: AppHandle) -> Result<(), AppError> {
+    if !end(&app) {
+        return Err(AppError::Invalid("no focus session".into()));
+    }
+    Ok(())
+}
@@ -0,0 +1,137 @@
+//! Streak freezes: every `PERFECT_DAYS_PER_FREEZE` perfect days (every daily habit done)
+//! earn a token, and at day rollover a token is spent on a missed day that would
+//! otherwise end a daily streak. The frozen day then counts like an excused one: the
+//! streak carries on across it without growing. Each token covers one missed day.
+//!
+//! Tokens are a shared balance across habits; progress lives in `meta` and every use is
+//! recorded in the `streak_freezes` table.
+
+use chrono::{Days, NaiveDate};
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::AppError;
+use crate::events::HabitEvent;
+use crate::model::{Habit, HabitKind};
+use crate::schedule::Schedule;
+use crate::stats::{current_streak, date_key};
+use crate::storage::{FreezeUse, Storage};
+
+pub const PERFECT_DAYS_PER_FREEZE: u32 = 7;
+
+const PERFECT_DAYS_KEY: &str = "freezes.perfect_days";
+const EARNED_KEY: &str = "freezes.earned";
+
+/// Never settle more than this many days at once (the app was closed for a long time).
+const MAX_SETTLE_DAYS: u64 = 366;
+
+/// The habits a freeze can protect: active, daily, build habits.
+fn protected(habit: &Habit) -> bool {
+    !habit.archived && habit.kind == HabitKind::Build && Schedule::of(habit) == Schedule::Daily
+}
+
+/// Every protected habit that existed on `date` and wasn't excused was done, and there was
+/// at least one.
+pub(crate) fn is_perfect(habits: &[Habit], date: NaiveDate) -> bool {
+    let key = date_key(date);
+    let mut counted = habits
+        .iter()
+        .filter(|h| protected(h) && h.start_date() <= key.as_str() && !h.is_excused(&key))
+        .peekable();
+    counted.peek().is_some() && counted.all(|h| h.is_done_on(&key))
+}
+
+fn read_counter(storage: &Storage, key: &str) -> rusqlite::Result<u32> {
+    Ok(storage
+        .get_meta(key)?
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0))
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StreakFreezes {
+    /// Tokens available to spend.
+    pub balance: u32,
+    pub earned: u32,
+    pub used: u32,
+    /// Perfect days still needed for the next token.
+    pub days_to_next: u32,
+    /// Newest first.
+    pub history: Vec<FreezeUse>,
+}
+
+pub fn summary(storage: &Storage) -> rusqlite::Result<StreakFreezes> {
+    let earned = read_counter(storage, EARNED_KEY)?;
+    let used = storage.count_streak_freezes()?;
+    let perfect = read_counter(storage, PERFECT_DAYS_KEY)?;
+    Ok(StreakFreezes {
+        balance: earned.saturating_sub(used),
+        earned,
+        used,
+        days_to_next: PERFECT_DAYS_PER_FREEZE - perfect % PERFECT_DAYS_PER_FREEZE,
+        history: storage.list_streak_freezes()?,
+    })
+}
+
+/// Settles the days from `previous` up to (not including) `today`: counts perfect days
+/// toward new tokens, then spends tokens on misses that would break a streak. Returns the
+/// `StreakFrozen` events to publish.
+pub fn settle(
+    storage: &Storage,
+    previous: NaiveDate,
+    today: NaiveDate,
+) -> rusqlite::Result<Vec<HabitEvent>> {
+    let mut habits = storage.list_habits()?;
+    let mut perfect = read_counter(storage, PERFECT_DAYS_KEY)?;
+    let mut earned = read_counter(storage, EARNED_KEY)?;
+    let mut balance = earned.saturating_sub(storage.count_streak_freezes()?);
+    let mut events = Vec::new();
+
+    let start = today
+        .checked_sub_days(Days::new(MAX_SETTLE_DAYS))
+        .map_or(previous, |floor| previous.max(floor));
+    for day in start.iter_days().take_while(|d| *d < today) {
+        if is_perfect(&habits, day) {
+            perfect += 1;
+            if perfect % PERFECT_DAYS_PER_FREEZE == 0 {
+                earned += 1;
+                balance += 1;
+            }
+            continue;
+        }
+
+        let key = date_key(day);
+        for habit in habits.iter_mut().filter(|h| protected(h)) {
+            if balance == 0 {
+                break;
+            }
+            if habit.is_done_on(&key) || habit.is_excused(&key) || habit.is_frozen(&key) {
+                continue;
+            }
+            // Counts back from the day before, since `day` itself isn't done.
+            let streak = current_streak(habit, day);
+            if streak == 0 || !storage.insert_streak_freeze(&habit.id, &key)? {
+                continue;
+            }
+            balance -= 1;
+            habit.frozen.insert(key.clone());
+            events.push(HabitEvent::StreakFrozen {
+                habit_id: habit.id.clone(),
+                name: habit.name.clone(),
+                date: key.clone(),
+                streak,
+            });
+        }
+    }
+
+    storage.set_meta(PERFECT_DAYS_KEY, &perfect.to_string())?;
+    storage.set_meta(EARNED_KEY, &earned.to_string())?;
+    Ok(events)
+}
+
+/// Token balance, progress toward the next one and where tokens were spent.
+#[tauri::command]
+pub fn get_streak_freezes(storage: State<'_, Storage>) -> Result<StreakFreezes, AppError> {
+    Ok(summary(&storage)?)
+}
@@ -0,0 +1,238 @@
+//! XP and levels, off unless `settings.gamification.enabled`.
+//!
+//! XP is recomputed from the history rather than accumulated, so undoing a check-in or
+//! editing past days can't leave it out of step. Each completion is worth its habit's
+//! difficulty (easy 5, medium 10, hard 20), times a streak multiplier of +10% per full week
+//! of consecutive days done, capped at ×2. Reaching level `n` takes `50·n·(n−1)` XP in
+//! total: 100 for level 2, 300 for level 3, 600 for level 4, and so on. Completed daily
+//! challenges (`challenges.rs`) add their bonus on top.
+//!
+//! A background task watches the data; when the level goes up it publishes a `levelUp`
+//! event, shows a notification and emits `player:level-up`. Every change emits
+//! `player:changed`, and the tray tooltip shows the level.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::AppError;
+use crate::events::{EventBus, HabitEvent};
+use crate::features::{self, Feature};
+use crate::model::{ChallengeStatus, Difficulty, Habit};
+use crate::settings::SettingsStore;
+use crate::stats::{date_key, parse_date, today};
+use crate::storage::Storage;
+
+pub const PLAYER_CHANGED_EVENT: &str = "player:changed";
+pub const LEVEL_UP_EVENT: &str = "player:level-up";
+const LEVEL_KEY: &str = "gamification.level";
+
+pub fn base_xp(difficulty: Difficulty) -> u64 {
+    match difficulty {
+        Difficulty::Easy => 5,
+        Difficulty::Medium => 10,
+        Difficulty::Hard => 20,
+    }
+}
+
+/// 1.0, plus 0.1 per full week in a run of `run` consecutive days, at most 2.0.
+pub fn streak_multiplier(run: u32) -> f64 {
+    1.0 + f64::from((run / 7).min(10)) / 10.0
+}
+
+/// Total XP needed to reach `level`.
+pub fn level_threshold(level: u32) -> u64 {
+    let n = u64::from(level.max(1));
+    50 * n * (n - 1)
+}
+
+pub fn level_for(xp: u64) -> u32 {
+    let mut level = 1;
+    while level_threshold(level + 1) <= xp {
+        level += 1;
+    }
+    level
+}
+
+/// XP each completion of `habit` earned, by day.
+fn awards(habit: &Habit) -> Vec<(NaiveDate, u64)> {
+    let base = base_xp(habit.difficulty) as f64;
+    let mut run = 0;
+    let mut last: Option<NaiveDate> = None;
+    habit
+        .done_dates()
+        .into_iter()
+        .filter_map(parse_date)
+        .map(|date| {
+            run = match last {
+                Some(prev) if prev.succ_opt() == Some(date) => run + 1,
+                _ => 1,
+            };
+            last = Some(date);
+            (date, (base * streak_multiplier(run)).round() as u64)
+        })
+        .collect()
+}
+
+/// XP completions from `from` through `to` earned, leaving out challenge bonuses.
+pub fn xp_between(habits: &[Habit], from: NaiveDate, to: NaiveDate) -> u64 {
+    habits
+        .iter()
+        .flat_map(awards)
+        .filter(|(date, _)| (from..=to).contains(date))
+        .map(|(_, award)| award)
+        .sum()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayerState {
+    pub enabled: bool,
+    pub xp: u64,
+    pub level: u32,
+    /// XP earned since reaching `level`.
+    pub level_xp: u64,
+    /// XP between `level` and the next one.
+    pub next_level_xp: u64,
+    pub xp_today: u64,
+}
+
+/// `bonus` and `bonus_today` are XP from completed daily challenges.
+pub fn player_state(
+    habits: &[Habit],
+    (bonus, bonus_today): (u64, u64),
+    enabled: bool,
+    today: NaiveDate,
+) -> PlayerState {
+    let (mut xp, mut xp_today) = (bonus, bonus_today);
+    for habit in habits {
+        for (date, award) in awards(habit) {
+            xp += award;
+            if date == today {
+                xp_today += award;
+            }
+        }
+    }
+    let level = level_for(xp);
+    PlayerState {
+        enabled,
+        xp,
+        level,
+        level_xp: xp - level_threshold(level),
+        next_level_xp: level_threshold(level + 1) - level_threshold(level),
+        xp_today,
+    }
+}
+
+pub fn load(storage: &Storage, settings: &SettingsStore) -> Result<PlayerState, String> {
+    let habits = storage.list_habits().map_err(|e| e.to_string())?;
+    let today = today();
+    let bonus = storage.challenge_xp().map_err(|e| e.to_string())?;
+    let bonus_today = storage
+        .get_challenge(&date_key(today))
+        .map_err(|e| e.to_string())?
+        .filter(|c| c.status == ChallengeStatus::Completed)
+        .map_or(0, |c| u64::from(c.xp));
+    Ok(player_state(
+        &habits,
+        (bonus, bonus_today),
+        settings.get().gamification.enabled && features::enabled(Feature::Gamification),
+        today,
+    ))
+}
+
+pub fn set_difficulty(
+    storage: &Storage,
+    habit_id: &str,
+    difficulty: Difficulty,
+) -> Result<Habit, AppError> {
+    if !storage.set_habit_difficulty(habit_id, difficulty)? {
+        return Err(AppError::not_found("habit", habit_id));
+    }
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+/// The tray tooltip while gamification is on.
+pub fn tooltip(state: &PlayerState) -> String {
+    crate::i18n::t_args(
+        "tray-tooltip-level",
+        &[
+            ("level", state.level.into()),
+            ("xp", state.level_xp.into()),
+            ("next", state.next_level_xp.into()),
+        ],
+    )
+}
+
+fn update(app: &AppHandle) {
+    let storage = app.state::<Storage>();
+    let state = match load(&storage, &app.state::<SettingsStore>()) {
+        Ok(s) => s,
+        Err(e) => {
+            log::warn!("gamification: failed to compute XP: {e}");
+            return;
+        }
+    };
+    if !state.enabled {
+        return;
+    }
+    let previous = storage
+        .get_meta(LEVEL_KEY)
+        .ok()
+        .flatten()
+        .and_then(|l| l.parse::<u32>().ok());
+    if previous != Some(state.level) {
+        if let Err(e) = storage.set_meta(LEVEL_KEY, &state.level.to_string()) {
+            log::warn!("gamification: failed to store the level: {e}");
+        }
+    }
+    // The first run only records the level; losing XP drops it silently.
+    if previous.is_some_and(|p| state.level > p) {
+        app.state::<EventBus>().publish(HabitEvent::LevelUp {
+            level: state.level,
+            xp: state.xp,
+            date: date_key(today()),
+        });
+        crate::reminders::notify(
+            app,
+            &crate::i18n::t("level-up-title"),
+            &crate::i18n::t_args("level-up-body", &[("level", state.level.into())]),
+        );
+        let _ = app.emit(LEVEL_UP_EVENT, &state);
+    }
+    let _ = app.emit(PLAYER_CHANGED_EVENT, &state);
+}
+
+/// Recomputes XP on startup and after every data change.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    let mut data = app.state::<EventBus>().watch_data();
+    tauri::async_runtime::spawn(async move {
+        update(&app);
+        while data.changed().await.is_ok() {
+            update(&app);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_player_state(app: AppHandle) -> Result<PlayerState, AppError> {
+    Ok(load(
+        &app.state::<Storage>(),
+        &app.state::<SettingsStore>(),
+    )?)
+}
+
+#[tauri::command]
+pub fn set_habit_difficulty(
+    app: AppHandle,
+    habit_id: String,
+    difficulty: Difficulty,
+) -> Result<Habit, AppError> {
+    let habit = set_difficulty(&app.state::<Storage>(), &habit_id, difficulty)?;
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(&app);
+    Ok(habit)
+}
@@ -0,0 +1,208 @@
+//! Goals: a target for one habit over a month or a quarter ("run 100 km this quarter").
+//!
+//! Progress is computed here from the habit's logs so the webview doesn't have to replay
+//! months of history: logged amounts are summed for quantified (and several-times-a-day)
+//! habits, completions are counted for the rest. Each goal also knows its deadline, the
+//! pace needed to meet it and whether it's on track.
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::AppError;
+use crate::model::{Goal, GoalPeriod, Habit};
+use crate::settings::generate_token;
+use crate::stats::{date_arg, date_key, parse_date, today};
+use crate::storage::Storage;
+
+/// First and last day of the `period` containing `date`.
+pub fn bounds(period: GoalPeriod, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let (month, months) = match period {
+        GoalPeriod::Month => (date.month(), 1),
+        GoalPeriod::Quarter => ((date.month() - 1) / 3 * 3 + 1, 3),
+    };
+    let start = NaiveDate::from_ymd_opt(date.year(), month, 1).unwrap_or(date);
+    (start, start + Months::new(months) - Days::new(1))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GoalStatus {
+    Upcoming,
+    OnTrack,
+    Behind,
+    Achieved,
+    Missed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GoalProgress {
+    #[serde(flatten)]
+    pub goal: Goal,
+    /// The habit's target unit; `None` means the goal counts completions.
+    pub unit: Option<String>,
+    pub current: f64,
+    /// 0–100, capped.
+    pub percent: f64,
+    /// Where a steady pace would be by the end of today.
+    pub expected: f64,
+    pub days_total: u32,
+    /// Including today; 0 once the deadline has passed.
+    pub days_left: u32,
+    /// What's still needed each remaining day, while the goal is open.
+    pub needed_per_day: Option<f64>,
+    pub status: GoalStatus,
+}
+
+fn amount(habit: &Habit, start: NaiveDate, end: NaiveDate) -> f64 {
+    let (start, end) = (date_key(start), date_key(end));
+    let in_range = |d: &str| d >= start.as_str() && d <= end.as_str();
+    if habit.target.is_some() || habit.is_repeated() {
+        habit
+            .values
+            .iter()
+            .filter(|(d, _)| in_range(d))
+            .map(|(_, v)| v)
+            .sum()
+    } else {
+        habit
+            .done_dates()
+            .into_iter()
+            .filter(|d| in_range(d))
+            .count() as f64
+    }
+}
+
+pub fn progress(goal: Goal, habit: &Habit, today: NaiveDate) -> GoalProgress {
+    let (start, end) = match (parse_date(&goal.start), parse_date(&goal.end)) {
+        (Some(start), Some(end)) => (start, end),
+        _ => bounds(goal.period, today),
+    };
+    let days_total = (end - start).num_days() as u32 + 1;
+    let elapsed = if today < start {
+        0
+    } else {
+        ((today.min(end) - start).num_days() as u32 + 1).min(days_total)
+    };
+    let days_left = days_total - elapsed + u32::from(today >= start && today <= end);
+    let current = amount(habit, start, end);
+    let expected = goal.target * f64::from(elapsed) / f64::from(days_total);
+    let status = if current >= goal.target {
+        GoalStatus::Achieved
+    } else if today > end {
+        GoalStatus::Missed
+    } else if today < start {
+        GoalStatus::Upcoming
+    } else if current >= expected {
+        GoalStatus::OnTrack
+    } else {
+        GoalStatus::Behind
+    };
+    let needed_per_day = matches!(
+        status,
+        GoalStatus::Upcoming | GoalStatus::OnTrack | GoalStatus::Behind
+    )
+    .then(|| (goal.target - current) / f64::from(days_left.max(1)));
+    GoalProgress {
+        unit: habit.target.as_ref().map(|t| t.unit.clone()),
+        percent: (current / goal.target * 100.0).min(100.0),
+        current,
+        expected,
+        days_total,
+        days_left,
+        needed_per_day,
+        status,
+        goal,
+    }
+}
+
+/// Creates a goal for the month or quarter containing `date` (default today).
+pub fn add(
+    storage: &Storage,
+    title: &str,
+    habit_id: &str,
+    period: GoalPeriod,
+    target: f64,
+    date: Option<NaiveDate>,
+) -> Result<GoalProgress, AppError> {
+    let title = title.trim();
+    if title.is_empty() {
+        return Err(AppError::Invalid("a goal needs a title".into()));
+    }
+    if !target.is_finite() || target <= 0.0 {
+        return Err(AppError::Invalid(
+            "the target must be a positive number".into(),
+        ));
+    }
+    let habit = storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+    let today = today();
+    let (start, end) = bounds(period, date.unwrap_or(today));
+    let goal = Goal {
+        id: generate_token()[..16].to_string(),
+        title: title.to_string(),
+        habit_id: habit_id.to_string(),
+        period,
+        start: date_key(start),
+        end: date_key(end),
+        target,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    storage.insert_goal(&goal)?;
+    Ok(progress(goal, &habit, today))
+}
+
+/// Progress on every goal, or just `goal_id`. Goals whose habit is gone are left out.
+pub fn list(storage: &Storage, goal_id: Option<&str>) -> Result<Vec<GoalProgress>, AppError> {
+    let habits = storage.list_habits()?;
+    let today = today();
+    let goals: Vec<GoalProgress> = storage
+        .list_goals()?
+        .into_iter()
+        .filter(|g| goal_id.map_or(true, |id| g.id == id))
+        .filter_map(|g| {
+            let habit = habits.iter().find(|h| h.id == g.habit_id)?;
+            Some(progress(g, habit, today))
+        })
+        .collect();
+    match goal_id {
+        Some(id) if goals.is_empty() => Err(AppError::not_found("goal", id)),
+        _ => Ok(goals),
+    }
+}
+
+pub fn remove(storage: &Storage, id: &str) -> Result<(), AppError> {
+    if !storage.delete_goal(id)? {
+        return Err(AppError::not_found("goal", id));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn add_goal(
+    storage: State<'_, Storage>,
+    title: String,
+    habit_id: String,
+    period: GoalPeriod,
+    target: f64,
+    date: Option<String>,
+) -> Result<GoalProgress, AppError> {
+    let date = date.map(|d| date_arg(&d)).transpose()?;
+    add(&storage, &title, &habit_id, period, target, date)
+}
+
+/// Every goal with its progress, or only `goal_id`'s.
+#[tauri::command]
+pub fn get_goal_progress(
+    storage: State<'_, Storage>,
+    goal_id: Option<String>,
+) -> Result<Vec<GoalProgress>, AppError> {
+    list(&storage, goal_id.as_deref())
+}
+
+#[tauri::command]
+pub fn remove_goal(storage: State<'_, Storage>, id: String) -> Result<(), AppError> {
+    remove(&storage, &id)
+}
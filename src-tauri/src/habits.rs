@@ -0,0 +1,623 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::AppError;
+use crate::events::{EventBus, HabitEvent, STREAK_MILESTONES};
+use crate::model::{Habit, HabitKind, Lapse};
+use crate::settings::{BackfillSettings, SettingsStore};
+use crate::stats::{current_streak, date_arg, date_key, today};
+use crate::storage::{CheckIn, Storage, Tx};
+
+/// Emitted with the full habit list whenever the backend changes data on its own
+/// (local API, tray, ...). The webview replaces its state with the payload.
+pub const HABITS_CHANGED_EVENT: &str = "habits:changed";
+
+pub fn notify_changed(app: &AppHandle) {
+    match app.state::<Storage>().list_habits() {
+        Ok(habits) => {
+            let _ = app.emit(HABITS_CHANGED_EVENT, habits);
+        }
+        Err(e) => log::error!("failed to load habits for change notification: {e}"),
+    }
+}
+
+/// Random v4 UUID, the same shape as the webview's `crypto.randomUUID()`.
+pub fn new_id() -> String {
+    use rand::RngCore;
+
+    let mut bytes = [0u8; 16];
+    rand::rng().fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Appends a habit to the end of the list. Its name must be valid and not taken by another
+/// active habit (`text.rs`).
+pub fn insert(storage: &Storage, habit: Habit) -> Result<Habit, AppError> {
+    let id = habit.id.clone();
+    let mut habits = storage.list_habits()?;
+    let seen = crate::revisions::seen(&habits);
+    let name = crate::text::habit_name(&habit.name)?;
+    crate::text::check_unique(&habits, &name, None)?;
+    habits.push(Habit { name, ..habit });
+    let stale = storage.replace_habits_checked(&habits, &seen)?;
+    if !stale.is_empty() {
+        return Err(changed_meanwhile(&stale));
+    }
+    storage
+        .get_habit(&id)?
+        .ok_or_else(|| "habit disappeared".into())
+}
+
+/// The error for a write of every habit that some of them changed since they were read.
+fn changed_meanwhile(ids: &[String]) -> AppError {
+    AppError::Failed(format!(
+        "changed while saving, try again: {}",
+        ids.join(", ")
+    ))
+}
+
+/// [`insert`] on behalf of a non-webview caller.
+pub fn create(app: &AppHandle, habit: Habit) -> Result<Habit, AppError> {
+    let habit = insert(&app.state::<Storage>(), habit)?;
+    let bus = app.state::<EventBus>();
+    bus.publish(HabitEvent::HabitCreated {
+        habit_id: habit.id.clone(),
+        name: habit.name.clone(),
+    });
+    bus.data_changed();
+    notify_changed(app);
+    Ok(habit)
+}
+
+/// Resolves a user-supplied reference: exact id first, then case-insensitive name, the way
+/// `text.rs` compares them.
+pub fn find<'a>(habits: &'a [Habit], query: &str) -> Option<&'a Habit> {
+    let query = query.trim();
+    let key = crate::text::fold(query);
+    habits
+        .iter()
+        .find(|h| h.id == query)
+        .or_else(|| habits.iter().find(|h| crate::text::fold(&h.name) == key))
+}
+
+/// Stores the webview's snapshot and publishes events for whatever changed since the last one.
+/// `order` is a new manual order to apply in the same transaction (see `write_queue.rs`).
+pub fn sync_from_webview(
+    app: &AppHandle,
+    mut habits: Vec<Habit>,
+    order: Option<&[String]>,
+) -> Result<(), AppError> {
+    let storage = app.state::<Storage>();
+    let revisions = crate::revisions::lock();
+    let previous = storage.list_habits()?;
+    let stored = crate::revisions::seen(&previous);
+    // The manual order is kept in the backend (`reorder_habits`); new habits go at the end.
+    let existing: Vec<String> = previous.iter().map(|h| h.id.clone()).collect();
+    let position: HashMap<String, usize> =
+        crate::ordering::reordered(&existing, order.unwrap_or(&[]))
+            .into_iter()
+            .enumerate()
+            .map(|(i, id)| (id.clone(), i))
+            .collect();
+    let sent: Vec<String> = habits.iter().map(|h| h.id.clone()).collect();
+    habits.sort_by_key(|h| position.get(&h.id).copied().unwrap_or(usize::MAX));
+    let reordered = habits.iter().map(|h| &h.id).ne(sent.iter());
+    let previous: HashMap<String, Habit> =
+        previous.into_iter().map(|h| (h.id.clone(), h)).collect();
+
+    // A habit changed elsewhere (a widget, the tray, sync, the CLI) since the webview read
+    // it keeps what's stored; the webview gets the stored version back.
+    let mut seen = HashMap::new();
+    let mut conflicts = Vec::new();
+    for habit in &mut habits {
+        seen.insert(habit.id.clone(), habit.revision);
+        if let Some(old) = previous.get(&habit.id) {
+            if crate::revisions::snapshot_stale(old, habit.revision) {
+                conflicts.push(old.clone());
+                *habit = old.clone();
+                seen.remove(&habit.id);
+            }
+        }
+    }
+
+    // The webview only flips `archived`; keep the original archive time while it stays set.
+    // Off periods, streak freezes, notes, pins, difficulty, covers, weekday reminders and
+    // backfill marks never come from the webview.
+    for habit in &mut habits {
+        match previous.get(&habit.id) {
+            Some(old) => {
+                habit.pinned = old.pinned;
+                habit.difficulty = old.difficulty;
+                habit.cover = old.cover.clone();
+                habit.weekday_reminders = old.weekday_reminders.clone();
+                habit.backfilled = old
+                    .backfilled
+                    .iter()
+                    .filter(|d| habit.logs.get(*d) == Some(&true))
+                    .cloned()
+                    .collect();
+                habit.off_periods = old.off_periods.clone();
+                habit.frozen = old.frozen.clone();
+                habit.notes = old.notes.clone();
+            }
+            None => {
+                habit.pinned = false;
+                habit.difficulty = Default::default();
+                habit.cover = None;
+                habit.weekday_reminders.clear();
+                habit.backfilled.clear();
+                habit.notes.clear();
+            }
+        }
+        if !habit.archived {
+            habit.archived_at = None;
+        } else if habit.archived_at.is_none() {
+            habit.archived_at = Some(
+                previous
+                    .get(&habit.id)
+                    .and_then(|old| old.archived_at.clone())
+                    .unwrap_or_else(|| chrono::Utc::now().to_rfc3339()),
+            );
+        }
+    }
+
+    // Today's check marks only count inside a habit's time window: drop ones made outside
+    // it, and push the corrected list back so the webview agrees.
+    let now = crate::time_windows::now();
+    let today_key = date_key(now.date());
+    let mut rejected = false;
+    for habit in &mut habits {
+        habit.window = habit
+            .window
+            .take()
+            .filter(|w| crate::time_windows::validate(w).is_ok());
+        let was_done = previous
+            .get(&habit.id)
+            .is_some_and(|old| old.logs.get(&today_key) == Some(&true));
+        if habit.logs.get(&today_key) == Some(&true)
+            && !was_done
+            && crate::time_windows::check(habit, now.date(), now).is_err()
+        {
+            habit.logs.remove(&today_key);
+            rejected = true;
+        }
+    }
+
+    // Likewise a repeated habit's check mark: checking it off completes the day's count and
+    // unchecking clears it.
+    for habit in habits.iter_mut().filter(|h| h.is_repeated()) {
+        let Some(old) = previous.get(&habit.id) else {
+            continue;
+        };
+        let logged = |h: &Habit, d: &str| h.logs.get(d).copied().unwrap_or(false);
+        let toggled: Vec<(String, bool)> = habit
+            .logs
+            .keys()
+            .chain(old.logs.keys())
+            .filter(|d| logged(habit, d) != logged(old, d))
+            .map(|d| (d.clone(), logged(habit, d)))
+            .collect();
+        for (date, done) in toggled {
+            if done {
+                let goal = f64::from(habit.times_per_day);
+                let count = habit.values.entry(date).or_insert(0.0);
+                *count = count.max(goal);
+            } else {
+                habit.values.remove(&date);
+            }
+        }
+    }
+
+    // The webview only toggles a checklist habit as a whole; carry that to its items.
+    for habit in habits.iter_mut().filter(|h| !h.checklist.is_empty()) {
+        let Some(old) = previous.get(&habit.id) else {
+            continue;
+        };
+        let logged = |h: &Habit, d: &str| h.logs.get(d).copied().unwrap_or(false);
+        let toggled: Vec<(String, bool)> = habit
+            .logs
+            .keys()
+            .chain(old.logs.keys())
+            .filter(|d| logged(habit, d) != logged(old, d))
+            .map(|d| (d.clone(), logged(habit, d)))
+            .collect();
+        for (date, done) in toggled {
+            for item in &mut habit.checklist {
+                if done {
+                    item.done.insert(date.clone());
+                } else {
+                    item.done.remove(&date);
+                }
+            }
+        }
+    }
+
+    // Stored names and tags may differ from what the webview sent, so it gets them back.
+    let normalized = match crate::text::normalize_habits(&habits, &previous) {
+        Some(fixed) => {
+            habits = fixed;
+            true
+        }
+        None => false,
+    };
+    // On what `previous` was read from, or not at all: a habit changed by another process
+    // in between would be overwritten. The queue tries again (`write_queue.rs`).
+    let stale = storage.replace_habits_checked(&habits, &stored)?;
+    if !stale.is_empty() {
+        return Err(changed_meanwhile(&stale));
+    }
+    for (id, seen) in seen {
+        let revision = storage.revision(&id, "")?;
+        crate::revisions::snapshot_written(&id, seen, revision);
+    }
+    drop(revisions);
+
+    let bus = app.state::<EventBus>();
+    let today = today();
+    for habit in &habits {
+        let Some(old) = previous.get(&habit.id) else {
+            // New habits (or restored backups) don't replay their history as completions.
+            bus.publish(HabitEvent::HabitCreated {
+                habit_id: habit.id.clone(),
+                name: habit.name.clone(),
+            });
+            continue;
+        };
+        for date in habit.done_dates() {
+            if !old.is_done_on(date) {
+                publish_completion(&storage, &bus, habit, date, today);
+            }
+        }
+    }
+    bus.data_changed();
+    if !conflicts.is_empty() {
+        log::info!("habits: kept {} habits changed elsewhere", conflicts.len());
+        let _ = app.emit(crate::revisions::HABITS_CONFLICT_EVENT, &conflicts);
+    }
+    if rejected || reordered || normalized || !conflicts.is_empty() {
+        notify_changed(app);
+    }
+    Ok(())
+}
+
+/// Archives or restores a habit, keeping its history either way. Returns `Ok(None)` if the
+/// habit doesn't exist.
+pub fn set_archived(
+    tx: &Tx<'_>,
+    habit_id: &str,
+    archived: bool,
+) -> Result<Option<Habit>, AppError> {
+    let Some(habit) = tx.get_habit(habit_id)? else {
+        return Ok(None);
+    };
+    if habit.archived == archived {
+        return Ok(Some(habit));
+    }
+    let at = archived.then(|| chrono::Utc::now().to_rfc3339());
+    tx.set_archived(habit_id, at.as_deref())?;
+    Ok(tx.get_habit(habit_id)?)
+}
+
+/// [`set_archived`] if the habit is still on `expected` (see `revisions.rs`).
+pub fn set_archived_checked(
+    storage: &Storage,
+    habit_id: &str,
+    archived: bool,
+    expected: Option<u64>,
+) -> Result<Habit, AppError> {
+    crate::revisions::check_habit(storage, habit_id, expected, |tx| {
+        set_archived(tx, habit_id, archived)?.ok_or_else(|| AppError::not_found("habit", habit_id))
+    })
+}
+
+/// Marks or unmarks `date` for a habit. Checking off a day other than today is a backfill:
+/// it has to pass [`crate::backfill::check`] and is marked as backfilled. Returns the
+/// updated habit and whether its completion changed, or `Ok(None)` if the habit doesn't
+/// exist.
+pub fn record_completion(
+    tx: &Tx<'_>,
+    rules: &BackfillSettings,
+    habit_id: &str,
+    date: NaiveDate,
+    done: bool,
+) -> Result<Option<(Habit, bool)>, AppError> {
+    let Some(habit) = tx.get_habit(habit_id)? else {
+        return Ok(None);
+    };
+    let key = date_key(date);
+    let backfilled = done && date != today();
+    if done {
+        crate::time_windows::check(&habit, date, crate::time_windows::now())?;
+        if habit.is_done_on(&key) {
+            return Ok(Some((habit, false)));
+        }
+        if backfilled {
+            crate::backfill::check(&habit, date, today(), rules)?;
+        }
+    }
+
+    set_repetitions(tx, &habit, &key, done)?;
+    let changed = if backfilled {
+        tx.backfill_completion(habit_id, &key)
+    } else {
+        tx.set_completion(habit_id, &key, done)
+    }?;
+    let habit = tx.get_habit(habit_id)?.ok_or("habit disappeared")?;
+    Ok(Some((habit, changed)))
+}
+
+/// [`record_completion`] on behalf of a non-webview caller.
+pub fn set_completion(
+    app: &AppHandle,
+    habit_id: &str,
+    date: NaiveDate,
+    done: bool,
+) -> Result<Option<Habit>, AppError> {
+    let storage = app.state::<Storage>();
+    let rules = app.state::<SettingsStore>().get().backfill;
+    let Some((habit, changed)) =
+        storage.transaction(|tx| record_completion(tx, &rules, habit_id, date, done))?
+    else {
+        return Ok(None);
+    };
+    if changed {
+        let bus = app.state::<EventBus>();
+        if done {
+            publish_completion(&storage, &bus, &habit, &date_key(date), today());
+        }
+        bus.data_changed();
+        notify_changed(app);
+    }
+    Ok(Some(habit))
+}
+
+/// A repeated habit's check mark stands for its check-ins: checking it off fills in the
+/// missing ones, and unchecking drops them all. Other habits are left alone.
+pub fn set_repetitions(tx: &Tx<'_>, habit: &Habit, date: &str, done: bool) -> rusqlite::Result<()> {
+    if !habit.is_repeated() {
+        return Ok(());
+    }
+    if !done {
+        return tx.clear_check_ins(&habit.id, date);
+    }
+    let missing = f64::from(habit.times_per_day) - habit.value_on(date);
+    for _ in 0..missing.max(0.0).ceil() as u32 {
+        tx.add_check_in(&habit.id, date, 1.0, &[])?;
+    }
+    Ok(())
+}
+
+/// Adds `value` to a quantified habit's total (or a repeated habit's check-in count) for
+/// `date`, checking the day off once the target is reached. `tags` label this check-in only. Returns the updated habit and
+/// whether this log completed the day, or `Ok(None)` if the habit doesn't exist.
+pub fn record_value(
+    tx: &Tx<'_>,
+    habit_id: &str,
+    date: NaiveDate,
+    value: f64,
+    tags: Vec<String>,
+) -> Result<Option<(Habit, bool)>, AppError> {
+    let Some(habit) = tx.get_habit(habit_id)? else {
+        return Ok(None);
+    };
+    if habit.target.is_none() && !habit.is_repeated() {
+        return Err(AppError::Invalid(format!(
+            "{} has no numeric target",
+            habit.name
+        )));
+    }
+    if !value.is_finite() {
+        return Err(AppError::Invalid("value must be a number".into()));
+    }
+    crate::time_windows::check(&habit, date, crate::time_windows::now())?;
+
+    let key = date_key(date);
+    let was_done = habit.is_done_on(&key);
+    tx.add_check_in(habit_id, &key, value, &crate::tags::normalize(tags))?;
+    let mut habit = tx.get_habit(habit_id)?.ok_or("habit disappeared")?;
+
+    // Keep `logs` in step so views that only know about check marks agree.
+    let done = habit.is_done_on(&key);
+    if done != habit.logs.get(&key).copied().unwrap_or(false) {
+        tx.set_completion(habit_id, &key, done)?;
+        if done {
+            habit.logs.insert(key, true);
+        } else {
+            habit.logs.remove(&key);
+        }
+    }
+    Ok(Some((habit, done && !was_done)))
+}
+
+/// Logs a slip-up on an avoidance habit. Returns the updated habit and the streak the
+/// lapse ended (0 if none), or `Ok(None)` if the habit doesn't exist.
+pub fn record_lapse(
+    tx: &Tx<'_>,
+    habit_id: &str,
+    date: NaiveDate,
+    note: Option<String>,
+) -> Result<Option<(Habit, u32)>, AppError> {
+    let Some(habit) = tx.get_habit(habit_id)? else {
+        return Ok(None);
+    };
+    if habit.kind != HabitKind::Avoid {
+        return Err(AppError::Invalid(format!(
+            "{} is not an avoidance habit",
+            habit.name
+        )));
+    }
+
+    let today = today();
+    let before = current_streak(&habit, today);
+    let lapse = Lapse {
+        date: date_key(date),
+        note: note.filter(|n| !n.trim().is_empty()),
+        logged_at: chrono::Utc::now().to_rfc3339(),
+    };
+    tx.add_lapse(habit_id, &lapse)?;
+    let habit = tx.get_habit(habit_id)?.ok_or("habit disappeared")?;
+    let after = current_streak(&habit, today);
+    Ok(Some((habit, if after < before { before } else { 0 })))
+}
+
+/// [`record_lapse`] if the day is still on `expected`.
+pub fn record_lapse_checked(
+    storage: &Storage,
+    habit_id: &str,
+    date: NaiveDate,
+    note: Option<String>,
+    expected: Option<u64>,
+) -> Result<(Habit, u32), AppError> {
+    crate::revisions::check_day(storage, habit_id, date, expected, |tx| {
+        record_lapse(tx, habit_id, date, note)?
+            .ok_or_else(|| AppError::not_found("habit", habit_id))
+    })
+}
+
+/// A habit's check-ins, on `date` (`yyyy-MM-dd`) or every day.
+pub fn check_ins(
+    storage: &Storage,
+    habit_id: &str,
+    date: Option<&str>,
+) -> Result<Vec<CheckIn>, AppError> {
+    let date = date.map(date_arg).transpose()?.map(date_key);
+    Ok(storage.list_check_ins(habit_id, date.as_deref())?)
+}
+
+/// [`record_lapse`] on behalf of a non-webview caller.
+pub fn log_lapse(
+    app: &AppHandle,
+    habit_id: &str,
+    date: NaiveDate,
+    note: Option<String>,
+) -> Result<Option<Habit>, AppError> {
+    let Some(lapsed) = app
+        .state::<Storage>()
+        .transaction(|tx| record_lapse(tx, habit_id, date, note))?
+    else {
+        return Ok(None);
+    };
+    Ok(Some(lapse_logged(app, lapsed)))
+}
+
+/// Announces what [`record_lapse`] recorded: the lapse, and the streak it broke.
+pub fn lapse_logged(app: &AppHandle, (habit, broken): (Habit, u32)) -> Habit {
+    let bus = app.state::<EventBus>();
+    if broken > 0 {
+        bus.publish(HabitEvent::StreakBroken {
+            habit_id: habit.id.clone(),
+            name: habit.name.clone(),
+            streak: broken,
+        });
+    }
+    bus.data_changed();
+    notify_changed(app);
+    habit
+}
+
+/// [`record_value`] if the day is still on `expected` (see `revisions.rs`).
+pub fn record_value_checked(
+    storage: &Storage,
+    habit_id: &str,
+    date: NaiveDate,
+    value: f64,
+    tags: Vec<String>,
+    expected: Option<u64>,
+) -> Result<(Habit, bool), AppError> {
+    crate::revisions::check_day(storage, habit_id, date, expected, |tx| {
+        record_value(tx, habit_id, date, value, tags)?
+            .ok_or_else(|| AppError::not_found("habit", habit_id))
+    })
+}
+
+/// [`record_value`] on behalf of a non-webview caller.
+pub fn log_value(
+    app: &AppHandle,
+    habit_id: &str,
+    date: NaiveDate,
+    value: f64,
+    tags: Vec<String>,
+) -> Result<Option<Habit>, AppError> {
+    let Some(logged) = app
+        .state::<Storage>()
+        .transaction(|tx| record_value(tx, habit_id, date, value, tags))?
+    else {
+        return Ok(None);
+    };
+    Ok(Some(value_logged(app, logged, date)))
+}
+
+/// Announces what [`record_value`] recorded on `date`, and the completion if it made one.
+pub fn value_logged(app: &AppHandle, (habit, completed): (Habit, bool), date: NaiveDate) -> Habit {
+    let bus = app.state::<EventBus>();
+    if completed {
+        publish_completion(
+            &app.state::<Storage>(),
+            &bus,
+            &habit,
+            &date_key(date),
+            today(),
+        );
+    }
+    bus.data_changed();
+    notify_changed(app);
+    habit
+}
+
+/// Checks a habit off for `date` and never unchecks it, for callers that may act on a
+/// stale view (the mobile widgets, Shortcuts). Repeated habits take one more check-in.
+pub fn check_off(
+    app: &AppHandle,
+    habit: &Habit,
+    date: NaiveDate,
+) -> Result<Option<Habit>, AppError> {
+    if habit.is_done_on(&date_key(date)) {
+        return Ok(Some(habit.clone()));
+    }
+    if habit.is_repeated() {
+        log_value(app, &habit.id, date, 1.0, Vec::new())
+    } else {
+        set_completion(app, &habit.id, date, true)
+    }
+}
+
+pub fn publish_completion(
+    storage: &Storage,
+    bus: &EventBus,
+    habit: &Habit,
+    date: &str,
+    today: NaiveDate,
+) {
+    // Streaks only move when today is checked off; backfilled days just report the completion.
+    let streak = if date == date_key(today) {
+        crate::streaks::current(storage, habit, today)
+    } else {
+        0
+    };
+
+    bus.publish(HabitEvent::HabitCompleted {
+        habit_id: habit.id.clone(),
+        name: habit.name.clone(),
+        date: date.to_string(),
+        streak,
+    });
+    if STREAK_MILESTONES.contains(&streak) {
+        bus.publish(HabitEvent::StreakMilestone {
+            habit_id: habit.id.clone(),
+            name: habit.name.clone(),
+            streak,
+        });
+    }
+}
@@ -0,0 +1,354 @@
+//! Drives commands without a window, on a throwaway data dir and a [`ManualClock`], for
+//! tests of things that depend on the date:
+//!
+//! ```ignore
+//! let h = Harness::at("2024-02-28T21:00:00+01:00")?;
+//! h.call("sync_habits", json!({ "habits": [habit] }))?;
+//! h.call("set_completion", json!({ "habitId": "read", "done": true }))?;
+//! h.advance(Duration::days(1)); // the 29th
+//! ```
+//!
+//! Calls go through the same dispatch as `--rpc`, so methods and params are the commands'.
+//! The database is a real SQLite file with every migration applied, not a stand-in, so
+//! what passes here passes against the app's storage.
+//!
+//! The clock is only swapped on the thread that made the harness; make the calls from
+//! that thread. Dropping the harness puts the system clock back and deletes the dir.
+//!
+//! Storage stays the concrete [`Storage`] rather than a trait with an in-memory stand-in:
+//! streaks, revisions and check-in totals are partly SQL (triggers, migrations), and a
+//! second implementation would test itself rather than the app. A throwaway SQLite file
+//! is fast enough for that not to matter. Only built for tests.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, FixedOffset, NaiveDate};
+use serde_json::Value;
+
+use crate::clock::{self, ManualClock};
+use crate::error::AppError;
+use crate::rpc::{self, Context};
+use crate::settings::{generate_token, SettingsStore, SETTINGS_FILE_NAME};
+use crate::storage::Storage;
+
+pub struct Harness {
+    ctx: Context,
+    clock: Arc<ManualClock>,
+}
+
+impl Harness {
+    /// A fresh data dir, with the clock at `now`.
+    pub fn new(now: DateTime<FixedOffset>) -> Result<Self, AppError> {
+        let dir =
+            std::env::temp_dir().join(format!("habitflow-harness-{}", &generate_token()[..16]));
+        std::fs::create_dir_all(&dir)?;
+        let storage = Storage::open(&dir.join("habitflow.db"))?;
+        let settings = SettingsStore::load(dir.join(SETTINGS_FILE_NAME));
+        let clock = Arc::new(ManualClock::new(now));
+        clock::install(clock.clone());
+        Ok(Self {
+            ctx: Context {
+                storage,
+                settings,
+                data_dir: dir,
+                timers: Default::default(),
+                pomodoro: Default::default(),
+                jobs: Default::default(),
+                locked: Default::default(),
+            },
+            clock,
+        })
+    }
+
+    /// [`Harness::new`] at an RFC 3339 time, `2024-03-31T01:30:00+01:00`.
+    pub fn at(now: &str) -> Result<Self, AppError> {
+        let now = DateTime::parse_from_rfc3339(now)
+            .map_err(|e| AppError::Invalid(format!("invalid time {now:?}: {e}")))?;
+        Self::new(now)
+    }
+
+    /// Runs `method` with named `params`. Errors are the JSON-RPC code and message.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value, (i64, String)> {
+        rpc::dispatch(&self.ctx, method, params).map_err(|e| (e.code, e.message))
+    }
+
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        clock::Clock::now(&*self.clock)
+    }
+
+    /// The day the app would call today.
+    pub fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.clock.advance(by);
+    }
+
+    /// Same instant, another offset: a DST change (`+01:00` to `+02:00`), or a move to
+    /// another timezone.
+    pub fn set_offset(&self, offset: FixedOffset) {
+        self.clock.set_offset(offset);
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        clock::reset();
+        let _ = std::fs::remove_dir_all(&self.ctx.data_dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn add_habit(h: &Harness, id: &str) {
+        let habit = json!({
+            "id": id,
+            "name": id,
+            "createdAt": "2024-01-01T08:00:00+00:00",
+        });
+        h.call("sync_habits", json!({ "habits": [habit] })).unwrap();
+    }
+
+    fn check_off(h: &Harness, id: &str) {
+        h.call("set_completion", json!({ "habitId": id, "done": true }))
+            .unwrap();
+    }
+
+    fn check_off_on(h: &Harness, id: &str, date: &str) {
+        h.call(
+            "set_completion",
+            json!({ "habitId": id, "date": date, "done": true }),
+        )
+        .unwrap();
+    }
+
+    /// `(current, longest)` as `get_stats` reports them today.
+    fn streaks(h: &Harness, id: &str) -> (u64, u64) {
+        let stats = h.call("get_stats", json!({})).unwrap();
+        let habit = stats["habits"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|s| s["habitId"] == id)
+            .unwrap()
+            .clone();
+        (
+            habit["currentStreak"].as_u64().unwrap(),
+            habit["longestStreak"].as_u64().unwrap(),
+        )
+    }
+
+    fn offset(hours: i32) -> FixedOffset {
+        FixedOffset::east_opt(hours * 3600).unwrap()
+    }
+
+    #[test]
+    fn spring_forward_keeps_the_streak() {
+        // Berlin, the night clocks go from 02:00 to 03:00.
+        let h = Harness::at("2024-03-30T21:00:00+01:00").unwrap();
+        add_habit(&h, "read");
+        check_off(&h, "read");
+        h.advance(Duration::hours(5)); // 02:00 +01:00, which is when clocks jump
+        h.set_offset(offset(2));
+        assert_eq!(h.now().to_rfc3339(), "2024-03-31T03:00:00+02:00");
+        assert_eq!(streaks(&h, "read"), (1, 1));
+        h.advance(Duration::hours(18)); // 21:00 on the 23-hour day
+        check_off(&h, "read");
+        assert_eq!(h.today().to_string(), "2024-03-31");
+        assert_eq!(streaks(&h, "read"), (2, 2));
+    }
+
+    #[test]
+    fn fall_back_keeps_the_streak() {
+        // Berlin, the night clocks go from 03:00 back to 02:00.
+        let h = Harness::at("2024-10-26T23:30:00+02:00").unwrap();
+        add_habit(&h, "read");
+        check_off(&h, "read");
+        h.advance(Duration::hours(3) + Duration::minutes(30)); // 03:00 +02:00
+        h.set_offset(offset(1));
+        assert_eq!(h.now().to_rfc3339(), "2024-10-27T02:00:00+01:00");
+        check_off(&h, "read");
+        // 22 hours later by the clock on the wall, 23 in real time, still the 27th.
+        h.advance(Duration::hours(21) + Duration::minutes(59));
+        assert_eq!(h.today().to_string(), "2024-10-27");
+        assert_eq!(streaks(&h, "read"), (2, 2));
+        h.advance(Duration::minutes(2));
+        assert_eq!(h.today().to_string(), "2024-10-28");
+        assert_eq!(streaks(&h, "read"), (2, 2));
+    }
+
+    #[test]
+    fn leap_day_is_a_day_of_its_own() {
+        let h = Harness::at("2024-02-28T20:00:00+00:00").unwrap();
+        add_habit(&h, "run");
+        check_off(&h, "run");
+        h.advance(Duration::days(2));
+        assert_eq!(h.today().to_string(), "2024-03-01");
+        check_off(&h, "run");
+        // The 29th was missed, so the streak starts over.
+        assert_eq!(streaks(&h, "run"), (1, 1));
+        check_off_on(&h, "run", "2024-02-29");
+        assert_eq!(streaks(&h, "run"), (3, 3));
+    }
+
+    #[test]
+    fn no_leap_day_in_common_years() {
+        let h = Harness::at("2023-02-28T20:00:00+00:00").unwrap();
+        add_habit(&h, "run");
+        check_off(&h, "run");
+        h.advance(Duration::days(1));
+        assert_eq!(h.today().to_string(), "2023-03-01");
+        check_off(&h, "run");
+        assert_eq!(streaks(&h, "run"), (2, 2));
+    }
+
+    #[test]
+    fn flying_west_stays_on_the_same_day() {
+        // Checked off in Berlin late in the evening, then a flight to New York.
+        let h = Harness::at("2024-06-10T23:00:00+02:00").unwrap();
+        add_habit(&h, "stretch");
+        check_off(&h, "stretch");
+        h.set_offset(offset(-4));
+        assert_eq!(h.today().to_string(), "2024-06-10");
+        assert_eq!(streaks(&h, "stretch"), (1, 1));
+        h.advance(Duration::hours(12)); // the 11th in New York
+        check_off(&h, "stretch");
+        assert_eq!(streaks(&h, "stretch"), (2, 2));
+    }
+
+    #[test]
+    fn flying_east_can_skip_a_day() {
+        // Checked off in New York in the evening, landing in Tokyo the next morning.
+        let h = Harness::at("2024-06-10T20:00:00-04:00").unwrap();
+        add_habit(&h, "stretch");
+        check_off(&h, "stretch");
+        h.advance(Duration::hours(15));
+        h.set_offset(offset(9));
+        assert_eq!(h.now().to_rfc3339(), "2024-06-12T00:00:00+09:00");
+        // Still the first hour of the 12th: the 10th was the last day done and the 11th
+        // was mostly spent in the air, so the streak has already ended.
+        assert_eq!(streaks(&h, "stretch"), (0, 1));
+        check_off_on(&h, "stretch", "2024-06-11");
+        check_off(&h, "stretch");
+        assert_eq!(streaks(&h, "stretch"), (3, 3));
+    }
+
+    #[test]
+    fn a_refused_write_keeps_the_revision() {
+        let h = Harness::at("2024-06-10T12:00:00+00:00").unwrap();
+        add_habit(&h, "read");
+        // "read" has no numeric target, so logging a value fails after the check.
+        let logged = h.call(
+            "log_habit_value",
+            json!({ "habitId": "read", "value": 3, "date": "2024-06-09", "expectedRevision": 0 }),
+        );
+        assert!(logged.is_err());
+        let backfilled = h.call(
+            "backfill_completion",
+            json!({ "habitId": "read", "date": "2024-06-09", "expectedRevision": 0 }),
+        );
+        assert!(backfilled.is_ok(), "{backfilled:?}");
+        // No revision rows for habits that don't exist.
+        let missing = h.call(
+            "backfill_completion",
+            json!({ "habitId": "gone", "date": "2024-06-09", "expectedRevision": 0 }),
+        );
+        assert!(missing.unwrap_err().1.contains("not found"));
+    }
+
+    #[test]
+    fn dated_check_offs_are_backfills() {
+        let h = Harness::at("2024-06-10T12:00:00+00:00").unwrap();
+        add_habit(&h, "read");
+        let early = h.call(
+            "set_completion",
+            json!({ "habitId": "read", "date": "2024-05-01", "done": true }),
+        );
+        assert!(early.unwrap_err().1.contains("only the last 7 days"));
+        let future = h.call(
+            "set_completion",
+            json!({ "habitId": "read", "date": "2024-06-11", "done": true }),
+        );
+        assert!(future.is_err());
+        check_off_on(&h, "read", "2024-06-08");
+        check_off(&h, "read");
+        let habit = h
+            .call("list_habits", json!({}))
+            .unwrap()
+            .as_array()
+            .unwrap()[0]
+            .clone();
+        assert_eq!(habit["backfilled"], json!(["2024-06-08"]));
+    }
+
+    #[test]
+    fn habit_names_stay_unique() {
+        let h = Harness::at("2024-06-10T12:00:00+00:00").unwrap();
+        let habit = |id: &str, name: &str| json!({ "id": id, "name": name, "createdAt": "2024-01-01T08:00:00+00:00" });
+        let sent = h.call(
+            "sync_habits",
+            json!({ "habits": [habit("a", "Run"), habit("b", "RUN")] }),
+        );
+        assert!(sent.unwrap_err().1.contains("already exists"));
+        h.call(
+            "sync_habits",
+            json!({ "habits": [habit("a", "Run"), habit("b", "Walk")] }),
+        )
+        .unwrap();
+        // A restore can't be refused, so the habit that took the name is numbered, even
+        // when it comes first.
+        let restored: Vec<crate::model::Habit> =
+            serde_json::from_value(json!([habit("b", "run"), habit("a", "Run")])).unwrap();
+        h.ctx.storage.replace_habits(&restored).unwrap();
+        let names: Vec<(String, String)> = h
+            .ctx
+            .storage
+            .list_habits()
+            .unwrap()
+            .into_iter()
+            .map(|h| (h.id, h.name))
+            .collect();
+        assert!(names.contains(&("a".into(), "Run".into())), "{names:?}");
+        assert!(names.contains(&("b".into(), "run (2)".into())), "{names:?}");
+    }
+
+    #[test]
+    fn refused_values_are_invalid() {
+        let h = Harness::at("2024-06-10T12:00:00+00:00").unwrap();
+        add_habit(&h, "read");
+        let refused = [
+            (
+                "backfill_completion",
+                json!({ "habitId": "read", "date": "2024-01-01" }),
+            ),
+            ("log_habit_value", json!({ "habitId": "read", "value": 1 })),
+            ("log_mood", json!({ "score": 9 })),
+        ];
+        for (method, params) in refused {
+            let (code, message) = h.call(method, params).unwrap_err();
+            assert_eq!(code, -32602, "{method}: {message}");
+        }
+    }
+
+    #[test]
+    fn replica_stamps_follow_the_clock() {
+        let h = Harness::at("2024-06-10T14:00:00+02:00").unwrap();
+        let storage = &h.ctx.storage;
+        storage
+            .replica_track(&storage.replicated_schema().unwrap())
+            .unwrap();
+        h.advance(Duration::days(1));
+        add_habit(&h, "read");
+        let changes = storage
+            .replica_changes(&storage.replicated_schema().unwrap())
+            .unwrap();
+        let habits = changes.iter().find(|t| t.name == "habits").unwrap();
+        assert_eq!(habits.rows.len(), 1);
+        assert_eq!(habits.rows[0].changed_at, "2024-06-11T12:00:00.000Z");
+    }
+}
@@ -0,0 +1,83 @@
+//! A habit's completion history a page at a time, newest first, so the history view and
+//! the charts don't have to load years of days at once.
+//!
+//! Paging is by key: `nextCursor` is the date of the page's last (oldest) day, and the
+//! next page starts just before it. Days logged meanwhile don't shift the pages.
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::AppError;
+use crate::stats::{date_key, parse_date};
+use crate::storage::{CompletionDay, Storage};
+
+pub const DEFAULT_LIMIT: u32 = 100;
+pub const MAX_LIMIT: u32 = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionPage {
+    pub days: Vec<CompletionDay>,
+    /// Pass back as `cursor` for the next page; `None` on the last one.
+    pub next_cursor: Option<String>,
+}
+
+fn date(value: Option<&str>, what: &str) -> Result<Option<String>, AppError> {
+    value
+        .map(|d| {
+            parse_date(d).map(date_key).ok_or_else(|| {
+                AppError::Invalid(format!("invalid {what} {d:?}, expected yyyy-MM-dd"))
+            })
+        })
+        .transpose()
+}
+
+/// `from` and `to` (`yyyy-MM-dd`, inclusive) narrow the range.
+pub fn page(
+    storage: &Storage,
+    habit_id: &str,
+    cursor: Option<&str>,
+    limit: Option<u32>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<CompletionPage, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let (from, to, before) = (
+        date(from, "from date")?,
+        date(to, "to date")?,
+        date(cursor, "cursor")?,
+    );
+    // One more than asked tells whether there's another page.
+    let mut days = storage
+        .list_completion_days(
+            habit_id,
+            from.as_deref(),
+            to.as_deref(),
+            before.as_deref(),
+            limit + 1,
+        )?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+    let more = days.len() > limit as usize;
+    days.truncate(limit as usize);
+    let next_cursor = more.then(|| days.last().map(|d| d.date.clone())).flatten();
+    Ok(CompletionPage { days, next_cursor })
+}
+
+#[tauri::command]
+pub fn get_completions(
+    storage: State<'_, Storage>,
+    habit_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<CompletionPage, AppError> {
+    page(
+        &storage,
+        &habit_id,
+        cursor.as_deref(),
+        limit,
+        from.as_deref(),
+        to.as_deref(),
+    )
+}
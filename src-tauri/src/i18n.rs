@@ -0,0 +1,398 @@
+//! Translations for what the backend shows itself: the tray menu, notifications and
+//! dialogs. The webview translates its own strings, and calls `set_locale` so the two
+//! match.
+//!
+//! The catalogs are the Fluent files in `locales/`, built into the binary. Only the part
+//! of Fluent they need is read:
+//!
+//! - `id = text` messages, with indented lines continuing them and `#` comments.
+//! - `{ $name }` placeables, and `{ "{" }` for a literal brace.
+//! - Selects on an argument, with variants on their own lines. A variant matches an exact
+//!   number (`[0]`), a number's plural category in the locale (`[one]`, `[other]`), or a
+//!   string. `*` marks the default.
+//!
+//! A message missing from a catalog falls back to English, and one missing there too
+//! shows its id.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{OnceLock, RwLock};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::settings::SettingsStore;
+
+/// Language codes with a catalog, and the catalog.
+const LOCALES: &[(&str, &str)] = &[
+    ("de", include_str!("../locales/de.ftl")),
+    ("en", include_str!("../locales/en.ftl")),
+    ("es", include_str!("../locales/es.ftl")),
+    ("fr", include_str!("../locales/fr.ftl")),
+];
+const FALLBACK: &str = "en";
+
+static CURRENT: RwLock<&str> = RwLock::new(FALLBACK);
+
+#[derive(Debug)]
+enum Part {
+    Text(String),
+    Var(String),
+    Select {
+        var: String,
+        variants: Vec<(String, Vec<Part>)>,
+        default: usize,
+    },
+}
+
+type Catalog = HashMap<String, Vec<Part>>;
+
+/// A value for a placeable.
+#[derive(Debug, Clone)]
+pub enum Arg {
+    Number(f64),
+    Text(String),
+}
+
+impl From<usize> for Arg {
+    fn from(n: usize) -> Self {
+        Arg::Number(n as f64)
+    }
+}
+
+impl From<u32> for Arg {
+    fn from(n: u32) -> Self {
+        Arg::Number(n.into())
+    }
+}
+
+impl From<u64> for Arg {
+    fn from(n: u64) -> Self {
+        Arg::Number(n as f64)
+    }
+}
+
+impl From<f64> for Arg {
+    fn from(n: f64) -> Self {
+        Arg::Number(n)
+    }
+}
+
+impl From<&str> for Arg {
+    fn from(s: &str) -> Self {
+        Arg::Text(s.to_string())
+    }
+}
+
+impl From<String> for Arg {
+    fn from(s: String) -> Self {
+        Arg::Text(s)
+    }
+}
+
+fn skip_space(s: &[char], i: &mut usize) {
+    while s.get(*i).is_some_and(|c| c.is_whitespace()) {
+        *i += 1;
+    }
+}
+
+fn ident(s: &[char], i: &mut usize) -> Option<String> {
+    let start = *i;
+    while s
+        .get(*i)
+        .is_some_and(|c| c.is_alphanumeric() || *c == '-' || *c == '_')
+    {
+        *i += 1;
+    }
+    (*i > start).then(|| s[start..*i].iter().collect())
+}
+
+/// Drops the whitespace around a pattern, as Fluent does.
+fn trimmed(mut parts: Vec<Part>) -> Vec<Part> {
+    if let Some(Part::Text(text)) = parts.first_mut() {
+        *text = text.trim_start().to_string();
+    }
+    if let Some(Part::Text(text)) = parts.last_mut() {
+        *text = text.trim_end().to_string();
+    }
+    parts.retain(|p| !matches!(p, Part::Text(t) if t.is_empty()));
+    parts
+}
+
+/// Text and placeables up to the end, or for a variant, to the end of its line.
+fn pattern(s: &[char], i: &mut usize, variant: bool) -> Option<Vec<Part>> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    while let Some(&c) = s.get(*i) {
+        *i += 1;
+        match c {
+            '\n' if variant => break,
+            '\n' => {
+                text.push('\n');
+                while s.get(*i).is_some_and(|c| *c == ' ') {
+                    *i += 1;
+                }
+            }
+            '{' => {
+                if !text.is_empty() {
+                    parts.push(Part::Text(std::mem::take(&mut text)));
+                }
+                parts.push(placeable(s, i)?);
+            }
+            '}' => return None,
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(Part::Text(text));
+    }
+    Some(trimmed(parts))
+}
+
+/// What follows a `{`, through its `}`.
+fn placeable(s: &[char], i: &mut usize) -> Option<Part> {
+    skip_space(s, i);
+    let part = match s.get(*i)? {
+        '"' => {
+            *i += 1;
+            let start = *i;
+            while *s.get(*i)? != '"' {
+                *i += 1;
+            }
+            *i += 1;
+            Part::Text(s[start..*i - 1].iter().collect())
+        }
+        '$' => {
+            *i += 1;
+            let var = ident(s, i)?;
+            skip_space(s, i);
+            if s.get(*i) == Some(&'-') && s.get(*i + 1) == Some(&'>') {
+                *i += 2;
+                select(s, i, var)?
+            } else {
+                Part::Var(var)
+            }
+        }
+        _ => return None,
+    };
+    skip_space(s, i);
+    (s.get(*i) == Some(&'}')).then(|| {
+        *i += 1;
+        part
+    })
+}
+
+/// The variants of a select, up to its closing `}`.
+fn select(s: &[char], i: &mut usize, var: String) -> Option<Part> {
+    let mut variants = Vec::new();
+    let mut default = None;
+    loop {
+        skip_space(s, i);
+        match s.get(*i)? {
+            '}' => break,
+            '*' => {
+                default = Some(variants.len());
+                *i += 1;
+            }
+            _ => {}
+        }
+        if s.get(*i) != Some(&'[') {
+            return None;
+        }
+        *i += 1;
+        let start = *i;
+        while *s.get(*i)? != ']' {
+            *i += 1;
+        }
+        let key: String = s[start..*i].iter().collect();
+        *i += 1;
+        variants.push((key.trim().to_string(), pattern(s, i, true)?));
+    }
+    Some(Part::Select {
+        var,
+        variants,
+        default: default?,
+    })
+}
+
+fn parse(lang: &str, source: &str) -> Catalog {
+    let mut entries: Vec<(String, String)> = Vec::new();
+    for line in source.lines() {
+        let continues = line.starts_with([' ', '\t', '}', '[', '*']);
+        match entries.last_mut() {
+            Some((_, value)) if continues => {
+                value.push('\n');
+                value.push_str(line.trim_start());
+            }
+            _ if line.trim().is_empty() || line.starts_with('#') => {}
+            _ => match line.split_once('=') {
+                Some((id, value)) => entries.push((id.trim().to_string(), value.to_string())),
+                None => log::warn!("i18n: {lang}: can't read {line:?}"),
+            },
+        }
+    }
+    entries
+        .into_iter()
+        .filter_map(|(id, value)| {
+            let chars: Vec<char> = value.chars().collect();
+            match pattern(&chars, &mut 0, false) {
+                Some(parts) => Some((id, parts)),
+                None => {
+                    log::warn!("i18n: {lang}: can't read message {id}");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn catalogs() -> &'static HashMap<&'static str, Catalog> {
+    static CATALOGS: OnceLock<HashMap<&'static str, Catalog>> = OnceLock::new();
+    CATALOGS.get_or_init(|| {
+        LOCALES
+            .iter()
+            .map(|(lang, source)| (*lang, parse(lang, source)))
+            .collect()
+    })
+}
+
+/// The CLDR plural category of `n`, for the languages with a catalog.
+fn plural(lang: &str, n: f64) -> &'static str {
+    let one = match lang {
+        "fr" => (0.0..2.0).contains(&n),
+        _ => n == 1.0,
+    };
+    if one {
+        "one"
+    } else {
+        "other"
+    }
+}
+
+fn matches(key: &str, arg: Option<&Arg>, lang: &str) -> bool {
+    match arg {
+        Some(Arg::Number(n)) => key == n.to_string() || key == plural(lang, *n),
+        Some(Arg::Text(t)) => key == t,
+        None => false,
+    }
+}
+
+fn format(parts: &[Part], args: &[(&str, Arg)], lang: &str, out: &mut String) {
+    let arg = |name: &str| args.iter().find(|(n, _)| *n == name).map(|(_, a)| a);
+    for part in parts {
+        match part {
+            Part::Text(text) => out.push_str(text),
+            Part::Var(var) => match arg(var) {
+                Some(Arg::Number(n)) => {
+                    let _ = write!(out, "{n}");
+                }
+                Some(Arg::Text(t)) => out.push_str(t),
+                None => {
+                    let _ = write!(out, "{{${var}}}");
+                }
+            },
+            Part::Select {
+                var,
+                variants,
+                default,
+            } => {
+                let chosen = variants
+                    .iter()
+                    .position(|(key, _)| matches(key, arg(var), lang))
+                    .unwrap_or(*default);
+                format(&variants[chosen].1, args, lang, out);
+            }
+        }
+    }
+}
+
+/// The catalog for a BCP 47 tag: its language if there's a catalog for it, else
+/// English.
+pub fn resolve(tag: &str) -> &'static str {
+    let tag = tag.trim().to_lowercase();
+    let lang = tag.split(['-', '_']).next().unwrap_or_default();
+    LOCALES
+        .iter()
+        .map(|(l, _)| *l)
+        .find(|l| *l == lang)
+        .unwrap_or(FALLBACK)
+}
+
+/// The language strings are being translated into.
+pub fn locale() -> &'static str {
+    *CURRENT.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// `language.locale`, or the system's locale while it's empty.
+pub fn tag(settings: &SettingsStore) -> String {
+    let wanted = settings.get().language.locale;
+    if wanted.is_empty() {
+        tauri_plugin_os::locale().unwrap_or_default()
+    } else {
+        wanted
+    }
+}
+
+/// Follows `language.locale`, or the system's language while it's empty.
+pub fn apply(settings: &SettingsStore) -> &'static str {
+    let locale = resolve(&tag(settings));
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = locale;
+    locale
+}
+
+/// Message `id` in the current language.
+pub fn t(id: &str) -> String {
+    t_args(id, &[])
+}
+
+/// Message `id` in the current language, with its placeables filled in from `args`.
+pub fn t_args(id: &str, args: &[(&str, Arg)]) -> String {
+    let catalogs = catalogs();
+    let found = [locale(), FALLBACK]
+        .into_iter()
+        .find_map(|lang| Some((lang, catalogs.get(lang)?.get(id)?)));
+    let Some((lang, parts)) = found else {
+        log::warn!("i18n: no message {id}");
+        return id.to_string();
+    };
+    let mut out = String::new();
+    format(parts, args, lang, &mut out);
+    out
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocaleInfo {
+    /// The catalog in use.
+    pub locale: &'static str,
+    /// What's saved in the settings; empty follows the system.
+    pub requested: String,
+    pub available: Vec<&'static str>,
+}
+
+/// Translates the backend's strings into `locale` from now on, with `None` or an empty
+/// string following the system. A language without a catalog gets English. The tray and
+/// the scheduled reminders are redone in the new language.
+#[tauri::command]
+pub fn set_locale(app: AppHandle, locale: Option<String>) -> Result<LocaleInfo, AppError> {
+    let info = save_locale(&app.state::<SettingsStore>(), locale)?;
+    crate::calendar::apply(&app);
+    crate::tray::refresh(&app);
+    crate::mobile_reminders::sync_logged(&app);
+    Ok(info)
+}
+
+/// Saves the language setting and translates into it from now on.
+pub fn save_locale(
+    settings: &SettingsStore,
+    locale: Option<String>,
+) -> Result<LocaleInfo, AppError> {
+    let requested = locale.unwrap_or_default().trim().to_string();
+    settings.update(|s| s.language.locale = requested.clone())?;
+    Ok(LocaleInfo {
+        locale: apply(settings),
+        requested,
+        available: LOCALES.iter().map(|(l, _)| *l).collect(),
+    })
+}
@@ -0,0 +1,167 @@
+//! What the OS says about the user's presence: how long since the last keyboard or mouse
+//! input, and whether the session is locked. Used by the app lock's auto-lock
+//! (`lock.rs`). Each probe returns `None` where the platform or desktop can't tell.
+
+use std::time::Duration;
+
+#[cfg(windows)]
+mod imp {
+    use std::time::Duration;
+
+    use windows_sys::Win32::System::StationsAndDesktops::{
+        CloseDesktop, OpenInputDesktop, DESKTOP_SWITCHDESKTOP,
+    };
+    use windows_sys::Win32::System::SystemInformation::GetTickCount;
+    use windows_sys::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+
+    pub fn idle_time() -> Option<Duration> {
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        if unsafe { GetLastInputInfo(&mut info) } == 0 {
+            return None;
+        }
+        let now = unsafe { GetTickCount() };
+        Some(Duration::from_millis(now.wrapping_sub(info.dwTime).into()))
+    }
+
+    /// While the workstation is locked the input desktop is Winlogon's, which a user
+    /// process can't open.
+    pub fn session_locked() -> Option<bool> {
+        let desktop = unsafe { OpenInputDesktop(0, 0, DESKTOP_SWITCHDESKTOP) };
+        if desktop.is_null() {
+            return Some(true);
+        }
+        unsafe { CloseDesktop(desktop) };
+        Some(false)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use std::ffi::{c_char, c_void};
+    use std::time::Duration;
+
+    const COMBINED_SESSION_STATE: i32 = 0;
+    const ANY_INPUT_EVENT: u32 = u32::MAX;
+    const UTF8: u32 = 0x0800_0100;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGEventSourceSecondsSinceLastEventType(state: i32, event_type: u32) -> f64;
+        fn CGSessionCopyCurrentDictionary() -> *const c_void;
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFStringCreateWithCString(
+            alloc: *const c_void,
+            s: *const c_char,
+            encoding: u32,
+        ) -> *const c_void;
+        fn CFDictionaryGetValue(dict: *const c_void, key: *const c_void) -> *const c_void;
+        fn CFBooleanGetValue(boolean: *const c_void) -> u8;
+        fn CFRelease(cf: *const c_void);
+    }
+
+    pub fn idle_time() -> Option<Duration> {
+        let seconds = unsafe {
+            CGEventSourceSecondsSinceLastEventType(COMBINED_SESSION_STATE, ANY_INPUT_EVENT)
+        };
+        seconds
+            .is_finite()
+            .then(|| Duration::from_secs_f64(seconds.max(0.0)))
+    }
+
+    /// The session dictionary only has `CGSSessionScreenIsLocked` while the screen is
+    /// locked.
+    pub fn session_locked() -> Option<bool> {
+        unsafe {
+            let session = CGSessionCopyCurrentDictionary();
+            if session.is_null() {
+                return None;
+            }
+            let key = CFStringCreateWithCString(
+                std::ptr::null(),
+                c"CGSSessionScreenIsLocked".as_ptr(),
+                UTF8,
+            );
+            let value = if key.is_null() {
+                std::ptr::null()
+            } else {
+                CFDictionaryGetValue(session, key)
+            };
+            let locked = !value.is_null() && CFBooleanGetValue(value) != 0;
+            if !key.is_null() {
+                CFRelease(key);
+            }
+            CFRelease(session);
+            Some(locked)
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::time::Duration;
+
+    use dbus::blocking::stdintf::org_freedesktop_dbus::Properties;
+    use dbus::blocking::Connection;
+
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// GNOME's idle monitor, else the freedesktop screensaver (KDE and others).
+    pub fn idle_time() -> Option<Duration> {
+        let conn = Connection::new_session().ok()?;
+        let mutter = conn.with_proxy(
+            "org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            TIMEOUT,
+        );
+        if let Ok((ms,)) =
+            mutter.method_call::<(u64,), _, _, _>("org.gnome.Mutter.IdleMonitor", "GetIdletime", ())
+        {
+            return Some(Duration::from_millis(ms));
+        }
+        let screensaver = conn.with_proxy("org.freedesktop.ScreenSaver", "/ScreenSaver", TIMEOUT);
+        screensaver
+            .method_call::<(u32,), _, _, _>("org.freedesktop.ScreenSaver", "GetSessionIdleTime", ())
+            .ok()
+            .map(|(secs,)| Duration::from_secs(secs.into()))
+    }
+
+    /// logind's `LockedHint`, which the desktop sets while its lock screen is up.
+    pub fn session_locked() -> Option<bool> {
+        let conn = Connection::new_system().ok()?;
+        conn.with_proxy(
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1/session/auto",
+            TIMEOUT,
+        )
+        .get::<bool>("org.freedesktop.login1.Session", "LockedHint")
+        .ok()
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod imp {
+    use std::time::Duration;
+
+    pub fn idle_time() -> Option<Duration> {
+        None
+    }
+
+    pub fn session_locked() -> Option<bool> {
+        None
+    }
+}
+
+/// Time since the last keyboard or mouse input anywhere in the session.
+pub fn idle_time() -> Option<Duration> {
+    imp::idle_time()
+}
+
+pub fn session_locked() -> Option<bool> {
+    imp::session_locked()
+}
@@ -0,0 +1,378 @@
+//! Heavy analytics (mood correlations, exports, the year in review) run as background
+//! jobs, off the threads that handle commands and drive the tray. `start_job` returns at
+//! once with the job's id; progress arrives as `job:progress` events and the outcome as
+//! `job:finished`, and `get_job` returns it later too.
+//!
+//! At most [`WORKERS`] jobs run at a time, each on the blocking thread pool; later ones
+//! wait their turn. `cancel_job` stops a job at its next step (after the habit it's on),
+//! or before it starts if it's still waiting. Jobs don't survive quitting the app.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::sync::Semaphore;
+
+use crate::error::AppError;
+use crate::settings::{generate_token, ExportFormat};
+use crate::storage::Storage;
+
+/// Emitted with the job's [`JobStatus`] (without a result) as it moves along.
+pub const JOB_PROGRESS_EVENT: &str = "job:progress";
+/// Emitted with the job's [`JobStatus`] once it's done, failed or cancelled.
+pub const JOB_FINISHED_EVENT: &str = "job:finished";
+
+/// Jobs that run at once.
+pub const WORKERS: usize = 2;
+/// Finished jobs kept for `get_job`; older ones are dropped.
+const KEEP_FINISHED: usize = 20;
+/// Progress events at most this often per job.
+const PROGRESS_EVERY: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum JobRequest {
+    /// `get_mood_insights`, in the background.
+    MoodInsights { days: Option<u32> },
+    /// `export_data`, in the background; `path` has to come from `pick_save_path`.
+    Export {
+        path: String,
+        format: ExportFormat,
+        passphrase: Option<String>,
+    },
+    /// Default this year.
+    YearReview { year: Option<i32> },
+}
+
+impl JobRequest {
+    fn kind(&self) -> &'static str {
+        match self {
+            JobRequest::MoodInsights { .. } => "moodInsights",
+            JobRequest::Export { .. } => "export",
+            JobRequest::YearReview { .. } => "yearReview",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    pub id: String,
+    pub kind: &'static str,
+    pub state: JobState,
+    /// 0 to 1.
+    pub progress: f64,
+    pub queued_at: String,
+    pub finished_at: Option<String>,
+    /// What the job produced, once done: `MoodInsights`, the export's path or a
+    /// `YearReview`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    /// The `AppError`, if it failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<serde_json::Value>,
+}
+
+struct Job {
+    status: JobStatus,
+    cancel: Arc<AtomicBool>,
+}
+
+pub struct Jobs {
+    /// Oldest first.
+    jobs: Mutex<Vec<Job>>,
+    workers: Arc<Semaphore>,
+}
+
+impl Default for Jobs {
+    fn default() -> Self {
+        Self {
+            jobs: Mutex::new(Vec::new()),
+            workers: Arc::new(Semaphore::new(WORKERS)),
+        }
+    }
+}
+
+impl Jobs {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Job>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Applies `f` to the job's status and returns a copy, if the job is still kept.
+    fn update(&self, id: &str, f: impl FnOnce(&mut JobStatus)) -> Option<JobStatus> {
+        let mut jobs = self.lock();
+        let job = jobs.iter_mut().find(|j| j.status.id == id)?;
+        f(&mut job.status);
+        Some(job.status.clone())
+    }
+
+    pub fn get(&self, id: &str) -> Option<JobStatus> {
+        self.lock()
+            .iter()
+            .find(|j| j.status.id == id)
+            .map(|j| j.status.clone())
+    }
+
+    /// Newest first, without results.
+    pub fn list(&self) -> Vec<JobStatus> {
+        self.lock()
+            .iter()
+            .rev()
+            .map(|j| JobStatus {
+                result: None,
+                ..j.status.clone()
+            })
+            .collect()
+    }
+
+    /// Returns the job's status, or `None` if there's no such job.
+    pub fn cancel(&self, id: &str) -> Option<JobStatus> {
+        let jobs = self.lock();
+        let job = jobs.iter().find(|j| j.status.id == id)?;
+        job.cancel.store(true, Ordering::Relaxed);
+        Some(job.status.clone())
+    }
+
+    /// Jobs kept, and the size of their results.
+    pub fn usage(&self) -> (usize, u64) {
+        let jobs = self.lock();
+        let bytes = jobs
+            .iter()
+            .filter_map(|j| j.status.result.as_ref())
+            .map(|r| r.to_string().len() as u64)
+            .sum();
+        (jobs.len(), bytes)
+    }
+
+    /// Drops every finished job, results and all.
+    pub fn forget_finished(&self) {
+        self.lock().retain(|j| j.status.finished_at.is_none());
+    }
+
+    fn forget_old(&self) {
+        let mut jobs = self.lock();
+        let finished = jobs
+            .iter()
+            .filter(|j| j.status.finished_at.is_some())
+            .count();
+        let mut extra = finished.saturating_sub(KEEP_FINISHED);
+        jobs.retain(|j| {
+            let drop = extra > 0 && j.status.finished_at.is_some();
+            extra -= usize::from(drop);
+            !drop
+        });
+    }
+
+    /// Adds a job that hasn't started yet.
+    fn queue(&self, request: &JobRequest) -> (JobStatus, Arc<AtomicBool>) {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let status = JobStatus {
+            id: generate_token()[..16].to_string(),
+            kind: request.kind(),
+            state: JobState::Queued,
+            progress: 0.0,
+            queued_at: chrono::Utc::now().to_rfc3339(),
+            finished_at: None,
+            result: None,
+            error: None,
+        };
+        self.lock().push(Job {
+            status: status.clone(),
+            cancel: cancel.clone(),
+        });
+        (status, cancel)
+    }
+
+    /// Records how the job ended.
+    fn finish(&self, id: &str, outcome: Result<serde_json::Value, AppError>) -> Option<JobStatus> {
+        let now = chrono::Utc::now().to_rfc3339();
+        self.update(id, |s| {
+            s.finished_at = Some(now);
+            match outcome {
+                Ok(value) => {
+                    s.state = JobState::Done;
+                    s.progress = 1.0;
+                    s.result = Some(value);
+                }
+                Err(AppError::Cancelled) => s.state = JobState::Cancelled,
+                Err(e) => {
+                    log::warn!("job {id} failed: {e}");
+                    s.state = JobState::Failed;
+                    s.error = serde_json::to_value(&e).ok();
+                }
+            }
+        })
+    }
+
+    /// Runs `request` on this thread and returns how it ended, for `--rpc`, where nothing
+    /// runs between requests. An export's `path` is used as given.
+    pub(crate) fn run_now(&self, storage: &Storage, request: JobRequest) -> JobStatus {
+        let (queued, _) = self.queue(&request);
+        let id = queued.id.clone();
+        self.update(&id, |s| s.state = JobState::Running);
+        let mut step = |done: usize, total: usize| {
+            self.update(&id, |s| s.progress = fraction(done, total));
+            Ok(())
+        };
+        let outcome = perform(storage, request, |path| Ok(path.into()), &mut step);
+        let status = self.finish(&id, outcome);
+        self.forget_old();
+        status.unwrap_or(queued)
+    }
+}
+
+fn fraction(done: usize, total: usize) -> f64 {
+    if total == 0 {
+        1.0
+    } else {
+        done as f64 / total as f64
+    }
+}
+
+fn emit_progress(app: &AppHandle, status: &JobStatus) {
+    let _ = app.emit(
+        JOB_PROGRESS_EVENT,
+        JobStatus {
+            result: None,
+            ..status.clone()
+        },
+    );
+}
+
+/// Does the work. `allowed` turns an export's `path` into where to write.
+fn perform(
+    storage: &Storage,
+    request: JobRequest,
+    allowed: impl FnOnce(&str) -> Result<PathBuf, AppError>,
+    step: &mut dyn FnMut(usize, usize) -> Result<(), AppError>,
+) -> Result<serde_json::Value, AppError> {
+    let value = match request {
+        JobRequest::MoodInsights { days } => {
+            serde_json::to_value(crate::mood::insights_with(storage, days, step)?)
+        }
+        JobRequest::Export {
+            path,
+            format,
+            passphrase,
+        } => {
+            step(0, 1)?;
+            let path = allowed(&path)?;
+            let written = crate::exports::export_to(storage, &path, format, passphrase.as_deref())?;
+            step(1, 1)?;
+            serde_json::to_value(written.display().to_string())
+        }
+        JobRequest::YearReview { year } => {
+            use chrono::Datelike;
+            let year = year.unwrap_or_else(|| crate::stats::today().year());
+            serde_json::to_value(crate::review::year_review(storage, year, step)?)
+        }
+    };
+    value.map_err(|e| AppError::Failed(e.to_string()))
+}
+
+fn run(
+    app: &AppHandle,
+    request: JobRequest,
+    step: &mut dyn FnMut(usize, usize) -> Result<(), AppError>,
+) -> Result<serde_json::Value, AppError> {
+    perform(
+        &app.state::<Storage>(),
+        request,
+        |path| Ok(crate::files::allowed(app, path)?),
+        step,
+    )
+}
+
+/// Queues a job and returns its status right away.
+pub fn start(app: &AppHandle, request: JobRequest) -> Result<JobStatus, AppError> {
+    if let JobRequest::Export { path, .. } = &request {
+        // Fail now rather than in the background.
+        crate::files::allowed(app, path)?;
+    }
+    let jobs = app.state::<Jobs>();
+    let (status, cancel) = jobs.queue(&request);
+
+    let app = app.clone();
+    let id = status.id.clone();
+    let workers = jobs.workers.clone();
+    tauri::async_runtime::spawn(async move {
+        let Ok(_permit) = workers.acquire_owned().await else {
+            return;
+        };
+        let outcome = if cancel.load(Ordering::Relaxed) {
+            Err(AppError::Cancelled)
+        } else {
+            let jobs = app.state::<Jobs>();
+            if let Some(status) = jobs.update(&id, |s| s.state = JobState::Running) {
+                emit_progress(&app, &status);
+            }
+            let (a, job_id) = (app.clone(), id.clone());
+            let work = tauri::async_runtime::spawn_blocking(move || {
+                let mut last = Instant::now();
+                let mut step = |done: usize, total: usize| {
+                    if cancel.load(Ordering::Relaxed) {
+                        return Err(AppError::Cancelled);
+                    }
+                    let progress = fraction(done, total);
+                    let status = a.state::<Jobs>().update(&job_id, |s| s.progress = progress);
+                    if let Some(status) = status.filter(|_| last.elapsed() >= PROGRESS_EVERY) {
+                        emit_progress(&a, &status);
+                        last = Instant::now();
+                    }
+                    Ok(())
+                };
+                run(&a, request, &mut step)
+            });
+            work.await
+                .unwrap_or_else(|e| Err(AppError::Failed(e.to_string())))
+        };
+
+        let status = app.state::<Jobs>().finish(&id, outcome);
+        if let Some(status) = status {
+            let _ = app.emit(JOB_FINISHED_EVENT, &status);
+        }
+        app.state::<Jobs>().forget_old();
+    });
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn start_job(app: AppHandle, request: JobRequest) -> Result<JobStatus, AppError> {
+    start(&app, request)
+}
+
+/// Includes the result once the job is done.
+#[tauri::command]
+pub fn get_job(jobs: State<'_, Jobs>, id: String) -> Result<JobStatus, AppError> {
+    jobs.get(&id).ok_or_else(|| AppError::not_found("job", &id))
+}
+
+/// Queued, running and recently finished jobs, newest first, without results.
+#[tauri::command]
+pub fn list_jobs(jobs: State<'_, Jobs>) -> Vec<JobStatus> {
+    jobs.list()
+}
+
+/// Cancelling a finished job does nothing.
+#[tauri::command]
+pub fn cancel_job(jobs: State<'_, Jobs>, id: String) -> Result<JobStatus, AppError> {
+    jobs.cancel(&id)
+        .ok_or_else(|| AppError::not_found("job", &id))
+}
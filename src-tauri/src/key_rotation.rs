@@ -0,0 +1,243 @@
+//! Changing the passphrase of encrypted exports (`archive.rs`) without an export/import
+//! cycle.
+//!
+//! `rotate_encryption_key` finds the encrypted export jobs whose passphrase is `old`, and
+//! every `.enc` file they wrote: the ones in the export history and any other
+//! `habitflow-*.enc` in their folders. Each file is decrypted and written again under
+//! `new` next to itself, with `encryption:progress` events on the way. Only once every
+//! file is ready are the jobs' keychain entries changed and the new files renamed over the
+//! old ones, so a failure before that leaves everything as it was. Files that don't open
+//! with `old` are left alone and listed as skipped.
+//!
+//! Scheduled encrypted exports wait while this runs. HabitFlow doesn't encrypt its
+//! database, so exports are all there is to rotate; copies already on a backup drive
+//! (`backup_drive.rs`) keep the old passphrase.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::archive;
+use crate::error::AppError;
+use crate::exports::{expand_home, passphrase_key, ENCRYPTED_WRITES};
+use crate::secrets;
+use crate::settings::{ExportJob, SettingsStore};
+use crate::storage::Storage;
+
+/// Emitted with a [`RotationProgress`] before each file and once all are ready.
+pub const PROGRESS_EVENT: &str = "encryption:progress";
+
+/// Appended to a re-encrypted file until it replaces the original.
+const STAGED_SUFFIX: &str = "rotating";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationProgress {
+    /// Files re-encrypted so far.
+    pub done: usize,
+    pub total: usize,
+    /// The file being re-encrypted; left out once all are done.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotationReport {
+    /// Ids of the export jobs now using the new passphrase.
+    pub jobs: Vec<String>,
+    /// Files now encrypted with the new passphrase.
+    pub files: Vec<String>,
+    /// Files that didn't open with the old passphrase, left as they were.
+    pub skipped: Vec<String>,
+}
+
+fn is_export(path: &Path) -> bool {
+    path.file_name().and_then(|n| n.to_str()).is_some_and(|n| {
+        n.starts_with("habitflow-") && n.ends_with(&format!(".{}", archive::EXTENSION))
+    })
+}
+
+/// The encrypted files `jobs` wrote that are still around.
+fn files(storage: &Storage, jobs: &[ExportJob]) -> Result<Vec<PathBuf>, String> {
+    let mut files = BTreeSet::new();
+    for job in jobs {
+        let runs = storage
+            .list_export_runs(Some(&job.id), u32::MAX)
+            .map_err(|e| e.to_string())?;
+        files.extend(
+            runs.into_iter()
+                .filter_map(|r| r.path)
+                .map(PathBuf::from)
+                .filter(|p| is_export(p) && p.is_file()),
+        );
+        // Files whose runs are no longer in the history.
+        if let Ok(entries) = std::fs::read_dir(expand_home(&job.directory)) {
+            files.extend(
+                entries
+                    .flatten()
+                    .map(|e| e.path())
+                    .filter(|p| is_export(p) && p.is_file()),
+            );
+        }
+    }
+    Ok(files.into_iter().collect())
+}
+
+fn staged(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{STAGED_SUFFIX}"));
+    PathBuf::from(name)
+}
+
+/// Writes `path` re-encrypted under `new` next to it. `Ok(None)` if it doesn't open with
+/// `old`.
+fn stage(path: &Path, old: &str, new: &str) -> Result<Option<PathBuf>, String> {
+    let data = std::fs::read(path).map_err(|e| format!("can't read {}: {e}", path.display()))?;
+    let Ok(plaintext) = archive::decrypt(&data, old) else {
+        return Ok(None);
+    };
+    let target = staged(path);
+    std::fs::write(&target, archive::encrypt(&plaintext, new)?)
+        .and_then(|_| std::fs::File::open(&target)?.sync_all())
+        .map_err(|e| {
+            let _ = std::fs::remove_file(&target);
+            format!("can't write {}: {e}", target.display())
+        })?;
+    Ok(Some(target))
+}
+
+fn discard(staged: &[(PathBuf, PathBuf)]) {
+    for (_, temp) in staged {
+        let _ = std::fs::remove_file(temp);
+    }
+}
+
+/// Moves every encrypted export job using `old` over to `new`, re-encrypting their files.
+pub fn rotate(app: &AppHandle, old: &str, new: &str) -> Result<RotationReport, AppError> {
+    rotate_with(
+        &app.state::<Storage>(),
+        &app.state::<SettingsStore>(),
+        old,
+        new,
+        &mut |progress| {
+            let _ = app.emit(PROGRESS_EVENT, progress);
+        },
+    )
+}
+
+/// [`rotate`], reporting progress to `progress` rather than as events.
+pub fn rotate_with(
+    storage: &Storage,
+    settings: &SettingsStore,
+    old: &str,
+    new: &str,
+    progress: &mut dyn FnMut(RotationProgress),
+) -> Result<RotationReport, AppError> {
+    if old == new {
+        return Err(AppError::Invalid(
+            "the new passphrase is the same as the old one".into(),
+        ));
+    }
+    if new.chars().count() < archive::MIN_PASSPHRASE_LEN {
+        return Err(AppError::Invalid(format!(
+            "the passphrase needs at least {} characters",
+            archive::MIN_PASSPHRASE_LEN
+        )));
+    }
+    let _writes = ENCRYPTED_WRITES.lock().unwrap_or_else(|e| e.into_inner());
+    let mut jobs = Vec::new();
+    for job in settings.get().export_jobs {
+        if job.encrypted && secrets::get(&passphrase_key(&job.id))?.as_deref() == Some(old) {
+            jobs.push(job);
+        }
+    }
+    if jobs.is_empty() {
+        return Err(AppError::Invalid(
+            "no encrypted export job uses that passphrase".into(),
+        ));
+    }
+
+    let files = files(storage, &jobs)?;
+    let total = files.len();
+    let mut report = RotationReport::default();
+    let mut ready = Vec::new();
+    for (done, path) in files.into_iter().enumerate() {
+        progress(RotationProgress {
+            done,
+            total,
+            path: Some(path.display().to_string()),
+        });
+        match stage(&path, old, new) {
+            Ok(Some(temp)) => ready.push((path, temp)),
+            Ok(None) => report.skipped.push(path.display().to_string()),
+            Err(e) => {
+                discard(&ready);
+                return Err(AppError::Failed(e));
+            }
+        }
+    }
+    progress(RotationProgress {
+        done: total,
+        total,
+        path: None,
+    });
+
+    // Everything is ready: the keychain first, then the files.
+    for (i, job) in jobs.iter().enumerate() {
+        if let Err(e) = secrets::set(&passphrase_key(&job.id), new) {
+            for job in &jobs[..i] {
+                let _ = secrets::set(&passphrase_key(&job.id), old);
+            }
+            discard(&ready);
+            return Err(AppError::Failed(e));
+        }
+        report.jobs.push(job.id.clone());
+    }
+    let mut failed = Vec::new();
+    for (path, temp) in ready {
+        match std::fs::rename(&temp, &path) {
+            Ok(()) => report.files.push(path.display().to_string()),
+            Err(e) => {
+                log::error!("key rotation: can't replace {}: {e}", path.display());
+                failed.push(format!(
+                    "{} (the new copy is {})",
+                    path.display(),
+                    temp.display()
+                ));
+            }
+        }
+    }
+    if !failed.is_empty() {
+        return Err(AppError::Failed(format!(
+            "the passphrase changed, but some files couldn't be replaced: {}",
+            failed.join(", ")
+        )));
+    }
+    log::info!(
+        "key rotation: {} jobs and {} files, {} skipped",
+        report.jobs.len(),
+        report.files.len(),
+        report.skipped.len()
+    );
+    Ok(report)
+}
+
+/// Changes the passphrase of the encrypted export jobs using `old` to `new`, and
+/// re-encrypts their files in the background. Progress comes as `encryption:progress`.
+#[tauri::command]
+pub async fn rotate_encryption_key(
+    app: AppHandle,
+    old: String,
+    new: String,
+) -> Result<RotationReport, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let report = rotate(&app, &old, &new);
+        crate::redact::refresh_secrets(&app);
+        report
+    })
+    .await
+    .map_err(|e| AppError::Failed(e.to_string()))?
+}
@@ -0,0 +1,242 @@
+//! One-time import of the habits older builds kept only in the webview's localStorage
+//! (`habitflow_data_v1`).
+//!
+//! The webview calls `import_legacy_state` with that value on the first launch that has
+//! the backend storage. Each entry is checked on its own: ones that aren't habits are
+//! skipped and reported, and days that aren't `yyyy-MM-dd` are dropped. A habit that's
+//! already stored (the same id, or the same name and creation time), or that's in the
+//! payload twice, is merged: days it doesn't have yet are added and everything else stays.
+//!
+//! Everything is written in one transaction, so an import that fails partway leaves the
+//! database as it was and can be tried again. Once one succeeds it's recorded, and later
+//! calls do nothing. localStorage itself is left alone.
+
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::{Habit, DEFAULT_CATEGORY};
+use crate::stats::{date_key, parse_date};
+use crate::storage::Storage;
+
+/// Set to the time of the import once one succeeded.
+pub const IMPORTED_KEY: &str = "legacy.imported";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacySkip {
+    /// Position in the payload.
+    pub index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyImport {
+    /// An earlier import already ran; nothing was done.
+    pub already_imported: bool,
+    /// New habits added.
+    pub imported: usize,
+    /// Entries merged into a stored habit or an earlier entry.
+    pub merged: usize,
+    /// Days added to habits that were already there.
+    pub days_added: usize,
+    /// Days dropped for not being dates.
+    pub days_dropped: usize,
+    pub skipped: Vec<LegacySkip>,
+}
+
+/// `yyyy-MM-dd`, also taken from the front of a full timestamp.
+fn day(key: &str) -> Option<String> {
+    parse_date(key.get(..10).unwrap_or(key)).map(date_key)
+}
+
+/// Parses and tidies one entry. Returns the habit and how many days were dropped.
+fn parse(value: serde_json::Value) -> Result<(Habit, usize), String> {
+    let mut habit: Habit = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    habit.id = habit.id.trim().to_string();
+    habit.name = habit.name.trim().to_string();
+    if habit.id.is_empty() {
+        return Err("it has no id".into());
+    }
+    if habit.name.is_empty() {
+        return Err("it has no name".into());
+    }
+    if habit.category.trim().is_empty() {
+        habit.category = DEFAULT_CATEGORY.into();
+    }
+    // Nothing the old builds stored knows about revisions.
+    habit.revision = 0;
+
+    let mut dropped = 0;
+    let mut days = |keys: Vec<String>| -> Vec<(String, String)> {
+        keys.into_iter()
+            .filter_map(|key| {
+                let day = day(&key);
+                dropped += usize::from(day.is_none());
+                Some((key, day?))
+            })
+            .collect()
+    };
+    let logs = days(habit.logs.keys().cloned().collect());
+    let values = days(habit.values.keys().cloned().collect());
+    let notes = days(habit.notes.keys().cloned().collect());
+    habit.logs = rekey(std::mem::take(&mut habit.logs), &logs);
+    habit.values = rekey(std::mem::take(&mut habit.values), &values);
+    habit.notes = rekey(std::mem::take(&mut habit.notes), &notes);
+    habit.lapses.retain_mut(|l| match day(&l.date) {
+        Some(d) => {
+            l.date = d;
+            true
+        }
+        None => {
+            dropped += 1;
+            false
+        }
+    });
+    Ok((habit, dropped))
+}
+
+/// `map` with its keys renamed per `keys` (old, new); keys not listed are dropped.
+fn rekey<V>(mut map: BTreeMap<String, V>, keys: &[(String, String)]) -> BTreeMap<String, V> {
+    keys.iter()
+        .filter_map(|(old, new)| Some((new.clone(), map.remove(old)?)))
+        .collect()
+}
+
+/// Adds the days of `from` that `into` doesn't have. Returns how many were added.
+fn merge(into: &mut Habit, from: Habit) -> usize {
+    let mut added = 0;
+    for (date, done) in from.logs {
+        if let Entry::Vacant(day) = into.logs.entry(date) {
+            day.insert(done);
+            added += 1;
+        }
+    }
+    for (date, value) in from.values {
+        into.values.entry(date).or_insert(value);
+    }
+    for (date, note) in from.notes {
+        into.notes.entry(date).or_insert(note);
+    }
+    for lapse in from.lapses {
+        if !into.lapses.iter().any(|l| l.date == lapse.date) {
+            into.lapses.push(lapse);
+            added += 1;
+        }
+    }
+    into.lapses.sort_by(|a, b| a.date.cmp(&b.date));
+    for item in from.checklist {
+        if let Some(mine) = into.checklist.iter_mut().find(|i| i.id == item.id) {
+            mine.done.extend(item.done);
+        }
+    }
+    added
+}
+
+/// Works out what to store: `stored` with the entries of `payload` added or merged in.
+fn plan(stored: Vec<Habit>, payload: Vec<serde_json::Value>) -> (Vec<Habit>, LegacyImport) {
+    let mut summary = LegacyImport::default();
+    let mut habits = stored;
+    let stored_count = habits.len();
+    let mut by_id: HashMap<String, usize> = HashMap::new();
+    let mut by_name: HashMap<(String, String), usize> = HashMap::new();
+    for (i, h) in habits.iter().enumerate() {
+        by_id.insert(h.id.clone(), i);
+        by_name.insert((h.name.to_lowercase(), h.created_at.clone()), i);
+    }
+
+    for (index, value) in payload.into_iter().enumerate() {
+        let id = value.get("id").and_then(|v| v.as_str()).map(str::to_string);
+        let (habit, dropped) = match parse(value) {
+            Ok(parsed) => parsed,
+            Err(reason) => {
+                summary.skipped.push(LegacySkip { index, id, reason });
+                continue;
+            }
+        };
+        summary.days_dropped += dropped;
+        let name_key = (habit.name.to_lowercase(), habit.created_at.clone());
+        let existing = by_id
+            .get(&habit.id)
+            .or_else(|| by_name.get(&name_key))
+            .copied();
+        match existing {
+            Some(i) => {
+                let added = merge(&mut habits[i], habit);
+                if i < stored_count {
+                    summary.days_added += added;
+                }
+                summary.merged += 1;
+            }
+            None => {
+                by_id.insert(habit.id.clone(), habits.len());
+                by_name.insert(name_key, habits.len());
+                habits.push(habit);
+                summary.imported += 1;
+            }
+        }
+    }
+    (habits, summary)
+}
+
+pub fn import(app: &AppHandle, json: &str) -> Result<LegacyImport, AppError> {
+    let summary = import_into(&app.state::<Storage>(), json)?;
+    if !summary.already_imported {
+        app.state::<EventBus>().data_changed();
+        crate::habits::notify_changed(app);
+        crate::mobile_reminders::sync_logged(app);
+    }
+    Ok(summary)
+}
+
+/// [`import`] without telling a window about it.
+pub fn import_into(storage: &Storage, json: &str) -> Result<LegacyImport, AppError> {
+    if storage.get_meta(IMPORTED_KEY)?.is_some() {
+        return Ok(LegacyImport {
+            already_imported: true,
+            ..Default::default()
+        });
+    }
+    let payload: Vec<serde_json::Value> = serde_json::from_str(json)
+        .map_err(|e| AppError::Invalid(format!("the saved habits aren't a list: {e}")))?;
+
+    let summary = {
+        let stored = storage.list_habits()?;
+        let seen = crate::revisions::seen(&stored);
+        let (habits, summary) = plan(stored, payload);
+        let stale = storage
+            .replace_habits_checked(&habits, &seen)
+            .inspect_err(|e| {
+                log::error!("legacy: import failed and was rolled back: {e}");
+            })?;
+        if !stale.is_empty() {
+            return Err(AppError::Failed(format!(
+                "habits changed while importing, try again: {}",
+                stale.join(", ")
+            )));
+        }
+        summary
+    };
+    storage.set_meta(IMPORTED_KEY, &chrono::Utc::now().to_rfc3339())?;
+    log::info!(
+        "legacy: imported {} habits, merged {}, skipped {}",
+        summary.imported,
+        summary.merged,
+        summary.skipped.len()
+    );
+    Ok(summary)
+}
+
+/// `json` is the `habitflow_data_v1` value from localStorage. Runs once; see the module
+/// docs.
+#[tauri::command]
+pub fn import_legacy_state(app: AppHandle, json: String) -> Result<LegacyImport, AppError> {
+    import(&app, &json)
+}
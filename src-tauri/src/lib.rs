@@ -1,84 +1,462 @@
+use std::sync::atomic::AtomicBool;
+
+mod access;
+mod achievements;
+mod api;
+mod appearance;
+mod archive;
+mod assets;
+mod attachments;
+mod automation;
+mod backfill;
+mod backup_drive;
+mod benchmarks;
+mod biometric;
+mod blocking;
+mod calendar;
+mod categories;
+mod challenges;
+mod checklist;
+pub mod cli;
+pub mod clock;
+mod commands;
+mod crash;
+mod demo;
+mod dependencies;
+mod diagnostics;
+mod error;
+mod events;
+mod exports;
+mod features;
+mod files;
+mod focus;
+mod freezes;
+mod gamification;
+mod goals;
+mod habits;
+#[cfg(test)]
+mod harness;
+mod history;
+mod i18n;
+mod idle;
+mod jobs;
+mod key_rotation;
+mod legacy;
+mod lock;
+mod logging;
+mod maintenance;
+mod milestones;
+mod mobile_reminders;
+mod model;
+mod mood;
+mod mqtt;
+mod network;
+mod nfc;
+mod notes;
+mod off_periods;
+mod ordering;
+mod pauses;
+mod plugins;
+mod pomodoro;
+mod privacy;
+mod qr;
+mod quick_add;
+mod redact;
+mod reminders;
+mod repeats;
+mod replica;
+mod resources;
+mod review;
+mod revisions;
+mod rewards;
+mod rpc;
+mod schedule;
+mod scheduler;
+mod screen_time;
+mod secrets;
+mod seed;
+mod settings;
+mod share;
+mod shortcuts;
+mod social;
+mod startup;
+mod stats;
+mod stats_cache;
+mod storage;
+mod streaks;
+mod sync_queue;
+mod tags;
+mod telemetry;
+mod templates;
+mod text;
+mod time_windows;
+mod timers;
+mod tray;
+mod updater;
+mod versioning;
+mod watch;
+mod webhooks;
+mod widgets;
+mod window_effects;
+mod write_queue;
+
+struct AppState {
+    quitting: AtomicBool,
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    use std::sync::atomic::{AtomicBool, Ordering};
-    use tauri::{Emitter, Manager, WindowEvent};
-    use tauri::menu::{Menu, MenuItem};
-    use tauri::tray::{TrayIconBuilder, TrayIconEvent};
+    use std::sync::atomic::Ordering;
+    use tauri::{Manager, RunEvent, WindowEvent};
 
-    struct AppState {
-        quitting: AtomicBool,
-    }
+    #[cfg(target_os = "ios")]
+    mobile_reminders::register_background_refresh();
 
     tauri::Builder::default()
+        .manage(startup::Startup::new())
         .plugin(tauri_plugin_autostart::Builder::new().build())
+        .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_os::init())
+        .register_asynchronous_uri_scheme_protocol(assets::SCHEME, |ctx, request, responder| {
+            let app = ctx.app_handle().clone();
+            tauri::async_runtime::spawn_blocking(move || {
+                responder.respond(assets::respond(&app, &request));
+            });
+        })
         .manage(AppState {
             quitting: AtomicBool::new(false),
         })
+        .manage(api::ApiServer::default())
+        .manage(events::EventBus::default())
+        .manage(mqtt::MqttBridge::default())
+        .manage(timers::Timers::default())
+        .manage(pomodoro::Pomodoro::default())
+        .manage(focus::Focus::default())
+        .manage(lock::AppLock::default())
+        .manage(files::Grants::default())
+        .manage(jobs::Jobs::default())
+        .manage(write_queue::WriteQueue::default())
+        .manage(nfc::NfcBinding::default())
+        .manage(sync_queue::SyncQueue::default())
+        .manage(replica::Replica::default())
+        .manage(backup_drive::BackupDrive::default())
+        .manage(blocking::Blocking::default())
+        .manage(network::Network::default())
+        .manage(updater::Updater::default())
+        .manage(telemetry::Telemetry::default())
+        .invoke_handler(access::guarded(lock::guarded(features::guarded(
+            write_queue::guarded(telemetry::measured(tauri::generate_handler![
+                commands::sync_habits,
+                write_queue::flush_writes,
+                commands::list_habits,
+                commands::log_habit_value,
+                commands::list_check_ins,
+                history::get_completions,
+                stats_cache::get_range_stats,
+                streaks::rebuild_streaks,
+                commands::log_lapse,
+                commands::list_due_habits,
+                commands::archive_habit,
+                commands::unarchive_habit,
+                categories::list_categories,
+                categories::create_category,
+                categories::update_category,
+                categories::reorder_categories,
+                categories::delete_category,
+                categories::assign_category,
+                checklist::add_checklist_item,
+                checklist::rename_checklist_item,
+                checklist::remove_checklist_item,
+                checklist::reorder_checklist,
+                checklist::set_checklist_item_done,
+                dependencies::set_habit_dependencies,
+                dependencies::get_dependency_graph,
+                templates::list_templates,
+                templates::add_template,
+                templates::save_habit_as_template,
+                templates::remove_template,
+                templates::create_from_template,
+                freezes::get_streak_freezes,
+                time_windows::set_habit_window,
+                timers::get_timers,
+                timers::start_timer,
+                timers::pause_timer,
+                timers::stop_timer,
+                appearance::set_habit_appearance,
+                appearance::set_habit_cover,
+                appearance::get_habit_cover,
+                backfill::backfill_completion,
+                milestones::add_milestone,
+                milestones::list_milestones,
+                milestones::remove_milestone,
+                achievements::list_achievements,
+                challenges::get_daily_challenge,
+                challenges::skip_daily_challenge,
+                gamification::get_player_state,
+                gamification::set_habit_difficulty,
+                goals::add_goal,
+                goals::get_goal_progress,
+                goals::remove_goal,
+                lock::get_lock_status,
+                lock::set_app_pin,
+                lock::remove_app_pin,
+                lock::lock_app,
+                lock::unlock_app,
+                secrets::store_secret,
+                secrets::delete_secret,
+                secrets::has_secret,
+                biometric::get_auth_methods,
+                biometric::set_biometric_unlock,
+                biometric::unlock_app_biometric,
+                social::get_social_status,
+                social::enable_social,
+                social::disable_social,
+                social::set_social_privacy,
+                social::add_friend,
+                social::remove_friend,
+                social::get_leaderboard,
+                rewards::list_rewards,
+                rewards::add_reward,
+                rewards::remove_reward,
+                rewards::redeem_reward,
+                repeats::set_times_per_day,
+                repeats::log_repetition,
+                repeats::undo_repetition,
+                reminders::set_weekday_reminders,
+                reminders::next_occurrences,
+                ordering::reorder_habits,
+                ordering::set_habit_pinned,
+                ordering::get_habit_order,
+                ordering::set_sort_preference,
+                pomodoro::get_pomodoro,
+                pomodoro::start_pomodoro,
+                pomodoro::pause_pomodoro,
+                pomodoro::resume_pomodoro,
+                pomodoro::skip_pomodoro_phase,
+                pomodoro::stop_pomodoro,
+                focus::get_focus,
+                focus::start_focus,
+                focus::stop_focus,
+                window_effects::get_window_effects,
+                window_effects::set_window_effect,
+                window_effects::set_window_opacity,
+                network::get_network_status,
+                key_rotation::rotate_encryption_key,
+                text::check_habit_name,
+                maintenance::run_maintenance_now,
+                maintenance::get_maintenance_report,
+                mood::log_mood,
+                mood::list_moods,
+                mood::remove_mood,
+                mood::get_mood_insights,
+                jobs::start_job,
+                jobs::get_job,
+                jobs::list_jobs,
+                jobs::cancel_job,
+                notes::update_checkin_note,
+                notes::search_notes,
+                off_periods::add_off_period,
+                off_periods::list_off_periods,
+                off_periods::remove_off_period,
+                pauses::pause_habit,
+                pauses::resume_habit,
+                quick_add::parse_quick_add,
+                tags::list_tags,
+                tags::set_habit_tags,
+                tags::list_by_tag,
+                tags::get_tag_stats,
+                commands::get_settings,
+                commands::update_settings,
+                commands::regenerate_api_token,
+                webhooks::list_webhooks,
+                webhooks::add_webhook,
+                webhooks::remove_webhook,
+                webhooks::get_webhook_deliveries,
+                automation::list_automations,
+                automation::reload_automations,
+                exports::list_export_jobs,
+                exports::add_export_job,
+                exports::remove_export_job,
+                exports::run_export_job_now,
+                exports::export_data,
+                exports::import_data,
+                files::pick_save_path,
+                files::pick_open_path,
+                files::pick_folder,
+                files::save_backup,
+                files::write_auto_backup,
+                exports::get_export_history,
+                versioning::commit_data_version,
+                versioning::list_data_versions,
+                versioning::restore_data_version,
+                plugins::list_plugins,
+                plugins::reload_plugins,
+                plugins::enable_plugin,
+                plugins::run_plugin_export,
+                startup::get_startup_metrics,
+                benchmarks::run_benchmarks,
+                resources::get_resource_usage,
+                resources::trim_caches,
+                seed::seed_synthetic_data,
+                widgets::get_today_snapshot,
+                widgets::toggle_tile_habit,
+                shortcuts::run_shortcut,
+                mobile_reminders::sync_reminders,
+                attachments::add_checkin_attachment,
+                attachments::list_checkin_attachments,
+                attachments::get_checkin_attachment,
+                attachments::remove_checkin_attachment,
+                share::list_pending_shares,
+                share::attach_share,
+                share::dismiss_share,
+                watch::watch_request,
+                nfc::list_nfc_tags,
+                nfc::bind_nfc_tag,
+                nfc::bind_next_nfc_tag,
+                nfc::unbind_nfc_tag,
+                nfc::scan_nfc_tag,
+                sync_queue::get_sync_status,
+                sync_queue::sync_now,
+                sync_queue::reset_sync,
+                updater::check_for_updates,
+                updater::download_update,
+                updater::restart_to_update,
+                crash::list_crash_reports,
+                crash::submit_crash_report,
+                crash::delete_crash_report,
+                logging::set_log_level,
+                logging::export_logs,
+                telemetry::preview_telemetry_payload,
+                i18n::set_locale,
+                diagnostics::create_diagnostic_bundle,
+                demo::load_demo_data,
+                demo::reset_to_fresh,
+                features::get_feature_flags,
+                legacy::import_legacy_state,
+                replica::get_libsql_status,
+                replica::libsql_sync_now,
+                backup_drive::list_volumes,
+                backup_drive::get_backup_drive_status,
+                qr::generate_qr,
+                templates::share_template,
+                templates::import_shared_template,
+                screen_time::get_screen_time,
+                blocking::get_block_status,
+                blocking::override_block_rule,
+            ])),
+        ))))
         .setup(|app| {
-            // Tray icon + menu
-            let open_item = MenuItem::with_id(app, "open", "Open", true, None::<&str>)?;
-            let add_item = MenuItem::with_id(app, "add_habit", "Add Habit", true, None::<&str>)?;
-            let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            let tray_menu = Menu::with_items(app, &[&open_item, &add_item, &quit_item])?;
+            let startup = app.state::<startup::Startup>();
+            // Backend storage + settings live in the app data dir.
+            let data_dir = app.path().app_data_dir()?;
+            std::fs::create_dir_all(&data_dir)?;
+            crash::install(&data_dir, &app.package_info().version.to_string());
+            let storage = startup.time("storage", false, || {
+                storage::Storage::open(&data_dir.join(storage::DB_FILE_NAME))
+            })?;
+            app.manage(storage);
+
+            let settings = startup.time("settings", false, || {
+                settings::SettingsStore::load(data_dir.join(settings::SETTINGS_FILE_NAME))
+            });
+            if settings.get().api.token.is_empty() {
+                settings.update(|s| s.api.token = settings::generate_token())?;
+            }
+            // Before the tray, whose labels are translated.
+            i18n::apply(&settings);
+            features::apply(&settings);
+            app.manage(settings);
+            calendar::apply(app.handle());
 
-            TrayIconBuilder::with_id("habitflow-tray")
-                .icon(app.default_window_icon().ok_or("missing default window icon")?.clone())
-                .menu(&tray_menu)
-                .on_menu_event(move |app, event| {
-                    let id = event.id().as_ref();
+            #[cfg(mobile)]
+            app.handle().plugin(tauri_plugin_biometric::init())?;
+            #[cfg(mobile)]
+            app.handle().plugin(widgets::init())?;
+            #[cfg(target_os = "android")]
+            app.handle().plugin(share::init())?;
+            #[cfg(target_os = "android")]
+            app.handle().plugin(nfc::init())?;
+            startup.time("lock", false, || lock::init(app.handle()));
+            startup.time("tray", false, || tray::create(app.handle()))?;
+            // Created here rather than from the config, so the tray is up before the
+            // webview starts loading.
+            startup.time("window", false, || create_main_window(app.handle()))?;
 
-                    if id == "open" {
-                        if let Some(w) = app.get_webview_window("main") {
-                            let _ = w.show();
-                            let _ = w.set_focus();
-                        }
-                    } else if id == "add_habit" {
-                        if let Some(w) = app.get_webview_window("main") {
-                            let _ = w.show();
-                            let _ = w.set_focus();
-                            let _ = w.emit("tray:add-habit", ());
-                        }
-                    } else if id == "quit" {
-                        // Allow next close to actually quit
-                        let state = app.state::<AppState>();
-                        state.quitting.store(true, Ordering::SeqCst);
-                        app.exit(0);
-                    }
-                })
-                .on_tray_icon_event(move |tray, event| {
-                    if let TrayIconEvent::Click { .. } = event {
-                        if let Some(w) = tray.app_handle().get_webview_window("main") {
-                            let _ = w.show();
-                            let _ = w.set_focus();
-                        }
-                    }
-                })
-                .build(app)?;
+            app.manage(automation::Automations::new(app.handle(), &data_dir));
+            app.manage(plugins::PluginHost::new(&data_dir));
+            blocking::clear_hosts(app.handle());
 
-            if cfg!(debug_assertions) {
-                app.handle().plugin(
-                    tauri_plugin_log::Builder::default()
-                        .level(log::LevelFilter::Info)
-                        .build(),
-                )?;
-            }
+            tray::start(app.handle());
+            lock::start(app.handle());
+            write_queue::start(app.handle());
+            #[cfg(target_os = "ios")]
+            shortcuts::install(app.handle());
+            startup.setup_done();
 
+            let app = app.handle().clone();
+            tauri::async_runtime::spawn_blocking(move || startup::deferred(&app));
             Ok(())
         })
+        .on_page_load(|webview, payload| {
+            if webview.label() == access::MAIN_WINDOW
+                && payload.event() == tauri::webview::PageLoadEvent::Finished
+            {
+                webview.state::<startup::Startup>().webview_loaded();
+            }
+        })
         // Close button => minimize to tray (hide) unless quitting.
-        .on_window_event(|window, event| {
-            if let WindowEvent::CloseRequested { api, .. } = event {
+        .on_window_event(|window, event| match event {
+            WindowEvent::CloseRequested { api, .. } => {
+                write_queue::flush_logged(window.app_handle());
                 let state = window.state::<AppState>();
-                if !state.quitting.load(Ordering::SeqCst) {
+                if state.quitting.load(Ordering::SeqCst) {
+                    exports::export_on_quit(window.app_handle());
+                } else {
                     api.prevent_close();
                     let _ = window.hide();
                 }
             }
+            WindowEvent::Focused(false) if window.label() == access::MAIN_WINDOW => {
+                write_queue::flush_logged(window.app_handle());
+            }
+            _ => {}
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let RunEvent::ExitRequested { .. } | RunEvent::Exit = event {
+                write_queue::flush_logged(app);
+                exports::export_on_quit(app);
+            }
+            if let RunEvent::Exit = event {
+                telemetry::end_session(app);
+                blocking::clear_hosts(app);
+            }
+            // Taps on the home-screen widget and shares from the iOS share extension queue
+            // up while the app is in the background, and the sync server may be reachable
+            // again.
+            #[cfg(mobile)]
+            if let RunEvent::Resumed = event {
+                widgets::drain_intents(app);
+                mobile_reminders::sync_logged(app);
+                share::announce(app);
+                sync_queue::wake(app);
+            }
+        });
+}
+
+/// The main window from `tauri.conf.json`, which has `"create": false`.
+fn create_main_window(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let config = app
+        .config()
+        .app
+        .windows
+        .iter()
+        .find(|w| w.label == access::MAIN_WINDOW)
+        .cloned()
+        .ok_or_else(|| tauri::Error::WindowNotFound)?;
+    tauri::WebviewWindowBuilder::from_config(app, &config)?.build()?;
+    Ok(())
 }
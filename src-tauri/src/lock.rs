@@ -0,0 +1,494 @@
+//! App lock: a PIN that has to be entered before the app's data can be reached.
+//!
+//! The PIN is stored as an argon2 hash in the OS keychain through `secrets.rs`, never in
+//! `settings.json`. With a PIN set the app starts locked, and
+//! while it's locked the backend itself refuses every command except the ones needed to
+//! unlock (see `guarded`), the local API answers `423 Locked` and the tray hides the
+//! habits; the webview's lock screen is only the face of it. Repeated wrong PINs back off
+//! exponentially. It also locks itself after `settings.lock.autoLockMinutes` without
+//! input, and when the OS session locks or the machine sleeps (`lock_with_system`), going
+//! by what the OS reports (`idle.rs`). Biometric unlock is in `biometric.rs`. Changes are
+//! emitted to the webview as `lock:changed`.
+//!
+//! Whether a PIN is set and the backoff are also kept in the database's meta table. A
+//! keychain that can't be read at startup then still starts the app locked if a PIN was
+//! set (`keychainError` says why it can't be unlocked with it yet), and restarting the app
+//! doesn't reset the wait after wrong PINs.
+//!
+//! The CLI and `--rpc` don't run in the app, so they go by the meta table too: with a PIN
+//! set they want it first (see `unlock_headless`), and wrong ones count towards the same
+//! backoff. Event sockets on the local API close when the app locks (`subscribe`).
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use serde::Serialize;
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Emitter, Manager, Runtime};
+use tokio::sync::watch;
+
+use crate::error::AppError;
+use crate::secrets;
+use crate::settings::{LockSettings, SettingsStore};
+use crate::storage::Storage;
+
+pub const LOCK_CHANGED_EVENT: &str = "lock:changed";
+/// Commands that still run while locked.
+const ALLOWED_WHILE_LOCKED: &[&str] = &[
+    "get_lock_status",
+    "lock_app",
+    "unlock_app",
+    "get_auth_methods",
+    "unlock_app_biometric",
+];
+/// Wrong PINs allowed before each further attempt has to wait.
+const FREE_ATTEMPTS: u32 = 5;
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+/// Meta keys: a PIN is set, wrong PINs in a row, and when (Unix seconds) the next attempt
+/// is accepted.
+const PIN_SET_KEY: &str = "lock.pin_set";
+const FAILURES_KEY: &str = "lock.failures";
+const RETRY_AT_KEY: &str = "lock.retry_at";
+/// How often the auto-lock asks the OS about idle time and the session.
+const AUTO_LOCK_TICK: Duration = Duration::from_secs(5);
+/// A tick arriving this much late means the machine was asleep.
+const SUSPEND_GAP: Duration = Duration::from_secs(30);
+
+/// The PIN for the CLI and `--rpc`, so scripts don't have to answer a prompt.
+pub const PIN_ENV: &str = "HABITFLOW_PIN";
+
+pub struct AppLock {
+    state: Mutex<LockState>,
+    /// Whether it's locked, for whoever has to stop when it locks.
+    changes: watch::Sender<bool>,
+}
+
+impl Default for AppLock {
+    fn default() -> Self {
+        Self {
+            state: Mutex::default(),
+            changes: watch::channel(false).0,
+        }
+    }
+}
+
+#[derive(Default)]
+struct LockState {
+    /// A PIN is in the keyring.
+    configured: bool,
+    locked: bool,
+    failures: u32,
+    retry_at: Option<Instant>,
+    /// Why the keychain couldn't be read, while that keeps the PIN from being checked.
+    keychain_error: Option<String>,
+}
+
+impl AppLock {
+    fn state(&self) -> std::sync::MutexGuard<'_, LockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.state().locked
+    }
+
+    /// Follows whether it's locked.
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.changes.subscribe()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LockStatus {
+    pub configured: bool,
+    pub locked: bool,
+    /// Seconds until another PIN attempt is accepted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after: Option<u64>,
+    /// The keychain couldn't be read, so the app stays locked until it can be.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keychain_error: Option<String>,
+}
+
+pub fn status(lock: &AppLock) -> LockStatus {
+    let state = lock.state();
+    LockStatus {
+        configured: state.configured,
+        locked: state.locked,
+        retry_after: state
+            .retry_at
+            .and_then(|at| at.checked_duration_since(Instant::now()))
+            .map(|d| d.as_secs().max(1)),
+        keychain_error: state.keychain_error.clone(),
+    }
+}
+
+/// The stored hash, if a PIN is set.
+fn stored_hash() -> Result<Option<String>, String> {
+    secrets::get(secrets::LOCK_PIN)
+}
+
+fn validate_pin(pin: &str) -> Result<(), AppError> {
+    if !(4..=12).contains(&pin.len()) || !pin.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(AppError::Invalid("a PIN is 4 to 12 digits".into()));
+    }
+    Ok(())
+}
+
+fn hash_pin(pin: &str) -> Result<String, String> {
+    use rand::RngCore;
+
+    let mut bytes = [0u8; 16];
+    rand::rng().fill_bytes(&mut bytes);
+    let salt =
+        SaltString::encode_b64(&bytes).map_err(|e| format!("failed to hash the PIN: {e}"))?;
+    Argon2::default()
+        .hash_password(pin.as_bytes(), &salt)
+        .map(|h| h.to_string())
+        .map_err(|e| format!("failed to hash the PIN: {e}"))
+}
+
+fn pin_matches(pin: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|parsed| {
+        Argon2::default()
+            .verify_password(pin.as_bytes(), &parsed)
+            .is_ok()
+    })
+}
+
+/// How long to wait before the next attempt after `failures` wrong PINs in a row.
+fn backoff(failures: u32) -> Option<Duration> {
+    let doublings = failures.checked_sub(FREE_ATTEMPTS)?.min(5);
+    Some((BASE_BACKOFF * 2u32.pow(doublings)).min(MAX_BACKOFF))
+}
+
+/// Stores the backoff in the meta table.
+fn save_backoff(app: &AppHandle, state: &LockState) {
+    let wait = state
+        .retry_at
+        .and_then(|at| at.checked_duration_since(Instant::now()));
+    store_backoff(&app.state::<Storage>(), state.failures, wait);
+}
+
+fn store_backoff(storage: &Storage, failures: u32, wait: Option<Duration>) {
+    let retry_at = wait.map(|wait| chrono::Utc::now().timestamp() + wait.as_secs() as i64 + 1);
+    let saved = storage
+        .set_meta(FAILURES_KEY, &failures.to_string())
+        .and_then(|()| match retry_at {
+            Some(at) => storage.set_meta(RETRY_AT_KEY, &at.to_string()),
+            None => storage.delete_meta(RETRY_AT_KEY),
+        });
+    if let Err(e) = saved {
+        log::warn!("lock: failed to save the PIN backoff: {e}");
+    }
+}
+
+/// Records in the meta table whether a PIN is set.
+fn mark_pin_set(storage: &Storage, set: bool) -> rusqlite::Result<()> {
+    if set {
+        storage.set_meta(PIN_SET_KEY, "1")
+    } else {
+        storage.delete_meta(PIN_SET_KEY)
+    }
+}
+
+/// Checks `pin` against the keyring, applying the backoff. Errors on a wrong PIN.
+fn verify(app: &AppHandle, pin: &str) -> Result<(), String> {
+    let lock = app.state::<AppLock>();
+    if let Some(wait) = status(&lock).retry_after {
+        return Err(format!("too many wrong PINs; try again in {wait}s"));
+    }
+    let hash = match stored_hash() {
+        Ok(hash) => {
+            lock.state().keychain_error = None;
+            hash.ok_or("no PIN is set")?
+        }
+        Err(e) => {
+            lock.state().keychain_error = Some(e.clone());
+            return Err(format!("the PIN can't be checked: {e}"));
+        }
+    };
+    let mut state = lock.state();
+    if pin_matches(pin, &hash) {
+        if state.failures > 0 {
+            state.failures = 0;
+            state.retry_at = None;
+            save_backoff(app, &state);
+        }
+        return Ok(());
+    }
+    state.failures += 1;
+    if let Some(wait) = backoff(state.failures) {
+        state.retry_at = Some(Instant::now() + wait);
+    }
+    save_backoff(app, &state);
+    Err("wrong PIN".into())
+}
+
+/// Whether the CLI and `--rpc` need the PIN: one is set, or was and the keyring can't be
+/// read. Goes by the meta table, as `init` does.
+pub(crate) fn pin_required(storage: &Storage) -> Result<bool, AppError> {
+    if storage.get_meta(PIN_SET_KEY)?.is_none() {
+        return Ok(false);
+    }
+    // `init` clears the mark when the keyring turns out to have no PIN.
+    Ok(!matches!(stored_hash(), Ok(None)))
+}
+
+/// The CLI and `--rpc`'s `unlock_app`: checks `pin` with the same backoff as the app,
+/// kept in the meta table since nothing else outlives the process.
+pub(crate) fn unlock_headless(storage: &Storage, pin: &str) -> Result<(), AppError> {
+    let meta = |key| storage.get_meta(key).ok().flatten();
+    let retry_after = meta(RETRY_AT_KEY)
+        .and_then(|v| v.parse::<i64>().ok())
+        .map(|at| at - chrono::Utc::now().timestamp())
+        .filter(|wait| *wait > 0);
+    if let Some(wait) = retry_after {
+        return Err(AppError::Forbidden(format!(
+            "too many wrong PINs; try again in {wait}s"
+        )));
+    }
+    let Some(hash) =
+        stored_hash().map_err(|e| AppError::Failed(format!("the PIN can't be checked: {e}")))?
+    else {
+        return Ok(());
+    };
+    let failures: u32 = meta(FAILURES_KEY).and_then(|v| v.parse().ok()).unwrap_or(0);
+    if pin_matches(pin, &hash) {
+        if failures > 0 {
+            store_backoff(storage, 0, None);
+        }
+        return Ok(());
+    }
+    store_backoff(storage, failures + 1, backoff(failures + 1));
+    Err(AppError::Forbidden("wrong PIN".into()))
+}
+
+/// `--rpc`'s `set_app_pin`. Changing the PIN needs the current one, checked as
+/// [`unlock_headless`] does.
+pub(crate) fn set_pin_headless(
+    storage: &Storage,
+    pin: &str,
+    current_pin: Option<&str>,
+) -> Result<(), AppError> {
+    validate_pin(pin)?;
+    if stored_hash()?.is_some() {
+        unlock_headless(storage, current_pin.unwrap_or_default())?;
+    }
+    secrets::set(secrets::LOCK_PIN, &hash_pin(pin)?)?;
+    mark_pin_set(storage, true)?;
+    Ok(())
+}
+
+/// `--rpc`'s `remove_app_pin`.
+pub(crate) fn remove_pin_headless(storage: &Storage, pin: &str) -> Result<(), AppError> {
+    if stored_hash()?.is_none() {
+        return Err(AppError::Invalid("no PIN is set".into()));
+    }
+    unlock_headless(storage, pin)?;
+    secrets::delete(secrets::LOCK_PIN)?;
+    mark_pin_set(storage, false)?;
+    Ok(())
+}
+
+/// Starts locked if a PIN is set, or if one was and the keyring can't be read.
+pub fn init(app: &AppHandle) {
+    let storage = app.state::<Storage>();
+    let meta = |key| storage.get_meta(key).ok().flatten();
+    let marked = meta(PIN_SET_KEY).is_some();
+    let (configured, keychain_error) = match stored_hash() {
+        Ok(hash) => {
+            if hash.is_some() != marked {
+                if let Err(e) = mark_pin_set(&storage, hash.is_some()) {
+                    log::warn!("lock: failed to record whether a PIN is set: {e}");
+                }
+            }
+            (hash.is_some(), None)
+        }
+        Err(e) if marked => {
+            log::warn!("lock: {e}; a PIN is set, so starting locked");
+            (true, Some(e))
+        }
+        Err(e) => {
+            log::warn!("lock: {e}; no PIN was set, so starting unlocked");
+            (false, None)
+        }
+    };
+    let failures = meta(FAILURES_KEY).and_then(|v| v.parse().ok()).unwrap_or(0);
+    let retry_at = meta(RETRY_AT_KEY)
+        .and_then(|v| v.parse::<i64>().ok())
+        .and_then(|at| u64::try_from(at - chrono::Utc::now().timestamp()).ok())
+        .map(|wait| Instant::now() + Duration::from_secs(wait.min(MAX_BACKOFF.as_secs())));
+    let lock = app.state::<AppLock>();
+    let mut state = lock.state();
+    state.configured = configured;
+    state.locked = configured;
+    state.failures = failures;
+    state.retry_at = retry_at;
+    state.keychain_error = keychain_error;
+    lock.changes.send_replace(configured);
+}
+
+/// Wraps the invoke handler so commands outside `ALLOWED_WHILE_LOCKED` are rejected while
+/// the app is locked.
+pub fn guarded<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let command = invoke.message.command();
+        let locked = invoke
+            .message
+            .webview_ref()
+            .try_state::<AppLock>()
+            .is_some_and(|lock| lock.is_locked());
+        if locked && !ALLOWED_WHILE_LOCKED.contains(&command) {
+            invoke.resolver.reject(AppError::Locked);
+            return true;
+        }
+        handler(invoke)
+    }
+}
+
+fn changed(app: &AppHandle) -> LockStatus {
+    let lock = app.state::<AppLock>();
+    let status = status(&lock);
+    lock.changes.send_replace(status.locked);
+    crate::tray::refresh(app);
+    crate::widgets::refresh(app);
+    let _ = app.emit(LOCK_CHANGED_EVENT, &status);
+    status
+}
+
+/// Unlocks after the user was verified some other way (`biometric.rs`).
+pub(crate) fn unlock_verified(app: &AppHandle) -> LockStatus {
+    app.state::<AppLock>().state().locked = false;
+    changed(app)
+}
+
+#[tauri::command]
+pub fn get_lock_status(app: AppHandle) -> LockStatus {
+    status(&app.state::<AppLock>())
+}
+
+/// Sets or changes the PIN. Changing it needs the current one.
+#[tauri::command]
+pub fn set_app_pin(
+    app: AppHandle,
+    pin: String,
+    current_pin: Option<String>,
+) -> Result<LockStatus, AppError> {
+    validate_pin(&pin)?;
+    let lock = app.state::<AppLock>();
+    if stored_hash()?.is_some() {
+        verify(&app, current_pin.as_deref().unwrap_or_default())?;
+    }
+    secrets::set(secrets::LOCK_PIN, &hash_pin(&pin)?)?;
+    mark_pin_set(&app.state::<Storage>(), true)?;
+    lock.state().configured = true;
+    Ok(changed(&app))
+}
+
+/// Removes the PIN, which turns the lock off.
+#[tauri::command]
+pub fn remove_app_pin(app: AppHandle, pin: String) -> Result<LockStatus, AppError> {
+    verify(&app, &pin)?;
+    secrets::delete(secrets::LOCK_PIN)?;
+    mark_pin_set(&app.state::<Storage>(), false)?;
+    let lock = app.state::<AppLock>();
+    {
+        let mut state = lock.state();
+        state.configured = false;
+        state.locked = false;
+    }
+    Ok(changed(&app))
+}
+
+/// Locks if a PIN is set and the app isn't locked already. Returns whether it locked.
+fn lock(app: &AppHandle) -> bool {
+    {
+        let lock = app.state::<AppLock>();
+        let mut state = lock.state();
+        if !state.configured || state.locked {
+            return false;
+        }
+        state.locked = true;
+    }
+    crate::write_queue::flush_logged(app);
+    changed(app);
+    true
+}
+
+/// Why the app should lock by itself now, if it should. Asks the OS, not the webview.
+fn auto_lock_reason(settings: &LockSettings, suspended: bool) -> Option<&'static str> {
+    if settings.lock_with_system {
+        if suspended {
+            return Some("the system resumed from sleep");
+        }
+        if crate::idle::session_locked() == Some(true) {
+            return Some("the session was locked");
+        }
+    }
+    let limit = Duration::from_secs(u64::from(settings.auto_lock_minutes) * 60);
+    if settings.auto_lock_minutes > 0 && crate::idle::idle_time().is_some_and(|idle| idle >= limit)
+    {
+        return Some("of inactivity");
+    }
+    None
+}
+
+/// Watches for inactivity, the session locking and the machine suspending while a PIN is
+/// set. Suspends show up as the wall clock jumping ahead between two ticks, since the
+/// loop doesn't run while the machine sleeps.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut ticks = tokio::time::interval(AUTO_LOCK_TICK);
+        let mut last = chrono::Utc::now();
+        loop {
+            ticks.tick().await;
+            let now = chrono::Utc::now();
+            let elapsed = (now - last).to_std().unwrap_or_default();
+            last = now;
+            let armed = {
+                let lock = app.state::<AppLock>();
+                let state = lock.state();
+                state.configured && !state.locked
+            };
+            if !armed {
+                continue;
+            }
+            let settings = app.state::<SettingsStore>().get().lock;
+            let suspended = elapsed > AUTO_LOCK_TICK + SUSPEND_GAP;
+            let reason = tauri::async_runtime::spawn_blocking(move || {
+                auto_lock_reason(&settings, suspended)
+            })
+            .await
+            .ok()
+            .flatten();
+            if let Some(reason) = reason {
+                if lock(&app) {
+                    log::info!("lock: locked because {reason}");
+                }
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn lock_app(app: AppHandle) -> Result<LockStatus, AppError> {
+    if !app.state::<AppLock>().state().configured {
+        return Err(AppError::Invalid("set a PIN first".into()));
+    }
+    lock(&app);
+    Ok(status(&app.state::<AppLock>()))
+}
+
+#[tauri::command]
+pub fn unlock_app(app: AppHandle, pin: String) -> Result<LockStatus, AppError> {
+    verify(&app, &pin)?;
+    app.state::<AppLock>().state().locked = false;
+    Ok(changed(&app))
+}
@@ -0,0 +1,270 @@
+//! Logging, in every build. Each line goes through `redact` first.
+//!
+//! - Log files are JSON lines (`{ ts, level, target, message }`) in `logs/` in the app data
+//!   dir. A file is rotated at [`MAX_FILE_SIZE`], the [`KEEP_FILES`] newest are kept, and
+//!   files older than [`MAX_AGE_DAYS`] are removed on launch and by maintenance
+//!   (`maintenance.rs`).
+//! - Debug builds also log to the terminal, as plain text.
+//! - The level is `settings.logging.level`, changed at runtime with `set_log_level`. Chatty
+//!   dependencies stay at `info` whatever it is.
+//! - `export_logs` zips the log files up for a bug report.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_log::{RotationStrategy, Target, TargetKind};
+
+use crate::error::AppError;
+use crate::redact::redact;
+use crate::settings::{LogLevel, SettingsStore};
+
+pub const LOG_DIR: &str = "logs";
+const LOG_FILE: &str = "habitflow";
+pub const MAX_FILE_SIZE: u128 = 2 * 1024 * 1024;
+pub const KEEP_FILES: usize = 10;
+pub const MAX_AGE_DAYS: u64 = 14;
+/// Dependencies that would drown the app's own lines at `debug`.
+const QUIET: &[&str] = &[
+    "hyper",
+    "hyper_util",
+    "reqwest",
+    "rustls",
+    "rumqttc",
+    "tao",
+    "wry",
+];
+
+fn filter(level: LogLevel) -> log::LevelFilter {
+    match level {
+        LogLevel::Error => log::LevelFilter::Error,
+        LogLevel::Warn => log::LevelFilter::Warn,
+        LogLevel::Info => log::LevelFilter::Info,
+        LogLevel::Debug => log::LevelFilter::Debug,
+        LogLevel::Trace => log::LevelFilter::Trace,
+    }
+}
+
+pub(crate) fn log_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    Ok(app
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::Failed(e.to_string()))?
+        .join(LOG_DIR))
+}
+
+/// Log files, newest first.
+pub(crate) fn log_files(dir: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let mut files: Vec<_> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|e| e == "log"))
+        .filter_map(|p| {
+            let modified = std::fs::metadata(&p).and_then(|m| m.modified()).ok()?;
+            Some((p, modified))
+        })
+        .collect();
+    files.sort_by_key(|f| std::cmp::Reverse(f.1));
+    files
+}
+
+/// Removes log files older than [`MAX_AGE_DAYS`]. Returns how many went and their size.
+pub(crate) fn prune(dir: &Path) -> (usize, u64) {
+    let mut removed = (0, 0);
+    let Some(cutoff) =
+        SystemTime::now().checked_sub(Duration::from_secs(MAX_AGE_DAYS * 24 * 60 * 60))
+    else {
+        return removed;
+    };
+    for (path, modified) in log_files(dir) {
+        if modified >= cutoff {
+            continue;
+        }
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if std::fs::remove_file(path).is_ok() {
+            removed.0 += 1;
+            removed.1 += size;
+        }
+    }
+    removed
+}
+
+pub fn install(app: &AppHandle) -> Result<(), AppError> {
+    let dir = log_dir(app)?;
+    prune(&dir);
+
+    let file = Target::new(TargetKind::Folder {
+        path: dir,
+        file_name: Some(LOG_FILE.into()),
+    })
+    .format(|out, message, record| {
+        let line = serde_json::json!({
+            "ts": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": redact(&message.to_string()),
+        });
+        out.finish(format_args!("{line}"))
+    });
+    let mut targets = vec![file];
+    if cfg!(debug_assertions) {
+        targets.push(
+            Target::new(TargetKind::Stdout).format(|out, message, record| {
+                out.finish(format_args!(
+                    "{}[{}][{}] {}",
+                    chrono::Utc::now().format("[%Y-%m-%d][%H:%M:%S]"),
+                    record.target(),
+                    record.level(),
+                    redact(&message.to_string())
+                ))
+            }),
+        );
+    }
+
+    // Everything passes the plugin, and `log::set_max_level` does the filtering, so the
+    // level can change without building a new logger.
+    let mut builder = tauri_plugin_log::Builder::default()
+        .clear_targets()
+        .targets(targets)
+        .level(log::LevelFilter::Trace)
+        .max_file_size(MAX_FILE_SIZE)
+        .rotation_strategy(RotationStrategy::KeepSome(KEEP_FILES));
+    for module in QUIET {
+        builder = builder.level_for(*module, log::LevelFilter::Info);
+    }
+    app.plugin(builder.build())
+        .map_err(|e| AppError::Failed(e.to_string()))?;
+    log::set_max_level(filter(app.state::<SettingsStore>().get().logging.level));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn set_log_level(settings: State<'_, SettingsStore>, level: LogLevel) -> Result<(), AppError> {
+    save_level(&settings, level)
+}
+
+/// Saves the log level and logs at it from now on.
+pub fn save_level(settings: &SettingsStore, level: LogLevel) -> Result<(), AppError> {
+    settings.update(|s| s.logging.level = level)?;
+    log::set_max_level(filter(level));
+    log::info!("logging: level is now {level:?}");
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogExport {
+    pub path: String,
+    /// Log files in the archive.
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// MS-DOS time and date, as zip headers want them.
+fn dos_time(time: SystemTime) -> (u16, u16) {
+    use chrono::{Datelike, Timelike};
+    let t = chrono::DateTime::<chrono::Utc>::from(time);
+    let year = t.year().clamp(1980, 2107) as u16;
+    let time = (t.hour() as u16) << 11 | (t.minute() as u16) << 5 | (t.second() as u16 / 2);
+    let date = (year - 1980) << 9 | (t.month() as u16) << 5 | t.day() as u16;
+    (time, date)
+}
+
+/// Writes a deflated zip of `entries` (name, contents, modified).
+pub(crate) fn write_zip(
+    out: &mut impl Write,
+    entries: &[(String, Vec<u8>, SystemTime)],
+) -> std::io::Result<()> {
+    let mut central = Vec::new();
+    let mut offset: u32 = 0;
+    for (name, data, modified) in entries {
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        let crc = crc32fast::hash(data);
+        let (time, date) = dos_time(*modified);
+
+        // version needed, flags (UTF-8 names), deflate, time, date, crc, sizes, name length
+        let mut common = Vec::new();
+        common.extend_from_slice(&20u16.to_le_bytes());
+        common.extend_from_slice(&0x0800u16.to_le_bytes());
+        common.extend_from_slice(&8u16.to_le_bytes());
+        common.extend_from_slice(&time.to_le_bytes());
+        common.extend_from_slice(&date.to_le_bytes());
+        common.extend_from_slice(&crc.to_le_bytes());
+        common.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        common.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        common.extend_from_slice(&0u16.to_le_bytes());
+
+        out.write_all(&0x04034b50u32.to_le_bytes())?;
+        out.write_all(&common)?;
+        out.write_all(name.as_bytes())?;
+        out.write_all(&compressed)?;
+
+        central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&common);
+        // comment length, disk, internal and external attributes
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+
+        offset += (30 + name.len() + compressed.len()) as u32;
+    }
+    out.write_all(&central)?;
+    out.write_all(&0x06054b50u32.to_le_bytes())?;
+    out.write_all(&[0; 4])?;
+    out.write_all(&(entries.len() as u16).to_le_bytes())?;
+    out.write_all(&(entries.len() as u16).to_le_bytes())?;
+    out.write_all(&(central.len() as u32).to_le_bytes())?;
+    out.write_all(&offset.to_le_bytes())?;
+    out.write_all(&0u16.to_le_bytes())
+}
+
+/// Zips the log files, with `about.json` (versions and platform) to say where they came
+/// from.
+pub fn export(app: &AppHandle, path: &Path) -> Result<LogExport, AppError> {
+    log::logger().flush();
+    let mut entries = Vec::new();
+    for (file, modified) in log_files(&log_dir(app)?) {
+        let name = file
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        entries.push((name, std::fs::read(&file)?, modified));
+    }
+    let files = entries.len();
+    let about = serde_json::json!({
+        "appVersion": app.package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "exportedAt": chrono::Utc::now().to_rfc3339(),
+    });
+    entries.push((
+        "about.json".into(),
+        serde_json::to_vec_pretty(&about).map_err(|e| AppError::Failed(e.to_string()))?,
+        SystemTime::now(),
+    ));
+
+    let mut zip = Vec::new();
+    write_zip(&mut zip, &entries)?;
+    std::fs::write(path, &zip)?;
+    Ok(LogExport {
+        path: path.display().to_string(),
+        files,
+        bytes: zip.len() as u64,
+    })
+}
+
+/// Writes the zip to `path`, which has to come from `pick_save_path`.
+#[tauri::command]
+pub fn export_logs(app: AppHandle, path: String) -> Result<LogExport, AppError> {
+    let path = crate::files::allowed(&app, &path)?;
+    export(&app, &path)
+}
@@ -2,5 +2,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // Headless CLI subcommands run and exit before any window is created.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = app_lib::cli::run(&args) {
+        std::process::exit(code);
+    }
+
     app_lib::run();
 }
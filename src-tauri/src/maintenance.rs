@@ -0,0 +1,295 @@
+//! Keeping the data dir tidy: every `settings.maintenance.intervalDays` (7 by default),
+//! once the computer has gone `idleMinutes` without input, the scheduler runs maintenance
+//! in the background. `run_maintenance_now` runs it straight away. A run:
+//!
+//! - removes the photos of habits that no longer exist, and attachment and cover files
+//!   nothing refers to;
+//! - removes log files past their age (`logging.rs`);
+//! - rebuilds the database without its free pages (`VACUUM`), refreshes the query
+//!   planner's statistics (`ANALYZE`, `PRAGMA optimize`) and folds the write-ahead log
+//!   back in.
+//!
+//! Each step goes on when an earlier one fails; what went wrong is in the report. HabitFlow
+//! deletes habits for good rather than keeping a trash, so what a deleted habit leaves
+//! behind is all there is to clean up. Where idle time can't be told (some Linux desktops,
+//! mobile), runs start as soon as they're due.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::AppError;
+use crate::settings::SettingsStore;
+use crate::storage::Storage;
+
+/// Emitted with the [`MaintenanceReport`] after every run.
+pub const MAINTENANCE_DONE_EVENT: &str = "maintenance:done";
+
+const LAST_REPORT_KEY: &str = "maintenance.last_report";
+/// Files younger than this are left alone even if nothing refers to them, since they may be
+/// an attachment or cover that is still being added.
+const FILE_GRACE: Duration = Duration::from_secs(60 * 60);
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MaintenanceReport {
+    /// RFC 3339.
+    pub started_at: String,
+    pub duration_ms: u64,
+    /// Started with `run_maintenance_now` rather than by the scheduler.
+    pub manual: bool,
+    /// The database file's size before and after, in bytes.
+    pub database_before: u64,
+    pub database_after: u64,
+    /// Photos of deleted habits.
+    pub attachments_removed: usize,
+    /// Attachment and cover files nothing referred to.
+    pub files_removed: usize,
+    pub logs_removed: usize,
+    /// Everything freed on disk, in bytes.
+    pub reclaimed_bytes: u64,
+    /// Steps that failed; the others still ran.
+    pub errors: Vec<String>,
+}
+
+/// The last run's report, if there has been one.
+pub fn last_report(storage: &Storage) -> Option<MaintenanceReport> {
+    match storage.get_meta(LAST_REPORT_KEY) {
+        Ok(value) => value.and_then(|v| serde_json::from_str(&v).ok()),
+        Err(e) => {
+            log::warn!("maintenance: failed to read the last report: {e}");
+            None
+        }
+    }
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Whether `path` is old enough to have been left behind rather than still being written.
+fn settled(path: &Path) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age >= FILE_GRACE)
+}
+
+/// Removes `path`, counting it in `report`.
+fn remove(path: &Path, report: &mut MaintenanceReport) {
+    let size = file_size(path);
+    match std::fs::remove_file(path) {
+        Ok(()) => {
+            report.files_removed += 1;
+            report.reclaimed_bytes += size;
+        }
+        Err(e) => log::warn!("maintenance: can't remove {}: {e}", path.display()),
+    }
+}
+
+/// Whether the backend has any habits. Without them everything would look orphaned, as
+/// it does before the webview's first snapshot, so nothing is cleaned up.
+fn has_habits(storage: &Storage) -> Result<bool, String> {
+    Ok(!storage.list_habits().map_err(|e| e.to_string())?.is_empty())
+}
+
+fn clean_attachments(
+    storage: &Storage,
+    data_dir: &Path,
+    report: &mut MaintenanceReport,
+) -> Result<(), String> {
+    if !has_habits(storage)? {
+        return Ok(());
+    }
+    let dir = data_dir.join(crate::attachments::ATTACHMENTS_DIR);
+    for attachment in storage.orphaned_attachments().map_err(|e| e.to_string())? {
+        storage
+            .delete_attachment(&attachment.id)
+            .map_err(|e| e.to_string())?;
+        report.attachments_removed += 1;
+        let path = dir.join(&attachment.file);
+        report.reclaimed_bytes += file_size(&path);
+        let _ = std::fs::remove_file(path);
+    }
+
+    // Files are `<habit id>/<name>`, as in each attachment's `file`.
+    let known = storage.attachment_files().map_err(|e| e.to_string())?;
+    for habit_dir in std::fs::read_dir(&dir).into_iter().flatten().flatten() {
+        let habit_dir = habit_dir.path();
+        if !habit_dir.is_dir() {
+            continue;
+        }
+        for file in std::fs::read_dir(&habit_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+        {
+            let path = file.path();
+            let name = format!(
+                "{}/{}",
+                habit_dir.file_name().unwrap_or_default().to_string_lossy(),
+                file.file_name().to_string_lossy()
+            );
+            if path.is_file() && !known.contains(&name) && settled(&path) {
+                remove(&path, report);
+            }
+        }
+        // Only goes if it's empty now.
+        let _ = std::fs::remove_dir(&habit_dir);
+    }
+    Ok(())
+}
+
+fn clean_covers(
+    storage: &Storage,
+    data_dir: &Path,
+    report: &mut MaintenanceReport,
+) -> Result<(), String> {
+    let habits = storage.list_habits().map_err(|e| e.to_string())?;
+    if habits.is_empty() {
+        return Ok(());
+    }
+    let covers: Vec<String> = habits.into_iter().filter_map(|h| h.cover).collect();
+    let dir = data_dir.join(crate::appearance::COVERS_DIR);
+    for file in std::fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = file.path();
+        let name = file.file_name().to_string_lossy().into_owned();
+        if path.is_file() && !covers.contains(&name) && settled(&path) {
+            remove(&path, report);
+        }
+    }
+    Ok(())
+}
+
+fn step(report: &mut MaintenanceReport, name: &str, result: Result<(), String>) {
+    if let Err(e) = result {
+        log::warn!("maintenance: {name} failed: {e}");
+        report.errors.push(format!("{name}: {e}"));
+    }
+}
+
+/// Runs every step and stores the report. Blocks for as long as it takes.
+pub fn run(app: &AppHandle, manual: bool) -> Result<MaintenanceReport, AppError> {
+    let data_dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| AppError::Failed(e.to_string()))?;
+    let report = run_in(&app.state::<Storage>(), &data_dir, manual)?;
+    if report.attachments_removed > 0 {
+        app.state::<crate::events::EventBus>().data_changed();
+    }
+    let _ = app.emit(MAINTENANCE_DONE_EVENT, &report);
+    Ok(report)
+}
+
+/// [`run`] on `data_dir`, without telling a window about it.
+pub fn run_in(
+    storage: &Storage,
+    data_dir: &Path,
+    manual: bool,
+) -> Result<MaintenanceReport, AppError> {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return Err(AppError::Invalid("maintenance is already running".into()));
+    }
+    let started = Instant::now();
+    let mut report = MaintenanceReport {
+        started_at: crate::clock::now().to_rfc3339(),
+        manual,
+        ..MaintenanceReport::default()
+    };
+
+    let result = clean_attachments(storage, data_dir, &mut report);
+    step(&mut report, "attachments", result);
+    let result = clean_covers(storage, data_dir, &mut report);
+    step(&mut report, "covers", result);
+    let (count, bytes) = crate::logging::prune(&data_dir.join(crate::logging::LOG_DIR));
+    report.logs_removed = count;
+    report.reclaimed_bytes += bytes;
+
+    report.database_before = storage.database_size().unwrap_or(0);
+    let result = storage.vacuum().map_err(|e| e.to_string());
+    step(&mut report, "database", result);
+    report.database_after = storage.database_size().unwrap_or(report.database_before);
+    report.reclaimed_bytes += report.database_before.saturating_sub(report.database_after);
+    report.duration_ms = started.elapsed().as_millis() as u64;
+    RUNNING.store(false, Ordering::SeqCst);
+
+    log::info!(
+        "maintenance: reclaimed {} bytes in {} ms ({} attachments, {} files, {} logs)",
+        report.reclaimed_bytes,
+        report.duration_ms,
+        report.attachments_removed,
+        report.files_removed,
+        report.logs_removed
+    );
+    match serde_json::to_string(&report) {
+        Ok(json) => {
+            if let Err(e) = storage.set_meta(LAST_REPORT_KEY, &json) {
+                log::warn!("maintenance: failed to store the report: {e}");
+            }
+        }
+        Err(e) => log::warn!("maintenance: failed to store the report: {e}"),
+    }
+    Ok(report)
+}
+
+/// Whether the computer has gone `minutes` without input, or its session is locked. True
+/// where it can't be told.
+fn idle(minutes: u32) -> bool {
+    if crate::idle::session_locked() == Some(true) {
+        return true;
+    }
+    match crate::idle::idle_time() {
+        Some(idle) => idle >= Duration::from_secs(u64::from(minutes) * 60),
+        None => true,
+    }
+}
+
+fn due(storage: &Storage, interval_days: u32) -> bool {
+    let Some(last) = last_report(storage) else {
+        return true;
+    };
+    let Ok(last) = chrono::DateTime::parse_from_rfc3339(&last.started_at) else {
+        return true;
+    };
+    crate::clock::now().signed_duration_since(last)
+        >= chrono::Duration::days(i64::from(interval_days.max(1)))
+}
+
+/// Starts a run in the background if one is due and the computer is idle; the scheduler
+/// calls this once a minute.
+pub fn run_due(app: &AppHandle) {
+    let settings = app.state::<SettingsStore>().get().maintenance;
+    if !settings.enabled
+        || RUNNING.load(Ordering::SeqCst)
+        || !due(&app.state::<Storage>(), settings.interval_days)
+        || !idle(settings.idle_minutes)
+    {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Err(e) = run(&app, false) {
+            log::warn!("maintenance: {e}");
+        }
+    });
+}
+
+/// Runs maintenance now, in the background, and reports what it freed.
+#[tauri::command]
+pub async fn run_maintenance_now(app: AppHandle) -> Result<MaintenanceReport, AppError> {
+    tauri::async_runtime::spawn_blocking(move || run(&app, true))
+        .await
+        .map_err(|e| AppError::Failed(e.to_string()))?
+}
+
+#[tauri::command]
+pub fn get_maintenance_report(app: AppHandle) -> Option<MaintenanceReport> {
+    last_report(&app.state::<Storage>())
+}
@@ -0,0 +1,200 @@
+//! Milestones: goals a habit works toward ("50 completions", "a 100-day streak", "10,000
+//! minutes in total").
+//!
+//! The backend checks them whenever the data changes, so a milestone is caught no matter
+//! which path logged the check-in. The first time one is reached it is recorded, a
+//! `milestoneReached` event is published, a celebration notification goes out and the
+//! webview gets `milestone:reached`. A milestone that's already met when it's added is
+//! recorded as reached without the celebration.
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::events::{EventBus, HabitEvent};
+use crate::model::{Habit, Milestone, MilestoneKind};
+use crate::settings::generate_token;
+use crate::stats::today;
+use crate::storage::Storage;
+
+/// Emitted with a [`MilestoneProgress`] when a milestone is first reached.
+pub const MILESTONE_REACHED_EVENT: &str = "milestone:reached";
+
+/// Where a habit stands on `kind`.
+pub fn current(storage: &Storage, habit: &Habit, kind: MilestoneKind, today: NaiveDate) -> f64 {
+    match kind {
+        MilestoneKind::Completions => habit.done_dates().len() as f64,
+        MilestoneKind::Streak => f64::from(crate::streaks::current(storage, habit, today)),
+        // Plain habits have no amounts, so each completion counts as one.
+        MilestoneKind::Total if habit.target.is_none() && !habit.is_repeated() => {
+            habit.done_dates().len() as f64
+        }
+        MilestoneKind::Total => habit.values.values().sum(),
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MilestoneProgress {
+    #[serde(flatten)]
+    pub milestone: Milestone,
+    pub name: String,
+    pub current: f64,
+}
+
+fn progress(
+    storage: &Storage,
+    milestone: Milestone,
+    habit: &Habit,
+    today: NaiveDate,
+) -> MilestoneProgress {
+    MilestoneProgress {
+        current: current(storage, habit, milestone.kind, today),
+        name: habit.name.clone(),
+        milestone,
+    }
+}
+
+pub fn add(
+    storage: &Storage,
+    habit_id: &str,
+    kind: MilestoneKind,
+    threshold: f64,
+) -> Result<MilestoneProgress, AppError> {
+    if !threshold.is_finite() || threshold <= 0.0 {
+        return Err(AppError::Invalid(
+            "the threshold must be a positive number".into(),
+        ));
+    }
+    let habit = storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+    let today = today();
+    let now = chrono::Utc::now().to_rfc3339();
+    let milestone = Milestone {
+        id: generate_token()[..16].to_string(),
+        habit_id: habit_id.to_string(),
+        kind,
+        threshold,
+        reached_at: (current(storage, &habit, kind, today) >= threshold).then(|| now.clone()),
+        created_at: now,
+    };
+    storage.insert_milestone(&milestone)?;
+    Ok(progress(storage, milestone, &habit, today))
+}
+
+/// Milestones with each habit's current standing. Ones whose habit is gone are left out.
+pub fn list(storage: &Storage, habit_id: Option<&str>) -> Result<Vec<MilestoneProgress>, AppError> {
+    let habits = storage.list_habits()?;
+    let today = today();
+    Ok(storage
+        .list_milestones(habit_id)?
+        .into_iter()
+        .filter_map(|m| {
+            let habit = habits.iter().find(|h| h.id == m.habit_id)?;
+            Some(progress(storage, m, habit, today))
+        })
+        .collect())
+}
+
+/// Records the milestones reached since the last check and returns them.
+pub fn check(storage: &Storage) -> Result<Vec<MilestoneProgress>, AppError> {
+    let now = chrono::Utc::now().to_rfc3339();
+    let mut reached = Vec::new();
+    for item in list(storage, None)? {
+        if item.milestone.reached_at.is_some() || item.current < item.milestone.threshold {
+            continue;
+        }
+        if storage.mark_milestone_reached(&item.milestone.id, &now)? {
+            let mut item = item;
+            item.milestone.reached_at = Some(now.clone());
+            reached.push(item);
+        }
+    }
+    Ok(reached)
+}
+
+fn describe(item: &MilestoneProgress) -> String {
+    let id = match item.milestone.kind {
+        MilestoneKind::Completions => "milestone-completions",
+        MilestoneKind::Streak => "milestone-streak",
+        MilestoneKind::Total => "milestone-total",
+    };
+    crate::i18n::t_args(
+        id,
+        &[
+            ("name", item.name.clone().into()),
+            ("threshold", item.milestone.threshold.into()),
+        ],
+    )
+}
+
+fn celebrate(app: &AppHandle) {
+    let reached = match check(&app.state::<Storage>()) {
+        Ok(r) => r,
+        Err(e) => {
+            log::warn!("milestones: check failed: {e}");
+            return;
+        }
+    };
+    let bus = app.state::<EventBus>();
+    for item in reached {
+        bus.publish(HabitEvent::MilestoneReached {
+            habit_id: item.milestone.habit_id.clone(),
+            name: item.name.clone(),
+            milestone_id: item.milestone.id.clone(),
+            kind: item.milestone.kind,
+            threshold: item.milestone.threshold,
+        });
+        let body = if crate::privacy::hide_names(app) {
+            crate::i18n::t("milestone-hidden")
+        } else {
+            describe(&item)
+        };
+        crate::reminders::notify(app, &crate::i18n::t("milestone-title"), &body);
+        let _ = app.emit(MILESTONE_REACHED_EVENT, &item);
+    }
+}
+
+/// Checks milestones on startup and after every data change.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    let mut data = app.state::<EventBus>().watch_data();
+    tauri::async_runtime::spawn(async move {
+        celebrate(&app);
+        while data.changed().await.is_ok() {
+            celebrate(&app);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn add_milestone(
+    storage: State<'_, Storage>,
+    habit_id: String,
+    kind: MilestoneKind,
+    threshold: f64,
+) -> Result<MilestoneProgress, AppError> {
+    add(&storage, &habit_id, kind, threshold)
+}
+
+#[tauri::command]
+pub fn list_milestones(
+    storage: State<'_, Storage>,
+    habit_id: Option<String>,
+) -> Result<Vec<MilestoneProgress>, AppError> {
+    list(&storage, habit_id.as_deref())
+}
+
+pub fn remove(storage: &Storage, id: &str) -> Result<(), AppError> {
+    if !storage.delete_milestone(id)? {
+        return Err(AppError::not_found("milestone", id));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_milestone(storage: State<'_, Storage>, id: String) -> Result<(), AppError> {
+    remove(&storage, &id)
+}
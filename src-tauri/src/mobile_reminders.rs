@@ -0,0 +1,320 @@
+//! Reminders on Android and iOS, where the app isn't kept running for the scheduler's
+//! per-minute tick (`reminders.rs`). Instead the next two weeks of reminders are registered
+//! as scheduled local notifications, which the OS delivers on its own, and re-registered
+//! whenever they could have changed:
+//!
+//! - on start and on resume, and after every data change: a check-in drops the rest of the
+//!   day's reminders for that habit, and edited times or schedules move them
+//! - after the settings change, since privacy mode changes their text
+//! - from the OS's background refresh: WorkManager on Android, `BGTaskScheduler` on iOS
+//!
+//! The evening summary is only registered for today, since who's at risk tomorrow isn't
+//! known yet. Registered notifications are kept under [`REGISTERED_KEY`] with a
+//! fingerprint each, so only the ones that changed are cancelled and scheduled again. The
+//! notification plugin puts Android's back after a reboot; iOS keeps them itself.
+
+use std::collections::BTreeMap;
+
+use chrono::{Local, NaiveDateTime, NaiveTime, TimeZone};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::i18n::{t, t_args};
+use crate::model::Habit;
+use crate::reminders::{MISSED_ALERT_TIME, MISSED_TITLE, REMINDER_TITLE};
+use crate::stats::{date_key, parse_date};
+use crate::storage::Storage;
+
+/// Notification id => fingerprint, as JSON.
+pub const REGISTERED_KEY: &str = "reminders.registered";
+/// Read by the Android refresh worker: when the registered reminders run out.
+pub const HORIZON_FILE: &str = "reminders/horizon.json";
+/// iOS keeps at most 64 pending notifications per app.
+const MAX_PENDING: usize = if cfg!(target_os = "ios") { 60 } else { 400 };
+
+#[cfg(mobile)]
+mod imp {
+    use tauri::AppHandle;
+    use tauri_plugin_notification::{NotificationExt, Schedule};
+
+    pub fn schedule(
+        app: &AppHandle,
+        id: i32,
+        title: &str,
+        body: &str,
+        at: chrono::DateTime<chrono::Local>,
+    ) -> Result<(), String> {
+        let date =
+            time::OffsetDateTime::from_unix_timestamp(at.timestamp()).map_err(|e| e.to_string())?;
+        app.notification()
+            .builder()
+            .id(id)
+            .title(title)
+            .body(body)
+            .schedule(Schedule::At {
+                date,
+                repeating: false,
+                allow_while_idle: true,
+            })
+            .show()
+            .map_err(|e| e.to_string())
+    }
+
+    pub fn cancel(app: &AppHandle, ids: Vec<i32>) -> Result<(), String> {
+        app.notification().cancel(ids).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(not(mobile))]
+mod imp {
+    use tauri::AppHandle;
+
+    pub fn schedule(
+        _app: &AppHandle,
+        _id: i32,
+        _title: &str,
+        _body: &str,
+        _at: chrono::DateTime<chrono::Local>,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn cancel(_app: &AppHandle, _ids: Vec<i32>) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Planned {
+    at: NaiveDateTime,
+    title: String,
+    body: String,
+}
+
+impl Planned {
+    fn fingerprint(&self) -> String {
+        format!("{}|{}|{}", self.at, self.title, self.body)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReminderSync {
+    pub scheduled: usize,
+    pub cancelled: usize,
+    pub registered: usize,
+    /// The last registered reminder, local `yyyy-MM-ddTHH:MM`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<String>,
+}
+
+/// A stable id for a slot, since ids outlive the process. FNV-1a, kept positive.
+fn notification_id(key: &str) -> i32 {
+    let hash = key.bytes().fold(0x811c_9dc5u32, |h, b| {
+        (h ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    });
+    (hash & 0x7fff_ffff) as i32
+}
+
+/// Everything that should be registered as of `now`, by id.
+fn plan(habits: &[Habit], now: NaiveDateTime, hide_names: bool) -> BTreeMap<i32, Planned> {
+    let today = now.date();
+    // Grouped by time, so privacy mode says "2 habits due" once.
+    let mut slots: BTreeMap<(String, String), Vec<&Habit>> = BTreeMap::new();
+    for occurrence in crate::reminders::upcoming(habits, now, MAX_PENDING) {
+        let Some(habit) = habits.iter().find(|h| h.id == occurrence.habit_id) else {
+            continue;
+        };
+        // Only today's prerequisites are known.
+        if occurrence.date == date_key(today)
+            && crate::dependencies::is_blocked(habit, habits, today)
+        {
+            continue;
+        }
+        slots
+            .entry((occurrence.date, occurrence.time))
+            .or_default()
+            .push(habit);
+    }
+
+    let mut planned = BTreeMap::new();
+    for ((date, time), due) in slots {
+        let Some(at) = parse_date(&date).and_then(|d| {
+            NaiveTime::parse_from_str(&time, "%H:%M")
+                .ok()
+                .map(|t| d.and_time(t))
+        }) else {
+            continue;
+        };
+        if hide_names {
+            let body = t_args("habits-due", &[("count", due.len().into())]);
+            let id = notification_id(&format!("slot|{date}|{time}"));
+            planned.insert(
+                id,
+                Planned {
+                    at,
+                    title: t(REMINDER_TITLE),
+                    body,
+                },
+            );
+        } else {
+            for habit in due {
+                let id = notification_id(&format!("habit|{}|{date}|{time}", habit.id));
+                let body = crate::appearance::label(habit);
+                planned.insert(
+                    id,
+                    Planned {
+                        at,
+                        title: t(REMINDER_TITLE),
+                        body,
+                    },
+                );
+            }
+        }
+    }
+
+    let summary_time = NaiveTime::parse_from_str(MISSED_ALERT_TIME, "%H:%M").ok();
+    if let Some(at) = summary_time
+        .map(|t| today.and_time(t))
+        .filter(|at| *at > now)
+    {
+        if let Some(body) = crate::reminders::missed_summary(habits, at, hide_names) {
+            let id = notification_id(&format!("summary|{}", date_key(today)));
+            planned.insert(
+                id,
+                Planned {
+                    at,
+                    title: t(MISSED_TITLE),
+                    body,
+                },
+            );
+        }
+    }
+    planned
+}
+
+fn registered(storage: &Storage) -> BTreeMap<i32, String> {
+    storage
+        .get_meta(REGISTERED_KEY)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn write_horizon(app: &AppHandle, until: Option<NaiveDateTime>) -> Result<(), AppError> {
+    let path = app.path().app_data_dir()?.join(HORIZON_FILE);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let until = until.and_then(|at| Local.from_local_datetime(&at).earliest());
+    let json = serde_json::json!({ "until": until.map(|at| at.to_rfc3339()) });
+    std::fs::write(path, json.to_string())?;
+    Ok(())
+}
+
+/// Brings the registered notifications in line with the data. Does nothing on desktop.
+pub fn sync(app: &AppHandle) -> Result<ReminderSync, AppError> {
+    if !cfg!(mobile) {
+        return Ok(ReminderSync::default());
+    }
+    let storage = app.state::<Storage>();
+    let habits = storage.list_habits()?;
+    let now = crate::time_windows::now();
+    let planned = plan(&habits, now, crate::privacy::hide_names(app));
+    let before = registered(&storage);
+
+    let stale: Vec<i32> = before
+        .iter()
+        .filter(|(id, print)| planned.get(id).map(Planned::fingerprint).as_ref() != Some(print))
+        .map(|(id, _)| *id)
+        .collect();
+    if !stale.is_empty() {
+        imp::cancel(app, stale.clone())?;
+    }
+    let mut after: BTreeMap<i32, String> = before
+        .into_iter()
+        .filter(|(id, _)| !stale.contains(id))
+        .collect();
+    let mut scheduled = 0;
+    for (id, reminder) in &planned {
+        if after.contains_key(id) {
+            continue;
+        }
+        let Some(at) = Local.from_local_datetime(&reminder.at).earliest() else {
+            continue;
+        };
+        match imp::schedule(app, *id, &reminder.title, &reminder.body, at) {
+            Ok(()) => {
+                after.insert(*id, reminder.fingerprint());
+                scheduled += 1;
+            }
+            Err(e) => log::warn!("reminders: failed to schedule for {}: {e}", reminder.at),
+        }
+    }
+    let json = serde_json::to_string(&after).map_err(|e| AppError::Failed(e.to_string()))?;
+    storage.set_meta(REGISTERED_KEY, &json)?;
+
+    let until = planned.values().map(|p| p.at).max();
+    write_horizon(app, until)?;
+    Ok(ReminderSync {
+        scheduled,
+        cancelled: stale.len(),
+        registered: after.len(),
+        until: until.map(|at| at.format("%Y-%m-%dT%H:%M").to_string()),
+    })
+}
+
+/// [`sync`] where there's no one to report a failure to.
+pub fn sync_logged(app: &AppHandle) {
+    if let Err(e) = sync(app) {
+        log::warn!("reminders: failed to register: {e}");
+    }
+}
+
+/// Registers reminders now and again whenever the data changes; the day rolling over is
+/// one too.
+pub fn start(app: &AppHandle) {
+    if !cfg!(mobile) {
+        return;
+    }
+    sync_logged(app);
+    let app = app.clone();
+    let mut data = app.state::<EventBus>().watch_data();
+    tauri::async_runtime::spawn(async move {
+        while data.changed().await.is_ok() {
+            let a = app.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || sync_logged(&a)).await;
+        }
+    });
+}
+
+#[cfg(target_os = "ios")]
+extern "C" {
+    fn habitflow_register_reminder_refresh();
+}
+
+/// Registers the background refresh task (`ios/App/ReminderRefresh.swift`). iOS wants
+/// this before the app has finished launching, so it's the first thing `run` does.
+#[cfg(target_os = "ios")]
+pub fn register_background_refresh() {
+    unsafe { habitflow_register_reminder_refresh() }
+}
+
+/// Called by iOS's background refresh, which launches the app if needed.
+#[cfg(target_os = "ios")]
+#[no_mangle]
+pub extern "C" fn habitflow_refresh_reminders() -> bool {
+    match crate::shortcuts::app() {
+        Some(app) => sync(app).is_ok(),
+        None => false,
+    }
+}
+
+/// Re-registers the scheduled reminders right away. Desktop has nothing to register.
+#[tauri::command]
+pub fn sync_reminders(app: AppHandle) -> Result<ReminderSync, AppError> {
+    sync(&app)
+}
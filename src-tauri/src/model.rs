@@ -0,0 +1,550 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+/// Mirrors `HabitFrequency` in `types.ts`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HabitFrequency {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub goal: u32,
+}
+
+impl Default for HabitFrequency {
+    fn default() -> Self {
+        Self {
+            kind: "daily".into(),
+            goal: 1,
+        }
+    }
+}
+
+/// `Avoid` habits ("no sugar") succeed every day no lapse is logged.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HabitKind {
+    #[default]
+    Build,
+    Avoid,
+}
+
+impl HabitKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HabitKind::Build => "build",
+            HabitKind::Avoid => "avoid",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        if s == "avoid" {
+            HabitKind::Avoid
+        } else {
+            HabitKind::Build
+        }
+    }
+
+    pub fn is_build(&self) -> bool {
+        *self == HabitKind::Build
+    }
+}
+
+/// How much XP a completion is worth; see `gamification.rs`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "easy" => Difficulty::Easy,
+            "hard" => Difficulty::Hard,
+            _ => Difficulty::Medium,
+        }
+    }
+
+    pub fn is_medium(&self) -> bool {
+        *self == Difficulty::Medium
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lapse {
+    /// `yyyy-MM-dd`
+    pub date: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub logged_at: String,
+}
+
+/// One step of a checklist habit ("Morning routine": make bed, stretch, journal).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistItem {
+    pub id: String,
+    pub name: String,
+    /// `yyyy-MM-dd` days this item was checked off.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub done: BTreeSet<String>,
+}
+
+/// Days off ("vacation mode"), inclusive. `habit_id: None` applies to every habit.
+/// Excused days neither count toward nor break a streak, and nothing is due on them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OffPeriod {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub habit_id: Option<String>,
+    /// `yyyy-MM-dd`
+    pub start: String,
+    /// `yyyy-MM-dd`, inclusive.
+    pub end: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Set by `pause_habit`; such periods belong to one habit and start when it was paused.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub paused: bool,
+}
+
+/// A goal a habit works toward, celebrated once when first reached.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Milestone {
+    pub id: String,
+    pub habit_id: String,
+    pub kind: MilestoneKind,
+    pub threshold: f64,
+    /// RFC 3339, once reached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reached_at: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MilestoneKind {
+    /// Days checked off, all time.
+    Completions,
+    /// Current streak, in days (or periods for weekly/monthly habits).
+    Streak,
+    /// Sum of everything logged, in the target's unit ("10,000 minutes").
+    Total,
+}
+
+impl MilestoneKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MilestoneKind::Completions => "completions",
+            MilestoneKind::Streak => "streak",
+            MilestoneKind::Total => "total",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "completions" => Some(MilestoneKind::Completions),
+            "streak" => Some(MilestoneKind::Streak),
+            "total" => Some(MilestoneKind::Total),
+            _ => None,
+        }
+    }
+}
+
+/// Long-horizon target over one period for a habit ("run 100 km this quarter").
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Goal {
+    pub id: String,
+    pub title: String,
+    pub habit_id: String,
+    pub period: GoalPeriod,
+    /// First day, `YYYY-MM-DD`.
+    pub start: String,
+    /// Deadline, inclusive.
+    pub end: String,
+    /// In the habit's target unit, or completions for habits without one.
+    pub target: f64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum GoalPeriod {
+    Month,
+    Quarter,
+}
+
+impl GoalPeriod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GoalPeriod::Month => "month",
+            GoalPeriod::Quarter => "quarter",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "month" => Some(GoalPeriod::Month),
+            "quarter" => Some(GoalPeriod::Quarter),
+            _ => None,
+        }
+    }
+}
+
+/// The day's optional challenge; see `challenges.rs`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyChallenge {
+    /// `yyyy-MM-dd`, one challenge per day.
+    pub date: String,
+    pub kind: ChallengeKind,
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub habit_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// `HH:MM`, for `BeforeTime`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<String>,
+    pub xp: u32,
+    pub status: ChallengeStatus,
+    /// RFC 3339, once completed, failed or skipped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolved_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChallengeKind {
+    /// Do one habit before `deadline`.
+    BeforeTime,
+    /// Log at least 1.5× a quantified habit's target.
+    Overachieve,
+    /// Finish everything due in one category.
+    AllInCategory,
+    /// Finish everything due today.
+    AllDone,
+}
+
+impl ChallengeKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChallengeKind::BeforeTime => "beforeTime",
+            ChallengeKind::Overachieve => "overachieve",
+            ChallengeKind::AllInCategory => "allInCategory",
+            ChallengeKind::AllDone => "allDone",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "beforeTime" => Some(ChallengeKind::BeforeTime),
+            "overachieve" => Some(ChallengeKind::Overachieve),
+            "allInCategory" => Some(ChallengeKind::AllInCategory),
+            "allDone" => Some(ChallengeKind::AllDone),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChallengeStatus {
+    Open,
+    Completed,
+    Failed,
+    Skipped,
+}
+
+impl ChallengeStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChallengeStatus::Open => "open",
+            ChallengeStatus::Completed => "completed",
+            ChallengeStatus::Failed => "failed",
+            ChallengeStatus::Skipped => "skipped",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "completed" => ChallengeStatus::Completed,
+            "failed" => ChallengeStatus::Failed,
+            "skipped" => ChallengeStatus::Skipped,
+            _ => ChallengeStatus::Open,
+        }
+    }
+}
+
+/// Something the user treats themselves to, bought with points; see `rewards.rs`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reward {
+    pub id: String,
+    pub name: String,
+    pub cost: u32,
+    pub created_at: String,
+}
+
+/// A reward bought; keeps the name and cost it had at the time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Redemption {
+    pub id: String,
+    pub reward_id: String,
+    pub name: String,
+    pub cost: u32,
+    pub redeemed_at: String,
+}
+
+/// Local time of day a habit counts in, `HH:MM` to `HH:MM` (end exclusive), within a day.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: String,
+    pub end: String,
+}
+
+impl TimeWindow {
+    /// `time` is `HH:MM`.
+    pub fn contains(&self, time: &str) -> bool {
+        self.start.as_str() <= time && time < self.end.as_str()
+    }
+}
+
+/// Daily target for quantified habits, e.g. 2000 "ml" or 30 "pages".
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HabitTarget {
+    pub amount: f64,
+    pub unit: String,
+}
+
+/// Mirrors `Habit` in `types.ts` so snapshots can round-trip through the webview unchanged.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Habit {
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub color: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Shown where the app's icons can't be drawn (tray menu, notifications); without one
+    /// the icon's usual emoji is used.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    /// File name of the cover image in the app data's `covers` folder. Set through
+    /// `set_habit_cover`; snapshots from the webview don't change it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
+    #[serde(default = "default_category")]
+    pub category: String,
+    #[serde(default)]
+    pub frequency: HabitFrequency,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reminder_time: Option<String>,
+    /// Weekday (0 = Sunday, like JS `getDay()`) => `HH:MM`, overriding `reminder_time` on
+    /// those days. Set through `set_weekday_reminders`; snapshots from the webview don't
+    /// change them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub weekday_reminders: BTreeMap<u8, String>,
+    /// Check-ins only count inside this window of the day.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<TimeWindow>,
+    pub created_at: String,
+    /// `yyyy-MM-dd` => done. Only `true` entries are meaningful.
+    #[serde(default)]
+    pub logs: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub archived: bool,
+    /// RFC 3339, set while archived. History is kept; archived habits just stop being due.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub archived_at: Option<String>,
+    /// Shown ahead of unpinned habits in every view, whatever its sort. The list itself is
+    /// in manual order (`reorder_habits`).
+    #[serde(default)]
+    pub pinned: bool,
+    /// Set through `set_habit_difficulty`; snapshots from the webview don't change it.
+    #[serde(default, skip_serializing_if = "Difficulty::is_medium")]
+    pub difficulty: Difficulty,
+    /// Lowercase, e.g. life areas like "health" or "career".
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "HabitKind::is_build")]
+    pub kind: HabitKind,
+    /// Slip-ups on an `Avoid` habit, oldest first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lapses: Vec<Lapse>,
+    /// Ids of habits that must be done first each day before this one is due.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Steps of a checklist habit, in order. The habit counts as done on a day once every
+    /// item is.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checklist: Vec<ChecklistItem>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<HabitTarget>,
+    /// Check-ins needed for a day to count ("take medication 3x"); each is a timestamped
+    /// check-in of 1. Ignored for quantified habits, whose target already sets the bar.
+    #[serde(default = "once", skip_serializing_if = "is_once")]
+    pub times_per_day: u32,
+    /// `yyyy-MM-dd` => total logged that day. Only used with a `target`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<String, f64>,
+    /// Days checked off after the fact with `backfill_completion`, which stats can leave
+    /// out. Snapshots from the webview can only drop them, by unchecking the day.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub backfilled: BTreeSet<String>,
+    /// `yyyy-MM-dd` => journal note for that day's check-in. Set through
+    /// `update_checkin_note`; snapshots from the webview don't change them.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub notes: BTreeMap<String, String>,
+    /// Off periods covering this habit (its own and global ones), filled in by storage.
+    /// Managed through their own commands rather than habit snapshots.
+    #[serde(skip)]
+    pub off_periods: Vec<OffPeriod>,
+    /// First day back from the current pause, filled in by storage; read-only for the
+    /// webview.
+    #[serde(default, skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub paused_until: Option<String>,
+    /// Missed days a streak freeze was spent on, filled in by storage.
+    #[serde(skip)]
+    pub frozen: BTreeSet<String>,
+    /// Bumped by storage on every change to the habit or any of its days. A snapshot
+    /// carrying an older one is rejected as a conflict rather than written over the newer
+    /// record; 0 skips the check.
+    #[serde(default)]
+    pub revision: u64,
+}
+
+pub const DEFAULT_CATEGORY: &str = "General";
+
+fn once() -> u32 {
+    1
+}
+
+fn is_once(times: &u32) -> bool {
+    *times <= 1
+}
+
+fn default_category() -> String {
+    DEFAULT_CATEGORY.into()
+}
+
+/// A group of habits with its own color and place in the list. Habits refer to their
+/// category by name (`Habit::category`), which is all the webview knows about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Category {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    pub position: u32,
+}
+
+impl Habit {
+    /// Avoidance habits count every day from creation without a lapse (callers stop at
+    /// today). Quantified habits count a day once its logged total reaches the target, and
+    /// repeated ones once it has `times_per_day` check-ins; days checked off without any
+    /// (or plain habits) go by `logs`.
+    pub fn is_done_on(&self, date: &str) -> bool {
+        if self.kind == HabitKind::Avoid {
+            return date >= self.start_date() && !self.lapsed_on(date);
+        }
+        match (&self.target, self.values.get(date)) {
+            (Some(target), Some(value)) => *value >= target.amount,
+            (None, Some(count)) if self.is_repeated() => *count >= f64::from(self.times_per_day),
+            _ => self.logs.get(date).copied().unwrap_or(false),
+        }
+    }
+
+    /// Done by counting check-ins rather than one check mark.
+    pub fn is_repeated(&self) -> bool {
+        self.target.is_none() && self.times_per_day > 1
+    }
+
+    /// Inside an off period.
+    pub fn is_excused(&self, date: &str) -> bool {
+        self.off_periods
+            .iter()
+            .any(|p| p.start.as_str() <= date && date <= p.end.as_str())
+    }
+
+    /// The reminder time on a weekday (0 = Sunday), before any time window applies.
+    pub fn reminder_time_on(&self, weekday: u8) -> Option<&str> {
+        self.weekday_reminders
+            .get(&weekday)
+            .or(self.reminder_time.as_ref())
+            .map(String::as_str)
+    }
+
+    /// The pause covering `date` or starting later, if any.
+    pub fn pause_from(&self, date: &str) -> Option<&OffPeriod> {
+        self.off_periods
+            .iter()
+            .filter(|p| p.paused && p.end.as_str() >= date)
+            .max_by(|a, b| a.end.cmp(&b.end))
+    }
+
+    /// A streak freeze covered this day.
+    pub fn is_frozen(&self, date: &str) -> bool {
+        self.frozen.contains(date)
+    }
+
+    pub fn lapsed_on(&self, date: &str) -> bool {
+        self.lapses.iter().any(|l| l.date == date)
+    }
+
+    /// `yyyy-MM-dd` part of `created_at`.
+    pub fn start_date(&self) -> &str {
+        self.created_at.get(..10).unwrap_or(&self.created_at)
+    }
+
+    /// Checklist items done on `date`, or `None` for habits without a checklist.
+    pub fn checklist_progress(&self, date: &str) -> Option<(usize, usize)> {
+        if self.checklist.is_empty() {
+            return None;
+        }
+        let done = self
+            .checklist
+            .iter()
+            .filter(|i| i.done.contains(date))
+            .count();
+        Some((done, self.checklist.len()))
+    }
+
+    pub fn value_on(&self, date: &str) -> f64 {
+        self.values.get(date).copied().unwrap_or(0.0)
+    }
+
+    /// Every recorded completion, in date order. Avoidance habits have none; their
+    /// successes are implied by the absence of lapses.
+    pub fn done_dates(&self) -> Vec<&str> {
+        if self.kind == HabitKind::Avoid {
+            return Vec::new();
+        }
+        let dates: BTreeSet<&str> = self
+            .logs
+            .keys()
+            .chain(self.values.keys())
+            .map(String::as_str)
+            .collect();
+        dates.into_iter().filter(|d| self.is_done_on(d)).collect()
+    }
+}
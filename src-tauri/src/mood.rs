@@ -0,0 +1,125 @@
+//! Daily mood: one 1–5 score per day with optional tags ("tired", "social"), kept in the
+//! backend and compared against habit completion in `stats::mood_correlation`.
+
+use chrono::Days;
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::AppError;
+use crate::stats::{self, date_key, day_arg, today, MoodCorrelation};
+use crate::storage::{MoodEntry, Storage};
+
+/// How far back insights look by default.
+pub const DEFAULT_INSIGHT_DAYS: u32 = 90;
+
+/// Records (or replaces) the mood for `date`, default today.
+pub fn log(
+    storage: &Storage,
+    score: u8,
+    date: Option<&str>,
+    tags: Vec<String>,
+) -> Result<MoodEntry, AppError> {
+    if !(1..=5).contains(&score) {
+        return Err(AppError::Invalid("mood must be between 1 and 5".into()));
+    }
+    let date = day_arg(date)?;
+    let entry = MoodEntry {
+        date: date_key(date),
+        score,
+        tags: crate::tags::normalize(tags),
+        logged_at: chrono::Utc::now().to_rfc3339(),
+    };
+    storage.set_mood(&entry)?;
+    Ok(entry)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoodInsights {
+    pub from: String,
+    pub to: String,
+    pub entries: usize,
+    /// `None` without entries.
+    pub average: Option<f64>,
+    pub correlations: Vec<MoodCorrelation>,
+}
+
+/// Mood over the last `days` days (including today) and how it relates to each habit.
+pub fn insights(storage: &Storage, days: Option<u32>) -> Result<MoodInsights, String> {
+    insights_with(storage, days, &mut |_, _| Ok(())).map_err(|e| e.to_string())
+}
+
+/// [`insights`], calling `step(done, total)` after each habit; an error from it stops the
+/// analysis (see `jobs.rs`).
+pub fn insights_with(
+    storage: &Storage,
+    days: Option<u32>,
+    step: &mut dyn FnMut(usize, usize) -> Result<(), AppError>,
+) -> Result<MoodInsights, AppError> {
+    let to = today();
+    let days = days.unwrap_or(DEFAULT_INSIGHT_DAYS).max(1);
+    let from = to
+        .checked_sub_days(Days::new(u64::from(days - 1)))
+        .unwrap_or(to);
+    let (from, to) = (date_key(from), date_key(to));
+    let moods = storage.list_moods(Some(&from), Some(&to))?;
+    let habits = storage.list_habits()?;
+    let average = (!moods.is_empty()).then(|| {
+        let total: f64 = moods.iter().map(|m| f64::from(m.score)).sum();
+        (total / moods.len() as f64 * 100.0).round() / 100.0
+    });
+    let mut correlations = Vec::new();
+    for (i, habit) in habits.iter().enumerate() {
+        correlations.extend(stats::mood_correlation(habit, &moods));
+        step(i + 1, habits.len())?;
+    }
+    stats::sort_correlations(&mut correlations);
+    Ok(MoodInsights {
+        correlations,
+        entries: moods.len(),
+        average,
+        from,
+        to,
+    })
+}
+
+#[tauri::command]
+pub fn log_mood(
+    storage: State<'_, Storage>,
+    score: u8,
+    date: Option<String>,
+    tags: Option<Vec<String>>,
+) -> Result<MoodEntry, AppError> {
+    log(&storage, score, date.as_deref(), tags.unwrap_or_default())
+}
+
+/// Entries from `from` through `to` (both optional, `yyyy-MM-dd`), oldest first.
+#[tauri::command]
+pub fn list_moods(
+    storage: State<'_, Storage>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<MoodEntry>, AppError> {
+    Ok(storage.list_moods(from.as_deref(), to.as_deref())?)
+}
+
+pub fn remove(storage: &Storage, date: &str) -> Result<(), AppError> {
+    if !storage.delete_mood(date)? {
+        return Err(AppError::not_found("mood", date));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_mood(storage: State<'_, Storage>, date: String) -> Result<(), AppError> {
+    remove(&storage, &date)
+}
+
+/// Average mood and per-habit correlations over the last `days` days (default 90).
+#[tauri::command]
+pub fn get_mood_insights(
+    storage: State<'_, Storage>,
+    days: Option<u32>,
+) -> Result<MoodInsights, AppError> {
+    Ok(insights(&storage, days)?)
+}
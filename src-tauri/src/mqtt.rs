@@ -0,0 +1,293 @@
+//! MQTT bridge (e.g. for Home Assistant).
+//!
+//! Topics, relative to `settings.mqtt.topic_prefix`:
+//! - `availability`: `online` / `offline` (retained, offline via last will)
+//! - `state`: `{ date, completed, total, allDone }` JSON (retained)
+//! - `habit/<id>/state`: `ON` / `OFF` for today (retained)
+//! - `habit/<id>/complete`: any payload marks the habit done today
+//! - `command`: a habit id or name marks it done today
+//!
+//! With discovery enabled, Home Assistant gets an "all habits done" binary sensor, a
+//! progress sensor, and a binary sensor + complete button per active habit.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions, Packet, Publish, QoS};
+use serde_json::json;
+use tauri::{AppHandle, Manager};
+use tokio::sync::oneshot;
+
+use crate::events::EventBus;
+use crate::features::{self, Feature};
+use crate::model::Habit;
+use crate::settings::{MqttSettings, SettingsStore};
+use crate::stats::{date_key, today};
+use crate::storage::Storage;
+
+#[derive(Default)]
+pub struct MqttBridge {
+    shutdown: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+/// Starts, stops or restarts the bridge to match the current settings.
+pub fn apply(app: &AppHandle) {
+    let bridge = app.state::<MqttBridge>();
+    let mut shutdown = bridge.shutdown.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(tx) = shutdown.take() {
+        let _ = tx.send(());
+    }
+
+    let settings = app.state::<SettingsStore>().get().mqtt;
+    if !settings.enabled || !features::enabled(Feature::Mqtt) {
+        return;
+    }
+
+    let (tx, rx) = oneshot::channel();
+    *shutdown = Some(tx);
+    tauri::async_runtime::spawn(run(app.clone(), settings, rx));
+}
+
+async fn run(app: AppHandle, cfg: MqttSettings, mut shutdown: oneshot::Receiver<()>) {
+    let prefix = cfg.topic_prefix.trim_end_matches('/').to_string();
+    let availability = format!("{prefix}/availability");
+
+    let mut options = MqttOptions::new(&cfg.client_id, &cfg.host, cfg.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(
+        &availability,
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let Some(username) = &cfg.username {
+        let password = crate::secrets::get(crate::secrets::MQTT_PASSWORD).unwrap_or_else(|e| {
+            log::warn!("mqtt: {e}");
+            None
+        });
+        options.set_credentials(username, password.unwrap_or_default());
+    }
+
+    // State publishes use `try_publish` from this same task, so size the request queue
+    // for a full republish (a few messages per habit) rather than blocking the event loop.
+    let (client, mut eventloop) = AsyncClient::new(options, 1024);
+    let mut data = app.state::<EventBus>().watch_data();
+    // Habit ids we've published discovery configs for, so removed habits can be cleared.
+    // `None` until the first publish on a connection.
+    let mut discovered: Option<HashSet<String>> = None;
+
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => {
+                let _ = client.publish(&availability, QoS::AtLeastOnce, true, "offline").await;
+                let _ = client.disconnect().await;
+                break;
+            }
+            polled = eventloop.poll() => match polled {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    log::info!("mqtt: connected to {}:{}", cfg.host, cfg.port);
+                    let _ = client.subscribe(format!("{prefix}/command"), QoS::AtLeastOnce).await;
+                    let _ = client.subscribe(format!("{prefix}/habit/+/complete"), QoS::AtLeastOnce).await;
+                    let _ = client.publish(&availability, QoS::AtLeastOnce, true, "online").await;
+                    // Broker may have lost retained messages; republish everything.
+                    discovered = None;
+                    publish_state(&app, &client, &cfg, &prefix, &mut discovered);
+                }
+                Ok(Event::Incoming(Packet::Publish(msg))) => handle_command(&app, &prefix, &msg),
+                Ok(_) => {}
+                Err(e) => {
+                    // The event loop reconnects on the next poll.
+                    log::warn!("mqtt: connection error: {e}");
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            },
+            changed = data.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                publish_state(&app, &client, &cfg, &prefix, &mut discovered);
+            }
+        }
+    }
+}
+
+fn handle_command(app: &AppHandle, prefix: &str, msg: &Publish) {
+    let reference = if msg.topic == format!("{prefix}/command") {
+        String::from_utf8_lossy(&msg.payload).trim().to_string()
+    } else if let Some(id) = msg
+        .topic
+        .strip_prefix(&format!("{prefix}/habit/"))
+        .and_then(|rest| rest.strip_suffix("/complete"))
+    {
+        id.to_string()
+    } else {
+        return;
+    };
+
+    let habits = match app.state::<Storage>().list_habits() {
+        Ok(h) => h,
+        Err(e) => {
+            log::error!("mqtt: failed to load habits: {e}");
+            return;
+        }
+    };
+    let Some(habit) = crate::habits::find(&habits, &reference) else {
+        log::warn!("mqtt: no habit matches {reference:?}");
+        return;
+    };
+    if let Err(e) = crate::habits::set_completion(app, &habit.id, today(), true) {
+        log::error!("mqtt: failed to complete {}: {e}", habit.id);
+    }
+}
+
+fn publish_state(
+    app: &AppHandle,
+    client: &AsyncClient,
+    cfg: &MqttSettings,
+    prefix: &str,
+    discovered: &mut Option<HashSet<String>>,
+) {
+    let habits: Vec<Habit> = match app.state::<Storage>().list_habits() {
+        Ok(h) => h.into_iter().filter(|h| !h.archived).collect(),
+        Err(e) => {
+            log::error!("mqtt: failed to load habits: {e}");
+            return;
+        }
+    };
+    let today = date_key(today());
+    let completed = habits.iter().filter(|h| h.is_done_on(&today)).count();
+
+    let state = json!({
+        "date": today,
+        "completed": completed,
+        "total": habits.len(),
+        "allDone": !habits.is_empty() && completed == habits.len(),
+    });
+    let _ = client.try_publish(
+        format!("{prefix}/state"),
+        QoS::AtLeastOnce,
+        true,
+        state.to_string(),
+    );
+    for habit in &habits {
+        let payload = if habit.is_done_on(&today) {
+            "ON"
+        } else {
+            "OFF"
+        };
+        let _ = client.try_publish(
+            format!("{prefix}/habit/{}/state", habit.id),
+            QoS::AtLeastOnce,
+            true,
+            payload,
+        );
+    }
+
+    if cfg.discovery {
+        publish_discovery(client, cfg, prefix, &habits, discovered);
+    }
+}
+
+fn publish_discovery(
+    client: &AsyncClient,
+    cfg: &MqttSettings,
+    prefix: &str,
+    habits: &[Habit],
+    discovered: &mut Option<HashSet<String>>,
+) {
+    let root = cfg.discovery_prefix.trim_end_matches('/');
+    let node = sanitize(&cfg.client_id);
+    let device = json!({
+        "identifiers": [node],
+        "name": "HabitFlow",
+        "manufacturer": "HabitFlow",
+    });
+    let availability = format!("{prefix}/availability");
+
+    let previous = discovered.take();
+    let mut configs = Vec::new();
+    if previous.is_none() {
+        configs.push((
+            format!("{root}/binary_sensor/{node}/all_done/config"),
+            json!({
+                "name": "All habits done",
+                "unique_id": format!("{node}_all_done"),
+                "state_topic": format!("{prefix}/state"),
+                "value_template": "{{ 'ON' if value_json.allDone else 'OFF' }}",
+                "availability_topic": availability,
+                "device": device,
+            }),
+        ));
+        configs.push((
+            format!("{root}/sensor/{node}/completed/config"),
+            json!({
+                "name": "Habits completed today",
+                "unique_id": format!("{node}_completed"),
+                "state_topic": format!("{prefix}/state"),
+                "value_template": "{{ value_json.completed }}",
+                "json_attributes_topic": format!("{prefix}/state"),
+                "availability_topic": availability,
+                "device": device,
+            }),
+        ));
+    }
+
+    let current: HashSet<String> = habits.iter().map(|h| h.id.clone()).collect();
+    let previous = previous.unwrap_or_default();
+    for habit in habits.iter().filter(|h| !previous.contains(&h.id)) {
+        let object = format!("habit_{}", sanitize(&habit.id));
+        configs.push((
+            format!("{root}/binary_sensor/{node}/{object}/config"),
+            json!({
+                "name": habit.name,
+                "unique_id": format!("{node}_{object}"),
+                "state_topic": format!("{prefix}/habit/{}/state", habit.id),
+                "availability_topic": availability,
+                "device": device,
+            }),
+        ));
+        configs.push((
+            format!("{root}/button/{node}/{object}/config"),
+            json!({
+                "name": format!("Complete {}", habit.name),
+                "unique_id": format!("{node}_{object}_complete"),
+                "command_topic": format!("{prefix}/habit/{}/complete", habit.id),
+                "availability_topic": availability,
+                "device": device,
+            }),
+        ));
+    }
+
+    for (topic, payload) in configs {
+        let _ = client.try_publish(topic, QoS::AtLeastOnce, true, payload.to_string());
+    }
+
+    // An empty retained config removes the entity in Home Assistant.
+    for gone in previous.difference(&current) {
+        let object = format!("habit_{}", sanitize(gone));
+        for component in ["binary_sensor", "button"] {
+            let _ = client.try_publish(
+                format!("{root}/{component}/{node}/{object}/config"),
+                QoS::AtLeastOnce,
+                true,
+                "",
+            );
+        }
+    }
+
+    *discovered = Some(current);
+}
+
+/// Discovery object ids may only contain `[a-zA-Z0-9_-]`.
+fn sanitize(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,154 @@
+//! Connectivity monitor, so the parts that talk to servers wait for the network instead of
+//! failing against it over and over.
+//!
+//! Every so often the hosts in `settings.network.probeHosts` are tried in turn, and
+//! reaching any of them counts as online: every 60 seconds while online, every 10 while
+//! offline. Callers that hit a network error say so with [`check_now`], which checks
+//! straight away. Changes are emitted as `network:online` and `network:offline`, and going
+//! back online wakes the sync queue and the libSQL replica.
+//!
+//! Waiting with [`wait_online`] are the sync queue, the replica, webhook deliveries, update
+//! checks, telemetry and leaderboard uploads. MQTT is left alone, since brokers are usually
+//! on the local network.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::net::TcpStream;
+use tokio::sync::{watch, Notify};
+
+use crate::settings::SettingsStore;
+
+pub const ONLINE_EVENT: &str = "network:online";
+pub const OFFLINE_EVENT: &str = "network:offline";
+
+const CHECK_ONLINE: Duration = Duration::from_secs(60);
+const CHECK_OFFLINE: Duration = Duration::from_secs(10);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkStatus {
+    pub online: bool,
+    /// Whether the monitor is on; off, the network counts as always up.
+    pub monitored: bool,
+    /// RFC 3339 of the last check.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checked_at: Option<String>,
+    /// RFC 3339 of the last change between online and offline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<String>,
+}
+
+pub struct Network {
+    /// Assumed up until a check says otherwise, so nothing waits on the first one.
+    online: watch::Sender<bool>,
+    status: Mutex<NetworkStatus>,
+    check: Notify,
+}
+
+impl Default for Network {
+    fn default() -> Self {
+        Self {
+            online: watch::Sender::new(true),
+            status: Mutex::new(NetworkStatus {
+                online: true,
+                ..NetworkStatus::default()
+            }),
+            check: Notify::new(),
+        }
+    }
+}
+
+impl Network {
+    fn status(&self) -> std::sync::MutexGuard<'_, NetworkStatus> {
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+pub fn is_online(app: &AppHandle) -> bool {
+    *app.state::<Network>().online.borrow()
+}
+
+/// Returns once the network is up, straight away if it already is.
+pub async fn wait_online(app: &AppHandle) {
+    let mut online = app.state::<Network>().online.subscribe();
+    let _ = online.wait_for(|up| *up).await;
+}
+
+/// Asks for a check now, after a request failed in a way that may be the network or the
+/// settings changed.
+pub fn check_now(app: &AppHandle) {
+    app.state::<Network>().check.notify_one();
+}
+
+/// Whether any of `hosts` takes a connection.
+async fn probe(hosts: &[String]) -> bool {
+    for host in hosts {
+        match tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(host.as_str())).await {
+            Ok(Ok(_)) => return true,
+            Ok(Err(e)) => log::debug!("network: {host}: {e}"),
+            Err(_) => log::debug!("network: {host}: timed out"),
+        }
+    }
+    false
+}
+
+fn set(app: &AppHandle, online: bool, monitored: bool) {
+    let network = app.state::<Network>();
+    let now = crate::clock::now().to_rfc3339();
+    let changed = {
+        let mut status = network.status();
+        let changed = status.online != online;
+        status.online = online;
+        status.monitored = monitored;
+        status.checked_at = Some(now.clone());
+        if changed {
+            status.since = Some(now);
+        }
+        changed
+    };
+    if !changed {
+        return;
+    }
+    network.online.send_replace(online);
+    let status = network.status().clone();
+    if online {
+        log::info!("network: back online");
+        let _ = app.emit(ONLINE_EVENT, status);
+        crate::sync_queue::wake(app);
+        crate::replica::wake(app);
+    } else {
+        log::warn!("network: offline");
+        let _ = app.emit(OFFLINE_EVENT, status);
+    }
+}
+
+/// Checks the network for as long as the app runs.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let settings = app.state::<SettingsStore>().get().network;
+            let online = if settings.monitor && !settings.probe_hosts.is_empty() {
+                probe(&settings.probe_hosts).await
+            } else {
+                true
+            };
+            set(&app, online, settings.monitor);
+            let delay = if online { CHECK_ONLINE } else { CHECK_OFFLINE };
+            let network = app.state::<Network>();
+            tokio::select! {
+                _ = network.check.notified() => {}
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_network_status(app: AppHandle) -> NetworkStatus {
+    app.state::<Network>().status().clone()
+}
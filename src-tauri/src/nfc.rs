@@ -0,0 +1,331 @@
+//! NFC tag check-ins: a tag on the gym bag or the toothbrush cup, bound to a habit, checks
+//! it off when the phone is held to it, with a notification to say so.
+//!
+//! - On Android, tapping any tag opens the app (`NfcPlugin.kt`), which passes the tag's
+//!   hardware id to [`scan`]. A tap that arrives before the backend is listening is held
+//!   until it is.
+//! - iOS doesn't open apps for arbitrary tags, so there a Shortcuts automation ("When I tap
+//!   the gym bag tag") runs the "Check In with Tag" intent, which names the tag by its
+//!   label (`shortcuts.rs`).
+//!
+//! A tag is bound with `bind_nfc_tag` when its id is known, or with `bind_next_nfc_tag`,
+//! after which the next tag scanned within [`BIND_WINDOW`] is bound instead of checking
+//! anything off. Like the widgets, a scan never unchecks a habit, and nothing is checked
+//! off while the app is locked.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::i18n::{t, t_args};
+use crate::settings::generate_token;
+use crate::stats::{date_key, today};
+use crate::storage::{NfcTag, Storage};
+
+/// How long `bind_next_nfc_tag` waits for a tag.
+pub const BIND_WINDOW: Duration = Duration::from_secs(120);
+/// Carries the tag id of a tap on a tag that isn't bound, so the app can offer to bind it.
+pub const UNKNOWN_TAG_EVENT: &str = "nfc:unknown-tag";
+#[cfg(target_os = "android")]
+const TITLE: &str = "HabitFlow";
+
+#[cfg(target_os = "android")]
+mod imp {
+    use tauri::plugin::{Builder, TauriPlugin};
+    use tauri::Wry;
+
+    const PLUGIN_PACKAGE: &str = "com.sushil930.habitflow.nfc";
+
+    #[derive(serde::Serialize)]
+    struct Handler {
+        handler: tauri::ipc::Channel<serde_json::Value>,
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Tap {
+        tag_id: String,
+    }
+
+    /// Registers `NfcPlugin.kt` and hands it a channel for taps.
+    pub fn init() -> TauriPlugin<Wry> {
+        Builder::new("habit-nfc")
+            .setup(|app, api| {
+                let handle = api.register_android_plugin(PLUGIN_PACKAGE, "NfcPlugin")?;
+                let a = app.clone();
+                let channel = tauri::ipc::Channel::new(move |body| {
+                    let tap: Tap = body.deserialize()?;
+                    let a = a.clone();
+                    tauri::async_runtime::spawn_blocking(move || {
+                        super::scan_logged(&a, &tap.tag_id)
+                    });
+                    Ok(())
+                });
+                handle.run_mobile_plugin::<serde_json::Value>(
+                    "setTagHandler",
+                    Handler { handler: channel },
+                )?;
+                Ok(())
+            })
+            .build()
+    }
+}
+
+#[cfg(target_os = "android")]
+pub use imp::init;
+
+/// A `bind_next_nfc_tag` waiting for its tag.
+struct Armed {
+    habit_id: String,
+    label: Option<String>,
+    until: Instant,
+}
+
+#[derive(Default)]
+pub struct NfcBinding(Mutex<Option<Armed>>);
+
+impl NfcBinding {
+    fn take(&self) -> Option<Armed> {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+            .filter(|armed| armed.until > Instant::now())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScanOutcome {
+    /// Checked off, or one more check-in for a repeated habit.
+    CheckedIn,
+    AlreadyDone,
+    /// The tag was bound by `bind_next_nfc_tag`.
+    Bound,
+    Unknown,
+    Locked,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagScan {
+    pub tag_id: String,
+    pub outcome: ScanOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub habit_id: Option<String>,
+    /// The notification's text.
+    pub message: String,
+}
+
+/// Uppercase hex without separators, however the id was written ("04:a2:..." or "04A2...").
+fn normalized(tag_id: &str) -> Result<String, AppError> {
+    let hex: String = tag_id
+        .chars()
+        .filter(|c| !matches!(c, ':' | '-' | ' '))
+        .collect::<String>()
+        .to_uppercase();
+    if hex.is_empty() || hex.len() > 64 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::Invalid(format!("not a tag id: {tag_id:?}")));
+    }
+    Ok(hex)
+}
+
+fn clean_label(label: Option<String>) -> Option<String> {
+    label
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+}
+
+/// Without a tag id, as on iOS where the app never sees it, the tag is known by its label
+/// alone and gets a made-up id.
+pub fn bind(
+    storage: &Storage,
+    tag_id: Option<&str>,
+    habit_id: &str,
+    label: Option<String>,
+) -> Result<NfcTag, AppError> {
+    let habit = storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+    if habit.archived {
+        return Err(AppError::Invalid(format!("{} is archived", habit.name)));
+    }
+    let label = clean_label(label);
+    let tag_id = match (tag_id, &label) {
+        (Some(id), _) => normalized(id)?,
+        (None, Some(_)) => generate_token()[..16].to_uppercase(),
+        (None, None) => {
+            return Err(AppError::Invalid(
+                "a tag needs its id or a label to be known by".into(),
+            ))
+        }
+    };
+    let tag = NfcTag {
+        tag_id,
+        habit_id: habit.id,
+        label,
+        bound_at: chrono::Utc::now().to_rfc3339(),
+        last_scanned_at: None,
+    };
+    storage.bind_nfc_tag(&tag)?;
+    Ok(tag)
+}
+
+/// Handles a tag being tapped: binds it if `bind_next_nfc_tag` is waiting, else checks
+/// its habit off for today. `reference` is the tag id, or on iOS its label.
+pub fn scan(app: &AppHandle, reference: &str) -> Result<TagScan, AppError> {
+    crate::telemetry::count(app, "nfc");
+    let storage = app.state::<Storage>();
+    let hide_names = crate::privacy::hide_names(app);
+    let reply = |tag_id: &str, outcome, habit_id: Option<&str>, message: String| TagScan {
+        tag_id: tag_id.to_string(),
+        outcome,
+        habit_id: habit_id.map(str::to_string),
+        message,
+    };
+
+    if app.state::<crate::lock::AppLock>().is_locked() {
+        return Ok(reply(reference, ScanOutcome::Locked, None, t("nfc-locked")));
+    }
+    if let Some(armed) = app.state::<NfcBinding>().take() {
+        let tag = bind(&storage, Some(reference), &armed.habit_id, armed.label)?;
+        let message = match storage.get_habit(&tag.habit_id)? {
+            Some(habit) if !hide_names => t_args(
+                "nfc-linked",
+                &[("name", crate::appearance::label(&habit).into())],
+            ),
+            _ => t("nfc-linked-hidden"),
+        };
+        return Ok(reply(
+            &tag.tag_id,
+            ScanOutcome::Bound,
+            Some(&tag.habit_id),
+            message,
+        ));
+    }
+
+    let tag = match storage.find_nfc_tag(reference)? {
+        Some(tag) => tag,
+        None => match normalized(reference) {
+            Ok(id) => match storage.find_nfc_tag(&id)? {
+                Some(tag) => tag,
+                None => {
+                    let _ = app.emit(UNKNOWN_TAG_EVENT, &id);
+                    return Ok(reply(&id, ScanOutcome::Unknown, None, t("nfc-unknown")));
+                }
+            },
+            Err(_) => return Err(AppError::not_found("tag", reference)),
+        },
+    };
+    let habit = storage
+        .get_habit(&tag.habit_id)?
+        .filter(|h| !h.archived)
+        .ok_or_else(|| AppError::not_found("habit", &tag.habit_id))?;
+    storage.touch_nfc_tag(&tag.tag_id, &chrono::Utc::now().to_rfc3339())?;
+
+    let date = today();
+    let key = date_key(date);
+    let name = if hide_names {
+        t("nfc-hidden-name")
+    } else {
+        crate::appearance::label(&habit)
+    };
+    if habit.is_done_on(&key) {
+        return Ok(reply(
+            &tag.tag_id,
+            ScanOutcome::AlreadyDone,
+            Some(&habit.id),
+            t_args("nfc-already-done", &[("name", name.into())]),
+        ));
+    }
+    let updated = crate::habits::check_off(app, &habit, date)?
+        .ok_or_else(|| AppError::not_found("habit", &habit.id))?;
+    let message = if updated.is_done_on(&key) {
+        t_args("nfc-done", &[("name", name.into())])
+    } else {
+        let progress = crate::schedule::progress(&updated, date);
+        t_args(
+            "nfc-progress",
+            &[
+                ("name", name.into()),
+                ("done", progress.done.into()),
+                ("goal", progress.goal.into()),
+            ],
+        )
+    };
+    Ok(reply(
+        &tag.tag_id,
+        ScanOutcome::CheckedIn,
+        Some(&habit.id),
+        message,
+    ))
+}
+
+/// [`scan`], with the outcome or the failure shown as a notification.
+#[cfg(target_os = "android")]
+pub fn scan_logged(app: &AppHandle, reference: &str) {
+    match scan(app, reference) {
+        Ok(scan) => crate::reminders::notify(app, TITLE, &scan.message),
+        Err(e) => {
+            log::warn!("nfc: tag tap failed: {e}");
+            crate::reminders::notify(app, TITLE, &t("nfc-failed"));
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_nfc_tags(storage: State<'_, Storage>) -> Result<Vec<NfcTag>, AppError> {
+    Ok(storage.list_nfc_tags()?)
+}
+
+/// Binds a tag, replacing what it was bound to. Leave `tag_id` out to bind a label alone,
+/// for iOS.
+#[tauri::command]
+pub fn bind_nfc_tag(
+    storage: State<'_, Storage>,
+    tag_id: Option<String>,
+    habit_id: String,
+    label: Option<String>,
+) -> Result<NfcTag, AppError> {
+    bind(&storage, tag_id.as_deref(), &habit_id, label)
+}
+
+/// Binds the next tag scanned within two minutes to the habit, instead of checking
+/// anything off. Calling it again replaces the wait.
+#[tauri::command]
+pub fn bind_next_nfc_tag(
+    app: AppHandle,
+    habit_id: String,
+    label: Option<String>,
+) -> Result<(), AppError> {
+    if app.state::<Storage>().get_habit(&habit_id)?.is_none() {
+        return Err(AppError::not_found("habit", &habit_id));
+    }
+    *app.state::<NfcBinding>()
+        .0
+        .lock()
+        .unwrap_or_else(|e| e.into_inner()) = Some(Armed {
+        habit_id,
+        label: clean_label(label),
+        until: Instant::now() + BIND_WINDOW,
+    });
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unbind_nfc_tag(storage: State<'_, Storage>, tag_id: String) -> Result<(), AppError> {
+    let tag_id = normalized(&tag_id)?;
+    if !storage.delete_nfc_tag(&tag_id)? {
+        return Err(AppError::not_found("tag", &tag_id));
+    }
+    Ok(())
+}
+
+/// Handles a tag tap as the phone would, without the notification.
+#[tauri::command]
+pub fn scan_nfc_tag(app: AppHandle, tag_id: String) -> Result<TagScan, AppError> {
+    scan(&app, &tag_id)
+}
@@ -0,0 +1,107 @@
+//! Journal notes on check-ins: a line of free text per completed day ("pushed through
+//! after a bad night"), kept in the backend, full-text indexed and included in exports.
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::Habit;
+use crate::revisions;
+use crate::stats::{date_arg, date_key};
+use crate::storage::{NoteMatch, Storage, Tx};
+
+/// Longest note accepted, in characters.
+pub const MAX_NOTE_LEN: usize = 2000;
+
+/// Sets the note on a completed day; an empty note removes it. Returns `Ok(None)` if the
+/// habit doesn't exist.
+pub fn update(
+    tx: &Tx<'_>,
+    habit_id: &str,
+    date: &str,
+    note: Option<&str>,
+) -> Result<Option<Habit>, AppError> {
+    let date = date_arg(date)?;
+    let key = date_key(date);
+    let note = note.map(str::trim).filter(|n| !n.is_empty());
+    if note.is_some_and(|n| n.chars().count() > MAX_NOTE_LEN) {
+        return Err(AppError::Invalid(format!(
+            "notes are limited to {MAX_NOTE_LEN} characters"
+        )));
+    }
+    let Some(habit) = tx.get_habit(habit_id)? else {
+        return Ok(None);
+    };
+    if note.is_some() && !habit.is_done_on(&key) {
+        return Err(AppError::Invalid(format!(
+            "{} isn't checked off on {key}",
+            habit.name
+        )));
+    }
+    tx.set_completion_note(habit_id, &key, note)?;
+    Ok(tx.get_habit(habit_id)?)
+}
+
+/// Every word must appear; the last one may be a prefix ("meditat" finds "meditation").
+fn fts_query(text: &str) -> Option<String> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|w| format!("\"{}\"", w.replace('"', "\"\"")))
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+    Some(format!("{}*", words.join(" ")))
+}
+
+pub fn search(storage: &Storage, text: &str, limit: Option<u32>) -> Result<Vec<NoteMatch>, String> {
+    let Some(query) = fts_query(text) else {
+        return Ok(Vec::new());
+    };
+    storage
+        .search_notes(&query, limit.unwrap_or(50))
+        .map_err(|e| e.to_string())
+}
+
+/// [`update`] if the day (`yyyy-MM-dd`) is still on `expected` (see `revisions.rs`).
+pub fn update_checked(
+    storage: &Storage,
+    habit_id: &str,
+    date: &str,
+    note: Option<&str>,
+    expected: Option<u64>,
+) -> Result<Habit, AppError> {
+    revisions::check_day(storage, habit_id, date_arg(date)?, expected, |tx| {
+        update(tx, habit_id, date, note)?.ok_or_else(|| AppError::not_found("habit", habit_id))
+    })
+}
+
+/// Sets (or, with an empty `note`, clears) the note on a completed day.
+#[tauri::command]
+pub fn update_checkin_note(
+    app: AppHandle,
+    habit_id: String,
+    date: String,
+    note: Option<String>,
+    expected_revision: Option<u64>,
+) -> Result<Habit, AppError> {
+    let habit = update_checked(
+        &app.state::<Storage>(),
+        &habit_id,
+        &date,
+        note.as_deref(),
+        expected_revision,
+    )?;
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(&app);
+    Ok(habit)
+}
+
+#[tauri::command]
+pub fn search_notes(
+    storage: State<'_, Storage>,
+    query: String,
+    limit: Option<u32>,
+) -> Result<Vec<NoteMatch>, AppError> {
+    Ok(search(&storage, &query, limit)?)
+}
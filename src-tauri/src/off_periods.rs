@@ -0,0 +1,98 @@
+//! Vacation / skip mode: date ranges when habits are off, for every habit or just one.
+//!
+//! Excused days are neutral for streaks (they neither extend nor break one), nothing is
+//! due on them so reminders stay quiet, and stats count them as excused instead of missed.
+//! Periods are kept by the backend only; the webview sees their effect through stats.
+
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::OffPeriod;
+use crate::settings::generate_token;
+use crate::stats::{date_arg, date_key};
+use crate::storage::Storage;
+
+/// Validates and stores a new period. `end` defaults to `start`.
+pub fn add(
+    storage: &Storage,
+    start: &str,
+    end: Option<&str>,
+    habit_id: Option<String>,
+    reason: Option<String>,
+) -> Result<OffPeriod, AppError> {
+    let start_date = date_arg(start)?;
+    let end_date = match end {
+        Some(e) => date_arg(e)?,
+        None => start_date,
+    };
+    if end_date < start_date {
+        return Err(AppError::Invalid("the period ends before it starts".into()));
+    }
+    if let Some(id) = &habit_id {
+        if storage.get_habit(id)?.is_none() {
+            return Err(AppError::not_found("habit", id));
+        }
+    }
+    let period = OffPeriod {
+        id: generate_token()[..16].to_string(),
+        habit_id,
+        start: date_key(start_date),
+        end: date_key(end_date),
+        reason: reason
+            .map(|r| r.trim().to_string())
+            .filter(|r| !r.is_empty()),
+        paused: false,
+    };
+    storage.insert_off_period(&period)?;
+    Ok(period)
+}
+
+fn changed(app: &AppHandle) {
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(app);
+}
+
+/// Adds an off period from `start` through `end` (inclusive, default `start`), for one
+/// habit or, without `habit_id`, for all of them.
+#[tauri::command]
+pub fn add_off_period(
+    app: AppHandle,
+    start: String,
+    end: Option<String>,
+    habit_id: Option<String>,
+    reason: Option<String>,
+) -> Result<OffPeriod, AppError> {
+    let period = add(
+        &app.state::<Storage>(),
+        &start,
+        end.as_deref(),
+        habit_id,
+        reason,
+    )?;
+    changed(&app);
+    Ok(period)
+}
+
+/// Periods covering `habit_id` (its own and global ones), or every period without it.
+#[tauri::command]
+pub fn list_off_periods(
+    storage: State<'_, Storage>,
+    habit_id: Option<String>,
+) -> Result<Vec<OffPeriod>, AppError> {
+    Ok(storage.list_off_periods(habit_id.as_deref())?)
+}
+
+pub fn remove(storage: &Storage, id: &str) -> Result<(), AppError> {
+    if !storage.delete_off_period(id)? {
+        return Err(AppError::not_found("off period", id));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_off_period(app: AppHandle, id: String) -> Result<(), AppError> {
+    remove(&app.state::<Storage>(), &id)?;
+    changed(&app);
+    Ok(())
+}
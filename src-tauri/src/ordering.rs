@@ -0,0 +1,136 @@
+//! Habit order, kept in the backend so every view agrees: the manual order (storage lists
+//! habits in it), pins that put a habit ahead of the rest, and a sort per view in
+//! `settings.sort`.
+
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+use chrono::NaiveDate;
+use serde::Deserialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::Habit;
+use crate::settings::{HabitSort, SettingsStore, SortSettings};
+use crate::stats::today;
+use crate::storage::Storage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum View {
+    Main,
+    Tray,
+    Widget,
+}
+
+impl View {
+    pub fn sort(self, settings: &SortSettings) -> HabitSort {
+        match self {
+            View::Main => settings.main,
+            View::Tray => settings.tray,
+            View::Widget => settings.widget,
+        }
+    }
+}
+
+/// Sorts habits given in manual order; pinned ones end up first, each group in `sort` order.
+pub fn sort(storage: &Storage, habits: &mut [Habit], sort: HabitSort, today: NaiveDate) {
+    match sort {
+        HabitSort::Manual => {}
+        HabitSort::Name => habits.sort_by_cached_key(|h| h.name.to_lowercase()),
+        HabitSort::Streak => {
+            habits.sort_by_cached_key(|h| Reverse(crate::streaks::current(storage, h, today)))
+        }
+        HabitSort::Created => habits.sort_by(|a, b| b.created_at.cmp(&a.created_at)),
+    }
+    habits.sort_by_key(|h| !h.pinned);
+}
+
+/// Every habit, in the order `view` shows them.
+pub fn for_view(
+    storage: &Storage,
+    settings: &SettingsStore,
+    view: View,
+) -> Result<Vec<Habit>, String> {
+    let mut habits = storage.list_habits().map_err(|e| e.to_string())?;
+    sort(
+        storage,
+        &mut habits,
+        view.sort(&settings.get().sort),
+        today(),
+    );
+    Ok(habits)
+}
+
+/// `ids` first, in that order (unknown ones dropped), then the rest of `existing` as it
+/// was.
+pub fn reordered<'a>(existing: &'a [String], ids: &'a [String]) -> Vec<&'a String> {
+    let listed: HashSet<&str> = ids.iter().map(String::as_str).collect();
+    ids.iter()
+        .filter(|id| existing.contains(id))
+        .chain(existing.iter().filter(|id| !listed.contains(id.as_str())))
+        .collect()
+}
+
+/// Pins or unpins a habit.
+pub fn set_pinned(storage: &Storage, habit_id: &str, pinned: bool) -> Result<Habit, AppError> {
+    if !storage.set_habit_pinned(habit_id, pinned)? {
+        return Err(AppError::not_found("habit", habit_id));
+    }
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+/// Sets how `view` sorts its habits and returns the sort settings for every view.
+pub fn set_sort(
+    settings: &SettingsStore,
+    view: View,
+    sort: HabitSort,
+) -> std::io::Result<SortSettings> {
+    let settings = settings.update(|s| match view {
+        View::Main => s.sort.main = sort,
+        View::Tray => s.sort.tray = sort,
+        View::Widget => s.sort.widget = sort,
+    })?;
+    Ok(settings.sort)
+}
+
+pub(crate) fn changed(app: &AppHandle) {
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(app);
+}
+
+/// `ids` in the new manual order; habits left out keep their order after the listed ones.
+#[tauri::command]
+/// Queued like `sync_habits` (see `write_queue.rs`).
+pub fn reorder_habits(app: AppHandle, ids: Vec<String>) -> Result<Vec<Habit>, AppError> {
+    Ok(crate::write_queue::queue_order(&app, ids)?)
+}
+
+#[tauri::command]
+pub fn set_habit_pinned(app: AppHandle, habit_id: String, pinned: bool) -> Result<Habit, AppError> {
+    let habit = set_pinned(&app.state::<Storage>(), &habit_id, pinned)?;
+    changed(&app);
+    Ok(habit)
+}
+
+/// Habit ids in the order `view` shows them.
+#[tauri::command]
+pub fn get_habit_order(app: AppHandle, view: View) -> Result<Vec<String>, AppError> {
+    let habits = for_view(&app.state::<Storage>(), &app.state::<SettingsStore>(), view)?;
+    Ok(habits.into_iter().map(|h| h.id).collect())
+}
+
+#[tauri::command]
+pub fn set_sort_preference(
+    app: AppHandle,
+    view: View,
+    sort: HabitSort,
+) -> Result<SortSettings, AppError> {
+    let sort = set_sort(&app.state::<SettingsStore>(), view, sort)?;
+    // The tray rebuilds its menu on data changes.
+    app.state::<EventBus>().data_changed();
+    Ok(sort)
+}
@@ -0,0 +1,125 @@
+//! Pausing a habit for a while (an injury, a move) without archiving it.
+//!
+//! A pause is an off period for that one habit, from the day it was paused through the
+//! day before `until`, so history stays and the paused days are excused: nothing is due,
+//! reminders stay quiet and streaks survive. The habit comes back on its own on `until`;
+//! the scheduler reports that at the day rollover.
+
+use chrono::{Days, NaiveDate};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::{EventBus, HabitEvent};
+use crate::model::{Habit, OffPeriod};
+use crate::settings::generate_token;
+use crate::stats::{date_arg, date_key, parse_date, today};
+use crate::storage::Storage;
+
+/// Pauses `habit_id` from `today` until `until` (the first day back), or moves the end of
+/// the pause it's already in.
+pub fn pause(
+    storage: &Storage,
+    habit_id: &str,
+    until: NaiveDate,
+    today: NaiveDate,
+) -> Result<Habit, AppError> {
+    if until <= today {
+        return Err(AppError::Invalid(
+            "a pause has to last until a later day".into(),
+        ));
+    }
+    let Some(habit) = storage.get_habit(habit_id)? else {
+        return Err(AppError::not_found("habit", habit_id));
+    };
+    let end = date_key(until - Days::new(1));
+    match habit.pause_from(&date_key(today)) {
+        Some(current) => {
+            storage.set_off_period_end(&current.id, &end)?;
+        }
+        None => {
+            let period = OffPeriod {
+                id: generate_token()[..16].to_string(),
+                habit_id: Some(habit_id.to_string()),
+                start: date_key(today),
+                end,
+                reason: None,
+                paused: true,
+            };
+            storage.insert_off_period(&period)?;
+        }
+    }
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+/// Ends the pause early so the habit is back today. Days already paused stay excused.
+pub fn resume(storage: &Storage, habit_id: &str, today: NaiveDate) -> Result<Habit, AppError> {
+    let Some(habit) = storage.get_habit(habit_id)? else {
+        return Err(AppError::not_found("habit", habit_id));
+    };
+    let pause = habit
+        .pause_from(&date_key(today))
+        .ok_or_else(|| AppError::Invalid(format!("{} isn't paused", habit.name)))?;
+    let yesterday = today - Days::new(1);
+    if parse_date(&pause.start).map_or(true, |start| start > yesterday) {
+        storage.delete_off_period(&pause.id)?;
+    } else {
+        storage.set_off_period_end(&pause.id, &date_key(yesterday))?;
+    }
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+/// Habits whose pause ran out during `previous..today`, i.e. that are back today.
+pub fn resumed(
+    storage: &Storage,
+    previous: NaiveDate,
+    today: NaiveDate,
+) -> Result<Vec<Habit>, String> {
+    let (previous, today) = (date_key(previous), date_key(today));
+    Ok(storage
+        .list_habits()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|h| {
+            h.pause_from(&today).is_none()
+                && h.off_periods.iter().any(|p| {
+                    p.paused
+                        && p.end.as_str() >= previous.as_str()
+                        && p.end.as_str() < today.as_str()
+                })
+        })
+        .collect())
+}
+
+fn changed(app: &AppHandle) {
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(app);
+}
+
+/// `until` (`yyyy-MM-dd`) is the first day the habit is active again.
+#[tauri::command]
+pub fn pause_habit(app: AppHandle, habit_id: String, until: String) -> Result<Habit, AppError> {
+    let until_date = date_arg(&until)?;
+    let habit = pause(&app.state::<Storage>(), &habit_id, until_date, today())?;
+    app.state::<EventBus>().publish(HabitEvent::HabitPaused {
+        habit_id: habit.id.clone(),
+        name: habit.name.clone(),
+        until: date_key(until_date),
+    });
+    changed(&app);
+    Ok(habit)
+}
+
+#[tauri::command]
+pub fn resume_habit(app: AppHandle, habit_id: String) -> Result<Habit, AppError> {
+    let habit = resume(&app.state::<Storage>(), &habit_id, today())?;
+    app.state::<EventBus>().publish(HabitEvent::HabitResumed {
+        habit_id: habit.id.clone(),
+        name: habit.name.clone(),
+    });
+    changed(&app);
+    Ok(habit)
+}
@@ -0,0 +1,629 @@
+//! Sandboxed WASM plugins.
+//!
+//! Each plugin is a folder in `<app data>/plugins/` with a `manifest.json`:
+//!
+//! ```json
+//! {
+//!   "id": "csv-export",
+//!   "name": "CSV export",
+//!   "version": "1.0.0",
+//!   "wasm": "plugin.wasm",
+//!   "hooks": ["on_completion", "on_day_rollover", "export"],
+//!   "capabilities": ["read_habits", "notify", "write_completions"],
+//!   "exporter": { "name": "CSV", "extension": "csv" }
+//! }
+//! ```
+//!
+//! Plugins are disabled until enabled via `enable_plugin`, which also records which of the
+//! requested capabilities the user granted. Host functions for ungranted capabilities trap.
+//!
+//! ABI: the module exports `memory` and `alloc(len: i32) -> i32`. Payloads are UTF-8 JSON
+//! passed as `(ptr, len)`; results come back packed as `(ptr << 32) | len` in an `i64`.
+//! - `on_completion(ptr, len)`: a `habitCompleted` event
+//! - `on_day_rollover(ptr, len)`: `{ "date": "yyyy-MM-dd" }`
+//! - `export(ptr, len) -> i64`: gets the habit list, returns the exported file contents
+//!
+//! Imports from the `habitflow` module: `log(ptr, len)`, `notify(title_ptr, title_len,
+//! body_ptr, body_len)`, `habits() -> i64` and `complete(ptr, len) -> i32` (habit id or name).
+//! Every call runs on a fresh instance with a fuel budget and at most [`MAX_MEMORY`] of
+//! memory. Lengths the plugin hands back are checked against its memory before anything
+//! is allocated for them.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use wasmi::{
+    AsContext, Caller, Config, Engine, Extern, Linker, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+use crate::error::AppError;
+use crate::events::{EventBus, HabitEvent};
+use crate::features::{self, Feature};
+use crate::settings::{PluginGrant, SettingsStore};
+use crate::stats::today;
+use crate::storage::Storage;
+
+pub const PLUGINS_DIR: &str = "plugins";
+const FUEL_PER_CALL: u64 = 50_000_000;
+/// Linear memory a plugin instance can grow to.
+const MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+pub const HOOKS: &[&str] = &["on_completion", "on_day_rollover", "export"];
+pub const CAPABILITIES: &[&str] = &["read_habits", "notify", "write_completions"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginManifest {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default = "default_wasm")]
+    pub wasm: String,
+    #[serde(default)]
+    pub hooks: Vec<String>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub exporter: Option<ExporterInfo>,
+}
+
+fn default_wasm() -> String {
+    "plugin.wasm".into()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExporterInfo {
+    pub name: String,
+    pub extension: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginInfo {
+    pub id: String,
+    pub dir: String,
+    pub manifest: Option<PluginManifest>,
+    pub enabled: bool,
+    pub granted: Vec<String>,
+    pub error: Option<String>,
+}
+
+struct LoadedPlugin {
+    id: String,
+    dir: PathBuf,
+    manifest: Option<PluginManifest>,
+    module: Option<Module>,
+    error: Option<String>,
+}
+
+pub struct PluginHost {
+    engine: Engine,
+    dir: PathBuf,
+    plugins: Mutex<Vec<LoadedPlugin>>,
+}
+
+/// Per-call store data.
+struct HostCtx {
+    app: AppHandle,
+    plugin: String,
+    granted: Vec<String>,
+    limits: StoreLimits,
+}
+
+impl HostCtx {
+    fn require(&self, capability: &str) -> Result<(), wasmi::Error> {
+        if self.granted.iter().any(|c| c == capability) {
+            Ok(())
+        } else {
+            Err(wasmi::Error::new(format!(
+                "plugin {} was not granted {capability}",
+                self.plugin
+            )))
+        }
+    }
+}
+
+impl PluginHost {
+    /// Empty until the first [`reload`](Self::reload).
+    pub fn new(data_dir: &Path) -> Self {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        Self {
+            engine: Engine::new(&config),
+            dir: data_dir.join(PLUGINS_DIR),
+            plugins: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<LoadedPlugin>> {
+        self.plugins.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Re-reads manifests and recompiles modules.
+    pub fn reload(&self) {
+        if let Err(e) = std::fs::create_dir_all(&self.dir) {
+            log::warn!("plugins: can't create {}: {e}", self.dir.display());
+        }
+        let mut dirs: Vec<PathBuf> = std::fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|e| e.ok().map(|e| e.path()))
+                    .filter(|p| p.is_dir())
+                    .collect()
+            })
+            .unwrap_or_default();
+        dirs.sort();
+
+        let plugins = dirs.into_iter().map(|dir| self.load(dir)).collect();
+        *self.lock() = plugins;
+    }
+
+    fn load(&self, dir: PathBuf) -> LoadedPlugin {
+        let folder = dir
+            .file_name()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut plugin = LoadedPlugin {
+            id: folder,
+            dir,
+            manifest: None,
+            module: None,
+            error: None,
+        };
+
+        let manifest = std::fs::read_to_string(plugin.dir.join("manifest.json"))
+            .map_err(|e| format!("can't read manifest.json: {e}"))
+            .and_then(|text| {
+                serde_json::from_str::<PluginManifest>(&text)
+                    .map_err(|e| format!("invalid manifest.json: {e}"))
+            })
+            .and_then(validate_manifest);
+        let manifest = match manifest {
+            Ok(m) => m,
+            Err(e) => {
+                plugin.error = Some(e);
+                return plugin;
+            }
+        };
+
+        plugin.id = manifest.id.clone();
+        match std::fs::read(plugin.dir.join(&manifest.wasm)) {
+            Ok(bytes) => match Module::new(&self.engine, bytes) {
+                Ok(module) => plugin.module = Some(module),
+                Err(e) => plugin.error = Some(format!("invalid module: {e}")),
+            },
+            Err(e) => plugin.error = Some(format!("can't read {}: {e}", manifest.wasm)),
+        }
+        plugin.manifest = Some(manifest);
+        if let Some(e) = &plugin.error {
+            log::warn!("plugins: {} failed to load: {e}", plugin.id);
+        }
+        plugin
+    }
+
+    pub fn list(&self, settings: &SettingsStore) -> Vec<PluginInfo> {
+        let grants = settings.get().plugins;
+        self.lock()
+            .iter()
+            .map(|p| {
+                let grant = grants.get(&p.id);
+                PluginInfo {
+                    id: p.id.clone(),
+                    dir: p.dir.display().to_string(),
+                    manifest: p.manifest.clone(),
+                    enabled: grant.is_some_and(|g| g.enabled),
+                    granted: grant.map(|g| g.capabilities.clone()).unwrap_or_default(),
+                    error: p.error.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Runs `hook` with `payload` in every enabled plugin that declares it.
+    pub fn broadcast(&self, app: &AppHandle, hook: &str, payload: &[u8]) {
+        if !features::enabled(Feature::Plugins) {
+            return;
+        }
+        let grants = app.state::<SettingsStore>().get().plugins;
+        let targets: Vec<(String, Module, Vec<String>)> = self
+            .lock()
+            .iter()
+            .filter_map(|p| {
+                let manifest = p.manifest.as_ref()?;
+                let grant = grants.get(&p.id).filter(|g| g.enabled)?;
+                if !manifest.hooks.iter().any(|h| h == hook) {
+                    return None;
+                }
+                Some((p.id.clone(), p.module.clone()?, grant.capabilities.clone()))
+            })
+            .collect();
+
+        for (id, module, granted) in targets {
+            if let Err(e) = self.call(app, &id, &module, granted, hook, payload, false) {
+                log::warn!("plugins: {id}::{hook} failed: {e}");
+            }
+        }
+    }
+
+    /// Runs an exporter plugin over the current habit list and returns its output.
+    pub fn export(&self, app: &AppHandle, id: &str) -> Result<String, AppError> {
+        let grant = app
+            .state::<SettingsStore>()
+            .get()
+            .plugins
+            .get(id)
+            .filter(|g| g.enabled)
+            .cloned()
+            .ok_or_else(|| AppError::Forbidden(format!("plugin {id} is not enabled")))?;
+        if !grant.capabilities.iter().any(|c| c == "read_habits") {
+            return Err(AppError::Forbidden(
+                "exporters need the read_habits capability".into(),
+            ));
+        }
+
+        let module = {
+            let plugins = self.lock();
+            let plugin = plugins
+                .iter()
+                .find(|p| p.id == id)
+                .ok_or_else(|| AppError::not_found("plugin", id))?;
+            let exports = plugin
+                .manifest
+                .as_ref()
+                .is_some_and(|m| m.hooks.iter().any(|h| h == "export"));
+            if !exports {
+                return Err(AppError::Invalid(format!("plugin {id} is not an exporter")));
+            }
+            plugin
+                .module
+                .clone()
+                .ok_or_else(|| AppError::Failed(format!("plugin {id} failed to load")))?
+        };
+
+        let habits = app.state::<Storage>().list_habits()?;
+        let payload = serde_json::to_vec(&habits).map_err(|e| e.to_string())?;
+        let output = self
+            .call(
+                app,
+                id,
+                &module,
+                grant.capabilities,
+                "export",
+                &payload,
+                true,
+            )
+            .map_err(|e| e.to_string())?
+            .unwrap_or_default();
+        String::from_utf8(output)
+            .map_err(|_| AppError::Failed("exporter returned invalid UTF-8".into()))
+    }
+
+    /// Instantiates `module` and calls `hook(ptr, len)`. With `returns`, the hook's packed
+    /// `i64` result is read back out of guest memory.
+    #[allow(clippy::too_many_arguments)]
+    fn call(
+        &self,
+        app: &AppHandle,
+        id: &str,
+        module: &Module,
+        granted: Vec<String>,
+        hook: &str,
+        payload: &[u8],
+        returns: bool,
+    ) -> Result<Option<Vec<u8>>, wasmi::Error> {
+        let mut store = Store::new(
+            &self.engine,
+            HostCtx {
+                app: app.clone(),
+                plugin: id.to_string(),
+                granted,
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(MAX_MEMORY)
+                    .instances(1)
+                    .memories(1)
+                    .build(),
+            },
+        );
+        store.limiter(|ctx| &mut ctx.limits);
+        store.set_fuel(FUEL_PER_CALL)?;
+
+        let linker = host_linker(&self.engine)?;
+        let instance = linker.instantiate_and_start(&mut store, module)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| wasmi::Error::new("plugin does not export memory"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&store, "alloc")?;
+
+        let ptr = alloc.call(&mut store, payload.len() as i32)?;
+        memory.write(&mut store, ptr as usize, payload)?;
+
+        if !returns {
+            let f = instance.get_typed_func::<(i32, i32), ()>(&store, hook)?;
+            f.call(&mut store, (ptr, payload.len() as i32))?;
+            return Ok(None);
+        }
+
+        let f = instance.get_typed_func::<(i32, i32), i64>(&store, hook)?;
+        let packed = f.call(&mut store, (ptr, payload.len() as i32))?;
+        let (out_ptr, out_len) = unpack(packed);
+        Ok(Some(read_memory(memory, &store, out_ptr, out_len)?))
+    }
+}
+
+fn validate_manifest(m: PluginManifest) -> Result<PluginManifest, String> {
+    if m.id.is_empty()
+        || !m
+            .id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("manifest id must be non-empty and use only [A-Za-z0-9_-]".into());
+    }
+    if let Some(h) = m.hooks.iter().find(|h| !HOOKS.contains(&h.as_str())) {
+        return Err(format!("unknown hook {h}"));
+    }
+    if let Some(c) = m
+        .capabilities
+        .iter()
+        .find(|c| !CAPABILITIES.contains(&c.as_str()))
+    {
+        return Err(format!("unknown capability {c}"));
+    }
+    if Path::new(&m.wasm).components().count() != 1 {
+        return Err("wasm must be a file name inside the plugin folder".into());
+    }
+    Ok(m)
+}
+
+fn pack(ptr: i32, len: i32) -> i64 {
+    ((ptr as u32 as i64) << 32) | (len as u32 as i64)
+}
+
+fn unpack(packed: i64) -> (usize, usize) {
+    (
+        (packed as u64 >> 32) as usize,
+        (packed as u64 & 0xffff_ffff) as usize,
+    )
+}
+
+/// `len` bytes at `ptr`, if they're inside `memory`.
+fn read_memory(
+    memory: Memory,
+    ctx: impl AsContext,
+    ptr: usize,
+    len: usize,
+) -> Result<Vec<u8>, wasmi::Error> {
+    if ptr
+        .checked_add(len)
+        .map_or(true, |end| end > memory.data_size(&ctx))
+    {
+        return Err(wasmi::Error::new(
+            "plugin passed a range outside its memory",
+        ));
+    }
+    let mut buf = vec![0; len];
+    memory.read(&ctx, ptr, &mut buf)?;
+    Ok(buf)
+}
+
+fn read_guest(caller: &Caller<'_, HostCtx>, ptr: i32, len: i32) -> Result<Vec<u8>, wasmi::Error> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("plugin does not export memory"))?;
+    if ptr < 0 || len < 0 {
+        return Err(wasmi::Error::new(
+            "plugin passed a negative pointer or length",
+        ));
+    }
+    read_memory(memory, caller, ptr as usize, len as usize)
+}
+
+fn read_guest_str(
+    caller: &Caller<'_, HostCtx>,
+    ptr: i32,
+    len: i32,
+) -> Result<String, wasmi::Error> {
+    String::from_utf8(read_guest(caller, ptr, len)?)
+        .map_err(|_| wasmi::Error::new("expected UTF-8"))
+}
+
+/// Copies `bytes` into guest memory via its `alloc` export.
+fn write_guest(caller: &mut Caller<'_, HostCtx>, bytes: &[u8]) -> Result<i64, wasmi::Error> {
+    let alloc = caller
+        .get_export("alloc")
+        .and_then(Extern::into_func)
+        .ok_or_else(|| wasmi::Error::new("plugin does not export alloc"))?
+        .typed::<i32, i32>(&*caller)?;
+    let ptr = alloc.call(&mut *caller, bytes.len() as i32)?;
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("plugin does not export memory"))?;
+    memory.write(&mut *caller, ptr as usize, bytes)?;
+    Ok(pack(ptr, bytes.len() as i32))
+}
+
+fn host_linker(engine: &Engine) -> Result<Linker<HostCtx>, wasmi::Error> {
+    let mut linker = Linker::<HostCtx>::new(engine);
+
+    linker.func_wrap(
+        "habitflow",
+        "log",
+        |caller: Caller<'_, HostCtx>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+            let msg = read_guest_str(&caller, ptr, len)?;
+            log::info!("plugin {}: {msg}", caller.data().plugin);
+            Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        "habitflow",
+        "notify",
+        |caller: Caller<'_, HostCtx>,
+         title_ptr: i32,
+         title_len: i32,
+         body_ptr: i32,
+         body_len: i32|
+         -> Result<(), wasmi::Error> {
+            caller.data().require("notify")?;
+            let title = read_guest_str(&caller, title_ptr, title_len)?;
+            let body = read_guest_str(&caller, body_ptr, body_len)?;
+            crate::reminders::notify(&caller.data().app, &title, &body);
+            Ok(())
+        },
+    )?;
+
+    linker.func_wrap(
+        "habitflow",
+        "habits",
+        |mut caller: Caller<'_, HostCtx>| -> Result<i64, wasmi::Error> {
+            caller.data().require("read_habits")?;
+            let habits = caller
+                .data()
+                .app
+                .state::<Storage>()
+                .list_habits()
+                .map_err(|e| wasmi::Error::new(e.to_string()))?;
+            let json = serde_json::to_vec(&habits).map_err(|e| wasmi::Error::new(e.to_string()))?;
+            write_guest(&mut caller, &json)
+        },
+    )?;
+
+    linker.func_wrap(
+        "habitflow",
+        "complete",
+        |caller: Caller<'_, HostCtx>, ptr: i32, len: i32| -> Result<i32, wasmi::Error> {
+            caller.data().require("write_completions")?;
+            let reference = read_guest_str(&caller, ptr, len)?;
+            let app = caller.data().app.clone();
+            let habits = app.state::<Storage>().list_habits().unwrap_or_default();
+            let Some(habit) = crate::habits::find(&habits, &reference) else {
+                return Ok(0);
+            };
+            let done = crate::habits::set_completion(&app, &habit.id, today(), true)
+                .ok()
+                .flatten()
+                .is_some();
+            Ok(done as i32)
+        },
+    )?;
+
+    Ok(linker)
+}
+
+/// Feeds completions into plugins for the lifetime of the app.
+pub fn start(app: &AppHandle) {
+    let mut rx = app.state::<EventBus>().subscribe();
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+            if !matches!(event, HabitEvent::HabitCompleted { .. }) {
+                continue;
+            }
+            let Ok(payload) = serde_json::to_vec(&event) else {
+                continue;
+            };
+            let app = app.clone();
+            // Guest code is interpreted; keep it off the async workers.
+            let _ = tauri::async_runtime::spawn_blocking(move || {
+                app.state::<PluginHost>()
+                    .broadcast(&app, "on_completion", &payload);
+            })
+            .await;
+        }
+    });
+}
+
+pub fn on_day_rollover(app: &AppHandle, date: &str) {
+    let payload = serde_json::json!({ "date": date }).to_string().into_bytes();
+    let app = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        app.state::<PluginHost>()
+            .broadcast(&app, "on_day_rollover", &payload);
+    });
+}
+
+#[tauri::command]
+pub fn list_plugins(
+    host: State<'_, PluginHost>,
+    settings: State<'_, SettingsStore>,
+) -> Vec<PluginInfo> {
+    host.list(&settings)
+}
+
+#[tauri::command]
+pub fn reload_plugins(
+    host: State<'_, PluginHost>,
+    settings: State<'_, SettingsStore>,
+) -> Vec<PluginInfo> {
+    host.reload();
+    host.list(&settings)
+}
+
+/// Enables/disables a plugin. `capabilities` defaults to everything the manifest requests;
+/// anything it doesn't request is ignored.
+#[tauri::command]
+pub fn enable_plugin(
+    host: State<'_, PluginHost>,
+    settings: State<'_, SettingsStore>,
+    id: String,
+    enabled: bool,
+    capabilities: Option<Vec<String>>,
+) -> Result<Vec<PluginInfo>, AppError> {
+    let requested = {
+        let plugins = host.lock();
+        let plugin = plugins
+            .iter()
+            .find(|p| p.id == id)
+            .ok_or("plugin not found")?;
+        if enabled && plugin.module.is_none() {
+            return Err(AppError::Failed(
+                plugin
+                    .error
+                    .clone()
+                    .unwrap_or_else(|| "plugin failed to load".into()),
+            ));
+        }
+        plugin
+            .manifest
+            .as_ref()
+            .map(|m| m.capabilities.clone())
+            .unwrap_or_default()
+    };
+
+    let granted = match capabilities {
+        Some(caps) => requested.into_iter().filter(|c| caps.contains(c)).collect(),
+        None => requested,
+    };
+    settings.update(|s| {
+        s.plugins.insert(
+            id,
+            PluginGrant {
+                enabled,
+                capabilities: granted,
+            },
+        );
+    })?;
+    Ok(host.list(&settings))
+}
+
+#[tauri::command]
+pub fn run_plugin_export(
+    app: AppHandle,
+    host: State<'_, PluginHost>,
+    id: String,
+) -> Result<String, AppError> {
+    host.export(&app, &id)
+}
@@ -0,0 +1,398 @@
+//! Pomodoro sessions: work intervals alternating with short breaks, and a long break
+//! after every few work intervals (lengths in `settings.pomodoro`).
+//!
+//! The backend runs the clock, sends a notification at every transition and shows the
+//! countdown in the tray. A session can be bound to a habit; each finished work interval
+//! then logs progress: its length for habits with a time target, one unit for other
+//! quantified habits ("4 pomodoros"), and a check-off for plain ones.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::i18n::t;
+use crate::model::Habit;
+use crate::settings::{PomodoroSettings, SettingsStore};
+use crate::stats::today;
+use crate::storage::Storage;
+
+/// Emitted with `get_pomodoro`'s payload whenever the session changes phase or state.
+pub const POMODORO_CHANGED_EVENT: &str = "pomodoro:changed";
+
+const TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    fn minutes(self, settings: &PomodoroSettings) -> u32 {
+        match self {
+            Phase::Work => settings.work_minutes,
+            Phase::ShortBreak => settings.short_break_minutes,
+            Phase::LongBreak => settings.long_break_minutes,
+        }
+        .max(1)
+    }
+}
+
+struct Session {
+    habit_id: Option<String>,
+    phase: Phase,
+    length: Duration,
+    /// Set while running.
+    running_since: Option<Instant>,
+    /// Time spent in the phase before the last pause.
+    banked: Duration,
+    /// Work intervals finished so far.
+    completed: u32,
+    settings: PomodoroSettings,
+}
+
+impl Session {
+    fn elapsed(&self) -> Duration {
+        self.banked + self.running_since.map_or(Duration::ZERO, |s| s.elapsed())
+    }
+
+    fn remaining(&self) -> Duration {
+        self.length.saturating_sub(self.elapsed())
+    }
+
+    fn enter(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.length = Duration::from_secs(u64::from(phase.minutes(&self.settings)) * 60);
+        self.banked = Duration::ZERO;
+        self.running_since = Some(Instant::now());
+    }
+
+    /// The phase after the current one, counting a finished work interval.
+    fn advance(&mut self) {
+        let next = match self.phase {
+            Phase::Work => {
+                self.completed += 1;
+                let every = self.settings.sessions_before_long_break.max(1);
+                if self.completed % every == 0 {
+                    Phase::LongBreak
+                } else {
+                    Phase::ShortBreak
+                }
+            }
+            Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+        };
+        self.enter(next);
+    }
+}
+
+#[derive(Default)]
+pub struct Pomodoro {
+    session: Mutex<Option<Session>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PomodoroStatus {
+    pub habit_id: Option<String>,
+    pub phase: Phase,
+    pub running: bool,
+    pub remaining_secs: u64,
+    pub completed: u32,
+}
+
+/// What a tick changed, for the caller to act on outside the lock.
+pub(crate) enum Transition {
+    None,
+    /// A work interval finished; its habit (if any) gets progress logged.
+    WorkDone {
+        habit_id: Option<String>,
+        minutes: u32,
+    },
+    BreakDone,
+}
+
+impl Pomodoro {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Session>> {
+        self.session.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn status(&self) -> Option<PomodoroStatus> {
+        self.lock().as_ref().map(|s| PomodoroStatus {
+            habit_id: s.habit_id.clone(),
+            phase: s.phase,
+            running: s.running_since.is_some(),
+            remaining_secs: s.remaining().as_secs(),
+            completed: s.completed,
+        })
+    }
+
+    /// Starts a new session with a work interval, replacing any current one.
+    pub fn start(&self, habit_id: Option<String>, settings: PomodoroSettings) {
+        let mut session = Session {
+            habit_id,
+            phase: Phase::Work,
+            length: Duration::ZERO,
+            running_since: None,
+            banked: Duration::ZERO,
+            completed: 0,
+            settings,
+        };
+        session.enter(Phase::Work);
+        *self.lock() = Some(session);
+    }
+
+    /// Pauses or resumes. Returns false without a session.
+    pub fn set_running(&self, running: bool) -> bool {
+        let mut guard = self.lock();
+        let Some(session) = guard.as_mut() else {
+            return false;
+        };
+        match (running, session.running_since) {
+            (true, None) => session.running_since = Some(Instant::now()),
+            (false, Some(since)) => {
+                session.banked += since.elapsed();
+                session.running_since = None;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Returns false without a session.
+    pub fn stop(&self) -> bool {
+        self.lock().take().is_some()
+    }
+
+    /// Moves on to the next phase early; skipped work doesn't count.
+    pub fn skip(&self) -> bool {
+        let mut guard = self.lock();
+        let Some(session) = guard.as_mut() else {
+            return false;
+        };
+        let next = match session.phase {
+            Phase::Work => Phase::ShortBreak,
+            Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+        };
+        session.enter(next);
+        true
+    }
+
+    /// Moves on if the phase has run out. Ticking again catches up on any later phases
+    /// that ran out too.
+    pub(crate) fn tick(&self) -> Transition {
+        let mut guard = self.lock();
+        let Some(session) = guard.as_mut() else {
+            return Transition::None;
+        };
+        if session.running_since.is_none() || !session.remaining().is_zero() {
+            return Transition::None;
+        }
+        let finished = session.phase;
+        let minutes = finished.minutes(&session.settings);
+        let overshoot = session.elapsed().saturating_sub(session.length);
+        session.advance();
+        // The next phase started when this one ran out, not at this tick.
+        session.running_since = Instant::now()
+            .checked_sub(overshoot)
+            .or(session.running_since);
+        match finished {
+            Phase::Work => Transition::WorkDone {
+                habit_id: session.habit_id.clone(),
+                minutes,
+            },
+            Phase::ShortBreak | Phase::LongBreak => Transition::BreakDone,
+        }
+    }
+}
+
+/// `MM:SS` with a marker for the phase, for the tray.
+fn countdown(status: &PomodoroStatus) -> String {
+    let marker = match (status.running, status.phase) {
+        (false, _) => "⏸",
+        (true, Phase::Work) => "🍅",
+        (true, _) => "☕",
+    };
+    let secs = status.remaining_secs;
+    format!("{marker} {:02}:{:02}", secs / 60, secs % 60)
+}
+
+fn changed(app: &AppHandle) {
+    let status = app.state::<Pomodoro>().status();
+    crate::tray::set_status(app, status.as_ref().map(countdown).as_deref());
+    let _ = app.emit(POMODORO_CHANGED_EVENT, status);
+}
+
+/// What a finished work interval adds to `habit`: its length for a time target, one unit
+/// for other targets, and `None` for plain habits, which get checked off instead.
+pub(crate) fn progress(habit: &Habit, minutes: u32) -> Option<f64> {
+    let target = habit.target.as_ref()?;
+    Some(match crate::timers::unit_seconds(&target.unit) {
+        Some(unit) => f64::from(minutes) * 60.0 / unit,
+        None => 1.0,
+    })
+}
+
+/// Logs a finished work interval against the bound habit.
+pub(crate) fn log_progress(app: &AppHandle, habit_id: &str, minutes: u32) -> Result<(), String> {
+    let Some(habit) = app
+        .state::<Storage>()
+        .get_habit(habit_id)
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(());
+    };
+    let date = today();
+    match progress(&habit, minutes) {
+        Some(amount) => {
+            crate::habits::log_value(app, habit_id, date, amount, Vec::new())
+                .map_err(|e| e.to_string())?;
+        }
+        None => {
+            crate::habits::set_completion(app, habit_id, date, true).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+/// Drives phase transitions and the tray countdown.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            interval.tick().await;
+            let pomodoro = app.state::<Pomodoro>();
+            match pomodoro.tick() {
+                Transition::None => {}
+                Transition::WorkDone { habit_id, minutes } => {
+                    crate::reminders::notify(
+                        &app,
+                        &t("pomodoro-done-title"),
+                        &t("pomodoro-done-body"),
+                    );
+                    if let Some(habit_id) = habit_id {
+                        if let Err(e) = log_progress(&app, &habit_id, minutes) {
+                            log::warn!("pomodoro: failed to log progress: {e}");
+                        }
+                    }
+                    changed(&app);
+                }
+                Transition::BreakDone => {
+                    crate::reminders::notify(&app, &t("break-over-title"), &t("break-over-body"));
+                    changed(&app);
+                }
+            }
+            if let Some(status) = pomodoro.status().filter(|s| s.running) {
+                crate::tray::set_status(&app, Some(&countdown(&status)));
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_pomodoro(pomodoro: State<'_, Pomodoro>) -> Option<PomodoroStatus> {
+    pomodoro.status()
+}
+
+/// Starts a session, optionally bound to a habit that finished work intervals count toward.
+#[tauri::command]
+pub fn start_pomodoro(
+    app: AppHandle,
+    habit_id: Option<String>,
+) -> Result<Option<PomodoroStatus>, AppError> {
+    if app.state::<crate::focus::Focus>().is_active() {
+        return Err(AppError::Invalid(
+            "a focus session is running; stop it first".into(),
+        ));
+    }
+    let status = start_in(
+        &app.state::<Storage>(),
+        &app.state::<SettingsStore>(),
+        &app.state::<Pomodoro>(),
+        habit_id,
+    )?;
+    changed(&app);
+    Ok(status)
+}
+
+/// Starts a session with the configured lengths, after checking `habit_id` exists.
+pub fn start_in(
+    storage: &Storage,
+    settings: &SettingsStore,
+    pomodoro: &Pomodoro,
+    habit_id: Option<String>,
+) -> Result<Option<PomodoroStatus>, AppError> {
+    if let Some(id) = &habit_id {
+        if storage.get_habit(id)?.is_none() {
+            return Err(AppError::not_found("habit", id));
+        }
+    }
+    pomodoro.start(habit_id, settings.get().pomodoro);
+    Ok(pomodoro.status())
+}
+
+#[tauri::command]
+pub fn pause_pomodoro(app: AppHandle) -> Result<Option<PomodoroStatus>, AppError> {
+    set_running(&app, false)
+}
+
+#[tauri::command]
+pub fn resume_pomodoro(app: AppHandle) -> Result<Option<PomodoroStatus>, AppError> {
+    set_running(&app, true)
+}
+
+fn set_running(app: &AppHandle, running: bool) -> Result<Option<PomodoroStatus>, AppError> {
+    let status = set_running_in(&app.state::<Pomodoro>(), running)?;
+    changed(app);
+    Ok(status)
+}
+
+fn no_session() -> AppError {
+    AppError::Invalid("no Pomodoro session".into())
+}
+
+/// Pauses or resumes the session.
+pub fn set_running_in(
+    pomodoro: &Pomodoro,
+    running: bool,
+) -> Result<Option<PomodoroStatus>, AppError> {
+    if !pomodoro.set_running(running) {
+        return Err(no_session());
+    }
+    Ok(pomodoro.status())
+}
+
+/// Moves on to the next phase.
+pub fn skip_in(pomodoro: &Pomodoro) -> Result<Option<PomodoroStatus>, AppError> {
+    if !pomodoro.skip() {
+        return Err(no_session());
+    }
+    Ok(pomodoro.status())
+}
+
+pub fn stop_in(pomodoro: &Pomodoro) -> Result<(), AppError> {
+    if !pomodoro.stop() {
+        return Err(no_session());
+    }
+    Ok(())
+}
+
+/// Ends the current phase early without counting it.
+#[tauri::command]
+pub fn skip_pomodoro_phase(app: AppHandle) -> Result<Option<PomodoroStatus>, AppError> {
+    let status = skip_in(&app.state::<Pomodoro>())?;
+    changed(&app);
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn stop_pomodoro(app: AppHandle) -> Result<(), AppError> {
+    stop_in(&app.state::<Pomodoro>())?;
+    changed(&app);
+    Ok(())
+}
@@ -0,0 +1,17 @@
+//! Privacy mode (`settings.privacy.hideHabitNames`) for shared or screen-shared machines:
+//! notifications and the tray say how many habits instead of which ones, e.g. "1 habit
+//! due" rather than "Therapy exercises due". The webview itself is unaffected; the app
+//! lock (`lock.rs`) is what keeps it closed.
+
+use tauri::{AppHandle, Manager};
+
+use crate::settings::SettingsStore;
+
+pub fn hide_names(app: &AppHandle) -> bool {
+    app.state::<SettingsStore>().get().privacy.hide_habit_names
+}
+
+/// "1 habit", "3 habits".
+pub fn habits(count: usize) -> String {
+    crate::i18n::t_args("habit-count", &[("count", count.into())])
+}
@@ -0,0 +1,167 @@
+//! QR codes as PNG images, so the webview can show one without a QR library of its own:
+//! template links for friends to scan (`templates.rs`), or anything else passed to
+//! `generate_qr`.
+//!
+//! Encoding is the `qrcode` crate's, at any of the four error correction levels and in the
+//! smallest version the payload fits; drawing the PNG is done here.
+
+use std::io::Write;
+
+use base64::Engine;
+use qrcode::types::{Color, QrError};
+use qrcode::{EcLevel, QrCode, Version};
+use serde::{Deserialize, Serialize};
+
+use crate::error::AppError;
+
+/// Blank modules around the code, as the standard asks for.
+const QUIET_ZONE: usize = 4;
+const DEFAULT_SCALE: u32 = 8;
+const MAX_SCALE: u32 = 32;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QrLevel {
+    /// Survives about 7% damage.
+    Low,
+    /// 15%; plenty for a code on a screen.
+    #[default]
+    Medium,
+    Quartile,
+    /// 30%, for printing.
+    High,
+}
+
+impl QrLevel {
+    fn ec_level(self) -> EcLevel {
+        match self {
+            Self::Low => EcLevel::L,
+            Self::Medium => EcLevel::M,
+            Self::Quartile => EcLevel::Q,
+            Self::High => EcLevel::H,
+        }
+    }
+}
+
+/// A generated code, ready to show.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QrImage {
+    /// `data:image/png;base64,…`
+    pub data_url: String,
+    pub version: u8,
+    /// Modules per side, without the quiet zone.
+    pub modules: usize,
+    /// Pixels per side.
+    pub pixels: usize,
+}
+
+/// Modules that make up the drawn code; `true` is dark.
+struct Matrix {
+    size: usize,
+    dark: Vec<bool>,
+}
+
+impl Matrix {
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.dark[y * self.size + x]
+    }
+}
+
+/// The module matrix for `payload`, in the smallest version it fits.
+fn encode(payload: &[u8], level: QrLevel) -> Result<(Matrix, u8), String> {
+    let code =
+        QrCode::with_error_correction_level(payload, level.ec_level()).map_err(|e| match e {
+            QrError::DataTooLong => format!(
+                "{} bytes is too much for a QR code at this error correction level",
+                payload.len()
+            ),
+            e => format!("can't make a QR code: {e}"),
+        })?;
+    let version = match code.version() {
+        Version::Normal(v) => v as u8,
+        Version::Micro(v) => v as u8,
+    };
+    let matrix = Matrix {
+        size: code.width(),
+        dark: code
+            .to_colors()
+            .into_iter()
+            .map(|c| c == Color::Dark)
+            .collect(),
+    };
+    Ok((matrix, version))
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend((data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend(kind);
+    out.extend(data);
+    let crc = crc32fast::hash(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+/// A black-on-white 8-bit grayscale PNG, `scale` pixels per module.
+fn png(matrix: &Matrix, scale: usize) -> Result<(Vec<u8>, usize), String> {
+    let side = (matrix.size + QUIET_ZONE * 2) * scale;
+    let mut pixels = Vec::with_capacity((side + 1) * side);
+    for py in 0..side {
+        // No filter on any scanline.
+        pixels.push(0);
+        for px in 0..side {
+            let (x, y) = (
+                (px / scale).wrapping_sub(QUIET_ZONE),
+                (py / scale).wrapping_sub(QUIET_ZONE),
+            );
+            let dark = x < matrix.size && y < matrix.size && matrix.get(x, y);
+            pixels.push(if dark { 0 } else { 255 });
+        }
+    }
+    let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    zlib.write_all(&pixels).map_err(|e| e.to_string())?;
+    let compressed = zlib.finish().map_err(|e| e.to_string())?;
+
+    let mut out = b"\x89PNG\r\n\x1a\n".to_vec();
+    let mut header = Vec::with_capacity(13);
+    header.extend((side as u32).to_be_bytes());
+    header.extend((side as u32).to_be_bytes());
+    // 8-bit grayscale, deflate, adaptive filtering, no interlace.
+    header.extend([8, 0, 0, 0, 0]);
+    png_chunk(&mut out, b"IHDR", &header);
+    png_chunk(&mut out, b"IDAT", &compressed);
+    png_chunk(&mut out, b"IEND", &[]);
+    Ok((out, side))
+}
+
+/// `payload` as a QR code image. `scale` is pixels per module, 8 when left out.
+pub fn generate(payload: &str, scale: Option<u32>, level: QrLevel) -> Result<QrImage, String> {
+    if payload.is_empty() {
+        return Err("there's nothing to put in the QR code".into());
+    }
+    let scale = scale.unwrap_or(DEFAULT_SCALE);
+    if !(1..=MAX_SCALE).contains(&scale) {
+        return Err(format!("scale must be between 1 and {MAX_SCALE}"));
+    }
+    let (matrix, version) = encode(payload.as_bytes(), level)?;
+    let (bytes, pixels) = png(&matrix, scale as usize)?;
+    Ok(QrImage {
+        data_url: format!(
+            "data:image/png;base64,{}",
+            base64::engine::general_purpose::STANDARD.encode(bytes)
+        ),
+        version,
+        modules: matrix.size,
+        pixels,
+    })
+}
+
+/// Renders `payload` (a link, a pairing code, any text) as a QR code PNG.
+#[tauri::command]
+pub fn generate_qr(
+    payload: String,
+    scale: Option<u32>,
+    level: Option<QrLevel>,
+) -> Result<QrImage, AppError> {
+    Ok(generate(&payload, scale, level.unwrap_or_default())?)
+}
@@ -0,0 +1,814 @@
+//! Quick add: turns a line like "meditate every weekday at 7am for 10 min #health" into a
+//! habit draft the form can be prefilled with.
+//!
+//! Phrases the parser recognizes anywhere in the text:
+//! - schedule: "daily", "every day", "every other day", "every 3 days", "every weekday",
+//!   "on weekends", "every mon and thu", "3 times a week", "twice a month", "weekly"
+//! - repetitions: "3 times a day"
+//! - reminder: "at 7am", "at 7:30 pm", "at 19:00", "at 19h30", "at noon", "every morning"
+//! - target: "for 10 min", "for 1 hour", "for 20 pages", "15min"
+//! - start: "starting tomorrow", "from monday", "from 2026-11-02", "starting 3/11"
+//! - tags: "#health"
+//!
+//! Whatever is left is the name. Numeric dates follow the locale: month first for US
+//! English, day first everywhere else. Times are taken in 12- or 24-hour form either way.
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::model::{HabitFrequency, HabitTarget};
+use crate::stats::{date_key, today};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuickAddDraft {
+    pub name: String,
+    pub frequency: HabitFrequency,
+    /// RFC 5545 recurrence rule of the schedule. "3 times a week" has no rule of its own,
+    /// so it's `FREQ=WEEKLY` and the count is in `frequency.goal`.
+    pub rrule: String,
+    /// Days the habit is scheduled on, 0 (Sunday) to 6, when the text named any.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub weekdays: Vec<u8>,
+    pub times_per_day: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reminder_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<HabitTarget>,
+    pub tags: Vec<String>,
+    /// `yyyy-MM-dd`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Repeat {
+    Daily,
+    EveryNDays(u32),
+    Weekly(u32),
+    Monthly(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Per {
+    Day,
+    Week,
+    Month,
+}
+
+/// The locale to read dates in: `locale`, else the environment's, else US English.
+fn month_first(locale: Option<&str>) -> bool {
+    let locale = locale
+        .map(str::to_string)
+        .or_else(|| {
+            ["LC_ALL", "LC_TIME", "LANG"]
+                .iter()
+                .filter_map(|v| std::env::var(v).ok())
+                .find(|v| !v.is_empty() && v != "C" && v != "POSIX")
+        })
+        .unwrap_or_else(|| "en-US".into());
+    let locale = locale.to_lowercase().replace('_', "-");
+    let locale = locale.split('.').next().unwrap_or_default();
+    matches!(locale, "en-us" | "en-ph" | "en" | "fil-ph")
+}
+
+fn number(word: &str) -> Option<u32> {
+    let n = match word {
+        "one" | "a" | "an" => 1,
+        "two" => 2,
+        "three" => 3,
+        "four" => 4,
+        "five" => 5,
+        "six" => 6,
+        "seven" => 7,
+        "eight" => 8,
+        "nine" => 9,
+        "ten" => 10,
+        _ => return word.parse().ok(),
+    };
+    Some(n)
+}
+
+fn weekday(word: &str) -> Option<Weekday> {
+    let day = match word {
+        "mon" | "monday" | "mondays" => Weekday::Mon,
+        "tue" | "tues" | "tuesday" | "tuesdays" => Weekday::Tue,
+        "wed" | "wednesday" | "wednesdays" => Weekday::Wed,
+        "thu" | "thur" | "thurs" | "thursday" | "thursdays" => Weekday::Thu,
+        "fri" | "friday" | "fridays" => Weekday::Fri,
+        "sat" | "saturday" | "saturdays" => Weekday::Sat,
+        "sun" | "sunday" | "sundays" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(day)
+}
+
+fn per(word: &str) -> Option<Per> {
+    match word {
+        "day" | "daily" => Some(Per::Day),
+        "week" | "weekly" => Some(Per::Week),
+        "month" | "monthly" => Some(Per::Month),
+        _ => None,
+    }
+}
+
+/// `7`, `7am`, `7:30`, `7.30pm`, `19h`, `19h30`; `meridiem` is a separate "am"/"pm".
+fn clock(word: &str, meridiem: Option<&str>) -> Option<String> {
+    let (word, suffix) = match word
+        .strip_suffix("am")
+        .or_else(|| word.strip_suffix("a.m."))
+    {
+        Some(w) => (w, Some("am")),
+        None => match word
+            .strip_suffix("pm")
+            .or_else(|| word.strip_suffix("p.m."))
+        {
+            Some(w) => (w, Some("pm")),
+            None => (word, meridiem),
+        },
+    };
+    let (hour, minute) = match word.split_once([':', '.', 'h']) {
+        Some((h, "")) => (h, "0"),
+        Some((h, m)) if m.len() == 2 => (h, m),
+        Some(_) => return None,
+        None => (word, "0"),
+    };
+    let (mut hour, minute) = (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?);
+    if minute > 59 {
+        return None;
+    }
+    match suffix {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some("am") if hour == 12 => hour = 0,
+        Some("pm") if hour != 12 => hour += 12,
+        _ if hour > 23 => return None,
+        _ => {}
+    }
+    Some(format!("{hour:02}:{minute:02}"))
+}
+
+fn looks_like_time(word: &str) -> bool {
+    word.starts_with(|c: char| c.is_ascii_digit())
+        && (word.contains(':') || word.ends_with("am") || word.ends_with("pm"))
+}
+
+fn duration_unit(word: &str) -> Option<f64> {
+    match word {
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(1.0),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(60.0),
+        _ => None,
+    }
+}
+
+/// `15min` -> (15, "min")
+fn split_amount(word: &str) -> Option<(f64, &str)> {
+    let at = word.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+    let (amount, unit) = word.split_at(at);
+    Some((amount.parse().ok()?, unit))
+}
+
+fn date(word: &str, today: NaiveDate, month_first: bool) -> Option<NaiveDate> {
+    match word {
+        "today" => return Some(today),
+        "tomorrow" => return today.succ_opt(),
+        _ => {}
+    }
+    if let Some(day) = weekday(word) {
+        let ahead = (7 + day.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+        return today.checked_add_days(Days::new(ahead.into()));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(word, "%Y-%m-%d") {
+        return Some(date);
+    }
+    let parts: Vec<u32> = word
+        .trim_end_matches('.')
+        .split(['/', '.'])
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let (a, b, year) = match parts[..] {
+        [a, b] => (a, b, None),
+        [a, b, y] if y < 100 => (a, b, Some(2000 + y as i32)),
+        [a, b, y] => (a, b, Some(y as i32)),
+        _ => return None,
+    };
+    // Dotted dates ("3.11.") are day first in every locale that writes them.
+    let (month, day) = if month_first && !word.contains('.') {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    match year {
+        Some(year) => NaiveDate::from_ymd_opt(year, month, day),
+        None => {
+            let this_year = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+            if this_year >= today {
+                Some(this_year)
+            } else {
+                NaiveDate::from_ymd_opt(today.year() + 1, month, day)
+            }
+        }
+    }
+}
+
+struct Parser {
+    words: Vec<String>,
+    used: Vec<bool>,
+    repeat: Option<Repeat>,
+    weekdays: Vec<Weekday>,
+    times_per_day: u32,
+    reminder_time: Option<String>,
+    target: Option<HabitTarget>,
+    tags: Vec<String>,
+    start: Option<NaiveDate>,
+}
+
+impl Parser {
+    fn word(&self, i: usize) -> &str {
+        self.words.get(i).map_or("", String::as_str)
+    }
+
+    /// "mon and wed", "mon wed fri", "weekdays"; returns the days and words used.
+    fn weekday_list(&self, i: usize) -> Option<(Vec<Weekday>, usize)> {
+        match self.word(i) {
+            "weekday" | "weekdays" => {
+                return Some((
+                    vec![
+                        Weekday::Mon,
+                        Weekday::Tue,
+                        Weekday::Wed,
+                        Weekday::Thu,
+                        Weekday::Fri,
+                    ],
+                    1,
+                ))
+            }
+            "weekend" | "weekends" => return Some((vec![Weekday::Sat, Weekday::Sun], 1)),
+            _ => {}
+        }
+        let mut days = vec![weekday(self.word(i))?];
+        let mut len = 1;
+        loop {
+            let gap = usize::from(matches!(self.word(i + len), "and" | "&"));
+            match weekday(self.word(i + len + gap)) {
+                Some(day) => {
+                    days.push(day);
+                    len += gap + 1;
+                }
+                None => return Some((days, len)),
+            }
+        }
+    }
+
+    /// "3 times", "3x", "3 x", "twice"
+    fn count(&self, i: usize) -> Option<(u32, usize)> {
+        match self.word(i) {
+            "once" => return Some((1, 1)),
+            "twice" => return Some((2, 1)),
+            "thrice" => return Some((3, 1)),
+            _ => {}
+        }
+        if let Some(n) = self.word(i).strip_suffix('x').and_then(|n| n.parse().ok()) {
+            return Some((n, 1));
+        }
+        let n = number(self.word(i)).filter(|_| !matches!(self.word(i), "a" | "an"))?;
+        matches!(self.word(i + 1), "times" | "time" | "x").then_some((n, 2))
+    }
+
+    /// "a week", "per day", "each month", "weekly"
+    fn period(&self, i: usize) -> Option<(Per, usize)> {
+        if let Some(p) = per(self.word(i)).filter(|_| self.word(i).ends_with("ly")) {
+            return Some((p, 1));
+        }
+        if matches!(self.word(i), "a" | "an" | "per" | "each" | "every") {
+            return per(self.word(i + 1)).map(|p| (p, 2));
+        }
+        None
+    }
+
+    /// A time starting at `i`, after "at" (where a bare hour is fine) or on its own.
+    fn time(&self, i: usize, after_at: bool) -> Option<(String, usize)> {
+        let meridiem = match self.word(i + 1) {
+            "am" | "a.m." => Some("am"),
+            "pm" | "p.m." => Some("pm"),
+            _ => None,
+        };
+        match self.word(i) {
+            "noon" | "midday" => return Some(("12:00".into(), 1)),
+            "midnight" => return Some(("00:00".into(), 1)),
+            word if !(after_at
+                || looks_like_time(word)
+                || (word.parse::<u32>().is_ok() && meridiem.is_some())) =>
+            {
+                return None
+            }
+            _ => {}
+        }
+        if let Some(time) = meridiem.and_then(|m| clock(self.word(i), Some(m))) {
+            return Some((time, 2));
+        }
+        clock(self.word(i), None).map(|t| (t, 1))
+    }
+
+    /// Tries every phrase at `i`; returns how many words it used.
+    fn phrase(&mut self, i: usize, today: NaiveDate, month_first: bool) -> Option<usize> {
+        let word = self.word(i).to_string();
+        if let Some(tag) = word.strip_prefix('#').filter(|t| !t.is_empty()) {
+            self.tags.push(tag.to_string());
+            return Some(1);
+        }
+        match word.as_str() {
+            "daily" | "everyday" => {
+                self.repeat = Some(Repeat::Daily);
+                return Some(1);
+            }
+            "weekly" => {
+                self.repeat = Some(Repeat::Weekly(1));
+                return Some(1);
+            }
+            "monthly" => {
+                self.repeat = Some(Repeat::Monthly(1));
+                return Some(1);
+            }
+            "every" | "each" => return self.every(i + 1).map(|len| len + 1),
+            "on" => {
+                let (days, len) = self.weekday_list(i + 1)?;
+                self.weekdays = days;
+                return Some(len + 1);
+            }
+            "at" => {
+                let (time, len) = self.time(i + 1, true)?;
+                self.reminder_time = Some(time);
+                return Some(len + 1);
+            }
+            "for" => return self.amount(i + 1, true).map(|len| len + 1),
+            "starting" | "from" | "beginning" | "start" => {
+                let skip = usize::from(self.word(i + 1) == "on");
+                self.start = Some(date(self.word(i + 1 + skip), today, month_first)?);
+                return Some(skip + 2);
+            }
+            _ => {}
+        }
+        if let Some((n, len)) = self.count(i) {
+            if let Some((per, more)) = self.period(i + len) {
+                match per {
+                    Per::Day => self.times_per_day = n,
+                    Per::Week => self.repeat = Some(Repeat::Weekly(n)),
+                    Per::Month => self.repeat = Some(Repeat::Monthly(n)),
+                }
+                return Some(len + more);
+            }
+        }
+        // Plural day names stand on their own: "mondays and thursdays".
+        if word.ends_with('s') {
+            if let Some((days, len)) = self.weekday_list(i) {
+                self.weekdays = days;
+                return Some(len);
+            }
+        }
+        if let Some((time, len)) = self.time(i, false) {
+            self.reminder_time = Some(time);
+            return Some(len);
+        }
+        self.amount(i, false)
+    }
+
+    /// After "every": "day", "other day", "3 days", "week", "weekday", "mon and thu",
+    /// "morning".
+    fn every(&mut self, i: usize) -> Option<usize> {
+        let word = self.word(i).to_string();
+        let (repeat, len) = match word.as_str() {
+            "day" => (Repeat::Daily, 1),
+            "week" => (Repeat::Weekly(1), 1),
+            "month" => (Repeat::Monthly(1), 1),
+            "other" if self.word(i + 1) == "day" => (Repeat::EveryNDays(2), 2),
+            "morning" | "evening" | "night" => {
+                let time = match word.as_str() {
+                    "morning" => "08:00",
+                    "evening" => "19:00",
+                    _ => "21:00",
+                };
+                self.reminder_time.get_or_insert_with(|| time.into());
+                (Repeat::Daily, 1)
+            }
+            _ => match number(&word).filter(|_| self.word(i + 1) == "days") {
+                Some(n) if n > 1 => (Repeat::EveryNDays(n), 2),
+                Some(_) => (Repeat::Daily, 2),
+                None => {
+                    let (days, len) = self.weekday_list(i)?;
+                    self.weekdays = days;
+                    return Some(len);
+                }
+            },
+        };
+        self.repeat = Some(repeat);
+        Some(len)
+    }
+
+    /// "10 min", "1 hour", "15min"; after "for" any unit ("20 pages") is taken.
+    fn amount(&mut self, i: usize, after_for: bool) -> Option<usize> {
+        let (amount, unit, len) = match split_amount(self.word(i)) {
+            Some((amount, unit)) if !unit.is_empty() => (amount, unit.to_string(), 1),
+            _ => {
+                let amount = self.word(i).parse::<f64>().ok()?;
+                (amount, self.word(i + 1).to_string(), 2)
+            }
+        };
+        let target = match duration_unit(&unit) {
+            Some(minutes) => HabitTarget {
+                amount: amount * minutes,
+                unit: "min".into(),
+            },
+            None if after_for && unit.chars().all(char::is_alphabetic) && !unit.is_empty() => {
+                HabitTarget { amount, unit }
+            }
+            None => return None,
+        };
+        if !target.amount.is_finite() || target.amount <= 0.0 {
+            return None;
+        }
+        self.target = Some(target);
+        Some(len)
+    }
+}
+
+fn rrule(repeat: Repeat, weekdays: &[Weekday], time: Option<&str>) -> String {
+    let mut rule = match repeat {
+        _ if !weekdays.is_empty() => format!(
+            "FREQ=WEEKLY;BYDAY={}",
+            weekdays
+                .iter()
+                .map(|d| d.to_string().to_uppercase()[..2].to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        Repeat::Daily => "FREQ=DAILY".into(),
+        Repeat::EveryNDays(n) => format!("FREQ=DAILY;INTERVAL={n}"),
+        Repeat::Weekly(_) => "FREQ=WEEKLY".into(),
+        Repeat::Monthly(_) => "FREQ=MONTHLY".into(),
+    };
+    if let Some((hour, minute)) = time.and_then(|t| t.split_once(':')) {
+        let hour: u32 = hour.parse().unwrap_or_default();
+        let minute: u32 = minute.parse().unwrap_or_default();
+        rule.push_str(&format!(";BYHOUR={hour};BYMINUTE={minute}"));
+    }
+    rule
+}
+
+const FILLER: [&str; 8] = ["and", "at", "for", "on", "every", "each", "the", "to"];
+
+pub fn parse(
+    text: &str,
+    locale: Option<&str>,
+    today: NaiveDate,
+) -> Result<QuickAddDraft, AppError> {
+    let originals: Vec<&str> = text.split_whitespace().collect();
+    let words: Vec<String> = originals
+        .iter()
+        .map(|w| w.trim_end_matches([',', ';', '!', '?']).to_lowercase())
+        .collect();
+    let month_first = month_first(locale);
+    let mut parser = Parser {
+        used: vec![false; words.len()],
+        words,
+        repeat: None,
+        weekdays: Vec::new(),
+        times_per_day: 1,
+        reminder_time: None,
+        target: None,
+        tags: Vec::new(),
+        start: None,
+    };
+    let mut i = 0;
+    while i < parser.words.len() {
+        match parser.phrase(i, today, month_first) {
+            Some(len) => {
+                let end = (i + len).min(parser.used.len());
+                parser.used[i..end].fill(true);
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+
+    let mut name: Vec<&str> = originals
+        .iter()
+        .zip(&parser.used)
+        .filter(|(_, used)| !**used)
+        .map(|(w, _)| w.trim_end_matches([',', ';']))
+        .collect();
+    while name
+        .first()
+        .is_some_and(|w| FILLER.contains(&w.to_lowercase().as_str()))
+    {
+        name.remove(0);
+    }
+    while name
+        .last()
+        .is_some_and(|w| FILLER.contains(&w.to_lowercase().as_str()))
+    {
+        name.pop();
+    }
+    let name = name.join(" ");
+    let mut chars = name.chars();
+    let name = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+        None => {
+            return Err(AppError::Invalid(format!(
+                "couldn't find a habit name in {text:?}"
+            )))
+        }
+    };
+
+    let mut weekdays = parser.weekdays;
+    weekdays.sort_by_key(|d| d.num_days_from_sunday());
+    weekdays.dedup();
+    let repeat = parser.repeat.unwrap_or(Repeat::Daily);
+    let frequency = match repeat {
+        _ if weekdays.len() == 7 => ("daily", 1),
+        _ if !weekdays.is_empty() => ("weekly", weekdays.len() as u32),
+        Repeat::Daily => ("daily", 1),
+        Repeat::EveryNDays(n) => ("interval", n),
+        Repeat::Weekly(n) => ("weekly", n.clamp(1, 7)),
+        Repeat::Monthly(n) => ("monthly", n.clamp(1, 31)),
+    };
+    Ok(QuickAddDraft {
+        name,
+        rrule: rrule(repeat, &weekdays, parser.reminder_time.as_deref()),
+        frequency: HabitFrequency {
+            kind: frequency.0.into(),
+            goal: frequency.1,
+        },
+        weekdays: weekdays
+            .iter()
+            .map(|d| d.num_days_from_sunday() as u8)
+            .collect(),
+        times_per_day: parser
+            .times_per_day
+            .clamp(1, crate::repeats::MAX_TIMES_PER_DAY),
+        reminder_time: parser.reminder_time,
+        target: parser.target,
+        tags: crate::tags::normalize(parser.tags),
+        start: parser.start.map(date_key),
+    })
+}
+
+/// Parses a quick-add line into a draft; nothing is created. `locale` (e.g. "en-GB")
+/// decides how numeric dates are read and defaults to the system's.
+#[tauri::command]
+pub fn parse_quick_add(text: String, locale: Option<String>) -> Result<QuickAddDraft, AppError> {
+    parse(&text, locale.as_deref(), today())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A Monday.
+    const TODAY: NaiveDate = match NaiveDate::from_ymd_opt(2024, 6, 10) {
+        Some(date) => date,
+        None => panic!(),
+    };
+
+    fn draft(text: &str) -> QuickAddDraft {
+        parse(text, Some("en-US"), TODAY).unwrap_or_else(|e| panic!("{text:?}: {e}"))
+    }
+
+    fn target(amount: f64, unit: &str) -> Option<HabitTarget> {
+        Some(HabitTarget {
+            amount,
+            unit: unit.into(),
+        })
+    }
+
+    #[test]
+    fn schedules() {
+        // Text, name, frequency, goal, rule and weekdays.
+        type Case = (
+            &'static str,
+            &'static str,
+            &'static str,
+            u32,
+            &'static str,
+            &'static [u8],
+        );
+        let cases: [Case; 11] = [
+            ("read daily", "Read", "daily", 1, "FREQ=DAILY", &[]),
+            ("read", "Read", "daily", 1, "FREQ=DAILY", &[]),
+            (
+                "water plants every other day",
+                "Water plants",
+                "interval",
+                2,
+                "FREQ=DAILY;INTERVAL=2",
+                &[],
+            ),
+            (
+                "run every 3 days",
+                "Run",
+                "interval",
+                3,
+                "FREQ=DAILY;INTERVAL=3",
+                &[],
+            ),
+            (
+                "stretch every weekday",
+                "Stretch",
+                "weekly",
+                5,
+                "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR",
+                &[1, 2, 3, 4, 5],
+            ),
+            (
+                "hike on weekends",
+                "Hike",
+                "weekly",
+                2,
+                "FREQ=WEEKLY;BYDAY=SU,SA",
+                &[0, 6],
+            ),
+            (
+                "gym every mon and thu",
+                "Gym",
+                "weekly",
+                2,
+                "FREQ=WEEKLY;BYDAY=MO,TH",
+                &[1, 4],
+            ),
+            (
+                "piano mondays and thursdays",
+                "Piano",
+                "weekly",
+                2,
+                "FREQ=WEEKLY;BYDAY=MO,TH",
+                &[1, 4],
+            ),
+            (
+                "swim 3 times a week",
+                "Swim",
+                "weekly",
+                3,
+                "FREQ=WEEKLY",
+                &[],
+            ),
+            (
+                "call mom twice a month",
+                "Call mom",
+                "monthly",
+                2,
+                "FREQ=MONTHLY",
+                &[],
+            ),
+            (
+                "floss 9 times a week",
+                "Floss",
+                "weekly",
+                7,
+                "FREQ=WEEKLY",
+                &[],
+            ),
+        ];
+        for (text, name, kind, goal, rrule, weekdays) in cases {
+            let draft = draft(text);
+            assert_eq!(draft.name, name, "{text:?}");
+            assert_eq!(draft.frequency.kind, kind, "{text:?}");
+            assert_eq!(draft.frequency.goal, goal, "{text:?}");
+            assert_eq!(draft.rrule, rrule, "{text:?}");
+            assert_eq!(draft.weekdays, weekdays, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn units() {
+        let cases = [
+            ("meditate for 10 min", "Meditate", target(10.0, "min")),
+            ("walk for 1 hour", "Walk", target(60.0, "min")),
+            ("walk for 1.5 hrs", "Walk", target(90.0, "min")),
+            ("plank 15min", "Plank", target(15.0, "min")),
+            ("read for 20 pages", "Read", target(20.0, "pages")),
+            // Without "for" only durations are taken.
+            ("read 20 pages", "Read 20 pages", None),
+            ("rest for 0 min", "Rest for 0 min", None),
+        ];
+        for (text, name, target) in cases {
+            let draft = draft(text);
+            assert_eq!(draft.name, name, "{text:?}");
+            assert_eq!(draft.target, target, "{text:?}");
+        }
+    }
+
+    #[test]
+    fn reminders_and_repetitions() {
+        let cases = [
+            ("journal at 7am", Some("07:00"), 1),
+            ("journal at 7:30 pm", Some("19:30"), 1),
+            ("journal at 19:00", Some("19:00"), 1),
+            ("journal at 19h30", Some("19:30"), 1),
+            ("journal at 12am", Some("00:00"), 1),
+            ("journal at noon", Some("12:00"), 1),
+            ("journal 9pm", Some("21:00"), 1),
+            ("journal every morning", Some("08:00"), 1),
+            ("journal at 25:00", None, 1),
+            ("drink water 3 times a day", None, 3),
+            (
+                "drink water 100 times a day",
+                None,
+                crate::repeats::MAX_TIMES_PER_DAY,
+            ),
+        ];
+        for (text, time, times_per_day) in cases {
+            let draft = draft(text);
+            assert_eq!(draft.reminder_time.as_deref(), time, "{text:?}");
+            assert_eq!(draft.times_per_day, times_per_day, "{text:?}");
+        }
+        assert_eq!(
+            draft("journal at 7:30 pm").rrule,
+            "FREQ=DAILY;BYHOUR=19;BYMINUTE=30"
+        );
+    }
+
+    #[test]
+    fn dates() {
+        let cases = [
+            ("starting today", "2024-06-10"),
+            ("starting tomorrow", "2024-06-11"),
+            ("from monday", "2024-06-10"),
+            ("from friday", "2024-06-14"),
+            ("starting on friday", "2024-06-14"),
+            ("from 2024-11-02", "2024-11-02"),
+            // Already past this year, so next year's.
+            ("starting 3/11", "2025-03-11"),
+            ("starting 12/25/24", "2024-12-25"),
+            ("starting 3.11.", "2024-11-03"),
+        ];
+        for (text, start) in cases {
+            let draft = draft(&format!("run {text}"));
+            assert_eq!(draft.name, "Run", "{text:?}");
+            assert_eq!(draft.start.as_deref(), Some(start), "{text:?}");
+        }
+        let draft = draft("run from 2/30");
+        assert_eq!(draft.start, None);
+        assert_eq!(draft.name, "Run from 2/30");
+    }
+
+    #[test]
+    fn locales() {
+        let cases = [
+            (Some("en-US"), "2025-03-11"),
+            (Some("en_US.UTF-8"), "2025-03-11"),
+            (Some("fil-PH"), "2025-03-11"),
+            (Some("en-GB"), "2024-11-03"),
+            (Some("de_DE.UTF-8"), "2024-11-03"),
+            (Some("fr"), "2024-11-03"),
+        ];
+        for (locale, start) in cases {
+            let draft = parse("run starting 3/11", locale, TODAY).unwrap();
+            assert_eq!(draft.start.as_deref(), Some(start), "{locale:?}");
+        }
+        // Dotted dates are day first even in the US.
+        let draft = parse("run starting 3.11.2024", Some("en-US"), TODAY).unwrap();
+        assert_eq!(draft.start.as_deref(), Some("2024-11-03"));
+        // So is a date that can only be read one way.
+        let draft = parse("run starting 25/12", Some("en-GB"), TODAY).unwrap();
+        assert_eq!(draft.start.as_deref(), Some("2024-12-25"));
+        let draft = parse("run starting 25/12", Some("en-US"), TODAY).unwrap();
+        assert_eq!(draft.start, None);
+    }
+
+    #[test]
+    fn tags() {
+        let draft = draft("run #Health #morning #health every day");
+        assert_eq!(draft.name, "Run");
+        assert_eq!(draft.tags, ["health", "morning"]);
+    }
+
+    #[test]
+    fn garbage() {
+        for text in [
+            "",
+            "   ",
+            "daily at 7am",
+            "#health",
+            "every the and",
+            "at at at",
+        ] {
+            assert!(
+                matches!(parse(text, Some("en-US"), TODAY), Err(AppError::Invalid(_))),
+                "{text:?}"
+            );
+        }
+        let cases = [
+            ("asdf qwer", "Asdf qwer"),
+            ("!!!", "!!!"),
+            ("ünïcode 🙂", "Ünïcode 🙂"),
+            ("at 99:99 for x", "99:99 for x"),
+        ];
+        for (text, name) in cases {
+            let draft = draft(text);
+            assert_eq!(draft.name, name, "{text:?}");
+            assert_eq!(draft.rrule, "FREQ=DAILY", "{text:?}");
+            assert_eq!(draft.reminder_time, None, "{text:?}");
+            assert_eq!(draft.target, None, "{text:?}");
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Scrubs every log line before it's written to the terminal or the log file (the log
+//! plugin's format in `lib.rs` runs it), so call sites don't have to:
+//!
+//! - tokens and other credentials: the API token, every value in the keychain
+//!   (`secrets::known_keys`: MQTT, sync, libSQL, integrations, webhook secrets, export
+//!   passphrases), anything after `Bearer`, `token=`, `secret:` and the like, and long hex
+//!   strings
+//! - paths under the user's home directory, which would give away their name
+//! - habit names, while privacy mode (`privacy.rs`) is on
+//!
+//! The known values are kept here and refreshed when the data, the settings or the
+//! secrets change. Nothing in this module logs, since that would come back through it.
+
+use std::sync::{OnceLock, RwLock};
+
+use regex::{NoExpand, Regex};
+use tauri::{AppHandle, Manager};
+
+use crate::events::EventBus;
+use crate::settings::SettingsStore;
+use crate::storage::Storage;
+
+const REDACTED: &str = "[redacted]";
+const HABIT: &str = "[habit]";
+/// Habit names shorter than this are too likely to be ordinary words.
+const MIN_NAME_LEN: usize = 3;
+
+struct Known {
+    secrets: Vec<String>,
+    /// Longest first, so "Read" doesn't cut into "Read 20 pages".
+    habits: Vec<String>,
+}
+
+static KNOWN: RwLock<Known> = RwLock::new(Known {
+    secrets: Vec::new(),
+    habits: Vec::new(),
+});
+
+fn credentials() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(
+            r#"(?i)\b(bearer\s+|(?:token|secret|password|passphrase|pin)\s*[=:]\s*)[^\s,;&"]+"#,
+        )
+        .expect("valid regex")
+    })
+}
+
+fn hex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"\b[0-9a-fA-F]{32,}\b").expect("valid regex"))
+}
+
+/// The home directory and whatever path follows it, up to a space, quote or colon.
+fn home() -> Option<&'static Regex> {
+    static RE: OnceLock<Option<Regex>> = OnceLock::new();
+    RE.get_or_init(|| {
+        let home = dirs::home_dir()?;
+        let home = home.to_str().filter(|h| h.len() > 1)?;
+        Regex::new(&format!(r#"{}([/\\][^\s"':]*)?"#, regex::escape(home))).ok()
+    })
+    .as_ref()
+}
+
+pub fn redact(line: &str) -> String {
+    let mut line = credentials()
+        .replace_all(line, format!("${{1}}{REDACTED}"))
+        .into_owned();
+    line = hex().replace_all(&line, NoExpand(REDACTED)).into_owned();
+    if let Some(home) = home() {
+        line = home
+            .replace_all(&line, |caps: &regex::Captures| {
+                if caps.get(1).is_some_and(|rest| rest.len() > 1) {
+                    "~/[path]"
+                } else {
+                    "~"
+                }
+            })
+            .into_owned();
+    }
+    let known = KNOWN.read().unwrap_or_else(|e| e.into_inner());
+    for secret in &known.secrets {
+        line = line.replace(secret.as_str(), REDACTED);
+    }
+    for name in &known.habits {
+        line = line.replace(name.as_str(), HABIT);
+    }
+    line
+}
+
+/// Reloads the API token and the keychain credentials (`secrets::known_keys`).
+pub fn refresh_secrets(app: &AppHandle) {
+    let mut secrets = vec![app.state::<SettingsStore>().get().api.token];
+    for key in crate::secrets::known_keys(app) {
+        if let Ok(Some(value)) = crate::secrets::get(&key) {
+            secrets.push(value);
+        }
+    }
+    secrets.retain(|s| !s.is_empty());
+    // Fetched before taking the lock: the keychain crates log.
+    KNOWN.write().unwrap_or_else(|e| e.into_inner()).secrets = secrets;
+}
+
+/// Reloads habit names, or clears them when privacy mode is off.
+pub fn refresh_habits(app: &AppHandle) {
+    let mut habits = Vec::new();
+    if crate::privacy::hide_names(app) {
+        habits = app
+            .state::<Storage>()
+            .list_habits()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|h| h.name.trim().to_string())
+            .filter(|n| n.chars().count() >= MIN_NAME_LEN)
+            .collect();
+        habits.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        habits.dedup();
+    }
+    KNOWN.write().unwrap_or_else(|e| e.into_inner()).habits = habits;
+}
+
+/// Loads the known values and keeps the habit names in step with the data.
+pub fn start(app: &AppHandle) {
+    refresh_secrets(app);
+    refresh_habits(app);
+    let app = app.clone();
+    let mut data = app.state::<EventBus>().watch_data();
+    tauri::async_runtime::spawn(async move {
+        while data.changed().await.is_ok() {
+            refresh_habits(&app);
+        }
+    });
+}
@@ -0,0 +1,227 @@
+//! Desktop reminders, driven by the scheduler's per-minute tick.
+//!
+//! A habit with a `reminderTime` gets a notification at that time if it's still due, so a
+//! "3 times per week" habit goes quiet once the week's quota is met. Per-weekday times
+//! (`weekdayReminders`) replace it on their days, e.g. 07:00 on workdays and 09:30 on
+//! weekends. At 21:00 one summary
+//! lists the habits that are at risk of missing today (or this period). Habits still
+//! waiting on a prerequisite stay quiet in both. Habits with a time window are reminded
+//! inside it, and drop out of the summary once it has closed.
+
+use std::collections::BTreeMap;
+
+use chrono::{Days, NaiveDateTime, NaiveTime};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::dependencies;
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::i18n::{t, t_args};
+use crate::model::Habit;
+use crate::schedule;
+use crate::stats::{date_key, today};
+use crate::storage::Storage;
+
+pub const MISSED_ALERT_TIME: &str = "21:00";
+/// Message ids of the titles (`i18n.rs`).
+pub const REMINDER_TITLE: &str = "reminder-title";
+pub const MISSED_TITLE: &str = "missed-title";
+
+/// How far ahead `next_occurrences` looks.
+const PREVIEW_DAYS: u64 = 14;
+
+/// `minute` is local `HH:MM`; the scheduler calls this once per minute. On mobile the app
+/// isn't kept running, so reminders are scheduled ahead instead (`mobile_reminders.rs`).
+pub fn on_minute(app: &AppHandle, minute: &str) {
+    if cfg!(mobile) {
+        return;
+    }
+    let habits = match app.state::<Storage>().list_habits() {
+        Ok(h) => h,
+        Err(e) => {
+            log::error!("reminders: failed to load habits: {e}");
+            return;
+        }
+    };
+    let today = today();
+
+    let hide_names = crate::privacy::hide_names(app);
+    let due: Vec<&Habit> = habits
+        .iter()
+        .filter(|h| crate::time_windows::reminder_time(h, today) == Some(minute))
+        .filter(|h| schedule::is_due(h, today))
+        .filter(|h| !dependencies::is_blocked(h, &habits, today))
+        .collect();
+    if hide_names && !due.is_empty() {
+        let body = t_args("habits-due", &[("count", due.len().into())]);
+        notify(app, &t(REMINDER_TITLE), &body);
+    } else {
+        for habit in due {
+            notify(app, &t(REMINDER_TITLE), &crate::appearance::label(habit));
+        }
+    }
+
+    if minute == MISSED_ALERT_TIME {
+        if let Some(body) = missed_summary(&habits, crate::time_windows::now(), hide_names) {
+            notify(app, &t(MISSED_TITLE), &body);
+        }
+    }
+}
+
+/// The evening summary's text as of `now`, or `None` with nothing at risk.
+pub fn missed_summary(habits: &[Habit], now: NaiveDateTime, hide_names: bool) -> Option<String> {
+    let today = now.date();
+    let missed: Vec<&Habit> = habits
+        .iter()
+        .filter(|h| schedule::is_at_risk(h, today))
+        .filter(|h| !crate::time_windows::is_closed(h, now))
+        .filter(|h| !dependencies::is_blocked(h, habits, today))
+        .collect();
+    if missed.is_empty() {
+        return None;
+    }
+    if hide_names {
+        return Some(t_args("missed-hidden", &[("count", missed.len().into())]));
+    }
+    let top: Vec<String> = missed
+        .iter()
+        .take(3)
+        .map(|h| crate::appearance::label(h))
+        .collect();
+    if missed.len() > 3 {
+        return Some(t_args(
+            "missed-more",
+            &[
+                ("names", top.join(", ").into()),
+                ("count", (missed.len() - 3).into()),
+            ],
+        ));
+    }
+    Some(top.join(", "))
+}
+
+/// Shows a notification, unless a focus session (`focus.rs`) is holding them back.
+pub fn notify(app: &AppHandle, title: &str, body: &str) {
+    if app.state::<crate::focus::Focus>().hold() {
+        log::debug!("reminders: held back during focus: {title}");
+        return;
+    }
+    notify_critical(app, title, body);
+}
+
+/// Shows a notification even during a focus session, for things that can't wait.
+pub fn notify_critical(app: &AppHandle, title: &str, body: &str) {
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        log::warn!("reminders: notification failed: {e}");
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Occurrence {
+    pub habit_id: String,
+    pub name: String,
+    /// `yyyy-MM-dd`
+    pub date: String,
+    /// `HH:MM`
+    pub time: String,
+}
+
+/// Reminders after `now`, soonest first, over the next two weeks. Days a habit
+/// won't be due on (off periods, a quota already met) are left out; prerequisites aren't
+/// known ahead of time, so chained habits are listed as if they'd be unblocked.
+pub fn upcoming(habits: &[Habit], now: NaiveDateTime, limit: usize) -> Vec<Occurrence> {
+    let current = now.format("%H:%M").to_string();
+    let mut occurrences = Vec::new();
+    for offset in 0..PREVIEW_DAYS {
+        let Some(date) = now.date().checked_add_days(Days::new(offset)) else {
+            break;
+        };
+        for habit in habits.iter().filter(|h| schedule::is_due(h, date)) {
+            let Some(time) = crate::time_windows::reminder_time(habit, date) else {
+                continue;
+            };
+            if offset == 0 && time <= current.as_str() {
+                continue;
+            }
+            occurrences.push(Occurrence {
+                habit_id: habit.id.clone(),
+                name: habit.name.clone(),
+                date: date_key(date),
+                time: time.to_string(),
+            });
+        }
+    }
+    occurrences.sort_by(|a, b| (&a.date, &a.time).cmp(&(&b.date, &b.time)));
+    occurrences.truncate(limit);
+    occurrences
+}
+
+/// Replaces a habit's per-weekday times (0 = Sunday).
+pub fn set_weekday_times(
+    storage: &Storage,
+    habit_id: &str,
+    times: BTreeMap<u8, String>,
+) -> Result<Habit, AppError> {
+    for (weekday, time) in &times {
+        if *weekday > 6 {
+            return Err(AppError::Invalid(format!(
+                "invalid weekday {weekday}, expected 0 (Sunday) to 6"
+            )));
+        }
+        if time.len() != 5 || NaiveTime::parse_from_str(time, "%H:%M").is_err() {
+            return Err(AppError::Invalid(format!(
+                "invalid time {time:?}, expected HH:MM"
+            )));
+        }
+    }
+    if !storage.set_weekday_reminders(habit_id, &times)? {
+        return Err(AppError::not_found("habit", habit_id));
+    }
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+/// `times` maps weekdays (0 = Sunday) to `HH:MM`; days left out use the habit's
+/// `reminderTime`, and an empty map clears them all.
+#[tauri::command]
+pub fn set_weekday_reminders(
+    app: AppHandle,
+    habit_id: String,
+    times: BTreeMap<u8, String>,
+) -> Result<Habit, AppError> {
+    let habit = set_weekday_times(&app.state::<Storage>(), &habit_id, times)?;
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(&app);
+    Ok(habit)
+}
+
+/// The next `limit` (default 10) reminders, of every habit or just `habit_id`.
+pub fn next(
+    storage: &Storage,
+    habit_id: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Vec<Occurrence>, AppError> {
+    let mut habits = storage.list_habits()?;
+    if let Some(id) = habit_id {
+        habits.retain(|h| h.id == id);
+    }
+    Ok(upcoming(
+        &habits,
+        crate::time_windows::now(),
+        limit.unwrap_or(10),
+    ))
+}
+
+/// The next `limit` (default 10) reminders, optionally for one habit.
+#[tauri::command]
+pub fn next_occurrences(
+    storage: State<'_, Storage>,
+    habit_id: Option<String>,
+    limit: Option<usize>,
+) -> Result<Vec<Occurrence>, AppError> {
+    next(&storage, habit_id.as_deref(), limit)
+}
@@ -0,0 +1,108 @@
+//! Habits done several times a day: "Take medication" with `timesPerDay: 3` needs three
+//! check-ins before the day counts. Each one is a timestamped check-in of 1 (see
+//! `list_check_ins`), and streaks, quotas and stats go by whether the count was reached.
+
+use chrono::NaiveDate;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::Habit;
+use crate::revisions;
+use crate::stats::{date_key, day_arg};
+use crate::storage::{Storage, Tx};
+
+/// Highest `times_per_day` accepted.
+pub const MAX_TIMES_PER_DAY: u32 = 50;
+
+pub fn set_times(storage: &Storage, habit_id: &str, times: u32) -> Result<Habit, AppError> {
+    if !(1..=MAX_TIMES_PER_DAY).contains(&times) {
+        return Err(AppError::Invalid(format!(
+            "times per day must be between 1 and {MAX_TIMES_PER_DAY}"
+        )));
+    }
+    if !storage.set_times_per_day(habit_id, times)? {
+        return Err(AppError::not_found("habit", habit_id));
+    }
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+/// Takes back the day's latest check-in, unchecking the day if it drops below the count.
+/// Returns `Ok(None)` if the habit doesn't exist.
+pub fn undo(tx: &Tx<'_>, habit_id: &str, date: NaiveDate) -> Result<Option<Habit>, String> {
+    let key = date_key(date);
+    if tx.get_habit(habit_id).map_err(|e| e.to_string())?.is_none() {
+        return Ok(None);
+    }
+    if !tx
+        .remove_last_check_in(habit_id, &key)
+        .map_err(|e| e.to_string())?
+    {
+        return Err(format!("nothing logged on {key}"));
+    }
+    let Some(habit) = tx.get_habit(habit_id).map_err(|e| e.to_string())? else {
+        return Ok(None);
+    };
+    if !habit.is_done_on(&key) && habit.logs.get(&key) == Some(&true) {
+        tx.set_completion(habit_id, &key, false)
+            .map_err(|e| e.to_string())?;
+    }
+    tx.get_habit(habit_id).map_err(|e| e.to_string())
+}
+
+/// [`undo`] if the day is still on `expected` (see `revisions.rs`).
+pub fn undo_checked(
+    storage: &Storage,
+    habit_id: &str,
+    date: NaiveDate,
+    expected: Option<u64>,
+) -> Result<Habit, AppError> {
+    revisions::check_day(storage, habit_id, date, expected, |tx| {
+        undo(tx, habit_id, date)?.ok_or_else(|| AppError::not_found("habit", habit_id))
+    })
+}
+
+#[tauri::command]
+pub fn set_times_per_day(app: AppHandle, habit_id: String, times: u32) -> Result<Habit, AppError> {
+    let habit = set_times(&app.state::<Storage>(), &habit_id, times)?;
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(&app);
+    Ok(habit)
+}
+
+/// Logs one repetition on `date` (default today).
+#[tauri::command]
+pub fn log_repetition(
+    app: AppHandle,
+    habit_id: String,
+    date: Option<String>,
+    expected_revision: Option<u64>,
+) -> Result<Habit, AppError> {
+    let date = day_arg(date.as_deref())?;
+    let logged = crate::habits::record_value_checked(
+        &app.state::<Storage>(),
+        &habit_id,
+        date,
+        1.0,
+        Vec::new(),
+        expected_revision,
+    )?;
+    Ok(crate::habits::value_logged(&app, logged, date))
+}
+
+/// Takes back the latest check-in on `date` (default today).
+#[tauri::command]
+pub fn undo_repetition(
+    app: AppHandle,
+    habit_id: String,
+    date: Option<String>,
+    expected_revision: Option<u64>,
+) -> Result<Habit, AppError> {
+    let date = day_arg(date.as_deref())?;
+    let habit = undo_checked(&app.state::<Storage>(), &habit_id, date, expected_revision)?;
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(&app);
+    Ok(habit)
+}
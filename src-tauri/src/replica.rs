@@ -0,0 +1,1080 @@
+//! Keeping the database in a remote libSQL/Turso database as well (`settings.libsql`),
+//! for using the same data on several devices without running a sync server.
+//!
+//! The local SQLite file stays the embedded replica: everything reads and writes it, so
+//! the app works the same offline. Whenever something changed, and every
+//! `interval_secs`, a round brings both sides together over libSQL's HTTP protocol
+//! (`POST /v2/pipeline`, with `Authorization: Bearer <libsql.token>` from the keychain).
+//! Rounds go row by row, by each table's primary key:
+//!
+//! - The remote database has the same tables as this one ([`Storage::replicated_schema`]:
+//!   everything but full-text indexes and [`LOCAL_TABLES`]), plus `habitflow_replica`
+//!   with the schema version and a generation bumped by every change sent, and
+//!   `habitflow_changes`, with when each row sent last changed and the generation that
+//!   sent it. The first device creates them.
+//! - Here, triggers note the key and time of each row written (`replica_changes`), and
+//!   `replica_rows` has a hash of each row as last synced. A round reads only the rows
+//!   written since, and only those that differ from what was synced count as changed.
+//!   From the remote side it reads only the rows later generations sent.
+//! - A row changed on one side only is copied to the other; one changed on both goes to
+//!   the later change ([`wins`]). Changes at the same millisecond go the same way on both
+//!   devices: an edit beats a delete, and two edits are ordered by their hash. What's sent goes in one remote transaction that only commits if
+//!   nobody else sent anything since the round read the remote state. Rows left without
+//!   their parent are dropped, here and then on the remote side.
+//! - A device joining a remote database that already has data takes it over; what it
+//!   had before is kept as a data version (`versioning.rs`).
+//! - A device with a newer schema rebuilds the remote tables from its rows when it next
+//!   sends; one with an older schema stops syncing until it's updated.
+//!
+//! Times come from each device's clock, so a device whose clock is far off wins or loses
+//! more often than it should. Photos and covers are files, not rows, and stay on the
+//! device they were added on.
+//!
+//! [`LOCAL_TABLES`]: crate::storage::LOCAL_TABLES
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::Engine;
+use rusqlite::types::Value as SqlValue;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::features::{self, Feature};
+use crate::settings::SettingsStore;
+use crate::storage::{
+    key_matches, quote_ident, row_hash, upsert_sql, ReplicatedTable, RowChange, Storage,
+    TableChanges, TableRows,
+};
+
+const REMOTE_META: &str = "habitflow_replica";
+const REMOTE_CHANGES: &str = "habitflow_changes";
+/// `format` in `habitflow_replica` once the remote tables are kept row by row. Databases
+/// HabitFlow set up before that are rebuilt.
+const FORMAT: &str = "rows";
+/// The remote generation this device has every change up to.
+const SINCE_KEY: &str = "libsql.since";
+/// The database URL [`SINCE_KEY`] is about.
+const URL_KEY: &str = "libsql.url";
+/// Kept by `reset_to_fresh`, so a reset is sent up rather than undone by the next round.
+pub const LOCAL_KEYS: &[&str] = &[SINCE_KEY, URL_KEY];
+/// How long to let changes settle before sending them.
+const SETTLE: Duration = Duration::from_secs(3);
+const ROWS_PER_INSERT: usize = 100;
+/// Rounds to try when another device keeps sending first.
+const ATTEMPTS: usize = 3;
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicaStatus {
+    pub enabled: bool,
+    /// The remote database's generation as of the last round.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub generation: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_synced_at: Option<String>,
+    /// Why the last round failed; cleared by the next one that works.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Failed rounds in a row.
+    pub failures: u32,
+    /// Tables with rows copied down and sent up in the last round that did anything.
+    pub pulled: Vec<String>,
+    pub pushed: Vec<String>,
+}
+
+/// Wakes the replicator and keeps what `get_libsql_status` reports.
+#[derive(Default)]
+pub struct Replica {
+    status: Mutex<ReplicaStatus>,
+    wake: Notify,
+    /// Held for a round, so `libsql_sync_now` and the background loop take turns.
+    round: tokio::sync::Mutex<()>,
+}
+
+impl Replica {
+    fn status(&self) -> std::sync::MutexGuard<'_, ReplicaStatus> {
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn enabled(app: &AppHandle) -> bool {
+    app.state::<SettingsStore>().get().libsql.enabled && features::enabled(Feature::Libsql)
+}
+
+/// Has the replicator run a round soon, for a changed setting or token.
+pub fn wake(app: &AppHandle) {
+    app.state::<Replica>().wake.notify_one();
+}
+
+fn encode(value: &SqlValue) -> Value {
+    match value {
+        SqlValue::Null => json!({ "type": "null" }),
+        SqlValue::Integer(i) => json!({ "type": "integer", "value": i.to_string() }),
+        SqlValue::Real(f) => json!({ "type": "float", "value": f }),
+        SqlValue::Text(s) => json!({ "type": "text", "value": s }),
+        SqlValue::Blob(b) => json!({
+            "type": "blob",
+            "base64": base64::engine::general_purpose::STANDARD_NO_PAD.encode(b),
+        }),
+    }
+}
+
+fn decode(value: &Value) -> SqlValue {
+    let text = value["value"].as_str();
+    match value["type"].as_str() {
+        Some("integer") => text
+            .and_then(|s| s.parse().ok())
+            .map_or(SqlValue::Null, SqlValue::Integer),
+        Some("float") => value["value"]
+            .as_f64()
+            .map_or(SqlValue::Null, SqlValue::Real),
+        Some("text") => SqlValue::Text(text.unwrap_or_default().to_string()),
+        Some("blob") => value["base64"]
+            .as_str()
+            .and_then(|b| {
+                base64::engine::general_purpose::STANDARD_NO_PAD
+                    .decode(b.trim_end_matches('='))
+                    .ok()
+            })
+            .map_or(SqlValue::Null, SqlValue::Blob),
+        _ => SqlValue::Null,
+    }
+}
+
+struct Stmt {
+    sql: String,
+    args: Vec<SqlValue>,
+}
+
+impl Stmt {
+    fn new(sql: impl Into<String>) -> Self {
+        Self {
+            sql: sql.into(),
+            args: Vec::new(),
+        }
+    }
+
+    fn with(sql: impl Into<String>, args: Vec<SqlValue>) -> Self {
+        Self {
+            sql: sql.into(),
+            args,
+        }
+    }
+
+    fn wire(&self) -> Value {
+        json!({ "sql": self.sql, "args": self.args.iter().map(encode).collect::<Vec<_>>() })
+    }
+}
+
+/// A remote write that didn't go through.
+enum Refused {
+    /// Another device sent changes since this round read the remote state.
+    Stale,
+    Failed(String),
+}
+
+impl From<String> for Refused {
+    fn from(e: String) -> Self {
+        Self::Failed(e)
+    }
+}
+
+struct RemoteState {
+    schema: usize,
+    generation: u64,
+    /// Whether it's kept row by row, with `habitflow_changes`.
+    rows: bool,
+}
+
+struct Remote {
+    client: reqwest::Client,
+    endpoint: String,
+    token: Option<String>,
+}
+
+impl Remote {
+    fn new(client: reqwest::Client, url: &str) -> Result<Self, String> {
+        let url = url.trim().trim_end_matches('/');
+        if url.is_empty() {
+            return Err("no libSQL database URL is set".into());
+        }
+        let base = match url.split_once("://") {
+            Some(("libsql" | "wss" | "https", rest)) => format!("https://{rest}"),
+            Some(("ws" | "http", rest)) => format!("http://{rest}"),
+            _ => return Err(format!("{url:?} isn't a libSQL database URL")),
+        };
+        Ok(Self {
+            client,
+            endpoint: format!("{base}/v2/pipeline"),
+            token: crate::secrets::get(crate::secrets::LIBSQL_TOKEN)?,
+        })
+    }
+
+    /// Runs `requests` on one stream. Returns each one's result or error.
+    async fn pipeline(&self, requests: Vec<Value>) -> Result<Vec<Result<Value, String>>, String> {
+        let count = requests.len();
+        let mut requests = requests;
+        requests.push(json!({ "type": "close" }));
+        let mut request = self
+            .client
+            .post(&self.endpoint)
+            .json(&json!({ "baton": null, "requests": requests }));
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("couldn't reach the libSQL database: {e}"))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("the libSQL database answered {status}"));
+        }
+        let reply: Value = response
+            .json()
+            .await
+            .map_err(|e| format!("the libSQL database sent an unreadable reply: {e}"))?;
+        let results = reply["results"].as_array().cloned().unwrap_or_default();
+        if results.len() < count {
+            return Err("the libSQL database left requests unanswered".into());
+        }
+        Ok(results
+            .into_iter()
+            .take(count)
+            .map(|r| match r["type"].as_str() {
+                Some("ok") => Ok(r["response"]["result"].clone()),
+                _ => Err(r["error"]["message"]
+                    .as_str()
+                    .unwrap_or("unknown error")
+                    .to_string()),
+            })
+            .collect())
+    }
+
+    /// Runs each query; returns their rows, or the first error.
+    async fn query(
+        &self,
+        stmts: &[Stmt],
+    ) -> Result<Vec<(Vec<String>, Vec<Vec<SqlValue>>)>, String> {
+        let requests = stmts
+            .iter()
+            .map(|s| json!({ "type": "execute", "stmt": s.wire() }))
+            .collect();
+        self.pipeline(requests)
+            .await?
+            .into_iter()
+            .map(|result| {
+                let result = result?;
+                let columns = result["cols"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|c| c["name"].as_str().unwrap_or_default().to_string())
+                    .collect();
+                let rows = result["rows"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|row| row.as_array().into_iter().flatten().map(decode).collect())
+                    .collect();
+                Ok((columns, rows))
+            })
+            .collect()
+    }
+
+    /// What `habitflow_replica` says, or `None` if nothing was set up yet.
+    async fn state(&self) -> Result<Option<RemoteState>, String> {
+        let stmt = Stmt::new(format!("SELECT key, value FROM {REMOTE_META}"));
+        let rows = match self.query(&[stmt]).await {
+            Ok(mut results) => results.remove(0).1,
+            Err(e) if e.contains("no such table") => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let mut state = RemoteState {
+            schema: 0,
+            generation: 0,
+            rows: false,
+        };
+        for row in rows {
+            let (Some(SqlValue::Text(key)), Some(SqlValue::Text(value))) =
+                (row.first(), row.get(1))
+            else {
+                continue;
+            };
+            match key.as_str() {
+                "schema" => state.schema = value.parse().unwrap_or(0),
+                "generation" => state.generation = value.parse().unwrap_or(0),
+                "format" => state.rows = value == FORMAT,
+                _ => {}
+            }
+        }
+        Ok(Some(state))
+    }
+
+    async fn tables(&self) -> Result<HashSet<String>, String> {
+        let stmt = Stmt::new("SELECT name FROM sqlite_master WHERE type = 'table'");
+        Ok(self
+            .query(&[stmt])
+            .await?
+            .remove(0)
+            .1
+            .iter()
+            .map(|row| text(row.first()))
+            .collect())
+    }
+
+    /// The rows of `schema` sent after generation `since`, as the remote side has them now.
+    async fn changes(
+        &self,
+        schema: &[ReplicatedTable],
+        since: u64,
+    ) -> Result<Vec<TableChanges>, String> {
+        let since = SqlValue::Integer(since as i64);
+        let stmt = Stmt::with(
+            format!("SELECT DISTINCT tbl FROM {REMOTE_CHANGES} WHERE generation > ?1"),
+            vec![since.clone()],
+        );
+        let changed: HashSet<String> = self
+            .query(&[stmt])
+            .await?
+            .remove(0)
+            .1
+            .iter()
+            .map(|row| text(row.first()))
+            .collect();
+        let tables: Vec<&ReplicatedTable> = schema
+            .iter()
+            .filter(|t| changed.contains(&t.name))
+            .collect();
+        if tables.is_empty() {
+            return Ok(Vec::new());
+        }
+        let queries: Vec<Stmt> = tables
+            .iter()
+            .map(|t| {
+                Stmt::with(
+                    format!(
+                        "SELECT c.pk, c.changed_at, r.{} IS NOT NULL, r.* FROM {REMOTE_CHANGES} c
+                         LEFT JOIN {} r ON {}
+                         WHERE c.tbl = ?1 AND c.generation > ?2",
+                        quote_ident(&t.key[0]),
+                        quote_ident(&t.name),
+                        key_matches("r", &t.key, "c.pk")
+                    ),
+                    vec![SqlValue::Text(t.name.clone()), since.clone()],
+                )
+            })
+            .collect();
+        Ok(self
+            .query(&queries)
+            .await?
+            .into_iter()
+            .zip(tables)
+            .map(|((columns, rows), table)| TableChanges {
+                name: table.name.clone(),
+                columns: columns.into_iter().skip(3).collect(),
+                rows: rows
+                    .into_iter()
+                    .filter(|row| row.len() >= 3)
+                    .map(|mut row| {
+                        let values = row.split_off(3);
+                        RowChange {
+                            key: text(row.first()),
+                            changed_at: text(row.get(1)),
+                            row: (row[2] == SqlValue::Integer(1)).then_some(values),
+                        }
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+
+    /// Runs `stmts` in one remote transaction: all of them, or none if one fails.
+    /// `guarded` means the first one is the generation check.
+    async fn transaction(&self, stmts: &[Stmt], guarded: bool) -> Result<(), Refused> {
+        let mut steps = vec![json!({ "stmt": Stmt::new("BEGIN").wire() })];
+        for stmt in stmts {
+            let previous = steps.len() - 1;
+            steps.push(json!({
+                "stmt": stmt.wire(),
+                "condition": { "type": "ok", "step": previous },
+            }));
+        }
+        let commit = steps.len();
+        steps.push(json!({
+            "stmt": Stmt::new("COMMIT").wire(),
+            "condition": { "type": "ok", "step": commit - 1 },
+        }));
+        steps.push(json!({
+            "stmt": Stmt::new("ROLLBACK").wire(),
+            "condition": { "type": "not", "cond": { "type": "ok", "step": commit } },
+        }));
+        let request = json!({ "type": "batch", "batch": { "steps": steps } });
+        let result = self.pipeline(vec![request]).await?.remove(0)?;
+        let failed = result["step_errors"].as_array().and_then(|errors| {
+            errors
+                .iter()
+                .position(|e| !e.is_null())
+                .map(|step| (step, errors[step]["message"].as_str().unwrap_or_default()))
+        });
+        match failed {
+            Some((1, _)) if guarded => Err(Refused::Stale),
+            Some((_, message)) => Err(Refused::Failed(format!(
+                "the libSQL database refused the changes: {message}"
+            ))),
+            None if result["step_results"][commit].is_null() => Err(Refused::Failed(
+                "the remote transaction didn't commit".into(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The text in `value`, or nothing.
+fn text(value: Option<&SqlValue>) -> String {
+    match value {
+        Some(SqlValue::Text(s)) => s.clone(),
+        _ => String::new(),
+    }
+}
+
+/// Fails (with bad JSON) unless the remote generation is still `generation`.
+fn guard(generation: u64) -> Stmt {
+    Stmt::with(
+        format!(
+            "SELECT json(CASE WHEN (SELECT value FROM {REMOTE_META} WHERE key = 'generation') = ?1
+                 THEN '1' ELSE 'stale' END)"
+        ),
+        vec![SqlValue::Text(generation.to_string())],
+    )
+}
+
+fn set_remote(key: &str, value: String) -> Stmt {
+    Stmt::with(
+        format!(
+            "INSERT INTO {REMOTE_META} (key, value) VALUES (?1, ?2)
+             ON CONFLICT (key) DO UPDATE SET value = excluded.value"
+        ),
+        vec![SqlValue::Text(key.into()), SqlValue::Text(value)],
+    )
+}
+
+/// Notes `changes` in `habitflow_changes` as sent by `generation`. A row's time only ever
+/// moves forward, so a whole table sent up keeps the times its rows had there.
+fn log(changes: &TableChanges, generation: u64, stmts: &mut Vec<Stmt>) {
+    for chunk in changes.rows.chunks(ROWS_PER_INSERT) {
+        let mut args = Vec::with_capacity(chunk.len() * 4);
+        for row in chunk {
+            args.extend([
+                SqlValue::Text(changes.name.clone()),
+                SqlValue::Text(row.key.clone()),
+                SqlValue::Text(row.changed_at.clone()),
+                SqlValue::Integer(generation as i64),
+            ]);
+        }
+        stmts.push(Stmt::with(
+            format!(
+                "INSERT INTO {REMOTE_CHANGES} (tbl, pk, changed_at, generation) VALUES {}
+                 ON CONFLICT (tbl, pk) DO UPDATE SET
+                     changed_at = max(changed_at, excluded.changed_at),
+                     generation = excluded.generation",
+                vec!["(?, ?, ?, ?)"; chunk.len()].join(", ")
+            ),
+            args,
+        ));
+    }
+}
+
+/// The statements that write `changes` to the remote `table`, and log them.
+fn send(table: &ReplicatedTable, changes: &TableChanges, generation: u64, stmts: &mut Vec<Stmt>) {
+    let (kept, deleted): (Vec<&RowChange>, Vec<&RowChange>) =
+        changes.rows.iter().partition(|r| r.row.is_some());
+    for chunk in kept.chunks(ROWS_PER_INSERT) {
+        stmts.push(Stmt::with(
+            upsert_sql(
+                table,
+                changes.columns.iter().map(String::as_str),
+                chunk.len(),
+            ),
+            chunk
+                .iter()
+                .filter_map(|r| r.row.clone())
+                .flatten()
+                .collect(),
+        ));
+    }
+    let columns: Vec<String> = table.key.iter().map(|c| quote_ident(c)).collect();
+    let parts: Vec<String> = (0..table.key.len())
+        .map(|i| format!("json_extract(value, '$[{i}]')"))
+        .collect();
+    for chunk in deleted.chunks(ROWS_PER_INSERT) {
+        let keys: Vec<&str> = chunk.iter().map(|r| r.key.as_str()).collect();
+        stmts.push(Stmt::with(
+            format!(
+                "DELETE FROM {} WHERE ({}) IN (SELECT {} FROM json_each(?1))",
+                quote_ident(&table.name),
+                columns.join(", "),
+                parts.join(", ")
+            ),
+            vec![SqlValue::Text(format!("[{}]", keys.join(",")))],
+        ));
+    }
+    log(changes, generation, stmts);
+}
+
+/// Rows changed since the last round, sorted out.
+#[derive(Default)]
+struct Merged {
+    /// Changed on the remote side, to write here.
+    apply: Vec<TableChanges>,
+    /// Changed here, to send.
+    push: Vec<TableChanges>,
+    /// Changed on both sides to the same thing.
+    agreed: Vec<TableChanges>,
+}
+
+fn hash(row: &RowChange) -> Option<String> {
+    row.row.as_deref().map(row_hash)
+}
+
+/// Whether this device's change to a row beats the remote side's: the later one wins,
+/// and at the same moment, the same one on every device.
+fn wins(local: &RowChange, remote: &RowChange) -> bool {
+    (local.changed_at.as_str(), hash(local)) > (remote.changed_at.as_str(), hash(remote))
+}
+
+fn merge(local: Vec<TableChanges>, remote: Vec<TableChanges>) -> Merged {
+    let mut merged = Merged::default();
+    // Rows the remote side's change doesn't win.
+    let mut kept: HashSet<(String, String)> = HashSet::new();
+    {
+        let theirs: HashMap<(&str, &str), &RowChange> = remote
+            .iter()
+            .flat_map(|t| {
+                t.rows
+                    .iter()
+                    .map(move |r| ((t.name.as_str(), r.key.as_str()), r))
+            })
+            .collect();
+        for table in local {
+            let (mut push, mut agreed) = (Vec::new(), Vec::new());
+            for row in table.rows {
+                match theirs.get(&(table.name.as_str(), row.key.as_str())) {
+                    Some(other) if hash(&row) == hash(other) => {
+                        kept.insert((table.name.clone(), row.key.clone()));
+                        agreed.push(row);
+                    }
+                    Some(other) if !wins(&row, other) => {}
+                    _ => {
+                        kept.insert((table.name.clone(), row.key.clone()));
+                        push.push(row);
+                    }
+                }
+            }
+            for (rows, into) in [(push, &mut merged.push), (agreed, &mut merged.agreed)] {
+                if !rows.is_empty() {
+                    into.push(TableChanges {
+                        name: table.name.clone(),
+                        columns: table.columns.clone(),
+                        rows,
+                    });
+                }
+            }
+        }
+    }
+    for mut table in remote {
+        table
+            .rows
+            .retain(|r| !kept.contains(&(table.name.clone(), r.key.clone())));
+        if !table.rows.is_empty() {
+            merged.apply.push(table);
+        }
+    }
+    merged
+}
+
+fn names(changes: &[TableChanges]) -> Vec<String> {
+    changes
+        .iter()
+        .filter(|t| !t.rows.is_empty())
+        .map(|t| t.name.clone())
+        .collect()
+}
+
+/// The remote generation this device has every change up to, if it synced with `url`
+/// before. Another URL is another database, so what was synced with the last one is
+/// forgotten.
+fn since(storage: &Storage, url: &str) -> Result<Option<u64>, String> {
+    let url = url.trim();
+    if storage
+        .get_meta(URL_KEY)
+        .map_err(|e| e.to_string())?
+        .as_deref()
+        != Some(url)
+    {
+        storage.replica_forget().map_err(|e| e.to_string())?;
+        storage.delete_meta(SINCE_KEY).map_err(|e| e.to_string())?;
+        storage.set_meta(URL_KEY, url).map_err(|e| e.to_string())?;
+        return Ok(None);
+    }
+    Ok(storage
+        .get_meta(SINCE_KEY)
+        .map_err(|e| e.to_string())?
+        .and_then(|g| g.parse().ok()))
+}
+
+fn changed(app: &AppHandle) {
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(app);
+    crate::mobile_reminders::sync_logged(app);
+}
+
+/// Replaces the tables here with the remote ones, for a device joining a database that's
+/// in use. Returns the tables copied down.
+async fn take_over(
+    app: &AppHandle,
+    remote: &Remote,
+    schema: &[ReplicatedTable],
+) -> Result<Vec<String>, String> {
+    let storage = app.state::<Storage>();
+    let existing = remote.tables().await?;
+    let wanted: Vec<&ReplicatedTable> = schema
+        .iter()
+        .filter(|t| existing.contains(&t.name))
+        .collect();
+    if !storage.list_habits().map_err(|e| e.to_string())?.is_empty() {
+        let saved = crate::versioning::app_repository_path(app).and_then(|path| {
+            crate::versioning::commit_snapshot(&storage, &path, "Before joining a libSQL database")
+        });
+        if let Err(e) = saved {
+            log::warn!("libsql: couldn't save a data version first: {e}");
+        }
+    }
+    let tables: Vec<TableRows> = if wanted.is_empty() {
+        Vec::new()
+    } else {
+        let queries: Vec<Stmt> = wanted
+            .iter()
+            .map(|t| Stmt::new(format!("SELECT * FROM {}", quote_ident(&t.name))))
+            .collect();
+        remote
+            .query(&queries)
+            .await?
+            .into_iter()
+            .zip(&wanted)
+            .map(|((columns, rows), table)| TableRows {
+                name: table.name.clone(),
+                columns,
+                rows,
+            })
+            .collect()
+    };
+    let orphans = storage
+        .replace_tables(schema, &tables)
+        .map_err(|e| e.to_string())?;
+    if orphans > 0 {
+        log::info!("libsql: dropped {orphans} rows whose parent is gone");
+    }
+    changed(app);
+    Ok(wanted.into_iter().map(|t| t.name.clone()).collect())
+}
+
+/// Brings both sides together once. Returns the tables with rows pulled and pushed.
+async fn round(
+    app: &AppHandle,
+    remote: &Remote,
+    url: &str,
+) -> Result<(Vec<String>, Vec<String>), Refused> {
+    let storage = app.state::<Storage>();
+    let schema = storage.replicated_schema().map_err(|e| e.to_string())?;
+    let (version, _) = storage.schema_version().map_err(|e| e.to_string())?;
+    let since = since(&storage, url)?;
+    storage.replica_track(&schema).map_err(|e| e.to_string())?;
+    let state = remote.state().await?;
+    if state.as_ref().is_some_and(|s| s.schema > version) {
+        return Err(Refused::Failed(
+            "the libSQL database was set up by a newer HabitFlow; update this one to keep syncing"
+                .into(),
+        ));
+    }
+    // A remote database behind this device was set up again since (emptied by hand, say),
+    // so it's joined like a new one.
+    let since = since.filter(|s| state.as_ref().is_some_and(|state| *s <= state.generation));
+
+    let mut pulled = Vec::new();
+    let mut local = Vec::new();
+    match (&state, since) {
+        (Some(_), None) => pulled = take_over(app, remote, &schema).await?,
+        _ => {
+            local = storage
+                .replica_changes(&schema)
+                .map_err(|e| e.to_string())?;
+            let theirs = match (&state, since) {
+                (Some(state), Some(since)) if state.rows && since < state.generation => {
+                    remote.changes(&schema, since).await?
+                }
+                _ => Vec::new(),
+            };
+            let seen: HashMap<(String, String), String> = local
+                .iter()
+                .flat_map(|t| {
+                    t.rows
+                        .iter()
+                        .map(|r| ((t.name.clone(), r.key.clone()), r.changed_at.clone()))
+                })
+                .collect();
+            let merged = merge(local, theirs);
+            if !merged.apply.is_empty() {
+                let (written, orphans) = storage
+                    .replica_apply(&schema, &merged.apply, &seen)
+                    .map_err(|e| e.to_string())?;
+                if orphans > 0 {
+                    log::info!("libsql: dropped {orphans} rows whose parent is gone");
+                }
+                if written > 0 || orphans > 0 {
+                    changed(app);
+                }
+                pulled = names(&merged.apply);
+            }
+            storage
+                .replica_synced(&merged.agreed, false)
+                .map_err(|e| e.to_string())?;
+            local = merged.push;
+        }
+    }
+    if let Some(state) = &state {
+        storage
+            .set_meta(SINCE_KEY, &state.generation.to_string())
+            .map_err(|e| e.to_string())?;
+    }
+
+    let recreate = state
+        .as_ref()
+        .map_or(true, |s| s.schema < version || !s.rows);
+    if local.is_empty() && !recreate {
+        return Ok((pulled, Vec::new()));
+    }
+    let next = state.as_ref().map_or(0, |s| s.generation) + 1;
+    let mut stmts = Vec::new();
+    if let Some(state) = &state {
+        // Goes first, so a failure here can be told apart.
+        stmts.push(guard(state.generation));
+    }
+    stmts.push(Stmt::new("PRAGMA defer_foreign_keys = ON"));
+    let mut whole = Vec::new();
+    if recreate {
+        if state.is_none() {
+            stmts.push(Stmt::new(format!(
+                "CREATE TABLE {REMOTE_META} (key TEXT PRIMARY KEY, value TEXT NOT NULL)"
+            )));
+        }
+        for table in schema.iter().rev() {
+            stmts.push(Stmt::new(format!(
+                "DROP TABLE IF EXISTS {}",
+                quote_ident(&table.name)
+            )));
+        }
+        for table in &schema {
+            stmts.extend(table.sql.iter().map(Stmt::new));
+        }
+        stmts.push(Stmt::new(format!(
+            "CREATE TABLE IF NOT EXISTS {REMOTE_CHANGES} (
+                tbl TEXT NOT NULL,
+                pk TEXT NOT NULL,
+                changed_at TEXT NOT NULL,
+                generation INTEGER NOT NULL,
+                PRIMARY KEY (tbl, pk)
+            )"
+        )));
+        stmts.push(Stmt::new(format!(
+            "CREATE INDEX IF NOT EXISTS {REMOTE_CHANGES}_generation
+             ON {REMOTE_CHANGES} (generation)"
+        )));
+        // Per-table fingerprints, from before rows were kept one by one.
+        stmts.push(Stmt::new(format!(
+            "DELETE FROM {REMOTE_META} WHERE key LIKE 'table:%'"
+        )));
+        // Deletions are only in what changed; the rest is all in the tables.
+        for changes in &local {
+            log(changes, next, &mut stmts);
+        }
+        for table in &schema {
+            let rows = storage.replica_table(table).map_err(|e| e.to_string())?;
+            send(table, &rows, next, &mut stmts);
+            whole.push(rows);
+        }
+    } else {
+        for changes in &local {
+            if let Some(table) = schema.iter().find(|t| t.name == changes.name) {
+                send(table, changes, next, &mut stmts);
+            }
+        }
+    }
+    let device = crate::sync_queue::device_id(app).map_err(|e| e.to_string())?;
+    stmts.push(set_remote("schema", version.to_string()));
+    stmts.push(set_remote("format", FORMAT.to_string()));
+    stmts.push(set_remote("generation", next.to_string()));
+    stmts.push(set_remote("pushedBy", device));
+    stmts.push(set_remote("pushedAt", chrono::Utc::now().to_rfc3339()));
+    remote.transaction(&stmts, state.is_some()).await?;
+
+    let (sent, whole_tables) = if recreate {
+        (&whole, true)
+    } else {
+        (&local, false)
+    };
+    storage
+        .replica_synced(sent, whole_tables)
+        .map_err(|e| e.to_string())?;
+    storage
+        .set_meta(SINCE_KEY, &next.to_string())
+        .map_err(|e| e.to_string())?;
+    Ok((pulled, names(sent)))
+}
+
+/// Rounds until one goes through, with the outcome kept for `get_libsql_status`.
+/// Returns whether it failed.
+async fn attempt(app: &AppHandle, client: &reqwest::Client) -> bool {
+    let replica = app.state::<Replica>();
+    let _round = replica.round.lock().await;
+    let url = app.state::<SettingsStore>().get().libsql.url;
+    let mut result = Err("the libSQL database kept changing while syncing".to_string());
+    match Remote::new(client.clone(), &url) {
+        Ok(remote) => {
+            for _ in 0..ATTEMPTS {
+                match round(app, &remote, &url).await {
+                    Ok(tables) => {
+                        result = Ok(tables);
+                        break;
+                    }
+                    Err(Refused::Stale) => continue,
+                    Err(Refused::Failed(e)) => {
+                        result = Err(e);
+                        break;
+                    }
+                }
+            }
+        }
+        Err(e) => result = Err(e),
+    }
+
+    let generation = app
+        .state::<Storage>()
+        .get_meta(SINCE_KEY)
+        .ok()
+        .flatten()
+        .and_then(|g| g.parse().ok());
+    let mut status = replica.status();
+    status.generation = generation;
+    match result {
+        Ok((pulled, pushed)) => {
+            if !pulled.is_empty() || !pushed.is_empty() {
+                log::info!(
+                    "libsql: pulled {} tables, pushed {}",
+                    pulled.len(),
+                    pushed.len()
+                );
+                status.pulled = pulled;
+                status.pushed = pushed;
+            }
+            status.last_synced_at = Some(chrono::Utc::now().to_rfc3339());
+            status.last_error = None;
+            status.failures = 0;
+            false
+        }
+        Err(e) => {
+            log::warn!("libsql: {e}");
+            status.last_error = Some(e);
+            status.failures += 1;
+            true
+        }
+    }
+}
+
+fn client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(60))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+pub fn status(app: &AppHandle) -> ReplicaStatus {
+    let mut status = app.state::<Replica>().status().clone();
+    status.enabled = enabled(app);
+    status
+}
+
+/// Runs a round shortly after every change and every `interval_secs`, while it's on.
+pub fn start(app: &AppHandle) {
+    let a = app.clone();
+    let mut data = app.state::<EventBus>().watch_data();
+    tauri::async_runtime::spawn(async move {
+        while data.changed().await.is_ok() {
+            wake(&a);
+        }
+    });
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let Ok(client) = client() else {
+            log::error!("libsql: failed to build the HTTP client");
+            return;
+        };
+        loop {
+            let failed = enabled(&app) && {
+                crate::network::wait_online(&app).await;
+                attempt(&app, &client).await
+            };
+            let replica = app.state::<Replica>();
+            let interval = app.state::<SettingsStore>().get().libsql.interval_secs;
+            let mut delay = Duration::from_secs(u64::from(interval.max(5)));
+            if failed {
+                crate::network::check_now(&app);
+                let failures = replica.status().failures as usize;
+                let delays = crate::sync_queue::RETRY_DELAYS;
+                delay = delay.min(delays[failures.min(delays.len()) - 1]);
+            }
+            tokio::select! {
+                _ = replica.wake.notified() => tokio::time::sleep(SETTLE).await,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_libsql_status(app: AppHandle) -> ReplicaStatus {
+    status(&app)
+}
+
+/// Runs a round now and reports how it went.
+#[tauri::command]
+pub async fn libsql_sync_now(app: AppHandle) -> Result<ReplicaStatus, AppError> {
+    if !enabled(&app) {
+        return Err(AppError::Invalid("libSQL sync is turned off".into()));
+    }
+    attempt(&app, &client()?).await;
+    Ok(status(&app))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(key: &str, at: &str, value: Option<i64>) -> RowChange {
+        RowChange {
+            key: format!("[\"{key}\"]"),
+            changed_at: at.into(),
+            row: value.map(|v| vec![SqlValue::Text(key.into()), SqlValue::Integer(v)]),
+        }
+    }
+
+    fn table(rows: Vec<RowChange>) -> Vec<TableChanges> {
+        vec![TableChanges {
+            name: "habits".into(),
+            columns: vec!["id".into(), "value".into()],
+            rows,
+        }]
+    }
+
+    /// The keys of `changes`' rows, in order.
+    fn keys(changes: &[TableChanges]) -> Vec<&str> {
+        changes
+            .iter()
+            .flat_map(|t| t.rows.iter().map(|r| r.key.as_str()))
+            .collect()
+    }
+
+    const EARLY: &str = "2024-06-10T12:00:00.000Z";
+    const LATE: &str = "2024-06-10T12:00:00.001Z";
+
+    #[test]
+    fn the_later_change_wins() {
+        let early = change("a", EARLY, Some(1));
+        let late = change("a", LATE, Some(2));
+        assert!(wins(&late, &early));
+        assert!(!wins(&early, &late));
+        // A delete is a change like any other.
+        let deleted = change("a", LATE, None);
+        assert!(wins(&deleted, &early));
+        assert!(!wins(&early, &deleted));
+    }
+
+    #[test]
+    fn ties_go_the_same_way_on_both_devices() {
+        let cases = [
+            (change("a", EARLY, Some(1)), change("a", EARLY, Some(2))),
+            (change("a", EARLY, None), change("a", EARLY, Some(2))),
+        ];
+        for (one, other) in cases {
+            // Each device sees its own change as local, so exactly one of them wins.
+            assert_ne!(wins(&one, &other), wins(&other, &one));
+        }
+        // Deleting and editing at the same moment keeps the edit.
+        assert!(wins(
+            &change("a", EARLY, Some(1)),
+            &change("a", EARLY, None)
+        ));
+    }
+
+    #[test]
+    fn one_sided_changes_are_copied_across() {
+        let merged = merge(
+            table(vec![change("mine", EARLY, Some(1))]),
+            table(vec![change("theirs", EARLY, None)]),
+        );
+        assert_eq!(keys(&merged.push), ["[\"mine\"]"]);
+        assert_eq!(keys(&merged.apply), ["[\"theirs\"]"]);
+        assert!(merged.agreed.is_empty());
+    }
+
+    #[test]
+    fn concurrent_edits_go_to_the_later_one() {
+        let merged = merge(
+            table(vec![
+                change("newer-here", LATE, Some(1)),
+                change("newer-there", EARLY, Some(1)),
+                change("deleted-there", EARLY, Some(1)),
+                change("deleted-here", LATE, None),
+            ]),
+            table(vec![
+                change("newer-here", EARLY, Some(2)),
+                change("newer-there", LATE, Some(2)),
+                change("deleted-there", LATE, None),
+                change("deleted-here", EARLY, Some(2)),
+            ]),
+        );
+        assert_eq!(
+            keys(&merged.push),
+            ["[\"newer-here\"]", "[\"deleted-here\"]"]
+        );
+        assert_eq!(
+            keys(&merged.apply),
+            ["[\"newer-there\"]", "[\"deleted-there\"]"]
+        );
+        assert!(merged.agreed.is_empty());
+    }
+
+    #[test]
+    fn the_same_change_on_both_sides_is_neither_sent_nor_applied() {
+        let merged = merge(
+            table(vec![
+                change("edited", EARLY, Some(1)),
+                change("deleted", EARLY, None),
+            ]),
+            table(vec![
+                change("edited", LATE, Some(1)),
+                change("deleted", LATE, None),
+            ]),
+        );
+        assert!(merged.push.is_empty());
+        assert!(merged.apply.is_empty());
+        assert_eq!(keys(&merged.agreed), ["[\"edited\"]", "[\"deleted\"]"]);
+    }
+}
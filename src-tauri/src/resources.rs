@@ -0,0 +1,184 @@
+//! What the backend is holding on to, so a tray app that runs for weeks doesn't grow
+//! unnoticed: the process's memory, the database and attachment files on disk, and the
+//! caches that grow with use. `trim_caches` empties the caches, hands SQLite's spare
+//! memory back and folds the write-ahead log into the database file.
+
+use std::path::Path;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::jobs::Jobs;
+use crate::storage::{Storage, DB_FILE_NAME};
+
+#[cfg(target_os = "linux")]
+mod imp {
+    /// `VmRSS` in `/proc/self/status`, which is in kB.
+    pub fn resident_bytes() -> Option<u64> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+        let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kb * 1024)
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use windows_sys::Win32::System::ProcessStatus::{
+        K32GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    /// The working set.
+    pub fn resident_bytes() -> Option<u64> {
+        let mut counters: PROCESS_MEMORY_COUNTERS = unsafe { std::mem::zeroed() };
+        let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+        counters.cb = size;
+        if unsafe { K32GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) } == 0 {
+            return None;
+        }
+        Some(counters.WorkingSetSize as u64)
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    const MACH_TASK_BASIC_INFO: u32 = 20;
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct TimeValue {
+        seconds: i32,
+        microseconds: i32,
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct MachTaskBasicInfo {
+        virtual_size: u64,
+        resident_size: u64,
+        resident_size_max: u64,
+        user_time: TimeValue,
+        system_time: TimeValue,
+        policy: i32,
+        suspend_count: i32,
+    }
+
+    extern "C" {
+        static mach_task_self_: u32;
+        fn task_info(task: u32, flavor: u32, info: *mut MachTaskBasicInfo, count: *mut u32) -> i32;
+    }
+
+    pub fn resident_bytes() -> Option<u64> {
+        let mut info = MachTaskBasicInfo::default();
+        let mut count = (std::mem::size_of::<MachTaskBasicInfo>() / 4) as u32;
+        let status =
+            unsafe { task_info(mach_task_self_, MACH_TASK_BASIC_INFO, &mut info, &mut count) };
+        (status == 0).then_some(info.resident_size)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod imp {
+    pub fn resident_bytes() -> Option<u64> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheUsage {
+    pub name: &'static str,
+    pub entries: usize,
+    /// An estimate of what the entries take up in memory, where there's one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceUsage {
+    /// The whole process's resident memory, the webview's own processes aside. `None`
+    /// where the platform doesn't say.
+    pub memory_bytes: Option<u64>,
+    /// What SQLite has allocated, across every connection.
+    pub sqlite_memory_bytes: u64,
+    pub database_bytes: u64,
+    /// The write-ahead log and its index, which go back to nearly nothing on a trim.
+    pub database_log_bytes: u64,
+    /// Habit cover images and photos on check-ins.
+    pub attachments_bytes: u64,
+    pub caches: Vec<CacheUsage>,
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Everything under `dir`, which may not exist.
+fn dir_size(dir: &Path) -> u64 {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|e| match e.file_type() {
+                    Ok(t) if t.is_dir() => dir_size(&e.path()),
+                    _ => e.metadata().map(|m| m.len()).unwrap_or(0),
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+pub fn usage(app: &AppHandle) -> Result<ResourceUsage, AppError> {
+    let data_dir = app.path().app_data_dir()?;
+    let database = data_dir.join(DB_FILE_NAME);
+    let log = |suffix: &str| file_size(&data_dir.join(format!("{DB_FILE_NAME}{suffix}")));
+    let storage = app.state::<Storage>();
+    let (stats_entries, stats_bytes) = storage.stats_cache().usage();
+    let (jobs, job_bytes) = app.state::<Jobs>().usage();
+    Ok(ResourceUsage {
+        memory_bytes: imp::resident_bytes(),
+        sqlite_memory_bytes: u64::try_from(unsafe { rusqlite::ffi::sqlite3_memory_used() })
+            .unwrap_or(0),
+        database_bytes: file_size(&database),
+        database_log_bytes: log("-wal") + log("-shm"),
+        attachments_bytes: dir_size(&data_dir.join(crate::appearance::COVERS_DIR))
+            + dir_size(&data_dir.join(crate::attachments::ATTACHMENTS_DIR)),
+        caches: vec![
+            CacheUsage {
+                name: "stats",
+                entries: stats_entries,
+                bytes: Some(stats_bytes),
+            },
+            CacheUsage {
+                name: "jobs",
+                entries: jobs,
+                bytes: Some(job_bytes),
+            },
+            CacheUsage {
+                name: "streakRuns",
+                entries: storage.streak_run_count()?,
+                bytes: None,
+            },
+        ],
+    })
+}
+
+#[tauri::command]
+pub fn get_resource_usage(app: AppHandle) -> Result<ResourceUsage, AppError> {
+    usage(&app)
+}
+
+/// Empties the stats cache and drops finished jobs, then has SQLite free what it can.
+/// Streak runs are kept, since rebuilding them costs more than they take up. Returns the
+/// usage afterwards.
+#[tauri::command]
+pub fn trim_caches(app: AppHandle) -> Result<ResourceUsage, AppError> {
+    let storage = app.state::<Storage>();
+    storage.stats_cache().forget_habit(None);
+    app.state::<Jobs>().forget_finished();
+    storage.trim()?;
+    usage(&app)
+}
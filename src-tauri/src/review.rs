@@ -0,0 +1,157 @@
+//! The year in review: what each habit added up to over a calendar year, and the year's
+//! totals. Rendered as a background job (`jobs.rs`), since it walks every day of every
+//! habit. Weekday totals start on the configured first day of the week (`calendar.rs`).
+
+use std::collections::BTreeSet;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::model::Habit;
+use crate::stats::{bridged, date_key, today};
+use crate::storage::Storage;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HabitYear {
+    pub habit_id: String,
+    pub name: String,
+    pub completions: u32,
+    /// Percentage of the habit's days that year that were completed; excused days that
+    /// weren't are left out.
+    pub completion_rate: u32,
+    /// Longest streak within the year.
+    pub longest_streak: u32,
+    /// 1–12; `None` without completions.
+    pub best_month: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct YearReview {
+    pub year: i32,
+    pub completions: u32,
+    /// Completions per month, January first.
+    pub months: [u32; 12],
+    /// Completions per day of the week, starting on `week_start`.
+    pub weekdays: [u32; 7],
+    /// 0 = Sunday, like JS `getDay()`.
+    pub week_start: u8,
+    /// Days with at least one completion.
+    pub active_days: u32,
+    pub longest_streak: u32,
+    /// Most completions first.
+    pub habits: Vec<HabitYear>,
+}
+
+/// Counts across every habit.
+struct Totals {
+    months: [u32; 12],
+    weekdays: [u32; 7],
+    week_start: Weekday,
+}
+
+/// Where `day` falls in a week starting on `week_start`, 0 to 6.
+fn weekday_index(day: NaiveDate, week_start: Weekday) -> usize {
+    let from = |d: Weekday| d.num_days_from_monday() as usize;
+    (7 + from(day.weekday()) - from(week_start)) % 7
+}
+
+/// The habit's days in `year` up to today, from the day it started.
+fn days_of(habit: &Habit, year: i32, today: NaiveDate) -> Vec<NaiveDate> {
+    let (Some(first), Some(last)) = (
+        NaiveDate::from_ymd_opt(year, 1, 1),
+        NaiveDate::from_ymd_opt(year, 12, 31),
+    ) else {
+        return Vec::new();
+    };
+    let start = crate::stats::parse_date(habit.start_date()).map_or(first, |s| s.max(first));
+    start
+        .iter_days()
+        .take_while(|d| *d <= last.min(today))
+        .collect()
+}
+
+/// Adds the habit's completions to `totals` and its done days to `active`.
+fn habit_year(
+    habit: &Habit,
+    year: i32,
+    today: NaiveDate,
+    totals: &mut Totals,
+    active: &mut BTreeSet<NaiveDate>,
+) -> HabitYear {
+    let mut per_month = [0u32; 12];
+    let (mut completions, mut counted) = (0, 0);
+    let (mut longest, mut current) = (0, 0);
+    let mut prev: Option<NaiveDate> = None;
+    for day in days_of(habit, year, today) {
+        let key = date_key(day);
+        if habit.is_done_on(&key) {
+            completions += 1;
+            counted += 1;
+            per_month[day.month0() as usize] += 1;
+            totals.weekdays[weekday_index(day, totals.week_start)] += 1;
+            active.insert(day);
+            current = match prev {
+                Some(p) if bridged(habit, p, day) => current + 1,
+                _ => 1,
+            };
+            longest = longest.max(current);
+            prev = Some(day);
+        } else if !habit.is_excused(&key) {
+            counted += 1;
+        }
+    }
+    for (total, count) in totals.months.iter_mut().zip(per_month) {
+        *total += count;
+    }
+    let best_month = (completions > 0)
+        .then(|| (1..=12).zip(per_month).max_by_key(|(_, c)| *c))
+        .flatten()
+        .map(|(m, _)| m);
+    HabitYear {
+        habit_id: habit.id.clone(),
+        name: habit.name.clone(),
+        completions,
+        completion_rate: (completions * 100).checked_div(counted).unwrap_or(0),
+        longest_streak: longest,
+        best_month,
+    }
+}
+
+/// Reviews `year`, calling `step(done, total)` after each habit; an error from it stops
+/// the review.
+pub fn year_review(
+    storage: &Storage,
+    year: i32,
+    step: &mut dyn FnMut(usize, usize) -> Result<(), AppError>,
+) -> Result<YearReview, AppError> {
+    let habits = storage.list_habits()?;
+    let today = today();
+    let mut totals = Totals {
+        months: [0; 12],
+        weekdays: [0; 7],
+        week_start: crate::calendar::week_start(),
+    };
+    let mut active = BTreeSet::new();
+    let mut reviewed = Vec::new();
+    for (i, habit) in habits.iter().enumerate() {
+        let review = habit_year(habit, year, today, &mut totals, &mut active);
+        if review.completions > 0 {
+            reviewed.push(review);
+        }
+        step(i + 1, habits.len())?;
+    }
+    reviewed.sort_by(|a, b| b.completions.cmp(&a.completions).then(a.name.cmp(&b.name)));
+    Ok(YearReview {
+        year,
+        completions: totals.months.iter().sum(),
+        months: totals.months,
+        weekdays: totals.weekdays,
+        week_start: totals.week_start.num_days_from_sunday() as u8,
+        active_days: active.len() as u32,
+        longest_streak: reviewed.iter().map(|h| h.longest_streak).max().unwrap_or(0),
+        habits: reviewed,
+    })
+}
@@ -0,0 +1,126 @@
+//! Stale-write detection for habits and their days.
+//!
+//! Storage keeps a revision for each habit and for each day of one, bumped on every change
+//! by whoever makes it: the main window, a widget, the tray, sync or the CLI. Habits carry
+//! theirs as `revision` and check-ins carry their day's. A writer that passes the revision
+//! it last read as `expectedRevision` gets a `conflict` error, with the current habit, if
+//! the record has changed since; nothing is written. Leaving it out writes unchecked.
+//!
+//! The webview's snapshots (`sync_habits`) are checked per habit against the revision
+//! each habit carries. A habit that changed elsewhere since keeps what's stored, and
+//! `habits:conflict` is emitted with the stored versions. The webview's own queued
+//! snapshots don't count against each other: one based on revision 4 still lands after the
+//! one before it took the habit to 5.
+//!
+//! A checked write bumps the record's revision if it's still the expected one
+//! (`Tx::claim_revision`), then writes, in one transaction (`Storage::transaction`). Of two
+//! checked writers racing on the same record only one wins, whichever process they're in,
+//! and a write that fails leaves the revision where it was. A habit that doesn't exist is
+//! `not_found` rather than a conflict, and gets no revision. Writes of every habit at once (snapshots,
+//! the CLI's `sync_habits`, new habits) check the revisions inside the transaction that
+//! writes them (`Storage::replace_habits_checked`), against the habits they were based on.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::NaiveDate;
+
+use crate::error::AppError;
+use crate::model::Habit;
+use crate::stats::date_key;
+use crate::storage::{Storage, Tx};
+
+/// Emitted with the stored versions of habits a snapshot was refused for.
+pub const HABITS_CONFLICT_EVENT: &str = "habits:conflict";
+
+static LOCK: Mutex<()> = Mutex::new(());
+/// Per habit, the revision the webview's snapshots were based on and the one the latest
+/// of them left it on.
+static SNAPSHOTS: Mutex<BTreeMap<String, (u64, u64)>> = Mutex::new(BTreeMap::new());
+
+/// Held while one of the webview's snapshots is checked and written, so their bookkeeping
+/// follows the order they land in.
+pub fn lock() -> MutexGuard<'static, ()> {
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn snapshots() -> MutexGuard<'static, BTreeMap<String, (u64, u64)>> {
+    SNAPSHOTS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Whether a snapshot based on revision `seen` of the `stored` habit would undo a change
+/// made to it elsewhere. 0 is never stale.
+pub fn snapshot_stale(stored: &Habit, seen: u64) -> bool {
+    seen != 0
+        && seen != stored.revision
+        && snapshots().get(&stored.id) != Some(&(seen, stored.revision))
+}
+
+/// Notes that a snapshot based on `seen` left the habit on `revision`.
+pub fn snapshot_written(habit_id: &str, seen: u64, revision: u64) {
+    if seen != 0 {
+        snapshots().insert(habit_id.to_string(), (seen, revision));
+    }
+}
+
+/// The habits' revisions, for [`Storage::replace_habits_checked`].
+pub fn seen(habits: &[Habit]) -> HashMap<String, u64> {
+    habits.iter().map(|h| (h.id.clone(), h.revision)).collect()
+}
+
+/// The error for a write based on revision `expected` of a record that's now on `revision`.
+pub fn conflict(expected: u64, revision: u64, current: &Habit) -> AppError {
+    AppError::Conflict {
+        expected,
+        revision,
+        current: Box::new(serde_json::to_value(current).unwrap_or_default()),
+    }
+}
+
+fn checked<T>(
+    storage: &Storage,
+    habit_id: &str,
+    date: &str,
+    expected: Option<u64>,
+    write: impl FnOnce(&Tx<'_>) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    storage.transaction(|tx| {
+        let Some(expected) = expected else {
+            return write(tx);
+        };
+        if !tx.habit_exists(habit_id)? {
+            return Err(AppError::not_found("habit", habit_id));
+        }
+        if let Some(revision) = tx.claim_revision(habit_id, date, expected)? {
+            let current = tx
+                .get_habit(habit_id)?
+                .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+            log::info!(
+                "revisions: refused a write to {habit_id} {date} at {expected}, now {revision}"
+            );
+            return Err(conflict(expected, revision, &current));
+        }
+        write(tx)
+    })
+}
+
+/// Runs `write` in a transaction if the habit is still on `expected`.
+pub fn check_habit<T>(
+    storage: &Storage,
+    habit_id: &str,
+    expected: Option<u64>,
+    write: impl FnOnce(&Tx<'_>) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    checked(storage, habit_id, "", expected, write)
+}
+
+/// Runs `write` in a transaction if the habit's `date` is still on `expected`.
+pub fn check_day<T>(
+    storage: &Storage,
+    habit_id: &str,
+    date: NaiveDate,
+    expected: Option<u64>,
+    write: impl FnOnce(&Tx<'_>) -> Result<T, AppError>,
+) -> Result<T, AppError> {
+    checked(storage, habit_id, &date_key(date), expected, write)
+}
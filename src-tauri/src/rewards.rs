@@ -0,0 +1,183 @@
+//! Custom rewards: things the user treats themselves to ("an episode of a show", "a new
+//! book"), each with a point cost.
+//!
+//! Points are earned exactly like XP (see `gamification.rs`), so every completion adds
+//! to them, whether or not levels are shown. The balance is what's been earned minus what
+//! redemptions have spent. Since earned points follow the history, an undone check-in can
+//! take the balance below zero; nothing can be redeemed until it recovers. Redemptions
+//! keep the reward's name and cost, so removing or repricing a reward leaves the history
+//! as it was. Changes are emitted to the webview as `rewards:changed`.
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::AppError;
+use crate::model::{Redemption, Reward};
+use crate::settings::{generate_token, SettingsStore};
+use crate::storage::Storage;
+
+pub const REWARDS_CHANGED_EVENT: &str = "rewards:changed";
+/// Redemptions `list_rewards` returns by default.
+pub(crate) const HISTORY_LIMIT: u32 = 50;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RewardStatus {
+    #[serde(flatten)]
+    pub reward: Reward,
+    pub affordable: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RewardsOverview {
+    pub earned: u64,
+    pub spent: u64,
+    pub balance: i64,
+    pub rewards: Vec<RewardStatus>,
+    /// Newest first.
+    pub history: Vec<Redemption>,
+}
+
+fn earned(storage: &Storage, settings: &SettingsStore) -> Result<u64, AppError> {
+    Ok(crate::gamification::load(storage, settings)?.xp)
+}
+
+pub fn add(storage: &Storage, name: &str, cost: u32) -> Result<Reward, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::Invalid("a reward needs a name".into()));
+    }
+    if cost == 0 {
+        return Err(AppError::Invalid(
+            "a reward has to cost at least one point".into(),
+        ));
+    }
+    let reward = Reward {
+        id: generate_token()[..16].to_string(),
+        name: name.to_string(),
+        cost,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    };
+    storage.insert_reward(&reward)?;
+    Ok(reward)
+}
+
+pub fn overview(
+    storage: &Storage,
+    settings: &SettingsStore,
+    limit: u32,
+) -> Result<RewardsOverview, AppError> {
+    let earned = earned(storage, settings)?;
+    let spent = storage.points_spent()?;
+    let balance = earned as i64 - spent as i64;
+    let rewards = storage
+        .list_rewards()?
+        .into_iter()
+        .map(|reward| RewardStatus {
+            affordable: balance >= i64::from(reward.cost),
+            reward,
+        })
+        .collect();
+    Ok(RewardsOverview {
+        earned,
+        spent,
+        balance,
+        rewards,
+        history: storage.list_redemptions(limit)?,
+    })
+}
+
+/// Spends the reward's cost. Fails, recording nothing, if the balance doesn't cover it.
+pub fn redeem(
+    storage: &Storage,
+    settings: &SettingsStore,
+    reward_id: &str,
+) -> Result<Redemption, AppError> {
+    let reward = storage
+        .list_rewards()?
+        .into_iter()
+        .find(|r| r.id == reward_id)
+        .ok_or_else(|| AppError::not_found("reward", reward_id))?;
+    let redemption = Redemption {
+        id: generate_token()[..16].to_string(),
+        reward_id: reward.id,
+        name: reward.name,
+        cost: reward.cost,
+        redeemed_at: chrono::Utc::now().to_rfc3339(),
+    };
+    let earned = earned(storage, settings)?;
+    if !storage.redeem(&redemption, earned)? {
+        let spent = storage.points_spent()?;
+        return Err(AppError::Invalid(format!(
+            "{} costs {} points but only {} are available",
+            redemption.name,
+            redemption.cost,
+            (earned as i64 - spent as i64).max(0)
+        )));
+    }
+    Ok(redemption)
+}
+
+fn changed(app: &AppHandle) -> Result<RewardsOverview, AppError> {
+    let overview = overview(
+        &app.state::<Storage>(),
+        &app.state::<SettingsStore>(),
+        HISTORY_LIMIT,
+    )?;
+    let _ = app.emit(REWARDS_CHANGED_EVENT, &overview);
+    Ok(overview)
+}
+
+/// The balance, every reward and the latest redemptions (`limit`, default 50).
+#[tauri::command]
+pub fn list_rewards(app: AppHandle, limit: Option<u32>) -> Result<RewardsOverview, AppError> {
+    overview(
+        &app.state::<Storage>(),
+        &app.state::<SettingsStore>(),
+        limit.unwrap_or(HISTORY_LIMIT),
+    )
+}
+
+#[tauri::command]
+pub fn add_reward(app: AppHandle, name: String, cost: u32) -> Result<Reward, AppError> {
+    let reward = add(&app.state::<Storage>(), &name, cost)?;
+    changed(&app)?;
+    Ok(reward)
+}
+
+pub fn remove(storage: &Storage, id: &str) -> Result<(), AppError> {
+    if !storage.delete_reward(id)? {
+        return Err(AppError::not_found("reward", id));
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn remove_reward(app: AppHandle, id: String) -> Result<(), AppError> {
+    remove(&app.state::<Storage>(), &id)?;
+    changed(&app)?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn redeem_reward(app: AppHandle, reward_id: String) -> Result<Redemption, AppError> {
+    let redemption = redeem(
+        &app.state::<Storage>(),
+        &app.state::<SettingsStore>(),
+        &reward_id,
+    )?;
+    crate::reminders::notify(
+        &app,
+        &crate::i18n::t("reward-title"),
+        &crate::i18n::t_args(
+            "reward-body",
+            &[
+                ("name", redemption.name.clone().into()),
+                ("cost", redemption.cost.into()),
+            ],
+        ),
+    );
+    changed(&app)?;
+    Ok(redemption)
+}
@@ -0,0 +1,1684 @@
+//! `--rpc`: JSON-RPC 2.0 over stdin/stdout, one message per line.
+//!
+//! Method names and (named, camelCase) params match the Tauri commands that work on the
+//! data and settings: habits, check-ins, stats, categories, goals, rewards, exports, covers
+//! and attachments (with paths used as given), the PIN and the like. Commands that need a
+//! window or the running app's services aren't served, and answer "unknown method": file
+//! pickers and the files they grant, the window, tray and window effects, other keychain
+//! secrets and biometrics, backups to drives, updates, sync and the libSQL replica, network
+//! status, social, plugins, automations, NFC, focus sessions and blocking, the share sheet,
+//! mobile reminders, the widget tile, Shortcuts and the watch, crash reports, log export and
+//! other diagnostics. Two extras cover what the webview does locally:
+//! `set_completion { habitId, date?, done }` and `get_stats { tag? }`.
+//!
+//! With a PIN set the connection starts locked, like the app: everything but
+//! `get_lock_status`, `lock_app` and `unlock_app { pin }` answers "HabitFlow is locked"
+//! until it's unlocked, or the PIN is in `HABITFLOW_PIN` when it starts.
+//!
+//! Timers, the Pomodoro session and jobs belong to the connection: a window doesn't see
+//! them and they end when stdin closes. Nothing ticks between requests, so a timer that
+//! reached its goal or a phase that ran out is caught up on at the next one, and
+//! `start_job` runs the job to the end before replying.
+//!
+//! Runs without a window, against the same database and settings file as the app. Writes
+//! show up in a running window on its next scheduler tick; settings changes apply the next
+//! time the app starts.
+
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::error::AppError;
+use crate::model::{Habit, TimeWindow};
+use crate::pomodoro::Transition;
+use crate::qr::QrLevel;
+use crate::revisions;
+use crate::settings::{
+    ExportFormat, ExportSchedule, HabitTemplate, LogLevel, Settings, SettingsStore,
+    SETTINGS_FILE_NAME,
+};
+use crate::stats::{self, date_arg, day_arg, today};
+use crate::storage::Storage;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+/// A write based on a stale revision (see `revisions.rs`).
+const CONFLICT: i64 = -32001;
+/// The connection is locked until `unlock_app`.
+const LOCKED: i64 = -32002;
+
+pub(crate) struct RpcError {
+    pub(crate) code: i64,
+    pub(crate) message: String,
+}
+
+impl RpcError {
+    fn server(e: impl ToString) -> Self {
+        Self {
+            code: SERVER_ERROR,
+            message: e.to_string(),
+        }
+    }
+}
+
+impl From<AppError> for RpcError {
+    fn from(e: AppError) -> Self {
+        Self {
+            code: match e {
+                AppError::Invalid(_) => INVALID_PARAMS,
+                AppError::Conflict { .. } => CONFLICT,
+                AppError::Locked => LOCKED,
+                _ => SERVER_ERROR,
+            },
+            message: e.to_string(),
+        }
+    }
+}
+
+pub(crate) struct Context {
+    pub(crate) storage: Storage,
+    pub(crate) settings: SettingsStore,
+    pub(crate) data_dir: std::path::PathBuf,
+    /// Timers started over this connection; a window has its own.
+    pub(crate) timers: crate::timers::Timers,
+    /// Likewise the Pomodoro session.
+    pub(crate) pomodoro: crate::pomodoro::Pomodoro,
+    /// Jobs run over this connection. `start_job` runs them to the end before replying.
+    pub(crate) jobs: crate::jobs::Jobs,
+    /// Until `unlock_app`, when a PIN is set.
+    pub(crate) locked: AtomicBool,
+}
+
+/// Serves until stdin closes. Returns the process exit code.
+pub fn serve() -> i32 {
+    let storage = match crate::cli::open_storage() {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return 1;
+        }
+    };
+    let data_dir = match crate::cli::app_data_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return 1;
+        }
+    };
+    let locked = match crate::lock::pin_required(&storage) {
+        Ok(false) => false,
+        Ok(true) => match std::env::var(crate::lock::PIN_ENV) {
+            Ok(pin) => {
+                if let Err(e) = crate::lock::unlock_headless(&storage, &pin) {
+                    eprintln!("error: {e}");
+                    return 1;
+                }
+                false
+            }
+            Err(_) => true,
+        },
+        Err(e) => {
+            eprintln!("error: {e}");
+            return 1;
+        }
+    };
+    let settings = SettingsStore::load(data_dir.join(SETTINGS_FILE_NAME));
+    crate::features::apply(&settings);
+    let ctx = Context {
+        storage,
+        settings,
+        data_dir,
+        timers: Default::default(),
+        pomodoro: Default::default(),
+        jobs: Default::default(),
+        locked: AtomicBool::new(locked),
+    };
+
+    let stdin = std::io::stdin();
+    let mut stdout = std::io::stdout().lock();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_line(&ctx, &line) {
+            if writeln!(stdout, "{response}")
+                .and_then(|_| stdout.flush())
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+    0
+}
+
+fn lock_status(ctx: &Context) -> Result<crate::lock::LockStatus, AppError> {
+    Ok(crate::lock::LockStatus {
+        configured: crate::lock::pin_required(&ctx.storage)?,
+        locked: ctx.locked.load(Ordering::SeqCst),
+        retry_after: None,
+        keychain_error: None,
+    })
+}
+
+/// Returns `None` for notifications (requests without an `id`).
+fn handle_line(ctx: &Context, line: &str) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(v) => v,
+        Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, e.to_string())),
+    };
+
+    let id = request.get("id").cloned();
+    let method = request.get("method").and_then(Value::as_str);
+    let Some(method) = method.filter(|_| request.get("jsonrpc") == Some(&json!("2.0"))) else {
+        return Some(error_response(
+            id.unwrap_or(Value::Null),
+            INVALID_REQUEST,
+            "expected a JSON-RPC 2.0 request".into(),
+        ));
+    };
+    let params = request.get("params").cloned().unwrap_or(json!({}));
+
+    let result = dispatch(ctx, method, params);
+    let id = id?;
+    Some(match result {
+        Ok(value) => json!({ "jsonrpc": "2.0", "id": id, "result": value }),
+        Err(e) => error_response(id, e.code, e.message),
+    })
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+fn params<T: DeserializeOwned>(value: Value) -> Result<T, RpcError> {
+    serde_json::from_value(value).map_err(|e| RpcError {
+        code: INVALID_PARAMS,
+        message: e.to_string(),
+    })
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> Result<Value, RpcError> {
+    serde_json::to_value(value).map_err(RpcError::server)
+}
+
+#[derive(Deserialize)]
+struct SyncHabits {
+    habits: Vec<Habit>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateSettings {
+    new_settings: Settings,
+}
+
+#[derive(Deserialize)]
+struct AddWebhook {
+    url: String,
+    events: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Pin {
+    pin: String,
+}
+
+#[derive(Deserialize)]
+struct ById {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct AddExportJob {
+    name: String,
+    format: ExportFormat,
+    directory: String,
+    schedule: ExportSchedule,
+    #[serde(default)]
+    passphrase: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ExportData {
+    path: String,
+    format: ExportFormat,
+    #[serde(default)]
+    passphrase: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ImportData {
+    path: String,
+    #[serde(default)]
+    passphrase: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct ExportHistory {
+    job_id: Option<String>,
+    limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogHabitValue {
+    habit_id: String,
+    value: f64,
+    date: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    expected_revision: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogLapse {
+    habit_id: String,
+    date: Option<String>,
+    note: Option<String>,
+    expected_revision: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ByHabitId {
+    habit_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Archive {
+    habit_id: String,
+    expected_revision: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListCheckIns {
+    habit_id: String,
+    date: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetCompletions {
+    habit_id: String,
+    cursor: Option<String>,
+    limit: Option<u32>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetRangeStats {
+    habit_ids: Option<Vec<String>>,
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RebuildStreaks {
+    habit_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct CreateCategory {
+    name: String,
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UpdateCategory {
+    id: String,
+    name: Option<String>,
+    color: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ByIds {
+    ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetHabitPinned {
+    habit_id: String,
+    pinned: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetWeekdayReminders {
+    habit_id: String,
+    times: std::collections::BTreeMap<u8, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NextOccurrences {
+    #[serde(default)]
+    habit_id: Option<String>,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct AddMilestone {
+    habit_id: String,
+    kind: crate::model::MilestoneKind,
+    threshold: f64,
+}
+
+#[derive(Deserialize)]
+struct ListMilestones {
+    #[serde(default)]
+    habit_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AddGoal {
+    title: String,
+    habit_id: String,
+    period: crate::model::GoalPeriod,
+    target: f64,
+    #[serde(default)]
+    date: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GoalProgressParams {
+    #[serde(default)]
+    goal_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ListRewards {
+    #[serde(default)]
+    limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct AddReward {
+    name: String,
+    cost: u32,
+}
+
+#[derive(Deserialize)]
+struct RedeemReward {
+    reward_id: String,
+}
+
+#[derive(Deserialize)]
+struct PauseHabit {
+    habit_id: String,
+    until: String,
+}
+
+#[derive(Deserialize)]
+struct ParseQuickAdd {
+    text: String,
+    #[serde(default)]
+    locale: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SetHabitDifficulty {
+    habit_id: String,
+    difficulty: crate::model::Difficulty,
+}
+
+#[derive(Deserialize)]
+struct ByView {
+    view: crate::ordering::View,
+}
+
+#[derive(Deserialize)]
+struct SetSortPreference {
+    view: crate::ordering::View,
+    sort: crate::settings::HabitSort,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AssignCategory {
+    habit_id: String,
+    category_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddChecklistItem {
+    habit_id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RenameChecklistItem {
+    item_id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ByItemId {
+    item_id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReorderChecklist {
+    habit_id: String,
+    item_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetChecklistItemDone {
+    item_id: String,
+    done: bool,
+    date: Option<String>,
+    expected_revision: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetHabitDependencies {
+    habit_id: String,
+    depends_on: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct AddTemplate {
+    template: HabitTemplate,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveHabitAsTemplate {
+    habit_id: String,
+    name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateFromTemplate {
+    template_id: String,
+    name: Option<String>,
+    reminder_time: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ByDate {
+    date: String,
+}
+
+#[derive(Deserialize)]
+struct LogMood {
+    score: u8,
+    date: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ListMoods {
+    from: Option<String>,
+    to: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct MoodInsightsParams {
+    days: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct YearReviewParams {
+    year: Option<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateCheckinNote {
+    habit_id: String,
+    date: String,
+    note: Option<String>,
+    expected_revision: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct SearchNotes {
+    query: String,
+    limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetHabitWindow {
+    habit_id: String,
+    window: Option<TimeWindow>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddOffPeriod {
+    start: String,
+    end: Option<String>,
+    habit_id: Option<String>,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct ListOffPeriods {
+    habit_id: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ListTags {
+    prefix: Option<String>,
+    limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetHabitTags {
+    habit_id: String,
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ByTag {
+    tag: String,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct StatsParams {
+    tag: Option<String>,
+    exclude_backfilled: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct DateParam {
+    date: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct CommitDataVersion {
+    message: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Limit {
+    limit: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetCompletion {
+    habit_id: String,
+    date: Option<String>,
+    done: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetHabitAppearance {
+    habit_id: String,
+    #[serde(default)]
+    color: Option<String>,
+    #[serde(default)]
+    icon: Option<String>,
+    #[serde(default)]
+    emoji: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetTimesPerDay {
+    habit_id: String,
+    times: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Repetition {
+    habit_id: String,
+    #[serde(default)]
+    date: Option<String>,
+    expected_revision: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Backfill {
+    habit_id: String,
+    date: String,
+    expected_revision: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ImportLegacyState {
+    json: String,
+}
+
+#[derive(Deserialize)]
+struct RotateEncryptionKey {
+    old: String,
+    new: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CheckHabitName {
+    name: String,
+    #[serde(default)]
+    habit_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct StartJob {
+    request: crate::jobs::JobRequest,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct StartPomodoro {
+    habit_id: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetHabitCover {
+    habit_id: String,
+    #[serde(default)]
+    path: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AddCheckinAttachment {
+    habit_id: String,
+    date: String,
+    path: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetAppPin {
+    pin: String,
+    #[serde(default)]
+    current_pin: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SetLocale {
+    locale: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SetLogLevel {
+    level: LogLevel,
+}
+
+#[derive(Deserialize)]
+struct GenerateQr {
+    payload: String,
+    #[serde(default)]
+    scale: Option<u32>,
+    #[serde(default)]
+    level: Option<QrLevel>,
+}
+
+#[derive(Deserialize)]
+struct SeedSyntheticData {
+    habits: u32,
+    years: u32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ShareTemplate {
+    template_id: String,
+    #[serde(default)]
+    scale: Option<u32>,
+}
+
+#[derive(Deserialize)]
+struct ImportSharedTemplate {
+    link: String,
+}
+
+fn repository(ctx: &Context) -> std::path::PathBuf {
+    crate::versioning::repository_path(&ctx.settings.get().versioning, &ctx.data_dir)
+}
+
+/// Logs a finished work interval, as `pomodoro::log_progress` does in the app.
+fn log_pomodoro(ctx: &Context, habit_id: &str, minutes: u32) -> Result<(), AppError> {
+    let storage = &ctx.storage;
+    let Some(habit) = storage.get_habit(habit_id)? else {
+        return Ok(());
+    };
+    let date = today();
+    match crate::pomodoro::progress(&habit, minutes) {
+        Some(amount) => {
+            storage.transaction(|tx| {
+                crate::habits::record_value(tx, habit_id, date, amount, Vec::new())
+            })?;
+        }
+        None => {
+            let rules = ctx.settings.get().backfill;
+            storage.transaction(|tx| {
+                crate::habits::record_completion(tx, &rules, habit_id, date, true)
+            })?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn dispatch(ctx: &Context, method: &str, p: Value) -> Result<Value, RpcError> {
+    let storage = &ctx.storage;
+    let settings = &ctx.settings;
+
+    let locked = ctx.locked.load(Ordering::SeqCst);
+    if locked && !matches!(method, "get_lock_status" | "lock_app" | "unlock_app") {
+        return Err(AppError::Locked.into());
+    }
+
+    // Nothing ticks between requests, so timers that reached their goal stop here.
+    for habit_id in ctx.timers.reached_goal() {
+        if let Err(e) = crate::timers::stop_in(storage, &ctx.timers, &habit_id) {
+            eprintln!("timers: failed to log {habit_id}: {e}");
+        }
+    }
+    loop {
+        match ctx.pomodoro.tick() {
+            Transition::None => break,
+            Transition::WorkDone {
+                habit_id: Some(habit_id),
+                minutes,
+            } => {
+                if let Err(e) = log_pomodoro(ctx, &habit_id, minutes) {
+                    eprintln!("pomodoro: failed to log progress: {e}");
+                }
+            }
+            Transition::WorkDone { .. } | Transition::BreakDone => {}
+        }
+    }
+
+    match method {
+        "sync_habits" => {
+            let SyncHabits { habits } = params(p)?;
+            crate::text::check_all_unique(&habits)?;
+            let stored = storage.list_habits().map_err(RpcError::server)?;
+            // Habits sent with a revision have to still be on it, and the rest on what
+            // was just read.
+            let mut expected = revisions::seen(&stored);
+            for habit in habits.iter().filter(|h| h.revision != 0) {
+                if let Some(revision) = expected.get_mut(&habit.id) {
+                    *revision = habit.revision;
+                }
+            }
+            let stale = storage
+                .replace_habits_checked(&habits, &expected)
+                .map_err(RpcError::server)?;
+            if !stale.is_empty() {
+                return Err(RpcError {
+                    code: CONFLICT,
+                    message: format!("changed since read: {}", stale.join(", ")),
+                });
+            }
+            Ok(Value::Null)
+        }
+        "list_habits" => to_value(storage.list_habits().map_err(RpcError::server)?),
+        "log_habit_value" => {
+            let LogHabitValue {
+                habit_id,
+                value,
+                date,
+                tags,
+                expected_revision,
+            } = params(p)?;
+            let (habit, _) = crate::habits::record_value_checked(
+                storage,
+                &habit_id,
+                day_arg(date.as_deref())?,
+                value,
+                tags,
+                expected_revision,
+            )?;
+            to_value(habit)
+        }
+        "log_lapse" => {
+            let LogLapse {
+                habit_id,
+                date,
+                note,
+                expected_revision,
+            } = params(p)?;
+            let (habit, _) = crate::habits::record_lapse_checked(
+                storage,
+                &habit_id,
+                day_arg(date.as_deref())?,
+                note,
+                expected_revision,
+            )?;
+            to_value(habit)
+        }
+        "list_check_ins" => {
+            let ListCheckIns { habit_id, date } = params(p)?;
+            to_value(crate::habits::check_ins(
+                storage,
+                &habit_id,
+                date.as_deref(),
+            )?)
+        }
+        "rebuild_streaks" => {
+            let RebuildStreaks { habit_id } = params(p)?;
+            to_value(
+                crate::streaks::rebuild_all(storage, habit_id.as_deref())
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "get_completions" => {
+            let GetCompletions {
+                habit_id,
+                cursor,
+                limit,
+                from,
+                to,
+            } = params(p)?;
+            to_value(
+                crate::history::page(
+                    storage,
+                    &habit_id,
+                    cursor.as_deref(),
+                    limit,
+                    from.as_deref(),
+                    to.as_deref(),
+                )
+                .map_err(RpcError::server)?,
+            )
+        }
+        "get_range_stats" => {
+            let GetRangeStats {
+                habit_ids,
+                from,
+                to,
+            } = params(p)?;
+            to_value(
+                crate::stats_cache::range_stats(storage, habit_ids, from.as_deref(), to.as_deref())
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "archive_habit" | "unarchive_habit" => {
+            let Archive {
+                habit_id,
+                expected_revision,
+            } = params(p)?;
+            to_value(crate::habits::set_archived_checked(
+                storage,
+                &habit_id,
+                method == "archive_habit",
+                expected_revision,
+            )?)
+        }
+        "list_due_habits" => {
+            let DateParam { date } = params(p)?;
+            to_value(crate::schedule::due(storage, day_arg(date.as_deref())?)?)
+        }
+        "list_categories" => to_value(storage.list_categories().map_err(RpcError::server)?),
+        "create_category" => {
+            let CreateCategory { name, color } = params(p)?;
+            to_value(crate::categories::create(storage, &name, color)?)
+        }
+        "update_category" => {
+            let UpdateCategory { id, name, color } = params(p)?;
+            let (category, _) = crate::categories::update(storage, &id, name, color)?;
+            to_value(category)
+        }
+        "reorder_categories" => {
+            let ByIds { ids } = params(p)?;
+            to_value(crate::categories::reorder(storage, &ids)?)
+        }
+        "delete_category" => {
+            let ById { id } = params(p)?;
+            crate::categories::remove(storage, &id)?;
+            Ok(Value::Null)
+        }
+        "assign_category" => {
+            let AssignCategory {
+                habit_id,
+                category_id,
+            } = params(p)?;
+            to_value(crate::categories::assign(storage, &habit_id, &category_id)?)
+        }
+        "add_checklist_item" => {
+            let AddChecklistItem { habit_id, name } = params(p)?;
+            to_value(crate::checklist::add_item(storage, &habit_id, &name)?)
+        }
+        "rename_checklist_item" => {
+            let RenameChecklistItem { item_id, name } = params(p)?;
+            to_value(crate::checklist::rename_item(storage, &item_id, &name)?)
+        }
+        "remove_checklist_item" => {
+            let ByItemId { item_id } = params(p)?;
+            to_value(crate::checklist::remove_item(storage, &item_id)?)
+        }
+        "reorder_checklist" => {
+            let ReorderChecklist { habit_id, item_ids } = params(p)?;
+            to_value(crate::checklist::reorder(storage, &habit_id, &item_ids)?)
+        }
+        "set_checklist_item_done" => {
+            let SetChecklistItemDone {
+                item_id,
+                done,
+                date,
+                expected_revision,
+            } = params(p)?;
+            let (habit, _) = crate::checklist::set_item_done_checked(
+                storage,
+                &item_id,
+                day_arg(date.as_deref())?,
+                done,
+                expected_revision,
+            )?;
+            to_value(habit)
+        }
+        "set_habit_dependencies" => {
+            let SetHabitDependencies {
+                habit_id,
+                depends_on,
+            } = params(p)?;
+            to_value(crate::dependencies::set_dependencies(
+                storage, &habit_id, depends_on,
+            )?)
+        }
+        "get_dependency_graph" => {
+            let DateParam { date } = params(p)?;
+            to_value(crate::dependencies::graph_on(
+                storage,
+                day_arg(date.as_deref())?,
+            )?)
+        }
+        "list_templates" => to_value(crate::templates::list(settings)),
+        "add_template" => {
+            let AddTemplate { template } = params(p)?;
+            to_value(crate::templates::add(settings, template)?)
+        }
+        "save_habit_as_template" => {
+            let SaveHabitAsTemplate { habit_id, name } = params(p)?;
+            to_value(crate::templates::save_habit(
+                settings, storage, &habit_id, name,
+            )?)
+        }
+        "share_template" => {
+            let ShareTemplate { template_id, scale } = params(p)?;
+            to_value(crate::templates::share(settings, &template_id, scale)?)
+        }
+        "import_shared_template" => {
+            let ImportSharedTemplate { link } = params(p)?;
+            to_value(crate::templates::import_link(settings, &link)?)
+        }
+        "remove_template" => {
+            let ById { id } = params(p)?;
+            crate::templates::remove(settings, &id).map_err(RpcError::server)?;
+            Ok(Value::Null)
+        }
+        "create_from_template" => {
+            let CreateFromTemplate {
+                template_id,
+                name,
+                reminder_time,
+            } = params(p)?;
+            let template =
+                crate::templates::find(settings, &template_id).map_err(RpcError::server)?;
+            let habit = crate::templates::instantiate(&template, name, reminder_time);
+            to_value(crate::habits::insert(storage, habit)?)
+        }
+        "log_mood" => {
+            let LogMood { score, date, tags } = params(p)?;
+            to_value(crate::mood::log(storage, score, date.as_deref(), tags)?)
+        }
+        "list_moods" => {
+            let ListMoods { from, to } = params(p)?;
+            to_value(
+                storage
+                    .list_moods(from.as_deref(), to.as_deref())
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "remove_mood" => {
+            let ByDate { date } = params(p)?;
+            crate::mood::remove(storage, &date)?;
+            Ok(Value::Null)
+        }
+        "get_year_review" => {
+            use chrono::Datelike;
+            let YearReviewParams { year } = params(p)?;
+            let year = year.unwrap_or_else(|| today().year());
+            to_value(
+                crate::review::year_review(storage, year, &mut |_, _| Ok(()))
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "get_mood_insights" => {
+            let MoodInsightsParams { days } = params(p)?;
+            to_value(crate::mood::insights(storage, days).map_err(RpcError::server)?)
+        }
+        "update_checkin_note" => {
+            let UpdateCheckinNote {
+                habit_id,
+                date,
+                note,
+                expected_revision,
+            } = params(p)?;
+            to_value(crate::notes::update_checked(
+                storage,
+                &habit_id,
+                &date,
+                note.as_deref(),
+                expected_revision,
+            )?)
+        }
+        "search_notes" => {
+            let SearchNotes { query, limit } = params(p)?;
+            to_value(crate::notes::search(storage, &query, limit).map_err(RpcError::server)?)
+        }
+        "set_habit_window" => {
+            let SetHabitWindow { habit_id, window } = params(p)?;
+            to_value(crate::time_windows::set_window(storage, &habit_id, window)?)
+        }
+        "add_off_period" => {
+            let AddOffPeriod {
+                start,
+                end,
+                habit_id,
+                reason,
+            } = params(p)?;
+            to_value(crate::off_periods::add(
+                storage,
+                &start,
+                end.as_deref(),
+                habit_id,
+                reason,
+            )?)
+        }
+        "list_off_periods" => {
+            let ListOffPeriods { habit_id } = params(p)?;
+            to_value(
+                storage
+                    .list_off_periods(habit_id.as_deref())
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "remove_off_period" => {
+            let ById { id } = params(p)?;
+            crate::off_periods::remove(storage, &id)?;
+            Ok(Value::Null)
+        }
+        "pause_habit" => {
+            let PauseHabit { habit_id, until } = params(p)?;
+            let until = date_arg(&until)?;
+            let habit = crate::pauses::pause(storage, &habit_id, until, today())?;
+            to_value(habit)
+        }
+        "resume_habit" => {
+            let ByHabitId { habit_id } = params(p)?;
+            let habit = crate::pauses::resume(storage, &habit_id, today())?;
+            to_value(habit)
+        }
+        "get_streak_freezes" => {
+            to_value(crate::freezes::summary(storage).map_err(RpcError::server)?)
+        }
+        "reorder_habits" => {
+            let ByIds { ids } = params(p)?;
+            storage.reorder_habits(&ids).map_err(RpcError::server)?;
+            to_value(storage.list_habits().map_err(RpcError::server)?)
+        }
+        // Nothing is queued here: `sync_habits` and `reorder_habits` write before replying.
+        "flush_writes" => Ok(Value::Null),
+        "set_habit_pinned" => {
+            let SetHabitPinned { habit_id, pinned } = params(p)?;
+            let habit = crate::ordering::set_pinned(storage, &habit_id, pinned)?;
+            to_value(habit)
+        }
+        "list_achievements" => {
+            to_value(crate::achievements::list(storage).map_err(RpcError::server)?)
+        }
+        "get_daily_challenge" => {
+            to_value(crate::challenges::today(storage, settings).map_err(RpcError::server)?)
+        }
+        "skip_daily_challenge" => {
+            to_value(crate::challenges::skip(storage, settings).map_err(RpcError::server)?)
+        }
+        "get_player_state" => {
+            to_value(crate::gamification::load(storage, settings).map_err(RpcError::server)?)
+        }
+        "set_habit_difficulty" => {
+            let SetHabitDifficulty {
+                habit_id,
+                difficulty,
+            } = params(p)?;
+            let habit = crate::gamification::set_difficulty(storage, &habit_id, difficulty)?;
+            to_value(habit)
+        }
+        "get_habit_order" => {
+            let ByView { view } = params(p)?;
+            let habits =
+                crate::ordering::for_view(storage, settings, view).map_err(RpcError::server)?;
+            to_value(habits.into_iter().map(|h| h.id).collect::<Vec<_>>())
+        }
+        "set_sort_preference" => {
+            let SetSortPreference { view, sort } = params(p)?;
+            to_value(crate::ordering::set_sort(settings, view, sort).map_err(RpcError::server)?)
+        }
+        "set_weekday_reminders" => {
+            let SetWeekdayReminders { habit_id, times } = params(p)?;
+            let habit = crate::reminders::set_weekday_times(storage, &habit_id, times)?;
+            to_value(habit)
+        }
+        "next_occurrences" => {
+            let NextOccurrences { habit_id, limit } = params(p)?;
+            to_value(crate::reminders::next(storage, habit_id.as_deref(), limit)?)
+        }
+        "backfill_completion" => {
+            let Backfill {
+                habit_id,
+                date,
+                expected_revision,
+            } = params(p)?;
+            to_value(crate::backfill::backfill_checked(
+                storage,
+                &settings.get().backfill,
+                &habit_id,
+                date_arg(&date)?,
+                expected_revision,
+            )?)
+        }
+        "set_times_per_day" => {
+            let SetTimesPerDay { habit_id, times } = params(p)?;
+            let habit = crate::repeats::set_times(storage, &habit_id, times)?;
+            to_value(habit)
+        }
+        "log_repetition" => {
+            let Repetition {
+                habit_id,
+                date,
+                expected_revision,
+            } = params(p)?;
+            let (habit, _) = crate::habits::record_value_checked(
+                storage,
+                &habit_id,
+                day_arg(date.as_deref())?,
+                1.0,
+                Vec::new(),
+                expected_revision,
+            )?;
+            to_value(habit)
+        }
+        "undo_repetition" => {
+            let Repetition {
+                habit_id,
+                date,
+                expected_revision,
+            } = params(p)?;
+            to_value(crate::repeats::undo_checked(
+                storage,
+                &habit_id,
+                day_arg(date.as_deref())?,
+                expected_revision,
+            )?)
+        }
+        "set_habit_cover" => {
+            let SetHabitCover { habit_id, path } = params(p)?;
+            to_value(crate::appearance::set_cover(
+                storage,
+                &ctx.data_dir,
+                &habit_id,
+                path.as_deref().map(std::path::Path::new),
+            )?)
+        }
+        "get_habit_cover" => {
+            let ByHabitId { habit_id } = params(p)?;
+            to_value(crate::appearance::cover_data_url(
+                storage,
+                &ctx.data_dir,
+                &habit_id,
+            )?)
+        }
+        "add_checkin_attachment" => {
+            let AddCheckinAttachment {
+                habit_id,
+                date,
+                path,
+            } = params(p)?;
+            to_value(
+                crate::attachments::add_in(
+                    storage,
+                    &ctx.data_dir,
+                    &habit_id,
+                    &date,
+                    std::path::Path::new(&path),
+                )?
+                .ok_or_else(|| AppError::not_found("habit", &habit_id))?,
+            )
+        }
+        "list_checkin_attachments" => {
+            let ListCheckIns { habit_id, date } = params(p)?;
+            to_value(
+                storage
+                    .list_attachments(&habit_id, date.as_deref())
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "get_checkin_attachment" => {
+            let ById { id } = params(p)?;
+            to_value(crate::attachments::data_url_in(
+                storage,
+                &ctx.data_dir,
+                &id,
+            )?)
+        }
+        "remove_checkin_attachment" => {
+            let ById { id } = params(p)?;
+            crate::attachments::remove_in(storage, &ctx.data_dir, &id)?;
+            Ok(Value::Null)
+        }
+        "set_habit_appearance" => {
+            let SetHabitAppearance {
+                habit_id,
+                color,
+                icon,
+                emoji,
+            } = params(p)?;
+            let habit = crate::appearance::update(storage, &habit_id, color, icon, emoji)?;
+            to_value(habit)
+        }
+        "add_milestone" => {
+            let AddMilestone {
+                habit_id,
+                kind,
+                threshold,
+            } = params(p)?;
+            to_value(crate::milestones::add(storage, &habit_id, kind, threshold)?)
+        }
+        "list_milestones" => {
+            let ListMilestones { habit_id } = params(p)?;
+            to_value(crate::milestones::list(storage, habit_id.as_deref())?)
+        }
+        "remove_milestone" => {
+            let ById { id } = params(p)?;
+            crate::milestones::remove(storage, &id)?;
+            Ok(Value::Null)
+        }
+        "add_goal" => {
+            let AddGoal {
+                title,
+                habit_id,
+                period,
+                target,
+                date,
+            } = params(p)?;
+            let date = day_arg(date.as_deref())?;
+            to_value(crate::goals::add(
+                storage,
+                &title,
+                &habit_id,
+                period,
+                target,
+                Some(date),
+            )?)
+        }
+        "get_goal_progress" => {
+            let GoalProgressParams { goal_id } = params(p)?;
+            to_value(crate::goals::list(storage, goal_id.as_deref())?)
+        }
+        "remove_goal" => {
+            let ById { id } = params(p)?;
+            crate::goals::remove(storage, &id)?;
+            Ok(Value::Null)
+        }
+        "list_rewards" => {
+            let ListRewards { limit } = params(p)?;
+            to_value(crate::rewards::overview(
+                storage,
+                settings,
+                limit.unwrap_or(crate::rewards::HISTORY_LIMIT),
+            )?)
+        }
+        "add_reward" => {
+            let AddReward { name, cost } = params(p)?;
+            to_value(crate::rewards::add(storage, &name, cost)?)
+        }
+        "remove_reward" => {
+            let ById { id } = params(p)?;
+            crate::rewards::remove(storage, &id)?;
+            Ok(Value::Null)
+        }
+        "redeem_reward" => {
+            let RedeemReward { reward_id } = params(p)?;
+            to_value(crate::rewards::redeem(storage, settings, &reward_id)?)
+        }
+        "parse_quick_add" => {
+            let ParseQuickAdd { text, locale } = params(p)?;
+            to_value(crate::quick_add::parse(&text, locale.as_deref(), today())?)
+        }
+        "list_tags" => {
+            let ListTags { prefix, limit } = params(p)?;
+            to_value(
+                crate::tags::suggest(storage, prefix.as_deref(), limit)
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "set_habit_tags" => {
+            let SetHabitTags { habit_id, tags } = params(p)?;
+            to_value(crate::tags::set_for_habit(storage, &habit_id, tags)?)
+        }
+        "list_by_tag" => {
+            let ByTag { tag } = params(p)?;
+            to_value(crate::tags::tagged(storage, &tag).map_err(RpcError::server)?)
+        }
+        "get_tag_stats" => {
+            let ByTag { tag } = params(p)?;
+            to_value(crate::tags::summary(storage, &tag)?)
+        }
+        "get_settings" => to_value(settings.get()),
+        "update_settings" => {
+            let UpdateSettings { new_settings } = params(p)?;
+            to_value(crate::commands::save_settings(
+                settings,
+                new_settings,
+                |_| {},
+            )?)
+        }
+        "get_feature_flags" => to_value(crate::features::get_feature_flags()),
+        "set_locale" => {
+            let SetLocale { locale } = params(p)?;
+            to_value(crate::i18n::save_locale(settings, locale)?)
+        }
+        "set_log_level" => {
+            let SetLogLevel { level } = params(p)?;
+            crate::logging::save_level(settings, level)?;
+            Ok(Value::Null)
+        }
+        "generate_qr" => {
+            let GenerateQr {
+                payload,
+                scale,
+                level,
+            } = params(p)?;
+            to_value(
+                crate::qr::generate(&payload, scale, level.unwrap_or_default())
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "get_screen_time" => {
+            let DateParam { date } = params(p)?;
+            to_value(crate::screen_time::report(
+                storage,
+                &settings.get().screen_time,
+                day_arg(date.as_deref())?,
+            )?)
+        }
+        "load_demo_data" => to_value(crate::demo::load_into(storage)?),
+        "reset_to_fresh" => to_value(crate::demo::reset_in(storage, &ctx.data_dir)?),
+        "seed_synthetic_data" => {
+            let SeedSyntheticData { habits, years } = params(p)?;
+            to_value(crate::seed::seed_into(storage, habits, years)?)
+        }
+        "regenerate_api_token" => {
+            to_value(settings.regenerate_api_token().map_err(RpcError::server)?)
+        }
+        "list_webhooks" => to_value(settings.get().webhooks),
+        "add_webhook" => {
+            let AddWebhook { url, events } = params(p)?;
+            to_value(crate::webhooks::add(settings, url, events).map_err(RpcError::server)?)
+        }
+        "remove_webhook" => {
+            let ById { id } = params(p)?;
+            crate::webhooks::remove(settings, &id).map_err(RpcError::server)?;
+            Ok(Value::Null)
+        }
+        "get_webhook_deliveries" => {
+            let Limit { limit } = params(p)?;
+            to_value(
+                storage
+                    .list_webhook_deliveries(limit.unwrap_or(100))
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "list_export_jobs" => to_value(settings.get().export_jobs),
+        "add_export_job" => {
+            let AddExportJob {
+                name,
+                format,
+                directory,
+                schedule,
+                passphrase,
+            } = params(p)?;
+            to_value(crate::exports::add(
+                settings, name, format, directory, schedule, passphrase,
+            )?)
+        }
+        "remove_export_job" => {
+            let ById { id } = params(p)?;
+            crate::exports::remove(settings, &id).map_err(RpcError::server)?;
+            Ok(Value::Null)
+        }
+        "run_export_job_now" => {
+            let ById { id } = params(p)?;
+            let job = crate::exports::find(settings, &id)?;
+            to_value(crate::exports::run_job(storage, &job))
+        }
+        "export_data" => {
+            let ExportData {
+                path,
+                format,
+                passphrase,
+            } = params(p)?;
+            let path = crate::exports::export_to(
+                storage,
+                std::path::Path::new(&path),
+                format,
+                passphrase.as_deref(),
+            )
+            .map_err(RpcError::server)?;
+            to_value(path.display().to_string())
+        }
+        "import_data" => {
+            let ImportData { path, passphrase } = params(p)?;
+            to_value(
+                crate::exports::read_export(std::path::Path::new(&path), passphrase.as_deref())
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "get_export_history" => {
+            let ExportHistory { job_id, limit } = params(p)?;
+            to_value(
+                storage
+                    .list_export_runs(job_id.as_deref(), limit.unwrap_or(100))
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "commit_data_version" => {
+            let CommitDataVersion { message } = params(p)?;
+            to_value(crate::versioning::commit(
+                storage,
+                &repository(ctx),
+                message,
+            )?)
+        }
+        "list_data_versions" => {
+            let Limit { limit } = params(p)?;
+            to_value(
+                crate::versioning::list_versions(&repository(ctx), limit.unwrap_or(100) as usize)
+                    .map_err(RpcError::server)?,
+            )
+        }
+        "restore_data_version" => {
+            let ById { id } = params(p)?;
+            to_value(crate::versioning::restore(storage, &repository(ctx), &id)?)
+        }
+        "set_completion" => {
+            let SetCompletion {
+                habit_id,
+                date,
+                done,
+            } = params(p)?;
+            let date = day_arg(date.as_deref())?;
+            let rules = settings.get().backfill;
+            let (habit, _) = storage
+                .transaction(|tx| {
+                    crate::habits::record_completion(tx, &rules, &habit_id, date, done)
+                })?
+                .ok_or_else(|| AppError::not_found("habit", &habit_id))?;
+            to_value(habit)
+        }
+        "get_stats" => {
+            let StatsParams {
+                tag,
+                exclude_backfilled,
+            } = params(p)?;
+            let mut habits = storage.list_habits().map_err(RpcError::server)?;
+            if let Some(tag) = tag {
+                habits = crate::tags::with_tag(habits, &tag);
+            }
+            if exclude_backfilled {
+                habits = crate::backfill::without_backfilled(habits);
+            }
+            let categories = storage.list_categories().map_err(RpcError::server)?;
+            to_value(stats::summarize(&habits, &categories, today()))
+        }
+        // The lock belongs to a window, so names are only left out in privacy mode.
+        "get_today_snapshot" => to_value(crate::widgets::snapshot_of(storage, settings, false)?),
+        "import_legacy_state" => {
+            let ImportLegacyState { json } = params(p)?;
+            to_value(crate::legacy::import_into(storage, &json)?)
+        }
+        "rotate_encryption_key" => {
+            let RotateEncryptionKey { old, new } = params(p)?;
+            to_value(crate::key_rotation::rotate_with(
+                storage,
+                settings,
+                &old,
+                &new,
+                &mut |_| {},
+            )?)
+        }
+        "check_habit_name" => {
+            let CheckHabitName { name, habit_id } = params(p)?;
+            to_value(crate::text::check_name(
+                storage,
+                &name,
+                habit_id.as_deref(),
+            )?)
+        }
+        "run_maintenance_now" => {
+            to_value(crate::maintenance::run_in(storage, &ctx.data_dir, true)?)
+        }
+        "get_maintenance_report" => to_value(crate::maintenance::last_report(storage)),
+        "get_timers" => to_value(ctx.timers.list()),
+        "start_timer" => {
+            let ByHabitId { habit_id } = params(p)?;
+            to_value(crate::timers::start_in(storage, &ctx.timers, &habit_id)?)
+        }
+        "pause_timer" => {
+            let ByHabitId { habit_id } = params(p)?;
+            to_value(crate::timers::pause_in(&ctx.timers, &habit_id)?)
+        }
+        "stop_timer" => {
+            let ByHabitId { habit_id } = params(p)?;
+            let stopped = crate::timers::stop_in(storage, &ctx.timers, &habit_id)?
+                .ok_or_else(|| AppError::not_found("timer", &habit_id))?;
+            to_value(stopped.habit)
+        }
+        "get_pomodoro" => to_value(ctx.pomodoro.status()),
+        "start_pomodoro" => {
+            let StartPomodoro { habit_id } = params(p)?;
+            to_value(crate::pomodoro::start_in(
+                storage,
+                settings,
+                &ctx.pomodoro,
+                habit_id,
+            )?)
+        }
+        "pause_pomodoro" | "resume_pomodoro" => to_value(crate::pomodoro::set_running_in(
+            &ctx.pomodoro,
+            method == "resume_pomodoro",
+        )?),
+        "skip_pomodoro_phase" => to_value(crate::pomodoro::skip_in(&ctx.pomodoro)?),
+        "stop_pomodoro" => {
+            crate::pomodoro::stop_in(&ctx.pomodoro)?;
+            Ok(Value::Null)
+        }
+        "start_job" => {
+            let StartJob { request } = params(p)?;
+            to_value(ctx.jobs.run_now(storage, request))
+        }
+        "get_job" => {
+            let ById { id } = params(p)?;
+            to_value(
+                ctx.jobs
+                    .get(&id)
+                    .ok_or_else(|| AppError::not_found("job", &id))?,
+            )
+        }
+        "list_jobs" => to_value(ctx.jobs.list()),
+        "get_lock_status" => to_value(lock_status(ctx)?),
+        "lock_app" => {
+            if !crate::lock::pin_required(storage)? {
+                return Err(AppError::Invalid("set a PIN first".into()).into());
+            }
+            ctx.locked.store(true, Ordering::SeqCst);
+            to_value(lock_status(ctx)?)
+        }
+        "unlock_app" => {
+            let Pin { pin } = params(p)?;
+            crate::lock::unlock_headless(storage, &pin)?;
+            ctx.locked.store(false, Ordering::SeqCst);
+            to_value(lock_status(ctx)?)
+        }
+        "set_app_pin" => {
+            let SetAppPin { pin, current_pin } = params(p)?;
+            crate::lock::set_pin_headless(storage, &pin, current_pin.as_deref())?;
+            to_value(lock_status(ctx)?)
+        }
+        "remove_app_pin" => {
+            let Pin { pin } = params(p)?;
+            crate::lock::remove_pin_headless(storage, &pin)?;
+            to_value(lock_status(ctx)?)
+        }
+        "cancel_job" => {
+            let ById { id } = params(p)?;
+            to_value(
+                ctx.jobs
+                    .cancel(&id)
+                    .ok_or_else(|| AppError::not_found("job", &id))?,
+            )
+        }
+        _ => Err(RpcError {
+            code: METHOD_NOT_FOUND,
+            message: format!("unknown method {method}"),
+        }),
+    }
+}
@@ -0,0 +1,311 @@
+//! How often a habit is meant to happen, and what that means for "due" and streaks.
+//!
+//! - `daily`: every day
+//! - `weekly` / `monthly`: `goal` times per calendar week (starting on the day
+//!   `calendar.rs` says) or month; streaks count consecutive periods that met the quota
+//! - `interval`: every `goal` days ("every other day" is 2); streaks count completions
+//!   that each came within `goal` days of the previous one
+//!
+//! Excused days (off periods) are neutral: nothing is due on them, they lower a period's
+//! quota to the days that are left, and they don't count toward interval gaps. A period
+//! that is excused throughout neither extends nor breaks a streak.
+
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::Serialize;
+
+use crate::dependencies;
+use crate::error::AppError;
+use crate::model::{Habit, HabitKind};
+use crate::stats::{date_key, parse_date};
+use crate::storage::Storage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Schedule {
+    Daily,
+    Weekly { goal: u32 },
+    Monthly { goal: u32 },
+    Interval { days: u32 },
+}
+
+impl Schedule {
+    /// Unknown frequency types fall back to daily.
+    pub fn of(habit: &Habit) -> Self {
+        let goal = habit.frequency.goal.max(1);
+        match habit.frequency.kind.as_str() {
+            "weekly" => Schedule::Weekly { goal },
+            "monthly" => Schedule::Monthly { goal },
+            "interval" => Schedule::Interval { days: goal },
+            _ => Schedule::Daily,
+        }
+    }
+}
+
+/// Calendar period (inclusive) that `date` falls in. Interval schedules don't have one.
+fn period_containing(schedule: Schedule, date: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+    match schedule {
+        Schedule::Daily => Some((date, date)),
+        Schedule::Weekly { .. } => Some(crate::calendar::week_containing(date)),
+        Schedule::Monthly { .. } => {
+            let start = date.with_day(1)?;
+            Some((start, start + Months::new(1) - Days::new(1)))
+        }
+        Schedule::Interval { .. } => None,
+    }
+}
+
+fn goal(schedule: Schedule) -> u32 {
+    match schedule {
+        Schedule::Weekly { goal } | Schedule::Monthly { goal } => goal,
+        Schedule::Daily | Schedule::Interval { .. } => 1,
+    }
+}
+
+fn count_done(habit: &Habit, start: NaiveDate, end: NaiveDate) -> u32 {
+    start
+        .iter_days()
+        .take_while(|d| *d <= end)
+        .filter(|d| habit.is_done_on(&date_key(*d)))
+        .count() as u32
+}
+
+/// Excused days in `start..=end`.
+pub(crate) fn count_excused(habit: &Habit, start: NaiveDate, end: NaiveDate) -> u32 {
+    if habit.off_periods.is_empty() {
+        return 0;
+    }
+    start
+        .iter_days()
+        .take_while(|d| *d <= end)
+        .filter(|d| habit.is_excused(&date_key(*d)))
+        .count() as u32
+}
+
+/// The quota of `start..=end`, capped at the days in it that aren't excused.
+fn period_goal(habit: &Habit, schedule: Schedule, (start, end): (NaiveDate, NaiveDate)) -> u32 {
+    let days = (end - start).num_days() as u32 + 1;
+    goal(schedule).min(days - count_excused(habit, start, end))
+}
+
+/// Days from `from` to `to`, leaving out excused ones.
+fn gap(habit: &Habit, from: NaiveDate, to: NaiveDate) -> i64 {
+    let excused = from
+        .succ_opt()
+        .map_or(0, |next| count_excused(habit, next, to));
+    (to - from).num_days() - i64::from(excused)
+}
+
+/// Progress toward the quota of the period containing a date. For interval schedules the
+/// "period" is the window of `goal` days ending on that date.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeriodProgress {
+    pub start: String,
+    pub end: String,
+    pub done: u32,
+    pub goal: u32,
+    pub met: bool,
+}
+
+fn window(habit: &Habit, schedule: Schedule, date: NaiveDate) -> (NaiveDate, NaiveDate) {
+    match schedule {
+        Schedule::Interval { days } => {
+            // `days` days that aren't excused, ending on `date`.
+            let mut start = date;
+            let mut counted = u32::from(!habit.is_excused(&date_key(date)));
+            while counted < days {
+                match start.pred_opt() {
+                    Some(prev) => start = prev,
+                    None => break,
+                }
+                if !habit.is_excused(&date_key(start)) {
+                    counted += 1;
+                }
+            }
+            (start, date)
+        }
+        _ => period_containing(schedule, date).unwrap_or((date, date)),
+    }
+}
+
+pub fn progress(habit: &Habit, date: NaiveDate) -> PeriodProgress {
+    let schedule = Schedule::of(habit);
+    let (start, end) = window(habit, schedule, date);
+    let done = count_done(habit, start, end);
+    let goal = match schedule {
+        Schedule::Interval { .. } => 1,
+        _ => period_goal(habit, schedule, (start, end)),
+    };
+    PeriodProgress {
+        start: date_key(start),
+        end: date_key(end),
+        done,
+        goal,
+        met: done >= goal,
+    }
+}
+
+/// Still needs doing on `date`: today not checked off for daily habits, quota not yet met
+/// for weekly/monthly ones, nothing in the last `goal` days for interval ones. Never on
+/// an excused day.
+pub fn is_due(habit: &Habit, date: NaiveDate) -> bool {
+    if habit.archived || habit.kind == HabitKind::Avoid || habit.is_excused(&date_key(date)) {
+        return false;
+    }
+    !progress(habit, date).met
+}
+
+/// Due, and skipping `date` would miss the period's quota.
+pub fn is_at_risk(habit: &Habit, date: NaiveDate) -> bool {
+    if !is_due(habit, date) {
+        return false;
+    }
+    let schedule = Schedule::of(habit);
+    match period_containing(schedule, date) {
+        Some((_, end))
+            if matches!(schedule, Schedule::Weekly { .. } | Schedule::Monthly { .. }) =>
+        {
+            let p = progress(habit, date);
+            let days_after = date
+                .succ_opt()
+                .filter(|next| *next <= end)
+                .map_or(0, |next| {
+                    (end - date).num_days() as u32 - count_excused(habit, next, end)
+                });
+            p.goal - p.done > days_after
+        }
+        _ => true,
+    }
+}
+
+/// Current streak for non-daily schedules; daily ones are handled in `stats`.
+pub fn streak(habit: &Habit, today: NaiveDate) -> u32 {
+    let schedule = Schedule::of(habit);
+    match schedule {
+        Schedule::Daily => 0,
+        Schedule::Weekly { .. } | Schedule::Monthly { .. } => {
+            let Some(mut period) = period_containing(schedule, today) else {
+                return 0;
+            };
+            // The current period only adds to the streak; it can't break it while open.
+            let mut streak = match period_met(habit, schedule, period) {
+                Some(true) => 1,
+                _ => 0,
+            };
+            while let Some(prev) = period
+                .0
+                .pred_opt()
+                .and_then(|d| period_containing(schedule, d))
+            {
+                match period_met(habit, schedule, prev) {
+                    Some(true) => streak += 1,
+                    Some(false) => break,
+                    None => {}
+                }
+                period = prev;
+            }
+            streak
+        }
+        Schedule::Interval { days } => {
+            let dates: Vec<NaiveDate> = habit
+                .done_dates()
+                .into_iter()
+                .filter_map(parse_date)
+                .filter(|d| *d <= today)
+                .collect();
+            let Some(last) = dates.last() else {
+                return 0;
+            };
+            if gap(habit, *last, today) > i64::from(days) {
+                return 0;
+            }
+            let mut streak = 1;
+            for pair in dates.windows(2).rev() {
+                if gap(habit, pair[0], pair[1]) > i64::from(days) {
+                    break;
+                }
+                streak += 1;
+            }
+            streak
+        }
+    }
+}
+
+/// Whether a calendar period met its quota, or `None` if it was excused throughout.
+fn period_met(habit: &Habit, schedule: Schedule, period: (NaiveDate, NaiveDate)) -> Option<bool> {
+    match period_goal(habit, schedule, period) {
+        0 => None,
+        goal => Some(count_done(habit, period.0, period.1) >= goal),
+    }
+}
+
+/// Longest streak for non-daily schedules, in the same units as [`streak`].
+pub fn longest_streak(habit: &Habit) -> u32 {
+    let schedule = Schedule::of(habit);
+    let dates: Vec<NaiveDate> = habit
+        .done_dates()
+        .into_iter()
+        .filter_map(parse_date)
+        .collect();
+    let mut longest = 0;
+    let mut current = 0;
+
+    match schedule {
+        Schedule::Daily => {}
+        Schedule::Weekly { .. } | Schedule::Monthly { .. } => {
+            // Every period from the first completion's to the last one's, in order.
+            let mut period = dates.first().and_then(|d| period_containing(schedule, *d));
+            let last = dates.last().copied();
+            while let Some(p) = period.filter(|p| last.is_some_and(|l| p.0 <= l)) {
+                match period_met(habit, schedule, p) {
+                    Some(true) => current += 1,
+                    Some(false) => current = 0,
+                    None => {}
+                }
+                longest = longest.max(current);
+                period = p.1.succ_opt().and_then(|d| period_containing(schedule, d));
+            }
+        }
+        Schedule::Interval { days } => {
+            let mut prev: Option<NaiveDate> = None;
+            for d in dates {
+                current = match prev {
+                    Some(p) if gap(habit, p, d) <= i64::from(days) => current + 1,
+                    _ => 1,
+                };
+                longest = longest.max(current);
+                prev = Some(d);
+            }
+        }
+    }
+    longest
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DueHabit {
+    pub habit_id: String,
+    pub name: String,
+    pub progress: PeriodProgress,
+    /// Skipping today would miss this period's quota.
+    pub at_risk: bool,
+}
+
+/// [`due_on`] for the stored habits.
+pub fn due(storage: &Storage, date: NaiveDate) -> Result<Vec<DueHabit>, AppError> {
+    Ok(due_on(&storage.list_habits()?, date))
+}
+
+/// Non-archived habits still due on `date`, in list order. Habits waiting on a prerequisite
+/// aren't due yet.
+pub fn due_on(habits: &[Habit], date: NaiveDate) -> Vec<DueHabit> {
+    habits
+        .iter()
+        .filter(|h| is_due(h, date) && !dependencies::is_blocked(h, habits, date))
+        .map(|h| DueHabit {
+            habit_id: h.id.clone(),
+            name: h.name.clone(),
+            progress: progress(h, date),
+            at_risk: is_at_risk(h, date),
+        })
+        .collect()
+}
@@ -0,0 +1,204 @@
+//! Background clock for time-based backend work: detects day rollovers (including ones
+//! that happened while the app wasn't running), writes made by other processes, and
+//! drives per-minute reminders, automation hooks, scheduled exports, the tray's
+//! next-reminder line and database maintenance.
+
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use tauri::{AppHandle, Manager};
+
+use crate::automation::Automations;
+use crate::events::{EventBus, HabitEvent, STREAK_MILESTONES};
+use crate::i18n::{t, t_args};
+use crate::model::HabitKind;
+use crate::stats::{current_streak, date_key, parse_date, today};
+use crate::storage::Storage;
+
+const TICK: Duration = Duration::from_secs(30);
+const LAST_ACTIVE_DAY_KEY: &str = "scheduler.last_active_day";
+
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(TICK);
+        let mut data_version = None;
+        let mut last_minute = String::new();
+        loop {
+            interval.tick().await;
+            check_external_writes(&app, &mut data_version);
+            check_rollover(&app);
+
+            // Ticks are shorter than a minute, so every minute is seen at least once.
+            let minute = crate::clock::now().format("%H:%M").to_string();
+            if minute != last_minute {
+                app.state::<Automations>()
+                    .call("on_minute", minute.clone().into());
+                crate::reminders::on_minute(&app, &minute);
+                crate::exports::run_due(&app);
+                crate::challenges::check(&app);
+                crate::tray::update_next_reminder(&app);
+                crate::maintenance::run_due(&app);
+                last_minute = minute;
+            }
+        }
+    });
+}
+
+/// Picks up writes from the headless CLI so the UI and integrations see them.
+fn check_external_writes(app: &AppHandle, last: &mut Option<i64>) {
+    let version = match app.state::<Storage>().data_version() {
+        Ok(v) => v,
+        Err(e) => {
+            log::error!("scheduler: failed to read data version: {e}");
+            return;
+        }
+    };
+    if last.is_some_and(|last| last != version) {
+        crate::habits::notify_changed(app);
+        app.state::<EventBus>().data_changed();
+    }
+    *last = Some(version);
+}
+
+fn check_rollover(app: &AppHandle) {
+    let storage = app.state::<Storage>();
+    let today = today();
+    let last = match storage.get_meta(LAST_ACTIVE_DAY_KEY) {
+        Ok(last) => last.as_deref().and_then(parse_date),
+        Err(e) => {
+            log::error!("scheduler: failed to read last active day: {e}");
+            return;
+        }
+    };
+
+    if let Some(previous) = last.filter(|l| *l < today) {
+        on_day_rollover(app, previous, today);
+    }
+    if last != Some(today) {
+        if let Err(e) = storage.set_meta(LAST_ACTIVE_DAY_KEY, &date_key(today)) {
+            log::error!("scheduler: failed to store last active day: {e}");
+        }
+    }
+}
+
+/// `previous` is the last day the app saw, `today` the new one.
+fn on_day_rollover(app: &AppHandle, previous: NaiveDate, today: NaiveDate) {
+    log::info!(
+        "day rollover: {} -> {}",
+        date_key(previous),
+        date_key(today)
+    );
+    spend_streak_freezes(app, previous, today);
+    report_broken_streaks(app, previous, today);
+    report_avoidance_milestones(app, today);
+    resume_paused(app, previous, today);
+    crate::challenges::on_day_rollover(app);
+    crate::social::on_day_rollover(app, previous, today);
+    crate::screen_time::on_day_rollover(app, previous);
+    // "Done today" flips for everything.
+    app.state::<EventBus>().data_changed();
+    app.state::<Automations>()
+        .call("on_day_rollover", date_key(today).into());
+    crate::plugins::on_day_rollover(app, &date_key(today));
+    crate::versioning::on_day_rollover(app, &date_key(today));
+}
+
+/// Paused habits whose `until` has come are active again; their off period just ended.
+fn resume_paused(app: &AppHandle, previous: NaiveDate, today: NaiveDate) {
+    let habits = match crate::pauses::resumed(&app.state::<Storage>(), previous, today) {
+        Ok(h) => h,
+        Err(e) => {
+            log::error!("scheduler: failed to check paused habits: {e}");
+            return;
+        }
+    };
+    let bus = app.state::<EventBus>();
+    let hide_names = crate::privacy::hide_names(app);
+    if hide_names && !habits.is_empty() {
+        let body = t_args("welcome-back-hidden", &[("count", habits.len().into())]);
+        crate::reminders::notify(app, &t("welcome-back-title"), &body);
+    }
+    for habit in habits {
+        if !hide_names {
+            crate::reminders::notify(
+                app,
+                &t("welcome-back-title"),
+                &t_args(
+                    "welcome-back-habit",
+                    &[("name", crate::appearance::label(&habit).into())],
+                ),
+            );
+        }
+        bus.publish(HabitEvent::HabitResumed {
+            habit_id: habit.id,
+            name: habit.name,
+        });
+    }
+}
+
+/// Runs before `report_broken_streaks` so a streak saved by a freeze isn't reported.
+fn spend_streak_freezes(app: &AppHandle, previous: NaiveDate, today: NaiveDate) {
+    match crate::freezes::settle(&app.state::<Storage>(), previous, today) {
+        Ok(events) => {
+            let bus = app.state::<EventBus>();
+            for event in events {
+                bus.publish(event);
+            }
+        }
+        Err(e) => log::error!("scheduler: failed to settle streak freezes: {e}"),
+    }
+}
+
+/// A streak that was alive at the end of `previous` and isn't anymore was broken by the
+/// rollover: a missed day for daily habits, a missed quota for weekly/monthly ones, a
+/// too-long gap for interval ones.
+fn report_broken_streaks(app: &AppHandle, previous: NaiveDate, today: NaiveDate) {
+    let habits = match app.state::<Storage>().list_habits() {
+        Ok(h) => h,
+        Err(e) => {
+            log::error!("scheduler: failed to load habits: {e}");
+            return;
+        }
+    };
+    let bus = app.state::<EventBus>();
+    for habit in habits
+        .iter()
+        .filter(|h| !h.archived && h.kind == HabitKind::Build)
+    {
+        let streak = current_streak(habit, previous);
+        if streak > 0 && current_streak(habit, today) == 0 {
+            bus.publish(HabitEvent::StreakBroken {
+                habit_id: habit.id.clone(),
+                name: habit.name.clone(),
+                streak,
+            });
+        }
+    }
+}
+
+/// Avoidance streaks grow with the calendar rather than with check-ins, so their milestones
+/// are reached at midnight. (Their breaks are reported when the lapse is logged.)
+fn report_avoidance_milestones(app: &AppHandle, today: NaiveDate) {
+    let habits = match app.state::<Storage>().list_habits() {
+        Ok(h) => h,
+        Err(e) => {
+            log::error!("scheduler: failed to load habits: {e}");
+            return;
+        }
+    };
+    let bus = app.state::<EventBus>();
+    for habit in habits
+        .iter()
+        .filter(|h| !h.archived && h.kind == HabitKind::Avoid)
+    {
+        let streak = current_streak(habit, today);
+        if STREAK_MILESTONES.contains(&streak) {
+            bus.publish(HabitEvent::StreakMilestone {
+                habit_id: habit.id.clone(),
+                name: habit.name.clone(),
+                streak,
+            });
+        }
+    }
+}
@@ -0,0 +1,431 @@
+//! Desktop screen time, for screen-limit habits ("less than 2h of social media").
+//!
+//! While `settings.screenTime` is on, the app in the foreground is sampled every few
+//! seconds and the time is added to today's total for it, under the first category whose
+//! apps or window titles match. Time away from the keyboard and mouse, and time with the
+//! session locked, doesn't count. Only app names are stored; window titles are matched
+//! and dropped. Everything stays on this device.
+//!
+//! At day rollover each limit judges its habit by the day that ended, if anything was
+//! tracked on it: a build habit is checked off when the time stayed under the limit, and
+//! an avoidance habit gets a lapse when it went over. The same samples drive the warnings
+//! and blocking in `blocking.rs`.
+//!
+//! The foreground app comes from the window manager on Windows, `lsappinfo` on macOS (no
+//! window titles there) and `xprop` on X11. Wayland sessions have no way to ask, so
+//! nothing is tracked there.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::model::HabitKind;
+use crate::settings::{ScreenCategory, ScreenLimit, ScreenTimeSettings, SettingsStore};
+use crate::stats::{date_key, day_arg, parse_date, today};
+use crate::storage::{ScreenTimeEntry, Storage};
+
+const SAMPLE: Duration = Duration::from_secs(5);
+/// The last day limits were applied to, so a restart doesn't apply them twice.
+const JUDGED_KEY: &str = "screen_time.judged";
+
+/// What's in the foreground.
+pub(crate) struct Foreground {
+    pub app: String,
+    pub title: Option<String>,
+    /// Its process, where the platform says.
+    pub pid: Option<u32>,
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::path::Path;
+
+    use windows_sys::Win32::Foundation::CloseHandle;
+    use windows_sys::Win32::System::Threading::{
+        OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+        PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId,
+    };
+
+    use super::Foreground;
+
+    /// The foreground window's title and the file name of its program.
+    pub fn foreground() -> Option<Foreground> {
+        unsafe {
+            let window = GetForegroundWindow();
+            if window.is_null() {
+                return None;
+            }
+            let mut title = [0u16; 512];
+            let len = GetWindowTextW(window, title.as_mut_ptr(), title.len() as i32);
+            let title = String::from_utf16_lossy(&title[..len.max(0) as usize]);
+
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(window, &mut pid);
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+            if process.is_null() {
+                return None;
+            }
+            let mut path = [0u16; 1024];
+            let mut size = path.len() as u32;
+            let ok = QueryFullProcessImageNameW(
+                process,
+                PROCESS_NAME_WIN32,
+                path.as_mut_ptr(),
+                &mut size,
+            );
+            CloseHandle(process);
+            if ok == 0 {
+                return None;
+            }
+            let path = String::from_utf16_lossy(&path[..size as usize]);
+            let app = Path::new(&path).file_stem()?.to_string_lossy().into_owned();
+            Some(Foreground {
+                app,
+                title: (!title.is_empty()).then_some(title),
+                pid: Some(pid),
+            })
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use std::process::Command;
+
+    use super::Foreground;
+
+    fn run(args: &[&str]) -> Option<String> {
+        let output = Command::new("lsappinfo").args(args).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// One field of the app's info, e.g. `"LSDisplayName"="Safari"`.
+    fn field(app: &str, name: &str) -> Option<String> {
+        let info = run(&["info", "-only", name, app])?;
+        let (_, value) = info.split_once('=')?;
+        Some(value.trim().trim_matches('"').to_string())
+    }
+
+    /// The frontmost app's display name and process.
+    pub fn foreground() -> Option<Foreground> {
+        let front = run(&["front"])?;
+        let app = field(&front, "name").filter(|a| !a.is_empty())?;
+        Some(Foreground {
+            app,
+            title: None,
+            pid: field(&front, "pid").and_then(|p| p.parse().ok()),
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::process::Command;
+
+    use super::Foreground;
+
+    fn xprop(args: &[&str]) -> Option<String> {
+        let output = Command::new("xprop").args(args).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// The quoted values after `name(TYPE) = ` in `xprop`'s output.
+    fn values<'a>(output: &'a str, name: &str) -> Vec<&'a str> {
+        output
+            .lines()
+            .find(|l| l.starts_with(&format!("{name}(")))
+            .and_then(|l| l.split_once(" = "))
+            .map(|(_, v)| v.split(", ").map(|s| s.trim().trim_matches('"')).collect())
+            .unwrap_or_default()
+    }
+
+    /// The active window's class (the program) and title, on X11.
+    pub fn foreground() -> Option<Foreground> {
+        std::env::var_os("DISPLAY")?;
+        let root = xprop(&["-root", "_NET_ACTIVE_WINDOW"])?;
+        let id = root.split_whitespace().next_back()?;
+        if id == "0x0" {
+            return None;
+        }
+        let window = xprop(&["-id", id, "WM_CLASS", "_NET_WM_NAME", "_NET_WM_PID"])?;
+        let app = values(&window, "WM_CLASS").last()?.to_string();
+        let title = values(&window, "_NET_WM_NAME")
+            .first()
+            .map(|t| t.to_string());
+        let pid = values(&window, "_NET_WM_PID")
+            .first()
+            .and_then(|p| p.parse().ok());
+        Some(Foreground { app, title, pid })
+    }
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+mod imp {
+    use super::Foreground;
+
+    pub fn foreground() -> Option<Foreground> {
+        None
+    }
+}
+
+/// The first category the app or its window title is in.
+pub(crate) fn category<'a>(
+    categories: &'a [ScreenCategory],
+    foreground: &Foreground,
+) -> Option<&'a str> {
+    let app = foreground.app.to_lowercase();
+    let title = foreground
+        .title
+        .as_deref()
+        .unwrap_or_default()
+        .to_lowercase();
+    let matches = |patterns: &[String], text: &str| {
+        !text.is_empty()
+            && patterns
+                .iter()
+                .map(|p| p.trim().to_lowercase())
+                .any(|p| !p.is_empty() && text.contains(&p))
+    };
+    categories
+        .iter()
+        .find(|c| matches(&c.apps, &app) || matches(&c.titles, &title))
+        .map(|c| c.name.as_str())
+}
+
+/// Adds `elapsed` to the foreground app's time, unless the user is away. Returns what's
+/// in the foreground.
+fn sample(
+    storage: &Storage,
+    settings: &ScreenTimeSettings,
+    elapsed: Duration,
+) -> Option<Foreground> {
+    let idle_limit = Duration::from_secs(u64::from(settings.idle_minutes) * 60);
+    if crate::idle::session_locked() == Some(true)
+        || crate::idle::idle_time().is_some_and(|idle| idle >= idle_limit)
+    {
+        return None;
+    }
+    let foreground = imp::foreground()?;
+    let category = category(&settings.categories, &foreground).unwrap_or_default();
+    let result = storage.add_screen_time(
+        &date_key(today()),
+        category,
+        &foreground.app,
+        elapsed.as_secs(),
+    );
+    if let Err(e) = result {
+        log::error!("screen time: failed to record a sample: {e}");
+    }
+    Some(foreground)
+}
+
+/// Seconds per category in `entries`.
+fn totals(entries: &[ScreenTimeEntry]) -> BTreeMap<&str, u64> {
+    let mut totals = BTreeMap::new();
+    for entry in entries {
+        *totals.entry(entry.category.as_str()).or_default() += entry.seconds;
+    }
+    totals
+}
+
+/// Checks off or lapses each limit's habit for `date`.
+fn judge(app: &AppHandle, date: NaiveDate, limits: &[ScreenLimit]) -> Result<(), String> {
+    let storage = app.state::<Storage>();
+    let key = date_key(date);
+    let entries = storage.screen_time(&key).map_err(|e| e.to_string())?;
+    if entries.is_empty() {
+        // Nothing was tracked, so there's nothing to go by.
+        return Ok(());
+    }
+    let totals = totals(&entries);
+    for limit in limits {
+        let Some(habit) = storage
+            .get_habit(&limit.habit_id)
+            .map_err(|e| e.to_string())?
+            .filter(|h| !h.archived && !h.is_excused(&key))
+        else {
+            continue;
+        };
+        let used = totals.get(limit.category.as_str()).copied().unwrap_or(0);
+        let over = used > u64::from(limit.max_minutes) * 60;
+        match habit.kind {
+            HabitKind::Build if !over && !habit.is_done_on(&key) => {
+                crate::habits::set_completion(app, &habit.id, date, true)
+                    .map_err(|e| e.to_string())?;
+            }
+            HabitKind::Avoid if over && !habit.lapses.iter().any(|l| l.date == key) => {
+                let note = crate::i18n::t_args(
+                    "screen-time-lapse",
+                    &[
+                        ("minutes", (used / 60).into()),
+                        ("category", limit.category.as_str().into()),
+                    ],
+                );
+                crate::habits::log_lapse(app, &habit.id, date, Some(note))
+                    .map_err(|e| e.to_string())?;
+            }
+            _ => continue,
+        }
+        log::info!(
+            "screen time: {} {key}: {} of {} minutes",
+            habit.id,
+            used / 60,
+            limit.max_minutes
+        );
+    }
+    Ok(())
+}
+
+/// Applies the limits to `previous`, the day that just ended.
+pub fn on_day_rollover(app: &AppHandle, previous: NaiveDate) {
+    let settings = app.state::<SettingsStore>().get().screen_time;
+    if settings.limits.is_empty() {
+        return;
+    }
+    let storage = app.state::<Storage>();
+    let judged = storage.get_meta(JUDGED_KEY).ok().flatten();
+    if judged.as_deref().and_then(parse_date) >= Some(previous) {
+        return;
+    }
+    if let Err(e) = judge(app, previous, &settings.limits) {
+        log::error!("screen time: failed to apply the limits: {e}");
+    }
+    if let Err(e) = storage.set_meta(JUDGED_KEY, &date_key(previous)) {
+        log::error!("screen time: {e}");
+    }
+}
+
+/// Samples the foreground app while screen time is on, and enforces the block rules.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut ticks = tokio::time::interval(SAMPLE);
+        let mut last = Instant::now();
+        loop {
+            ticks.tick().await;
+            // Longer gaps are the computer sleeping, not use.
+            let elapsed = last.elapsed().min(SAMPLE * 2);
+            last = Instant::now();
+            let settings = app.state::<SettingsStore>().get().screen_time;
+            if elapsed.as_secs() == 0 {
+                continue;
+            }
+            let a = app.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || {
+                let foreground = settings
+                    .enabled
+                    .then(|| sample(&a.state::<Storage>(), &settings, elapsed))
+                    .flatten();
+                crate::blocking::enforce(&a, &settings, foreground.as_ref());
+            })
+            .await;
+        }
+    });
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppTime {
+    pub app: String,
+    pub seconds: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryTime {
+    /// `""` for apps in no category.
+    pub category: String,
+    pub seconds: u64,
+    /// Most used first.
+    pub apps: Vec<AppTime>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitStatus {
+    pub habit_id: String,
+    pub category: String,
+    pub max_minutes: u32,
+    pub used_minutes: u64,
+    pub over: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenTimeReport {
+    pub date: String,
+    pub enabled: bool,
+    pub total_seconds: u64,
+    /// Most used first.
+    pub categories: Vec<CategoryTime>,
+    pub limits: Vec<LimitStatus>,
+}
+
+/// Time per category and app on `date`, and where each limit in `settings` stands.
+pub fn report(
+    storage: &Storage,
+    settings: &ScreenTimeSettings,
+    date: NaiveDate,
+) -> Result<ScreenTimeReport, AppError> {
+    let key = date_key(date);
+    let entries = storage.screen_time(&key)?;
+    let totals = totals(&entries);
+    let mut categories: Vec<CategoryTime> = totals
+        .iter()
+        .map(|(&category, &seconds)| CategoryTime {
+            category: category.to_string(),
+            seconds,
+            apps: entries
+                .iter()
+                .filter(|e| e.category == category)
+                .map(|e| AppTime {
+                    app: e.app.clone(),
+                    seconds: e.seconds,
+                })
+                .collect(),
+        })
+        .collect();
+    categories.sort_by_key(|c| std::cmp::Reverse(c.seconds));
+    let limits = settings
+        .limits
+        .iter()
+        .map(|limit| {
+            let used = totals.get(limit.category.as_str()).copied().unwrap_or(0);
+            LimitStatus {
+                habit_id: limit.habit_id.clone(),
+                category: limit.category.clone(),
+                max_minutes: limit.max_minutes,
+                used_minutes: used / 60,
+                over: used > u64::from(limit.max_minutes) * 60,
+            }
+        })
+        .collect();
+    Ok(ScreenTimeReport {
+        date: key,
+        enabled: settings.enabled,
+        total_seconds: totals.values().sum(),
+        categories,
+        limits,
+    })
+}
+
+/// Time per category and app on `date` (today when left out), and where each limit stands.
+#[tauri::command]
+pub fn get_screen_time(app: AppHandle, date: Option<String>) -> Result<ScreenTimeReport, AppError> {
+    let date = day_arg(date.as_deref())?;
+    let settings = app.state::<SettingsStore>().get().screen_time;
+    report(&app.state::<Storage>(), &settings, date)
+}
@@ -0,0 +1,200 @@
+//! Credentials for integrations (the MQTT broker, the sync server, webhook signing keys
+//! under `webhook.*`, and any integration added later under `integrations.*`), kept in the OS keychain: Keychain, Credential
+//! Manager or Secret Service. `settings.json` never holds them; values found there from
+//! older versions are moved over on load (see `absorb`). Errors and logs name the key,
+//! never the value.
+
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::settings::{Settings, SettingsStore};
+use crate::storage::Storage;
+
+const SERVICE: &str = "HabitFlow";
+pub const MQTT_PASSWORD: &str = "mqtt.password";
+pub const SOCIAL_SECRET: &str = "social.secret";
+/// The sync server's bearer token (`sync_queue.rs`).
+pub const SYNC_TOKEN: &str = "sync.token";
+/// The remote libSQL database's auth token (`replica.rs`).
+pub const LIBSQL_TOKEN: &str = "libsql.token";
+/// The app lock's PIN hash (`lock.rs`); not reachable through the commands.
+pub(crate) const LOCK_PIN: &str = "app-lock-pin";
+const INTEGRATION_PREFIX: &str = "integrations.";
+const WEBHOOK_PREFIX: &str = "webhook.";
+/// Meta key: the `integrations.*` keys with a value, as a JSON list, since the keychain
+/// can't list them.
+const INTEGRATIONS_KEY: &str = "secrets.integrations";
+
+/// The key a webhook's signing secret is kept under (`webhooks.rs`).
+pub fn webhook_key(id: &str) -> String {
+    format!("{WEBHOOK_PREFIX}{id}")
+}
+
+fn entry(key: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(SERVICE, key).map_err(|e| format!("keychain unavailable: {e}"))
+}
+
+pub fn get(key: &str) -> Result<Option<String>, String> {
+    match entry(key)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!("failed to read {key} from the keychain: {e}")),
+    }
+}
+
+pub fn set(key: &str, value: &str) -> Result<(), String> {
+    entry(key)?
+        .set_password(value)
+        .map_err(|e| format!("failed to store {key} in the keychain: {e}"))
+}
+
+/// Returns whether there was a value.
+pub fn delete(key: &str) -> Result<bool, String> {
+    match entry(key)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(format!("failed to remove {key} from the keychain: {e}")),
+    }
+}
+
+/// Keys the commands accept: integration credentials only, so the lock's PIN and the
+/// sync secret can't be overwritten from the webview.
+fn user_key(key: &str) -> Result<(), String> {
+    let integration = key.strip_prefix(INTEGRATION_PREFIX).is_some_and(|name| {
+        !name.is_empty()
+            && name.len() <= 64
+            && name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'_'))
+    });
+    if key == MQTT_PASSWORD || key == SYNC_TOKEN || key == LIBSQL_TOKEN || integration {
+        Ok(())
+    } else {
+        Err(format!("{key:?} isn't a secret that can be set"))
+    }
+}
+
+/// Moves credentials out of `settings` into the keychain, clearing them there. Returns
+/// whether anything was found. Values the keychain can't take are dropped with a warning
+/// rather than written to disk.
+pub fn absorb(settings: &mut Settings) -> bool {
+    let mut found = vec![
+        (MQTT_PASSWORD.to_string(), settings.mqtt.password.take()),
+        (
+            SOCIAL_SECRET.to_string(),
+            Some(std::mem::take(&mut settings.social.secret)).filter(|s| !s.is_empty()),
+        ),
+    ];
+    for hook in &mut settings.webhooks {
+        let secret = std::mem::take(&mut hook.secret);
+        found.push((
+            webhook_key(&hook.id),
+            Some(secret).filter(|s| !s.is_empty()),
+        ));
+    }
+    let mut any = false;
+    for (key, value) in found {
+        let Some(value) = value else {
+            continue;
+        };
+        any = true;
+        if let Err(e) = set(&key, &value) {
+            log::warn!("secrets: {e}; it has to be entered again");
+        }
+    }
+    any
+}
+
+fn integration_keys(storage: &Storage) -> Vec<String> {
+    storage
+        .get_meta(INTEGRATIONS_KEY)
+        .ok()
+        .flatten()
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+/// Notes whether an `integrations.*` key has a value.
+fn note_integration(app: &AppHandle, key: &str, set: bool) {
+    if !key.starts_with(INTEGRATION_PREFIX) {
+        return;
+    }
+    let storage = app.state::<Storage>();
+    let mut keys = integration_keys(&storage);
+    keys.retain(|k| k != key);
+    if set {
+        keys.push(key.to_string());
+    }
+    let saved = serde_json::to_string(&keys)
+        .map_err(|e| e.to_string())
+        .and_then(|json| {
+            storage
+                .set_meta(INTEGRATIONS_KEY, &json)
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = saved {
+        log::warn!("secrets: failed to note {key}: {e}");
+    }
+}
+
+/// Every key that can have a value worth keeping out of logs: the fixed ones, the
+/// integrations', the webhooks' and the encrypted export jobs'. The PIN is only a hash.
+pub fn known_keys(app: &AppHandle) -> Vec<String> {
+    let mut keys: Vec<String> = [MQTT_PASSWORD, SOCIAL_SECRET, SYNC_TOKEN, LIBSQL_TOKEN]
+        .map(String::from)
+        .into();
+    keys.extend(integration_keys(&app.state::<Storage>()));
+    let settings = app.state::<SettingsStore>().get();
+    keys.extend(settings.webhooks.iter().map(|h| webhook_key(&h.id)));
+    keys.extend(
+        settings
+            .export_jobs
+            .iter()
+            .filter(|j| j.encrypted)
+            .map(|j| crate::exports::passphrase_key(&j.id)),
+    );
+    keys
+}
+
+/// Reconnects whatever uses `key`.
+fn changed(app: &AppHandle, key: &str) {
+    crate::redact::refresh_secrets(app);
+    if key == MQTT_PASSWORD {
+        crate::mqtt::apply(app);
+    }
+    if key == SYNC_TOKEN {
+        crate::sync_queue::wake(app);
+    }
+    if key == LIBSQL_TOKEN {
+        crate::replica::wake(app);
+    }
+}
+
+#[tauri::command]
+pub fn store_secret(app: AppHandle, key: String, value: String) -> Result<(), AppError> {
+    user_key(&key)?;
+    if value.is_empty() {
+        return Err(AppError::Invalid("the secret is empty".into()));
+    }
+    set(&key, &value)?;
+    note_integration(&app, &key, true);
+    changed(&app, &key);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn delete_secret(app: AppHandle, key: String) -> Result<(), AppError> {
+    user_key(&key)?;
+    if delete(&key)? {
+        note_integration(&app, &key, false);
+        changed(&app, &key);
+    }
+    Ok(())
+}
+
+/// Whether `key` has a value; the value itself never goes back to the webview.
+#[tauri::command]
+pub fn has_secret(key: String) -> Result<bool, AppError> {
+    user_key(&key)?;
+    Ok(get(&key).map(|v| v.is_some())?)
+}
@@ -0,0 +1,210 @@
+//! `seed_synthetic_data`, for trying the app against years of history: generates habits
+//! from the template catalog and fills in their past the way people tend to keep habits.
+//! Each has its own steadiness, good and bad days come in runs, weekends slip, and now and
+//! then a whole week is skipped. Quantified habits get logged amounts, checklists their
+//! items, avoidance habits the odd lapse, and some days a note.
+//!
+//! Seeded habits have ids starting with `synthetic-`. Seeding again replaces them and
+//! leaves every other habit alone. Debug builds only.
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use rand::Rng;
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::{Habit, HabitKind, Lapse};
+use crate::stats::{date_key, today};
+use crate::storage::Storage;
+
+pub const SYNTHETIC_PREFIX: &str = "synthetic-";
+
+const MAX_HABITS: u32 = 1000;
+const MAX_YEARS: u32 = 20;
+/// Every this many habits, one is an avoidance habit, and one is archived.
+const AVOID_EVERY: u32 = 7;
+const ARCHIVE_EVERY: u32 = 10;
+const AVOID_NAMES: &[&str] = &["No Sugar", "No Social Media at Night", "No Smoking"];
+/// How much more likely a day goes the same way as the one before.
+const MOMENTUM: f64 = 0.15;
+const WEEKEND_SLIP: f64 = 0.15;
+const SKIPPED_WEEK_RATE: f64 = 0.03;
+const LAPSE_RATE: f64 = 0.04;
+const NOTE_RATE: f64 = 0.05;
+const NOTES: &[&str] = &[
+    "Felt great afterwards",
+    "Hard to get started today",
+    "Did it first thing in the morning",
+    "Squeezed it in late",
+    "Easier than expected",
+    "Tired, but showed up",
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeedSummary {
+    pub habits: usize,
+    pub completions: usize,
+    pub lapses: usize,
+    pub notes: usize,
+    /// Synthetic habits from an earlier seed that were replaced.
+    pub replaced: usize,
+}
+
+/// How likely the habit is done on a given day, before momentum and weekends.
+fn base_rate(habit: &Habit, rng: &mut impl Rng) -> f64 {
+    let steadiness = rng.random_range(0.45..0.92);
+    match habit.frequency.kind.as_str() {
+        "weekly" => steadiness * f64::from(habit.frequency.goal.min(7)) / 7.0,
+        "monthly" => steadiness * f64::from(habit.frequency.goal.min(30)) / 30.0,
+        "interval" => steadiness / f64::from(habit.frequency.goal.max(1)),
+        _ => steadiness,
+    }
+}
+
+pub(crate) fn fill_history(
+    habit: &mut Habit,
+    first: NaiveDate,
+    last: NaiveDate,
+    rng: &mut impl Rng,
+) {
+    let rate = base_rate(habit, rng);
+    let (mut yesterday, mut skipping) = (rng.random_bool(rate), false);
+    for day in first.iter_days().take_while(|d| *d <= last) {
+        if day.weekday() == Weekday::Mon {
+            skipping = rng.random_bool(SKIPPED_WEEK_RATE);
+        }
+        let key = date_key(day);
+        if habit.kind == HabitKind::Avoid {
+            if rng.random_bool(LAPSE_RATE) {
+                habit.lapses.push(Lapse {
+                    logged_at: format!("{key}T21:00:00Z"),
+                    date: key,
+                    note: None,
+                });
+            }
+            continue;
+        }
+        let mut chance = rate + if yesterday { MOMENTUM } else { -MOMENTUM };
+        if matches!(day.weekday(), Weekday::Sat | Weekday::Sun) {
+            chance -= WEEKEND_SLIP;
+        }
+        let done = !skipping && rng.random_bool(chance.clamp(0.02, 0.98));
+        yesterday = done;
+        if !done {
+            continue;
+        }
+        if let Some(target) = &habit.target {
+            let amount = target.amount * rng.random_range(0.6..1.4);
+            habit.values.insert(key.clone(), amount.round());
+            if amount.round() < target.amount {
+                continue;
+            }
+        }
+        for item in &mut habit.checklist {
+            item.done.insert(key.clone());
+        }
+        if rng.random_bool(NOTE_RATE) {
+            let note = NOTES[rng.random_range(0..NOTES.len())];
+            habit.notes.insert(key.clone(), note.to_string());
+        }
+        habit.logs.insert(key, true);
+    }
+}
+
+/// `habits` habits with up to `years` of history each, ending today.
+pub fn generate(habits: u32, years: u32) -> Vec<Habit> {
+    let mut rng = rand::rng();
+    let today = today();
+    let start = today
+        .checked_sub_days(Days::new(u64::from(years) * 365))
+        .unwrap_or(today);
+    let span = (today - start).num_days().max(1) as u64;
+    let templates = crate::templates::builtin();
+    (0..habits)
+        .map(|i| {
+            let template = &templates[i as usize % templates.len()];
+            let round = i as usize / templates.len();
+            let mut habit = crate::templates::instantiate(template, None, None);
+            habit.id = format!("{SYNTHETIC_PREFIX}{i}");
+            if round > 0 {
+                habit.name = format!("{} {}", habit.name, round + 1);
+            }
+            if i % AVOID_EVERY == AVOID_EVERY - 1 {
+                habit.kind = HabitKind::Avoid;
+                habit.frequency = Default::default();
+                habit.name = AVOID_NAMES[(i / AVOID_EVERY) as usize % AVOID_NAMES.len()].into();
+                habit.target = None;
+                habit.checklist.clear();
+            }
+            // The first few go back the whole way; later ones were picked up along it.
+            let first = if i < 3 {
+                start
+            } else {
+                start + Days::new(rng.random_range(0..span))
+            };
+            habit.created_at = format!("{}T08:00:00Z", date_key(first));
+            fill_history(&mut habit, first, today, &mut rng);
+            if i % ARCHIVE_EVERY == ARCHIVE_EVERY - 1 {
+                habit.archived = true;
+                habit.archived_at = Some(chrono::Utc::now().to_rfc3339());
+            }
+            habit
+        })
+        .collect()
+}
+
+/// Replaces earlier synthetic habits with fresh ones and pushes the result to the webview.
+pub fn seed(app: &AppHandle, habits: u32, years: u32) -> Result<SeedSummary, AppError> {
+    let summary = seed_into(&app.state::<Storage>(), habits, years)?;
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(app);
+    Ok(summary)
+}
+
+/// [`seed`] without telling the webview.
+pub fn seed_into(storage: &Storage, habits: u32, years: u32) -> Result<SeedSummary, AppError> {
+    if !cfg!(debug_assertions) {
+        return Err(AppError::Forbidden(
+            "synthetic data can only be seeded in debug builds".into(),
+        ));
+    }
+    if !(1..=MAX_HABITS).contains(&habits) || !(1..=MAX_YEARS).contains(&years) {
+        return Err(AppError::Invalid(format!(
+            "seed 1 to {MAX_HABITS} habits with 1 to {MAX_YEARS} years of history"
+        )));
+    }
+    let generated = generate(habits, years);
+    let summary = SeedSummary {
+        habits: generated.len(),
+        completions: generated.iter().map(|h| h.logs.len()).sum(),
+        lapses: generated.iter().map(|h| h.lapses.len()).sum(),
+        notes: generated.iter().map(|h| h.notes.len()).sum(),
+        replaced: 0,
+    };
+
+    let (earlier, mut kept): (Vec<Habit>, Vec<Habit>) = storage
+        .list_habits()?
+        .into_iter()
+        .partition(|h| h.id.starts_with(SYNTHETIC_PREFIX));
+    kept.extend(generated);
+    storage.replace_habits(&kept)?;
+    Ok(SeedSummary {
+        replaced: earlier.len(),
+        ..summary
+    })
+}
+
+/// Developer tool: fills the database with `habits` generated habits and up to `years` of
+/// history. Runs off the main thread, since large seeds take a few seconds.
+#[tauri::command]
+pub async fn seed_synthetic_data(
+    app: AppHandle,
+    habits: u32,
+    years: u32,
+) -> Result<SeedSummary, AppError> {
+    tauri::async_runtime::spawn_blocking(move || seed(&app, habits, years))
+        .await
+        .map_err(|e| AppError::Failed(e.to_string()))?
+}
@@ -0,0 +1,752 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::model::{HabitFrequency, HabitKind, HabitTarget};
+
+pub const SETTINGS_FILE_NAME: &str = "settings.json";
+
+/// Backend-side preferences, persisted as `settings.json` in the app data dir.
+/// Every field has a default so older files keep loading as new sections are added.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub api: ApiSettings,
+    pub webhooks: Vec<WebhookConfig>,
+    pub mqtt: MqttSettings,
+    /// Keyed by plugin id. Plugins without an entry are disabled.
+    pub plugins: BTreeMap<String, PluginGrant>,
+    pub export_jobs: Vec<ExportJob>,
+    pub quit_export: QuitExportSettings,
+    pub versioning: VersioningSettings,
+    /// User-defined habit templates; the built-in catalog is in `templates.rs`.
+    pub templates: Vec<HabitTemplate>,
+    pub pomodoro: PomodoroSettings,
+    pub focus: FocusSettings,
+    pub sort: SortSettings,
+    pub backfill: BackfillSettings,
+    pub gamification: GamificationSettings,
+    pub social: SocialSettings,
+    pub lock: LockSettings,
+    pub privacy: PrivacySettings,
+    pub files: FileSettings,
+    pub backup_drive: BackupDriveSettings,
+    pub screen_time: ScreenTimeSettings,
+    pub mobile: MobileSettings,
+    pub sync: SyncSettings,
+    pub network: NetworkSettings,
+    pub libsql: LibsqlSettings,
+    pub updates: UpdateSettings,
+    pub logging: LoggingSettings,
+    pub maintenance: MaintenanceSettings,
+    pub telemetry: TelemetrySettings,
+    pub language: LanguageSettings,
+    pub calendar: CalendarSettings,
+    /// Feature flag name => on, overriding the build's default (`features.rs`).
+    pub features: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Bearer token required on every request. Generated on first launch.
+    pub token: String,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 4757,
+            token: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MqttSettings {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    /// Only read from older files; the password is in the keychain (`secrets.rs`).
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    pub client_id: String,
+    /// Root for state/command topics, e.g. `habitflow/state`.
+    pub topic_prefix: String,
+    /// Publish Home Assistant MQTT discovery configs.
+    pub discovery: bool,
+    pub discovery_prefix: String,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: "localhost".into(),
+            port: 1883,
+            username: None,
+            password: None,
+            client_id: "habitflow".into(),
+            topic_prefix: "habitflow".into(),
+            discovery: true,
+            discovery_prefix: "homeassistant".into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookConfig {
+    pub id: String,
+    pub url: String,
+    /// `HabitEvent::kind()` values this hook receives.
+    pub events: Vec<String>,
+    /// HMAC-SHA256 key for the `X-HabitFlow-Signature` header. Kept in the keychain under
+    /// `webhook.<id>` (`secrets.rs`); only read here from older settings files.
+    #[serde(default, skip_serializing)]
+    pub secret: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VersioningSettings {
+    /// Commit a snapshot to the history repository on every day rollover.
+    pub enabled: bool,
+    /// Repository folder; empty means `<app data>/history`.
+    pub repository: String,
+}
+
+/// Interval lengths in minutes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PomodoroSettings {
+    pub work_minutes: u32,
+    pub short_break_minutes: u32,
+    pub long_break_minutes: u32,
+    /// Every this many work sessions, the break is a long one.
+    pub sessions_before_long_break: u32,
+}
+
+impl Default for PomodoroSettings {
+    fn default() -> Self {
+        Self {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            sessions_before_long_break: 4,
+        }
+    }
+}
+
+/// Focus mode (`focus.rs`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FocusSettings {
+    /// Length of a session started from the tray or without `minutes`.
+    pub minutes: u32,
+}
+
+impl Default for FocusSettings {
+    fn default() -> Self {
+        Self { minutes: 25 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackfillSettings {
+    /// How many days back `backfill_completion` reaches; 0 means no limit.
+    pub max_days: u32,
+}
+
+impl Default for BackfillSettings {
+    fn default() -> Self {
+        Self { max_days: 7 }
+    }
+}
+
+/// XP and levels; off unless the user opts in.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct GamificationSettings {
+    pub enabled: bool,
+    /// A small challenge each day (`get_daily_challenge`), worth bonus XP.
+    pub daily_challenges: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FileSettings {
+    /// The folder the last backup was saved to; set only by the save dialog (`files.rs`).
+    pub export_dir: Option<String>,
+}
+
+/// Copying backups to an external drive when it's plugged in (`backup_drive.rs`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupDriveSettings {
+    pub enabled: bool,
+    /// The drive's volume label, as `list_volumes` reports it.
+    pub label: String,
+    /// The drive's volume ID; when set, the label isn't looked at.
+    pub volume_id: String,
+    /// Where on the drive the backups go.
+    pub folder: String,
+}
+
+impl Default for BackupDriveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            label: String::new(),
+            volume_id: String::new(),
+            folder: "HabitFlow Backups".into(),
+        }
+    }
+}
+
+/// Desktop screen time, for screen-limit habits (`screen_time.rs`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScreenTimeSettings {
+    pub enabled: bool,
+    /// Time away from the keyboard and mouse past this doesn't count.
+    pub idle_minutes: u32,
+    pub categories: Vec<ScreenCategory>,
+    pub limits: Vec<ScreenLimit>,
+    /// Warnings and blocks while avoidance habits are in force (`blocking.rs`).
+    pub rules: Vec<BlockRule>,
+}
+
+impl Default for ScreenTimeSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            idle_minutes: 5,
+            categories: Vec::new(),
+            limits: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Apps and window titles that count as one kind of use. Matches are case-insensitive
+/// substrings; the first category that matches gets the time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScreenCategory {
+    /// "Social media".
+    pub name: String,
+    /// "discord", "slack".
+    pub apps: Vec<String>,
+    /// For use in a browser: "twitter", "reddit", "youtube".
+    pub titles: Vec<String>,
+    /// Sites blocked through the hosts file while a `block` rule is on: "reddit.com".
+    pub domains: Vec<String>,
+}
+
+/// A habit that's judged by the time spent in a category each day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenLimit {
+    pub habit_id: String,
+    /// A category's `name`.
+    pub category: String,
+    pub max_minutes: u32,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BlockMode {
+    /// A notification when the category comes to the foreground.
+    #[default]
+    Warn,
+    /// Its apps are closed and its domains blocked.
+    Block,
+}
+
+/// Keeps a category out of reach while an avoidance habit is in force.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockRule {
+    pub habit_id: String,
+    /// A category's `name`.
+    pub category: String,
+    #[serde(default)]
+    pub mode: BlockMode,
+    /// When in the day the rule applies ("no gaming before 6pm" is 00:00 to 18:00); all
+    /// day without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<crate::model::TimeWindow>,
+}
+
+/// The Android widget and Quick Settings tile (`widgets.rs`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MobileSettings {
+    /// The habit the tile toggles; the first pinned habit when unset.
+    pub tile_habit_id: Option<String>,
+}
+
+/// The outbound sync queue (`sync_queue.rs`). The server's token is `sync.token` in the
+/// keychain.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SyncSettings {
+    pub enabled: bool,
+    /// Base URL of the sync server, e.g. `https://sync.example.com`.
+    pub server_url: String,
+    /// Sent with every batch; generated when sync is first used.
+    pub device_id: String,
+}
+
+/// Keeping the database in a remote libSQL/Turso database as well (`replica.rs`). The
+/// database's auth token is `libsql.token` in the keychain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LibsqlSettings {
+    pub enabled: bool,
+    /// `libsql://my-db-me.turso.io`, or an `https://` URL.
+    pub url: String,
+    /// How often to look for changes from other devices, in seconds. Local changes go out
+    /// shortly after they're made.
+    pub interval_secs: u32,
+}
+
+impl Default for LibsqlSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            url: String::new(),
+            interval_secs: 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReleaseChannel {
+    #[default]
+    Stable,
+    /// Pre-releases too, and stable releases when they're newer.
+    Beta,
+}
+
+/// The desktop auto-updater (`updater.rs`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UpdateSettings {
+    pub channel: ReleaseChannel,
+    /// Look for an update on launch and once a day, and say when there is one.
+    pub check_automatically: bool,
+}
+
+impl Default for UpdateSettings {
+    fn default() -> Self {
+        Self {
+            channel: ReleaseChannel::Stable,
+            check_automatically: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+/// The connectivity monitor (`network.rs`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkSettings {
+    /// Off treats the network as always up.
+    pub monitor: bool,
+    /// `host:port`s tried in turn; reaching any of them counts as online.
+    pub probe_hosts: Vec<String>,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            monitor: true,
+            probe_hosts: vec![
+                "1.1.1.1:443".into(),
+                "8.8.8.8:443".into(),
+                "9.9.9.9:443".into(),
+            ],
+        }
+    }
+}
+
+/// The log files (`logging.rs`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LoggingSettings {
+    pub level: LogLevel,
+}
+
+/// Database cleanup in the background (`maintenance.rs`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct MaintenanceSettings {
+    pub enabled: bool,
+    /// Days between runs.
+    pub interval_days: u32,
+    /// How long the computer must have gone without input before a run starts.
+    pub idle_minutes: u32,
+}
+
+impl Default for MaintenanceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_days: 7,
+            idle_minutes: 10,
+        }
+    }
+}
+
+/// Anonymous usage counters (`telemetry.rs`), off until turned on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TelemetrySettings {
+    pub enabled: bool,
+    /// Random, made when telemetry is turned on and cleared when it's turned off.
+    pub install_id: String,
+}
+
+/// The language of the tray, notifications and dialogs (`i18n.rs`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LanguageSettings {
+    /// A BCP 47 tag such as `de` or `pt-BR`; empty follows the system.
+    pub locale: String,
+}
+
+/// The first day of a week, for weekly quotas and week totals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WeekStart {
+    /// Whatever's usual where the locale's region is.
+    #[default]
+    Auto,
+    Monday,
+    Saturday,
+    Sunday,
+}
+
+/// How dates the backend writes out for people look, such as snapshot names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DateFormat {
+    /// The locale's usual order and separator.
+    #[default]
+    Auto,
+    /// `2026-10-14`
+    Iso,
+    DayMonthYear,
+    MonthDayYear,
+}
+
+/// Weeks and dates (`calendar.rs`). Both follow `language.locale` unless set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CalendarSettings {
+    pub week_start: WeekStart,
+    pub date_format: DateFormat,
+}
+
+/// What `privacy.rs` keeps out of notifications and the tray.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PrivacySettings {
+    pub hide_habit_names: bool,
+}
+
+/// The app lock's PIN lives in the OS keyring (`lock.rs`), not here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LockSettings {
+    /// Also unlock with Touch ID, Windows Hello or the device's biometrics.
+    pub biometric: bool,
+    /// Lock after this many minutes without keyboard or mouse input; 0 never does.
+    pub auto_lock_minutes: u32,
+    /// Lock when the OS session locks or the machine suspends.
+    pub lock_with_system: bool,
+}
+
+impl Default for LockSettings {
+    fn default() -> Self {
+        Self {
+            biometric: false,
+            auto_lock_minutes: 5,
+            lock_with_system: true,
+        }
+    }
+}
+
+/// The opt-in friend leaderboard (`social.rs`). Only weekly aggregates are shared, and
+/// only the ones switched on here.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SocialSettings {
+    pub enabled: bool,
+    /// Base URL of the sync server, e.g. `https://sync.example.com`.
+    pub server_url: String,
+    /// Shown to friends; empty shows the friend code instead.
+    pub display_name: String,
+    /// Generated on opt-in, shared with friends out of band.
+    pub friend_code: String,
+    /// Proves to the server that `friend_code` is ours. Kept in the keychain
+    /// (`secrets.rs`) and only filled in while `social.rs` needs it; never written here.
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub friends: Vec<Friend>,
+    pub share_xp: bool,
+    pub share_streak: bool,
+    pub share_completion_rate: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Friend {
+    pub code: String,
+    /// Local only; never sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    pub added_at: String,
+}
+
+/// How each view orders habits. Pinned habits come first in all of them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SortSettings {
+    pub main: HabitSort,
+    pub tray: HabitSort,
+    pub widget: HabitSort,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum HabitSort {
+    /// The order set with `reorder_habits`.
+    #[default]
+    Manual,
+    Name,
+    /// Longest current streak first.
+    Streak,
+    /// Newest first.
+    Created,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportJob {
+    pub id: String,
+    pub name: String,
+    pub format: ExportFormat,
+    /// Target folder; a leading `~` is the home directory.
+    pub directory: String,
+    pub schedule: ExportSchedule,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// RFC 3339. Occurrences before this don't count as missed runs.
+    pub created_at: String,
+    /// Files are passphrase-encrypted (`archive.rs`); the passphrase is in the keychain.
+    #[serde(default)]
+    pub encrypted: bool,
+}
+
+/// An export written every time the app quits (`exports::export_on_quit`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QuitExportSettings {
+    pub enabled: bool,
+    pub format: ExportFormat,
+    /// Target folder; a leading `~` is the home directory.
+    pub directory: String,
+}
+
+/// Starting point for a new habit. `checklist` holds item names.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HabitTemplate {
+    /// Assigned when the template is saved.
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub category: String,
+    pub color: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub frequency: HabitFrequency,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reminder_time: Option<String>,
+    #[serde(default, skip_serializing_if = "HabitKind::is_build")]
+    pub kind: HabitKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<HabitTarget>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub checklist: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Part of the built-in catalog, so it can't be removed.
+    #[serde(default)]
+    pub builtin: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ExportFormat {
+    /// Same shape as the app's backup files, so it can be imported again.
+    #[default]
+    Json,
+    Csv,
+}
+
+/// `time` is local `HH:MM`; `weekday` is 0 = Sunday like JS `getDay()`; `day` past the
+/// end of a month means its last day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "every",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum ExportSchedule {
+    Daily { time: String },
+    Weekly { weekday: u32, time: String },
+    Monthly { day: u32, time: String },
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct PluginGrant {
+    pub enabled: bool,
+    /// Capabilities the user granted; a subset of what the manifest requests.
+    pub capabilities: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+pub struct SettingsStore {
+    path: PathBuf,
+    current: Mutex<Settings>,
+}
+
+impl SettingsStore {
+    /// Loads settings, falling back to defaults if the file is missing or unreadable.
+    pub fn load(path: PathBuf) -> Self {
+        let mut current = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).unwrap_or_else(|e| {
+                log::warn!("settings.json is invalid, using defaults: {e}");
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        };
+        let had_secrets = crate::secrets::absorb(&mut current);
+        let store = Self {
+            path,
+            current: Mutex::new(current),
+        };
+        // Rewrite the file so the plaintext credentials are gone from disk too.
+        if had_secrets {
+            if let Err(e) = store.update(|_| {}) {
+                log::warn!("failed to rewrite settings.json: {e}");
+            }
+        }
+        store
+    }
+
+    pub fn get(&self) -> Settings {
+        self.current
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Applies `f` and persists the result. The file is written via a temp file + rename
+    /// so a crash mid-write can't leave it truncated.
+    pub fn update(&self, f: impl FnOnce(&mut Settings)) -> std::io::Result<Settings> {
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        let mut next = current.clone();
+        f(&mut next);
+        crate::secrets::absorb(&mut next);
+
+        let json = serde_json::to_string_pretty(&next)?;
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &self.path)?;
+
+        *current = next.clone();
+        Ok(next)
+    }
+}
+
+impl SettingsStore {
+    /// Replaces all user-editable settings, then runs `then` in the same write. The API
+    /// token is kept as-is; it only changes through `regenerate_api_token`. So is the
+    /// telemetry install id, which follows `telemetry.enabled`. The folders exports go to
+    /// are kept too: export jobs are only added with `add_export_job`, so unknown ones are
+    /// dropped, and `then` is where a folder the user picked can be put in.
+    pub fn replace_with(
+        &self,
+        new_settings: Settings,
+        then: impl FnOnce(&mut Settings),
+    ) -> std::io::Result<Settings> {
+        self.update(|s| {
+            let token = std::mem::take(&mut s.api.token);
+            let files = std::mem::take(&mut s.files);
+            let install_id = std::mem::take(&mut s.telemetry.install_id);
+            let quit_dir = std::mem::take(&mut s.quit_export.directory);
+            let jobs = std::mem::take(&mut s.export_jobs);
+            *s = new_settings;
+            s.api.token = token;
+            s.files = files;
+            s.telemetry.install_id = install_id;
+            s.quit_export.directory = quit_dir;
+            s.export_jobs
+                .retain_mut(|job| match jobs.iter().find(|j| j.id == job.id) {
+                    Some(old) => {
+                        job.directory = old.directory.clone();
+                        true
+                    }
+                    None => false,
+                });
+            then(s);
+        })
+    }
+
+    pub fn regenerate_api_token(&self) -> std::io::Result<String> {
+        self.update(|s| s.api.token = generate_token())
+            .map(|s| s.api.token)
+    }
+}
+
+/// Random hex token for authenticating local clients.
+pub fn generate_token() -> String {
+    use rand::RngCore;
+
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
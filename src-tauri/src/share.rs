@@ -0,0 +1,310 @@
+//! The share sheet on Android and iOS: text or photos shared to HabitFlow from another app
+//! end up on a check-in of the habit the user picks, the text as its note and the photos
+//! as attachments (`notes.rs`, `attachments.rs`).
+//!
+//! The share target only drops what it was given into `share/inbox/<id>/`: a `share.json`
+//! with the text and the photos' file names, next to copies of the photos, since the
+//! backend can't read another app's content URIs or the share extension's sandbox. The
+//! inbox is in the app data folder on Android, where `SharePlugin.kt` takes shares as the
+//! app opens; on iOS it's in the App Group container, written by the share extension
+//! (`ios/ShareExtension/`) while the app may not be running at all.
+//!
+//! The inbox is read on start, on resume and when Android hands over a share, and what's
+//! waiting goes to the webview as `share:received`. The webview asks which habit it's for
+//! and calls `attach_share`, or `dismiss_share`. Shares nobody picked a habit for are
+//! dropped after [`MAX_AGE_DAYS`].
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::Habit;
+use crate::stats::{date_key, parse_date, today};
+use crate::storage::Storage;
+
+pub const INBOX_DIR: &str = "share/inbox";
+pub const MANIFEST_FILE: &str = "share.json";
+/// Carries the pending shares, oldest first.
+pub const SHARE_RECEIVED_EVENT: &str = "share:received";
+pub const MAX_AGE_DAYS: i64 = 7;
+
+#[cfg(target_os = "android")]
+mod imp {
+    use tauri::plugin::{Builder, TauriPlugin};
+    use tauri::Wry;
+
+    const PLUGIN_PACKAGE: &str = "com.sushil930.habitflow.share";
+
+    #[derive(serde::Serialize)]
+    struct Handler {
+        handler: tauri::ipc::Channel<serde_json::Value>,
+    }
+
+    /// Registers `SharePlugin.kt` and hands it a channel to say a share has arrived.
+    pub fn init() -> TauriPlugin<Wry> {
+        Builder::new("habit-share")
+            .setup(|app, api| {
+                let handle = api.register_android_plugin(PLUGIN_PACKAGE, "SharePlugin")?;
+                let a = app.clone();
+                let channel = tauri::ipc::Channel::new(move |_| {
+                    let a = a.clone();
+                    tauri::async_runtime::spawn_blocking(move || super::announce(&a));
+                    Ok(())
+                });
+                handle.run_mobile_plugin::<serde_json::Value>(
+                    "setShareHandler",
+                    Handler { handler: channel },
+                )?;
+                Ok(())
+            })
+            .build()
+    }
+}
+
+#[cfg(target_os = "android")]
+pub use imp::init;
+
+/// `share.json`, as the share targets write it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    /// RFC 3339.
+    received_at: String,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedPhoto {
+    pub name: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingShare {
+    pub id: String,
+    pub received_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub photos: Vec<SharedPhoto>,
+}
+
+fn inbox_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    Ok(crate::widgets::shared_dir(app)?.join(INBOX_DIR))
+}
+
+/// The folder of one share. Ids are folder names, so nothing else is accepted.
+fn share_dir(app: &AppHandle, id: &str) -> Result<PathBuf, AppError> {
+    let bare = !id.is_empty()
+        && !id.starts_with('.')
+        && Path::new(id).file_name().is_some_and(|n| n == id);
+    let dir = inbox_dir(app)?.join(id);
+    if !bare || !dir.join(MANIFEST_FILE).is_file() {
+        return Err(AppError::not_found("share", id));
+    }
+    Ok(dir)
+}
+
+/// The share, and when it arrived.
+fn read(dir: &Path, id: &str) -> Result<(PendingShare, DateTime<Utc>), AppError> {
+    let json = std::fs::read(dir.join(MANIFEST_FILE))?;
+    let manifest: Manifest =
+        serde_json::from_slice(&json).map_err(|e| AppError::Invalid(e.to_string()))?;
+    let received_at = DateTime::parse_from_rfc3339(&manifest.received_at)
+        .map_err(|e| AppError::Invalid(format!("receivedAt: {e}")))?
+        .with_timezone(&Utc);
+    let photos = manifest
+        .files
+        .iter()
+        // Names only; anything with a folder in it is ignored.
+        .filter(|name| {
+            Path::new(name)
+                .file_name()
+                .is_some_and(|n| n == name.as_str())
+        })
+        .filter_map(|name| {
+            let size = std::fs::metadata(dir.join(name)).ok()?.len();
+            Some(SharedPhoto {
+                name: name.clone(),
+                size,
+            })
+        })
+        .collect();
+    let share = PendingShare {
+        id: id.to_string(),
+        received_at: received_at.to_rfc3339(),
+        text: manifest
+            .text
+            .as_deref()
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .map(str::to_string),
+        photos,
+    };
+    Ok((share, received_at))
+}
+
+/// What's waiting in the inbox, oldest first. Shares older than [`MAX_AGE_DAYS`] and ones
+/// that can't be read are deleted on the way; folders starting with `.` are still being
+/// written.
+pub fn pending(app: &AppHandle) -> Result<Vec<PendingShare>, AppError> {
+    let inbox = inbox_dir(app)?;
+    let Ok(entries) = std::fs::read_dir(&inbox) else {
+        return Ok(Vec::new());
+    };
+    let cutoff = Utc::now() - chrono::Duration::days(MAX_AGE_DAYS);
+    let mut shares = Vec::new();
+    for entry in entries.filter_map(Result::ok) {
+        let Some(id) = entry.file_name().to_str().map(str::to_string) else {
+            continue;
+        };
+        if id.starts_with('.') || !entry.file_type().is_ok_and(|t| t.is_dir()) {
+            continue;
+        }
+        match read(&entry.path(), &id) {
+            Ok((share, received_at)) if received_at >= cutoff => shares.push(share),
+            Ok(_) => {
+                let _ = std::fs::remove_dir_all(entry.path());
+            }
+            Err(e) => {
+                log::warn!("share: dropping unreadable share {id}: {e}");
+                let _ = std::fs::remove_dir_all(entry.path());
+            }
+        }
+    }
+    shares.sort_by(|a, b| a.received_at.cmp(&b.received_at));
+    Ok(shares)
+}
+
+/// Tells the webview what's waiting, if anything.
+pub fn announce(app: &AppHandle) {
+    match pending(app) {
+        Ok(shares) if !shares.is_empty() => {
+            let _ = app.emit(SHARE_RECEIVED_EVENT, &shares);
+        }
+        Ok(_) => {}
+        Err(e) => log::warn!("share: failed to read the inbox: {e}"),
+    }
+}
+
+/// The note with `text` added as a new paragraph.
+fn appended(note: Option<&String>, text: &str) -> String {
+    match note.map(|n| n.trim()).filter(|n| !n.is_empty()) {
+        Some(note) => format!("{note}\n\n{text}"),
+        None => text.to_string(),
+    }
+}
+
+/// Puts a share on a habit's check-in, today's unless `date` says otherwise, and removes
+/// it from the inbox. With `check_off` the day is checked off first if it isn't yet;
+/// otherwise it has to be.
+pub fn attach(
+    app: &AppHandle,
+    id: &str,
+    habit_id: &str,
+    date: Option<&str>,
+    check_off: bool,
+) -> Result<Habit, AppError> {
+    crate::telemetry::count(app, "share");
+    let dir = share_dir(app, id)?;
+    let (share, _) = read(&dir, id)?;
+    let date = match date {
+        Some(d) => parse_date(d).ok_or_else(|| AppError::Invalid(format!("not a date: {d}")))?,
+        None => today(),
+    };
+    let key = date_key(date);
+    let storage = app.state::<Storage>();
+    let mut habit = storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+
+    // Checked before anything is written, so a refused share is left as it was.
+    let note = share
+        .text
+        .as_deref()
+        .map(|text| appended(habit.notes.get(&key), text));
+    if note
+        .as_ref()
+        .is_some_and(|n| n.chars().count() > crate::notes::MAX_NOTE_LEN)
+    {
+        return Err(AppError::Invalid(format!(
+            "the note would be over {} characters",
+            crate::notes::MAX_NOTE_LEN
+        )));
+    }
+    for photo in &share.photos {
+        crate::attachments::check(&dir.join(&photo.name))?;
+    }
+    if check_off && !habit.is_done_on(&key) {
+        habit = crate::habits::check_off(app, &habit, date)?
+            .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+    }
+    if !habit.is_done_on(&key) {
+        return Err(AppError::Invalid(format!(
+            "{} isn't checked off on {key}",
+            habit.name
+        )));
+    }
+
+    for photo in &share.photos {
+        crate::attachments::add(app, habit_id, &key, &dir.join(&photo.name))?;
+    }
+    if let Some(note) = &note {
+        habit = storage
+            .transaction(|tx| crate::notes::update(tx, habit_id, &key, Some(note)))?
+            .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+        app.state::<EventBus>().data_changed();
+        crate::habits::notify_changed(app);
+    }
+    std::fs::remove_dir_all(&dir)?;
+    Ok(habit)
+}
+
+/// Announces what arrived while the app wasn't running. Does nothing on desktop.
+pub fn start(app: &AppHandle) {
+    if !cfg!(mobile) {
+        return;
+    }
+    announce(app);
+}
+
+/// What's been shared to the app and not yet put on a check-in, oldest first.
+#[tauri::command]
+pub fn list_pending_shares(app: AppHandle) -> Result<Vec<PendingShare>, AppError> {
+    pending(&app)
+}
+
+/// Adds a share's text to the day's note and its photos to the day's attachments.
+/// `checkOff` checks the day off first if it isn't yet.
+#[tauri::command]
+pub fn attach_share(
+    app: AppHandle,
+    share_id: String,
+    habit_id: String,
+    date: Option<String>,
+    check_off: Option<bool>,
+) -> Result<Habit, AppError> {
+    attach(
+        &app,
+        &share_id,
+        &habit_id,
+        date.as_deref(),
+        check_off.unwrap_or(false),
+    )
+}
+
+/// Throws a share away without attaching it.
+#[tauri::command]
+pub fn dismiss_share(app: AppHandle, share_id: String) -> Result<(), AppError> {
+    std::fs::remove_dir_all(share_dir(&app, &share_id)?)?;
+    Ok(())
+}
@@ -0,0 +1,266 @@
+//! Siri and the Shortcuts app on iOS. The App Intents in `ios/App/HabitIntents.swift`
+//! ("Complete habit", "Log value", "Get today's progress", "Check in with tag") call [`run`] through
+//! `habitflow_run_shortcut`, so a workflow like "when my alarm stops, ask which habits are
+//! done" goes through the same code as the app. `run_shortcut` is the same thing as a
+//! command, for trying requests from the webview.
+//!
+//! Every reply has a `dialog`, the sentence Siri speaks. Nothing runs while the app is
+//! locked, and in privacy mode the dialog gives counts instead of names.
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::model::{Habit, HabitKind};
+use crate::stats::today;
+use crate::storage::Storage;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "intent", rename_all = "camelCase")]
+pub enum ShortcutRequest {
+    /// `habit` is an id or a name.
+    #[serde(rename_all = "camelCase")]
+    CompleteHabit {
+        habit: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    LogValue {
+        habit: String,
+        value: f64,
+    },
+    TodayProgress,
+    /// An NFC tag automation; `tag` is the tag's label (`nfc.rs`).
+    #[serde(rename_all = "camelCase")]
+    ScanTag {
+        tag: String,
+    },
+    /// The habits a shortcut can pick from.
+    ListHabits,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutHabit {
+    pub id: String,
+    pub name: String,
+    /// Quantified habits only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShortcutReply {
+    pub dialog: String,
+    /// `todayProgress` only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub done: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub habits: Vec<ShortcutHabit>,
+}
+
+impl ShortcutReply {
+    fn say(dialog: String) -> Self {
+        Self {
+            dialog,
+            done: None,
+            total: None,
+            habits: Vec::new(),
+        }
+    }
+}
+
+/// "Read, Run and Stretch".
+fn listed(names: &[&str]) -> String {
+    match names {
+        [] => String::new(),
+        [only] => only.to_string(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+fn find(app: &AppHandle, reference: &str) -> Result<Habit, AppError> {
+    let habits = app.state::<Storage>().list_habits()?;
+    crate::habits::find(&habits, reference)
+        .filter(|h| !h.archived)
+        .cloned()
+        .ok_or_else(|| AppError::not_found("habit", reference))
+}
+
+fn progress(app: &AppHandle) -> Result<ShortcutReply, AppError> {
+    let snapshot = crate::widgets::snapshot(app)?;
+    let dialog = if snapshot.total == 0 {
+        "Nothing is due today.".to_string()
+    } else if snapshot.done == snapshot.total {
+        format!("All {} done today.", crate::privacy::habits(snapshot.total))
+    } else if snapshot.hidden {
+        format!(
+            "{} of {} done today.",
+            snapshot.done,
+            crate::privacy::habits(snapshot.total)
+        )
+    } else {
+        let (done, left): (Vec<_>, Vec<_>) = snapshot.habits.iter().partition(|h| h.done);
+        let names = |habits: &[&crate::widgets::TodayHabit]| {
+            listed(&habits.iter().map(|h| h.name.as_str()).collect::<Vec<_>>())
+        };
+        if done.is_empty() {
+            format!("Nothing done yet. Still to do: {}.", names(&left))
+        } else {
+            format!("Done: {}. Still to do: {}.", names(&done), names(&left))
+        }
+    };
+    Ok(ShortcutReply {
+        dialog,
+        done: Some(snapshot.done),
+        total: Some(snapshot.total),
+        habits: Vec::new(),
+    })
+}
+
+/// What a habit is called out loud: its name, or "That habit" in privacy mode.
+fn spoken(app: &AppHandle, habit: &Habit) -> String {
+    if crate::privacy::hide_names(app) {
+        "That habit".into()
+    } else {
+        habit.name.clone()
+    }
+}
+
+pub fn run(app: &AppHandle, request: ShortcutRequest) -> Result<ShortcutReply, AppError> {
+    crate::telemetry::count(app, "shortcuts");
+    if app.state::<crate::lock::AppLock>().is_locked() {
+        return Err(AppError::Locked);
+    }
+    match request {
+        ShortcutRequest::CompleteHabit { habit } => {
+            let habit = find(app, &habit)?;
+            let date = today();
+            let before = habit.is_done_on(&crate::stats::date_key(date));
+            let updated = crate::habits::check_off(app, &habit, date)?
+                .ok_or_else(|| AppError::not_found("habit", &habit.id))?;
+            let name = spoken(app, &updated);
+            let dialog = if before {
+                format!("{name} was already done today.")
+            } else if updated.is_done_on(&crate::stats::date_key(date)) {
+                format!("{name} is done for today.")
+            } else {
+                format!("Checked in. {name} needs more today.")
+            };
+            Ok(ShortcutReply::say(dialog))
+        }
+        ShortcutRequest::LogValue { habit, value } => {
+            let habit = find(app, &habit)?;
+            let Some(target) = &habit.target else {
+                return Err(AppError::Invalid(format!(
+                    "{} isn't tracked by amount",
+                    spoken(app, &habit)
+                )));
+            };
+            let date = today();
+            let updated = crate::habits::log_value(app, &habit.id, date, value, Vec::new())?
+                .ok_or_else(|| AppError::not_found("habit", &habit.id))?;
+            let key = crate::stats::date_key(date);
+            let total = updated.values.get(&key).copied().unwrap_or(value);
+            Ok(ShortcutReply::say(format!(
+                "Logged {value} {unit}. {name} is at {total} of {goal} today.",
+                unit = target.unit,
+                name = spoken(app, &updated),
+                goal = target.amount,
+            )))
+        }
+        ShortcutRequest::TodayProgress => progress(app),
+        ShortcutRequest::ScanTag { tag } => {
+            Ok(ShortcutReply::say(crate::nfc::scan(app, &tag)?.message))
+        }
+        ShortcutRequest::ListHabits => {
+            let habits: Vec<ShortcutHabit> = app
+                .state::<Storage>()
+                .list_habits()?
+                .into_iter()
+                .filter(|h| !h.archived && h.kind == HabitKind::Build)
+                .map(|h| ShortcutHabit {
+                    unit: h.target.as_ref().map(|t| t.unit.clone()),
+                    id: h.id,
+                    name: h.name,
+                })
+                .collect();
+            Ok(ShortcutReply {
+                dialog: crate::privacy::habits(habits.len()),
+                done: None,
+                total: None,
+                habits,
+            })
+        }
+    }
+}
+
+/// Where Swift calls in. The app handle is set once setup has run; until then requests
+/// fail with `failed`, and the intents retry.
+#[cfg(target_os = "ios")]
+mod ffi {
+    use std::ffi::{c_char, CStr, CString};
+    use std::sync::OnceLock;
+
+    use tauri::AppHandle;
+
+    use super::ShortcutRequest;
+    use crate::error::AppError;
+
+    static APP: OnceLock<AppHandle> = OnceLock::new();
+
+    pub fn install(app: &AppHandle) {
+        let _ = APP.set(app.clone());
+    }
+
+    /// For the other entry points Swift calls (`mobile_reminders.rs`).
+    pub fn app() -> Option<&'static AppHandle> {
+        APP.get()
+    }
+
+    fn handle(request: &str) -> Result<super::ShortcutReply, AppError> {
+        let app = APP
+            .get()
+            .ok_or_else(|| AppError::Failed("HabitFlow is still starting".into()))?;
+        let request: ShortcutRequest =
+            serde_json::from_str(request).map_err(|e| AppError::Invalid(e.to_string()))?;
+        super::run(app, request)
+    }
+
+    /// Takes a JSON `ShortcutRequest` and returns `{"reply": ...}` or `{"error": ...}`,
+    /// to be freed with `habitflow_free_string`.
+    ///
+    /// # Safety
+    /// `request` must be a valid NUL-terminated string.
+    #[no_mangle]
+    pub unsafe extern "C" fn habitflow_run_shortcut(request: *const c_char) -> *mut c_char {
+        let request = CStr::from_ptr(request).to_string_lossy();
+        let response = match handle(&request) {
+            Ok(reply) => serde_json::json!({ "reply": reply }),
+            Err(e) => serde_json::json!({ "error": e }),
+        };
+        CString::new(response.to_string())
+            .map(CString::into_raw)
+            .unwrap_or(std::ptr::null_mut())
+    }
+
+    /// # Safety
+    /// `s` must come from `habitflow_run_shortcut`, and is freed only once.
+    #[no_mangle]
+    pub unsafe extern "C" fn habitflow_free_string(s: *mut c_char) {
+        if !s.is_null() {
+            drop(CString::from_raw(s));
+        }
+    }
+}
+
+#[cfg(target_os = "ios")]
+pub use ffi::{app, install};
+
+/// Runs a Shortcuts request, as the App Intents do.
+#[tauri::command]
+pub fn run_shortcut(app: AppHandle, request: ShortcutRequest) -> Result<ShortcutReply, AppError> {
+    run(&app, request)
+}
@@ -0,0 +1,493 @@
+//! Opt-in friend leaderboard through a sync server (`settings.social`).
+//!
+//! Only a weekly aggregate leaves the device: the number of completions, plus XP, the
+//! best current streak and the completion rate when each is switched on. Habit names,
+//! categories and dates are never sent. Each device has a friend code that is shared out
+//! of band, and a secret that the server ties to the code on the first upload, kept in
+//! the keychain (`secrets.rs`).
+//!
+//! The server protocol, all JSON with `Authorization: Bearer <secret>`:
+//!
+//! - `PUT /v1/social/scores/{code}` stores `{ code, name, week, friends, score }` for the
+//!   ISO week `week` (`2026-W42`).
+//! - `GET /v1/social/leaderboard/{code}?week=…` returns `{ entries: [{ code, name, score }] }`
+//!   for us and friends who have added us back.
+//! - `DELETE /v1/social/scores/{code}` forgets everything stored for the code.
+//!
+//! The score is uploaded when the leaderboard is fetched and at every day rollover, which
+//! also settles the previous week.
+
+use std::time::Duration;
+
+use chrono::{Datelike, Days, NaiveDate};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::model::{Habit, HabitKind};
+use crate::secrets;
+use crate::settings::{generate_token, Friend, SettingsStore, SocialSettings};
+use crate::stats::{completion_rate, current_streak, date_key, day_arg, parse_date, today};
+use crate::storage::Storage;
+
+/// Friend code characters; no 0/O or 1/I/L to misread.
+const CODE_ALPHABET: &[u8] = b"ABCDEFGHJKMNPQRSTUVWXYZ23456789";
+const CODE_LEN: usize = 8;
+const MAX_FRIENDS: usize = 50;
+
+/// `XXXX-XXXX`.
+fn new_friend_code() -> String {
+    let mut rng = rand::rng();
+    let code: String = (0..CODE_LEN)
+        .map(|_| CODE_ALPHABET[rng.random_range(0..CODE_ALPHABET.len())] as char)
+        .collect();
+    format!("{}-{}", &code[..4], &code[4..])
+}
+
+/// Accepts any case and spacing; returns the code as `XXXX-XXXX`.
+pub fn normalize_code(code: &str) -> Result<String, String> {
+    let chars: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    if chars.len() != CODE_LEN || !chars.bytes().all(|b| CODE_ALPHABET.contains(&b)) {
+        return Err(format!("{code:?} isn't a friend code"));
+    }
+    Ok(format!("{}-{}", &chars[..4], &chars[4..]))
+}
+
+/// `2026-W42`.
+pub fn week_key(date: NaiveDate) -> String {
+    let week = date.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+/// What's shared for a week. The optional parts are left out unless switched on.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklyScore {
+    pub completions: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xp: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub best_streak: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_rate: Option<u32>,
+}
+
+/// The score for `date`'s ISO week, counted up to `date`, with only what `social` shares.
+pub fn weekly_score(habits: &[Habit], date: NaiveDate, social: &SocialSettings) -> WeeklyScore {
+    let monday = date - Days::new(date.weekday().num_days_from_monday().into());
+    let days = (date - monday).num_days() as u32 + 1;
+    let completions = habits
+        .iter()
+        .flat_map(|h| h.done_dates())
+        .filter_map(parse_date)
+        .filter(|d| (monday..=date).contains(d))
+        .count() as u32;
+    let active: Vec<&Habit> = habits
+        .iter()
+        .filter(|h| !h.archived && h.kind == HabitKind::Build)
+        .collect();
+    let rate = (!active.is_empty()).then(|| {
+        let total: u32 = active.iter().map(|h| completion_rate(h, date, days)).sum();
+        total / active.len() as u32
+    });
+    WeeklyScore {
+        completions,
+        xp: social
+            .share_xp
+            .then(|| crate::gamification::xp_between(habits, monday, date)),
+        best_streak: social.share_streak.then(|| {
+            active
+                .iter()
+                .map(|h| current_streak(h, date))
+                .max()
+                .unwrap_or(0)
+        }),
+        completion_rate: rate.filter(|_| social.share_completion_rate),
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Upload<'a> {
+    code: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+    week: String,
+    /// Lets the server only show our score to friends we've added too.
+    friends: Vec<&'a str>,
+    score: WeeklyScore,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerEntry {
+    code: String,
+    #[serde(default)]
+    name: Option<String>,
+    score: WeeklyScore,
+}
+
+#[derive(Deserialize)]
+struct ServerLeaderboard {
+    entries: Vec<ServerEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderboardEntry {
+    pub rank: u32,
+    pub code: String,
+    /// The local nickname, else the name they share, else the code.
+    pub name: String,
+    pub is_me: bool,
+    #[serde(flatten)]
+    pub score: WeeklyScore,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Leaderboard {
+    pub week: String,
+    /// By completions, then XP.
+    pub entries: Vec<LeaderboardEntry>,
+    /// Friends with no score this week, or who haven't added us back.
+    pub pending: Vec<String>,
+}
+
+/// `settings.social` without the secret.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SocialStatus {
+    pub enabled: bool,
+    pub server_url: String,
+    pub friend_code: String,
+    pub display_name: String,
+    pub friends: Vec<Friend>,
+    pub share_xp: bool,
+    pub share_streak: bool,
+    pub share_completion_rate: bool,
+}
+
+pub fn status(settings: &SettingsStore) -> SocialStatus {
+    let social = settings.get().social;
+    SocialStatus {
+        enabled: social.enabled,
+        server_url: social.server_url,
+        friend_code: social.friend_code,
+        display_name: social.display_name,
+        friends: social.friends,
+        share_xp: social.share_xp,
+        share_streak: social.share_streak,
+        share_completion_rate: social.share_completion_rate,
+    }
+}
+
+fn client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .user_agent(concat!("HabitFlow/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .unwrap_or_default()
+}
+
+fn endpoint(social: &SocialSettings, path: &str) -> Result<reqwest::Url, AppError> {
+    let invalid = |e: &dyn std::fmt::Display| AppError::Invalid(format!("invalid server URL: {e}"));
+    let base = reqwest::Url::parse(&social.server_url).map_err(|e| invalid(&e))?;
+    if !matches!(base.scheme(), "http" | "https") {
+        return Err(AppError::Invalid(
+            "the sync server URL must be http(s)".into(),
+        ));
+    }
+    let base = format!("{}/", base.as_str().trim_end_matches('/'));
+    reqwest::Url::parse(&base)
+        .and_then(|b| b.join(path))
+        .map_err(|e| invalid(&e))
+}
+
+/// Error statuses become `AppError::Network` too, retryable unless they're 4xx.
+async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response, AppError> {
+    Ok(request.send().await?.error_for_status()?)
+}
+
+/// Uploads the score for `date`'s week.
+async fn upload(
+    social: &SocialSettings,
+    habits: &[Habit],
+    date: NaiveDate,
+) -> Result<(), AppError> {
+    let body = Upload {
+        code: &social.friend_code,
+        name: Some(social.display_name.trim()).filter(|n| !n.is_empty()),
+        week: week_key(date),
+        friends: social.friends.iter().map(|f| f.code.as_str()).collect(),
+        score: weekly_score(habits, date, social),
+    };
+    let url = endpoint(social, &format!("v1/social/scores/{}", social.friend_code))?;
+    send(client().put(url).bearer_auth(&social.secret).json(&body)).await?;
+    Ok(())
+}
+
+fn enabled(settings: &SettingsStore) -> Result<SocialSettings, AppError> {
+    let social = settings.get().social;
+    if !social.enabled {
+        return Err(AppError::Invalid("the friend leaderboard is off".into()));
+    }
+    Ok(with_secret(social)?)
+}
+
+/// Fills in `secret` from the keychain.
+fn with_secret(mut social: SocialSettings) -> Result<SocialSettings, String> {
+    social.secret = secrets::get(secrets::SOCIAL_SECRET)?.unwrap_or_default();
+    Ok(social)
+}
+
+fn rank(social: &SocialSettings, week: String, server: Vec<ServerEntry>) -> Leaderboard {
+    let known =
+        |code: &str| code == social.friend_code || social.friends.iter().any(|f| f.code == code);
+    let mut entries: Vec<LeaderboardEntry> = server
+        .into_iter()
+        .filter(|e| known(&e.code))
+        .map(|e| {
+            let nickname = social
+                .friends
+                .iter()
+                .find(|f| f.code == e.code)
+                .and_then(|f| f.nickname.clone());
+            LeaderboardEntry {
+                rank: 0,
+                is_me: e.code == social.friend_code,
+                name: nickname
+                    .or(e.name.filter(|n| !n.trim().is_empty()))
+                    .unwrap_or_else(|| e.code.clone()),
+                code: e.code,
+                score: e.score,
+            }
+        })
+        .collect();
+    entries.sort_by(|a, b| {
+        (b.score.completions, b.score.xp.unwrap_or(0))
+            .cmp(&(a.score.completions, a.score.xp.unwrap_or(0)))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+    let mut rank = 0;
+    for i in 0..entries.len() {
+        let tied = i > 0 && {
+            let (prev, cur) = (&entries[i - 1].score, &entries[i].score);
+            (prev.completions, prev.xp) == (cur.completions, cur.xp)
+        };
+        if !tied {
+            rank = i as u32 + 1;
+        }
+        entries[i].rank = rank;
+    }
+    let pending = social
+        .friends
+        .iter()
+        .filter(|f| entries.iter().all(|e| e.code != f.code))
+        .map(|f| f.code.clone())
+        .collect();
+    Leaderboard {
+        week,
+        entries,
+        pending,
+    }
+}
+
+/// Uploads our score, then fetches the leaderboard for `date`'s week.
+pub async fn leaderboard(
+    settings: &SettingsStore,
+    storage: &Storage,
+    date: NaiveDate,
+) -> Result<Leaderboard, AppError> {
+    let social = enabled(settings)?;
+    let habits = storage.list_habits()?;
+    // Past weeks keep whatever was uploaded while they were current.
+    if week_key(date) == week_key(today()) {
+        upload(&social, &habits, today()).await?;
+    }
+    let week = week_key(date);
+    let mut url = endpoint(
+        &social,
+        &format!("v1/social/leaderboard/{}", social.friend_code),
+    )?;
+    url.query_pairs_mut().append_pair("week", &week);
+    let response = send(client().get(url).bearer_auth(&social.secret)).await?;
+    let server: ServerLeaderboard = response.json().await?;
+    Ok(rank(&social, week, server.entries))
+}
+
+/// Uploads the final score for `previous`'s week, then the new day's.
+pub fn on_day_rollover(app: &AppHandle, previous: NaiveDate, today: NaiveDate) {
+    let Ok(social) = enabled(&app.state::<SettingsStore>()) else {
+        return;
+    };
+    let habits = match app.state::<Storage>().list_habits() {
+        Ok(h) => h,
+        Err(e) => {
+            log::warn!("social: failed to load habits: {e}");
+            return;
+        }
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        crate::network::wait_online(&app).await;
+        let mut dates = vec![previous];
+        if week_key(previous) != week_key(today) {
+            dates.push(today);
+        }
+        for date in dates {
+            if let Err(e) = upload(&social, &habits, date).await {
+                log::warn!(
+                    "social: failed to upload the score for {}: {e}",
+                    date_key(date)
+                );
+            }
+        }
+    });
+}
+
+/// Pushes the current score after a privacy change so the server drops what's no longer
+/// shared.
+fn refresh(app: &AppHandle) {
+    let Ok(social) = enabled(&app.state::<SettingsStore>()) else {
+        return;
+    };
+    let Ok(habits) = app.state::<Storage>().list_habits() else {
+        return;
+    };
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = upload(&social, &habits, today()).await {
+            log::warn!("social: failed to upload the score: {e}");
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_social_status(app: AppHandle) -> SocialStatus {
+    status(&app.state::<SettingsStore>())
+}
+
+/// Opts in. A friend code is generated the first time; it's kept when sharing is turned
+/// off and on again. Nothing is saved unless the server accepts the first upload.
+#[tauri::command]
+pub async fn enable_social(
+    app: AppHandle,
+    server_url: String,
+    display_name: Option<String>,
+) -> Result<SocialStatus, AppError> {
+    let settings = app.state::<SettingsStore>();
+    let mut social = with_secret(settings.get().social)?;
+    social.server_url = server_url.trim().to_string();
+    if let Some(name) = display_name {
+        social.display_name = name.trim().to_string();
+    }
+    if social.friend_code.is_empty() || social.secret.is_empty() {
+        social.friend_code = new_friend_code();
+        social.secret = generate_token();
+    }
+    social.enabled = true;
+    let habits = app.state::<Storage>().list_habits()?;
+    upload(&social, &habits, today()).await?;
+    secrets::set(secrets::SOCIAL_SECRET, &social.secret)?;
+    settings.update(|s| {
+        s.social = SocialSettings {
+            secret: String::new(),
+            ..social
+        }
+    })?;
+    Ok(status(&settings))
+}
+
+/// Opts out and asks the server to delete everything it has for our code.
+#[tauri::command]
+pub async fn disable_social(app: AppHandle) -> Result<SocialStatus, AppError> {
+    let settings = app.state::<SettingsStore>();
+    let social = with_secret(settings.get().social)?;
+    settings.update(|s| s.social.enabled = false)?;
+    if !social.friend_code.is_empty() {
+        let url = endpoint(&social, &format!("v1/social/scores/{}", social.friend_code))?;
+        send(client().delete(url).bearer_auth(&social.secret))
+            .await
+            .map_err(|e| format!("sharing is off, but the shared scores weren't deleted: {e}"))?;
+    }
+    Ok(status(&settings))
+}
+
+/// Chooses what's shared beyond the completion count, and the name friends see.
+#[tauri::command]
+pub fn set_social_privacy(
+    app: AppHandle,
+    display_name: Option<String>,
+    share_xp: bool,
+    share_streak: bool,
+    share_completion_rate: bool,
+) -> Result<SocialStatus, AppError> {
+    let settings = app.state::<SettingsStore>();
+    settings.update(|s| {
+        if let Some(name) = display_name {
+            s.social.display_name = name.trim().to_string();
+        }
+        s.social.share_xp = share_xp;
+        s.social.share_streak = share_streak;
+        s.social.share_completion_rate = share_completion_rate;
+    })?;
+    refresh(&app);
+    Ok(status(&settings))
+}
+
+#[tauri::command]
+pub fn add_friend(
+    app: AppHandle,
+    code: String,
+    nickname: Option<String>,
+) -> Result<SocialStatus, AppError> {
+    let code = normalize_code(&code)?;
+    let settings = app.state::<SettingsStore>();
+    let social = settings.get().social;
+    if code == social.friend_code {
+        return Err(AppError::Invalid("that's your own friend code".into()));
+    }
+    if social.friends.iter().any(|f| f.code == code) {
+        return Err(AppError::Invalid(format!("{code} is already a friend")));
+    }
+    if social.friends.len() >= MAX_FRIENDS {
+        return Err(AppError::Invalid(format!("at most {MAX_FRIENDS} friends")));
+    }
+    let friend = Friend {
+        code,
+        nickname: nickname
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty()),
+        added_at: chrono::Utc::now().to_rfc3339(),
+    };
+    settings.update(|s| s.social.friends.push(friend))?;
+    refresh(&app);
+    Ok(status(&settings))
+}
+
+#[tauri::command]
+pub fn remove_friend(app: AppHandle, code: String) -> Result<SocialStatus, AppError> {
+    let code = normalize_code(&code)?;
+    let settings = app.state::<SettingsStore>();
+    if settings.get().social.friends.iter().all(|f| f.code != code) {
+        return Err(AppError::not_found("friend", &code));
+    }
+    settings.update(|s| s.social.friends.retain(|f| f.code != code))?;
+    refresh(&app);
+    Ok(status(&settings))
+}
+
+/// The leaderboard for the week containing `date` (`yyyy-MM-dd`, default today).
+#[tauri::command]
+pub async fn get_leaderboard(
+    app: AppHandle,
+    date: Option<String>,
+) -> Result<Leaderboard, AppError> {
+    let date = day_arg(date.as_deref())?;
+    leaderboard(&app.state::<SettingsStore>(), &app.state::<Storage>(), date).await
+}
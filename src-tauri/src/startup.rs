@@ -0,0 +1,143 @@
+//! Startup, split in two so the window shows up sooner. The setup hook does only what the
+//! first frame needs (storage, settings, the lock, the tray, then the main window, in that
+//! order, so the tray is there before the webview starts loading). Everything else
+//! ([`deferred`]: logging, sync and integrations, background watchers, compiling scripts
+//! and plugins, warming up streaks) runs on a background thread right after.
+//!
+//! Each phase is timed; `get_startup_metrics` reports the timings so regressions show up.
+
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Phase {
+    pub name: &'static str,
+    /// Milliseconds since the app started.
+    pub started_ms: u64,
+    pub duration_ms: u64,
+    /// Ran after the setup hook, off its path.
+    pub deferred: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupMetrics {
+    /// In the order they started.
+    pub phases: Vec<Phase>,
+    /// When the setup hook returned.
+    pub setup_done_ms: Option<u64>,
+    /// When the main window's page finished loading.
+    pub webview_loaded_ms: Option<u64>,
+    /// When the deferred setup finished.
+    pub deferred_done_ms: Option<u64>,
+}
+
+pub struct Startup {
+    began: Instant,
+    metrics: Mutex<StartupMetrics>,
+}
+
+impl Startup {
+    pub fn new() -> Self {
+        Self {
+            began: Instant::now(),
+            metrics: Mutex::new(StartupMetrics {
+                phases: Vec::new(),
+                setup_done_ms: None,
+                webview_loaded_ms: None,
+                deferred_done_ms: None,
+            }),
+        }
+    }
+
+    fn metrics(&self) -> std::sync::MutexGuard<'_, StartupMetrics> {
+        self.metrics.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.began.elapsed().as_millis() as u64
+    }
+
+    /// Runs `f` as the phase `name`.
+    pub fn time<T>(&self, name: &'static str, deferred: bool, f: impl FnOnce() -> T) -> T {
+        let started_ms = self.elapsed_ms();
+        let value = f();
+        self.metrics().phases.push(Phase {
+            name,
+            started_ms,
+            duration_ms: self.elapsed_ms() - started_ms,
+            deferred,
+        });
+        value
+    }
+
+    pub fn setup_done(&self) {
+        self.metrics().setup_done_ms = Some(self.elapsed_ms());
+    }
+
+    /// Only the first load counts; reloading the page later doesn't move it.
+    pub fn webview_loaded(&self) {
+        let ms = self.elapsed_ms();
+        self.metrics().webview_loaded_ms.get_or_insert(ms);
+    }
+}
+
+/// The setup that can wait until the window is up. Logs written before the log plugin is
+/// installed here are dropped.
+pub fn deferred(app: &AppHandle) {
+    let startup = app.state::<Startup>();
+    let time = |name, f: &dyn Fn()| startup.time(name, true, f);
+
+    time("redaction", &|| crate::redact::start(app));
+    time("logging", &|| {
+        if let Err(e) = crate::logging::install(app) {
+            eprintln!("failed to install the log plugin: {e}");
+        }
+    });
+    time("sync", &|| {
+        crate::api::apply(app);
+        crate::webhooks::start(app);
+        crate::mqtt::apply(app);
+    });
+    time("scripts", &|| {
+        app.state::<crate::automation::Automations>().reload();
+        app.state::<crate::plugins::PluginHost>().reload();
+        crate::automation::start(app);
+        crate::plugins::start(app);
+    });
+    time("watchers", &|| {
+        crate::scheduler::start(app);
+        crate::dependencies::start(app);
+        crate::milestones::start(app);
+        crate::gamification::start(app);
+        crate::achievements::start(app);
+        crate::challenges::start(app);
+        crate::timers::start(app);
+        crate::pomodoro::start(app);
+        crate::focus::start(app);
+        crate::widgets::start(app);
+        crate::mobile_reminders::start(app);
+        crate::share::start(app);
+        crate::network::start(app);
+        crate::sync_queue::start(app);
+        crate::replica::start(app);
+        crate::backup_drive::start(app);
+        crate::screen_time::start(app);
+        crate::updater::start(app);
+        crate::crash::start(app);
+        crate::telemetry::start(app);
+    });
+    time("streaks", &|| {
+        crate::streaks::warm_up(&app.state::<crate::storage::Storage>())
+    });
+    startup.metrics().deferred_done_ms = Some(startup.elapsed_ms());
+}
+
+#[tauri::command]
+pub fn get_startup_metrics(startup: State<'_, Startup>) -> StartupMetrics {
+    startup.metrics().clone()
+}
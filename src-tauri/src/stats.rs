@@ -0,0 +1,434 @@
+use std::collections::BTreeSet;
+
+use chrono::{Days, NaiveDate};
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::model::{Category, Habit, HabitKind, HabitTarget};
+use crate::schedule::{self, PeriodProgress, Schedule};
+use crate::storage::MoodEntry;
+use crate::time_windows::{self, WindowState};
+
+pub const DATE_FORMAT: &str = "%Y-%m-%d";
+
+pub fn today() -> NaiveDate {
+    crate::clock::now().date_naive()
+}
+
+pub fn date_key(date: NaiveDate) -> String {
+    date.format(DATE_FORMAT).to_string()
+}
+
+pub fn parse_date(s: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(s, DATE_FORMAT).ok()
+}
+
+/// [`parse_date`] for a date the caller passed in, which is `invalid` if it isn't one.
+pub fn date_arg(s: &str) -> Result<NaiveDate, AppError> {
+    parse_date(s).ok_or_else(|| AppError::Invalid(format!("invalid date {s:?}")))
+}
+
+/// [`date_arg`] for an optional date, which is today when left out.
+pub fn day_arg(date: Option<&str>) -> Result<NaiveDate, AppError> {
+    date.map_or_else(|| Ok(today()), date_arg)
+}
+
+/// Same rules as `calculateStreak` in `habitService.ts`: a streak survives until the end of
+/// today, so it counts back from today if done, otherwise from yesterday. Excused and
+/// frozen days are skipped over without counting. Non-daily schedules count periods
+/// instead; see `schedule`.
+pub fn current_streak(habit: &Habit, today: NaiveDate) -> u32 {
+    if uses_periods(habit) {
+        return schedule::streak(habit, today);
+    }
+    let mut streak = 0;
+    let mut day = today;
+    loop {
+        let key = date_key(day);
+        if habit.is_done_on(&key) {
+            streak += 1;
+        } else if day != today && !is_neutral(habit, &key) {
+            break;
+        }
+        match day.checked_sub_days(Days::new(1)) {
+            Some(prev) => day = prev,
+            None => break,
+        }
+    }
+    streak
+}
+
+pub fn longest_streak(habit: &Habit, today: NaiveDate) -> u32 {
+    if habit.kind == HabitKind::Avoid {
+        return longest_clean_run(habit, today);
+    }
+    if uses_periods(habit) {
+        return schedule::longest_streak(habit);
+    }
+    let mut longest = 0;
+    let mut current = 0;
+    let mut prev: Option<NaiveDate> = None;
+
+    for date in habit.done_dates().into_iter().filter_map(parse_date) {
+        current = match prev {
+            Some(p) if date == p => current,
+            Some(p) if bridged(habit, p, date) => current + 1,
+            _ => 1,
+        };
+        longest = longest.max(current);
+        prev = Some(date);
+    }
+    longest
+}
+
+/// Neither extends nor breaks a daily streak: inside an off period, or covered by a freeze.
+fn is_neutral(habit: &Habit, key: &str) -> bool {
+    habit.is_excused(key) || habit.is_frozen(key)
+}
+
+/// Every day strictly between `from` and `to` is neutral (trivially so when adjacent).
+pub fn bridged(habit: &Habit, from: NaiveDate, to: NaiveDate) -> bool {
+    from.iter_days()
+        .skip(1)
+        .take_while(|d| *d < to)
+        .all(|d| is_neutral(habit, &date_key(d)))
+}
+
+pub fn uses_periods(habit: &Habit) -> bool {
+    habit.kind == HabitKind::Build && Schedule::of(habit) != Schedule::Daily
+}
+
+/// Distinct lapse days between the habit's start and `today`, in order.
+fn lapse_dates(habit: &Habit, today: NaiveDate) -> Vec<NaiveDate> {
+    let start = parse_date(habit.start_date());
+    let dates: BTreeSet<NaiveDate> = habit
+        .lapses
+        .iter()
+        .filter_map(|l| parse_date(&l.date))
+        .filter(|d| *d <= today && start.map_or(true, |s| *d >= s))
+        .collect();
+    dates.into_iter().collect()
+}
+
+/// Longest stretch of days without a lapse, up to and including today.
+fn longest_clean_run(habit: &Habit, today: NaiveDate) -> u32 {
+    let Some(start) = parse_date(habit.start_date()).filter(|s| *s <= today) else {
+        return 0;
+    };
+    let mut longest = 0;
+    let mut run_start = start;
+    for lapse in lapse_dates(habit, today) {
+        longest = longest.max((lapse - run_start).num_days());
+        run_start = lapse + Days::new(1);
+    }
+    longest = longest.max((today - run_start).num_days() + 1);
+    longest.max(0) as u32
+}
+
+/// Days an avoidance habit was kept, from its start through today.
+pub fn avoided_days(habit: &Habit, today: NaiveDate) -> u32 {
+    let Some(start) = parse_date(habit.start_date()).filter(|s| *s <= today) else {
+        return 0;
+    };
+    let total = (today - start).num_days() + 1;
+    (total - lapse_dates(habit, today).len() as i64).max(0) as u32
+}
+
+/// Percentage of the last `days` days (including today) that were completed. Excused days
+/// that weren't completed are left out rather than counted as missed.
+pub fn completion_rate(habit: &Habit, today: NaiveDate, days: u32) -> u32 {
+    if days == 0 {
+        return 0;
+    }
+    let (mut completed, mut counted) = (0u32, 0u32);
+    for key in (0..days)
+        .filter_map(|i| today.checked_sub_days(Days::new(i.into())))
+        .map(date_key)
+    {
+        if habit.is_done_on(&key) {
+            completed += 1;
+        } else if habit.is_excused(&key) {
+            continue;
+        }
+        counted += 1;
+    }
+    if counted == 0 {
+        return 100;
+    }
+    (f64::from(completed) / f64::from(counted) * 100.0).round() as u32
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HabitStats {
+    pub habit_id: String,
+    pub name: String,
+    pub done_today: bool,
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    pub completion_rate_30d: u32,
+    pub total_completions: usize,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
+    /// Today falls in an off period.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub excused_today: bool,
+    /// Days in the last 30 that fell in an off period, which count as excused, not missed.
+    pub excused_30d: u32,
+    #[serde(skip_serializing_if = "HabitKind::is_build")]
+    pub kind: HabitKind,
+    /// Avoidance habits only: days with a lapse in the last 30.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lapses_30d: Option<u32>,
+    /// Non-daily schedules only: progress toward the current period's quota.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period: Option<PeriodProgress>,
+    /// Habits with a time window only: where today stands against it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_today: Option<WindowState>,
+    /// Checklist habits only: items done today out of the total.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checklist_today: Option<ChecklistProgress>,
+    /// Habits done several times a day only: today's check-ins out of `timesPerDay`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repeats_today: Option<ChecklistProgress>,
+    /// Quantified habits only: today's logged total and the target it counts toward.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_today: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<HabitTarget>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ChecklistProgress {
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsSummary {
+    pub date: String,
+    pub active_habits: usize,
+    /// Build habits done today. Avoidance habits aren't counted since their day isn't
+    /// decided until it ends; `lapsed_today` counts the ones that already slipped.
+    pub completed_today: usize,
+    pub lapsed_today: usize,
+    pub archived_habits: usize,
+    /// Active habits first, then archived ones, whose history still counts.
+    pub habits: Vec<HabitStats>,
+    /// In category order; categories without active habits are left out.
+    pub categories: Vec<CategoryStats>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryStats {
+    pub category: String,
+    pub color: String,
+    pub active_habits: usize,
+    pub completed_today: usize,
+    /// Average of the habits' 30-day completion rates.
+    pub completion_rate_30d: u32,
+}
+
+/// Habits grouped by category, in `categories` order. Habits whose category has no record
+/// (not synced yet) follow in the order they're first seen.
+pub fn group_by_category<'a>(
+    habits: impl IntoIterator<Item = &'a Habit>,
+    categories: &[Category],
+) -> Vec<(Category, Vec<&'a Habit>)> {
+    let mut groups: Vec<(Category, Vec<&Habit>)> =
+        categories.iter().map(|c| (c.clone(), Vec::new())).collect();
+    for habit in habits {
+        let lowered = habit.category.to_lowercase();
+        match groups
+            .iter_mut()
+            .find(|(c, _)| c.name.to_lowercase() == lowered)
+        {
+            Some((_, members)) => members.push(habit),
+            None => {
+                let position = groups.len() as u32;
+                groups.push((
+                    Category {
+                        id: String::new(),
+                        name: habit.category.clone(),
+                        color: habit.color.clone(),
+                        position,
+                    },
+                    vec![habit],
+                ));
+            }
+        }
+    }
+    groups
+}
+
+pub fn habit_stats(habit: &Habit, today: NaiveDate) -> HabitStats {
+    let key = date_key(today);
+    HabitStats {
+        habit_id: habit.id.clone(),
+        name: habit.name.clone(),
+        done_today: habit.is_done_on(&key),
+        current_streak: current_streak(habit, today),
+        longest_streak: longest_streak(habit, today),
+        completion_rate_30d: completion_rate(habit, today, 30),
+        total_completions: match habit.kind {
+            HabitKind::Build => habit.done_dates().len(),
+            HabitKind::Avoid => avoided_days(habit, today) as usize,
+        },
+        archived: habit.archived,
+        excused_today: habit.is_excused(&key),
+        excused_30d: schedule::count_excused(
+            habit,
+            today.checked_sub_days(Days::new(29)).unwrap_or(today),
+            today,
+        ),
+        kind: habit.kind,
+        lapses_30d: (habit.kind == HabitKind::Avoid).then(|| {
+            let cutoff = today.checked_sub_days(Days::new(29)).unwrap_or(today);
+            lapse_dates(habit, today)
+                .iter()
+                .filter(|d| **d >= cutoff)
+                .count() as u32
+        }),
+        period: uses_periods(habit).then(|| schedule::progress(habit, today)),
+        window_today: time_windows::state(habit, today, time_windows::now()),
+        checklist_today: habit
+            .checklist_progress(&key)
+            .map(|(done, total)| ChecklistProgress { done, total }),
+        repeats_today: habit.is_repeated().then(|| ChecklistProgress {
+            done: habit.value_on(&key) as usize,
+            total: habit.times_per_day as usize,
+        }),
+        value_today: habit.target.as_ref().map(|_| habit.value_on(&key)),
+        target: habit.target.clone(),
+    }
+}
+
+/// Today's counts and the category breakdown cover active habits only; archived ones are
+/// listed after them for their history.
+pub fn summarize(habits: &[Habit], categories: &[Category], today: NaiveDate) -> StatsSummary {
+    let active = || habits.iter().filter(|h| !h.archived);
+    let stats: Vec<HabitStats> = active().map(|h| habit_stats(h, today)).collect();
+    let archived: Vec<HabitStats> = habits
+        .iter()
+        .filter(|h| h.archived)
+        .map(|h| habit_stats(h, today))
+        .collect();
+
+    let categories = group_by_category(active(), categories)
+        .into_iter()
+        .filter(|(_, members)| !members.is_empty())
+        .map(|(category, members)| {
+            let members: Vec<&HabitStats> = stats
+                .iter()
+                .filter(|s| members.iter().any(|h| h.id == s.habit_id))
+                .collect();
+            CategoryStats {
+                category: category.name,
+                color: category.color,
+                active_habits: members.len(),
+                completed_today: members
+                    .iter()
+                    .filter(|s| s.kind == HabitKind::Build && s.done_today)
+                    .count(),
+                completion_rate_30d: (members
+                    .iter()
+                    .map(|s| f64::from(s.completion_rate_30d))
+                    .sum::<f64>()
+                    / members.len() as f64)
+                    .round() as u32,
+            }
+        })
+        .collect();
+
+    StatsSummary {
+        date: date_key(today),
+        active_habits: stats.len(),
+        completed_today: stats
+            .iter()
+            .filter(|s| s.kind == HabitKind::Build && s.done_today)
+            .count(),
+        lapsed_today: stats
+            .iter()
+            .filter(|s| s.kind == HabitKind::Avoid && !s.done_today)
+            .count(),
+        archived_habits: archived.len(),
+        habits: stats.into_iter().chain(archived).collect(),
+        categories,
+    }
+}
+
+/// Fewest mood entries on each side (done / not done) before a habit is compared.
+pub const MIN_MOOD_SAMPLES: usize = 3;
+
+/// How the logged mood differs between days a habit was done and days it wasn't.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoodCorrelation {
+    pub habit_id: String,
+    pub name: String,
+    pub mood_when_done: f64,
+    pub mood_when_missed: f64,
+    /// `mood_when_done - mood_when_missed`.
+    pub difference: f64,
+    pub days_done: usize,
+    pub days_missed: usize,
+    /// "Your mood averages 0.8 higher on days you complete Workout".
+    pub summary: String,
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// How the mood relates to one habit; `None` if it's archived or without enough mood data
+/// on both sides. Days before the habit started or inside an off period are left out.
+pub fn mood_correlation(habit: &Habit, moods: &[MoodEntry]) -> Option<MoodCorrelation> {
+    if habit.archived {
+        return None;
+    }
+    let (mut done, mut missed) = (Vec::new(), Vec::new());
+    for mood in moods
+        .iter()
+        .filter(|m| m.date.as_str() >= habit.start_date() && !habit.is_excused(&m.date))
+    {
+        let score = f64::from(mood.score);
+        if habit.is_done_on(&mood.date) {
+            done.push(score);
+        } else {
+            missed.push(score);
+        }
+    }
+    if done.len() < MIN_MOOD_SAMPLES || missed.len() < MIN_MOOD_SAMPLES {
+        return None;
+    }
+    let average = |scores: &[f64]| scores.iter().sum::<f64>() / scores.len() as f64;
+    let (when_done, when_missed) = (average(&done), average(&missed));
+    let difference = when_done - when_missed;
+    let days = match habit.kind {
+        HabitKind::Build => format!("on days you complete {}", habit.name),
+        HabitKind::Avoid => format!("on days you keep up {}", habit.name),
+    };
+    let summary = match format!("{:.1}", difference.abs()).as_str() {
+        "0.0" => format!("Your mood is about the same {days} as on other days"),
+        amount if difference > 0.0 => format!("Your mood averages {amount} higher {days}"),
+        amount => format!("Your mood averages {amount} lower {days}"),
+    };
+    Some(MoodCorrelation {
+        habit_id: habit.id.clone(),
+        name: habit.name.clone(),
+        mood_when_done: round2(when_done),
+        mood_when_missed: round2(when_missed),
+        difference: round2(difference),
+        days_done: done.len(),
+        days_missed: missed.len(),
+        summary,
+    })
+}
+
+/// Biggest difference first.
+pub fn sort_correlations(correlations: &mut [MoodCorrelation]) {
+    correlations.sort_by(|a, b| b.difference.abs().total_cmp(&a.difference.abs()));
+}
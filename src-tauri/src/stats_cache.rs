@@ -0,0 +1,324 @@
+//! Per-habit stats over a date range (the heatmap, the completion rate and the strength
+//! score), cached so revisiting the dashboard doesn't recompute them. Entries are keyed by
+//! habit and range and dropped by the storage layer as it writes, at the same points that
+//! queue days for the streak runs (`streaks.rs`):
+//!
+//! - a changed day drops the habit's entries whose range covers that day
+//! - a change that can move every day (the schedule, the target, off periods, freezes, or
+//!   a synced avoidance habit, whose lapses aren't diffed) drops all of the habit's entries
+//! - writes from another process (the CLI) drop everything, noticed via `data_version`
+//!
+//! A result worked out from data that changed while it was being computed isn't kept.
+//! The week start is part of the key, since the week totals depend on it.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+use serde::Serialize;
+use tauri::State;
+
+use crate::error::AppError;
+use crate::model::Habit;
+use crate::stats::{date_key, parse_date, today};
+use crate::storage::Storage;
+
+/// The longest range one request can ask for.
+pub const MAX_RANGE_DAYS: u32 = 3 * 366;
+/// Entries kept; the least recently used go first.
+const MAX_ENTRIES: usize = 1000;
+/// The score's memory: a day's weight halves after this many days.
+const SCORE_HALF_LIFE_DAYS: f64 = 13.0;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DayCell {
+    pub date: String,
+    pub done: bool,
+    /// Quantified and repeated habits only: the day's logged total.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub excused: bool,
+}
+
+/// One week of a range, clipped to the range at either end.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeekTotal {
+    pub start: String,
+    pub completed: u32,
+    pub counted: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeStats {
+    pub habit_id: String,
+    pub from: String,
+    pub to: String,
+    /// One per day, oldest first.
+    pub days: Vec<DayCell>,
+    pub completed: u32,
+    /// Days that count toward the rate: excused days that weren't done are left out.
+    pub counted: u32,
+    /// Percentage, like `stats::completion_rate`.
+    pub completion_rate: u32,
+    /// Habit strength at `to`, 0 to 100. Every day done pulls it up and every day missed
+    /// pulls it down, recent days weighing most; excused days leave it as it was.
+    pub score: u32,
+    /// The first day of the week, 0 = Sunday like JS `getDay()`, for laying out the
+    /// heatmap.
+    pub week_start: u8,
+    /// Per week, oldest first, starting on `week_start`.
+    pub weeks: Vec<WeekTotal>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Key {
+    habit_id: String,
+    from: NaiveDate,
+    to: NaiveDate,
+    week_start: Weekday,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<Key, (RangeStats, u64)>,
+    /// Bumps on every invalidation, so results computed across one aren't stored.
+    epoch: u64,
+    /// Bumps on every hit or insert, to find the least recently used entry.
+    clock: u64,
+    data_version: Option<i64>,
+}
+
+#[derive(Default)]
+pub struct StatsCache {
+    inner: Mutex<Inner>,
+}
+
+impl StatsCache {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A day's data changed for one habit.
+    pub fn forget_day(&self, habit_id: &str, date: &str) {
+        let Some(date) = parse_date(date) else {
+            return self.forget_habit(Some(habit_id));
+        };
+        let mut inner = self.lock();
+        inner.epoch += 1;
+        inner
+            .entries
+            .retain(|k, _| k.habit_id != habit_id || !(k.from..=k.to).contains(&date));
+    }
+
+    /// Everything about one habit, or about all of them, may have changed.
+    pub fn forget_habit(&self, habit_id: Option<&str>) {
+        let mut inner = self.lock();
+        inner.epoch += 1;
+        match habit_id {
+            Some(id) => inner.entries.retain(|k, _| k.habit_id != id),
+            None => inner.entries.clear(),
+        }
+    }
+
+    /// Entries kept, and roughly what they take up.
+    pub fn usage(&self) -> (usize, u64) {
+        let inner = self.lock();
+        let bytes = inner
+            .entries
+            .values()
+            .map(|(stats, _)| {
+                std::mem::size_of::<(Key, RangeStats, u64)>()
+                    + stats.days.len() * (std::mem::size_of::<DayCell>() + "yyyy-MM-dd".len())
+            })
+            .sum::<usize>();
+        (inner.entries.len(), bytes as u64)
+    }
+
+    /// Drops everything if another connection wrote since last seen.
+    fn check_data_version(&self, version: i64) {
+        let mut inner = self.lock();
+        if inner.data_version.is_some_and(|v| v != version) {
+            inner.epoch += 1;
+            inner.entries.clear();
+        }
+        inner.data_version = Some(version);
+    }
+
+    fn get(&self, key: &Key) -> Option<RangeStats> {
+        let mut inner = self.lock();
+        inner.clock += 1;
+        let clock = inner.clock;
+        let (stats, used) = inner.entries.get_mut(key)?;
+        *used = clock;
+        Some(stats.clone())
+    }
+
+    fn epoch(&self) -> u64 {
+        self.lock().epoch
+    }
+
+    fn insert(&self, epoch: u64, key: Key, stats: RangeStats) {
+        let mut inner = self.lock();
+        if inner.epoch != epoch {
+            return;
+        }
+        if inner.entries.len() >= MAX_ENTRIES {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, used))| *used)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+            }
+        }
+        inner.clock += 1;
+        let clock = inner.clock;
+        inner.entries.insert(key, (stats, clock));
+    }
+}
+
+fn compute(habit: &Habit, from: NaiveDate, to: NaiveDate, week_start: Weekday) -> RangeStats {
+    let keep = 0.5f64.powf(1.0 / SCORE_HALF_LIFE_DAYS);
+    let (mut completed, mut counted, mut score) = (0u32, 0u32, 0f64);
+    let mut weeks: Vec<WeekTotal> = Vec::new();
+    let days: Vec<DayCell> = from
+        .iter_days()
+        .take_while(|d| *d <= to)
+        .map(|d| {
+            let date = date_key(d);
+            let done = habit.is_done_on(&date);
+            let excused = habit.is_excused(&date);
+            if weeks.is_empty() || d.weekday() == week_start {
+                weeks.push(WeekTotal {
+                    start: date.clone(),
+                    completed: 0,
+                    counted: 0,
+                });
+            }
+            if done || !excused {
+                counted += 1;
+                completed += u32::from(done);
+                score = score * keep + if done { 1.0 - keep } else { 0.0 };
+                if let Some(week) = weeks.last_mut() {
+                    week.counted += 1;
+                    week.completed += u32::from(done);
+                }
+            }
+            DayCell {
+                value: habit.values.get(&date).copied(),
+                date,
+                done,
+                excused,
+            }
+        })
+        .collect();
+    RangeStats {
+        habit_id: habit.id.clone(),
+        from: date_key(from),
+        to: date_key(to),
+        days,
+        completed,
+        counted,
+        completion_rate: if counted == 0 {
+            100
+        } else {
+            (f64::from(completed) / f64::from(counted) * 100.0).round() as u32
+        },
+        score: (score * 100.0).round() as u32,
+        week_start: week_start.num_days_from_sunday() as u8,
+        weeks,
+    }
+}
+
+/// Stats for each habit (every active one if `habit_ids` is `None`) between `from` and
+/// `to`, inclusive. `to` defaults to today and `from` to a year before it.
+pub fn range_stats(
+    storage: &Storage,
+    habit_ids: Option<Vec<String>>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<Vec<RangeStats>, AppError> {
+    let date = |s: &str| parse_date(s).ok_or_else(|| AppError::Invalid(format!("not a date: {s}")));
+    let to = to.map(date).transpose()?.unwrap_or_else(today);
+    let from = match from {
+        Some(from) => date(from)?,
+        None => to.checked_sub_days(Days::new(364)).unwrap_or(to),
+    };
+    if from > to {
+        return Err(AppError::Invalid("from is after to".into()));
+    }
+    if (to - from).num_days() >= i64::from(MAX_RANGE_DAYS) {
+        return Err(AppError::Invalid(format!(
+            "ranges are limited to {MAX_RANGE_DAYS} days"
+        )));
+    }
+
+    let cache = storage.stats_cache();
+    cache.check_data_version(storage.data_version()?);
+    let ids = match habit_ids {
+        Some(ids) => ids,
+        None => storage.active_habit_ids()?,
+    };
+    let week_start = crate::calendar::week_start();
+    let key = |id: &str| Key {
+        habit_id: id.to_string(),
+        from,
+        to,
+        week_start,
+    };
+    let mut found: Vec<Option<RangeStats>> = ids.iter().map(|id| cache.get(&key(id))).collect();
+    let missing: Vec<&String> = ids
+        .iter()
+        .zip(&found)
+        .filter(|(_, f)| f.is_none())
+        .map(|(id, _)| id)
+        .collect();
+    if !missing.is_empty() {
+        let epoch = cache.epoch();
+        // One habit at a time is only cheaper while few are missing.
+        let habits: HashMap<String, Habit> = if missing.len() <= 3 {
+            let mut habits = HashMap::new();
+            for id in &missing {
+                if let Some(habit) = storage.get_habit(id)? {
+                    habits.insert(habit.id.clone(), habit);
+                }
+            }
+            habits
+        } else {
+            storage
+                .list_habits()?
+                .into_iter()
+                .map(|h| (h.id.clone(), h))
+                .collect()
+        };
+        for (id, slot) in ids.iter().zip(&mut found) {
+            if slot.is_some() {
+                continue;
+            }
+            let habit = habits
+                .get(id)
+                .ok_or_else(|| AppError::not_found("habit", id))?;
+            let stats = compute(habit, from, to, week_start);
+            cache.insert(epoch, key(id), stats.clone());
+            *slot = Some(stats);
+        }
+    }
+    Ok(found.into_iter().flatten().collect())
+}
+
+/// The heatmap, week totals, completion rate and score of each habit over a range, served
+/// from the cache where nothing changed since.
+#[tauri::command]
+pub fn get_range_stats(
+    storage: State<'_, Storage>,
+    habit_ids: Option<Vec<String>>,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<Vec<RangeStats>, AppError> {
+    range_stats(&storage, habit_ids, from.as_deref(), to.as_deref())
+}
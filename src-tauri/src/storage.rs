@@ -0,0 +1,4192 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+use std::time::Duration;
+
+use rusqlite::functions::FunctionFlags;
+use rusqlite::{params, Connection, OptionalExtension, TransactionBehavior};
+use serde::Serialize;
+
+use crate::model::{
+    Category, ChallengeKind, ChallengeStatus, ChecklistItem, DailyChallenge, Difficulty, Goal,
+    GoalPeriod, Habit, HabitFrequency, HabitKind, HabitTarget, Lapse, Milestone, MilestoneKind,
+    OffPeriod, Redemption, Reward, TimeWindow,
+};
+use crate::stats_cache::StatsCache;
+
+/// File name inside the app data dir.
+pub const DB_FILE_NAME: &str = "habitflow.db";
+
+/// Read-only connections kept open next to the one that writes.
+const READERS: usize = 3;
+/// How long a statement waits on a lock held by another connection (the CLI, or the
+/// writer while a reader checkpoints) before failing with `SQLITE_BUSY`.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Prepared statements kept per connection.
+const STATEMENT_CACHE: usize = 64;
+/// Export runs kept per job; older ones are pruned as new ones are recorded.
+const EXPORT_RUNS_KEPT: u32 = 100;
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how many have run,
+/// so only ever append to this list.
+const MIGRATIONS: &[&str] = &[
+    // 1: habits + completions mirrored from the webview
+    "CREATE TABLE habits (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        description TEXT,
+        color TEXT NOT NULL,
+        icon TEXT,
+        category TEXT NOT NULL,
+        frequency_type TEXT NOT NULL,
+        frequency_goal INTEGER NOT NULL,
+        reminder_time TEXT,
+        created_at TEXT NOT NULL,
+        archived INTEGER NOT NULL DEFAULT 0,
+        position INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE TABLE completions (
+        habit_id TEXT NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        PRIMARY KEY (habit_id, date)
+    );",
+    // 2: backend bookkeeping + webhook delivery log
+    "CREATE TABLE meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE webhook_deliveries (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        webhook_id TEXT NOT NULL,
+        event_type TEXT NOT NULL,
+        url TEXT NOT NULL,
+        attempt INTEGER NOT NULL,
+        status_code INTEGER,
+        error TEXT,
+        success INTEGER NOT NULL,
+        attempted_at TEXT NOT NULL
+    );",
+    // 3: scheduled export job history
+    "CREATE TABLE export_runs (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        job_id TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        path TEXT,
+        error TEXT,
+        success INTEGER NOT NULL
+    );
+    CREATE INDEX export_runs_job ON export_runs (job_id, id);",
+    // 4: quantified habits; a day's value is the sum of its check-ins
+    "ALTER TABLE habits ADD COLUMN target_amount REAL;
+    ALTER TABLE habits ADD COLUMN target_unit TEXT;
+    CREATE TABLE check_ins (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        habit_id TEXT NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        value REAL NOT NULL,
+        logged_at TEXT NOT NULL
+    );
+    CREATE INDEX check_ins_habit_date ON check_ins (habit_id, date);",
+    // 5: avoidance habits and their lapses
+    "ALTER TABLE habits ADD COLUMN kind TEXT NOT NULL DEFAULT 'build';
+    CREATE TABLE lapses (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        habit_id TEXT NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        note TEXT,
+        logged_at TEXT NOT NULL
+    );
+    CREATE INDEX lapses_habit ON lapses (habit_id, date);",
+    // 6: categories as their own rows; habits still reference them by name
+    "CREATE TABLE categories (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+        color TEXT NOT NULL,
+        position INTEGER NOT NULL DEFAULT 0
+    );
+    INSERT OR IGNORE INTO categories (id, name, color, position)
+        SELECT lower(hex(randomblob(8))), category, MIN(color), MIN(position)
+        FROM habits GROUP BY category;",
+    // 7: tags on habits and on individual check-ins
+    "CREATE TABLE habit_tags (
+        habit_id TEXT NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (habit_id, tag)
+    );
+    CREATE INDEX habit_tags_tag ON habit_tags (tag);
+    CREATE TABLE check_in_tags (
+        check_in_id INTEGER NOT NULL REFERENCES check_ins(id) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (check_in_id, tag)
+    );
+    CREATE INDEX check_in_tags_tag ON check_in_tags (tag);",
+    // 8: checklist habits; the habit's own completion follows its items
+    "CREATE TABLE checklist_items (
+        id TEXT PRIMARY KEY,
+        habit_id TEXT NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+        name TEXT NOT NULL,
+        position INTEGER NOT NULL DEFAULT 0
+    );
+    CREATE INDEX checklist_items_habit ON checklist_items (habit_id, position);
+    CREATE TABLE checklist_completions (
+        item_id TEXT NOT NULL REFERENCES checklist_items(id) ON DELETE CASCADE,
+        date TEXT NOT NULL,
+        PRIMARY KEY (item_id, date)
+    );",
+    // 9: prerequisites between habits
+    "CREATE TABLE habit_dependencies (
+        habit_id TEXT NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+        depends_on TEXT NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+        PRIMARY KEY (habit_id, depends_on)
+    );",
+    // 10: when a habit was archived
+    "ALTER TABLE habits ADD COLUMN archived_at TEXT;",
+    // 11: vacation / skip days. No foreign key: habit rows are recreated on every sync.
+    "CREATE TABLE off_periods (
+        id TEXT PRIMARY KEY,
+        habit_id TEXT,
+        start_date TEXT NOT NULL,
+        end_date TEXT NOT NULL,
+        reason TEXT
+    );",
+    // 12: streak freezes spent on missed days. No foreign key, as above.
+    "CREATE TABLE streak_freezes (
+        habit_id TEXT NOT NULL,
+        date TEXT NOT NULL,
+        used_at TEXT NOT NULL,
+        PRIMARY KEY (habit_id, date)
+    );",
+    // 13: time-of-day windows
+    "ALTER TABLE habits ADD COLUMN window_start TEXT;
+    ALTER TABLE habits ADD COLUMN window_end TEXT;",
+    // 14: journal notes on check-ins, with a full-text index
+    "CREATE TABLE completion_notes (
+        habit_id TEXT NOT NULL,
+        date TEXT NOT NULL,
+        note TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        PRIMARY KEY (habit_id, date)
+    );
+    CREATE VIRTUAL TABLE completion_notes_fts USING fts5(
+        note, content = 'completion_notes', content_rowid = 'rowid'
+    );
+    CREATE TRIGGER completion_notes_ai AFTER INSERT ON completion_notes BEGIN
+        INSERT INTO completion_notes_fts (rowid, note) VALUES (new.rowid, new.note);
+    END;
+    CREATE TRIGGER completion_notes_ad AFTER DELETE ON completion_notes BEGIN
+        INSERT INTO completion_notes_fts (completion_notes_fts, rowid, note)
+            VALUES ('delete', old.rowid, old.note);
+    END;
+    CREATE TRIGGER completion_notes_au AFTER UPDATE ON completion_notes BEGIN
+        INSERT INTO completion_notes_fts (completion_notes_fts, rowid, note)
+            VALUES ('delete', old.rowid, old.note);
+        INSERT INTO completion_notes_fts (rowid, note) VALUES (new.rowid, new.note);
+    END;",
+    // 15: daily mood
+    "CREATE TABLE moods (
+        date TEXT PRIMARY KEY,
+        score INTEGER NOT NULL,
+        logged_at TEXT NOT NULL
+    );
+    CREATE TABLE mood_tags (
+        date TEXT NOT NULL REFERENCES moods(date) ON DELETE CASCADE,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (date, tag)
+    );",
+    // 16: pinned habits
+    "ALTER TABLE habits ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0;",
+    // 17: per-weekday reminder times
+    "CREATE TABLE weekday_reminders (
+        habit_id TEXT NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+        weekday INTEGER NOT NULL,
+        time TEXT NOT NULL,
+        PRIMARY KEY (habit_id, weekday)
+    );",
+    // 18: completions logged after the fact
+    "ALTER TABLE completions ADD COLUMN backfilled INTEGER NOT NULL DEFAULT 0;",
+    // 19: habits done several times a day
+    "ALTER TABLE habits ADD COLUMN times_per_day INTEGER NOT NULL DEFAULT 1;",
+    // 20: appearance beyond color and icon
+    "ALTER TABLE habits ADD COLUMN emoji TEXT;
+    ALTER TABLE habits ADD COLUMN cover TEXT;",
+    // 21: habit milestones. No foreign key: habit rows are recreated on every sync.
+    "CREATE TABLE milestones (
+        id TEXT PRIMARY KEY,
+        habit_id TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        threshold REAL NOT NULL,
+        reached_at TEXT,
+        created_at TEXT NOT NULL
+    );
+    CREATE INDEX milestones_habit ON milestones (habit_id);",
+    // 22: monthly / quarterly goals, also keyed by habit id without a foreign key
+    "CREATE TABLE goals (
+        id TEXT PRIMARY KEY,
+        title TEXT NOT NULL,
+        habit_id TEXT NOT NULL,
+        period TEXT NOT NULL,
+        start TEXT NOT NULL,
+        end TEXT NOT NULL,
+        target REAL NOT NULL,
+        created_at TEXT NOT NULL
+    );",
+    // 23: off periods created by pausing a habit
+    "ALTER TABLE off_periods ADD COLUMN paused INTEGER NOT NULL DEFAULT 0;",
+    // 24: difficulty, for XP
+    "ALTER TABLE habits ADD COLUMN difficulty TEXT NOT NULL DEFAULT 'medium';",
+    // 25: unlocked achievements; the catalog itself is in `achievements.rs`
+    "CREATE TABLE achievements (
+        id TEXT PRIMARY KEY,
+        unlocked_at TEXT NOT NULL
+    );",
+    // 26: daily challenges, one per day
+    "CREATE TABLE daily_challenges (
+        date TEXT PRIMARY KEY,
+        kind TEXT NOT NULL,
+        title TEXT NOT NULL,
+        habit_id TEXT,
+        category TEXT,
+        deadline TEXT,
+        xp INTEGER NOT NULL,
+        status TEXT NOT NULL DEFAULT 'open',
+        resolved_at TEXT
+    );",
+    // 27: rewards bought with points, and what was bought
+    "CREATE TABLE rewards (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        cost INTEGER NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    CREATE TABLE redemptions (
+        id TEXT PRIMARY KEY,
+        reward_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        cost INTEGER NOT NULL,
+        redeemed_at TEXT NOT NULL
+    );",
+    // 28: daily streaks kept as runs of done days (`streaks.rs`), keyed by habit id without
+    // a foreign key. `streak_habits` lists the habits whose runs are built, `streak_dates`
+    // the days changed since.
+    "CREATE TABLE streak_habits (
+        habit_id TEXT PRIMARY KEY
+    );
+    CREATE TABLE streak_runs (
+        habit_id TEXT NOT NULL,
+        start TEXT NOT NULL,
+        end TEXT NOT NULL,
+        days INTEGER NOT NULL,
+        PRIMARY KEY (habit_id, start)
+    );
+    CREATE INDEX streak_runs_end ON streak_runs (habit_id, end);
+    CREATE TABLE streak_dates (
+        habit_id TEXT NOT NULL,
+        date TEXT NOT NULL,
+        PRIMARY KEY (habit_id, date)
+    );",
+    // 29: photos attached to check-ins (`attachments.rs`); the files are in the data dir's
+    // `attachments` folder. Keyed by habit id without a foreign key, like the notes.
+    "CREATE TABLE attachments (
+        id TEXT PRIMARY KEY,
+        habit_id TEXT NOT NULL,
+        date TEXT NOT NULL,
+        file TEXT NOT NULL,
+        mime TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        added_at TEXT NOT NULL
+    );
+    CREATE INDEX attachments_day ON attachments (habit_id, date);",
+    // 30: NFC tags bound to habits (`nfc.rs`), by the tag's hardware id
+    "CREATE TABLE nfc_tags (
+        tag_id TEXT PRIMARY KEY,
+        habit_id TEXT NOT NULL,
+        label TEXT,
+        bound_at TEXT NOT NULL,
+        last_scanned_at TEXT
+    );",
+    // 31: the outbound sync queue (`sync_queue.rs`). `sync_state` has a fingerprint of
+    // everything queued so far, to find what changed since.
+    "CREATE TABLE sync_ops (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        id TEXT NOT NULL UNIQUE,
+        key TEXT NOT NULL UNIQUE,
+        op TEXT NOT NULL,
+        recorded_at TEXT NOT NULL
+    );
+    CREATE TABLE sync_state (
+        key TEXT PRIMARY KEY,
+        fingerprint TEXT NOT NULL
+    );",
+    // 32: revisions, for optimistic concurrency (`revisions.rs`). One row per habit (`date`
+    // is '') and one per day that has changed. No foreign key, so a habit's numbers keep
+    // going up across the delete and insert of a sync. `replace_habits` turns the
+    // triggers off and bumps what actually changed itself.
+    "CREATE TABLE revisions (
+        habit_id TEXT NOT NULL,
+        date TEXT NOT NULL,
+        revision INTEGER NOT NULL,
+        PRIMARY KEY (habit_id, date)
+    );
+    INSERT INTO revisions (habit_id, date, revision) SELECT id, '', 1 FROM habits;
+    CREATE TRIGGER habits_rev_ai AFTER INSERT ON habits
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER habits_rev_au AFTER UPDATE OF name, description, color, icon, category,
+        frequency_type, frequency_goal, reminder_time, created_at, archived, target_amount,
+        target_unit, kind, archived_at, window_start, window_end, pinned, times_per_day,
+        emoji, cover, difficulty ON habits
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER completions_rev_ai AFTER INSERT ON completions
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1), (NEW.habit_id, NEW.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER completions_rev_au AFTER UPDATE ON completions
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1), (NEW.habit_id, NEW.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER completions_rev_ad AFTER DELETE ON completions
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (OLD.habit_id, '', 1), (OLD.habit_id, OLD.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER check_ins_rev_ai AFTER INSERT ON check_ins
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1), (NEW.habit_id, NEW.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER check_ins_rev_au AFTER UPDATE ON check_ins
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1), (NEW.habit_id, NEW.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER check_ins_rev_ad AFTER DELETE ON check_ins
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (OLD.habit_id, '', 1), (OLD.habit_id, OLD.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER lapses_rev_ai AFTER INSERT ON lapses
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1), (NEW.habit_id, NEW.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER lapses_rev_au AFTER UPDATE ON lapses
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1), (NEW.habit_id, NEW.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER lapses_rev_ad AFTER DELETE ON lapses
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (OLD.habit_id, '', 1), (OLD.habit_id, OLD.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER completion_notes_rev_ai AFTER INSERT ON completion_notes
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1), (NEW.habit_id, NEW.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER completion_notes_rev_au AFTER UPDATE ON completion_notes
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1), (NEW.habit_id, NEW.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER completion_notes_rev_ad AFTER DELETE ON completion_notes
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (OLD.habit_id, '', 1), (OLD.habit_id, OLD.date, 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER checklist_completions_rev_ai AFTER INSERT ON checklist_completions
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions SELECT habit_id, '', 1 FROM checklist_items WHERE id = NEW.item_id
+            UNION ALL
+            SELECT habit_id, NEW.date, 1 FROM checklist_items WHERE id = NEW.item_id
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER checklist_completions_rev_ad AFTER DELETE ON checklist_completions
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions SELECT habit_id, '', 1 FROM checklist_items WHERE id = OLD.item_id
+            UNION ALL
+            SELECT habit_id, OLD.date, 1 FROM checklist_items WHERE id = OLD.item_id
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER habit_tags_rev_ai AFTER INSERT ON habit_tags
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER habit_tags_rev_ad AFTER DELETE ON habit_tags
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (OLD.habit_id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER checklist_items_rev_ai AFTER INSERT ON checklist_items
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER checklist_items_rev_au AFTER UPDATE ON checklist_items
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER checklist_items_rev_ad AFTER DELETE ON checklist_items
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (OLD.habit_id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER habit_dependencies_rev_ai AFTER INSERT ON habit_dependencies
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER habit_dependencies_rev_ad AFTER DELETE ON habit_dependencies
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (OLD.habit_id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER weekday_reminders_rev_ai AFTER INSERT ON weekday_reminders
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER weekday_reminders_rev_au AFTER UPDATE ON weekday_reminders
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (NEW.habit_id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;
+    CREATE TRIGGER weekday_reminders_rev_ad AFTER DELETE ON weekday_reminders
+    WHEN NOT EXISTS (SELECT 1 FROM meta WHERE key = 'revisions.paused') BEGIN
+        INSERT INTO revisions VALUES (OLD.habit_id, '', 1)
+            ON CONFLICT DO UPDATE SET revision = revision + 1;
+    END;",
+    // 33: time spent per app and day (`screen_time.rs`), under the category the app was
+    // in when it was used. `category` is '' for apps that aren't in one.
+    "CREATE TABLE screen_time (
+        date TEXT NOT NULL,
+        category TEXT NOT NULL,
+        app TEXT NOT NULL,
+        seconds INTEGER NOT NULL,
+        PRIMARY KEY (date, category, app)
+    );",
+    // 34: row-level libSQL replication (`replica.rs`). `replica_changes` has the key of
+    // each row written since the replicator last looked, from triggers it adds to every
+    // replicated table; `replica_rows` a hash of each row as the remote database has it.
+    // The per-table fingerprints from before are dropped, so devices join again.
+    "CREATE TABLE replica_changes (
+        tbl TEXT NOT NULL,
+        pk TEXT NOT NULL,
+        changed_at TEXT NOT NULL,
+        PRIMARY KEY (tbl, pk)
+    );
+    CREATE TABLE replica_rows (
+        tbl TEXT NOT NULL,
+        pk TEXT NOT NULL,
+        hash TEXT NOT NULL,
+        PRIMARY KEY (tbl, pk)
+    );
+    DELETE FROM meta WHERE key IN ('libsql.generation', 'libsql.tables');",
+];
+
+/// Backend copy of the habit data. The webview stays the source of truth and pushes
+/// snapshots via `sync_habits`; everything else in the backend (local API, tray, ...)
+/// reads and writes through here.
+///
+/// The database is in WAL mode: one connection writes, and plain queries go to a small pool
+/// of read-only connections, so reads from the tray, the scheduler and the local API don't
+/// queue behind a write the UI started, and the other way round. Writes, and reads that
+/// are part of one, stay on the writer.
+pub struct Storage {
+    conn: Mutex<Connection>,
+    readers: Vec<Mutex<Connection>>,
+    /// Which reader to wait for when they're all busy.
+    next_reader: AtomicUsize,
+    stats: StatsCache,
+}
+
+/// One write transaction, from [`Storage::transaction`]. What's read through it includes what
+/// was written through it before; dropping it without committing undoes everything.
+pub struct Tx<'a> {
+    storage: &'a Storage,
+    tx: rusqlite::Transaction<'a>,
+}
+
+impl Tx<'_> {
+    pub fn get_habit(&self, id: &str) -> rusqlite::Result<Option<Habit>> {
+        load_habit(&self.tx, id)
+    }
+
+    pub fn habit_exists(&self, id: &str) -> rusqlite::Result<bool> {
+        Ok(self
+            .tx
+            .query_row("SELECT 1 FROM habits WHERE id = ?1", [id], |_| Ok(()))
+            .optional()?
+            .is_some())
+    }
+
+    /// Bumps the revision of a habit (`date` empty) or one of its days if it's still
+    /// `expected`. Returns the revision it's on instead if it isn't.
+    pub fn claim_revision(
+        &self,
+        habit_id: &str,
+        date: &str,
+        expected: u64,
+    ) -> rusqlite::Result<Option<u64>> {
+        let claimed = if expected == 0 {
+            self.tx.execute(
+                "INSERT INTO revisions (habit_id, date, revision) VALUES (?1, ?2, 1)
+                 ON CONFLICT DO NOTHING",
+                params![habit_id, date],
+            )?
+        } else {
+            self.tx.execute(
+                "UPDATE revisions SET revision = revision + 1
+                 WHERE habit_id = ?1 AND date = ?2 AND revision = ?3",
+                params![habit_id, date, expected as i64],
+            )?
+        };
+        if claimed == 1 {
+            return Ok(None);
+        }
+        let revision: Option<i64> = self
+            .tx
+            .query_row(
+                "SELECT revision FROM revisions WHERE habit_id = ?1 AND date = ?2",
+                params![habit_id, date],
+                |r| r.get(0),
+            )
+            .optional()?;
+        Ok(Some(revision.unwrap_or(0) as u64))
+    }
+
+    /// Returns whether the habit exists.
+    pub fn set_archived(
+        &self,
+        habit_id: &str,
+        archived_at: Option<&str>,
+    ) -> rusqlite::Result<bool> {
+        let changed = self.tx.execute(
+            "UPDATE habits SET archived = ?2, archived_at = ?3 WHERE id = ?1",
+            params![habit_id, archived_at.is_some(), archived_at],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Marks or unmarks a day, along with every checklist item of the habit. Returns
+    /// whether the habit's completion changed.
+    pub fn set_completion(&self, habit_id: &str, date: &str, done: bool) -> rusqlite::Result<bool> {
+        self.write_completion(habit_id, date, done, false)
+    }
+
+    /// Checks off a past day, marked as logged after the fact. Returns whether it wasn't
+    /// checked off already.
+    pub fn backfill_completion(&self, habit_id: &str, date: &str) -> rusqlite::Result<bool> {
+        self.write_completion(habit_id, date, true, true)
+    }
+
+    fn write_completion(
+        &self,
+        habit_id: &str,
+        date: &str,
+        done: bool,
+        backfilled: bool,
+    ) -> rusqlite::Result<bool> {
+        let tx = &self.tx;
+        let changed = if done {
+            tx.execute(
+                "INSERT OR IGNORE INTO checklist_completions (item_id, date)
+                 SELECT id, ?2 FROM checklist_items WHERE habit_id = ?1",
+                params![habit_id, date],
+            )?;
+            tx.execute(
+                "INSERT OR IGNORE INTO completions (habit_id, date, backfilled)
+                 VALUES (?1, ?2, ?3)",
+                params![habit_id, date, backfilled],
+            )?
+        } else {
+            tx.execute(
+                "DELETE FROM checklist_completions WHERE date = ?2
+                 AND item_id IN (SELECT id FROM checklist_items WHERE habit_id = ?1)",
+                params![habit_id, date],
+            )?;
+            tx.execute(
+                "DELETE FROM completions WHERE habit_id = ?1 AND date = ?2",
+                params![habit_id, date],
+            )?
+        };
+        self.storage.day_changed(tx, habit_id, date)?;
+        Ok(changed > 0)
+    }
+
+    /// Checks or unchecks one item, then completes the habit for that day if every item is
+    /// now done (or un-completes it if not). Returns the habit id and whether the habit's
+    /// completion changed, or `None` if there's no such item.
+    pub fn set_checklist_item_done(
+        &self,
+        item_id: &str,
+        date: &str,
+        done: bool,
+    ) -> rusqlite::Result<Option<(String, bool)>> {
+        let tx = &self.tx;
+        let Some(habit_id) = checklist_item_habit(tx, item_id)? else {
+            return Ok(None);
+        };
+        if done {
+            tx.execute(
+                "INSERT OR IGNORE INTO checklist_completions (item_id, date) VALUES (?1, ?2)",
+                params![item_id, date],
+            )?;
+        } else {
+            tx.execute(
+                "DELETE FROM checklist_completions WHERE item_id = ?1 AND date = ?2",
+                params![item_id, date],
+            )?;
+        }
+        let remaining: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM checklist_items i WHERE i.habit_id = ?1 AND NOT EXISTS
+                (SELECT 1 FROM checklist_completions c WHERE c.item_id = i.id AND c.date = ?2)",
+            params![habit_id, date],
+            |r| r.get(0),
+        )?;
+        let changed = if remaining == 0 {
+            tx.execute(
+                "INSERT OR IGNORE INTO completions (habit_id, date) VALUES (?1, ?2)",
+                params![habit_id, date],
+            )?
+        } else {
+            tx.execute(
+                "DELETE FROM completions WHERE habit_id = ?1 AND date = ?2",
+                params![habit_id, date],
+            )?
+        };
+        self.storage.day_changed(tx, &habit_id, date)?;
+        Ok(Some((habit_id, changed > 0)))
+    }
+
+    /// Sets or (with `None`) clears the note on a day. Returns whether the habit exists.
+    pub fn set_completion_note(
+        &self,
+        habit_id: &str,
+        date: &str,
+        note: Option<&str>,
+    ) -> rusqlite::Result<bool> {
+        if !self.habit_exists(habit_id)? {
+            return Ok(false);
+        }
+        match note {
+            Some(note) => self.tx.execute(
+                "INSERT INTO completion_notes (habit_id, date, note, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(habit_id, date) DO UPDATE
+                 SET note = excluded.note, updated_at = excluded.updated_at",
+                params![habit_id, date, note, chrono::Utc::now().to_rfc3339()],
+            )?,
+            None => self.tx.execute(
+                "DELETE FROM completion_notes WHERE habit_id = ?1 AND date = ?2",
+                params![habit_id, date],
+            )?,
+        };
+        Ok(true)
+    }
+
+    pub fn add_check_in(
+        &self,
+        habit_id: &str,
+        date: &str,
+        value: f64,
+        tags: &[String],
+    ) -> rusqlite::Result<()> {
+        let tx = &self.tx;
+        tx.execute(
+            "INSERT INTO check_ins (habit_id, date, value, logged_at) VALUES (?1, ?2, ?3, ?4)",
+            params![habit_id, date, value, chrono::Utc::now().to_rfc3339()],
+        )?;
+        let id = tx.last_insert_rowid();
+        for tag in tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO check_in_tags (check_in_id, tag) VALUES (?1, ?2)",
+                params![id, tag],
+            )?;
+        }
+        self.storage.day_changed(tx, habit_id, date)
+    }
+
+    /// Removes the day's latest check-in. Returns whether there was one.
+    pub fn remove_last_check_in(&self, habit_id: &str, date: &str) -> rusqlite::Result<bool> {
+        let removed = self.tx.execute(
+            "DELETE FROM check_ins WHERE id = (
+                SELECT MAX(id) FROM check_ins WHERE habit_id = ?1 AND date = ?2
+            )",
+            params![habit_id, date],
+        )?;
+        self.storage.day_changed(&self.tx, habit_id, date)?;
+        Ok(removed > 0)
+    }
+
+    pub fn clear_check_ins(&self, habit_id: &str, date: &str) -> rusqlite::Result<()> {
+        self.tx.execute(
+            "DELETE FROM check_ins WHERE habit_id = ?1 AND date = ?2",
+            params![habit_id, date],
+        )?;
+        self.storage.day_changed(&self.tx, habit_id, date)
+    }
+
+    pub fn add_lapse(&self, habit_id: &str, lapse: &Lapse) -> rusqlite::Result<()> {
+        self.tx.execute(
+            "INSERT INTO lapses (habit_id, date, note, logged_at) VALUES (?1, ?2, ?3, ?4)",
+            params![habit_id, lapse.date, lapse.note, lapse.logged_at],
+        )?;
+        self.storage.stats.forget_day(habit_id, &lapse.date);
+        Ok(())
+    }
+}
+
+fn configure(conn: &Connection) -> rusqlite::Result<()> {
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.set_prepared_statement_cache_capacity(STATEMENT_CACHE);
+    // Called on the thread doing the write, so it's on the same clock as everything else.
+    conn.create_scalar_function(
+        NOW_FUNCTION,
+        0,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_INNOCUOUS,
+        |_| Ok(changed_now()),
+    )
+}
+
+impl Storage {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let mut conn = Connection::open(path)?;
+        configure(&conn)?;
+        // A query, since it answers with the mode it ended up in.
+        conn.query_row("PRAGMA journal_mode = WAL", [], |_| Ok(()))?;
+        conn.execute_batch("PRAGMA synchronous = NORMAL; PRAGMA foreign_keys = ON;")?;
+        migrate(&mut conn)?;
+        let readers = (0..READERS)
+            .map(|_| {
+                let reader = Connection::open(path)?;
+                configure(&reader)?;
+                reader.execute_batch("PRAGMA query_only = ON;")?;
+                Ok(Mutex::new(reader))
+            })
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+            readers,
+            next_reader: AtomicUsize::new(0),
+            stats: StatsCache::default(),
+        })
+    }
+
+    /// Has every connection drop its prepared statements and spare memory, and folds the
+    /// write-ahead log back into the database file.
+    pub fn trim(&self) -> rusqlite::Result<()> {
+        for conn in std::iter::once(&self.conn).chain(&self.readers) {
+            let conn = conn.lock().unwrap_or_else(|e| e.into_inner());
+            conn.flush_prepared_statement_cache();
+            conn.execute_batch("PRAGMA shrink_memory;")?;
+        }
+        self.conn()
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))
+    }
+
+    /// Bytes the database file takes up, without the write-ahead log.
+    pub fn database_size(&self) -> rusqlite::Result<u64> {
+        self.reader().query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |r| r.get::<_, i64>(0).map(|n| n as u64),
+        )
+    }
+
+    /// Rebuilds the database file without its free pages and refreshes the statistics the
+    /// query planner uses, then [`trim`](Self::trim)s. Writes wait until it's done.
+    pub fn vacuum(&self) -> rusqlite::Result<()> {
+        self.conn()
+            .execute_batch("VACUUM; ANALYZE; PRAGMA optimize;")?;
+        self.trim()
+    }
+
+    /// A free reader, or the next one in turn if they're all busy.
+    fn reader(&self) -> MutexGuard<'_, Connection> {
+        for reader in &self.readers {
+            match reader.try_lock() {
+                Ok(guard) => return guard,
+                Err(TryLockError::Poisoned(e)) => return e.into_inner(),
+                Err(TryLockError::WouldBlock) => {}
+            }
+        }
+        let next = self.next_reader.fetch_add(1, Ordering::Relaxed) % self.readers.len();
+        self.readers[next].lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn stats_cache(&self) -> &StatsCache {
+        &self.stats
+    }
+
+    /// A habit's day changed: queued for its streak runs and dropped from the stats cache.
+    fn day_changed(&self, conn: &Connection, habit_id: &str, date: &str) -> rusqlite::Result<()> {
+        self.stats.forget_day(habit_id, date);
+        queue_streak_date(conn, habit_id, date)
+    }
+
+    /// Every day of one habit, or of all of them, may count differently now.
+    fn days_changed(&self, conn: &Connection, habit_id: Option<&str>) -> rusqlite::Result<()> {
+        self.stats.forget_habit(habit_id);
+        forget_streaks(conn, habit_id)
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        // A panic while holding the lock can't leave SQLite itself inconsistent.
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Runs `write` in one transaction on the writer, committed only if it succeeds. Other
+    /// writers, in this process or another, wait until it's done.
+    pub fn transaction<T, E: From<rusqlite::Error>>(
+        &self,
+        write: impl FnOnce(&Tx<'_>) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let mut conn = self.conn();
+        let tx = Tx {
+            storage: self,
+            tx: conn.transaction_with_behavior(TransactionBehavior::Immediate)?,
+        };
+        let out = write(&tx)?;
+        tx.tx.commit()?;
+        Ok(out)
+    }
+
+    /// Ids of the habits that aren't archived, in list order.
+    pub fn active_habit_ids(&self) -> rusqlite::Result<Vec<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id FROM habits WHERE archived = 0 ORDER BY position, created_at",
+        )?;
+        let rows = stmt.query_map([], |r| r.get(0))?;
+        rows.collect()
+    }
+
+    /// Migrations applied to the database, and how many this build knows of.
+    pub fn schema_version(&self) -> rusqlite::Result<(usize, usize)> {
+        let applied = self
+            .reader()
+            .query_row("PRAGMA user_version", [], |r| r.get(0))?;
+        Ok((applied, MIGRATIONS.len()))
+    }
+
+    /// Rows in each table, by name.
+    pub fn table_counts(&self) -> rusqlite::Result<Vec<(String, u64)>> {
+        let conn = self.reader();
+        let names: Vec<String> = conn
+            .prepare(
+                "SELECT name FROM sqlite_master
+                 WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            )?
+            .query_map([], |r| r.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        names
+            .into_iter()
+            .map(|name| {
+                let count = conn.query_row(
+                    &format!("SELECT COUNT(*) FROM \"{}\"", name.replace('"', "\"\"")),
+                    [],
+                    |r| r.get(0),
+                )?;
+                Ok((name, count))
+            })
+            .collect()
+    }
+
+    /// Empties every table but the `meta` rows named in `keep`, leaving the schema as it is.
+    /// What the libSQL replica has synced stays, so the deletions are sent up. Returns how
+    /// many rows went.
+    pub fn clear_all(&self, keep: &[&str]) -> rusqlite::Result<u64> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute_batch("PRAGMA defer_foreign_keys = ON;")?;
+        // Full-text indexes follow their tables through triggers; their own tables are
+        // left to them.
+        let tables: Vec<(String, bool)> = tx
+            .prepare(
+                "SELECT name, sql LIKE 'CREATE VIRTUAL TABLE%' FROM sqlite_master
+                 WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+            )?
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let indexes: Vec<&str> = tables
+            .iter()
+            .filter(|(_, virtual_table)| *virtual_table)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        let mut removed = 0;
+        for (name, _) in &tables {
+            if REPLICA_TABLES.contains(&name.as_str())
+                || indexes
+                    .iter()
+                    .any(|index| name == index || name.starts_with(&format!("{index}_")))
+            {
+                continue;
+            }
+            let quoted = name.replace('"', "\"\"");
+            removed += if name == "meta" {
+                let keep = serde_json::to_string(keep).unwrap_or_default();
+                tx.execute(
+                    "DELETE FROM meta WHERE key NOT IN (SELECT value FROM json_each(?1))",
+                    [keep],
+                )?
+            } else {
+                tx.execute(&format!("DELETE FROM \"{quoted}\""), [])?
+            } as u64;
+        }
+        tx.commit()?;
+        self.stats.forget_habit(None);
+        Ok(removed)
+    }
+
+    /// The tables holding the user's data, in creation order. Full-text indexes,
+    /// [`LOCAL_TABLES`] and tables without a primary key are left out.
+    pub fn replicated_schema(&self) -> rusqlite::Result<Vec<ReplicatedTable>> {
+        let conn = self.reader();
+        let entries: Vec<(String, String, String)> = conn
+            .prepare(
+                "SELECT type, tbl_name, sql FROM sqlite_master
+                 WHERE type IN ('table', 'index') AND sql IS NOT NULL
+                   AND name NOT LIKE 'sqlite_%'
+                 ORDER BY rowid",
+            )?
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let indexes: Vec<&str> = entries
+            .iter()
+            .filter(|(_, _, sql)| sql.starts_with("CREATE VIRTUAL TABLE"))
+            .map(|(_, table, _)| table.as_str())
+            .collect();
+        let mut tables: Vec<ReplicatedTable> = Vec::new();
+        for (kind, table, sql) in &entries {
+            let full_text = indexes
+                .iter()
+                .any(|index| table == index || table.starts_with(&format!("{index}_")));
+            if full_text || LOCAL_TABLES.contains(&table.as_str()) {
+                continue;
+            }
+            if kind == "table" {
+                let mut key: Vec<(i64, String)> = conn
+                    .prepare(&format!("PRAGMA table_info({})", quote_ident(table)))?
+                    .query_map([], |r| Ok((r.get(5)?, r.get(1)?)))?
+                    .filter(|c| c.as_ref().map_or(true, |(pk, _)| *pk > 0))
+                    .collect::<rusqlite::Result<_>>()?;
+                if key.is_empty() {
+                    log::warn!("{table} has no primary key, so it isn't replicated");
+                    continue;
+                }
+                key.sort();
+                tables.push(ReplicatedTable {
+                    name: table.clone(),
+                    sql: vec![sql.clone()],
+                    key: key.into_iter().map(|(_, column)| column).collect(),
+                });
+            } else if let Some(t) = tables.iter_mut().find(|t| t.name == *table) {
+                t.sql.push(sql.clone());
+            }
+        }
+        Ok(tables)
+    }
+
+    /// Adds the triggers that fill `replica_changes` to each of `tables` missing them, and
+    /// counts all of such a table's rows as changed, since nothing recorded what was
+    /// written to it before (a migration that rebuilt it, say). Triggers from before
+    /// [`NOW_FUNCTION`] are replaced without that.
+    pub fn replica_track(&self, tables: &[ReplicatedTable]) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let existing: HashMap<String, String> = tx
+            .prepare("SELECT name, sql FROM sqlite_master WHERE type = 'trigger'")?
+            .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        for table in tables {
+            let triggers = replica_triggers(table);
+            if triggers.iter().all(|(name, _)| existing.contains_key(name)) {
+                for (name, sql) in &triggers {
+                    if !existing[name].contains(NOW_FUNCTION) {
+                        tx.execute_batch(&format!("DROP TRIGGER {}", quote_ident(name)))?;
+                        tx.execute_batch(sql)?;
+                    }
+                }
+                continue;
+            }
+            for (_, sql) in &triggers {
+                tx.execute_batch(sql)?;
+            }
+            tx.execute(
+                &format!(
+                    "INSERT OR IGNORE INTO replica_changes (tbl, pk, changed_at)
+                     SELECT ?1, {}, {CHANGED_NOW} FROM {}",
+                    key_json("", &table.key),
+                    quote_ident(&table.name)
+                ),
+                [&table.name],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Forgets what was written and synced, for a device starting over with another
+    /// libSQL database.
+    pub fn replica_forget(&self) -> rusqlite::Result<()> {
+        self.conn()
+            .execute_batch("DELETE FROM replica_changes; DELETE FROM replica_rows;")
+    }
+
+    /// The rows of `tables` written since they were last synced, as they are now (`None`
+    /// when deleted). Writes that left a row as the remote side has it are forgotten.
+    pub fn replica_changes(
+        &self,
+        tables: &[ReplicatedTable],
+    ) -> rusqlite::Result<Vec<TableChanges>> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let mut changes = Vec::new();
+        for table in tables {
+            let mut settled = Vec::new();
+            let mut stmt = tx.prepare(&format!(
+                "SELECT c.pk, c.changed_at, s.hash, r.{} IS NOT NULL, r.*
+                 FROM replica_changes c
+                 LEFT JOIN replica_rows s ON s.tbl = c.tbl AND s.pk = c.pk
+                 LEFT JOIN {} r ON {}
+                 WHERE c.tbl = ?1",
+                quote_ident(&table.key[0]),
+                quote_ident(&table.name),
+                key_matches("r", &table.key, "c.pk")
+            ))?;
+            let columns: Vec<String> = stmt
+                .column_names()
+                .into_iter()
+                .skip(4)
+                .map(String::from)
+                .collect();
+            let mut found = TableChanges {
+                name: table.name.clone(),
+                columns,
+                rows: Vec::new(),
+            };
+            let mut rows = stmt.query([&table.name])?;
+            while let Some(r) = rows.next()? {
+                let row = if r.get(3)? {
+                    Some(
+                        (4..4 + found.columns.len())
+                            .map(|i| r.get(i))
+                            .collect::<rusqlite::Result<Vec<_>>>()?,
+                    )
+                } else {
+                    None
+                };
+                let key: String = r.get(0)?;
+                if row.as_deref().map(row_hash) == r.get::<_, Option<String>>(2)? {
+                    settled.push(key);
+                } else {
+                    found.rows.push(RowChange {
+                        key,
+                        changed_at: r.get(1)?,
+                        row,
+                    });
+                }
+            }
+            drop(rows);
+            drop(stmt);
+            for key in settled {
+                tx.execute(
+                    "DELETE FROM replica_changes WHERE tbl = ?1 AND pk = ?2",
+                    params![table.name, key],
+                )?;
+            }
+            if !found.rows.is_empty() {
+                changes.push(found);
+            }
+        }
+        tx.commit()?;
+        Ok(changes)
+    }
+
+    /// Every row of `table`, as [`TableChanges`] for sending it up whole. Rows written
+    /// since the last sync have when; the others an empty `changed_at`.
+    pub fn replica_table(&self, table: &ReplicatedTable) -> rusqlite::Result<TableChanges> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {}, coalesce(c.changed_at, ''), r.* FROM {} r
+             LEFT JOIN replica_changes c ON c.tbl = ?1 AND c.pk = {0}",
+            key_json("r.", &table.key),
+            quote_ident(&table.name)
+        ))?;
+        let columns: Vec<String> = stmt
+            .column_names()
+            .into_iter()
+            .skip(2)
+            .map(String::from)
+            .collect();
+        let count = columns.len();
+        let rows = stmt
+            .query_map([&table.name], |r| {
+                Ok(RowChange {
+                    key: r.get(0)?,
+                    changed_at: r.get(1)?,
+                    row: Some(
+                        (2..2 + count)
+                            .map(|i| r.get(i))
+                            .collect::<rusqlite::Result<_>>()?,
+                    ),
+                })
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(TableChanges {
+            name: table.name.clone(),
+            columns,
+            rows,
+        })
+    }
+
+    /// Writes what changed on the remote side, in one transaction, and records it as
+    /// synced. A row written here since `seen` (when [`replica_changes`](Self::replica_changes)
+    /// last saw it written, if it did) is left alone; it's newer, so it's sent next round.
+    /// Columns this schema doesn't have are left out. Rows left pointing at a parent
+    /// that's gone are deleted, and those deletions sent next round. Returns how many rows
+    /// were written, and how many orphans went.
+    pub fn replica_apply(
+        &self,
+        tables: &[ReplicatedTable],
+        changes: &[TableChanges],
+        seen: &HashMap<(String, String), String>,
+    ) -> rusqlite::Result<(usize, usize)> {
+        let mut conn = self.conn();
+        // Deleting a row mustn't cascade into rows that changed on the remote side too;
+        // those deletions come with the rest, and what's left over is cleaned up after.
+        conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+        let result = self.replica_apply_in(&mut conn, tables, changes, seen);
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        result
+    }
+
+    fn replica_apply_in(
+        &self,
+        conn: &mut Connection,
+        tables: &[ReplicatedTable],
+        changes: &[TableChanges],
+        seen: &HashMap<(String, String), String>,
+    ) -> rusqlite::Result<(usize, usize)> {
+        let tx = conn.transaction()?;
+        let mut written = 0;
+        for change in changes {
+            let Some(table) = tables.iter().find(|t| t.name == change.name) else {
+                continue;
+            };
+            let quoted = quote_ident(&table.name);
+            let known: HashSet<String> = tx
+                .prepare(&format!("PRAGMA table_info({quoted})"))?
+                .query_map([], |r| r.get(1))?
+                .collect::<rusqlite::Result<_>>()?;
+            let columns: Vec<usize> = (0..change.columns.len())
+                .filter(|i| known.contains(&change.columns[*i]))
+                .collect();
+            let matches = key_matches(&quoted, &table.key, "?1");
+            let mut upsert = tx.prepare(&upsert_sql(
+                table,
+                columns.iter().map(|i| change.columns[*i].as_str()),
+                1,
+            ))?;
+            let mut delete = tx.prepare(&format!("DELETE FROM {quoted} WHERE {matches}"))?;
+            let mut read = tx.prepare(&format!("SELECT * FROM {quoted} WHERE {matches}"))?;
+            let count = read.column_count();
+            for row in &change.rows {
+                let (pending, synced): (Option<String>, Option<String>) = tx.query_row(
+                    "SELECT
+                         (SELECT changed_at FROM replica_changes WHERE tbl = ?1 AND pk = ?2),
+                         (SELECT hash FROM replica_rows WHERE tbl = ?1 AND pk = ?2)",
+                    params![change.name, row.key],
+                    |r| Ok((r.get(0)?, r.get(1)?)),
+                )?;
+                if pending.as_ref() != seen.get(&(change.name.clone(), row.key.clone())) {
+                    // Written again since, but maybe only to what it was (a sync of the
+                    // whole list does that).
+                    let current: Option<Vec<rusqlite::types::Value>> = read
+                        .query_row([&row.key], |r| (0..count).map(|i| r.get(i)).collect())
+                        .optional()?;
+                    if current.as_deref().map(row_hash) != synced {
+                        continue;
+                    }
+                }
+                match &row.row {
+                    Some(values) => {
+                        upsert.execute(rusqlite::params_from_iter(
+                            columns.iter().map(|i| &values[*i]),
+                        ))?;
+                    }
+                    None => {
+                        delete.execute([&row.key])?;
+                    }
+                }
+                let stored: Option<Vec<rusqlite::types::Value>> = read
+                    .query_row([&row.key], |r| (0..count).map(|i| r.get(i)).collect())
+                    .optional()?;
+                set_synced(&tx, &change.name, &row.key, stored.as_deref())?;
+                tx.execute(
+                    "DELETE FROM replica_changes WHERE tbl = ?1 AND pk = ?2",
+                    params![change.name, row.key],
+                )?;
+                written += 1;
+            }
+        }
+        let orphans = delete_orphans(&tx)?;
+        if written + orphans > 0 {
+            self.days_changed(&tx, None)?;
+        }
+        tx.commit()?;
+        Ok((written, orphans))
+    }
+
+    /// Records `changes` as what the remote side has now. `whole` means they're every row
+    /// of their tables, so rows recorded before and not among them are gone there.
+    pub fn replica_synced(&self, changes: &[TableChanges], whole: bool) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        for change in changes {
+            if whole {
+                tx.execute("DELETE FROM replica_rows WHERE tbl = ?1", [&change.name])?;
+            }
+            for row in &change.rows {
+                set_synced(&tx, &change.name, &row.key, row.row.as_deref())?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Replaces the rows of each of `tables`, in one transaction, for taking over a remote
+    /// database: what it has is recorded as synced, and what was written here before is
+    /// forgotten. Columns this schema doesn't have are left out. Rows left pointing at a
+    /// parent that's gone are deleted; returns how many.
+    pub fn replace_tables(
+        &self,
+        schema: &[ReplicatedTable],
+        tables: &[TableRows],
+    ) -> rusqlite::Result<usize> {
+        let mut conn = self.conn();
+        // Deleting a table's rows mustn't cascade into the tables that aren't replaced.
+        conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+        let result = self.replace_tables_in(&mut conn, schema, tables);
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        result
+    }
+
+    fn replace_tables_in(
+        &self,
+        conn: &mut Connection,
+        schema: &[ReplicatedTable],
+        tables: &[TableRows],
+    ) -> rusqlite::Result<usize> {
+        let tx = conn.transaction()?;
+        for table in tables {
+            let quoted = quote_ident(&table.name);
+            let known: HashSet<String> = tx
+                .prepare(&format!("PRAGMA table_info({quoted})"))?
+                .query_map([], |r| r.get(1))?
+                .collect::<rusqlite::Result<_>>()?;
+            let columns: Vec<usize> = (0..table.columns.len())
+                .filter(|i| known.contains(&table.columns[*i]))
+                .collect();
+            tx.execute(&format!("DELETE FROM {quoted}"), [])?;
+            if columns.is_empty() {
+                continue;
+            }
+            let names: Vec<String> = columns
+                .iter()
+                .map(|i| quote_ident(&table.columns[*i]))
+                .collect();
+            let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{i}")).collect();
+            let mut insert = tx.prepare(&format!(
+                "INSERT INTO {quoted} ({}) VALUES ({})",
+                names.join(", "),
+                placeholders.join(", ")
+            ))?;
+            for row in &table.rows {
+                insert.execute(rusqlite::params_from_iter(columns.iter().map(|i| &row[*i])))?;
+            }
+        }
+        let orphans = delete_orphans(&tx)?;
+        tx.execute_batch("DELETE FROM replica_changes; DELETE FROM replica_rows;")?;
+        for table in schema
+            .iter()
+            .filter(|t| tables.iter().any(|r| r.name == t.name))
+        {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT {}, * FROM {}",
+                key_json("", &table.key),
+                quote_ident(&table.name)
+            ))?;
+            let count = stmt.column_count();
+            let rows: Vec<(String, Vec<rusqlite::types::Value>)> = stmt
+                .query_map([], |r| {
+                    Ok((
+                        r.get(0)?,
+                        (1..count)
+                            .map(|i| r.get(i))
+                            .collect::<rusqlite::Result<_>>()?,
+                    ))
+                })?
+                .collect::<rusqlite::Result<_>>()?;
+            for (key, row) in rows {
+                set_synced(&tx, &table.name, &key, Some(&row))?;
+            }
+        }
+        self.days_changed(&tx, None)?;
+        tx.commit()?;
+        Ok(orphans)
+    }
+
+    /// `page_size`, `page_count` and `freelist_count`.
+    pub fn page_stats(&self) -> rusqlite::Result<(u64, u64, u64)> {
+        let conn = self.reader();
+        let pragma = |name: &str| conn.query_row(&format!("PRAGMA {name}"), [], |r| r.get(0));
+        Ok((
+            pragma("page_size")?,
+            pragma("page_count")?,
+            pragma("freelist_count")?,
+        ))
+    }
+
+    /// `PRAGMA quick_check`: `["ok"]`, or what's wrong.
+    pub fn quick_check(&self) -> rusqlite::Result<Vec<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare("PRAGMA quick_check")?;
+        let rows = stmt.query_map([], |r| r.get(0))?;
+        rows.collect()
+    }
+
+    /// Changes whenever *another* connection (e.g. the CLI) commits to the database.
+    pub fn data_version(&self) -> rusqlite::Result<i64> {
+        self.conn()
+            .query_row("PRAGMA data_version", [], |r| r.get(0))
+    }
+
+    /// Habits are read with a dozen queries, so each read is one transaction: a write
+    /// landing in between can't leave a habit half old, half new.
+    pub fn list_habits(&self) -> rusqlite::Result<Vec<Habit>> {
+        let conn = self.reader();
+        let tx = conn.unchecked_transaction()?;
+        let habits = load_habits(&tx)?;
+        tx.commit()?;
+        Ok(habits)
+    }
+
+    pub fn get_habit(&self, id: &str) -> rusqlite::Result<Option<Habit>> {
+        let reader = self.reader();
+        let tx = reader.unchecked_transaction()?;
+        let habit = load_habit(&tx, id)?;
+        tx.commit()?;
+        Ok(habit)
+    }
+
+    /// Replaces everything with the webview's snapshot in one transaction.
+    ///
+    /// Snapshots only carry each day's total, so individual check-ins are kept wherever
+    /// they still add up to it; a day whose total changed becomes a single check-in.
+    /// Categories the snapshot uses that don't exist yet are created at the end of the list.
+    /// Names and tags are normalized first, and an active habit given a name already in use
+    /// is numbered (`text.rs`).
+    pub fn replace_habits(&self, habits: &[Habit]) -> rusqlite::Result<()> {
+        self.replace_habits_checked(habits, &HashMap::new())
+            .map(|_| ())
+    }
+
+    /// [`replace_habits`](Self::replace_habits) if every habit in `expected` is still on
+    /// the revision given for it (0 for gone), checked in the same transaction. Otherwise
+    /// nothing is written, and the habits that changed are returned.
+    pub fn replace_habits_checked(
+        &self,
+        habits: &[Habit],
+        expected: &HashMap<String, u64>,
+    ) -> rusqlite::Result<Vec<String>> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        // Everything is deleted and written again below, so the triggers would bump every
+        // revision; only what actually differs afterwards is bumped.
+        tx.execute(
+            "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, '1')",
+            [REVISIONS_PAUSED_KEY],
+        )?;
+        let before: HashMap<String, Habit> = load_habits(&tx)?
+            .into_iter()
+            .map(|h| (h.id.clone(), h))
+            .collect();
+        let stale: Vec<String> = expected
+            .iter()
+            .filter(|(id, revision)| before.get(*id).map_or(0, |h| h.revision) != **revision)
+            .map(|(id, _)| id.clone())
+            .collect();
+        if !stale.is_empty() {
+            return Ok(stale);
+        }
+        let normalized = crate::text::normalize_habits(habits, &before);
+        let habits = normalized.as_deref().unwrap_or(habits);
+        let mut check_in_tags = load_check_in_tags(&tx)?;
+        // (value, logged_at, tags) per (habit, date)
+        type Kept = Vec<(f64, String, Vec<String>)>;
+        let mut check_ins: HashMap<(String, String), Kept> = HashMap::new();
+        {
+            let mut stmt = tx.prepare_cached(
+                "SELECT id, habit_id, date, value, logged_at FROM check_ins ORDER BY id",
+            )?;
+            let rows = stmt.query_map([], |r| {
+                Ok((
+                    r.get::<_, i64>(0)?,
+                    (r.get::<_, String>(1)?, r.get::<_, String>(2)?),
+                    (r.get::<_, f64>(3)?, r.get::<_, String>(4)?),
+                ))
+            })?;
+            for row in rows {
+                let (id, key, (value, logged_at)) = row?;
+                let tags = check_in_tags.remove(&id).unwrap_or_default();
+                check_ins
+                    .entry(key)
+                    .or_default()
+                    .push((value, logged_at, tags));
+            }
+        }
+
+        // Streak runs follow the days whose completion or total changed; habits whose rules
+        // changed, or that are gone, get theirs rebuilt.
+        let old_rules: HashMap<String, Habit> = {
+            let mut stmt = tx.prepare_cached(HABIT_SELECT)?;
+            let rows = stmt.query_map([], habit_from_row)?;
+            rows.map(|h| h.map(|h| (h.id.clone(), h)))
+                .collect::<rusqlite::Result<_>>()?
+        };
+        let mut old_done: HashMap<String, HashSet<String>> = HashMap::new();
+        {
+            let mut stmt = tx.prepare_cached("SELECT habit_id, date FROM completions")?;
+            let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get(1)?)))?;
+            for row in rows {
+                let (habit_id, date) = row?;
+                old_done.entry(habit_id).or_default().insert(date);
+            }
+        }
+        let mut old_totals: HashMap<String, HashMap<String, f64>> = HashMap::new();
+        for ((habit_id, date), kept) in &check_ins {
+            let total = kept.iter().map(|(v, ..)| v).sum::<f64>();
+            old_totals
+                .entry(habit_id.clone())
+                .or_default()
+                .insert(date.clone(), total);
+        }
+
+        // Unchanged notes keep their edit time.
+        let note_times: HashMap<(String, String, String), String> = {
+            let mut stmt =
+                tx.prepare_cached("SELECT habit_id, date, note, updated_at FROM completion_notes")?;
+            let rows =
+                stmt.query_map([], |r| Ok(((r.get(0)?, r.get(1)?, r.get(2)?), r.get(3)?)))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+
+        tx.execute("DELETE FROM completion_notes", [])?;
+        tx.execute("DELETE FROM lapses", [])?;
+        tx.execute("DELETE FROM check_ins", [])?;
+        tx.execute("DELETE FROM completions", [])?;
+        tx.execute("DELETE FROM habits", [])?;
+        {
+            let mut insert_habit = tx.prepare_cached(
+                "INSERT INTO habits (id, name, description, color, icon, category, frequency_type,
+                    frequency_goal, reminder_time, created_at, archived, position, target_amount,
+                    target_unit, kind, archived_at, window_start, window_end, pinned,
+                    times_per_day, emoji, cover, difficulty)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                    ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+            )?;
+            let mut insert_lapse = tx.prepare_cached(
+                "INSERT INTO lapses (habit_id, date, note, logged_at) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let mut insert_completion = tx.prepare_cached(
+                "INSERT OR IGNORE INTO completions (habit_id, date, backfilled) VALUES (?1, ?2, ?3)",
+            )?;
+            let mut insert_check_in = tx.prepare_cached(
+                "INSERT INTO check_ins (habit_id, date, value, logged_at) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let mut insert_habit_tag = tx.prepare_cached(
+                "INSERT OR IGNORE INTO habit_tags (habit_id, tag) VALUES (?1, ?2)",
+            )?;
+            let mut insert_check_in_tag = tx.prepare_cached(
+                "INSERT OR IGNORE INTO check_in_tags (check_in_id, tag) VALUES (?1, ?2)",
+            )?;
+            let mut insert_item = tx.prepare_cached(
+                "INSERT OR IGNORE INTO checklist_items (id, habit_id, name, position)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let mut insert_item_done = tx.prepare_cached(
+                "INSERT OR IGNORE INTO checklist_completions (item_id, date) VALUES (?1, ?2)",
+            )?;
+            let mut insert_weekday_reminder = tx.prepare_cached(
+                "INSERT OR IGNORE INTO weekday_reminders (habit_id, weekday, time)
+                 VALUES (?1, ?2, ?3)",
+            )?;
+            let mut insert_note = tx.prepare_cached(
+                "INSERT OR IGNORE INTO completion_notes (habit_id, date, note, updated_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let now = chrono::Utc::now().to_rfc3339();
+
+            for (position, h) in habits.iter().enumerate() {
+                insert_habit.execute(params![
+                    h.id,
+                    h.name,
+                    h.description,
+                    h.color,
+                    h.icon,
+                    h.category,
+                    h.frequency.kind,
+                    h.frequency.goal,
+                    h.reminder_time,
+                    h.created_at,
+                    h.archived,
+                    position as i64,
+                    h.target.as_ref().map(|t| t.amount),
+                    h.target.as_ref().map(|t| &t.unit),
+                    h.kind.as_str(),
+                    h.archived_at,
+                    h.window.as_ref().map(|w| &w.start),
+                    h.window.as_ref().map(|w| &w.end),
+                    h.pinned,
+                    h.times_per_day.max(1),
+                    h.emoji,
+                    h.cover,
+                    h.difficulty.as_str(),
+                ])?;
+                for lapse in &h.lapses {
+                    insert_lapse.execute(params![h.id, lapse.date, lapse.note, lapse.logged_at])?;
+                }
+                for tag in crate::tags::normalize(h.tags.iter().cloned()) {
+                    insert_habit_tag.execute(params![h.id, tag])?;
+                }
+                for (position, item) in h.checklist.iter().enumerate() {
+                    insert_item.execute(params![item.id, h.id, item.name, position as i64])?;
+                    for date in &item.done {
+                        insert_item_done.execute(params![item.id, date])?;
+                    }
+                }
+                for (date, done) in &h.logs {
+                    if *done {
+                        let backfilled = h.backfilled.contains(date);
+                        insert_completion.execute(params![h.id, date, backfilled])?;
+                    }
+                }
+                for (weekday, time) in &h.weekday_reminders {
+                    insert_weekday_reminder.execute(params![h.id, weekday, time])?;
+                }
+                for (date, note) in &h.notes {
+                    let updated_at = note_times
+                        .get(&(h.id.clone(), date.clone(), note.clone()))
+                        .unwrap_or(&now);
+                    insert_note.execute(params![h.id, date, note, updated_at])?;
+                }
+                for (date, total) in &h.values {
+                    let existing = check_ins
+                        .remove(&(h.id.clone(), date.clone()))
+                        .filter(|e| (e.iter().map(|(v, ..)| v).sum::<f64>() - total).abs() < 1e-9)
+                        .unwrap_or_else(|| vec![(*total, now.clone(), Vec::new())]);
+                    for (value, logged_at, tags) in existing {
+                        insert_check_in.execute(params![h.id, date, value, logged_at])?;
+                        let id = tx.last_insert_rowid();
+                        for tag in tags {
+                            insert_check_in_tag.execute(params![id, tag])?;
+                        }
+                    }
+                }
+            }
+            // After every habit exists, so prerequisites can point forward in the list.
+            let mut insert_dependency = tx.prepare_cached(
+                "INSERT OR IGNORE INTO habit_dependencies (habit_id, depends_on)
+                 SELECT ?1, id FROM habits WHERE id = ?2",
+            )?;
+            for h in habits {
+                for prerequisite in h.depends_on.iter().filter(|d| **d != h.id) {
+                    insert_dependency.execute(params![h.id, prerequisite])?;
+                }
+            }
+            insert_missing_categories(&tx, habits)?;
+
+            let empty_done = HashSet::new();
+            let empty_totals = HashMap::new();
+            for h in habits {
+                if old_rules.get(&h.id).map(streak_rules) != Some(streak_rules(h)) {
+                    self.days_changed(&tx, Some(&h.id))?;
+                    continue;
+                }
+                if h.kind == HabitKind::Avoid {
+                    self.stats.forget_habit(Some(&h.id));
+                }
+                let done = old_done.get(&h.id).unwrap_or(&empty_done);
+                let totals = old_totals.get(&h.id).unwrap_or(&empty_totals);
+                let logged = |d: &String| h.logs.get(d) == Some(&true);
+                let changed: BTreeSet<&String> = h
+                    .logs
+                    .keys()
+                    .filter(|d| logged(d) != done.contains(*d))
+                    .chain(done.iter().filter(|d| !logged(d)))
+                    .chain(h.values.iter().filter_map(|(d, v)| {
+                        totals
+                            .get(d)
+                            .map_or(true, |t| (t - v).abs() >= 1e-9)
+                            .then_some(d)
+                    }))
+                    .chain(totals.keys().filter(|d| !h.values.contains_key(*d)))
+                    .collect();
+                for date in changed {
+                    self.day_changed(&tx, &h.id, date)?;
+                }
+            }
+            let kept: HashSet<&str> = habits.iter().map(|h| h.id.as_str()).collect();
+            for id in old_rules.keys().filter(|id| !kept.contains(id.as_str())) {
+                self.days_changed(&tx, Some(id))?;
+            }
+        }
+
+        let after = load_habits(&tx)?;
+        {
+            let mut bump = tx.prepare_cached(BUMP_REVISION)?;
+            for h in &after {
+                let old = before.get(&h.id);
+                if old != Some(h) {
+                    bump.execute(params![h.id, ""])?;
+                }
+                let mut days = record_days(h);
+                days.extend(old.map(record_days).unwrap_or_default());
+                for date in days {
+                    if old.map(|o| day_record(o, date)) != Some(day_record(h, date)) {
+                        bump.execute(params![h.id, date])?;
+                    }
+                }
+            }
+        }
+        tx.execute("DELETE FROM meta WHERE key = ?1", [REVISIONS_PAUSED_KEY])?;
+        tx.commit()?;
+        Ok(Vec::new())
+    }
+
+    /// The revision of a habit (`date` empty) or one of its days; 0 if it never had one.
+    pub fn revision(&self, habit_id: &str, date: &str) -> rusqlite::Result<u64> {
+        let revision: Option<i64> = self
+            .reader()
+            .query_row(
+                "SELECT revision FROM revisions WHERE habit_id = ?1 AND date = ?2",
+                params![habit_id, date],
+                |r| r.get(0),
+            )
+            .optional()?;
+        Ok(revision.unwrap_or(0) as u64)
+    }
+
+    /// Returns whether the habit exists.
+    pub fn set_habit_window(
+        &self,
+        habit_id: &str,
+        window: Option<&TimeWindow>,
+    ) -> rusqlite::Result<bool> {
+        let changed = self.conn().execute(
+            "UPDATE habits SET window_start = ?2, window_end = ?3 WHERE id = ?1",
+            params![habit_id, window.map(|w| &w.start), window.map(|w| &w.end)],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Replaces a habit's per-weekday reminder times. Returns whether the habit exists.
+    pub fn set_weekday_reminders(
+        &self,
+        habit_id: &str,
+        times: &BTreeMap<u8, String>,
+    ) -> rusqlite::Result<bool> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let exists = tx
+            .query_row("SELECT 1 FROM habits WHERE id = ?1", [habit_id], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if !exists {
+            return Ok(false);
+        }
+        tx.execute(
+            "DELETE FROM weekday_reminders WHERE habit_id = ?1",
+            [habit_id],
+        )?;
+        {
+            let mut insert = tx.prepare_cached(
+                "INSERT INTO weekday_reminders (habit_id, weekday, time) VALUES (?1, ?2, ?3)",
+            )?;
+            for (weekday, time) in times {
+                insert.execute(params![habit_id, weekday, time])?;
+            }
+        }
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Returns whether the habit exists.
+    pub fn set_habit_appearance(
+        &self,
+        habit_id: &str,
+        color: &str,
+        icon: Option<&str>,
+        emoji: Option<&str>,
+    ) -> rusqlite::Result<bool> {
+        let changed = self.conn().execute(
+            "UPDATE habits SET color = ?2, icon = ?3, emoji = ?4 WHERE id = ?1",
+            params![habit_id, color, icon, emoji],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Returns whether the habit exists.
+    pub fn set_habit_cover(&self, habit_id: &str, cover: Option<&str>) -> rusqlite::Result<bool> {
+        let changed = self.conn().execute(
+            "UPDATE habits SET cover = ?2 WHERE id = ?1",
+            params![habit_id, cover],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Returns whether the habit exists.
+    pub fn set_times_per_day(&self, habit_id: &str, times: u32) -> rusqlite::Result<bool> {
+        let conn = self.conn();
+        let changed = conn.execute(
+            "UPDATE habits SET times_per_day = ?2 WHERE id = ?1",
+            params![habit_id, times],
+        )?;
+        self.days_changed(&conn, Some(habit_id))?;
+        Ok(changed > 0)
+    }
+
+    /// Returns whether the habit exists.
+    /// Returns false if the habit doesn't exist.
+    pub fn set_habit_difficulty(
+        &self,
+        habit_id: &str,
+        difficulty: Difficulty,
+    ) -> rusqlite::Result<bool> {
+        let changed = self.conn().execute(
+            "UPDATE habits SET difficulty = ?2 WHERE id = ?1",
+            params![habit_id, difficulty.as_str()],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn set_habit_pinned(&self, habit_id: &str, pinned: bool) -> rusqlite::Result<bool> {
+        let changed = self.conn().execute(
+            "UPDATE habits SET pinned = ?2 WHERE id = ?1",
+            params![habit_id, pinned],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Positions follow the order of `ids`; habits not listed keep their relative order
+    /// after them.
+    pub fn reorder_habits(&self, ids: &[String]) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let existing: Vec<String> = {
+            let mut stmt =
+                tx.prepare_cached("SELECT id FROM habits ORDER BY position, created_at")?;
+            let rows = stmt.query_map([], |r| r.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        {
+            let mut update = tx.prepare_cached("UPDATE habits SET position = ?2 WHERE id = ?1")?;
+            for (position, id) in crate::ordering::reordered(&existing, ids)
+                .into_iter()
+                .enumerate()
+            {
+                update.execute(params![id, position as i64])?;
+            }
+        }
+        tx.commit()
+    }
+}
+
+impl Storage {
+    /// Appends an item to a habit's checklist. Returns whether the habit exists.
+    pub fn insert_checklist_item(
+        &self,
+        habit_id: &str,
+        item: &ChecklistItem,
+    ) -> rusqlite::Result<bool> {
+        let conn = self.conn();
+        let exists = conn
+            .query_row("SELECT 1 FROM habits WHERE id = ?1", [habit_id], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if exists {
+            conn.execute(
+                "INSERT INTO checklist_items (id, habit_id, name, position)
+                 SELECT ?1, ?2, ?3, COALESCE(MAX(position) + 1, 0)
+                 FROM checklist_items WHERE habit_id = ?2",
+                params![item.id, habit_id, item.name],
+            )?;
+        }
+        Ok(exists)
+    }
+
+    /// Returns the item's habit id, or `None` if there's no such item.
+    pub fn rename_checklist_item(
+        &self,
+        item_id: &str,
+        name: &str,
+    ) -> rusqlite::Result<Option<String>> {
+        let conn = self.conn();
+        let habit_id = checklist_item_habit(&conn, item_id)?;
+        if habit_id.is_some() {
+            conn.execute(
+                "UPDATE checklist_items SET name = ?2 WHERE id = ?1",
+                params![item_id, name],
+            )?;
+        }
+        Ok(habit_id)
+    }
+
+    /// Returns the item's habit id, or `None` if there's no such item. Days already
+    /// completed stay completed.
+    pub fn delete_checklist_item(&self, item_id: &str) -> rusqlite::Result<Option<String>> {
+        let conn = self.conn();
+        let habit_id = checklist_item_habit(&conn, item_id)?;
+        conn.execute("DELETE FROM checklist_items WHERE id = ?1", [item_id])?;
+        Ok(habit_id)
+    }
+
+    /// Positions follow `item_ids`; items not listed keep their order after them.
+    pub fn reorder_checklist(&self, habit_id: &str, item_ids: &[String]) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let existing: Vec<String> = {
+            let mut stmt = tx.prepare_cached(
+                "SELECT id FROM checklist_items WHERE habit_id = ?1 ORDER BY position, rowid",
+            )?;
+            let rows = stmt.query_map([habit_id], |r| r.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        let listed: HashSet<&str> = item_ids.iter().map(String::as_str).collect();
+        let order = item_ids
+            .iter()
+            .filter(|id| existing.contains(id))
+            .chain(existing.iter().filter(|id| !listed.contains(id.as_str())));
+        {
+            let mut update =
+                tx.prepare_cached("UPDATE checklist_items SET position = ?2 WHERE id = ?1")?;
+            for (position, id) in order.enumerate() {
+                update.execute(params![id, position as i64])?;
+            }
+        }
+        tx.commit()
+    }
+}
+
+impl Storage {
+    /// Replaces a habit's prerequisites; unknown ids are dropped. Returns whether the
+    /// habit exists.
+    pub fn set_habit_dependencies(
+        &self,
+        habit_id: &str,
+        depends_on: &[String],
+    ) -> rusqlite::Result<bool> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let exists = tx
+            .query_row("SELECT 1 FROM habits WHERE id = ?1", [habit_id], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if exists {
+            tx.execute(
+                "DELETE FROM habit_dependencies WHERE habit_id = ?1",
+                [habit_id],
+            )?;
+            for prerequisite in depends_on.iter().filter(|d| *d != habit_id) {
+                tx.execute(
+                    "INSERT OR IGNORE INTO habit_dependencies (habit_id, depends_on)
+                     SELECT ?1, id FROM habits WHERE id = ?2",
+                    params![habit_id, prerequisite],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(exists)
+    }
+}
+
+impl Storage {
+    /// Global periods and those of `habit_id`, or every period without a filter, by start.
+    pub fn list_off_periods(&self, habit_id: Option<&str>) -> rusqlite::Result<Vec<OffPeriod>> {
+        let periods = load_off_periods(&self.reader())?;
+        Ok(match habit_id {
+            Some(id) => covering(&periods, id),
+            None => periods,
+        })
+    }
+
+    /// Off periods bridge streaks, so changing one has every habit's runs rebuilt (the same
+    /// goes for the two below).
+    pub fn insert_off_period(&self, period: &OffPeriod) -> rusqlite::Result<()> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO off_periods (id, habit_id, start_date, end_date, reason, paused)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                period.id,
+                period.habit_id,
+                period.start,
+                period.end,
+                period.reason,
+                period.paused
+            ],
+        )?;
+        self.days_changed(&conn, None)
+    }
+
+    /// Moves the last day of an off period. Returns whether it existed.
+    pub fn set_off_period_end(&self, id: &str, end: &str) -> rusqlite::Result<bool> {
+        let conn = self.conn();
+        let changed = conn.execute(
+            "UPDATE off_periods SET end_date = ?2 WHERE id = ?1",
+            params![id, end],
+        )?;
+        self.days_changed(&conn, None)?;
+        Ok(changed > 0)
+    }
+
+    /// Returns whether it existed.
+    pub fn delete_off_period(&self, id: &str) -> rusqlite::Result<bool> {
+        let conn = self.conn();
+        let changed = conn.execute("DELETE FROM off_periods WHERE id = ?1", [id])?;
+        self.days_changed(&conn, None)?;
+        Ok(changed > 0)
+    }
+}
+
+impl Storage {
+    /// Optionally for one habit, by threshold.
+    pub fn list_milestones(&self, habit_id: Option<&str>) -> rusqlite::Result<Vec<Milestone>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, habit_id, kind, threshold, reached_at, created_at FROM milestones
+             WHERE ?1 IS NULL OR habit_id = ?1 ORDER BY habit_id, kind, threshold",
+        )?;
+        let rows = stmt.query_map([habit_id], |r| {
+            Ok((
+                r.get::<_, String>(2)?,
+                Milestone {
+                    id: r.get(0)?,
+                    habit_id: r.get(1)?,
+                    kind: MilestoneKind::Completions,
+                    threshold: r.get(3)?,
+                    reached_at: r.get(4)?,
+                    created_at: r.get(5)?,
+                },
+            ))
+        })?;
+        let mut milestones = Vec::new();
+        for row in rows {
+            let (kind, mut milestone) = row?;
+            // Rows of a kind this build doesn't know are skipped rather than misread.
+            if let Some(kind) = MilestoneKind::parse(&kind) {
+                milestone.kind = kind;
+                milestones.push(milestone);
+            }
+        }
+        Ok(milestones)
+    }
+
+    pub fn insert_milestone(&self, milestone: &Milestone) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT INTO milestones (id, habit_id, kind, threshold, reached_at, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                milestone.id,
+                milestone.habit_id,
+                milestone.kind.as_str(),
+                milestone.threshold,
+                milestone.reached_at,
+                milestone.created_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Returns false if it was already reached (or doesn't exist).
+    pub fn mark_milestone_reached(&self, id: &str, at: &str) -> rusqlite::Result<bool> {
+        let changed = self.conn().execute(
+            "UPDATE milestones SET reached_at = ?2 WHERE id = ?1 AND reached_at IS NULL",
+            params![id, at],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Returns whether it existed.
+    pub fn delete_milestone(&self, id: &str) -> rusqlite::Result<bool> {
+        let changed = self
+            .conn()
+            .execute("DELETE FROM milestones WHERE id = ?1", [id])?;
+        Ok(changed > 0)
+    }
+
+    /// Achievement id => when it was unlocked.
+    pub fn list_unlocked_achievements(&self) -> rusqlite::Result<HashMap<String, String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached("SELECT id, unlocked_at FROM achievements")?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Returns false if it was already unlocked.
+    pub fn unlock_achievement(&self, id: &str, at: &str) -> rusqlite::Result<bool> {
+        let changed = self.conn().execute(
+            "INSERT OR IGNORE INTO achievements (id, unlocked_at) VALUES (?1, ?2)",
+            params![id, at],
+        )?;
+        Ok(changed > 0)
+    }
+
+    pub fn get_challenge(&self, date: &str) -> rusqlite::Result<Option<DailyChallenge>> {
+        let conn = self.reader();
+        let row = conn
+            .query_row(
+                "SELECT date, kind, title, habit_id, category, deadline, xp, status, resolved_at
+                 FROM daily_challenges WHERE date = ?1",
+                [date],
+                |r| {
+                    Ok((
+                        r.get::<_, String>(1)?,
+                        DailyChallenge {
+                            date: r.get(0)?,
+                            kind: ChallengeKind::AllDone,
+                            title: r.get(2)?,
+                            habit_id: r.get(3)?,
+                            category: r.get(4)?,
+                            deadline: r.get(5)?,
+                            xp: r.get(6)?,
+                            status: ChallengeStatus::parse(&r.get::<_, String>(7)?),
+                            resolved_at: r.get(8)?,
+                        },
+                    ))
+                },
+            )
+            .optional()?;
+        Ok(row.and_then(|(kind, mut challenge)| {
+            challenge.kind = ChallengeKind::parse(&kind)?;
+            Some(challenge)
+        }))
+    }
+
+    /// Keeps an existing challenge for the same day.
+    pub fn insert_challenge(&self, challenge: &DailyChallenge) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT OR IGNORE INTO daily_challenges
+                (date, kind, title, habit_id, category, deadline, xp, status, resolved_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                challenge.date,
+                challenge.kind.as_str(),
+                challenge.title,
+                challenge.habit_id,
+                challenge.category,
+                challenge.deadline,
+                challenge.xp,
+                challenge.status.as_str(),
+                challenge.resolved_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Resolves an open challenge. Returns false if it wasn't open (or doesn't exist).
+    pub fn resolve_challenge(
+        &self,
+        date: &str,
+        status: ChallengeStatus,
+        at: &str,
+    ) -> rusqlite::Result<bool> {
+        let changed = self.conn().execute(
+            "UPDATE daily_challenges SET status = ?2, resolved_at = ?3
+             WHERE date = ?1 AND status = 'open'",
+            params![date, status.as_str(), at],
+        )?;
+        Ok(changed > 0)
+    }
+
+    /// Still-open challenges from before `date`.
+    pub fn open_challenges_before(&self, date: &str) -> rusqlite::Result<Vec<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT date FROM daily_challenges WHERE status = 'open' AND date < ?1 ORDER BY date",
+        )?;
+        let rows = stmt.query_map([date], |r| r.get(0))?;
+        rows.collect()
+    }
+
+    /// XP from every completed challenge.
+    pub fn challenge_xp(&self) -> rusqlite::Result<u64> {
+        let xp: i64 = self.reader().query_row(
+            "SELECT COALESCE(SUM(xp), 0) FROM daily_challenges WHERE status = 'completed'",
+            [],
+            |r| r.get(0),
+        )?;
+        Ok(xp.max(0) as u64)
+    }
+
+    pub fn list_rewards(&self) -> rusqlite::Result<Vec<Reward>> {
+        let conn = self.reader();
+        let mut stmt = conn
+            .prepare_cached("SELECT id, name, cost, created_at FROM rewards ORDER BY cost, name")?;
+        let rows = stmt.query_map([], |r| {
+            Ok(Reward {
+                id: r.get(0)?,
+                name: r.get(1)?,
+                cost: r.get(2)?,
+                created_at: r.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn insert_reward(&self, reward: &Reward) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT INTO rewards (id, name, cost, created_at) VALUES (?1, ?2, ?3, ?4)",
+            params![reward.id, reward.name, reward.cost, reward.created_at],
+        )?;
+        Ok(())
+    }
+
+    /// Returns whether it existed. Past redemptions of it are kept.
+    pub fn delete_reward(&self, id: &str) -> rusqlite::Result<bool> {
+        let changed = self
+            .conn()
+            .execute("DELETE FROM rewards WHERE id = ?1", [id])?;
+        Ok(changed > 0)
+    }
+
+    /// Newest first.
+    pub fn list_redemptions(&self, limit: u32) -> rusqlite::Result<Vec<Redemption>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, reward_id, name, cost, redeemed_at FROM redemptions
+             ORDER BY redeemed_at DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit], |r| {
+            Ok(Redemption {
+                id: r.get(0)?,
+                reward_id: r.get(1)?,
+                name: r.get(2)?,
+                cost: r.get(3)?,
+                redeemed_at: r.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Points spent on redemptions so far.
+    pub fn points_spent(&self) -> rusqlite::Result<u64> {
+        let spent: i64 =
+            self.reader()
+                .query_row("SELECT COALESCE(SUM(cost), 0) FROM redemptions", [], |r| {
+                    r.get(0)
+                })?;
+        Ok(spent.max(0) as u64)
+    }
+
+    /// Records `redemption` if `earned` points still cover it after what's been spent,
+    /// checked and written in one transaction. Returns false if they don't.
+    pub fn redeem(&self, redemption: &Redemption, earned: u64) -> rusqlite::Result<bool> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let spent: i64 =
+            tx.query_row("SELECT COALESCE(SUM(cost), 0) FROM redemptions", [], |r| {
+                r.get(0)
+            })?;
+        if earned < spent.max(0) as u64 + u64::from(redemption.cost) {
+            return Ok(false);
+        }
+        tx.execute(
+            "INSERT INTO redemptions (id, reward_id, name, cost, redeemed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                redemption.id,
+                redemption.reward_id,
+                redemption.name,
+                redemption.cost,
+                redemption.redeemed_at
+            ],
+        )?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    pub fn list_goals(&self) -> rusqlite::Result<Vec<Goal>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, title, habit_id, period, start, end, target, created_at FROM goals
+             ORDER BY end, created_at",
+        )?;
+        let rows = stmt.query_map([], |r| {
+            Ok((
+                r.get::<_, String>(3)?,
+                Goal {
+                    id: r.get(0)?,
+                    title: r.get(1)?,
+                    habit_id: r.get(2)?,
+                    period: GoalPeriod::Month,
+                    start: r.get(4)?,
+                    end: r.get(5)?,
+                    target: r.get(6)?,
+                    created_at: r.get(7)?,
+                },
+            ))
+        })?;
+        let mut goals = Vec::new();
+        for row in rows {
+            let (period, mut goal) = row?;
+            if let Some(period) = GoalPeriod::parse(&period) {
+                goal.period = period;
+                goals.push(goal);
+            }
+        }
+        Ok(goals)
+    }
+
+    pub fn insert_goal(&self, goal: &Goal) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT INTO goals (id, title, habit_id, period, start, end, target, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                goal.id,
+                goal.title,
+                goal.habit_id,
+                goal.period.as_str(),
+                goal.start,
+                goal.end,
+                goal.target,
+                goal.created_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Returns whether it existed.
+    pub fn delete_goal(&self, id: &str) -> rusqlite::Result<bool> {
+        let changed = self
+            .conn()
+            .execute("DELETE FROM goals WHERE id = ?1", [id])?;
+        Ok(changed > 0)
+    }
+}
+
+fn load_off_periods(conn: &Connection) -> rusqlite::Result<Vec<OffPeriod>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, habit_id, start_date, end_date, reason, paused FROM off_periods
+         ORDER BY start_date, end_date",
+    )?;
+    let rows = stmt.query_map([], |r| {
+        Ok(OffPeriod {
+            id: r.get(0)?,
+            habit_id: r.get(1)?,
+            start: r.get(2)?,
+            end: r.get(3)?,
+            reason: r.get(4)?,
+            paused: r.get(5)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// The day after the pause that covers today or starts later.
+fn paused_until(habit: &Habit) -> Option<String> {
+    let pause = habit.pause_from(&crate::stats::date_key(crate::stats::today()))?;
+    let end = crate::stats::parse_date(&pause.end)?;
+    end.succ_opt().map(crate::stats::date_key)
+}
+
+fn covering(periods: &[OffPeriod], habit_id: &str) -> Vec<OffPeriod> {
+    periods
+        .iter()
+        .filter(|p| p.habit_id.as_deref().map_or(true, |id| id == habit_id))
+        .cloned()
+        .collect()
+}
+
+/// A streak freeze spent on a missed day.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FreezeUse {
+    pub habit_id: String,
+    pub date: String,
+    /// RFC 3339.
+    pub used_at: String,
+}
+
+impl Storage {
+    /// Newest first.
+    pub fn list_streak_freezes(&self) -> rusqlite::Result<Vec<FreezeUse>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT habit_id, date, used_at FROM streak_freezes ORDER BY date DESC, habit_id",
+        )?;
+        let rows = stmt.query_map([], |r| {
+            Ok(FreezeUse {
+                habit_id: r.get(0)?,
+                date: r.get(1)?,
+                used_at: r.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn count_streak_freezes(&self) -> rusqlite::Result<u32> {
+        self.reader()
+            .query_row("SELECT COUNT(*) FROM streak_freezes", [], |r| r.get(0))
+    }
+
+    /// Returns false if that day was already frozen.
+    pub fn insert_streak_freeze(&self, habit_id: &str, date: &str) -> rusqlite::Result<bool> {
+        let conn = self.conn();
+        let changed = conn.execute(
+            "INSERT OR IGNORE INTO streak_freezes (habit_id, date, used_at) VALUES (?1, ?2, ?3)",
+            params![habit_id, date, chrono::Utc::now().to_rfc3339()],
+        )?;
+        // A frozen day can join two runs.
+        self.days_changed(&conn, Some(habit_id))?;
+        Ok(changed > 0)
+    }
+}
+
+/// How a day felt, 1 (awful) to 5 (great).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MoodEntry {
+    pub date: String,
+    pub score: u8,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    pub logged_at: String,
+}
+
+impl Storage {
+    /// Replaces the entry for `entry.date`.
+    pub fn set_mood(&self, entry: &MoodEntry) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM moods WHERE date = ?1", [&entry.date])?;
+        tx.execute(
+            "INSERT INTO moods (date, score, logged_at) VALUES (?1, ?2, ?3)",
+            params![entry.date, entry.score, entry.logged_at],
+        )?;
+        for tag in &entry.tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO mood_tags (date, tag) VALUES (?1, ?2)",
+                params![entry.date, tag],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Returns whether there was an entry.
+    pub fn delete_mood(&self, date: &str) -> rusqlite::Result<bool> {
+        let changed = self
+            .conn()
+            .execute("DELETE FROM moods WHERE date = ?1", [date])?;
+        Ok(changed > 0)
+    }
+
+    /// Entries in `from..=to` (either end open), oldest first.
+    pub fn list_moods(
+        &self,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> rusqlite::Result<Vec<MoodEntry>> {
+        let conn = self.reader();
+        let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+        {
+            let mut stmt = conn.prepare_cached("SELECT date, tag FROM mood_tags ORDER BY tag")?;
+            let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get(1)?)))?;
+            for row in rows {
+                let (date, tag) = row?;
+                tags.entry(date).or_default().push(tag);
+            }
+        }
+        let mut stmt = conn.prepare_cached(
+            "SELECT date, score, logged_at FROM moods
+             WHERE (?1 IS NULL OR date >= ?1) AND (?2 IS NULL OR date <= ?2)
+             ORDER BY date",
+        )?;
+        let rows = stmt.query_map(params![from, to], |r| {
+            let date: String = r.get(0)?;
+            Ok(MoodEntry {
+                tags: tags.remove(&date).unwrap_or_default(),
+                date,
+                score: r.get(1)?,
+                logged_at: r.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// A journal note matching a search.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoteMatch {
+    pub habit_id: String,
+    pub habit_name: String,
+    pub date: String,
+    pub note: String,
+    /// The matching part of the note with hits wrapped in `[` `]`.
+    pub snippet: String,
+}
+
+impl Storage {
+    /// Full-text search over notes, best matches first. `query` is FTS5 syntax.
+    pub fn search_notes(&self, query: &str, limit: u32) -> rusqlite::Result<Vec<NoteMatch>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT n.habit_id, h.name, n.date, n.note,
+                snippet(completion_notes_fts, 0, '[', ']', '…', 12)
+             FROM completion_notes_fts
+             JOIN completion_notes n ON n.rowid = completion_notes_fts.rowid
+             JOIN habits h ON h.id = n.habit_id
+             WHERE completion_notes_fts MATCH ?1
+             ORDER BY rank
+             LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![query, limit], |r| {
+            Ok(NoteMatch {
+                habit_id: r.get(0)?,
+                habit_name: r.get(1)?,
+                date: r.get(2)?,
+                note: r.get(3)?,
+                snippet: r.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// Notes grouped by habit.
+fn load_notes(
+    conn: &Connection,
+    habit_id: Option<&str>,
+) -> rusqlite::Result<HashMap<String, BTreeMap<String, String>>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT habit_id, date, note FROM completion_notes WHERE ?1 IS NULL OR habit_id = ?1",
+    )?;
+    let rows = stmt.query_map([habit_id], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, String>(2)?,
+        ))
+    })?;
+    let mut notes: HashMap<String, BTreeMap<String, String>> = HashMap::new();
+    for row in rows {
+        let (habit_id, date, note) = row?;
+        notes.entry(habit_id).or_default().insert(date, note);
+    }
+    Ok(notes)
+}
+
+/// Per-weekday reminder times grouped by habit.
+fn load_weekday_reminders(
+    conn: &Connection,
+    habit_id: Option<&str>,
+) -> rusqlite::Result<HashMap<String, BTreeMap<u8, String>>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT habit_id, weekday, time FROM weekday_reminders WHERE ?1 IS NULL OR habit_id = ?1",
+    )?;
+    let rows = stmt.query_map([habit_id], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, u8>(1)?,
+            r.get::<_, String>(2)?,
+        ))
+    })?;
+    let mut reminders: HashMap<String, BTreeMap<u8, String>> = HashMap::new();
+    for row in rows {
+        let (habit_id, weekday, time) = row?;
+        reminders.entry(habit_id).or_default().insert(weekday, time);
+    }
+    Ok(reminders)
+}
+
+/// Frozen days grouped by habit.
+fn load_frozen(
+    conn: &Connection,
+    habit_id: Option<&str>,
+) -> rusqlite::Result<HashMap<String, BTreeSet<String>>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT habit_id, date FROM streak_freezes WHERE ?1 IS NULL OR habit_id = ?1",
+    )?;
+    let rows = stmt.query_map([habit_id], |r| {
+        Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))
+    })?;
+    let mut frozen: HashMap<String, BTreeSet<String>> = HashMap::new();
+    for row in rows {
+        let (habit_id, date) = row?;
+        frozen.entry(habit_id).or_default().insert(date);
+    }
+    Ok(frozen)
+}
+
+/// Prerequisite ids grouped by habit.
+fn load_dependencies(
+    conn: &Connection,
+    habit_id: Option<&str>,
+) -> rusqlite::Result<HashMap<String, Vec<String>>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT habit_id, depends_on FROM habit_dependencies
+         WHERE ?1 IS NULL OR habit_id = ?1 ORDER BY rowid",
+    )?;
+    let rows = stmt.query_map([habit_id], |r| {
+        Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))
+    })?;
+    let mut dependencies: HashMap<String, Vec<String>> = HashMap::new();
+    for row in rows {
+        let (habit_id, depends_on) = row?;
+        dependencies.entry(habit_id).or_default().push(depends_on);
+    }
+    Ok(dependencies)
+}
+
+impl Storage {
+    /// The habit a checklist item belongs to.
+    pub fn checklist_item_habit(&self, item_id: &str) -> rusqlite::Result<Option<String>> {
+        checklist_item_habit(&self.reader(), item_id)
+    }
+}
+
+fn checklist_item_habit(conn: &Connection, item_id: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT habit_id FROM checklist_items WHERE id = ?1",
+        [item_id],
+        |r| r.get(0),
+    )
+    .optional()
+}
+
+/// Checklist items grouped by habit, in order, with the days each was done.
+fn load_checklists(
+    conn: &Connection,
+    habit_id: Option<&str>,
+) -> rusqlite::Result<HashMap<String, Vec<ChecklistItem>>> {
+    let mut done: HashMap<String, BTreeSet<String>> = HashMap::new();
+    {
+        let mut stmt = conn.prepare_cached(
+            "SELECT c.item_id, c.date FROM checklist_completions c
+             JOIN checklist_items i ON i.id = c.item_id
+             WHERE ?1 IS NULL OR i.habit_id = ?1",
+        )?;
+        let rows = stmt.query_map([habit_id], |r| {
+            Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (item_id, date) = row?;
+            done.entry(item_id).or_default().insert(date);
+        }
+    }
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT habit_id, id, name FROM checklist_items
+         WHERE ?1 IS NULL OR habit_id = ?1 ORDER BY position, rowid",
+    )?;
+    let rows = stmt.query_map([habit_id], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            r.get::<_, String>(1)?,
+            r.get::<_, String>(2)?,
+        ))
+    })?;
+    let mut checklists: HashMap<String, Vec<ChecklistItem>> = HashMap::new();
+    for row in rows {
+        let (habit_id, id, name) = row?;
+        let item = ChecklistItem {
+            done: done.remove(&id).unwrap_or_default(),
+            id,
+            name,
+        };
+        checklists.entry(habit_id).or_default().push(item);
+    }
+    Ok(checklists)
+}
+
+/// One attempt to deliver a webhook, as recorded in the delivery log.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: String,
+    pub event_type: String,
+    pub url: String,
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub success: bool,
+    pub attempted_at: String,
+}
+
+impl Storage {
+    pub fn get_meta(&self, key: &str) -> rusqlite::Result<Option<String>> {
+        self.reader()
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |r| r.get(0))
+            .optional()
+    }
+
+    pub fn set_meta(&self, key: &str, value: &str) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT INTO meta (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_meta(&self, key: &str) -> rusqlite::Result<()> {
+        self.conn()
+            .execute("DELETE FROM meta WHERE key = ?1", [key])?;
+        Ok(())
+    }
+
+    /// Appends to the delivery log, keeping only the most recent 500 entries.
+    pub fn record_webhook_delivery(&self, d: &WebhookDelivery) -> rusqlite::Result<()> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO webhook_deliveries
+                (webhook_id, event_type, url, attempt, status_code, error, success, attempted_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                d.webhook_id,
+                d.event_type,
+                d.url,
+                d.attempt,
+                d.status_code,
+                d.error,
+                d.success,
+                d.attempted_at
+            ],
+        )?;
+        conn.execute(
+            "DELETE FROM webhook_deliveries WHERE id <= (SELECT MAX(id) - 500 FROM webhook_deliveries)",
+            [],
+        )?;
+        Ok(())
+    }
+
+    /// Newest first.
+    pub fn list_webhook_deliveries(&self, limit: u32) -> rusqlite::Result<Vec<WebhookDelivery>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, webhook_id, event_type, url, attempt, status_code, error, success, attempted_at
+             FROM webhook_deliveries ORDER BY id DESC LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit], |r| {
+            Ok(WebhookDelivery {
+                id: r.get(0)?,
+                webhook_id: r.get(1)?,
+                event_type: r.get(2)?,
+                url: r.get(3)?,
+                attempt: r.get(4)?,
+                status_code: r.get(5)?,
+                error: r.get(6)?,
+                success: r.get(7)?,
+                attempted_at: r.get(8)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// One day of a habit's history: marked done, with progress logged, or both.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletionDay {
+    pub date: String,
+    pub done: bool,
+    pub backfilled: bool,
+    /// The day's check-ins summed up, for quantified and repeated habits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+}
+
+impl Storage {
+    /// Up to `limit` days within `from..=to` and before `before`, newest first; `None` if
+    /// the habit doesn't exist. Walks the `(habit_id, date)` keys, so paging with
+    /// `before` costs the same however far back it goes.
+    pub fn list_completion_days(
+        &self,
+        habit_id: &str,
+        from: Option<&str>,
+        to: Option<&str>,
+        before: Option<&str>,
+        limit: u32,
+    ) -> rusqlite::Result<Option<Vec<CompletionDay>>> {
+        let conn = self.reader();
+        let exists = conn
+            .query_row("SELECT 1 FROM habits WHERE id = ?1", [habit_id], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if !exists {
+            return Ok(None);
+        }
+        let mut stmt = conn.prepare_cached(
+            "SELECT date, MAX(done), MAX(backfilled), SUM(value) FROM (
+                 SELECT date, 1 AS done, backfilled, NULL AS value FROM completions
+                 WHERE habit_id = ?1
+                 UNION ALL
+                 SELECT date, 0, 0, value FROM check_ins WHERE habit_id = ?1
+             )
+             WHERE (?2 IS NULL OR date >= ?2) AND (?3 IS NULL OR date <= ?3)
+                 AND (?4 IS NULL OR date < ?4)
+             GROUP BY date ORDER BY date DESC LIMIT ?5",
+        )?;
+        let rows = stmt.query_map(params![habit_id, from, to, before, limit], |r| {
+            Ok(CompletionDay {
+                date: r.get(0)?,
+                done: r.get(1)?,
+                backfilled: r.get(2)?,
+                value: r.get(3)?,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<_>>().map(Some)
+    }
+}
+
+/// A photo on a check-in. `file` is its name in the `attachments` folder.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: String,
+    pub habit_id: String,
+    pub date: String,
+    pub file: String,
+    pub mime: String,
+    pub size: u64,
+    pub added_at: String,
+}
+
+impl Storage {
+    pub fn add_attachment(&self, attachment: &Attachment) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT INTO attachments (id, habit_id, date, file, mime, size, added_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                attachment.id,
+                attachment.habit_id,
+                attachment.date,
+                attachment.file,
+                attachment.mime,
+                attachment.size as i64,
+                attachment.added_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// A habit's attachments, on one day or all of them, oldest first.
+    pub fn list_attachments(
+        &self,
+        habit_id: &str,
+        date: Option<&str>,
+    ) -> rusqlite::Result<Vec<Attachment>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, habit_id, date, file, mime, size, added_at FROM attachments
+             WHERE habit_id = ?1 AND (?2 IS NULL OR date = ?2) ORDER BY date, added_at",
+        )?;
+        let rows = stmt.query_map(params![habit_id, date], attachment_from_row)?;
+        rows.collect()
+    }
+
+    pub fn get_attachment(&self, id: &str) -> rusqlite::Result<Option<Attachment>> {
+        self.reader()
+            .query_row(
+                "SELECT id, habit_id, date, file, mime, size, added_at FROM attachments
+                 WHERE id = ?1",
+                [id],
+                attachment_from_row,
+            )
+            .optional()
+    }
+
+    /// Returns whether it existed.
+    pub fn delete_attachment(&self, id: &str) -> rusqlite::Result<bool> {
+        Ok(self
+            .conn()
+            .execute("DELETE FROM attachments WHERE id = ?1", [id])?
+            > 0)
+    }
+
+    /// Attachments of habits that no longer exist.
+    pub fn orphaned_attachments(&self) -> rusqlite::Result<Vec<Attachment>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, habit_id, date, file, mime, size, added_at FROM attachments
+             WHERE habit_id NOT IN (SELECT id FROM habits)",
+        )?;
+        let rows = stmt.query_map([], attachment_from_row)?;
+        rows.collect()
+    }
+
+    /// Every attachment's `file`.
+    pub fn attachment_files(&self) -> rusqlite::Result<HashSet<String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached("SELECT file FROM attachments")?;
+        let rows = stmt.query_map([], |r| r.get(0))?;
+        rows.collect()
+    }
+}
+
+fn attachment_from_row(r: &rusqlite::Row<'_>) -> rusqlite::Result<Attachment> {
+    Ok(Attachment {
+        id: r.get(0)?,
+        habit_id: r.get(1)?,
+        date: r.get(2)?,
+        file: r.get(3)?,
+        mime: r.get(4)?,
+        size: r.get::<_, i64>(5)? as u64,
+        added_at: r.get(6)?,
+    })
+}
+
+/// An NFC tag that checks a habit off when it's tapped.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NfcTag {
+    /// The tag's hardware id, as uppercase hex.
+    pub tag_id: String,
+    pub habit_id: String,
+    /// "Gym bag"; also how an iOS shortcut names the tag.
+    pub label: Option<String>,
+    pub bound_at: String,
+    pub last_scanned_at: Option<String>,
+}
+
+impl Storage {
+    /// Binds the tag, replacing what it was bound to before.
+    pub fn bind_nfc_tag(&self, tag: &NfcTag) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT INTO nfc_tags (tag_id, habit_id, label, bound_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(tag_id) DO UPDATE SET habit_id = excluded.habit_id,
+                label = excluded.label, bound_at = excluded.bound_at, last_scanned_at = NULL",
+            params![tag.tag_id, tag.habit_id, tag.label, tag.bound_at],
+        )?;
+        Ok(())
+    }
+
+    pub fn list_nfc_tags(&self) -> rusqlite::Result<Vec<NfcTag>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT tag_id, habit_id, label, bound_at, last_scanned_at FROM nfc_tags
+             ORDER BY bound_at",
+        )?;
+        let rows = stmt.query_map([], nfc_tag_from_row)?;
+        rows.collect()
+    }
+
+    /// By tag id, else by label, ignoring case.
+    pub fn find_nfc_tag(&self, reference: &str) -> rusqlite::Result<Option<NfcTag>> {
+        self.reader()
+            .query_row(
+                "SELECT tag_id, habit_id, label, bound_at, last_scanned_at FROM nfc_tags
+                 WHERE tag_id = ?1 OR label = ?1 COLLATE NOCASE
+                 ORDER BY tag_id = ?1 DESC LIMIT 1",
+                [reference],
+                nfc_tag_from_row,
+            )
+            .optional()
+    }
+
+    pub fn touch_nfc_tag(&self, tag_id: &str, at: &str) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "UPDATE nfc_tags SET last_scanned_at = ?2 WHERE tag_id = ?1",
+            params![tag_id, at],
+        )?;
+        Ok(())
+    }
+
+    /// Returns whether it was bound.
+    pub fn delete_nfc_tag(&self, tag_id: &str) -> rusqlite::Result<bool> {
+        Ok(self
+            .conn()
+            .execute("DELETE FROM nfc_tags WHERE tag_id = ?1", [tag_id])?
+            > 0)
+    }
+}
+
+fn nfc_tag_from_row(r: &rusqlite::Row<'_>) -> rusqlite::Result<NfcTag> {
+    Ok(NfcTag {
+        tag_id: r.get(0)?,
+        habit_id: r.get(1)?,
+        label: r.get(2)?,
+        bound_at: r.get(3)?,
+        last_scanned_at: r.get(4)?,
+    })
+}
+
+/// Time in one app on one day.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScreenTimeEntry {
+    pub date: String,
+    pub category: String,
+    pub app: String,
+    pub seconds: u64,
+}
+
+impl Storage {
+    pub fn add_screen_time(
+        &self,
+        date: &str,
+        category: &str,
+        app: &str,
+        seconds: u64,
+    ) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT INTO screen_time (date, category, app, seconds) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT DO UPDATE SET seconds = seconds + excluded.seconds",
+            params![date, category, app, seconds as i64],
+        )?;
+        Ok(())
+    }
+
+    /// The day's entries, most used first.
+    pub fn screen_time(&self, date: &str) -> rusqlite::Result<Vec<ScreenTimeEntry>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT date, category, app, seconds FROM screen_time WHERE date = ?1
+             ORDER BY seconds DESC, app",
+        )?;
+        let rows = stmt.query_map([date], |r| {
+            Ok(ScreenTimeEntry {
+                date: r.get(0)?,
+                category: r.get(1)?,
+                app: r.get(2)?,
+                seconds: r.get::<_, i64>(3)? as u64,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// An operation for the sync queue. A newer one with the same `key` replaces one that's
+/// still waiting, in its place.
+#[derive(Debug, Clone)]
+pub struct QueuedOp {
+    pub key: String,
+    pub id: String,
+    /// JSON.
+    pub op: String,
+    /// What's stored in `sync_state` for `key`; `None` removes it.
+    pub fingerprint: Option<String>,
+}
+
+/// A waiting operation, in queue order.
+#[derive(Debug, Clone)]
+pub struct PendingOp {
+    pub seq: i64,
+    pub id: String,
+    pub op: String,
+    pub recorded_at: String,
+}
+
+impl Storage {
+    pub fn sync_fingerprints(&self) -> rusqlite::Result<HashMap<String, String>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached("SELECT key, fingerprint FROM sync_state")?;
+        let rows = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?;
+        rows.collect()
+    }
+
+    /// Queues `ops` in one transaction. Waiting ops and fingerprints whose key starts with
+    /// one of `purged` go first, for a deleted habit's days.
+    pub fn queue_sync_ops(&self, ops: &[QueuedOp], purged: &[String]) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        for prefix in purged {
+            let pattern = format!("{}%", prefix.replace('%', "\\%").replace('_', "\\_"));
+            tx.execute(
+                "DELETE FROM sync_ops WHERE key LIKE ?1 ESCAPE '\\'",
+                [&pattern],
+            )?;
+            tx.execute(
+                "DELETE FROM sync_state WHERE key LIKE ?1 ESCAPE '\\'",
+                [&pattern],
+            )?;
+        }
+        {
+            let mut upsert = tx.prepare_cached(
+                "INSERT INTO sync_ops (id, key, op, recorded_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(key) DO UPDATE SET id = excluded.id, op = excluded.op,
+                    recorded_at = excluded.recorded_at",
+            )?;
+            let mut set_state = tx.prepare_cached(
+                "INSERT INTO sync_state (key, fingerprint) VALUES (?1, ?2)
+                 ON CONFLICT(key) DO UPDATE SET fingerprint = excluded.fingerprint",
+            )?;
+            let mut clear_state = tx.prepare_cached("DELETE FROM sync_state WHERE key = ?1")?;
+            let now = chrono::Utc::now().to_rfc3339();
+            for op in ops {
+                upsert.execute(params![op.id, op.key, op.op, now])?;
+                match &op.fingerprint {
+                    Some(print) => set_state.execute(params![op.key, print])?,
+                    None => clear_state.execute([&op.key])?,
+                };
+            }
+        }
+        tx.commit()
+    }
+
+    /// The oldest `limit` waiting ops.
+    pub fn pending_sync_ops(&self, limit: u32) -> rusqlite::Result<Vec<PendingOp>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT seq, id, op, recorded_at FROM sync_ops ORDER BY seq LIMIT ?1",
+        )?;
+        let rows = stmt.query_map([limit], |r| {
+            Ok(PendingOp {
+                seq: r.get(0)?,
+                id: r.get(1)?,
+                op: r.get(2)?,
+                recorded_at: r.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn count_sync_ops(&self) -> rusqlite::Result<usize> {
+        self.reader()
+            .query_row("SELECT COUNT(*) FROM sync_ops", [], |r| r.get(0))
+    }
+
+    /// Drops the ops the server has. An op replaced since it was sent has a new id, so it
+    /// stays.
+    pub fn ack_sync_ops(&self, ids: &[String]) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        {
+            let mut delete = tx.prepare_cached("DELETE FROM sync_ops WHERE id = ?1")?;
+            for id in ids {
+                delete.execute([id])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Forgets the queue and what was queued, so the next sync starts from scratch.
+    pub fn reset_sync(&self) -> rusqlite::Result<()> {
+        self.conn()
+            .execute_batch("DELETE FROM sync_ops; DELETE FROM sync_state;")
+    }
+}
+
+/// Consecutive done days of a daily habit, bridged by excused and frozen ones (which don't
+/// count towards `days`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreakRun {
+    pub start: String,
+    pub end: String,
+    pub days: u32,
+}
+
+fn streak_run_from_row(r: &rusqlite::Row<'_>) -> rusqlite::Result<StreakRun> {
+    Ok(StreakRun {
+        start: r.get(0)?,
+        end: r.get(1)?,
+        days: r.get(2)?,
+    })
+}
+
+/// The runs next to a day: the one covering it, else the closest on either side.
+#[derive(Debug, Default)]
+pub struct RunsAround {
+    pub at: Option<StreakRun>,
+    pub before: Option<StreakRun>,
+    pub after: Option<StreakRun>,
+}
+
+/// Everything that can change whether a day counts, apart from the day's own data.
+fn streak_rules(h: &Habit) -> (&str, &HabitFrequency, Option<f64>, u32) {
+    (
+        h.kind.as_str(),
+        &h.frequency,
+        h.target.as_ref().map(|t| t.amount),
+        h.times_per_day.max(1),
+    )
+}
+
+/// Notes a changed day for a habit whose runs are built; `streaks.rs` applies it on the
+/// next read.
+fn queue_streak_date(conn: &Connection, habit_id: &str, date: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO streak_dates (habit_id, date)
+         SELECT ?1, ?2 WHERE EXISTS (SELECT 1 FROM streak_habits WHERE habit_id = ?1)",
+        params![habit_id, date],
+    )?;
+    Ok(())
+}
+
+/// Drops the runs of one habit, or of all of them, to be rebuilt on the next read.
+fn forget_streaks(conn: &Connection, habit_id: Option<&str>) -> rusqlite::Result<()> {
+    for table in ["streak_habits", "streak_runs", "streak_dates"] {
+        conn.execute(
+            &format!("DELETE FROM {table} WHERE ?1 IS NULL OR habit_id = ?1"),
+            [habit_id],
+        )?;
+    }
+    Ok(())
+}
+
+impl Storage {
+    pub fn streak_runs_built(&self, habit_id: &str) -> rusqlite::Result<bool> {
+        let built = self
+            .reader()
+            .query_row(
+                "SELECT 1 FROM streak_habits WHERE habit_id = ?1",
+                [habit_id],
+                |_| Ok(()),
+            )
+            .optional()?;
+        Ok(built.is_some())
+    }
+
+    /// Removes and returns the days changed since the runs were last brought up to date,
+    /// in order.
+    pub fn take_streak_dates(&self, habit_id: &str) -> rusqlite::Result<Vec<String>> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let dates = {
+            let mut stmt = tx.prepare_cached(
+                "SELECT date FROM streak_dates WHERE habit_id = ?1 ORDER BY date",
+            )?;
+            let rows = stmt.query_map([habit_id], |r| r.get(0))?;
+            rows.collect::<rusqlite::Result<Vec<String>>>()?
+        };
+        tx.execute("DELETE FROM streak_dates WHERE habit_id = ?1", [habit_id])?;
+        tx.commit()?;
+        Ok(dates)
+    }
+
+    pub fn streak_runs_around(&self, habit_id: &str, date: &str) -> rusqlite::Result<RunsAround> {
+        let conn = self.reader();
+        let at = conn
+            .query_row(
+                "SELECT start, end, days FROM streak_runs
+                 WHERE habit_id = ?1 AND start <= ?2 AND end >= ?2",
+                params![habit_id, date],
+                streak_run_from_row,
+            )
+            .optional()?;
+        if at.is_some() {
+            return Ok(RunsAround {
+                at,
+                ..Default::default()
+            });
+        }
+        let before = conn
+            .query_row(
+                "SELECT start, end, days FROM streak_runs WHERE habit_id = ?1 AND end < ?2
+                 ORDER BY end DESC LIMIT 1",
+                params![habit_id, date],
+                streak_run_from_row,
+            )
+            .optional()?;
+        let after = conn
+            .query_row(
+                "SELECT start, end, days FROM streak_runs WHERE habit_id = ?1 AND start > ?2
+                 ORDER BY start LIMIT 1",
+                params![habit_id, date],
+                streak_run_from_row,
+            )
+            .optional()?;
+        Ok(RunsAround {
+            at: None,
+            before,
+            after,
+        })
+    }
+
+    /// Swaps the runs starting on `remove` for `add`, in one transaction.
+    pub fn edit_streak_runs(
+        &self,
+        habit_id: &str,
+        remove: &[&str],
+        add: &[StreakRun],
+    ) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        for start in remove {
+            tx.execute(
+                "DELETE FROM streak_runs WHERE habit_id = ?1 AND start = ?2",
+                params![habit_id, start],
+            )?;
+        }
+        for run in add {
+            tx.execute(
+                "INSERT OR REPLACE INTO streak_runs (habit_id, start, end, days)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![habit_id, run.start, run.end, run.days],
+            )?;
+        }
+        tx.commit()
+    }
+
+    /// Replaces a habit's runs and marks them built. Changed days queued meanwhile stay
+    /// queued.
+    pub fn replace_streak_runs(&self, habit_id: &str, runs: &[StreakRun]) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM streak_runs WHERE habit_id = ?1", [habit_id])?;
+        for run in runs {
+            tx.execute(
+                "INSERT INTO streak_runs (habit_id, start, end, days) VALUES (?1, ?2, ?3, ?4)",
+                params![habit_id, run.start, run.end, run.days],
+            )?;
+        }
+        tx.execute(
+            "INSERT OR IGNORE INTO streak_habits (habit_id) VALUES (?1)",
+            [habit_id],
+        )?;
+        tx.commit()
+    }
+
+    /// Drops the runs of one habit, or of every habit.
+    pub fn clear_streak_runs(&self, habit_id: Option<&str>) -> rusqlite::Result<()> {
+        forget_streaks(&self.conn(), habit_id)
+    }
+
+    pub fn streak_run_count(&self) -> rusqlite::Result<usize> {
+        self.reader()
+            .query_row("SELECT COUNT(*) FROM streak_runs", [], |r| r.get(0))
+    }
+
+    /// The latest run starting on or before `date`.
+    pub fn last_streak_run(
+        &self,
+        habit_id: &str,
+        date: &str,
+    ) -> rusqlite::Result<Option<StreakRun>> {
+        self.reader()
+            .query_row(
+                "SELECT start, end, days FROM streak_runs WHERE habit_id = ?1 AND start <= ?2
+                 ORDER BY start DESC LIMIT 1",
+                params![habit_id, date],
+                streak_run_from_row,
+            )
+            .optional()
+    }
+
+    pub fn longest_streak_run(&self, habit_id: &str) -> rusqlite::Result<u32> {
+        self.reader().query_row(
+            "SELECT COALESCE(MAX(days), 0) FROM streak_runs WHERE habit_id = ?1",
+            [habit_id],
+            |r| r.get(0),
+        )
+    }
+}
+
+/// A single value logged against a quantified habit.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckIn {
+    pub id: i64,
+    pub habit_id: String,
+    pub date: String,
+    pub value: f64,
+    pub logged_at: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// The revision of the day it's on; see `Habit::revision`.
+    pub revision: u64,
+}
+
+impl Storage {
+    /// Oldest first, optionally for one day.
+    pub fn list_check_ins(
+        &self,
+        habit_id: &str,
+        date: Option<&str>,
+    ) -> rusqlite::Result<Vec<CheckIn>> {
+        let conn = self.reader();
+        let mut tags = load_check_in_tags(&conn)?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, habit_id, date, value, logged_at, (SELECT revision FROM revisions r
+                 WHERE r.habit_id = check_ins.habit_id AND r.date = check_ins.date)
+             FROM check_ins WHERE habit_id = ?1 AND (?2 IS NULL OR date = ?2) ORDER BY date, id",
+        )?;
+        let rows = stmt.query_map(params![habit_id, date], check_in_from_row)?;
+        rows.map(|c| {
+            c.map(|mut c| {
+                c.tags = tags.remove(&c.id).unwrap_or_default();
+                c
+            })
+        })
+        .collect()
+    }
+
+    /// Check-ins carrying `tag`, on any habit, oldest first.
+    pub fn list_check_ins_by_tag(&self, tag: &str) -> rusqlite::Result<Vec<CheckIn>> {
+        let conn = self.reader();
+        let mut tags = load_check_in_tags(&conn)?;
+        let mut stmt = conn.prepare_cached(
+            "SELECT c.id, c.habit_id, c.date, c.value, c.logged_at, (SELECT revision
+                 FROM revisions r WHERE r.habit_id = c.habit_id AND r.date = c.date)
+             FROM check_ins c JOIN check_in_tags t ON t.check_in_id = c.id
+             WHERE t.tag = ?1 ORDER BY c.date, c.id",
+        )?;
+        let rows = stmt.query_map([tag], check_in_from_row)?;
+        rows.map(|c| {
+            c.map(|mut c| {
+                c.tags = tags.remove(&c.id).unwrap_or_default();
+                c
+            })
+        })
+        .collect()
+    }
+
+    /// Returns whether the habit exists.
+    pub fn set_habit_tags(&self, habit_id: &str, tags: &[String]) -> rusqlite::Result<bool> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let exists = tx
+            .query_row("SELECT 1 FROM habits WHERE id = ?1", [habit_id], |_| Ok(()))
+            .optional()?
+            .is_some();
+        if exists {
+            tx.execute("DELETE FROM habit_tags WHERE habit_id = ?1", [habit_id])?;
+            for tag in tags {
+                tx.execute(
+                    "INSERT OR IGNORE INTO habit_tags (habit_id, tag) VALUES (?1, ?2)",
+                    params![habit_id, tag],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(exists)
+    }
+
+    /// Every tag in use, most used first.
+    pub fn list_tags(&self) -> rusqlite::Result<Vec<TagUsage>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT tag, SUM(habit), SUM(check_in) FROM (
+                SELECT tag, 1 AS habit, 0 AS check_in FROM habit_tags
+                UNION ALL
+                SELECT tag, 0, 1 FROM check_in_tags
+             ) GROUP BY tag ORDER BY SUM(habit) + SUM(check_in) DESC, tag",
+        )?;
+        let rows = stmt.query_map([], |r| {
+            Ok(TagUsage {
+                tag: r.get(0)?,
+                habits: r.get(1)?,
+                check_ins: r.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+/// How many habits and check-ins carry a tag.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagUsage {
+    pub tag: String,
+    pub habits: u32,
+    pub check_ins: u32,
+}
+
+fn check_in_from_row(r: &rusqlite::Row<'_>) -> rusqlite::Result<CheckIn> {
+    Ok(CheckIn {
+        id: r.get(0)?,
+        habit_id: r.get(1)?,
+        date: r.get(2)?,
+        value: r.get(3)?,
+        logged_at: r.get(4)?,
+        tags: Vec::new(),
+        revision: r.get::<_, Option<i64>>(5)?.unwrap_or(0) as u64,
+    })
+}
+
+/// Tags grouped by habit, alphabetically.
+fn load_habit_tags(
+    conn: &Connection,
+    habit_id: Option<&str>,
+) -> rusqlite::Result<HashMap<String, Vec<String>>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT habit_id, tag FROM habit_tags WHERE ?1 IS NULL OR habit_id = ?1 ORDER BY tag",
+    )?;
+    let rows = stmt.query_map([habit_id], |r| {
+        Ok((r.get::<_, String>(0)?, r.get::<_, String>(1)?))
+    })?;
+    let mut tags: HashMap<String, Vec<String>> = HashMap::new();
+    for row in rows {
+        let (habit_id, tag) = row?;
+        tags.entry(habit_id).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+/// Tags grouped by check-in id, alphabetically.
+fn load_check_in_tags(conn: &Connection) -> rusqlite::Result<HashMap<i64, Vec<String>>> {
+    let mut stmt =
+        conn.prepare_cached("SELECT check_in_id, tag FROM check_in_tags ORDER BY tag")?;
+    let rows = stmt.query_map([], |r| Ok((r.get::<_, i64>(0)?, r.get::<_, String>(1)?)))?;
+    let mut tags: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in rows {
+        let (id, tag) = row?;
+        tags.entry(id).or_default().push(tag);
+    }
+    Ok(tags)
+}
+
+/// One run of a scheduled export job.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportRun {
+    pub id: i64,
+    pub job_id: String,
+    pub started_at: String,
+    pub path: Option<String>,
+    pub error: Option<String>,
+    pub success: bool,
+}
+
+impl Storage {
+    /// Appends to the export history, keeping the most recent [`EXPORT_RUNS_KEPT`] runs of
+    /// each job, so a job that runs often can't push another's history out.
+    pub fn record_export_run(&self, run: &ExportRun) -> rusqlite::Result<i64> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO export_runs (job_id, started_at, path, error, success)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![run.job_id, run.started_at, run.path, run.error, run.success],
+        )?;
+        let id = conn.last_insert_rowid();
+        conn.execute(
+            "DELETE FROM export_runs WHERE job_id = ?1 AND id <= (
+                SELECT id FROM export_runs WHERE job_id = ?1 ORDER BY id DESC LIMIT 1 OFFSET ?2
+            )",
+            params![run.job_id, EXPORT_RUNS_KEPT],
+        )?;
+        Ok(id)
+    }
+
+    /// Newest first, optionally for one job.
+    pub fn list_export_runs(
+        &self,
+        job_id: Option<&str>,
+        limit: u32,
+    ) -> rusqlite::Result<Vec<ExportRun>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, job_id, started_at, path, error, success FROM export_runs
+             WHERE ?1 IS NULL OR job_id = ?1 ORDER BY id DESC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![job_id, limit], |r| {
+            Ok(ExportRun {
+                id: r.get(0)?,
+                job_id: r.get(1)?,
+                started_at: r.get(2)?,
+                path: r.get(3)?,
+                error: r.get(4)?,
+                success: r.get(5)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+impl Storage {
+    /// In display order.
+    pub fn list_categories(&self) -> rusqlite::Result<Vec<Category>> {
+        let conn = self.reader();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, name, color, position FROM categories ORDER BY position, name",
+        )?;
+        let rows = stmt.query_map([], |r| {
+            Ok(Category {
+                id: r.get(0)?,
+                name: r.get(1)?,
+                color: r.get(2)?,
+                position: r.get(3)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn insert_category(&self, category: &Category) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT INTO categories (id, name, color, position) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                category.id,
+                category.name,
+                category.color,
+                category.position
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Renaming carries the habits in the category along. Returns how many habits were
+    /// renamed, or `None` if the category doesn't exist.
+    pub fn update_category(
+        &self,
+        id: &str,
+        name: &str,
+        color: &str,
+    ) -> rusqlite::Result<Option<usize>> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let old: Option<String> = tx
+            .query_row("SELECT name FROM categories WHERE id = ?1", [id], |r| {
+                r.get(0)
+            })
+            .optional()?;
+        let Some(old) = old else {
+            return Ok(None);
+        };
+        tx.execute(
+            "UPDATE categories SET name = ?2, color = ?3 WHERE id = ?1",
+            params![id, name, color],
+        )?;
+        let moved = if old == name {
+            0
+        } else {
+            tx.execute(
+                "UPDATE habits SET category = ?2 WHERE category = ?1 COLLATE NOCASE",
+                params![old, name],
+            )?
+        };
+        tx.commit()?;
+        Ok(Some(moved))
+    }
+
+    /// Deletes a category and moves its habits to `fallback` (created if needed). Returns
+    /// how many habits moved, or `None` if the category doesn't exist.
+    pub fn delete_category(
+        &self,
+        id: &str,
+        fallback: &Category,
+    ) -> rusqlite::Result<Option<usize>> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let name: Option<String> = tx
+            .query_row("SELECT name FROM categories WHERE id = ?1", [id], |r| {
+                r.get(0)
+            })
+            .optional()?;
+        let Some(name) = name else {
+            return Ok(None);
+        };
+        tx.execute("DELETE FROM categories WHERE id = ?1", [id])?;
+        let moved = tx.execute(
+            "UPDATE habits SET category = ?2 WHERE category = ?1 COLLATE NOCASE",
+            params![name, fallback.name],
+        )?;
+        if moved > 0 {
+            tx.execute(
+                "INSERT OR IGNORE INTO categories (id, name, color, position)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    fallback.id,
+                    fallback.name,
+                    fallback.color,
+                    fallback.position
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(Some(moved))
+    }
+
+    /// Positions follow the order of `ids`; categories not listed keep their relative
+    /// order after them.
+    pub fn reorder_categories(&self, ids: &[String]) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let existing: Vec<String> = {
+            let mut stmt =
+                tx.prepare_cached("SELECT id FROM categories ORDER BY position, name")?;
+            let rows = stmt.query_map([], |r| r.get(0))?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        let listed: HashSet<&str> = ids.iter().map(String::as_str).collect();
+        let order = ids
+            .iter()
+            .filter(|id| existing.contains(id))
+            .chain(existing.iter().filter(|id| !listed.contains(id.as_str())));
+        {
+            let mut update =
+                tx.prepare_cached("UPDATE categories SET position = ?2 WHERE id = ?1")?;
+            for (position, id) in order.enumerate() {
+                update.execute(params![id, position as i64])?;
+            }
+        }
+        tx.commit()
+    }
+
+    /// Returns whether the habit exists.
+    pub fn set_habit_category(&self, habit_id: &str, category: &str) -> rusqlite::Result<bool> {
+        let changed = self.conn().execute(
+            "UPDATE habits SET category = ?2 WHERE id = ?1",
+            params![habit_id, category],
+        )?;
+        Ok(changed > 0)
+    }
+}
+
+fn insert_missing_categories(conn: &Connection, habits: &[Habit]) -> rusqlite::Result<()> {
+    let mut known: HashSet<String> = HashSet::new();
+    let mut next: i64 = 0;
+    {
+        let mut stmt = conn.prepare_cached("SELECT name, position FROM categories")?;
+        let rows = stmt.query_map([], |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)?)))?;
+        for row in rows {
+            let (name, position) = row?;
+            known.insert(name.to_lowercase());
+            next = next.max(position + 1);
+        }
+    }
+    let mut insert = conn.prepare_cached(
+        "INSERT INTO categories (id, name, color, position) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    for h in habits {
+        if known.insert(h.category.to_lowercase()) {
+            insert.execute(params![
+                crate::settings::generate_token(),
+                h.category,
+                h.color,
+                next
+            ])?;
+            next += 1;
+        }
+    }
+    Ok(())
+}
+
+const HABIT_SELECT: &str = "SELECT id, name, description, color, icon, category, frequency_type,
+    frequency_goal, reminder_time, created_at, archived, target_amount, target_unit, kind,
+    archived_at, window_start, window_end, pinned, times_per_day, emoji, cover, difficulty,
+    (SELECT revision FROM revisions WHERE habit_id = habits.id AND date = '')
+    FROM habits";
+
+/// Tables that only mean something on this device: settings-like state, the sync queue,
+/// delivery and export logs, revision counters, caches built from the rest, and the
+/// device's own screen time.
+pub const LOCAL_TABLES: &[&str] = &[
+    "meta",
+    "replica_changes",
+    "replica_rows",
+    "sync_ops",
+    "sync_state",
+    "webhook_deliveries",
+    "export_runs",
+    "revisions",
+    "streak_habits",
+    "streak_runs",
+    "streak_dates",
+    "screen_time",
+];
+
+/// The libSQL replica's own bookkeeping, which [`Storage::clear_all`] leaves alone.
+const REPLICA_TABLES: &[&str] = &["replica_changes", "replica_rows"];
+
+/// A table as [`Storage::replicated_schema`] describes it.
+#[derive(Debug, Clone)]
+pub struct ReplicatedTable {
+    pub name: String,
+    /// `CREATE TABLE`, then `CREATE INDEX` for each of its indexes.
+    pub sql: Vec<String>,
+    /// Its primary key's columns, in order.
+    pub key: Vec<String>,
+}
+
+/// A table's rows, to copy it to or from another database.
+#[derive(Debug, Clone)]
+pub struct TableRows {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<rusqlite::types::Value>>,
+}
+
+/// Rows of one table that changed, on this device or the remote side.
+#[derive(Debug, Clone)]
+pub struct TableChanges {
+    pub name: String,
+    pub columns: Vec<String>,
+    pub rows: Vec<RowChange>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RowChange {
+    /// The primary key, as a JSON array (`["read","2024-06-01"]`).
+    pub key: String,
+    /// When the row was last written, in UTC with milliseconds.
+    pub changed_at: String,
+    /// `None` once it's deleted.
+    pub row: Option<Vec<rusqlite::types::Value>>,
+}
+
+/// `name` as a quoted SQL identifier.
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// `value` as an SQL string literal.
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// SQL for a row's key as [`RowChange::key`] has it, from its columns (`NEW.`, say).
+pub fn key_json(prefix: &str, key: &[String]) -> String {
+    let columns: Vec<String> = key
+        .iter()
+        .map(|c| format!("{prefix}{}", quote_ident(c)))
+        .collect();
+    format!("json_array({})", columns.join(", "))
+}
+
+/// SQL that's true for the row of the table known as `alias` whose key is the JSON array
+/// `json` (an SQL expression).
+pub fn key_matches(alias: &str, key: &[String], json: &str) -> String {
+    key.iter()
+        .enumerate()
+        .map(|(i, c)| {
+            format!(
+                "{alias}.{} = json_extract({json}, '$[{i}]')",
+                quote_ident(c)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" AND ")
+}
+
+/// `INSERT` of `rows` rows into `table`, or an update of the ones already there.
+pub fn upsert_sql<'a>(
+    table: &ReplicatedTable,
+    columns: impl Iterator<Item = &'a str>,
+    rows: usize,
+) -> String {
+    let columns: Vec<&str> = columns.collect();
+    let names: Vec<String> = columns.iter().map(|c| quote_ident(c)).collect();
+    let keys: Vec<String> = table.key.iter().map(|c| quote_ident(c)).collect();
+    let updates: Vec<String> = columns
+        .iter()
+        .filter(|c| !table.key.iter().any(|k| k == *c))
+        .map(|c| format!("{0} = excluded.{0}", quote_ident(c)))
+        .collect();
+    let row = format!("({})", vec!["?"; columns.len()].join(", "));
+    format!(
+        "INSERT INTO {} ({}) VALUES {} ON CONFLICT ({}) DO {}",
+        quote_ident(&table.name),
+        names.join(", "),
+        vec![row.as_str(); rows].join(", "),
+        keys.join(", "),
+        if updates.is_empty() {
+            "NOTHING".to_string()
+        } else {
+            format!("UPDATE SET {}", updates.join(", "))
+        }
+    )
+}
+
+/// Lowercase hex SHA-256 of a row's values, to tell whether it's still what was synced.
+pub fn row_hash(row: &[rusqlite::types::Value]) -> String {
+    use rusqlite::types::Value;
+    use sha2::{Digest, Sha256};
+    let mut hasher = Sha256::new();
+    for value in row {
+        match value {
+            Value::Null => hasher.update([0]),
+            Value::Integer(i) => {
+                hasher.update([1]);
+                hasher.update(i.to_le_bytes());
+            }
+            Value::Real(f) => {
+                hasher.update([2]);
+                hasher.update(f.to_le_bytes());
+            }
+            Value::Text(s) => {
+                hasher.update([3]);
+                hasher.update((s.len() as u64).to_le_bytes());
+                hasher.update(s);
+            }
+            Value::Blob(b) => {
+                hasher.update([4]);
+                hasher.update((b.len() as u64).to_le_bytes());
+                hasher.update(b);
+            }
+        }
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// The SQL function for [`crate::clock::now`], registered on every connection.
+const NOW_FUNCTION: &str = "habitflow_now";
+/// What the triggers put in `replica_changes.changed_at`.
+const CHANGED_NOW: &str = "habitflow_now()";
+
+/// [`crate::clock::now`] in UTC to the millisecond, as `strftime('%Y-%m-%dT%H:%M:%fZ')`
+/// writes it, so it sorts with stamps written before there was a function for it.
+fn changed_now() -> String {
+    crate::clock::now()
+        .with_timezone(&chrono::Utc)
+        .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+        .to_string()
+}
+
+/// The triggers that record the keys of `table`'s rows as they're written: names and SQL.
+fn replica_triggers(table: &ReplicatedTable) -> [(String, String); 3] {
+    let name = quote_literal(&table.name);
+    let new = key_json("NEW.", &table.key);
+    let old = key_json("OLD.", &table.key);
+    let trigger = |op: &str, event: &str, values: String| {
+        let trigger = format!("replica_{}_{op}", table.name);
+        let sql = format!(
+            "CREATE TRIGGER IF NOT EXISTS {} AFTER {event} ON {} BEGIN
+                INSERT OR REPLACE INTO replica_changes (tbl, pk, changed_at) VALUES {values};
+            END;",
+            quote_ident(&trigger),
+            quote_ident(&table.name)
+        );
+        (trigger, sql)
+    };
+    [
+        trigger("ai", "INSERT", format!("({name}, {new}, {CHANGED_NOW})")),
+        // Both keys, in case the key itself changed.
+        trigger(
+            "au",
+            "UPDATE",
+            format!("({name}, {old}, {CHANGED_NOW}), ({name}, {new}, {CHANGED_NOW})"),
+        ),
+        trigger("ad", "DELETE", format!("({name}, {old}, {CHANGED_NOW})")),
+    ]
+}
+
+/// Records `row` (`None`: no row) as what the remote side has under `key`.
+fn set_synced(
+    conn: &Connection,
+    table: &str,
+    key: &str,
+    row: Option<&[rusqlite::types::Value]>,
+) -> rusqlite::Result<()> {
+    match row {
+        Some(row) => conn.execute(
+            "INSERT OR REPLACE INTO replica_rows (tbl, pk, hash) VALUES (?1, ?2, ?3)",
+            params![table, key, row_hash(row)],
+        )?,
+        None => conn.execute(
+            "DELETE FROM replica_rows WHERE tbl = ?1 AND pk = ?2",
+            params![table, key],
+        )?,
+    };
+    Ok(())
+}
+
+/// Deletes the rows left pointing at a parent that's gone. Returns how many.
+fn delete_orphans(conn: &Connection) -> rusqlite::Result<usize> {
+    let orphans: Vec<(String, Option<i64>)> = conn
+        .prepare("PRAGMA foreign_key_check")?
+        .query_map([], |r| Ok((r.get(0)?, r.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (table, rowid) in &orphans {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE rowid = ?1", quote_ident(table)),
+            [rowid],
+        )?;
+    }
+    Ok(orphans.len())
+}
+
+/// Set while `replace_habits` works out which revisions to bump itself.
+const REVISIONS_PAUSED_KEY: &str = "revisions.paused";
+const BUMP_REVISION: &str = "INSERT INTO revisions (habit_id, date, revision) VALUES (?1, ?2, 1)
+    ON CONFLICT DO UPDATE SET revision = revision + 1";
+
+/// Days a habit has anything recorded on.
+fn record_days(h: &Habit) -> BTreeSet<&str> {
+    h.logs
+        .keys()
+        .chain(h.values.keys())
+        .chain(h.notes.keys())
+        .chain(&h.backfilled)
+        .chain(h.lapses.iter().map(|l| &l.date))
+        .chain(h.checklist.iter().flat_map(|i| &i.done))
+        .map(String::as_str)
+        .collect()
+}
+
+/// What a day's revision covers.
+type DayRecord<'a> = (
+    Option<&'a bool>,
+    Option<&'a f64>,
+    Option<&'a String>,
+    bool,
+    Vec<&'a Lapse>,
+    Vec<bool>,
+);
+
+fn day_record<'a>(h: &'a Habit, date: &str) -> DayRecord<'a> {
+    (
+        h.logs.get(date),
+        h.values.get(date),
+        h.notes.get(date),
+        h.backfilled.contains(date),
+        h.lapses.iter().filter(|l| l.date == date).collect(),
+        h.checklist.iter().map(|i| i.done.contains(date)).collect(),
+    )
+}
+
+/// Every habit with everything about it, in manual order.
+fn load_habit(conn: &Connection, id: &str) -> rusqlite::Result<Option<Habit>> {
+    let habit = conn
+        .query_row(
+            &format!("{HABIT_SELECT} WHERE id = ?1"),
+            [id],
+            habit_from_row,
+        )
+        .optional()?;
+    let Some(mut habit) = habit else {
+        return Ok(None);
+    };
+
+    {
+        let mut stmt = conn.prepare_cached(
+            "SELECT date, backfilled FROM completions WHERE habit_id = ?1 ORDER BY date",
+        )?;
+        let dates = stmt.query_map([id], |r| Ok((r.get::<_, String>(0)?, r.get::<_, bool>(1)?)))?;
+        for row in dates {
+            let (date, backfilled) = row?;
+            if backfilled {
+                habit.backfilled.insert(date.clone());
+            }
+            habit.logs.insert(date, true);
+        }
+    }
+    {
+        let mut stmt = conn.prepare_cached(
+            "SELECT date, SUM(value) FROM check_ins WHERE habit_id = ?1 GROUP BY date",
+        )?;
+        let values = stmt.query_map([id], |r| Ok((r.get::<_, String>(0)?, r.get::<_, f64>(1)?)))?;
+        for value in values {
+            let (date, value) = value?;
+            habit.values.insert(date, value);
+        }
+    }
+
+    if let Some(l) = load_lapses(conn, Some(id))?.remove(id) {
+        habit.lapses = l;
+    }
+    if let Some(t) = load_habit_tags(conn, Some(id))?.remove(id) {
+        habit.tags = t;
+    }
+    if let Some(c) = load_checklists(conn, Some(id))?.remove(id) {
+        habit.checklist = c;
+    }
+    if let Some(d) = load_dependencies(conn, Some(id))?.remove(id) {
+        habit.depends_on = d;
+    }
+    habit.off_periods = covering(&load_off_periods(conn)?, id);
+    habit.paused_until = paused_until(&habit);
+    if let Some(f) = load_frozen(conn, Some(id))?.remove(id) {
+        habit.frozen = f;
+    }
+    if let Some(n) = load_notes(conn, Some(id))?.remove(id) {
+        habit.notes = n;
+    }
+    if let Some(r) = load_weekday_reminders(conn, Some(id))?.remove(id) {
+        habit.weekday_reminders = r;
+    }
+    Ok(Some(habit))
+}
+
+fn load_habits(conn: &Connection) -> rusqlite::Result<Vec<Habit>> {
+    let mut logs: HashMap<String, Vec<(String, bool)>> = HashMap::new();
+    {
+        let mut stmt = conn
+            .prepare_cached("SELECT habit_id, date, backfilled FROM completions ORDER BY date")?;
+        let rows = stmt.query_map([], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, bool>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (habit_id, date, backfilled) = row?;
+            logs.entry(habit_id).or_default().push((date, backfilled));
+        }
+    }
+
+    let mut values: HashMap<String, BTreeMap<String, f64>> = HashMap::new();
+    {
+        let mut stmt = conn.prepare_cached(
+            "SELECT habit_id, date, SUM(value) FROM check_ins GROUP BY habit_id, date",
+        )?;
+        let rows = stmt.query_map([], |r| {
+            Ok((
+                r.get::<_, String>(0)?,
+                r.get::<_, String>(1)?,
+                r.get::<_, f64>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (habit_id, date, value) = row?;
+            values.entry(habit_id).or_default().insert(date, value);
+        }
+    }
+
+    let mut lapses = load_lapses(conn, None)?;
+    let mut tags = load_habit_tags(conn, None)?;
+    let mut checklists = load_checklists(conn, None)?;
+    let mut dependencies = load_dependencies(conn, None)?;
+    let off_periods = load_off_periods(conn)?;
+    let mut frozen = load_frozen(conn, None)?;
+    let mut notes = load_notes(conn, None)?;
+    let mut weekday_reminders = load_weekday_reminders(conn, None)?;
+
+    let mut stmt = conn.prepare_cached(&format!("{HABIT_SELECT} ORDER BY position, created_at"))?;
+    let habits = stmt
+        .query_map([], habit_from_row)?
+        .map(|h| {
+            h.map(|mut h| {
+                if let Some(dates) = logs.remove(&h.id) {
+                    for (date, backfilled) in dates {
+                        if backfilled {
+                            h.backfilled.insert(date.clone());
+                        }
+                        h.logs.insert(date, true);
+                    }
+                }
+                if let Some(v) = values.remove(&h.id) {
+                    h.values = v;
+                }
+                if let Some(l) = lapses.remove(&h.id) {
+                    h.lapses = l;
+                }
+                if let Some(t) = tags.remove(&h.id) {
+                    h.tags = t;
+                }
+                if let Some(c) = checklists.remove(&h.id) {
+                    h.checklist = c;
+                }
+                if let Some(d) = dependencies.remove(&h.id) {
+                    h.depends_on = d;
+                }
+                h.off_periods = covering(&off_periods, &h.id);
+                h.paused_until = paused_until(&h);
+                if let Some(f) = frozen.remove(&h.id) {
+                    h.frozen = f;
+                }
+                if let Some(n) = notes.remove(&h.id) {
+                    h.notes = n;
+                }
+                if let Some(r) = weekday_reminders.remove(&h.id) {
+                    h.weekday_reminders = r;
+                }
+                h
+            })
+        })
+        .collect();
+    habits
+}
+
+fn habit_from_row(r: &rusqlite::Row<'_>) -> rusqlite::Result<Habit> {
+    Ok(Habit {
+        id: r.get(0)?,
+        name: r.get(1)?,
+        description: r.get(2)?,
+        color: r.get(3)?,
+        icon: r.get(4)?,
+        emoji: r.get(19)?,
+        cover: r.get(20)?,
+        category: r.get(5)?,
+        frequency: HabitFrequency {
+            kind: r.get(6)?,
+            goal: r.get(7)?,
+        },
+        reminder_time: r.get(8)?,
+        window: match (r.get(15)?, r.get(16)?) {
+            (Some(start), Some(end)) => Some(TimeWindow { start, end }),
+            _ => None,
+        },
+        created_at: r.get(9)?,
+        logs: Default::default(),
+        archived: r.get(10)?,
+        archived_at: r.get(14)?,
+        pinned: r.get(17)?,
+        difficulty: Difficulty::parse(&r.get::<_, String>(21)?),
+        times_per_day: r.get(18)?,
+        target: match r.get::<_, Option<f64>>(11)? {
+            Some(amount) => Some(HabitTarget {
+                amount,
+                unit: r.get::<_, Option<String>>(12)?.unwrap_or_default(),
+            }),
+            None => None,
+        },
+        values: Default::default(),
+        notes: Default::default(),
+        weekday_reminders: Default::default(),
+        backfilled: Default::default(),
+        kind: HabitKind::parse(&r.get::<_, String>(13)?),
+        lapses: Vec::new(),
+        tags: Vec::new(),
+        depends_on: Vec::new(),
+        checklist: Vec::new(),
+        off_periods: Vec::new(),
+        paused_until: None,
+        frozen: Default::default(),
+        revision: r.get::<_, Option<i64>>(22)?.unwrap_or(0) as u64,
+    })
+}
+
+/// Lapses grouped by habit, oldest first.
+fn load_lapses(
+    conn: &Connection,
+    habit_id: Option<&str>,
+) -> rusqlite::Result<HashMap<String, Vec<Lapse>>> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT habit_id, date, note, logged_at FROM lapses
+         WHERE ?1 IS NULL OR habit_id = ?1 ORDER BY date, id",
+    )?;
+    let rows = stmt.query_map([habit_id], |r| {
+        Ok((
+            r.get::<_, String>(0)?,
+            Lapse {
+                date: r.get(1)?,
+                note: r.get(2)?,
+                logged_at: r.get(3)?,
+            },
+        ))
+    })?;
+    let mut lapses: HashMap<String, Vec<Lapse>> = HashMap::new();
+    for row in rows {
+        let (habit_id, lapse) = row?;
+        lapses.entry(habit_id).or_default().push(lapse);
+    }
+    Ok(lapses)
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |r| r.get(0))?;
+    for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(sql)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,208 @@
+//! Daily streaks, kept in the database as runs of done days (`streak_runs`) so reading one
+//! doesn't walk a habit's whole history. The storage layer queues every day whose data
+//! changes (check-ins, undos, backfills, synced snapshots) and the next read applies them
+//! one by one: marking a day joins it to the runs on either side, unmarking splits the run
+//! it was in. Changes that can reshape every run at once (off periods, freezes, a habit's
+//! schedule or target) drop the runs, and they're rebuilt from the history when next read.
+//!
+//! Weekly, monthly and interval habits count periods and avoidance habits count days
+//! between lapses; both are cheap enough to compute directly, so only daily build habits
+//! are kept here. `rebuild_streaks` recomputes the runs for repair.
+
+use chrono::NaiveDate;
+use tauri::State;
+
+use crate::error::AppError;
+use crate::model::{Habit, HabitKind};
+use crate::stats::{self, bridged, date_key, parse_date};
+use crate::storage::{Storage, StreakRun};
+
+/// More changed days than this and a rebuild is cheaper than applying them.
+const REBUILD_AFTER: usize = 60;
+
+fn materialized(habit: &Habit) -> bool {
+    habit.kind == HabitKind::Build && !stats::uses_periods(habit)
+}
+
+/// Runs made of `dates` (done days, in order).
+fn runs_of(habit: &Habit, dates: impl IntoIterator<Item = NaiveDate>) -> Vec<StreakRun> {
+    let mut runs: Vec<StreakRun> = Vec::new();
+    let mut prev: Option<NaiveDate> = None;
+    for date in dates {
+        match (prev, runs.last_mut()) {
+            (Some(p), _) if p == date => continue,
+            (Some(p), Some(run)) if bridged(habit, p, date) => {
+                run.end = date_key(date);
+                run.days += 1;
+            }
+            _ => runs.push(StreakRun {
+                start: date_key(date),
+                end: date_key(date),
+                days: 1,
+            }),
+        }
+        prev = Some(date);
+    }
+    runs
+}
+
+fn rebuild(storage: &Storage, habit_id: &str) -> rusqlite::Result<()> {
+    // Taken first, so days changed while this runs are still applied afterwards.
+    storage.take_streak_dates(habit_id)?;
+    let Some(habit) = storage.get_habit(habit_id)? else {
+        return storage.clear_streak_runs(Some(habit_id));
+    };
+    if !materialized(&habit) {
+        return storage.clear_streak_runs(Some(habit_id));
+    }
+    let runs = runs_of(
+        &habit,
+        habit.done_dates().into_iter().filter_map(parse_date),
+    );
+    storage.replace_streak_runs(habit_id, &runs)
+}
+
+/// Brings the runs in line with one changed day.
+fn apply(storage: &Storage, habit: &Habit, key: &str) -> rusqlite::Result<()> {
+    let Some(date) = parse_date(key) else {
+        return Ok(());
+    };
+    let around = storage.streak_runs_around(&habit.id, key)?;
+    match (habit.is_done_on(key), around.at) {
+        (true, Some(_)) | (false, None) => Ok(()),
+        (true, None) => {
+            let joins = |from: &str, to: &str| match (parse_date(from), parse_date(to)) {
+                (Some(from), Some(to)) => bridged(habit, from, to),
+                _ => false,
+            };
+            let before = around.before.filter(|r| joins(&r.end, key));
+            let after = around.after.filter(|r| joins(key, &r.start));
+            let mut run = StreakRun {
+                start: key.to_string(),
+                end: key.to_string(),
+                days: 1,
+            };
+            let mut remove = Vec::new();
+            if let Some(b) = &before {
+                run.start = b.start.clone();
+                run.days += b.days;
+                remove.push(b.start.as_str());
+            }
+            if let Some(a) = &after {
+                run.end = a.end.clone();
+                run.days += a.days;
+                remove.push(a.start.as_str());
+            }
+            storage.edit_streak_runs(&habit.id, &remove, &[run])
+        }
+        (false, Some(run)) => {
+            let (Some(start), Some(end)) = (parse_date(&run.start), parse_date(&run.end)) else {
+                return rebuild(storage, &habit.id);
+            };
+            let rest = start
+                .iter_days()
+                .take_while(|d| *d <= end)
+                .filter(|d| *d != date && habit.is_done_on(&date_key(*d)));
+            let split = runs_of(habit, rest);
+            storage.edit_streak_runs(&habit.id, &[run.start.as_str()], &split)
+        }
+    }
+}
+
+/// Builds the habit's runs if needed and applies the days changed since.
+fn catch_up(storage: &Storage, habit_id: &str) -> rusqlite::Result<()> {
+    if !storage.streak_runs_built(habit_id)? {
+        return rebuild(storage, habit_id);
+    }
+    let dates = storage.take_streak_dates(habit_id)?;
+    if dates.is_empty() {
+        return Ok(());
+    }
+    if dates.len() > REBUILD_AFTER {
+        return rebuild(storage, habit_id);
+    }
+    // The caller's copy of the habit may predate the writes that queued these.
+    let Some(habit) = storage.get_habit(habit_id)? else {
+        return storage.clear_streak_runs(Some(habit_id));
+    };
+    for date in &dates {
+        apply(storage, &habit, date)?;
+    }
+    Ok(())
+}
+
+/// Same result as [`stats::current_streak`], read from the runs where the habit has them.
+pub fn current(storage: &Storage, habit: &Habit, today: NaiveDate) -> u32 {
+    if !materialized(habit) {
+        return stats::current_streak(habit, today);
+    }
+    let key = date_key(today);
+    let run = catch_up(storage, &habit.id).and_then(|_| storage.last_streak_run(&habit.id, &key));
+    match run {
+        Ok(None) => 0,
+        // A run ending in the future (a clock change, or a date typed in) needs the walk.
+        Ok(Some(run)) if run.end <= key => match parse_date(&run.end) {
+            Some(end) if bridged(habit, end, today) => run.days,
+            _ => 0,
+        },
+        Ok(Some(_)) => stats::current_streak(habit, today),
+        Err(e) => {
+            log::warn!("streak runs unavailable for {}: {e}", habit.id);
+            stats::current_streak(habit, today)
+        }
+    }
+}
+
+/// Same result as [`stats::longest_streak`], read from the runs where the habit has them.
+pub fn longest(storage: &Storage, habit: &Habit, today: NaiveDate) -> u32 {
+    if !materialized(habit) {
+        return stats::longest_streak(habit, today);
+    }
+    match catch_up(storage, &habit.id).and_then(|_| storage.longest_streak_run(&habit.id)) {
+        Ok(longest) => longest,
+        Err(e) => {
+            log::warn!("streak runs unavailable for {}: {e}", habit.id);
+            stats::longest_streak(habit, today)
+        }
+    }
+}
+
+/// Catches up every habit's runs, so the first streak reads after startup don't have to.
+pub fn warm_up(storage: &Storage) {
+    let habits = match storage.list_habits() {
+        Ok(habits) => habits,
+        Err(e) => return log::warn!("streak warm-up skipped: {e}"),
+    };
+    for habit in habits.iter().filter(|h| materialized(h)) {
+        if let Err(e) = catch_up(storage, &habit.id) {
+            log::warn!("streak runs unavailable for {}: {e}", habit.id);
+        }
+    }
+}
+
+/// Recomputes the runs of one habit, or of every habit. Returns how many habits have runs
+/// afterwards.
+pub fn rebuild_all(storage: &Storage, habit_id: Option<&str>) -> Result<usize, AppError> {
+    let habits = match habit_id {
+        Some(id) => vec![storage
+            .get_habit(id)?
+            .ok_or_else(|| AppError::not_found("habit", id))?],
+        None => storage.list_habits()?,
+    };
+    storage.clear_streak_runs(habit_id)?;
+    let mut rebuilt = 0;
+    for habit in habits.iter().filter(|h| materialized(h)) {
+        rebuild(storage, &habit.id)?;
+        rebuilt += 1;
+    }
+    Ok(rebuilt)
+}
+
+/// For repair only: streaks stay up to date on their own.
+#[tauri::command]
+pub fn rebuild_streaks(
+    storage: State<'_, Storage>,
+    habit_id: Option<String>,
+) -> Result<usize, AppError> {
+    rebuild_all(&storage, habit_id.as_deref())
+}
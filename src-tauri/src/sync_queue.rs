@@ -0,0 +1,415 @@
+//! Offline-first sync to a server of your own (`settings.sync`), made for phones that are
+//! offline half the time. Every change is recorded as an operation in a queue in the
+//! database (`sync_ops`) and sent once the server can be reached; nothing in the app waits
+//! on the network, and the queue survives restarts.
+//!
+//! - Operations are found by comparing the data with what was queued before: each habit's
+//!   definition and each day's state (done, value, note, lapses) has a fingerprint in
+//!   `sync_state`. So a change from anywhere (the webview, a widget, the local API) becomes
+//!   an op, and turning sync on queues everything once.
+//! - An op carries the whole state of what it covers, so applying it twice does no harm. A
+//!   newer change to something whose op is still waiting replaces that op in place: it
+//!   keeps its place in the queue and gets a new id.
+//! - Ops are sent oldest first in batches of [`BATCH`], one batch at a time, and only
+//!   dropped once the server has acknowledged them. The server dedupes on `id`, so a batch
+//!   sent again after a lost reply is harmless.
+//! - A failed send is retried after [`RETRY_DELAYS`], and right away after the next
+//!   change, on resume, or on `sync_now`.
+//!
+//! The server protocol, JSON with `Authorization: Bearer <token>` (`sync.token` in the
+//! keychain):
+//!
+//! - `POST /v1/sync/ops` with `{ deviceId, ops: [{ id, seq, recordedAt, type, ... }] }`,
+//!   where `type` is `upsertHabit` (`habit`, without its history), `deleteHabit`
+//!   (`habitId`) or `setDay` (`habitId`, `date`, `done`, `value`, `note`, `lapses`).
+//!   Within a device, `seq` only goes up.
+//! - A 2xx reply acknowledges the whole batch. `{ rejected: [{ id, error }] }` in it names
+//!   ops the server refused; they're dropped and listed in `get_sync_status`.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::features::{self, Feature};
+use crate::model::Habit;
+use crate::settings::{generate_token, SettingsStore};
+use crate::storage::{PendingOp, QueuedOp, Storage};
+
+/// Ops per request.
+pub const BATCH: u32 = 100;
+/// Waits between failed sends; the last one repeats.
+pub const RETRY_DELAYS: &[Duration] = &[
+    Duration::from_secs(5),
+    Duration::from_secs(30),
+    Duration::from_secs(120),
+    Duration::from_secs(600),
+];
+/// Refused ops kept for `get_sync_status`.
+const MAX_REJECTED: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedOp {
+    pub id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStatus {
+    pub enabled: bool,
+    /// Ops waiting to be sent.
+    pub pending: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_synced_at: Option<String>,
+    /// Why the last send failed; cleared by the next one that works.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    /// Failed sends in a row.
+    pub failures: u32,
+    /// The most recent ops the server refused, newest last.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rejected: Vec<RejectedOp>,
+}
+
+/// Wakes the sender and keeps what `get_sync_status` reports.
+#[derive(Default)]
+pub struct SyncQueue {
+    status: Mutex<SyncStatus>,
+    wake: Notify,
+}
+
+impl SyncQueue {
+    fn status(&self) -> std::sync::MutexGuard<'_, SyncStatus> {
+        self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Lowercase hex SHA-256.
+fn fingerprint(value: &serde_json::Value) -> String {
+    Sha256::digest(value.to_string().as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// The habit without its history, which goes in `setDay` ops. The cover is a local file.
+fn definition(habit: &Habit) -> serde_json::Value {
+    let mut habit = habit.clone();
+    habit.logs.clear();
+    habit.values.clear();
+    habit.notes.clear();
+    habit.lapses.clear();
+    habit.backfilled.clear();
+    habit.cover = None;
+    habit.paused_until = None;
+    for item in &mut habit.checklist {
+        item.done.clear();
+    }
+    serde_json::to_value(habit).unwrap_or_default()
+}
+
+fn day(habit: &Habit, date: &str) -> serde_json::Value {
+    let lapses: Vec<_> = habit.lapses.iter().filter(|l| l.date == date).collect();
+    serde_json::json!({
+        "type": "setDay",
+        "habitId": habit.id,
+        "date": date,
+        "done": habit.logs.get(date).copied().unwrap_or(false),
+        "value": habit.values.get(date),
+        "note": habit.notes.get(date),
+        "lapses": lapses,
+    })
+}
+
+fn queued(key: String, op: serde_json::Value, fingerprint: Option<String>) -> QueuedOp {
+    QueuedOp {
+        key,
+        id: generate_token()[..24].to_string(),
+        op: op.to_string(),
+        fingerprint,
+    }
+}
+
+/// What changed since `state`: the ops to queue, and the key prefixes of deleted habits'
+/// days.
+fn diff(habits: &[Habit], state: &HashMap<String, String>) -> (Vec<QueuedOp>, Vec<String>) {
+    let mut ops = Vec::new();
+    let mut purged = Vec::new();
+    // Day keys already queued, by habit.
+    let mut known_days: HashMap<&str, Vec<&str>> = HashMap::new();
+    for key in state.keys() {
+        if let Some((habit_id, date)) = key
+            .strip_prefix("day|")
+            .and_then(|rest| rest.split_once('|'))
+        {
+            known_days.entry(habit_id).or_default().push(date);
+        }
+    }
+
+    for habit in habits {
+        let key = format!("habit|{}", habit.id);
+        let op = serde_json::json!({ "type": "upsertHabit", "habit": definition(habit) });
+        let print = fingerprint(&op);
+        if state.get(&key) != Some(&print) {
+            ops.push(queued(key, op, Some(print)));
+        }
+
+        let dates: BTreeSet<&str> = habit
+            .logs
+            .iter()
+            .filter(|(_, done)| **done)
+            .map(|(d, _)| d.as_str())
+            .chain(habit.values.keys().map(String::as_str))
+            .chain(habit.notes.keys().map(String::as_str))
+            .chain(habit.lapses.iter().map(|l| l.date.as_str()))
+            .collect();
+        for date in &dates {
+            let key = format!("day|{}|{date}", habit.id);
+            let op = day(habit, date);
+            let print = fingerprint(&op);
+            if state.get(&key) != Some(&print) {
+                ops.push(queued(key, op, Some(print)));
+            }
+        }
+        // Days that had something and now have nothing.
+        for date in known_days.get(habit.id.as_str()).into_iter().flatten() {
+            if !dates.contains(date) {
+                let key = format!("day|{}|{date}", habit.id);
+                ops.push(queued(key, day(habit, date), None));
+            }
+        }
+    }
+
+    let ids: BTreeSet<&str> = habits.iter().map(|h| h.id.as_str()).collect();
+    for key in state.keys() {
+        let Some(habit_id) = key.strip_prefix("habit|") else {
+            continue;
+        };
+        if !ids.contains(habit_id) {
+            purged.push(format!("day|{habit_id}|"));
+            let op = serde_json::json!({ "type": "deleteHabit", "habitId": habit_id });
+            ops.push(queued(key.clone(), op, None));
+        }
+    }
+    (ops, purged)
+}
+
+fn enabled(app: &AppHandle) -> bool {
+    app.state::<SettingsStore>().get().sync.enabled && features::enabled(Feature::Sync)
+}
+
+/// Queues whatever changed since the last call. Does nothing while sync is off.
+pub fn record(app: &AppHandle) -> Result<usize, AppError> {
+    if !enabled(app) {
+        return Ok(0);
+    }
+    let storage = app.state::<Storage>();
+    let (ops, purged) = diff(&storage.list_habits()?, &storage.sync_fingerprints()?);
+    if !ops.is_empty() || !purged.is_empty() {
+        storage.queue_sync_ops(&ops, &purged)?;
+    }
+    Ok(ops.len())
+}
+
+fn record_logged(app: &AppHandle) {
+    match record(app) {
+        Ok(0) => {}
+        Ok(_) => app.state::<SyncQueue>().wake.notify_one(),
+        Err(e) => log::warn!("sync: failed to queue changes: {e}"),
+    }
+}
+
+/// Has the sender try again now, for a changed server or token, or on resume.
+pub fn wake(app: &AppHandle) {
+    record_logged(app);
+    app.state::<SyncQueue>().wake.notify_one();
+}
+
+#[derive(Deserialize, Default)]
+struct Reply {
+    #[serde(default)]
+    rejected: Vec<RejectedOp>,
+}
+
+fn wire(op: &PendingOp) -> serde_json::Value {
+    let mut body: serde_json::Value = serde_json::from_str(&op.op).unwrap_or_default();
+    if let Some(fields) = body.as_object_mut() {
+        fields.insert("id".into(), op.id.clone().into());
+        fields.insert("seq".into(), op.seq.into());
+        fields.insert("recordedAt".into(), op.recorded_at.clone().into());
+    }
+    body
+}
+
+/// The device id, made up the first time.
+pub(crate) fn device_id(app: &AppHandle) -> Result<String, AppError> {
+    let settings = app.state::<SettingsStore>();
+    let id = settings.get().sync.device_id;
+    if !id.is_empty() {
+        return Ok(id);
+    }
+    let id = generate_token()[..16].to_string();
+    settings.update(|s| s.sync.device_id = id.clone())?;
+    Ok(id)
+}
+
+/// Sends everything waiting, a batch at a time.
+async fn send(app: &AppHandle, client: &reqwest::Client) -> Result<(), String> {
+    let sync = app.state::<SettingsStore>().get().sync;
+    if !enabled(app) {
+        return Ok(());
+    }
+    let server = sync.server_url.trim().trim_end_matches('/');
+    if server.is_empty() {
+        return Err("no sync server is set".into());
+    }
+    let token = crate::secrets::get(crate::secrets::SYNC_TOKEN)?
+        .ok_or("no sync token is stored; set sync.token")?;
+    let device = device_id(app).map_err(|e| e.to_string())?;
+    let url = format!("{server}/v1/sync/ops");
+    let storage = app.state::<Storage>();
+    loop {
+        let batch = storage.pending_sync_ops(BATCH).map_err(|e| e.to_string())?;
+        if batch.is_empty() {
+            return Ok(());
+        }
+        let body = serde_json::json!({
+            "deviceId": device,
+            "ops": batch.iter().map(wire).collect::<Vec<_>>(),
+        });
+        let response = client
+            .post(&url)
+            .bearer_auth(&token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| format!("couldn't reach the sync server: {e}"))?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("the sync server answered {status}"));
+        }
+        let reply: Reply = response.json().await.unwrap_or_default();
+        let ids: Vec<String> = batch.into_iter().map(|op| op.id).collect();
+        storage.ack_sync_ops(&ids).map_err(|e| e.to_string())?;
+
+        let queue = app.state::<SyncQueue>();
+        let mut status = queue.status();
+        for rejected in reply.rejected {
+            log::warn!(
+                "sync: the server refused op {}: {}",
+                rejected.id,
+                rejected.error
+            );
+            status.rejected.push(rejected);
+        }
+        let excess = status.rejected.len().saturating_sub(MAX_REJECTED);
+        status.rejected.drain(..excess);
+        status.last_synced_at = Some(chrono::Utc::now().to_rfc3339());
+    }
+}
+
+/// One round of sending, with the outcome kept for `get_sync_status`. Returns whether it
+/// failed.
+async fn attempt(app: &AppHandle, client: &reqwest::Client) -> bool {
+    let result = send(app, client).await;
+    let queue = app.state::<SyncQueue>();
+    let mut status = queue.status();
+    match result {
+        Ok(()) => {
+            status.last_error = None;
+            status.failures = 0;
+            false
+        }
+        Err(e) => {
+            log::warn!("sync: {e}");
+            status.last_error = Some(e);
+            status.failures += 1;
+            true
+        }
+    }
+}
+
+pub fn status(app: &AppHandle) -> Result<SyncStatus, AppError> {
+    let mut status = app.state::<SyncQueue>().status().clone();
+    status.enabled = enabled(app);
+    status.pending = app.state::<Storage>().count_sync_ops()?;
+    Ok(status)
+}
+
+/// Queues changes as they happen and sends them whenever there's something to send.
+pub fn start(app: &AppHandle) {
+    record_logged(app);
+
+    let a = app.clone();
+    let mut data = app.state::<EventBus>().watch_data();
+    tauri::async_runtime::spawn(async move {
+        while data.changed().await.is_ok() {
+            let b = a.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || record_logged(&b)).await;
+        }
+    });
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let Ok(client) = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+        else {
+            log::error!("sync: failed to build the HTTP client");
+            return;
+        };
+        loop {
+            crate::network::wait_online(&app).await;
+            let failed = attempt(&app, &client).await;
+            let queue = app.state::<SyncQueue>();
+            let failures = queue.status().failures as usize;
+            if failed {
+                crate::network::check_now(&app);
+                let delay = RETRY_DELAYS[failures.min(RETRY_DELAYS.len()) - 1];
+                tokio::select! {
+                    _ = queue.wake.notified() => {}
+                    _ = tokio::time::sleep(delay) => {}
+                }
+            } else {
+                queue.wake.notified().await;
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_sync_status(app: AppHandle) -> Result<SyncStatus, AppError> {
+    status(&app)
+}
+
+/// Queues what changed and sends everything waiting, then reports how that went.
+#[tauri::command]
+pub async fn sync_now(app: AppHandle) -> Result<SyncStatus, AppError> {
+    let a = app.clone();
+    tauri::async_runtime::spawn_blocking(move || record(&a))
+        .await
+        .map_err(|e| AppError::Failed(e.to_string()))??;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .map_err(|e| AppError::Failed(e.to_string()))?;
+    attempt(&app, &client).await;
+    status(&app)
+}
+
+/// Empties the queue and forgets what was sent, so everything is queued again from
+/// scratch. For a new or wiped server.
+#[tauri::command]
+pub fn reset_sync(app: AppHandle) -> Result<SyncStatus, AppError> {
+    app.state::<Storage>().reset_sync()?;
+    wake(&app);
+    status(&app)
+}
@@ -0,0 +1,129 @@
+//! Free-form tags on habits ("health", "career", "family") and on individual check-ins.
+//!
+//! Tags are stored cleaned up and lowercased (`text.rs`), so "Health" and "health " are the
+//! same tag. Habits carry theirs in `Habit::tags` and round-trip through the webview like
+//! any other field; check-in tags are set when the value is logged.
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::Habit;
+use crate::stats::{self, today, StatsSummary};
+use crate::storage::{CheckIn, Storage, TagUsage};
+
+/// Cleaned up and lowercased (`text.rs`), without blanks or duplicates, in alphabetical
+/// order.
+pub fn normalize(tags: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut tags: Vec<String> = tags
+        .into_iter()
+        .filter_map(|t| crate::text::tag(&t))
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+fn matches(habit: &Habit, tag: &str) -> bool {
+    crate::text::tag(tag).is_some_and(|tag| habit.tags.contains(&tag))
+}
+
+/// Keeps only the habits tagged `tag`.
+pub fn with_tag(habits: Vec<Habit>, tag: &str) -> Vec<Habit> {
+    habits.into_iter().filter(|h| matches(h, tag)).collect()
+}
+
+/// Tags in use starting with `prefix`, most used first.
+pub fn suggest(
+    storage: &Storage,
+    prefix: Option<&str>,
+    limit: Option<u32>,
+) -> Result<Vec<TagUsage>, String> {
+    let prefix = crate::text::fold(prefix.unwrap_or(""));
+    Ok(storage
+        .list_tags()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|t| t.tag.starts_with(&prefix))
+        .take(limit.unwrap_or(20) as usize)
+        .collect())
+}
+
+/// Replaces a habit's tags.
+pub fn set_for_habit(
+    storage: &Storage,
+    habit_id: &str,
+    tags: Vec<String>,
+) -> Result<Habit, AppError> {
+    if !storage.set_habit_tags(habit_id, &normalize(tags))? {
+        return Err(AppError::not_found("habit", habit_id));
+    }
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+/// Everything carrying a tag.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tagged {
+    pub tag: String,
+    pub habits: Vec<Habit>,
+    pub check_ins: Vec<CheckIn>,
+}
+
+pub fn tagged(storage: &Storage, tag: &str) -> Result<Tagged, String> {
+    let tag = tag.trim().to_lowercase();
+    let habits = storage.list_habits().map_err(|e| e.to_string())?;
+    Ok(Tagged {
+        habits: with_tag(habits, &tag),
+        check_ins: storage
+            .list_check_ins_by_tag(&tag)
+            .map_err(|e| e.to_string())?,
+        tag,
+    })
+}
+
+/// Autocomplete: tags starting with `prefix` (all tags without one), most used first.
+#[tauri::command]
+pub fn list_tags(
+    storage: State<'_, Storage>,
+    prefix: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<TagUsage>, AppError> {
+    Ok(suggest(&storage, prefix.as_deref(), limit)?)
+}
+
+#[tauri::command]
+pub fn set_habit_tags(
+    app: AppHandle,
+    habit_id: String,
+    tags: Vec<String>,
+) -> Result<Habit, AppError> {
+    let habit = set_for_habit(&app.state::<Storage>(), &habit_id, tags)?;
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(&app);
+    Ok(habit)
+}
+
+#[tauri::command]
+pub fn list_by_tag(storage: State<'_, Storage>, tag: String) -> Result<Tagged, AppError> {
+    Ok(tagged(&storage, &tag)?)
+}
+
+/// Stats over just the habits tagged `tag`.
+pub fn summary(storage: &Storage, tag: &str) -> Result<StatsSummary, AppError> {
+    let habits = storage.list_habits()?;
+    let categories = storage.list_categories()?;
+    Ok(stats::summarize(
+        &with_tag(habits, tag),
+        &categories,
+        today(),
+    ))
+}
+
+#[tauri::command]
+pub fn get_tag_stats(storage: State<'_, Storage>, tag: String) -> Result<StatsSummary, AppError> {
+    summary(&storage, &tag)
+}
@@ -0,0 +1,325 @@
+//! Anonymous usage counters, off unless `telemetry.enabled` is turned on.
+//!
+//! While it's on, the backend counts:
+//!
+//! - commands: how often each is called and how long it takes. Commands that aren't
+//!   `async` run inside the handler, so that's their whole run; `async` ones only their
+//!   dispatch.
+//! - features used outside the webview: the widget, the quick settings tile, the watch,
+//!   NFC tags, the share sheet and Shortcuts.
+//! - sessions, and how many ended in a crash: a session that doesn't get to exit cleanly
+//!   leaves `telemetry.session` behind, and the next launch counts it.
+//!
+//! That's all: no habit names or data and no paths. The only id is a random one made when
+//! telemetry is turned on, and it's thrown away with the counters when it's turned off.
+//! The counters are kept in `telemetry.json` and sent once a day, to
+//! `HABITFLOW_TELEMETRY_URL` as set at build time; builds without it never send anything.
+//! `preview_telemetry_payload` returns the exact body of the next send.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::settings::{generate_token, SettingsStore};
+
+const COUNTERS_FILE: &str = "telemetry.json";
+const SESSION_FILE: &str = "telemetry.session";
+const SEND_EVERY: Duration = Duration::from_secs(24 * 60 * 60);
+/// How often the counters are saved, so a crash loses little.
+const SAVE_EVERY: Duration = Duration::from_secs(10 * 60);
+const SCHEMA: u32 = 1;
+/// Where counters are sent, set when building a release.
+const TELEMETRY_URL: Option<&str> = option_env!("HABITFLOW_TELEMETRY_URL");
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Latency {
+    pub count: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct Counters {
+    /// When counting started for this batch.
+    since: Option<String>,
+    sessions: u32,
+    crashed_sessions: u32,
+    features: BTreeMap<String, u64>,
+    commands: BTreeMap<String, Latency>,
+}
+
+impl Counters {
+    fn is_empty(&self) -> bool {
+        self.sessions == 0 && self.features.is_empty() && self.commands.is_empty()
+    }
+
+    /// Takes out what was `sent`, leaving what was counted since.
+    fn subtract(&mut self, sent: &Counters) {
+        for (feature, n) in &sent.features {
+            if let Some(count) = self.features.get_mut(feature) {
+                *count = count.saturating_sub(*n);
+            }
+        }
+        self.features.retain(|_, n| *n > 0);
+        for (command, l) in &sent.commands {
+            if let Some(latency) = self.commands.get_mut(command) {
+                latency.count = latency.count.saturating_sub(l.count);
+                latency.total_ms = latency.total_ms.saturating_sub(l.total_ms);
+            }
+        }
+        self.commands.retain(|_, l| l.count > 0);
+        self.sessions = self.sessions.saturating_sub(sent.sessions);
+        self.crashed_sessions = self.crashed_sessions.saturating_sub(sent.crashed_sessions);
+        self.since = Some(chrono::Utc::now().to_rfc3339());
+    }
+}
+
+#[derive(Default)]
+pub struct Telemetry {
+    /// Mirrors `telemetry.enabled`, so commands don't read the settings to find out.
+    enabled: AtomicBool,
+    counters: Mutex<Counters>,
+    dir: OnceLock<PathBuf>,
+}
+
+impl Telemetry {
+    fn counters(&self) -> std::sync::MutexGuard<'_, Counters> {
+        self.counters.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    fn record(&self, command: &str, elapsed: Duration) {
+        let ms = elapsed.as_millis() as u64;
+        let mut counters = self.counters();
+        let latency = counters.commands.entry(command.to_string()).or_default();
+        latency.count += 1;
+        latency.total_ms += ms;
+        latency.max_ms = latency.max_ms.max(ms);
+    }
+
+    fn save(&self) {
+        let Some(dir) = self.dir.get() else {
+            return;
+        };
+        let json = serde_json::to_vec(&*self.counters()).unwrap_or_default();
+        if let Err(e) = crate::widgets::write_atomic(&dir.join(COUNTERS_FILE), &json) {
+            log::warn!("telemetry: failed to save the counters: {e}");
+        }
+    }
+
+    /// Forgets everything counted so far, on disk too.
+    fn clear(&self) {
+        *self.counters() = Counters::default();
+        if let Some(dir) = self.dir.get() {
+            let _ = std::fs::remove_file(dir.join(COUNTERS_FILE));
+            let _ = std::fs::remove_file(dir.join(SESSION_FILE));
+        }
+    }
+}
+
+/// Counts one use of a feature outside the webview, such as `"widget"`.
+pub fn count(app: &AppHandle, feature: &'static str) {
+    let telemetry = app.state::<Telemetry>();
+    if telemetry.enabled() {
+        *telemetry
+            .counters()
+            .features
+            .entry(feature.to_string())
+            .or_default() += 1;
+    }
+}
+
+/// Times every command while telemetry is on.
+pub fn measured(
+    handler: impl Fn(Invoke) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        let app = invoke.message.webview_ref().app_handle().clone();
+        let telemetry = app.state::<Telemetry>();
+        if !telemetry.enabled() {
+            return handler(invoke);
+        }
+        let command = invoke.message.command().to_string();
+        let started = Instant::now();
+        let handled = handler(invoke);
+        telemetry.record(&command, started.elapsed());
+        handled
+    }
+}
+
+/// Follows `telemetry.enabled`: turning it on makes a new install id, turning it off
+/// throws the id and the counters away.
+pub fn apply(app: &AppHandle) {
+    let telemetry = app.state::<Telemetry>();
+    let settings = app.state::<SettingsStore>();
+    let current = settings.get().telemetry;
+    let was = telemetry.enabled.swap(current.enabled, Ordering::Relaxed);
+    if current.enabled && current.install_id.is_empty() {
+        let id = generate_token()[..32].to_string();
+        if let Err(e) = settings.update(|s| s.telemetry.install_id = id) {
+            log::warn!("telemetry: failed to save the install id: {e}");
+        }
+    }
+    if !current.enabled && (was || !current.install_id.is_empty()) {
+        telemetry.clear();
+        if let Err(e) = settings.update(|s| s.telemetry.install_id.clear()) {
+            log::warn!("telemetry: failed to forget the install id: {e}");
+        }
+    }
+    if current.enabled && !was {
+        let mut counters = telemetry.counters();
+        counters
+            .since
+            .get_or_insert_with(|| chrono::Utc::now().to_rfc3339());
+    }
+}
+
+fn payload_of(app: &AppHandle, counters: &Counters) -> serde_json::Value {
+    let settings = app.state::<SettingsStore>().get().telemetry;
+    let commands: BTreeMap<_, _> = counters
+        .commands
+        .iter()
+        .map(|(name, l)| {
+            let mean = l.total_ms.checked_div(l.count).unwrap_or(0);
+            (
+                name.clone(),
+                serde_json::json!({ "count": l.count, "meanMs": mean, "maxMs": l.max_ms }),
+            )
+        })
+        .collect();
+    serde_json::json!({
+        "schema": SCHEMA,
+        "installId": settings.install_id,
+        "appVersion": app.package_info().version.to_string(),
+        "os": std::env::consts::OS,
+        "arch": std::env::consts::ARCH,
+        "from": counters.since,
+        "to": chrono::Utc::now().to_rfc3339(),
+        "sessions": {
+            "total": counters.sessions,
+            "crashed": counters.crashed_sessions,
+            "crashFree": counters.sessions.saturating_sub(counters.crashed_sessions),
+        },
+        "features": counters.features,
+        "commands": commands,
+    })
+}
+
+/// What the next send would carry.
+pub fn payload(app: &AppHandle) -> serde_json::Value {
+    let counters = app.state::<Telemetry>().counters().clone();
+    payload_of(app, &counters)
+}
+
+async fn send(app: &AppHandle, client: &reqwest::Client) -> Result<(), AppError> {
+    let telemetry = app.state::<Telemetry>();
+    let Some(url) = TELEMETRY_URL else {
+        return Ok(());
+    };
+    let sent = telemetry.counters().clone();
+    if !telemetry.enabled() || sent.is_empty() {
+        return Ok(());
+    }
+    let response = client
+        .post(url)
+        .json(&payload_of(app, &sent))
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(AppError::Failed(format!(
+            "the telemetry server answered {}",
+            response.status()
+        )));
+    }
+    // Anything counted while the request was out goes in the next batch.
+    telemetry.counters().subtract(&sent);
+    telemetry.save();
+    Ok(())
+}
+
+/// Loads the counters, counts this session (and the last one, if it crashed), and sends
+/// and saves them on a timer.
+pub fn start(app: &AppHandle) {
+    let telemetry = app.state::<Telemetry>();
+    let Ok(dir) = app.path().app_data_dir() else {
+        return;
+    };
+    if let Ok(bytes) = std::fs::read(dir.join(COUNTERS_FILE)) {
+        if let Ok(counters) = serde_json::from_slice(&bytes) {
+            *telemetry.counters() = counters;
+        }
+    }
+    let session = dir.join(SESSION_FILE);
+    let _ = telemetry.dir.set(dir);
+    apply(app);
+    if telemetry.enabled() {
+        let mut counters = telemetry.counters();
+        counters.sessions += 1;
+        if session.exists() {
+            counters.crashed_sessions += 1;
+        }
+        drop(counters);
+        let _ = std::fs::write(&session, b"");
+        telemetry.save();
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .unwrap_or_default();
+        let mut saves = tokio::time::interval(SAVE_EVERY);
+        let mut sends = tokio::time::interval(SEND_EVERY);
+        loop {
+            let telemetry = app.state::<Telemetry>();
+            tokio::select! {
+                _ = saves.tick() => {
+                    if telemetry.enabled() {
+                        telemetry.save();
+                    }
+                }
+                // Offline, the counters go with the next send instead.
+                _ = sends.tick() => {
+                    if !crate::network::is_online(&app) {
+                        continue;
+                    }
+                    if let Err(e) = send(&app, &client).await {
+                        log::warn!("telemetry: {e}");
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// Saves the counters and marks the session as ended cleanly.
+pub fn end_session(app: &AppHandle) {
+    let telemetry = app.state::<Telemetry>();
+    if !telemetry.enabled() {
+        return;
+    }
+    telemetry.save();
+    if let Some(dir) = telemetry.dir.get() {
+        let _ = std::fs::remove_file(dir.join(SESSION_FILE));
+    }
+}
+
+/// Exactly what would be sent next, for the settings screen to show before you opt in.
+/// The counters are only filled in while telemetry is on.
+#[tauri::command]
+pub fn preview_telemetry_payload(app: AppHandle) -> serde_json::Value {
+    payload(&app)
+}
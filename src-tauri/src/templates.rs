@@ -0,0 +1,417 @@
+//! Habit templates: a built-in catalog for onboarding plus the user's own, kept in
+//! settings. `create_from_template` turns one into a habit in a single step.
+//!
+//! A template can be shared as a link (`habitflow:template:` and the template as base64url
+//! JSON), shown as a QR code for a friend to scan. `import_shared_template` saves one.
+
+use base64::Engine;
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::error::AppError;
+use crate::model::{ChecklistItem, Habit, HabitFrequency, HabitKind, HabitTarget};
+use crate::qr::{QrImage, QrLevel};
+use crate::settings::{generate_token, HabitTemplate, SettingsStore};
+use crate::storage::Storage;
+
+pub const LINK_PREFIX: &str = "habitflow:template:";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateShare {
+    pub link: String,
+    pub qr: QrImage,
+}
+
+fn template(
+    id: &str,
+    name: &str,
+    category: &str,
+    color: &str,
+    icon: &str,
+    frequency: (&str, u32),
+    reminder_time: Option<&str>,
+) -> HabitTemplate {
+    HabitTemplate {
+        id: id.into(),
+        name: name.into(),
+        description: None,
+        category: category.into(),
+        color: color.into(),
+        icon: Some(icon.into()),
+        frequency: HabitFrequency {
+            kind: frequency.0.into(),
+            goal: frequency.1,
+        },
+        reminder_time: reminder_time.map(Into::into),
+        kind: HabitKind::Build,
+        target: None,
+        checklist: Vec::new(),
+        tags: Vec::new(),
+        builtin: true,
+    }
+}
+
+/// The catalog shipped with the app. Icons are names from `AVAILABLE_ICONS` in
+/// `HabitIcon.tsx`; colors match the form's predefined categories.
+pub fn builtin() -> Vec<HabitTemplate> {
+    let daily = ("daily", 1);
+    vec![
+        HabitTemplate {
+            description: Some("Ten quiet minutes to start the day".into()),
+            ..template(
+                "builtin:meditate",
+                "Morning Meditation",
+                "Mindfulness",
+                "#8b5cf6",
+                "brain",
+                daily,
+                Some("07:30"),
+            )
+        },
+        template(
+            "builtin:read",
+            "Read 30 Minutes",
+            "Growth",
+            "#6366f1",
+            "book",
+            daily,
+            Some("21:00"),
+        ),
+        template(
+            "builtin:workout",
+            "Workout",
+            "Health",
+            "#10b981",
+            "dumbbell",
+            ("weekly", 3),
+            Some("18:00"),
+        ),
+        HabitTemplate {
+            target: Some(HabitTarget {
+                amount: 2000.0,
+                unit: "ml".into(),
+            }),
+            ..template(
+                "builtin:water",
+                "Drink Water",
+                "Health",
+                "#10b981",
+                "water",
+                daily,
+                Some("10:00"),
+            )
+        },
+        HabitTemplate {
+            target: Some(HabitTarget {
+                amount: 10000.0,
+                unit: "steps".into(),
+            }),
+            ..template(
+                "builtin:steps",
+                "Walk 10k Steps",
+                "Health",
+                "#10b981",
+                "activity",
+                daily,
+                None,
+            )
+        },
+        template(
+            "builtin:journal",
+            "Journal",
+            "Mindfulness",
+            "#8b5cf6",
+            "pen",
+            daily,
+            Some("22:00"),
+        ),
+        template(
+            "builtin:sleep",
+            "In Bed by 11",
+            "Health",
+            "#10b981",
+            "sleep",
+            daily,
+            Some("22:30"),
+        ),
+        HabitTemplate {
+            checklist: vec!["Make bed".into(), "Stretch".into(), "Journal".into()],
+            ..template(
+                "builtin:morning-routine",
+                "Morning Routine",
+                "Mindfulness",
+                "#8b5cf6",
+                "sun",
+                daily,
+                Some("07:00"),
+            )
+        },
+        HabitTemplate {
+            kind: HabitKind::Avoid,
+            ..template(
+                "builtin:no-sugar",
+                "No Sugar",
+                "Health",
+                "#10b981",
+                "food",
+                daily,
+                None,
+            )
+        },
+        template(
+            "builtin:budget",
+            "Review Budget",
+            "Finance",
+            "#f59e0b",
+            "money",
+            ("weekly", 1),
+            Some("19:00"),
+        ),
+        template(
+            "builtin:call-family",
+            "Call Family",
+            "Social",
+            "#f43f5e",
+            "heart",
+            ("weekly", 1),
+            None,
+        ),
+        template(
+            "builtin:practice",
+            "Practice an Instrument",
+            "Growth",
+            "#6366f1",
+            "music",
+            ("interval", 2),
+            Some("17:00"),
+        ),
+    ]
+}
+
+/// Built-ins first, then the user's own in the order they were saved.
+pub fn list(settings: &SettingsStore) -> Vec<HabitTemplate> {
+    let mut templates = builtin();
+    templates.extend(settings.get().templates);
+    templates
+}
+
+pub fn find(settings: &SettingsStore, id: &str) -> Result<HabitTemplate, String> {
+    list(settings)
+        .into_iter()
+        .find(|t| t.id == id)
+        .ok_or_else(|| format!("template not found: {id}"))
+}
+
+/// Saves a user template under a fresh id.
+pub fn add(settings: &SettingsStore, template: HabitTemplate) -> Result<HabitTemplate, AppError> {
+    let template = HabitTemplate {
+        id: generate_token()[..16].to_string(),
+        name: crate::text::habit_name(&template.name)?,
+        tags: crate::tags::normalize(template.tags.clone()),
+        builtin: false,
+        ..template
+    };
+    settings.update(|s| s.templates.push(template.clone()))?;
+    Ok(template)
+}
+
+/// A template that recreates `habit`'s setup, without its history.
+pub fn from_habit(habit: &Habit, name: Option<String>) -> HabitTemplate {
+    HabitTemplate {
+        id: String::new(),
+        name: name
+            .filter(|n| !n.trim().is_empty())
+            .unwrap_or_else(|| habit.name.clone()),
+        description: habit.description.clone(),
+        category: habit.category.clone(),
+        color: habit.color.clone(),
+        icon: habit.icon.clone(),
+        frequency: habit.frequency.clone(),
+        reminder_time: habit.reminder_time.clone(),
+        kind: habit.kind,
+        target: habit.target.clone(),
+        checklist: habit.checklist.iter().map(|i| i.name.clone()).collect(),
+        tags: habit.tags.clone(),
+        builtin: false,
+    }
+}
+
+pub fn remove(settings: &SettingsStore, id: &str) -> Result<(), String> {
+    if builtin().iter().any(|t| t.id == id) {
+        return Err("built-in templates can't be removed".into());
+    }
+    settings
+        .update(|s| s.templates.retain(|t| t.id != id))
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// `template` as a link another install can import. The id stays behind.
+pub fn share_link(template: &HabitTemplate) -> String {
+    let template = HabitTemplate {
+        id: String::new(),
+        builtin: false,
+        ..template.clone()
+    };
+    let json = serde_json::to_vec(&template).unwrap_or_default();
+    format!(
+        "{LINK_PREFIX}{}",
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(json)
+    )
+}
+
+/// The template in a link from [`share_link`].
+pub fn from_link(link: &str) -> Result<HabitTemplate, String> {
+    let data = link
+        .trim()
+        .strip_prefix(LINK_PREFIX)
+        .ok_or("that isn't a HabitFlow template link")?;
+    let json = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(data.trim_end_matches('='))
+        .map_err(|_| "the template link is damaged")?;
+    serde_json::from_slice(&json).map_err(|e| format!("the template link is damaged: {e}"))
+}
+
+/// A new habit from `template`; `name` and `reminder_time` override the template's.
+pub fn instantiate(
+    template: &HabitTemplate,
+    name: Option<String>,
+    reminder_time: Option<String>,
+) -> Habit {
+    Habit {
+        id: crate::habits::new_id(),
+        name: name
+            .filter(|n| !n.trim().is_empty())
+            .unwrap_or_else(|| template.name.clone()),
+        description: template.description.clone(),
+        color: template.color.clone(),
+        icon: template.icon.clone(),
+        category: template.category.clone(),
+        frequency: template.frequency.clone(),
+        reminder_time: reminder_time.or_else(|| template.reminder_time.clone()),
+        window: None,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        logs: Default::default(),
+        archived: false,
+        archived_at: None,
+        pinned: false,
+        difficulty: Default::default(),
+        emoji: None,
+        cover: None,
+        times_per_day: 1,
+        tags: template.tags.clone(),
+        kind: template.kind,
+        lapses: Vec::new(),
+        depends_on: Vec::new(),
+        checklist: template
+            .checklist
+            .iter()
+            .map(|name| ChecklistItem {
+                id: generate_token(),
+                name: name.clone(),
+                done: Default::default(),
+            })
+            .collect(),
+        target: template.target.clone(),
+        values: Default::default(),
+        notes: Default::default(),
+        off_periods: Vec::new(),
+        paused_until: None,
+        frozen: Default::default(),
+        revision: 0,
+        weekday_reminders: Default::default(),
+        backfilled: Default::default(),
+    }
+}
+
+/// Saves `habit_id`'s setup as a template named `name`, or after the habit.
+pub fn save_habit(
+    settings: &SettingsStore,
+    storage: &Storage,
+    habit_id: &str,
+    name: Option<String>,
+) -> Result<HabitTemplate, AppError> {
+    let habit = storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+    add(settings, from_habit(&habit, name))
+}
+
+#[tauri::command]
+pub fn list_templates(settings: State<'_, SettingsStore>) -> Vec<HabitTemplate> {
+    list(&settings)
+}
+
+#[tauri::command]
+pub fn add_template(
+    settings: State<'_, SettingsStore>,
+    template: HabitTemplate,
+) -> Result<HabitTemplate, AppError> {
+    add(&settings, template)
+}
+
+/// Saves an existing habit's setup as a template.
+#[tauri::command]
+pub fn save_habit_as_template(
+    settings: State<'_, SettingsStore>,
+    storage: State<'_, Storage>,
+    habit_id: String,
+    name: Option<String>,
+) -> Result<HabitTemplate, AppError> {
+    save_habit(&settings, &storage, &habit_id, name)
+}
+
+#[tauri::command]
+pub fn remove_template(settings: State<'_, SettingsStore>, id: String) -> Result<(), AppError> {
+    Ok(remove(&settings, &id)?)
+}
+
+/// A template's link and a QR code of it, for sharing with friends.
+#[tauri::command]
+pub fn share_template(
+    settings: State<'_, SettingsStore>,
+    template_id: String,
+    scale: Option<u32>,
+) -> Result<TemplateShare, AppError> {
+    share(&settings, &template_id, scale)
+}
+
+/// A template's link, and a QR code of it with `scale` pixels per module.
+pub fn share(
+    settings: &SettingsStore,
+    template_id: &str,
+    scale: Option<u32>,
+) -> Result<TemplateShare, AppError> {
+    let link = share_link(&find(settings, template_id)?);
+    // Template links are long; Low keeps the code small enough to scan off a phone.
+    let qr = crate::qr::generate(&link, scale, QrLevel::Low)?;
+    Ok(TemplateShare { link, qr })
+}
+
+/// Saves the template in a link someone shared as one of the user's own.
+#[tauri::command]
+pub fn import_shared_template(
+    settings: State<'_, SettingsStore>,
+    link: String,
+) -> Result<HabitTemplate, AppError> {
+    import_link(&settings, &link)
+}
+
+/// Saves the template in `link` (see [`share_link`]).
+pub fn import_link(settings: &SettingsStore, link: &str) -> Result<HabitTemplate, AppError> {
+    add(settings, from_link(link)?)
+}
+
+/// Creates a habit from a template and pushes it to the webview.
+#[tauri::command]
+pub fn create_from_template(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    template_id: String,
+    name: Option<String>,
+    reminder_time: Option<String>,
+) -> Result<Habit, AppError> {
+    let template = find(&settings, &template_id)?;
+    crate::habits::create(&app, instantiate(&template, name, reminder_time))
+}
@@ -0,0 +1,207 @@
+//! The rules for habit names and tags, the same whichever way they come in: the webview,
+//! imports, the CLI, the local API or a template link.
+//!
+//! Text is put in Unicode NFC, so an "é" typed as one character and one pasted as "e" plus
+//! an accent are the same. Control characters and the invisible direction marks that can
+//! make a name display differently from what it is are dropped, and runs of whitespace
+//! become one space. Lengths count what a person would call characters (graphemes), so a
+//! family emoji or a flag counts as one and is never cut in half. Names are compared
+//! ignoring case: two active habits can't be called "Read" and "read". Adding or renaming
+//! a habit to a name in use is refused; a sync, import or restore that would store one
+//! anyway numbers it instead ("read (2)"), since those can't be sent back to fix.
+
+use std::collections::{HashMap, HashSet};
+
+use icu_normalizer::ComposingNormalizerBorrowed;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::AppError;
+use crate::model::Habit;
+
+pub const MAX_NAME_LEN: usize = 100;
+pub const MAX_TAG_LEN: usize = 40;
+
+/// Direction overrides, embeddings, isolates and marks, plus zero-width characters that
+/// only hide things. The zero-width joiner stays, since emoji sequences need it.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{061C}'
+            | '\u{200B}'
+            | '\u{200E}'
+            | '\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{FEFF}'
+    )
+}
+
+/// NFC, without control or direction characters, whitespace collapsed and trimmed.
+pub fn clean(text: &str) -> String {
+    let text = ComposingNormalizerBorrowed::new_nfc().normalize(text);
+    let mut out = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = !out.is_empty();
+        } else if !c.is_control() && !is_invisible(c) {
+            if space {
+                out.push(' ');
+                space = false;
+            }
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// What names and tags are compared by: [`clean`] and lowercased.
+pub fn fold(text: &str) -> String {
+    clean(text).to_lowercase()
+}
+
+pub fn len(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// At most `max` graphemes of `text`.
+pub fn truncate(text: &str, max: usize) -> String {
+    match text.grapheme_indices(true).nth(max) {
+        Some((end, _)) => text[..end].trim_end().to_string(),
+        None => text.to_string(),
+    }
+}
+
+/// The name as stored, or why it can't be one.
+pub fn habit_name(name: &str) -> Result<String, AppError> {
+    let name = clean(name);
+    if name.is_empty() {
+        return Err(AppError::Invalid("name is required".into()));
+    }
+    if len(&name) > MAX_NAME_LEN {
+        return Err(AppError::Invalid(format!(
+            "names can be at most {MAX_NAME_LEN} characters"
+        )));
+    }
+    Ok(name)
+}
+
+/// The tag as stored, cut down to [`MAX_TAG_LEN`]; `None` if nothing is left.
+pub fn tag(tag: &str) -> Option<String> {
+    let tag = truncate(&fold(tag), MAX_TAG_LEN);
+    (!tag.is_empty()).then_some(tag)
+}
+
+/// Fails if an active habit other than `except` is already called `name`, ignoring case.
+pub fn check_unique(habits: &[Habit], name: &str, except: Option<&str>) -> Result<(), AppError> {
+    let key = fold(name);
+    match habits
+        .iter()
+        .filter(|h| !h.archived && Some(h.id.as_str()) != except)
+        .find(|h| fold(&h.name) == key)
+    {
+        Some(existing) => Err(AppError::Invalid(format!(
+            "a habit named \"{}\" already exists",
+            existing.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// `habit` with its name and tags as they'd be stored. Names that are too long are cut
+/// rather than refused, since snapshots and imports can't be sent back to fix.
+fn normalized(habit: &Habit) -> Option<Habit> {
+    let name = truncate(&clean(&habit.name), MAX_NAME_LEN);
+    let tags = crate::tags::normalize(habit.tags.iter().cloned());
+    if name == habit.name && tags == habit.tags {
+        return None;
+    }
+    Some(Habit {
+        name,
+        tags,
+        ..habit.clone()
+    })
+}
+
+/// `habits` with every name and tag normalized and no two active habits called the same,
+/// or `None` if that was already so. `before` is what's stored now (see
+/// [`number_duplicates`]).
+pub fn normalize_habits(habits: &[Habit], before: &HashMap<String, Habit>) -> Option<Vec<Habit>> {
+    let mut out = habits.to_vec();
+    let mut changed = false;
+    for habit in &mut out {
+        if let Some(fixed) = normalized(habit) {
+            *habit = fixed;
+            changed = true;
+        }
+    }
+    changed |= number_duplicates(&mut out, before);
+    changed.then_some(out)
+}
+
+/// Numbers active habits whose name is taken: "Run", "Run (2)". A habit that already had
+/// its name in `before` keeps it, so the one that's new or renamed is the one numbered.
+/// Returns whether any name changed.
+fn number_duplicates(habits: &mut [Habit], before: &HashMap<String, Habit>) -> bool {
+    let kept = |h: &Habit| {
+        before
+            .get(&h.id)
+            .is_some_and(|old| !old.archived && fold(&old.name) == fold(&h.name))
+    };
+    let (first, rest): (Vec<usize>, Vec<usize>) = (0..habits.len())
+        .filter(|&i| !habits[i].archived)
+        .partition(|&i| kept(&habits[i]));
+    let mut taken = HashSet::new();
+    let mut changed = false;
+    for i in first.into_iter().chain(rest) {
+        let name = &habits[i].name;
+        if taken.insert(fold(name)) {
+            continue;
+        }
+        let mut n = 2;
+        let numbered = loop {
+            let suffix = format!(" ({n})");
+            let candidate = format!("{}{suffix}", truncate(name, MAX_NAME_LEN - suffix.len()));
+            if !taken.contains(&fold(&candidate)) {
+                break candidate;
+            }
+            n += 1;
+        };
+        taken.insert(fold(&numbered));
+        habits[i].name = numbered;
+        changed = true;
+    }
+    changed
+}
+
+/// Fails if two active habits in `habits` have the same name, for writes of every habit
+/// whose sender can be told (`sync_habits` over `--rpc`).
+pub fn check_all_unique(habits: &[Habit]) -> Result<(), AppError> {
+    habits
+        .iter()
+        .filter(|h| !h.archived)
+        .try_for_each(|h| check_unique(habits, &clean(&h.name), Some(&h.id)))
+}
+
+/// The name as it would be stored, or why it can't be used. `habit_id` is the habit being
+/// renamed, if any.
+pub fn check_name(
+    storage: &crate::storage::Storage,
+    name: &str,
+    habit_id: Option<&str>,
+) -> Result<String, AppError> {
+    let name = habit_name(name)?;
+    check_unique(&storage.list_habits()?, &name, habit_id)?;
+    Ok(name)
+}
+
+/// Checks a name before the webview saves it (see [`check_name`]).
+#[tauri::command]
+pub fn check_habit_name(
+    storage: tauri::State<'_, crate::storage::Storage>,
+    name: String,
+    habit_id: Option<String>,
+) -> Result<String, AppError> {
+    check_name(&storage, &name, habit_id.as_deref())
+}
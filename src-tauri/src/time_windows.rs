@@ -0,0 +1,129 @@
+//! Time-of-day windows: "Morning pages" only counts between 05:00 and 11:00.
+//!
+//! Checking a habit off today outside its window is rejected (backfilling earlier days
+//! isn't, since the time of a past check-in isn't known). Once the window has closed
+//! without a check-in the day shows as missed, and reminders are kept inside the window.
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::{Habit, TimeWindow};
+use crate::stats::date_key;
+use crate::storage::Storage;
+
+pub fn now() -> NaiveDateTime {
+    crate::clock::now().naive_local()
+}
+
+fn minute(now: NaiveDateTime) -> String {
+    now.format("%H:%M").to_string()
+}
+
+/// Both ends are valid `HH:MM` and the window doesn't wrap past midnight.
+pub fn validate(window: &TimeWindow) -> Result<(), AppError> {
+    for time in [&window.start, &window.end] {
+        if time.len() != 5 || NaiveTime::parse_from_str(time, "%H:%M").is_err() {
+            return Err(AppError::Invalid(format!(
+                "invalid time {time:?}, expected HH:MM"
+            )));
+        }
+    }
+    if window.start >= window.end {
+        return Err(AppError::Invalid(
+            "the window must end after it starts, on the same day".into(),
+        ));
+    }
+    Ok(())
+}
+
+/// Fails when `habit` is checked off for today outside its window.
+pub fn check(habit: &Habit, date: NaiveDate, now: NaiveDateTime) -> Result<(), AppError> {
+    match &habit.window {
+        Some(w) if date == now.date() && !w.contains(&minute(now)) => {
+            Err(AppError::Invalid(format!(
+                "{} only counts between {} and {}",
+                habit.name, w.start, w.end
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WindowState {
+    Upcoming,
+    Open,
+    /// Closed without a check-in.
+    Missed,
+    Done,
+}
+
+/// Where `date` stands against the window as of `now`, for habits that have one. Excused
+/// days have no state.
+pub fn state(habit: &Habit, date: NaiveDate, now: NaiveDateTime) -> Option<WindowState> {
+    let window = habit.window.as_ref()?;
+    let key = date_key(date);
+    if habit.is_done_on(&key) {
+        return Some(WindowState::Done);
+    }
+    if habit.is_excused(&key) {
+        return None;
+    }
+    let time = minute(now);
+    Some(match date.cmp(&now.date()) {
+        std::cmp::Ordering::Less => WindowState::Missed,
+        std::cmp::Ordering::Greater => WindowState::Upcoming,
+        _ if time < window.start => WindowState::Upcoming,
+        _ if time < window.end => WindowState::Open,
+        _ => WindowState::Missed,
+    })
+}
+
+/// The window closed today without a check-in, so there's nothing left to remind about.
+pub fn is_closed(habit: &Habit, now: NaiveDateTime) -> bool {
+    state(habit, now.date(), now) == Some(WindowState::Missed)
+}
+
+/// When to remind on `date`: the habit's own reminder time for that weekday if it falls
+/// inside the window, otherwise the window's opening.
+pub fn reminder_time(habit: &Habit, date: NaiveDate) -> Option<&str> {
+    let weekday = date.weekday().num_days_from_sunday() as u8;
+    match (habit.reminder_time_on(weekday), &habit.window) {
+        (Some(time), Some(w)) if w.contains(time) => Some(time),
+        (_, Some(w)) => Some(&w.start),
+        (time, None) => time,
+    }
+}
+
+/// Sets or (with `None`) clears a habit's window.
+pub fn set_window(
+    storage: &Storage,
+    habit_id: &str,
+    window: Option<TimeWindow>,
+) -> Result<Habit, AppError> {
+    if let Some(w) = &window {
+        validate(w)?;
+    }
+    if !storage.set_habit_window(habit_id, window.as_ref())? {
+        return Err(AppError::not_found("habit", habit_id));
+    }
+    storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))
+}
+
+#[tauri::command]
+pub fn set_habit_window(
+    app: AppHandle,
+    habit_id: String,
+    window: Option<TimeWindow>,
+) -> Result<Habit, AppError> {
+    let habit = set_window(&app.state::<Storage>(), &habit_id, window)?;
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(&app);
+    Ok(habit)
+}
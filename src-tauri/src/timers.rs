@@ -0,0 +1,269 @@
+//! Stopwatches for habits measured in time (a target in seconds, minutes or hours).
+//!
+//! Timers run in the backend, so reloading the webview or hiding the window doesn't lose
+//! them. Stopping one logs the elapsed time as a check-in for the day it was started; a
+//! running timer stops by itself once the day's target is reached, which completes the
+//! habit. Timers don't survive quitting the app.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::error::AppError;
+use crate::model::Habit;
+use crate::stats::{date_key, today};
+use crate::storage::Storage;
+
+/// Emitted with `get_timers`' payload whenever a timer starts, pauses or stops.
+pub const TIMERS_CHANGED_EVENT: &str = "timers:changed";
+
+const TICK: Duration = Duration::from_secs(1);
+
+/// Seconds in one unit of a time target, or `None` for units that aren't time.
+pub(crate) fn unit_seconds(unit: &str) -> Option<f64> {
+    match unit.trim().to_lowercase().as_str() {
+        "s" | "sec" | "secs" | "second" | "seconds" => Some(1.0),
+        "m" | "min" | "mins" | "minute" | "minutes" => Some(60.0),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Some(3600.0),
+        _ => None,
+    }
+}
+
+struct Timer {
+    date: NaiveDate,
+    /// Set while running.
+    running_since: Option<Instant>,
+    /// Time banked by earlier runs before a pause.
+    banked: Duration,
+    /// Elapsed time that completes the day, counting what was already logged.
+    goal: Duration,
+    unit_seconds: f64,
+}
+
+impl Timer {
+    fn elapsed(&self) -> Duration {
+        self.banked + self.running_since.map_or(Duration::ZERO, |s| s.elapsed())
+    }
+}
+
+#[derive(Default)]
+pub struct Timers {
+    timers: Mutex<HashMap<String, Timer>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerStatus {
+    pub habit_id: String,
+    pub date: String,
+    pub running: bool,
+    pub elapsed_secs: u64,
+    /// Seconds left until the day's target is reached.
+    pub remaining_secs: u64,
+}
+
+impl Timers {
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Timer>> {
+        self.timers.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn list(&self) -> Vec<TimerStatus> {
+        let mut list: Vec<TimerStatus> = self
+            .lock()
+            .iter()
+            .map(|(habit_id, t)| {
+                let elapsed = t.elapsed();
+                TimerStatus {
+                    habit_id: habit_id.clone(),
+                    date: date_key(t.date),
+                    running: t.running_since.is_some(),
+                    elapsed_secs: elapsed.as_secs(),
+                    remaining_secs: t.goal.saturating_sub(elapsed).as_secs(),
+                }
+            })
+            .collect();
+        list.sort_by(|a, b| a.habit_id.cmp(&b.habit_id));
+        list
+    }
+
+    /// Starts a timer or resumes a paused one.
+    pub fn start(&self, habit: &Habit) -> Result<(), String> {
+        let target = habit
+            .target
+            .as_ref()
+            .filter(|t| unit_seconds(&t.unit).is_some())
+            .ok_or_else(|| format!("{} isn't measured in time", habit.name))?;
+        let unit_seconds = unit_seconds(&target.unit).unwrap_or(1.0);
+        let mut timers = self.lock();
+        if let Some(timer) = timers.get_mut(&habit.id) {
+            timer.running_since.get_or_insert_with(Instant::now);
+            return Ok(());
+        }
+        let date = today();
+        let logged = habit.value_on(&date_key(date)) * unit_seconds;
+        let goal = (target.amount * unit_seconds - logged).max(0.0);
+        timers.insert(
+            habit.id.clone(),
+            Timer {
+                date,
+                running_since: Some(Instant::now()),
+                banked: Duration::ZERO,
+                goal: Duration::from_secs_f64(goal),
+                unit_seconds,
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns false if there's no running timer for the habit.
+    pub fn pause(&self, habit_id: &str) -> bool {
+        let mut timers = self.lock();
+        let Some(timer) = timers.get_mut(habit_id) else {
+            return false;
+        };
+        match timer.running_since.take() {
+            Some(since) => {
+                timer.banked += since.elapsed();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the timer and returns its day and elapsed time in the target's unit.
+    pub(crate) fn take(&self, habit_id: &str) -> Option<(NaiveDate, f64)> {
+        let timer = self.lock().remove(habit_id)?;
+        let amount = timer.elapsed().as_secs_f64() / timer.unit_seconds;
+        Some((timer.date, (amount * 100.0).round() / 100.0))
+    }
+
+    pub(crate) fn reached_goal(&self) -> Vec<String> {
+        self.lock()
+            .iter()
+            .filter(|(_, t)| t.running_since.is_some() && t.elapsed() >= t.goal)
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
+fn emit_changed(app: &AppHandle) {
+    let _ = app.emit(TIMERS_CHANGED_EVENT, app.state::<Timers>().list());
+}
+
+/// Stops a habit's timer and logs the elapsed time. Returns the updated habit, or
+/// `Ok(None)` if there was no timer.
+pub fn stop(app: &AppHandle, habit_id: &str) -> Result<Option<Habit>, AppError> {
+    let stopped = stop_in(&app.state::<Storage>(), &app.state::<Timers>(), habit_id);
+    emit_changed(app);
+    Ok(stopped?.map(|s| {
+        if s.logged {
+            crate::habits::value_logged(app, (s.habit, s.completed), s.date)
+        } else {
+            s.habit
+        }
+    }))
+}
+
+/// What [`stop_in`] did.
+pub struct Stopped {
+    pub habit: Habit,
+    /// The day the timer was started, which the time counts toward.
+    pub date: NaiveDate,
+    /// Whether any time was logged.
+    pub logged: bool,
+    /// Whether the time logged completed the habit.
+    pub completed: bool,
+}
+
+/// Stops `habit_id`'s timer and logs the time it ran. `Ok(None)` when there's no timer or
+/// the habit is gone.
+pub fn stop_in(
+    storage: &Storage,
+    timers: &Timers,
+    habit_id: &str,
+) -> Result<Option<Stopped>, AppError> {
+    let Some((date, amount)) = timers.take(habit_id) else {
+        return Ok(None);
+    };
+    if amount <= 0.0 {
+        return Ok(storage.get_habit(habit_id)?.map(|habit| Stopped {
+            habit,
+            date,
+            logged: false,
+            completed: false,
+        }));
+    }
+    let logged = storage
+        .transaction(|tx| crate::habits::record_value(tx, habit_id, date, amount, Vec::new()))?;
+    Ok(logged.map(|(habit, completed)| Stopped {
+        habit,
+        date,
+        logged: true,
+        completed,
+    }))
+}
+
+/// Starts `habit_id`'s timer, or resumes it if it's paused.
+pub fn start_in(
+    storage: &Storage,
+    timers: &Timers,
+    habit_id: &str,
+) -> Result<Vec<TimerStatus>, AppError> {
+    let habit = storage
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+    timers.start(&habit)?;
+    Ok(timers.list())
+}
+
+pub fn pause_in(timers: &Timers, habit_id: &str) -> Result<Vec<TimerStatus>, AppError> {
+    if !timers.pause(habit_id) {
+        return Err(AppError::not_found("running timer", habit_id));
+    }
+    Ok(timers.list())
+}
+
+/// Stops timers that reached their goal, which completes their habits.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(TICK);
+        loop {
+            interval.tick().await;
+            for habit_id in app.state::<Timers>().reached_goal() {
+                if let Err(e) = stop(&app, &habit_id) {
+                    log::warn!("timers: failed to log {habit_id}: {e}");
+                }
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_timers(timers: State<'_, Timers>) -> Vec<TimerStatus> {
+    timers.list()
+}
+
+#[tauri::command]
+pub fn start_timer(app: AppHandle, habit_id: String) -> Result<Vec<TimerStatus>, AppError> {
+    let timers = start_in(&app.state::<Storage>(), &app.state::<Timers>(), &habit_id)?;
+    emit_changed(&app);
+    Ok(timers)
+}
+
+#[tauri::command]
+pub fn pause_timer(app: AppHandle, habit_id: String) -> Result<Vec<TimerStatus>, AppError> {
+    let timers = pause_in(&app.state::<Timers>(), &habit_id)?;
+    emit_changed(&app);
+    Ok(timers)
+}
+
+/// Stops the timer and logs the time it ran.
+#[tauri::command]
+pub fn stop_timer(app: AppHandle, habit_id: String) -> Result<Habit, AppError> {
+    stop(&app, &habit_id)?.ok_or_else(|| AppError::not_found("timer", &habit_id))
+}
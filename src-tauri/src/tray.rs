@@ -0,0 +1,372 @@
+//! Tray icon and menu.
+//!
+//! Besides Open / Add Habit / Quit, the menu lists pinned habits and then a submenu per
+//! category with the rest, as check items in the tray's sort order; clicking one toggles
+//! today's completion. In privacy mode (`privacy.rs`) only the number of habits due is
+//! shown. The menu is rebuilt whenever the data changes. A running Pomodoro or focus
+//! session (`focus.rs`) shows its countdown next to the icon; the Focus submenu starts one
+//! on a habit. A greyed-out line at the top shows the next reminder ("Next: Stretch in 42
+//! min"), kept current by the scheduler's minute tick.
+//! Labels are in the language `set_locale` picked (`i18n.rs`).
+
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use tauri::menu::{CheckMenuItem, Menu, MenuBuilder, MenuItem, SubmenuBuilder};
+use tauri::tray::{TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::events::EventBus;
+use crate::focus::Focus;
+use crate::i18n::{t, t_args};
+use crate::model::{Habit, HabitKind};
+use crate::ordering::View;
+use crate::pomodoro::Pomodoro;
+use crate::settings::SettingsStore;
+use crate::stats::{date_key, group_by_category, today};
+use crate::storage::Storage;
+use crate::AppState;
+
+pub const TRAY_ID: &str = "habitflow-tray";
+
+/// Menu ids of habit items are this prefix plus the habit id.
+const HABIT_ITEM_PREFIX: &str = "habit:";
+/// The menu's next-reminder line, so the minute tick can update it in place.
+static NEXT_ITEM: Mutex<Option<MenuItem<Wry>>> = Mutex::new(None);
+
+/// Items of the Focus submenu: this prefix plus the habit id.
+const FOCUS_ITEM_PREFIX: &str = "focus:";
+
+pub fn create(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(
+            app.default_window_icon()
+                .ok_or("missing default window icon")?
+                .clone(),
+        )
+        .menu(&build_menu(app)?)
+        .on_menu_event(move |app, event| {
+            let id = event.id().as_ref();
+            // Tray actions read and write the data directly.
+            crate::write_queue::flush_logged(app);
+
+            if id == "open" {
+                show_main(app);
+            } else if id == "add_habit" {
+                if let Some(w) = show_main(app) {
+                    let _ = w.emit("tray:add-habit", ());
+                }
+            } else if id == "quit" {
+                // Allow next close to actually quit
+                let state = app.state::<AppState>();
+                state.quitting.store(true, Ordering::SeqCst);
+                crate::exports::export_on_quit(app);
+                app.exit(0);
+            } else if id == "focus_stop" {
+                crate::focus::end(app);
+            } else if let Some(habit_id) = id.strip_prefix(HABIT_ITEM_PREFIX) {
+                toggle_today(app, habit_id);
+            } else if let Some(habit_id) = id.strip_prefix(FOCUS_ITEM_PREFIX) {
+                if let Err(e) = crate::focus::begin(app, habit_id, None) {
+                    log::warn!("tray: failed to start focus: {e}");
+                }
+            }
+        })
+        .on_tray_icon_event(move |tray, event| {
+            if let TrayIconEvent::Click { .. } = event {
+                show_main(tray.app_handle());
+            }
+        })
+        .build(app)?;
+    Ok(())
+}
+
+/// Keeps the habit sections in step with the data.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    let mut data = app.state::<EventBus>().watch_data();
+    tauri::async_runtime::spawn(async move {
+        while data.changed().await.is_ok() {
+            refresh(&app);
+        }
+    });
+}
+
+pub fn refresh(app: &AppHandle) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    match build_menu(app) {
+        Ok(menu) => {
+            if let Err(e) = tray.set_menu(Some(menu)) {
+                log::warn!("tray: failed to update menu: {e}");
+            }
+        }
+        Err(e) => log::warn!("tray: failed to build menu: {e}"),
+    }
+    // A running pomodoro or focus session owns the tooltip until it stops.
+    if app.state::<Pomodoro>().status().is_none() && !app.state::<Focus>().is_active() {
+        let _ = tray.set_tooltip(Some(idle_tooltip(app)));
+    }
+}
+
+/// "HabitFlow", with the player's level while gamification is on.
+fn idle_tooltip(app: &AppHandle) -> String {
+    let settings = app.state::<SettingsStore>();
+    if !settings.get().gamification.enabled {
+        return "HabitFlow".into();
+    }
+    match crate::gamification::load(&app.state::<Storage>(), &settings) {
+        Ok(state) => crate::gamification::tooltip(&state),
+        Err(_) => "HabitFlow".into(),
+    }
+}
+
+/// "Next: Stretch in 42 min", or `None` with no reminder in the next two weeks.
+fn next_reminder(app: &AppHandle) -> Option<String> {
+    let habits = match app.state::<Storage>().list_habits() {
+        Ok(habits) => habits,
+        Err(e) => {
+            log::warn!("tray: failed to load habits: {e}");
+            return None;
+        }
+    };
+    let now = crate::time_windows::now();
+    let next = crate::reminders::upcoming(&habits, now, 1).pop()?;
+    let habit = if crate::privacy::hide_names(app) {
+        t("tray-next-hidden")
+    } else {
+        habits
+            .iter()
+            .find(|h| h.id == next.habit_id)
+            .map_or(next.name.clone(), crate::appearance::label)
+    };
+    let at = chrono::NaiveDateTime::parse_from_str(
+        &format!("{} {}", next.date, next.time),
+        "%Y-%m-%d %H:%M",
+    )
+    .ok()?;
+    // Rounded up, so a reminder in 30 seconds reads "in 1 min" rather than "in 0 min".
+    let minutes = (at - now).num_seconds().max(0).unsigned_abs().div_ceil(60);
+    let days = (at.date() - now.date()).num_days();
+    Some(match (days, minutes) {
+        (0, m) if m < 60 => t_args(
+            "tray-next-minutes",
+            &[("habit", habit.into()), ("minutes", m.into())],
+        ),
+        (0, m) => t_args(
+            "tray-next-hours",
+            &[
+                ("habit", habit.into()),
+                ("hours", (m / 60).into()),
+                ("minutes", (m % 60).into()),
+            ],
+        ),
+        (1, _) => t_args(
+            "tray-next-tomorrow",
+            &[("habit", habit.into()), ("time", next.time.into())],
+        ),
+        _ => t_args(
+            "tray-next-later",
+            &[
+                ("habit", habit.into()),
+                ("date", next.date.into()),
+                ("time", next.time.into()),
+            ],
+        ),
+    })
+}
+
+/// Brings the next-reminder line up to date; called by the scheduler every minute.
+pub fn update_next_reminder(app: &AppHandle) {
+    if app.state::<crate::lock::AppLock>().is_locked() {
+        return;
+    }
+    let text = next_reminder(app);
+    let item = NEXT_ITEM.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    match (item, text) {
+        (Some(item), Some(text)) => {
+            if let Err(e) = item.set_text(text) {
+                log::warn!("tray: failed to update next reminder: {e}");
+            }
+        }
+        // The line comes or goes, which takes a new menu.
+        (Some(_), None) | (None, Some(_)) => refresh(app),
+        (None, None) => {}
+    }
+}
+
+fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let open_item = MenuItem::with_id(app, "open", t("tray-open"), true, None::<&str>)?;
+    let add_item = MenuItem::with_id(app, "add_habit", t("tray-add-habit"), true, None::<&str>)?;
+    let quit_item = MenuItem::with_id(app, "quit", t("tray-quit"), true, None::<&str>)?;
+    let mut menu = MenuBuilder::new(app).items(&[&open_item, &add_item]);
+
+    // Habit names stay out of the tray while the app is locked.
+    let locked = app.state::<crate::lock::AppLock>().is_locked();
+    let next_item = next_reminder(app)
+        .filter(|_| !locked)
+        .map(|text| MenuItem::with_id(app, "next_reminder", text, false, None::<&str>))
+        .transpose()?;
+    if let Some(item) = &next_item {
+        menu = MenuBuilder::new(app)
+            .item(item)
+            .separator()
+            .items(&[&open_item, &add_item]);
+    }
+    *NEXT_ITEM.lock().unwrap_or_else(|e| e.into_inner()) = next_item;
+    if locked {
+        return menu.separator().item(&quit_item).build();
+    }
+    let storage = app.state::<Storage>();
+    let (habits, categories) = match (
+        crate::ordering::for_view(&storage, &app.state::<SettingsStore>(), View::Tray),
+        storage.list_categories().map_err(|e| e.to_string()),
+    ) {
+        (Ok(h), Ok(c)) => (h, c),
+        (Err(e), _) | (_, Err(e)) => {
+            log::warn!("tray: failed to load habits: {e}");
+            (Vec::new(), Vec::new())
+        }
+    };
+    let key = date_key(today());
+    let shown = habits
+        .iter()
+        .filter(|h| !h.archived && h.kind == HabitKind::Build);
+    let stop_focus = app
+        .state::<Focus>()
+        .is_active()
+        .then(|| MenuItem::with_id(app, "focus_stop", t("tray-stop-focus"), true, None::<&str>))
+        .transpose()?;
+
+    // In privacy mode one line with the count stands in for the habits.
+    if crate::privacy::hide_names(app) {
+        let due = shown
+            .filter(|h| crate::schedule::is_due(h, today()) && !h.is_done_on(&key))
+            .count();
+        let text = match due {
+            0 => t("tray-all-done"),
+            n => t_args("habits-due", &[("count", n.into())]),
+        };
+        let summary = MenuItem::with_id(app, "habits_due", text, false, None::<&str>)?;
+        menu = menu.separator().item(&summary);
+        if let Some(stop) = &stop_focus {
+            menu = menu.item(stop);
+        }
+        return menu.separator().item(&quit_item).build();
+    }
+
+    // One session at a time: a running one can be stopped, otherwise any habit can start one.
+    if let Some(stop) = &stop_focus {
+        menu = menu.separator().item(stop);
+    } else if shown.clone().next().is_some() {
+        let mut submenu = SubmenuBuilder::new(app, t("tray-focus"));
+        for habit in shown.clone() {
+            submenu = submenu.item(&MenuItem::with_id(
+                app,
+                format!("{FOCUS_ITEM_PREFIX}{}", habit.id),
+                crate::appearance::label(habit),
+                true,
+                None::<&str>,
+            )?);
+        }
+        menu = menu.separator().item(&submenu.build()?);
+    }
+
+    let (pinned, active): (Vec<&Habit>, Vec<&Habit>) = shown.partition(|h| h.pinned);
+    let check_item = |habit: &Habit| {
+        CheckMenuItem::with_id(
+            app,
+            format!("{HABIT_ITEM_PREFIX}{}", habit.id),
+            if habit.is_repeated() {
+                format!(
+                    "{} ({}/{})",
+                    crate::appearance::label(habit),
+                    habit.value_on(&key) as u32,
+                    habit.times_per_day
+                )
+            } else {
+                crate::appearance::label(habit)
+            },
+            true,
+            habit.is_done_on(&key),
+            None::<&str>,
+        )
+    };
+
+    // Pinned habits sit at the top level, ahead of the category submenus.
+    let pinned_items = pinned
+        .into_iter()
+        .map(check_item)
+        .collect::<tauri::Result<Vec<_>>>()?;
+    if !pinned_items.is_empty() {
+        menu = menu.separator();
+        for item in &pinned_items {
+            menu = menu.item(item);
+        }
+    }
+
+    let mut sections = Vec::new();
+    for (category, members) in group_by_category(active, &categories) {
+        if members.is_empty() {
+            continue;
+        }
+        let done = members.iter().filter(|h| h.is_done_on(&key)).count();
+        let mut submenu =
+            SubmenuBuilder::new(app, format!("{} ({done}/{})", category.name, members.len()));
+        for habit in members {
+            submenu = submenu.item(&check_item(habit)?);
+        }
+        sections.push(submenu.build()?);
+    }
+    if !sections.is_empty() {
+        menu = menu.separator();
+        for section in &sections {
+            menu = menu.item(section);
+        }
+    }
+
+    menu.separator().item(&quit_item).build()
+}
+
+/// Shows `text` next to the tray icon (where the platform supports titles) and as its
+/// tooltip; `None` clears it.
+pub fn set_status(app: &AppHandle, text: Option<&str>) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let _ = tray.set_title(text);
+    let tooltip = text.map_or_else(|| idle_tooltip(app), str::to_string);
+    let _ = tray.set_tooltip(Some(tooltip));
+}
+
+fn show_main(app: &AppHandle) -> Option<tauri::WebviewWindow> {
+    let w = app.get_webview_window(crate::access::MAIN_WINDOW)?;
+    let _ = w.show();
+    let _ = w.set_focus();
+    Some(w)
+}
+
+fn toggle_today(app: &AppHandle, habit_id: &str) {
+    if app.state::<crate::lock::AppLock>().is_locked() {
+        return;
+    }
+    let date = today();
+    let (done, repeated) = match app.state::<Storage>().get_habit(habit_id) {
+        Ok(Some(habit)) => (habit.is_done_on(&date_key(date)), habit.is_repeated()),
+        Ok(None) => return,
+        Err(e) => {
+            log::warn!("tray: failed to load habit: {e}");
+            return;
+        }
+    };
+    // Repeated habits take one more check-in per click until the day's count is reached.
+    let result = if repeated && !done {
+        crate::habits::log_value(app, habit_id, date, 1.0, Vec::new())
+    } else {
+        crate::habits::set_completion(app, habit_id, date, !done)
+    };
+    if let Err(e) = result {
+        log::warn!("tray: failed to update habit: {e}");
+    }
+}
@@ -0,0 +1,328 @@
+//! Desktop updates through `tauri-plugin-updater`: `tauri signer generate` makes the key
+//! pair, bundling with `"createUpdaterArtifacts": true` writes each installer's `.sig`, and
+//! `plugins.updater` in `tauri.conf.json` has the public key and the manifest URLs.
+//!
+//! - The plugin fills in `{{target}}`, `{{arch}}`, `{{current_version}}` and
+//!   `{{bundle_type}}`; `{{channel}}` is filled in here, so each channel has its own
+//!   `latest.json`.
+//! - On the stable channel only stable releases are offered. The beta channel checks the
+//!   beta manifest and the stable one, and offers whichever is newer, so switching back
+//!   never leaves you behind. Nothing older than what's running is offered.
+//! - The plugin only keeps a download whose signature checks out against the public key.
+//!   With `requireSignedVersion` on, the version in the signature's trusted comment has to
+//!   be the one offered, so a manifest pointing at an old build can't downgrade.
+//! - `restart_to_update` marks the app as quitting, so closing the window doesn't just hide
+//!   it in the tray, then has the plugin install: the Windows installer runs and the app
+//!   exits, anything else is replaced in place and the app restarts.
+
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_updater::UpdaterExt;
+
+use crate::error::AppError;
+use crate::settings::{ReleaseChannel, SettingsStore};
+
+/// Carries the [`Update`] a background check found.
+pub const UPDATE_AVAILABLE_EVENT: &str = "updater:available";
+/// Carries [`Progress`] while an update downloads.
+pub const UPDATE_PROGRESS_EVENT: &str = "updater:progress";
+/// Carries the [`Update`] once it's downloaded and verified.
+pub const UPDATE_READY_EVENT: &str = "updater:ready";
+/// Between background checks.
+const CHECK_EVERY: std::time::Duration = std::time::Duration::from_secs(24 * 60 * 60);
+const TITLE: &str = "HabitFlow";
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Update {
+    pub version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    /// The channel whose manifest it came from.
+    pub channel: ReleaseChannel,
+    #[serde(skip)]
+    release: tauri_plugin_updater::Update,
+}
+
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCheck {
+    pub current_version: String,
+    pub channel: ReleaseChannel,
+    /// `None` when what's running is the newest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub update: Option<Update>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Progress {
+    pub downloaded: u64,
+    /// Left out when the server doesn't say.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+}
+
+/// The update last found, and the verified installer once it's downloaded.
+#[derive(Default)]
+pub struct Updater(Mutex<UpdaterState>);
+
+#[derive(Default)]
+struct UpdaterState {
+    available: Option<Update>,
+    ready: Option<(Update, Vec<u8>)>,
+    downloading: bool,
+}
+
+impl Updater {
+    fn state(&self) -> std::sync::MutexGuard<'_, UpdaterState> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn failed(e: tauri_plugin_updater::Error) -> AppError {
+    AppError::Failed(e.to_string())
+}
+
+/// `plugins.updater.endpoints` with `{{channel}}` filled in. The plugin has already checked
+/// they're URLs, so it's read as it was written.
+fn endpoints(app: &AppHandle, channel: ReleaseChannel) -> Result<Vec<tauri::Url>, AppError> {
+    let name = match channel {
+        ReleaseChannel::Stable => "stable",
+        ReleaseChannel::Beta => "beta",
+    };
+    let endpoints = app
+        .config()
+        .plugins
+        .0
+        .get("updater")
+        .and_then(|c| c.get("endpoints"))
+        .and_then(|e| e.as_array())
+        .cloned()
+        .unwrap_or_default();
+    if endpoints.is_empty() {
+        return Err(AppError::Failed(
+            "this build has no update server configured".into(),
+        ));
+    }
+    endpoints
+        .iter()
+        .filter_map(|e| e.as_str())
+        .map(|e| {
+            e.replace("{{channel}}", name)
+                .parse()
+                .map_err(|e| AppError::Failed(format!("plugins.updater has a bad endpoint: {e}")))
+        })
+        .collect()
+}
+
+/// Checks one channel's manifests. The first endpoint that answers wins; the rest are
+/// mirrors.
+async fn fetch(app: &AppHandle, channel: ReleaseChannel) -> Result<Option<Update>, AppError> {
+    let release = app
+        .updater_builder()
+        .endpoints(endpoints(app, channel)?)
+        .map_err(failed)?
+        .version_comparator(move |current, release| {
+            release.version > current
+                && (channel == ReleaseChannel::Beta || release.version.pre.is_empty())
+        })
+        .build()
+        .map_err(failed)?
+        .check()
+        .await
+        .map_err(failed)?;
+    Ok(release.map(|release| Update {
+        version: release.version.clone(),
+        notes: release.body.clone(),
+        date: release
+            .raw_json
+            .get("pub_date")
+            .and_then(|d| d.as_str())
+            .map(str::to_string),
+        channel,
+        release,
+    }))
+}
+
+fn newer(a: &Update, b: &Update) -> bool {
+    match (
+        semver::Version::parse(&a.version),
+        semver::Version::parse(&b.version),
+    ) {
+        (Ok(a), Ok(b)) => a > b,
+        _ => false,
+    }
+}
+
+/// Looks for an update on the chosen channel and remembers it for `download_update`.
+pub async fn check(app: &AppHandle) -> Result<UpdateCheck, AppError> {
+    let channel = app.state::<SettingsStore>().get().updates.channel;
+    let current_version = app.package_info().version.to_string();
+
+    let stable = fetch(app, ReleaseChannel::Stable).await;
+    let update = match channel {
+        ReleaseChannel::Stable => stable?,
+        ReleaseChannel::Beta => {
+            let beta = fetch(app, ReleaseChannel::Beta).await;
+            match (beta, stable) {
+                (Ok(Some(b)), Ok(Some(s))) => Some(if newer(&s, &b) { s } else { b }),
+                (Ok(b), Ok(s)) => b.or(s),
+                // A beta manifest that isn't there yet shouldn't hide a stable release.
+                (Ok(found), Err(e)) | (Err(e), Ok(found)) => {
+                    log::warn!("updater: {e}");
+                    found
+                }
+                (Err(e), Err(_)) => return Err(e),
+            }
+        }
+    };
+
+    app.state::<Updater>().state().available = update.clone();
+    Ok(UpdateCheck {
+        current_version,
+        channel,
+        update,
+    })
+}
+
+/// Downloads the update `check` found and verifies it, reporting progress on the way.
+pub async fn download(app: &AppHandle) -> Result<Update, AppError> {
+    let update = {
+        let updater = app.state::<Updater>();
+        let mut state = updater.state();
+        if let Some((ready, _)) = &state.ready {
+            if state
+                .available
+                .as_ref()
+                .map_or(true, |a| a.version == ready.version)
+            {
+                return Ok(ready.clone());
+            }
+        }
+        if state.downloading {
+            return Err(AppError::Invalid(
+                "the update is already downloading".into(),
+            ));
+        }
+        let update = state
+            .available
+            .clone()
+            .ok_or_else(|| AppError::Invalid("there's no update; check for one first".into()))?;
+        state.downloading = true;
+        update
+    };
+    let mut downloaded = 0u64;
+    // The plugin checks the signature before handing the bytes back.
+    let result = update
+        .release
+        .download(
+            |chunk, total| {
+                downloaded += chunk as u64;
+                let _ = app.emit(UPDATE_PROGRESS_EVENT, Progress { downloaded, total });
+            },
+            || {},
+        )
+        .await;
+    let updater = app.state::<Updater>();
+    let mut state = updater.state();
+    state.downloading = false;
+    let bytes = result.map_err(failed)?;
+    state.ready = Some((update.clone(), bytes));
+    drop(state);
+    let _ = app.emit(UPDATE_READY_EVENT, &update);
+    Ok(update)
+}
+
+/// Installs the downloaded update, then restarts. On Windows the installer relaunches the
+/// app, and the plugin exits for it.
+pub fn restart(app: &AppHandle) -> Result<(), AppError> {
+    let (update, bytes) = app
+        .state::<Updater>()
+        .state()
+        .ready
+        .take()
+        .ok_or_else(|| AppError::Invalid("no update has been downloaded".into()))?;
+    crate::write_queue::flush_logged(app);
+    log::info!("updater: installing {}", update.version);
+    // Otherwise closing the main window would only hide it in the tray.
+    app.state::<crate::AppState>()
+        .quitting
+        .store(true, Ordering::SeqCst);
+    if let Err(e) = update.release.install(&bytes) {
+        app.state::<crate::AppState>()
+            .quitting
+            .store(false, Ordering::SeqCst);
+        app.state::<Updater>().state().ready = Some((update, bytes));
+        return Err(failed(e));
+    }
+    app.request_restart();
+    Ok(())
+}
+
+/// Checks on launch and once a day while `updates.checkAutomatically` is on, and says when
+/// there's something new.
+pub fn start(app: &AppHandle) {
+    if cfg!(mobile) {
+        return;
+    }
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut ticks = tokio::time::interval(CHECK_EVERY);
+        let mut announced: Option<String> = None;
+        loop {
+            ticks.tick().await;
+            if !app
+                .state::<SettingsStore>()
+                .get()
+                .updates
+                .check_automatically
+            {
+                continue;
+            }
+            crate::network::wait_online(&app).await;
+            match check(&app).await {
+                Ok(UpdateCheck {
+                    update: Some(update),
+                    ..
+                }) if announced.as_deref() != Some(&update.version) => {
+                    let _ = app.emit(UPDATE_AVAILABLE_EVENT, &update);
+                    crate::reminders::notify(
+                        &app,
+                        TITLE,
+                        &crate::i18n::t_args(
+                            "update-available",
+                            &[("version", update.version.as_str().into())],
+                        ),
+                    );
+                    announced = Some(update.version);
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("updater: check failed: {e}"),
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub async fn check_for_updates(app: AppHandle) -> Result<UpdateCheck, AppError> {
+    check(&app).await
+}
+
+/// Downloads and verifies the update `check_for_updates` found, with `updater:progress`
+/// events on the way. Returns straight away when it's already downloaded.
+#[tauri::command]
+pub async fn download_update(app: AppHandle) -> Result<Update, AppError> {
+    download(&app).await
+}
+
+/// Installs the downloaded update and restarts into it. Unsaved writes are flushed first.
+#[tauri::command]
+pub fn restart_to_update(app: AppHandle) -> Result<(), AppError> {
+    restart(&app)
+}
@@ -0,0 +1,237 @@
+//! Git-backed history of the data: a plaintext snapshot (`habits.json` plus
+//! `completions.csv`) committed to a local repository on every day rollover when enabled,
+//! or on demand. Gives a diffable record of changes and a way back to any earlier state.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, TimeZone};
+use git2::{Repository, Signature};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::Habit;
+use crate::settings::{ExportFormat, SettingsStore, VersioningSettings};
+use crate::storage::Storage;
+
+pub const DEFAULT_REPOSITORY_DIR: &str = "history";
+const HABITS_FILE: &str = "habits.json";
+const COMPLETIONS_FILE: &str = "completions.csv";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DataVersion {
+    pub id: String,
+    pub message: String,
+    pub committed_at: String,
+}
+
+impl DataVersion {
+    fn from_commit(commit: &git2::Commit<'_>) -> Self {
+        Self {
+            id: commit.id().to_string(),
+            message: commit.summary().unwrap_or_default().to_string(),
+            committed_at: Local
+                .timestamp_opt(commit.time().seconds(), 0)
+                .single()
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+pub fn repository_path(settings: &VersioningSettings, data_dir: &Path) -> PathBuf {
+    if settings.repository.trim().is_empty() {
+        data_dir.join(DEFAULT_REPOSITORY_DIR)
+    } else {
+        PathBuf::from(&settings.repository)
+    }
+}
+
+fn open_or_init(path: &Path) -> Result<Repository, git2::Error> {
+    match Repository::open(path) {
+        Ok(repo) => Ok(repo),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => Repository::init(path),
+        Err(e) => Err(e),
+    }
+}
+
+fn to_string(e: impl ToString) -> String {
+    e.to_string()
+}
+
+/// Writes the current data into the repository and commits it. Returns `None` when
+/// nothing changed since the last snapshot.
+pub fn commit_snapshot(
+    storage: &Storage,
+    repo_path: &Path,
+    message: &str,
+) -> Result<Option<DataVersion>, String> {
+    let habits = storage.list_habits().map_err(to_string)?;
+    let categories = storage.list_categories().map_err(to_string)?;
+    std::fs::create_dir_all(repo_path).map_err(to_string)?;
+    let repo = open_or_init(repo_path).map_err(to_string)?;
+    let workdir = repo.workdir().ok_or("history repository is bare")?;
+
+    let json = serde_json::to_string_pretty(&habits).map_err(to_string)?;
+    std::fs::write(workdir.join(HABITS_FILE), json + "\n").map_err(to_string)?;
+    std::fs::write(
+        workdir.join(COMPLETIONS_FILE),
+        crate::exports::render(ExportFormat::Csv, &habits, &categories),
+    )
+    .map_err(to_string)?;
+
+    let mut index = repo.index().map_err(to_string)?;
+    for file in [HABITS_FILE, COMPLETIONS_FILE] {
+        index.add_path(Path::new(file)).map_err(to_string)?;
+    }
+    index.write().map_err(to_string)?;
+    let tree_id = index.write_tree().map_err(to_string)?;
+
+    let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+    if parent.as_ref().is_some_and(|p| p.tree_id() == tree_id) {
+        return Ok(None);
+    }
+
+    let tree = repo.find_tree(tree_id).map_err(to_string)?;
+    let signature = Signature::now("HabitFlow", "habitflow@localhost").map_err(to_string)?;
+    let parents: Vec<&git2::Commit<'_>> = parent.iter().collect();
+    let id = repo
+        .commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .map_err(to_string)?;
+    let commit = repo.find_commit(id).map_err(to_string)?;
+    Ok(Some(DataVersion::from_commit(&commit)))
+}
+
+/// Newest first. Empty if nothing has been committed yet.
+pub fn list_versions(repo_path: &Path, limit: usize) -> Result<Vec<DataVersion>, String> {
+    let Ok(repo) = Repository::open(repo_path) else {
+        return Ok(Vec::new());
+    };
+    let mut walk = repo.revwalk().map_err(to_string)?;
+    if walk.push_head().is_err() {
+        return Ok(Vec::new());
+    }
+    walk.take(limit)
+        .map(|oid| {
+            let commit = repo
+                .find_commit(oid.map_err(to_string)?)
+                .map_err(to_string)?;
+            Ok(DataVersion::from_commit(&commit))
+        })
+        .collect()
+}
+
+/// Reads the habit list as it was in version `id`.
+pub fn read_version(repo_path: &Path, id: &str) -> Result<Vec<Habit>, String> {
+    let repo = Repository::open(repo_path).map_err(to_string)?;
+    let oid = git2::Oid::from_str(id).map_err(|_| format!("invalid version id {id:?}"))?;
+    let tree = repo
+        .find_commit(oid)
+        .and_then(|c| c.tree())
+        .map_err(to_string)?;
+    let entry = tree
+        .get_name(HABITS_FILE)
+        .ok_or("version has no habits.json")?;
+    let blob = repo.find_blob(entry.id()).map_err(to_string)?;
+    serde_json::from_slice(blob.content()).map_err(to_string)
+}
+
+pub(crate) fn app_repository_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path().app_data_dir().map_err(to_string)?;
+    Ok(repository_path(
+        &app.state::<SettingsStore>().get().versioning,
+        &data_dir,
+    ))
+}
+
+pub fn on_day_rollover(app: &AppHandle, date: &str) {
+    if !app.state::<SettingsStore>().get().versioning.enabled {
+        return;
+    }
+    let app = app.clone();
+    let message = format!("Snapshot {date}");
+    tauri::async_runtime::spawn_blocking(move || {
+        let result = app_repository_path(&app)
+            .and_then(|path| commit_snapshot(&app.state::<Storage>(), &path, &message));
+        if let Err(e) = result {
+            log::error!("versioning: snapshot failed: {e}");
+        }
+    });
+}
+
+#[tauri::command]
+pub fn commit_data_version(
+    app: AppHandle,
+    storage: State<'_, Storage>,
+    message: Option<String>,
+) -> Result<Option<DataVersion>, AppError> {
+    commit(&storage, &app_repository_path(&app)?, message)
+}
+
+/// Snapshots the data with `message`, or a dated "manual snapshot" one when it's blank.
+pub fn commit(
+    storage: &Storage,
+    repo_path: &Path,
+    message: Option<String>,
+) -> Result<Option<DataVersion>, AppError> {
+    let message = message.filter(|m| !m.trim().is_empty()).unwrap_or_else(|| {
+        let now = crate::clock::now();
+        crate::i18n::t_args(
+            "snapshot-manual",
+            &[
+                (
+                    "date",
+                    crate::calendar::format_date(now.date_naive()).into(),
+                ),
+                ("time", now.format("%H:%M").to_string().into()),
+            ],
+        )
+    });
+    Ok(commit_snapshot(storage, repo_path, &message)?)
+}
+
+#[tauri::command]
+pub fn list_data_versions(
+    app: AppHandle,
+    limit: Option<u32>,
+) -> Result<Vec<DataVersion>, AppError> {
+    Ok(list_versions(
+        &app_repository_path(&app)?,
+        limit.unwrap_or(100) as usize,
+    )?)
+}
+
+/// Replaces the current habits with version `id`'s, after snapshotting them.
+pub fn restore(storage: &Storage, repo_path: &Path, id: &str) -> Result<Vec<Habit>, AppError> {
+    let habits = read_version(repo_path, id)?;
+    // Keep today's state reachable too.
+    commit_snapshot(
+        storage,
+        repo_path,
+        &format!("Before restoring {}", &id[..id.len().min(7)]),
+    )?;
+    storage.replace_habits(&habits)?;
+    Ok(habits)
+}
+
+/// Replaces the current data with version `id`; the webview follows via `habits:changed`.
+#[tauri::command]
+pub fn restore_data_version(
+    app: AppHandle,
+    storage: State<'_, Storage>,
+    id: String,
+) -> Result<Vec<Habit>, AppError> {
+    let habits = restore(&storage, &app_repository_path(&app)?, &id)?;
+    app.state::<EventBus>().data_changed();
+    crate::habits::notify_changed(&app);
+    Ok(habits)
+}
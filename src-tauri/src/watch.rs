@@ -0,0 +1,169 @@
+//! The watch: a complication or tile with today's count, the next habit and the streak,
+//! and a button that checks that habit off. The watch apps only talk to the phone, so
+//! this is the small "glance" the phone side hands them, cut down from the widget's
+//! snapshot (`widgets.rs`).
+//!
+//! - The glance is written to `widget/glance.json` with each new snapshot. On Android
+//!   `WidgetPlugin.kt` puts it on the Wear OS Data Layer at `/habitflow/glance`; on iOS
+//!   `WidgetPlugin.swift` sends it to the watch as the WatchConnectivity application
+//!   context.
+//! - A "complete" from the watch comes back to the backend: on Android as a widget tap
+//!   through `WatchListenerService.kt`, on iOS through `habitflow_watch_request`, which
+//!   answers with the new glance.
+//!
+//! Like the widget, the glance has only counts while the app is locked or privacy mode is
+//! on, and nothing is checked off while locked.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::error::AppError;
+use crate::widgets::{TodaySnapshot, WidgetIntent};
+
+pub const GLANCE_FILE: &str = "glance.json";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GlanceHabit {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    pub count: u32,
+    pub goal: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Glance {
+    pub date: String,
+    pub done: usize,
+    pub total: usize,
+    pub streak: u32,
+    /// The first habit in the list that isn't done yet. Left out while `hidden`, and once
+    /// everything is done.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next: Option<GlanceHabit>,
+    pub hidden: bool,
+}
+
+impl From<&TodaySnapshot> for Glance {
+    fn from(snapshot: &TodaySnapshot) -> Self {
+        let next = snapshot
+            .habits
+            .iter()
+            .find(|h| !h.done)
+            .map(|h| GlanceHabit {
+                id: h.id.clone(),
+                name: h.name.clone(),
+                emoji: h.emoji.clone(),
+                count: h.count,
+                goal: h.goal,
+            });
+        Self {
+            date: snapshot.date.clone(),
+            done: snapshot.done,
+            total: snapshot.total,
+            streak: snapshot.streak,
+            next,
+            hidden: snapshot.hidden,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum WatchRequest {
+    Glance,
+    /// Checks off `habitId`, or the glance's `next` habit without one. A repeated habit
+    /// takes one more check-in.
+    #[serde(rename_all = "camelCase")]
+    Complete {
+        #[serde(default)]
+        habit_id: Option<String>,
+    },
+}
+
+pub fn glance(app: &AppHandle) -> Result<Glance, AppError> {
+    Ok(Glance::from(&crate::widgets::snapshot(app)?))
+}
+
+/// Writes the glance next to the snapshot it came from.
+pub fn write(dir: &Path, snapshot: &TodaySnapshot) -> Result<(), AppError> {
+    let json =
+        serde_json::to_vec(&Glance::from(snapshot)).map_err(|e| AppError::Failed(e.to_string()))?;
+    crate::widgets::write_atomic(&dir.join(GLANCE_FILE), &json)
+}
+
+/// Answers a watch; every request gets the glance as it is afterwards.
+pub fn handle(app: &AppHandle, request: WatchRequest) -> Result<Glance, AppError> {
+    crate::telemetry::count(app, "watch");
+    match request {
+        WatchRequest::Glance => glance(app),
+        WatchRequest::Complete { habit_id } => {
+            let current = glance(app)?;
+            let hidden = current.hidden;
+            let habit_id = habit_id
+                .or_else(|| current.next.map(|h| h.id))
+                .ok_or_else(|| {
+                    AppError::Invalid(if hidden {
+                        "names are hidden, so there's no next habit to check off".into()
+                    } else {
+                        "there's nothing left to check off".into()
+                    })
+                })?;
+            crate::widgets::apply(
+                app,
+                &WidgetIntent {
+                    action: "complete".into(),
+                    habit_id: Some(habit_id),
+                    date: Some(current.date),
+                },
+            )?;
+            crate::widgets::refresh(app);
+            glance(app)
+        }
+    }
+}
+
+/// Where the iOS WatchConnectivity bridge calls in.
+#[cfg(target_os = "ios")]
+mod ffi {
+    use std::ffi::{c_char, CStr, CString};
+
+    use super::WatchRequest;
+    use crate::error::AppError;
+
+    fn respond(request: &str) -> Result<super::Glance, AppError> {
+        let app = crate::shortcuts::app()
+            .ok_or_else(|| AppError::Failed("HabitFlow is still starting".into()))?;
+        let request: WatchRequest =
+            serde_json::from_str(request).map_err(|e| AppError::Invalid(e.to_string()))?;
+        super::handle(app, request)
+    }
+
+    /// Takes a JSON `WatchRequest` and returns `{"glance": ...}` or `{"error": ...}`, to be
+    /// freed with `habitflow_free_string`.
+    ///
+    /// # Safety
+    /// `request` must be a valid NUL-terminated string.
+    #[no_mangle]
+    pub unsafe extern "C" fn habitflow_watch_request(request: *const c_char) -> *mut c_char {
+        let request = CStr::from_ptr(request).to_string_lossy();
+        let response = match respond(&request) {
+            Ok(glance) => serde_json::json!({ "glance": glance }),
+            Err(e) => serde_json::json!({ "error": e }),
+        };
+        CString::new(response.to_string())
+            .map(CString::into_raw)
+            .unwrap_or(std::ptr::null_mut())
+    }
+}
+
+/// Runs a watch request, for trying them from the webview.
+#[tauri::command]
+pub fn watch_request(app: AppHandle, request: WatchRequest) -> Result<Glance, AppError> {
+    handle(&app, request)
+}
@@ -0,0 +1,240 @@
+//! Outgoing webhooks: POSTs signed JSON for selected `HabitEvent`s to user-registered URLs.
+//!
+//! Each request carries `X-HabitFlow-Event`, `X-HabitFlow-Delivery` and
+//! `X-HabitFlow-Signature: sha256=<hex HMAC of the body>` keyed by the hook's secret.
+//! Failed deliveries are retried with backoff; every attempt lands in the delivery log.
+//! Secrets are kept in the keychain (`secrets.rs`) and only shown once, by `add_webhook`.
+//! Deliveries wait while the network is down (`network.rs`).
+
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use tauri::{AppHandle, Manager, State};
+
+use crate::error::AppError;
+use crate::events::{EventBus, HabitEvent, EVENT_KINDS};
+use crate::secrets;
+use crate::settings::{generate_token, SettingsStore, WebhookConfig};
+use crate::storage::{Storage, WebhookDelivery};
+
+/// Wait before each retry; the number of entries + 1 is the max attempt count.
+const RETRY_DELAYS: &[Duration] = &[
+    Duration::from_secs(2),
+    Duration::from_secs(10),
+    Duration::from_secs(60),
+    Duration::from_secs(300),
+];
+
+/// Subscribes to the event bus and dispatches matching events for the lifetime of the app.
+pub fn start(app: &AppHandle) {
+    let mut rx = app.state::<EventBus>().subscribe();
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(15))
+            .user_agent(concat!("HabitFlow/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+
+        loop {
+            let event = match rx.recv().await {
+                Ok(event) => event,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                    log::warn!("webhooks: dispatcher lagged, skipped {skipped} events");
+                    continue;
+                }
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+            };
+
+            let hooks = app.state::<SettingsStore>().get().webhooks;
+            for hook in hooks
+                .into_iter()
+                .filter(|h| h.enabled && h.events.iter().any(|e| e == event.kind()))
+            {
+                tauri::async_runtime::spawn(deliver(
+                    app.clone(),
+                    client.clone(),
+                    hook,
+                    event.clone(),
+                ));
+            }
+        }
+    });
+}
+
+async fn deliver(app: AppHandle, client: reqwest::Client, hook: WebhookConfig, event: HabitEvent) {
+    let delivery_id = generate_token()[..16].to_string();
+    let body = serde_json::json!({
+        "id": delivery_id,
+        "event": event.kind(),
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "data": event,
+    })
+    .to_string();
+    let secret = match secrets::get(&secrets::webhook_key(&hook.id)) {
+        Ok(Some(secret)) => secret,
+        Ok(None) => {
+            log::warn!(
+                "webhooks: {} has no secret; remove it and add it again",
+                hook.url
+            );
+            return;
+        }
+        Err(e) => {
+            log::warn!("webhooks: not delivering to {}: {e}", hook.url);
+            return;
+        }
+    };
+    let signature = sign(&secret, body.as_bytes());
+
+    for attempt in 1..=RETRY_DELAYS.len() + 1 {
+        // Attempts aren't spent while the network is down.
+        crate::network::wait_online(&app).await;
+        let result = client
+            .post(&hook.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-HabitFlow-Event", event.kind())
+            .header("X-HabitFlow-Delivery", &delivery_id)
+            .header("X-HabitFlow-Signature", format!("sha256={signature}"))
+            .body(body.clone())
+            .send()
+            .await;
+
+        let (status_code, error, retryable) = match &result {
+            Ok(resp) if resp.status().is_success() => (Some(resp.status().as_u16()), None, false),
+            Ok(resp) => {
+                let status = resp.status();
+                let retryable = status.is_server_error() || status.as_u16() == 429;
+                (
+                    Some(status.as_u16()),
+                    Some(format!("HTTP {status}")),
+                    retryable,
+                )
+            }
+            Err(e) => {
+                crate::network::check_now(&app);
+                (None, Some(e.to_string()), true)
+            }
+        };
+        let success = error.is_none();
+
+        let record = WebhookDelivery {
+            id: 0,
+            webhook_id: hook.id.clone(),
+            event_type: event.kind().to_string(),
+            url: hook.url.clone(),
+            attempt: attempt as u32,
+            status_code,
+            error,
+            success,
+            attempted_at: chrono::Utc::now().to_rfc3339(),
+        };
+        if let Err(e) = app.state::<Storage>().record_webhook_delivery(&record) {
+            log::error!("webhooks: failed to record delivery: {e}");
+        }
+
+        if success || !retryable {
+            return;
+        }
+        match RETRY_DELAYS.get(attempt - 1) {
+            Some(delay) => tokio::time::sleep(*delay).await,
+            None => log::warn!(
+                "webhooks: giving up on {} after {attempt} attempts",
+                hook.url
+            ),
+        }
+    }
+}
+
+fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[tauri::command]
+pub fn list_webhooks(settings: State<'_, SettingsStore>) -> Vec<WebhookConfig> {
+    settings.get().webhooks
+}
+
+/// A hook just added, with its secret.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewWebhook {
+    #[serde(flatten)]
+    pub hook: WebhookConfig,
+    pub secret: String,
+}
+
+/// Registers a hook. The generated secret is only returned here, never again.
+#[tauri::command]
+pub fn add_webhook(
+    app: AppHandle,
+    settings: State<'_, SettingsStore>,
+    url: String,
+    events: Vec<String>,
+) -> Result<NewWebhook, AppError> {
+    let hook = add(&settings, url, events)?;
+    crate::redact::refresh_secrets(&app);
+    Ok(hook)
+}
+
+#[tauri::command]
+pub fn remove_webhook(settings: State<'_, SettingsStore>, id: String) -> Result<(), AppError> {
+    Ok(remove(&settings, &id)?)
+}
+
+pub fn add(
+    settings: &SettingsStore,
+    url: String,
+    events: Vec<String>,
+) -> Result<NewWebhook, String> {
+    let parsed = reqwest::Url::parse(&url).map_err(|e| format!("invalid URL: {e}"))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("webhook URL must be http(s)".into());
+    }
+    if events.is_empty() {
+        return Err("select at least one event".into());
+    }
+    if let Some(unknown) = events.iter().find(|e| !EVENT_KINDS.contains(&e.as_str())) {
+        return Err(format!("unknown event type: {unknown}"));
+    }
+
+    let hook = WebhookConfig {
+        id: generate_token()[..16].to_string(),
+        url,
+        events,
+        secret: String::new(),
+        enabled: true,
+    };
+    let secret = generate_token();
+    secrets::set(&secrets::webhook_key(&hook.id), &secret)?;
+    if let Err(e) = settings.update(|s| s.webhooks.push(hook.clone())) {
+        let _ = secrets::delete(&secrets::webhook_key(&hook.id));
+        return Err(e.to_string());
+    }
+    Ok(NewWebhook { hook, secret })
+}
+
+pub fn remove(settings: &SettingsStore, id: &str) -> Result<(), String> {
+    settings
+        .update(|s| s.webhooks.retain(|h| h.id != id))
+        .map_err(|e| e.to_string())?;
+    secrets::delete(&secrets::webhook_key(id))?;
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_webhook_deliveries(
+    storage: State<'_, Storage>,
+    limit: Option<u32>,
+) -> Result<Vec<WebhookDelivery>, AppError> {
+    Ok(storage.list_webhook_deliveries(limit.unwrap_or(100))?)
+}
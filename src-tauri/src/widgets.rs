@@ -0,0 +1,507 @@
+//! Home-screen widgets on mobile. On Android, a widget lists today's habits, each checked
+//! off with a tap, and a Quick Settings tile toggles one habit for the day; both are Kotlin
+//! (`android/`, copied into `gen/android`). On iOS, a WidgetKit widget for the home and
+//! lock screens shows today's progress and streaks (`ios/`). This side keeps them fed.
+//!
+//! - Today's snapshot is written to `widget/today.json` whenever the data changes, the day
+//!   rolls over, the lock or the settings change, after which the widgets are asked to
+//!   redraw. Snapshots that only differ in `updatedAt` aren't written, since iOS limits
+//!   how often a widget may reload.
+//! - `widget/` is in the app data folder on Android, and in the shared App Group container
+//!   on iOS, where the widget extension can read it.
+//! - A tap reaches the backend through the widget plugin while the app is running. When it
+//!   isn't, the widget appends the tap to `widget/intents.jsonl` and marks the row done
+//!   itself; the queued taps are applied on the next start or resume.
+//!
+//! The watch gets a smaller glance written next to each snapshot (`watch.rs`).
+//!
+//! Names are left out of the snapshot while the app is locked or privacy mode is on, and
+//! taps are ignored while locked, so the widget only shows how many habits are done.
+//! Elsewhere only `get_today_snapshot` and `toggle_tile_habit` are used, by the desktop
+//! widget window.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{Days, NaiveDate};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use crate::error::AppError;
+use crate::events::EventBus;
+use crate::model::{Habit, HabitKind};
+use crate::settings::SettingsStore;
+use crate::stats::{date_key, parse_date, today};
+use crate::storage::Storage;
+
+pub const WIDGET_DIR: &str = "widget";
+pub const SNAPSHOT_FILE: &str = "today.json";
+pub const INTENTS_FILE: &str = "intents.jsonl";
+
+#[cfg(target_os = "android")]
+mod imp {
+    use std::path::PathBuf;
+
+    use tauri::plugin::{Builder, PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Wry};
+
+    use super::WidgetIntent;
+
+    const PLUGIN_PACKAGE: &str = "com.sushil930.habitflow.widget";
+
+    struct Widget(PluginHandle<Wry>);
+
+    #[derive(serde::Serialize)]
+    struct Handler {
+        handler: tauri::ipc::Channel<serde_json::Value>,
+    }
+
+    /// Registers `WidgetPlugin.kt` and hands it a channel for taps.
+    pub fn init() -> TauriPlugin<Wry> {
+        Builder::new("habit-widget")
+            .setup(|app, api| {
+                let handle = api.register_android_plugin(PLUGIN_PACKAGE, "WidgetPlugin")?;
+                let a = app.clone();
+                let channel = tauri::ipc::Channel::new(move |body| {
+                    let intent: WidgetIntent = body.deserialize()?;
+                    let a = a.clone();
+                    tauri::async_runtime::spawn_blocking(move || super::apply_logged(&a, intent));
+                    Ok(())
+                });
+                handle.run_mobile_plugin::<serde_json::Value>(
+                    "setTapHandler",
+                    Handler { handler: channel },
+                )?;
+                app.manage(Widget(handle));
+                Ok(())
+            })
+            .build()
+    }
+
+    /// Has the widget reread the snapshot.
+    pub fn redraw(app: &AppHandle) {
+        if let Some(widget) = app.try_state::<Widget>() {
+            if let Err(e) = widget
+                .0
+                .run_mobile_plugin::<serde_json::Value>("refresh", ())
+            {
+                log::warn!("widget: failed to redraw: {e}");
+            }
+        }
+    }
+
+    /// The app data folder's `widget/` is readable by the widget.
+    pub fn shared_dir(_app: &AppHandle) -> Option<PathBuf> {
+        None
+    }
+}
+
+#[cfg(target_os = "ios")]
+mod imp {
+    use std::path::PathBuf;
+
+    use tauri::plugin::{Builder, PluginHandle, TauriPlugin};
+    use tauri::{AppHandle, Manager, Wry};
+
+    tauri::ios_plugin_binding!(init_plugin_habit_widget);
+
+    struct Widget {
+        handle: PluginHandle<Wry>,
+        /// The App Group container, shared with the widget extension.
+        container: PathBuf,
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Container {
+        path: PathBuf,
+    }
+
+    /// Registers `WidgetPlugin.swift`, which knows the App Group container.
+    pub fn init() -> TauriPlugin<Wry> {
+        Builder::new("habit-widget")
+            .setup(|app, api| {
+                let handle = api.register_ios_plugin(init_plugin_habit_widget)?;
+                let container: Container = handle.run_mobile_plugin("containerPath", ())?;
+                app.manage(Widget {
+                    handle,
+                    container: container.path,
+                });
+                Ok(())
+            })
+            .build()
+    }
+
+    /// Reloads the widget's timelines.
+    pub fn redraw(app: &AppHandle) {
+        if let Some(widget) = app.try_state::<Widget>() {
+            if let Err(e) = widget
+                .handle
+                .run_mobile_plugin::<serde_json::Value>("reload", ())
+            {
+                log::warn!("widget: failed to reload: {e}");
+            }
+        }
+    }
+
+    pub fn shared_dir(app: &AppHandle) -> Option<PathBuf> {
+        app.try_state::<Widget>().map(|w| w.container.clone())
+    }
+}
+
+#[cfg(not(mobile))]
+mod imp {
+    use std::path::PathBuf;
+
+    use tauri::AppHandle;
+
+    pub fn redraw(_app: &AppHandle) {}
+
+    pub fn shared_dir(_app: &AppHandle) -> Option<PathBuf> {
+        None
+    }
+}
+
+#[cfg(mobile)]
+pub use imp::init;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TodayHabit {
+    pub id: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emoji: Option<String>,
+    pub color: String,
+    pub done: bool,
+    /// Takes several check-ins a day, one per tap.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub repeated: bool,
+    /// Check-ins (or, for weekly and monthly habits, days) so far against the goal.
+    pub count: u32,
+    pub goal: u32,
+    /// The current streak, in days or periods.
+    pub streak: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TodaySnapshot {
+    pub date: String,
+    pub updated_at: String,
+    /// Due today or done today, in list order. Empty while `hidden`.
+    pub habits: Vec<TodayHabit>,
+    pub done: usize,
+    pub total: usize,
+    /// The longest current streak among today's habits, for the lock screen.
+    pub streak: u32,
+    /// Locked or in privacy mode: only the counts are shown, and taps do nothing.
+    pub hidden: bool,
+    /// `None` with no active habits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tile: Option<TileState>,
+}
+
+/// The Quick Settings tile.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TileState {
+    pub habit_id: String,
+    /// "Mark Meditate done", or "Meditate done" once it is.
+    pub label: String,
+    pub done: bool,
+    /// Greyed out while the app is locked.
+    pub available: bool,
+}
+
+/// What the widget sends for a tap.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WidgetIntent {
+    /// `complete` for a widget row, `toggleTile` for the tile.
+    pub action: String,
+    #[serde(default)]
+    pub habit_id: Option<String>,
+    /// The day the widget was showing; a tap from before midnight still counts for it.
+    #[serde(default)]
+    pub date: Option<String>,
+}
+
+fn shown(habit: &Habit, habits: &[Habit], date: NaiveDate) -> bool {
+    if habit.archived || habit.kind != HabitKind::Build {
+        return false;
+    }
+    habit.is_done_on(&date_key(date))
+        || (crate::schedule::is_due(habit, date)
+            && !crate::dependencies::is_blocked(habit, habits, date))
+}
+
+/// The tile's habit: the one picked in settings, else the first pinned habit, else the
+/// first habit in the list. Archived and avoidance habits are passed over.
+fn tile_habit<'a>(settings: &SettingsStore, habits: &'a [Habit]) -> Option<&'a Habit> {
+    let picked = settings.get().mobile.tile_habit_id;
+    let mut active = habits
+        .iter()
+        .filter(|h| !h.archived && h.kind == HabitKind::Build);
+    picked
+        .and_then(|id| active.clone().find(|h| h.id == id))
+        .or_else(|| active.clone().find(|h| h.pinned))
+        .or_else(|| active.next())
+}
+
+fn tile_state(habit: &Habit, hidden: bool, locked: bool) -> TileState {
+    let done = habit.is_done_on(&date_key(today()));
+    let name = if hidden { "habit" } else { habit.name.as_str() };
+    TileState {
+        habit_id: habit.id.clone(),
+        label: if done {
+            format!("{} done", capitalized(name))
+        } else {
+            format!("Mark {name} done")
+        },
+        done,
+        available: !locked,
+    }
+}
+
+fn capitalized(name: &str) -> String {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+pub fn snapshot(app: &AppHandle) -> Result<TodaySnapshot, AppError> {
+    let locked = app.state::<crate::lock::AppLock>().is_locked();
+    snapshot_of(
+        &app.state::<Storage>(),
+        &app.state::<SettingsStore>(),
+        locked,
+    )
+}
+
+/// [`snapshot`] from the data alone, for callers without a window.
+pub fn snapshot_of(
+    storage: &Storage,
+    settings: &SettingsStore,
+    locked: bool,
+) -> Result<TodaySnapshot, AppError> {
+    let habits = storage.list_habits()?;
+    let date = today();
+    let key = date_key(date);
+    let today: Vec<TodayHabit> = habits
+        .iter()
+        .filter(|h| shown(h, &habits, date))
+        .map(|h| {
+            let progress = crate::schedule::progress(h, date);
+            TodayHabit {
+                id: h.id.clone(),
+                name: h.name.clone(),
+                emoji: crate::appearance::emoji(h).map(str::to_string),
+                color: h.color.clone(),
+                done: h.is_done_on(&key),
+                repeated: h.is_repeated(),
+                count: progress.done,
+                goal: progress.goal,
+                streak: crate::streaks::current(storage, h, date),
+            }
+        })
+        .collect();
+    let hidden = locked || settings.get().privacy.hide_habit_names;
+    Ok(TodaySnapshot {
+        date: key,
+        updated_at: chrono::Utc::now().to_rfc3339(),
+        done: today.iter().filter(|h| h.done).count(),
+        total: today.len(),
+        streak: today.iter().map(|h| h.streak).max().unwrap_or(0),
+        habits: if hidden { Vec::new() } else { today },
+        hidden,
+        tile: tile_habit(settings, &habits).map(|h| tile_state(h, hidden, locked)),
+    })
+}
+
+/// The shared App Group container on iOS, which the app's extensions can reach too; the
+/// app data folder elsewhere.
+pub fn shared_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    match imp::shared_dir(app) {
+        Some(dir) => Ok(dir),
+        None => Ok(app.path().app_data_dir()?),
+    }
+}
+
+fn widget_dir(app: &AppHandle) -> Result<PathBuf, AppError> {
+    Ok(shared_dir(app)?.join(WIDGET_DIR))
+}
+
+/// Whether `new` shows anything `old` didn't.
+fn differs(old: &[u8], new: &serde_json::Value) -> bool {
+    let Ok(mut old) = serde_json::from_slice::<serde_json::Value>(old) else {
+        return true;
+    };
+    let mut new = new.clone();
+    for snapshot in [&mut old, &mut new] {
+        if let Some(fields) = snapshot.as_object_mut() {
+            fields.remove("updatedAt");
+        }
+    }
+    old != new
+}
+
+/// Replaces the file in one step, so the widget never reads half of it.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), AppError> {
+    let temp = path.with_extension("tmp");
+    std::fs::write(&temp, contents)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+fn publish(app: &AppHandle) -> Result<(), AppError> {
+    let dir = widget_dir(app)?;
+    std::fs::create_dir_all(&dir)?;
+    let path = dir.join(SNAPSHOT_FILE);
+    let today = snapshot(app)?;
+    let snapshot = serde_json::to_value(&today).map_err(|e| AppError::Failed(e.to_string()))?;
+    if std::fs::read(&path).is_ok_and(|old| !differs(&old, &snapshot)) {
+        return Ok(());
+    }
+    write_atomic(&path, snapshot.to_string().as_bytes())?;
+    crate::watch::write(&dir, &today)?;
+    imp::redraw(app);
+    Ok(())
+}
+
+/// Rewrites the snapshot and redraws the widgets. Does nothing on desktop.
+pub fn refresh(app: &AppHandle) {
+    if !cfg!(mobile) {
+        return;
+    }
+    if let Err(e) = publish(app) {
+        log::warn!("widget: failed to publish today's habits: {e}");
+    }
+}
+
+pub fn apply(app: &AppHandle, intent: &WidgetIntent) -> Result<(), AppError> {
+    if app.state::<crate::lock::AppLock>().is_locked() {
+        return Err(AppError::Locked);
+    }
+    match (intent.action.as_str(), &intent.habit_id) {
+        ("complete", Some(habit_id)) => complete(app, habit_id, intent.date.as_deref()),
+        ("toggleTile", _) => toggle_tile(app).map(|_| ()),
+        (action, _) => Err(AppError::Invalid(format!(
+            "unknown widget action {action:?}"
+        ))),
+    }
+}
+
+/// Checks off a habit from the widget. Unlike the tray this never unchecks, since the
+/// widget may be showing a stale row.
+fn complete(app: &AppHandle, habit_id: &str, date: Option<&str>) -> Result<(), AppError> {
+    let today = today();
+    let date = match date {
+        Some(d) => parse_date(d).ok_or_else(|| AppError::Invalid(format!("not a date: {d}")))?,
+        None => today,
+    };
+    if date > today
+        || today
+            .checked_sub_days(Days::new(1))
+            .is_some_and(|y| date < y)
+    {
+        return Err(AppError::Invalid(format!(
+            "the widget can only log today or yesterday, not {}",
+            date_key(date)
+        )));
+    }
+    let habit = app
+        .state::<Storage>()
+        .get_habit(habit_id)?
+        .ok_or_else(|| AppError::not_found("habit", habit_id))?;
+    crate::habits::check_off(app, &habit, date)?;
+    Ok(())
+}
+
+/// Toggles the tile's habit for today, the way the tray does: a repeated habit takes one
+/// more check-in until the day's count is reached, then the next tap clears it.
+pub fn toggle_tile(app: &AppHandle) -> Result<TileState, AppError> {
+    crate::telemetry::count(app, "tile");
+    if app.state::<crate::lock::AppLock>().is_locked() {
+        return Err(AppError::Locked);
+    }
+    let habits = app.state::<Storage>().list_habits()?;
+    let habit = tile_habit(&app.state::<SettingsStore>(), &habits)
+        .ok_or_else(|| AppError::Invalid("there's no habit for the tile".into()))?;
+    let date = today();
+    let done = habit.is_done_on(&date_key(date));
+    let updated = if habit.is_repeated() && !done {
+        crate::habits::log_value(app, &habit.id, date, 1.0, Vec::new())?
+    } else {
+        crate::habits::set_completion(app, &habit.id, date, !done)?
+    }
+    .ok_or_else(|| AppError::not_found("habit", &habit.id))?;
+    Ok(tile_state(&updated, crate::privacy::hide_names(app), false))
+}
+
+#[cfg(target_os = "android")]
+fn apply_logged(app: &AppHandle, intent: WidgetIntent) {
+    crate::telemetry::count(app, "widget");
+    if let Err(e) = apply(app, &intent) {
+        log::warn!("widget: ignored a {} tap: {e}", intent.action);
+    }
+    // Redraws even when nothing changed, to undo the widget's optimistic check.
+    refresh(app);
+}
+
+/// Applies the taps queued while the app wasn't running, oldest first.
+pub fn drain_intents(app: &AppHandle) {
+    let Ok(dir) = widget_dir(app) else {
+        return;
+    };
+    let queued = dir.join(INTENTS_FILE);
+    // Moved aside first, so taps arriving meanwhile start a new file.
+    let applying = queued.with_extension("applying");
+    if std::fs::rename(&queued, &applying).is_err() {
+        return;
+    }
+    let lines = std::fs::read_to_string(&applying).unwrap_or_default();
+    for line in lines.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str::<WidgetIntent>(line) {
+            Ok(intent) => {
+                crate::telemetry::count(app, "widget");
+                if let Err(e) = apply(app, &intent) {
+                    log::warn!("widget: ignored a queued {} tap: {e}", intent.action);
+                }
+            }
+            Err(e) => log::warn!("widget: unreadable queued tap: {e}"),
+        }
+    }
+    let _ = std::fs::remove_file(&applying);
+    refresh(app);
+}
+
+/// Applies queued taps, then keeps the widget in step with the data. Check-ins and the
+/// day rolling over both come through as data changes.
+pub fn start(app: &AppHandle) {
+    if !cfg!(mobile) {
+        return;
+    }
+    drain_intents(app);
+    refresh(app);
+    let app = app.clone();
+    let mut data = app.state::<EventBus>().watch_data();
+    tauri::async_runtime::spawn(async move {
+        while data.changed().await.is_ok() {
+            let a = app.clone();
+            let _ = tauri::async_runtime::spawn_blocking(move || refresh(&a)).await;
+        }
+    });
+}
+
+/// Today's habits as the home-screen widget sees them.
+#[tauri::command]
+pub fn get_today_snapshot(app: AppHandle) -> Result<TodaySnapshot, AppError> {
+    snapshot(&app)
+}
+
+/// What the Quick Settings tile does when tapped; returns the tile's new state.
+#[tauri::command]
+pub fn toggle_tile_habit(app: AppHandle) -> Result<TileState, AppError> {
+    toggle_tile(&app)
+}
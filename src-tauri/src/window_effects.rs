@@ -0,0 +1,190 @@
+//! Translucent window backgrounds (Mica, Acrylic or blur on Windows, vibrancy on macOS)
+//! and window opacity, mostly for the desktop widget window.
+//!
+//! Each command applies to the window that calls it. `get_window_effects` says what this
+//! system supports, so the webview can fall back to a solid background instead of asking
+//! for an effect that would be refused. Effects only show through a window created with
+//! `transparent: true` and a transparent page background.
+
+use serde::{Deserialize, Serialize};
+use tauri::window::{EffectState, EffectsBuilder};
+use tauri::WebviewWindow;
+
+use crate::error::AppError;
+
+const MIN_OPACITY: f64 = 0.2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Material {
+    /// Windows 11.
+    Mica,
+    /// Windows 10 (April 2018 update) and later.
+    Acrylic,
+    /// Windows 7 and 10.
+    Blur,
+    /// macOS.
+    Vibrancy,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowEffects {
+    /// What `set_window_effect` accepts here; empty where there are none (Linux).
+    pub materials: Vec<Material>,
+    /// Whether `set_window_opacity` works here.
+    pub opacity: bool,
+}
+
+/// The Windows build number, 0 if it can't be told.
+#[cfg(windows)]
+fn windows_build() -> u64 {
+    match tauri_plugin_os::version() {
+        tauri_plugin_os::Version::Semantic(_, _, build) => build,
+        _ => 0,
+    }
+}
+
+fn materials() -> Vec<Material> {
+    #[cfg(windows)]
+    {
+        let build = windows_build();
+        let mut materials = vec![Material::Blur];
+        if build >= 17134 {
+            materials.push(Material::Acrylic);
+        }
+        if build >= 22000 {
+            materials.push(Material::Mica);
+        }
+        materials
+    }
+    #[cfg(target_os = "macos")]
+    {
+        vec![Material::Vibrancy]
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    {
+        Vec::new()
+    }
+}
+
+pub fn supported() -> WindowEffects {
+    WindowEffects {
+        materials: materials(),
+        opacity: cfg!(any(windows, target_os = "macos", target_os = "linux")),
+    }
+}
+
+fn effect(material: Material, dark: Option<bool>) -> tauri::window::Effect {
+    use tauri::window::Effect;
+    match (material, dark) {
+        (Material::Mica, Some(true)) => Effect::MicaDark,
+        (Material::Mica, Some(false)) => Effect::MicaLight,
+        (Material::Mica, None) => Effect::Mica,
+        (Material::Acrylic, _) => Effect::Acrylic,
+        (Material::Blur, _) => Effect::Blur,
+        (Material::Vibrancy, Some(true)) => Effect::HudWindow,
+        (Material::Vibrancy, _) => Effect::Popover,
+    }
+}
+
+/// Sets the window's whole opacity, 1.0 being opaque.
+#[cfg(windows)]
+fn set_opacity(window: &WebviewWindow, opacity: f64) -> Result<(), String> {
+    #[cfg(target_pointer_width = "64")]
+    use windows_sys::Win32::UI::WindowsAndMessaging::{GetWindowLongPtrW, SetWindowLongPtrW};
+    #[cfg(target_pointer_width = "32")]
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        GetWindowLongW as GetWindowLongPtrW, SetWindowLongW as SetWindowLongPtrW,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        SetLayeredWindowAttributes, GWL_EXSTYLE, LWA_ALPHA, WS_EX_LAYERED,
+    };
+
+    let hwnd = window.hwnd().map_err(|e| e.to_string())?.0 as _;
+    let alpha = (opacity * 255.0).round() as u8;
+    unsafe {
+        let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE);
+        SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style | WS_EX_LAYERED as _);
+        if SetLayeredWindowAttributes(hwnd, 0, alpha, LWA_ALPHA) == 0 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn set_opacity(window: &WebviewWindow, opacity: f64) -> Result<(), String> {
+    use objc2::msg_send;
+    use objc2::runtime::AnyObject;
+
+    let ns_window = window.ns_window().map_err(|e| e.to_string())? as usize;
+    window
+        .run_on_main_thread(move || unsafe {
+            let ns_window = &*(ns_window as *const AnyObject);
+            let _: () = msg_send![ns_window, setAlphaValue: opacity];
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn set_opacity(window: &WebviewWindow, opacity: f64) -> Result<(), String> {
+    use gtk::prelude::WidgetExt;
+
+    let target = window.clone();
+    window
+        .run_on_main_thread(move || match target.gtk_window() {
+            Ok(gtk) => gtk.set_opacity(opacity),
+            Err(e) => log::warn!("window effects: {e}"),
+        })
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(not(any(windows, target_os = "macos", target_os = "linux")))]
+fn set_opacity(_window: &WebviewWindow, _opacity: f64) -> Result<(), String> {
+    Err("window opacity isn't supported here".into())
+}
+
+#[tauri::command]
+pub fn get_window_effects() -> WindowEffects {
+    supported()
+}
+
+/// Gives the calling window a translucent `material`, or takes it off with none. `dark`
+/// picks the dark or light variant where there is one; left out, it follows the system.
+#[tauri::command]
+pub fn set_window_effect(
+    window: WebviewWindow,
+    material: Option<Material>,
+    dark: Option<bool>,
+) -> Result<(), AppError> {
+    let Some(material) = material else {
+        window.set_effects(None)?;
+        return Ok(());
+    };
+    if !materials().contains(&material) {
+        return Err(AppError::Invalid(format!(
+            "{material:?} isn't supported on this system"
+        )));
+    }
+    window.set_effects(
+        EffectsBuilder::new()
+            .effect(effect(material, dark))
+            // The widget keeps its look when another window has focus.
+            .state(EffectState::Active)
+            .build(),
+    )?;
+    Ok(())
+}
+
+/// Sets how opaque the calling window is, from 0.2 to 1.0 (opaque).
+#[tauri::command]
+pub fn set_window_opacity(window: WebviewWindow, opacity: f64) -> Result<(), AppError> {
+    if !(MIN_OPACITY..=1.0).contains(&opacity) {
+        return Err(AppError::Invalid(format!(
+            "opacity must be between {MIN_OPACITY} and 1"
+        )));
+    }
+    set_opacity(&window, opacity)?;
+    Ok(())
+}
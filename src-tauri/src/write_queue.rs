@@ -0,0 +1,249 @@
+//! Batches the webview's bursts of writes. Every local change sends a full snapshot
+//! (`sync_habits`) and dragging a habit sends a new order (`reorder_habits`), so a bulk
+//! toggle or a drag means dozens of writes in a row. They're queued here instead: only the
+//! latest snapshot and the latest order are kept, and both are written in one transaction
+//! once the webview has been quiet for [`QUIET`], and never later than [`MAX_DELAY`] after
+//! the first queued change.
+//!
+//! The queue is flushed early whenever anything could read around it:
+//! - before any other command runs (see `guarded`), so the webview reads its own writes
+//! - on quit, when the main window loses focus or hides, and when the app locks
+//! - on `flush_writes`, which the webview calls when the page unloads
+//!
+//! A write that fails (the CLI holding the database, say) is put back and tried again,
+//! [`QUIET`] later and twice as long after each failure, unless newer changes replaced it
+//! in the meantime. Every failure is emitted as `habits:write-failed`. After
+//! [`MAX_ATTEMPTS`] the changes are given up on and the webview is sent the stored habits,
+//! so it shows what was actually saved.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+use crate::error::AppError;
+use crate::model::Habit;
+use crate::storage::Storage;
+
+/// How long the webview has to be quiet before queued writes land.
+pub const QUIET: Duration = Duration::from_millis(300);
+/// The longest a queued write waits.
+pub const MAX_DELAY: Duration = Duration::from_secs(1);
+/// Tries at writing the same changes before they're dropped.
+pub const MAX_ATTEMPTS: u32 = 6;
+
+/// Emitted with a [`WriteFailure`] whenever queued changes couldn't be written.
+pub const WRITE_FAILED_EVENT: &str = "habits:write-failed";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteFailure {
+    pub message: String,
+    /// Whether the changes are still queued to be tried again. Once false they're gone,
+    /// and `habits:changed` follows with what's stored.
+    pub retrying: bool,
+}
+
+/// Commands that queue instead of writing; every other command flushes first.
+const QUEUED_COMMANDS: &[&str] = &["sync_habits", "reorder_habits", "flush_writes"];
+
+#[derive(Default)]
+struct Pending {
+    habits: Option<Vec<Habit>>,
+    order: Option<Vec<String>>,
+    first: Option<Instant>,
+    last: Option<Instant>,
+    /// Failed writes of what's queued, and when to try again.
+    failures: u32,
+    retry_at: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct WriteQueue {
+    pending: Mutex<Pending>,
+    /// Held while writing, so flushes land in the order they were taken.
+    writing: Mutex<()>,
+    wake: Notify,
+}
+
+impl WriteQueue {
+    fn pending(&self) -> std::sync::MutexGuard<'_, Pending> {
+        self.pending.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn queue(&self, f: impl FnOnce(&mut Pending)) {
+        let now = Instant::now();
+        {
+            let mut pending = self.pending();
+            f(&mut pending);
+            pending.first.get_or_insert(now);
+            pending.last = Some(now);
+        }
+        self.wake.notify_one();
+    }
+
+    /// When the queued writes are due, if there are any.
+    fn due(&self) -> Option<Instant> {
+        let pending = self.pending();
+        let (first, last) = (pending.first?, pending.last?);
+        let due = (last + QUIET).min(first + MAX_DELAY);
+        Some(pending.retry_at.map_or(due, |retry| retry.max(due)))
+    }
+
+    /// Puts back what a failed write took, unless newer changes replaced it. Returns the
+    /// failures so far.
+    fn restore(
+        &self,
+        habits: Option<Vec<Habit>>,
+        order: Option<Vec<String>>,
+        failures: u32,
+    ) -> u32 {
+        let now = Instant::now();
+        let mut pending = self.pending();
+        if pending.habits.is_none() {
+            pending.habits = habits;
+        }
+        if pending.order.is_none() {
+            pending.order = order;
+        }
+        pending.first.get_or_insert(now);
+        pending.last.get_or_insert(now);
+        pending.failures = failures + 1;
+        pending.retry_at = Some(now + QUIET * 2u32.pow(failures.min(MAX_ATTEMPTS)));
+        pending.failures
+    }
+
+    /// The queued order, for answering `reorder_habits` before it's written.
+    fn order(&self) -> Option<Vec<String>> {
+        self.pending().order.clone()
+    }
+}
+
+fn write(
+    app: &AppHandle,
+    habits: Option<Vec<Habit>>,
+    order: Option<&[String]>,
+) -> Result<(), String> {
+    match (habits, order) {
+        (Some(habits), order) => {
+            crate::habits::sync_from_webview(app, habits, order).map_err(|e| e.to_string())
+        }
+        (None, Some(order)) => {
+            app.state::<Storage>()
+                .reorder_habits(order)
+                .map_err(|e| e.to_string())?;
+            crate::ordering::changed(app);
+            Ok(())
+        }
+        (None, None) => Ok(()),
+    }
+}
+
+/// Writes whatever is queued, in one transaction. On failure it stays queued (see the
+/// module docs).
+pub fn flush(app: &AppHandle) -> Result<(), String> {
+    let queue = app.state::<WriteQueue>();
+    let _writing = queue.writing.lock().unwrap_or_else(|e| e.into_inner());
+    let (habits, order, failures) = {
+        let mut pending = queue.pending();
+        let taken = (
+            pending.habits.take(),
+            pending.order.take(),
+            pending.failures,
+        );
+        *pending = Pending::default();
+        taken
+    };
+    let Err(e) = write(app, habits.clone(), order.as_deref()) else {
+        return Ok(());
+    };
+    let failures = queue.restore(habits, order, failures);
+    let retrying = failures < MAX_ATTEMPTS;
+    if retrying {
+        log::warn!("failed to write queued changes (attempt {failures}): {e}");
+    } else {
+        log::error!("giving up on queued changes after {failures} attempts: {e}");
+        *queue.pending() = Pending::default();
+        crate::habits::notify_changed(app);
+    }
+    let _ = app.emit(
+        WRITE_FAILED_EVENT,
+        WriteFailure {
+            message: e.clone(),
+            retrying,
+        },
+    );
+    Err(e)
+}
+
+/// [`flush`] where the failure only goes out as `habits:write-failed`.
+pub fn flush_logged(app: &AppHandle) {
+    let _ = flush(app);
+}
+
+/// Replaces any queued snapshot.
+pub fn queue_habits(app: &AppHandle, habits: Vec<Habit>) {
+    app.state::<WriteQueue>().queue(|p| p.habits = Some(habits));
+}
+
+/// Replaces any queued order. Returns the habits in the new order.
+pub fn queue_order(app: &AppHandle, ids: Vec<String>) -> Result<Vec<Habit>, String> {
+    let queue = app.state::<WriteQueue>();
+    queue.queue(|p| p.order = Some(ids));
+    let mut habits = app
+        .state::<Storage>()
+        .list_habits()
+        .map_err(|e| e.to_string())?;
+    if let Some(order) = queue.order() {
+        let existing: Vec<String> = habits.iter().map(|h| h.id.clone()).collect();
+        let position: std::collections::HashMap<&String, usize> =
+            crate::ordering::reordered(&existing, &order)
+                .into_iter()
+                .enumerate()
+                .map(|(i, id)| (id, i))
+                .collect();
+        habits.sort_by_key(|h| position.get(&h.id).copied());
+    }
+    Ok(habits)
+}
+
+/// Flushes the queue before every command that doesn't queue itself.
+pub fn guarded(
+    handler: impl Fn(Invoke) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke) -> bool + Send + Sync + 'static {
+    move |invoke| {
+        if !QUEUED_COMMANDS.contains(&invoke.message.command()) {
+            flush_logged(invoke.message.webview_ref().app_handle());
+        }
+        handler(invoke)
+    }
+}
+
+/// Writes queued changes once they're due.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let queue = app.state::<WriteQueue>();
+        loop {
+            queue.wake.notified().await;
+            while let Some(due) = queue.due() {
+                let now = Instant::now();
+                if due <= now {
+                    let a = app.clone();
+                    let _ = tauri::async_runtime::spawn_blocking(move || flush_logged(&a)).await;
+                } else {
+                    tokio::time::sleep(due - now).await;
+                }
+            }
+        }
+    });
+}
+
+/// Writes queued changes right away; the webview calls this when the page unloads.
+#[tauri::command]
+pub fn flush_writes(app: AppHandle) -> Result<(), AppError> {
+    Ok(flush(&app)?)
+}
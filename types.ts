@@ -3,22 +3,821 @@ export interface HabitLog {
 }
 
 export interface HabitFrequency {
-  type: 'daily' | 'weekly' | 'monthly';
+  // weekly/monthly: goal = times per period; interval: goal = every N days
+  type: 'daily' | 'weekly' | 'monthly' | 'interval';
   goal: number;
 }
 
+// Quantified habits, e.g. { amount: 2000, unit: 'ml' }.
+export interface HabitTarget {
+  amount: number;
+  unit: string;
+}
+
+// 'avoid' habits ("no sugar") succeed every day no lapse is logged.
+export type HabitKind = 'build' | 'avoid';
+
+export interface Lapse {
+  date: string; // "YYYY-MM-DD"
+  note?: string;
+  loggedAt: string; // ISO
+}
+
+// Step of a checklist habit; the habit is done on a day once every item is.
+export interface ChecklistItem {
+  id: string;
+  name: string;
+  done?: string[]; // "YYYY-MM-DD" days checked off
+}
+
 export interface Habit {
   id: string;
   name: string;
   description?: string;
   color: string;
   icon?: string;
+  emoji?: string; // shown in the tray and notifications; defaults to the icon's emoji
+  cover?: string; // cover image file, set with `set_habit_cover`; load with `get_habit_cover`
   category: string;
   frequency: HabitFrequency;
   reminderTime?: string; // "HH:MM" 24h format
+  window?: { start: string; end: string }; // "HH:MM"; check-ins only count inside it
   createdAt: string; // ISO Date string
   logs: HabitLog;
   archived: boolean;
+  archivedAt?: string; // ISO, set by the backend while archived
+  pausedUntil?: string; // "YYYY-MM-DD" first day back, set while paused with `pause_habit`
+  pinned?: boolean; // set with `set_habit_pinned`; pinned habits come first everywhere
+  difficulty?: 'easy' | 'medium' | 'hard'; // XP weight, set with `set_habit_difficulty`; default 'medium'
+  timesPerDay?: number; // check-ins needed per day (default 1); see `log_repetition`
+  backfilled?: string[]; // days checked off after the fact with `backfill_completion`
+  weekdayReminders?: Record<string, string>; // "0" (Sunday)–"6" => HH:MM, set with `set_weekday_reminders`
+  tags?: string[]; // lowercase, e.g. life areas
+  dependsOn?: string[]; // habit ids that must be done first each day
+  kind?: HabitKind; // defaults to 'build'
+  lapses?: Lapse[];
+  checklist?: ChecklistItem[];
+  target?: HabitTarget;
+  values?: { [dateIsoString: string]: number }; // daily totals, only with a target
+  notes?: { [dateIsoString: string]: string }; // journal notes, set via update_checkin_note
+  revision?: number; // set by the backend, send back as is; see "Concurrent edits" in the README
+}
+
+// Built-in or user-defined starting point for a habit (`list_templates`).
+export interface HabitTemplate {
+  id: string;
+  name: string;
+  description?: string;
+  category: string;
+  color: string;
+  icon?: string;
+  frequency: HabitFrequency;
+  reminderTime?: string;
+  kind?: HabitKind;
+  target?: HabitTarget;
+  checklist?: string[]; // item names
+  tags?: string[];
+  builtin: boolean;
+}
+
+// Backend category record; habits still reference categories by name.
+export interface Category {
+  id: string;
+  name: string;
+  color: string;
+  position: number;
+}
+
+// Backend "vacation" range; days inside it are excused rather than missed.
+export interface OffPeriod {
+  id: string;
+  habitId?: string; // omitted = every habit
+  start: string; // "YYYY-MM-DD"
+  end: string; // inclusive
+  reason?: string;
+  paused?: boolean; // created by `pause_habit`
+}
+
+// `get_streak_freezes`.
+export interface StreakFreezes {
+  balance: number;
+  earned: number;
+  used: number;
+  daysToNext: number;
+  history: { habitId: string; date: string; usedAt: string }[];
+}
+
+// `log_mood` / `list_moods`: one entry per day.
+export interface MoodEntry {
+  date: string; // "YYYY-MM-DD"
+  score: 1 | 2 | 3 | 4 | 5;
+  tags?: string[];
+  loggedAt: string;
+}
+
+// `get_timers` and the `timers:changed` event.
+export interface TimerStatus {
+  habitId: string;
+  date: string;
+  running: boolean;
+  elapsedSecs: number;
+  remainingSecs: number;
+}
+
+// `get_pomodoro` and the `pomodoro:changed` event (`null` without a session).
+export interface PomodoroStatus {
+  habitId?: string | null;
+  phase: 'work' | 'shortBreak' | 'longBreak';
+  running: boolean;
+  remainingSecs: number;
+  completed: number;
+}
+
+// Per-view sort preference (`set_sort_preference(view, sort)`).
+export type HabitSort = 'manual' | 'name' | 'streak' | 'created';
+export type HabitView = 'main' | 'tray' | 'widget';
+
+// `next_occurrences(habitId?, limit?)`
+export interface ReminderOccurrence {
+  habitId: string;
+  name: string;
+  date: string;
+  time: string;
+}
+
+// `list_milestones` and the `milestone:reached` event.
+export interface Milestone {
+  id: string;
+  habitId: string;
+  name: string; // the habit's
+  kind: 'completions' | 'streak' | 'total';
+  threshold: number;
+  current: number;
+  reachedAt?: string; // ISO, set once reached
+  createdAt: string;
+}
+
+// `get_goal_progress`: a target over a month or quarter for one habit.
+export interface GoalProgress {
+  id: string;
+  title: string;
+  habitId: string;
+  period: 'month' | 'quarter';
+  start: string; // "YYYY-MM-DD"
+  end: string; // deadline, inclusive
+  target: number;
+  createdAt: string;
+  unit?: string | null; // null = counts completions
+  current: number;
+  percent: number;
+  expected: number;
+  daysTotal: number;
+  daysLeft: number;
+  neededPerDay?: number | null;
+  status: 'upcoming' | 'onTrack' | 'behind' | 'achieved' | 'missed';
+}
+
+// `parse_quick_add(text, locale?)`
+export interface QuickAddDraft {
+  name: string;
+  frequency: HabitFrequency;
+  rrule: string; // e.g. "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR;BYHOUR=7;BYMINUTE=0"
+  weekdays?: number[]; // 0 (Sunday)–6
+  timesPerDay: number;
+  reminderTime?: string;
+  target?: HabitTarget;
+  tags: string[];
+  start?: string; // "YYYY-MM-DD"
+}
+
+// `get_player_state` and the `player:changed` / `player:level-up` events.
+export interface PlayerState {
+  enabled: boolean;
+  xp: number;
+  level: number;
+  levelXp: number; // earned since reaching `level`
+  nextLevelXp: number; // span of the current level
+  xpToday: number;
+}
+
+// `list_achievements` and the `achievement:unlocked` event.
+export interface Achievement {
+  id: string; // e.g. "streak-7", "perfect-month"
+  name: string;
+  description: string;
+  emoji: string;
+  unlockedAt?: string; // ISO
+}
+
+// `get_daily_challenge` and the `challenge:changed` event.
+export interface DailyChallenge {
+  date: string; // "YYYY-MM-DD"
+  kind: 'beforeTime' | 'overachieve' | 'allInCategory' | 'allDone';
+  title: string;
+  habitId?: string;
+  category?: string;
+  deadline?: string; // "HH:MM", for 'beforeTime'
+  xp: number;
+  status: 'open' | 'completed' | 'failed' | 'skipped';
+  resolvedAt?: string;
+}
+
+export interface Reward {
+  id: string;
+  name: string;
+  cost: number;
+  createdAt: string;
+}
+
+export interface Redemption {
+  id: string;
+  rewardId: string;
+  name: string; // as it was when redeemed
+  cost: number;
+  redeemedAt: string;
+}
+
+// `list_rewards` and the `rewards:changed` event.
+export interface RewardsOverview {
+  earned: number;
+  spent: number;
+  balance: number; // can dip below zero if check-ins are undone
+  rewards: (Reward & { affordable: boolean })[];
+  history: Redemption[]; // newest first
+}
+
+export interface WeeklyScore {
+  completions: number;
+  xp?: number; // only when shared
+  bestStreak?: number;
+  completionRate?: number; // percent
+}
+
+// `get_leaderboard`.
+export interface Leaderboard {
+  week: string; // ISO week, "2026-W42"
+  entries: (WeeklyScore & { rank: number; code: string; name: string; isMe: boolean })[];
+  pending: string[]; // friend codes without a score this week
+}
+
+// `get_social_status` and the other social commands.
+export interface SocialStatus {
+  enabled: boolean;
+  serverUrl: string;
+  friendCode: string; // "XXXX-XXXX"
+  displayName: string;
+  friends: { code: string; nickname?: string; addedAt: string }[];
+  shareXp: boolean;
+  shareStreak: boolean;
+  shareCompletionRate: boolean;
+}
+
+// `get_lock_status`, the lock commands and the `lock:changed` event.
+export interface LockStatus {
+  configured: boolean; // a PIN is set
+  locked: boolean;
+  retryAfter?: number; // seconds, after too many wrong PINs
+  keychainError?: string; // the keyring can't be read, so the PIN can't be checked
+}
+
+// `get_completions`, newest day first.
+export interface CompletionPage {
+  days: { date: string; done: boolean; backfilled: boolean; value?: number }[];
+  nextCursor: string | null;
+}
+
+// `start_job`, `get_job`, `list_jobs`, `cancel_job` and the `job:progress` / `job:finished`
+// events.
+export type JobRequest =
+  | { kind: 'moodInsights'; days?: number }
+  | { kind: 'export'; path: string; format: 'json' | 'csv'; passphrase?: string }
+  | { kind: 'yearReview'; year?: number };
+
+export interface JobStatus {
+  id: string;
+  kind: JobRequest['kind'];
+  state: 'queued' | 'running' | 'done' | 'failed' | 'cancelled';
+  progress: number; // 0 to 1
+  queuedAt: string;
+  finishedAt: string | null;
+  result?: unknown; // once done: mood insights, the export's path or a YearReview
+  error?: AppError;
+}
+
+// A `yearReview` job's result.
+export interface YearReview {
+  year: number;
+  completions: number;
+  months: number[]; // completions per month, January first
+  weekdays: number[]; // completions per day of the week, from weekStart
+  weekStart: number; // 0 = Sunday
+  activeDays: number;
+  longestStreak: number;
+  habits: {
+    habitId: string;
+    name: string;
+    completions: number;
+    completionRate: number;
+    longestStreak: number;
+    bestMonth: number | null; // 1-12
+  }[];
+}
+
+// Returned by get_range_stats, one per habit.
+export interface RangeStats {
+  habitId: string;
+  from: string;
+  to: string;
+  days: { date: string; done: boolean; value?: number; excused?: boolean }[]; // oldest first
+  completed: number;
+  counted: number; // excused days only count if done
+  completionRate: number; // percent
+  score: number; // 0-100 habit strength at `to`
+  weekStart: number; // 0 = Sunday, like getDay()
+  weeks: { start: string; completed: number; counted: number }[]; // oldest first, clipped to the range
+}
+
+// run_benchmarks' options and report; all options are optional.
+export interface BenchmarkOptions {
+  habits?: number; // default 20, at most 500
+  days?: number; // default 365, at most 3650
+  iterations?: number; // default 5, at most 100
+}
+
+export interface BenchmarkReport {
+  habits: number;
+  days: number;
+  iterations: number;
+  completions: number;
+  notes: number;
+  debugBuild: boolean; // timings from debug builds aren't representative
+  results: { name: string; meanMs: number; minMs: number; maxMs: number }[];
+  totalMs: number;
+}
+
+// Returned by get_resource_usage and trim_caches; sizes are in bytes.
+export interface ResourceUsage {
+  memoryBytes: number | null; // the backend process, not the webview's
+  sqliteMemoryBytes: number;
+  databaseBytes: number;
+  databaseLogBytes: number; // the -wal and -shm files
+  attachmentsBytes: number; // cover images
+  caches: { name: 'stats' | 'jobs' | 'streakRuns'; entries: number; bytes?: number }[];
+}
+
+// Returned by seed_synthetic_data (debug builds only).
+export interface SeedSummary {
+  habits: number;
+  completions: number;
+  lapses: number;
+  notes: number;
+  replaced: number; // synthetic habits from an earlier seed
+}
+
+// Returned by get_today_snapshot; also what the mobile widgets read from widget/today.json.
+export interface TodaySnapshot {
+  date: string;
+  updatedAt: string;
+  habits: {
+    id: string;
+    name: string;
+    emoji?: string;
+    color: string;
+    done: boolean;
+    repeated?: boolean; // one check-in per tap
+    count: number; // check-ins, or days for weekly and monthly habits, against goal
+    goal: number;
+    streak: number;
+  }[]; // empty while hidden
+  done: number;
+  total: number;
+  streak: number; // the longest current streak among these habits
+  hidden: boolean; // locked or privacy mode: counts only
+  tile?: TileState; // absent with no active habits
+}
+
+// The Android Quick Settings tile; also returned by toggle_tile_habit.
+export interface TileState {
+  habitId: string;
+  label: string; // "Mark Meditate done", or "Meditate done"
+  done: boolean;
+  available: boolean; // false while locked
+}
+
+// Taken by run_shortcut; the iOS App Intents send the same requests.
+export type ShortcutRequest =
+  | { intent: 'completeHabit'; habit: string } // an id or a name
+  | { intent: 'logValue'; habit: string; value: number }
+  | { intent: 'todayProgress' }
+  | { intent: 'scanTag'; tag: string } // the tag's label
+  | { intent: 'listHabits' };
+
+// Returned by run_shortcut.
+export interface ShortcutReply {
+  dialog: string; // what Siri says
+  done?: number; // todayProgress only
+  total?: number;
+  habits?: { id: string; name: string; unit?: string }[]; // listHabits only
+}
+
+// Returned by sync_reminders; always zeros on desktop.
+export interface ReminderSync {
+  scheduled: number;
+  cancelled: number;
+  registered: number;
+  until?: string; // local yyyy-MM-ddTHH:MM of the last registered reminder
+}
+
+// Returned by add_checkin_attachment and list_checkin_attachments.
+export interface Attachment {
+  id: string;
+  habitId: string;
+  date: string;
+  file: string; // habitId/name inside the attachments folder
+  mime: string;
+  size: number; // bytes
+  addedAt: string;
+}
+
+// Returned by list_pending_shares and carried by the share:received event.
+export interface PendingShare {
+  id: string;
+  receivedAt: string;
+  text?: string;
+  photos?: { name: string; size: number }[];
+}
+
+// Taken by watch_request; the watch bridges send the same requests.
+export type WatchRequest = { action: 'glance' } | { action: 'complete'; habitId?: string };
+
+// Returned by watch_request, and written to widget/glance.json for the watch.
+export interface Glance {
+  date: string;
+  done: number;
+  total: number;
+  streak: number;
+  next?: { id: string; name: string; emoji?: string; count: number; goal: number }; // left out while hidden
+  hidden: boolean;
+}
+
+// Returned by list_nfc_tags and bind_nfc_tag.
+export interface NfcTag {
+  tagId: string; // uppercase hex
+  habitId: string;
+  label: string | null;
+  boundAt: string;
+  lastScannedAt: string | null;
+}
+
+// Returned by scan_nfc_tag.
+export interface TagScan {
+  tagId: string;
+  outcome: 'checkedIn' | 'alreadyDone' | 'bound' | 'unknown' | 'locked';
+  habitId?: string;
+  message: string; // the notification's text
+}
+
+// Returned by get_sync_status, sync_now and reset_sync.
+export interface SyncStatus {
+  enabled: boolean;
+  pending: number; // operations waiting to be sent
+  lastSyncedAt?: string;
+  lastError?: string; // cleared by the next send that works
+  failures: number; // failed sends in a row
+  rejected?: { id: string; error: string }[]; // newest last
+}
+
+// Returned by download_update; the payload of updater:available and updater:ready.
+export interface Update {
+  version: string;
+  notes?: string;
+  date?: string;
+  channel: 'stable' | 'beta'; // the manifest it came from
+}
+
+// Returned by check_for_updates.
+export interface UpdateCheck {
+  currentVersion: string;
+  channel: 'stable' | 'beta';
+  update?: Update; // left out when up to date
+}
+
+// Payload of updater:progress.
+export interface UpdateProgress {
+  downloaded: number;
+  total?: number; // bytes, when the server says
+}
+
+// Returned by list_crash_reports and submit_crash_report.
+export interface CrashReport {
+  id: string;
+  kind: 'panic' | 'native';
+  occurredAt: string;
+  appVersion: string;
+  os: string;
+  arch: string;
+  message: string;
+  location?: string; // file:line:column, for panics
+  thread?: string;
+  backtrace?: string;
+  status: 'new' | 'dismissed' | 'submitted';
+  submittedAt?: string;
+}
+
+// Returned by export_logs.
+export interface LogExport {
+  path: string;
+  files: number; // log files in the zip
+  bytes: number;
+}
+
+// Returned by set_locale.
+export interface LocaleInfo {
+  locale: string; // the catalog in use, e.g. 'de'
+  requested: string; // as saved; '' follows the system
+  available: string[];
+}
+
+// Returned by create_diagnostic_bundle.
+export interface DiagnosticBundle {
+  path: string;
+  files: string[]; // names in the zip, e.g. 'about.json', 'logs/habitflow.log'
+  bytes: number;
+}
+
+// Returned by load_demo_data.
+export interface DemoSummary {
+  habits: number;
+  completions: number;
+  moods: number;
+  replaced: number; // demo habits from an earlier load
+}
+
+// Returned by reset_to_fresh.
+export interface ResetSummary {
+  habits: number;
+  rows: number; // rows deleted across all tables
+}
+
+// Returned by get_feature_flags.
+export interface FeatureFlag {
+  name: 'sync' | 'gamification' | 'localApi' | 'mqtt' | 'plugins';
+  description: string;
+  enabled: boolean;
+  default: boolean; // this build's default
+  overridden: boolean; // set in settings.features
+}
+
+// `list_check_ins`.
+export interface CheckIn {
+  id: number;
+  habitId: string;
+  date: string; // "YYYY-MM-DD"
+  value: number;
+  loggedAt: string;
+  tags?: string[];
+  revision: number; // of the day; pass as `expectedRevision`
+}
+
+// `import_legacy_state`.
+export interface LegacyImport {
+  alreadyImported: boolean; // an earlier call did it; nothing changed
+  imported: number; // new habits
+  merged: number; // entries merged into a habit that was already there
+  daysAdded: number;
+  daysDropped: number; // day keys that weren't dates
+  skipped: { index: number; id?: string; reason: string }[];
+}
+
+// `settings.libsql`. The auth token is kept in the keychain as `libsql.token`.
+export interface LibsqlSettings {
+  enabled: boolean;
+  url: string; // libsql://, https:// or http://
+  intervalSecs: number; // between rounds when nothing changed
+}
+
+// Returned by get_libsql_status and libsql_sync_now.
+export interface ReplicaStatus {
+  enabled: boolean;
+  generation?: number; // the remote database's, as of the last round
+  lastSyncedAt?: string;
+  lastError?: string; // cleared by the next round that works
+  failures: number; // failed rounds in a row
+  pulled: string[]; // tables with rows copied down in the last round that did anything
+  pushed: string[]; // tables with rows sent up in it
+}
+
+// `settings.backupDrive`.
+export interface BackupDriveSettings {
+  enabled: boolean;
+  label: string; // the drive's volume label, from list_volumes
+  volumeId: string; // when set, the label isn't looked at
+  folder: string; // on the drive; "HabitFlow Backups" by default
+}
+
+// Returned by list_volumes.
+export interface Volume {
+  label?: string;
+  id?: string; // filesystem UUID (Linux, macOS) or volume serial number (Windows)
+  mountPoint: string;
+}
+
+// Returned by get_backup_drive_status.
+export interface BackupDriveStatus {
+  enabled: boolean;
+  mountPoint?: string; // while the drive is plugged in
+  lastCopiedAt?: string;
+  lastCopied?: string; // the file on the drive
+  lastError?: string; // cleared by the next copy that works
+}
+
+// Returned by generate_qr; show `dataUrl` in an <img>.
+export type QrLevel = 'low' | 'medium' | 'quartile' | 'high';
+export interface QrImage {
+  dataUrl: string; // data:image/png;base64,...
+  version: number; // 1-40
+  modules: number; // per side, without the quiet zone
+  pixels: number; // per side
+}
+
+// Returned by share_template; `link` goes to import_shared_template on the other side.
+export interface TemplateShare {
+  link: string; // habitflow:template:...
+  qr: QrImage;
+}
+
+// `settings.screenTime`.
+export interface ScreenTimeSettings {
+  enabled: boolean;
+  idleMinutes: number; // time away past this doesn't count
+  categories: { name: string; apps: string[]; titles: string[]; domains: string[] }[]; // first match wins
+  limits: { habitId: string; category: string; maxMinutes: number }[];
+  rules: BlockRule[];
+}
+
+// `settings.screenTime.rules`; `domains` of the category go in the hosts file in `block` mode.
+export type BlockMode = 'warn' | 'block';
+export interface BlockRule {
+  habitId: string; // an avoidance habit
+  category: string;
+  mode?: BlockMode; // default 'warn'
+  window?: { start: string; end: string }; // "HH:MM"; all day when left out
+}
+
+// Returned by get_block_status.
+export interface BlockStatus {
+  rules: {
+    habitId: string;
+    category: string;
+    mode: BlockMode;
+    active: boolean;
+    overriddenUntil?: string;
+  }[];
+  blockedDomains: string[];
+  hostsError?: string; // the hosts file couldn't be changed; apps are still closed
+}
+
+// Returned by get_screen_time.
+export interface ScreenTimeReport {
+  date: string;
+  enabled: boolean;
+  totalSeconds: number;
+  categories: {
+    category: string; // '' for apps in no category
+    seconds: number;
+    apps: { app: string; seconds: number }[];
+  }[];
+  limits: {
+    habitId: string;
+    category: string;
+    maxMinutes: number;
+    usedMinutes: number;
+    over: boolean;
+  }[];
+}
+
+// `get_focus` and the `focus:changed` event (`null` without a session).
+export interface FocusStatus {
+  habitId: string;
+  minutes: number;
+  remainingSecs: number;
+  held: number; // notifications held back so far
+}
+
+// `settings.quitExport`.
+export interface QuitExportSettings {
+  enabled: boolean;
+  format: 'json' | 'csv';
+  directory: string; // a leading ~ is the home directory
+}
+
+// Returned by get_window_effects; `set_window_effect` takes one of `materials` or null.
+export type WindowMaterial = 'mica' | 'acrylic' | 'blur' | 'vibrancy';
+export interface WindowEffects {
+  materials: WindowMaterial[]; // empty on Linux
+  opacity: boolean; // set_window_opacity works
+}
+
+// Returned by get_network_status, and the payload of `network:online` / `network:offline`.
+export interface NetworkStatus {
+  online: boolean;
+  monitored: boolean; // false: settings.network.monitor is off and the network counts as up
+  checkedAt?: string;
+  since?: string; // last change between online and offline
+}
+
+// `encryption:progress` events while rotate_encryption_key runs.
+export interface RotationProgress {
+  done: number;
+  total: number;
+  path?: string; // the file being re-encrypted; left out once all are ready
+}
+
+// Returned by rotate_encryption_key.
+export interface RotationReport {
+  jobs: string[]; // export job ids now on the new passphrase
+  files: string[];
+  skipped: string[]; // didn't open with the old passphrase, left as they were
+}
+
+// `settings.maintenance`.
+export interface MaintenanceSettings {
+  enabled: boolean;
+  intervalDays: number;
+  idleMinutes: number; // minutes without input before a scheduled run starts
+}
+
+// Returned by run_maintenance_now and get_maintenance_report, and the payload of `maintenance:done`.
+export interface MaintenanceReport {
+  startedAt: string;
+  durationMs: number;
+  manual: boolean; // started with run_maintenance_now
+  databaseBefore: number; // bytes
+  databaseAfter: number;
+  attachmentsRemoved: number; // photos of deleted habits
+  filesRemoved: number; // attachment and cover files nothing referred to
+  logsRemoved: number;
+  reclaimedBytes: number;
+  errors: string[]; // steps that failed; the others still ran
+}
+
+// Payload of `habits:write-failed`, when queued syncs or reorders couldn't be written.
+export interface WriteFailure {
+  message: string;
+  retrying: boolean; // false: the changes were dropped, and `habits:changed` follows with what's stored
+}
+
+// Returned by get_startup_metrics; all times are milliseconds since the app started.
+export interface StartupMetrics {
+  phases: {
+    name: string;
+    startedMs: number;
+    durationMs: number;
+    deferred: boolean; // ran after the window was up
+  }[];
+  setupDoneMs: number | null;
+  webviewLoadedMs: number | null;
+  deferredDoneMs: number | null;
+}
+
+// What every backend command rejects with.
+export interface AppError {
+  code:
+    | 'not_found'
+    | 'invalid'
+    | 'locked'
+    | 'forbidden'
+    | 'cancelled'
+    | 'conflict'
+    | 'storage'
+    | 'io'
+    | 'network'
+    | 'failed';
+  message: string; // safe to show to the user
+  detail: string; // for logs and bug reports
+  retryable: boolean; // trying again later may help
+  revision?: number; // 'conflict' only: the record's current revision
+  current?: Habit; // 'conflict' only: the habit as it is now
+}
+
+// `settings.lock`.
+export interface LockSettings {
+  biometric: boolean;
+  autoLockMinutes: number; // 0 = never
+  lockWithSystem: boolean; // on session lock and after sleep
+}
+
+// `settings.privacy`.
+export interface PrivacySettings {
+  hideHabitNames: boolean; // counts instead of names in notifications and the tray
+}
+
+// `get_auth_methods`.
+export interface AuthMethods {
+  pin: boolean;
+  biometricAvailable: boolean;
+  biometricKind: 'touchId' | 'windowsHello' | 'biometrics' | 'none';
+  biometric: boolean; // switched on and usable now
 }
 
 export type ViewMode = 'dashboard' | 'analytics' | 'settings' | 'review';